@@ -1,11 +1,34 @@
-use super::common::{get_analysis, print_analysis_results};
+use super::common::{connect_ssh, get_analysis, parse_ssh_target, print_analysis_results};
+use super::doctor::print_findings;
 use super::error::CliError;
+use super::hosts::print_json;
 use super::parser::Cli;
+use super::ui;
+use crate::composer::types::ComposerOutput;
+use crate::deployer::default_remote_dir;
+use crate::deployer::doctor::{Finding, Severity};
+use crate::deployer::firewall::PortConfig;
+use crate::deployer::preflight::{self, HostFacts};
 use clap::Args;
-use tracing::info;
+use serde::Serialize;
+use std::path::Path;
+use tracing::{debug, info};
 
 #[derive(Debug, Args)]
-pub struct Analyze {}
+pub struct Analyze {
+    /// Also check this target ([user@]host[:port]) before deploying to it:
+    /// OS, Docker and Compose versions, free disk in the workdir, open
+    /// firewall ports and whether the workdir holds a deployment already
+    target: Option<String>,
+}
+
+/// `analyze <target> --json` output.
+#[derive(Serialize)]
+struct RemoteAnalysis<'a> {
+    analysis: &'a ComposerOutput,
+    host: &'a HostFacts,
+    findings: &'a [Finding],
+}
 
 impl Analyze {
     pub async fn run(&self, cli_args: &Cli) -> Result<(), CliError> {
@@ -15,8 +38,73 @@ impl Analyze {
             .await
             .map_err(|e| CliError::OperationFailed(format!("Local analysis failed: {}", e)))?;
 
-        print_analysis_results(&analysis);
+        let Some(target) = &self.target else {
+            if cli_args.json() {
+                print_json(&analysis)?;
+            } else if !cli_args.quiet {
+                print_analysis_results(&analysis);
+            }
+            return Ok(());
+        };
+
+        let workdir = cli_args
+            .remote_dir
+            .clone()
+            .unwrap_or_else(|| default_remote_dir(&analysis.resolved_project_dir));
+        let host = self.check_host(cli_args, target, &workdir).await?;
+        let findings = host.findings(&PortConfig::for_published(&analysis.exposed_ports));
+
+        if cli_args.json() {
+            print_json(&RemoteAnalysis {
+                analysis: &analysis,
+                host: &host,
+                findings: &findings,
+            })?;
+        } else if !cli_args.quiet {
+            print_analysis_results(&analysis);
+            println!(
+                "\n{}",
+                ui::format_header(&format!("Preflight checks for {}:", target))
+            );
+            print_findings(&findings);
+        }
 
+        let errors = findings
+            .iter()
+            .filter(|f| f.severity == Severity::Error)
+            .count();
+        if errors > 0 {
+            return Err(CliError::OperationFailed(format!(
+                "{} preflight check(s) failed",
+                errors
+            )));
+        }
         Ok(())
     }
+
+    async fn check_host(
+        &self,
+        cli_args: &Cli,
+        target_str: &str,
+        workdir: &Path,
+    ) -> Result<HostFacts, CliError> {
+        let target = parse_ssh_target(target_str)?;
+        debug!(user = %target.user, port = %target.port, key = ?cli_args.identity_file, "SSH details");
+
+        let pb = ui::create_spinner(&format!(
+            "Connecting to {}...",
+            ui::format_highlight(&target.host)
+        ));
+        let mut executor = connect_ssh(cli_args, &target).await.map_err(|e| {
+            pb.finish_and_clear();
+            CliError::OperationFailed(format!("SSH connection failed: {}", e))
+        })?;
+        pb.set_message("Running preflight checks...");
+
+        let host = preflight::gather(&mut executor, workdir)
+            .await
+            .map_err(|e| CliError::OperationFailed(format!("Preflight checks failed: {}", e)));
+        pb.finish_and_clear();
+        host
+    }
 }
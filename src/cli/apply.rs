@@ -0,0 +1,79 @@
+use super::error::CliError;
+use super::parser::Cli;
+use super::plan::current_plan;
+use super::ui;
+use super::up::Up;
+use crate::deployer::plan::DeployPlan;
+use crate::deployer::types::DeployError;
+use clap::Args;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, instrument};
+
+#[derive(Debug, Args)]
+pub struct Apply {
+    /// Plan file written by `dcd plan`
+    #[arg(value_name = "PLAN")]
+    plan: PathBuf,
+
+    #[command(flatten)]
+    pub up: Up,
+}
+
+impl Apply {
+    #[instrument(name = "apply", skip(self, cli_args), fields(plan = %self.plan.display()))]
+    pub async fn run(&self, cli_args: &Cli) -> Result<(), CliError> {
+        let targets = self.up.targets()?;
+        if !self.up.stacks.is_empty() {
+            return Err(CliError::ConfigError(
+                "--stack cannot be combined with apply; a plan covers one project".into(),
+            ));
+        }
+        let recorded = DeployPlan::load(&self.plan).map_err(|e| match e {
+            DeployError::Configuration(message) => CliError::ConfigError(message),
+            other => CliError::OperationFailed(other.to_string()),
+        })?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        info!(
+            "Applying plan created {} to {}",
+            recorded.created.summary(now),
+            ui::format_highlight(&targets.join(", "))
+        );
+
+        // Syncing other files than the plan recorded would defeat the review
+        if self.up.include_hidden != recorded.include_hidden {
+            return Err(CliError::ConfigError(format!(
+                "{} was written {} --include-hidden; apply it the same way",
+                self.plan.display(),
+                if recorded.include_hidden {
+                    "with"
+                } else {
+                    "without"
+                }
+            )));
+        }
+
+        // The plan's own -f/-e selection, so it is checked against the same files
+        let (analysis, current) = current_plan(
+            recorded.compose_files.clone(),
+            recorded.env_files.clone(),
+            recorded.include_hidden,
+        )
+        .await?;
+        let changes = recorded.changes(&current);
+        if !changes.is_empty() {
+            return Err(CliError::OperationFailed(format!(
+                "The local project changed since {} was created; run `dcd plan` again and \
+                 review it:\n  {}",
+                self.plan.display(),
+                changes.join("\n  ")
+            )));
+        }
+        info!("{}", ui::format_success("Local project matches the plan."));
+
+        self.up.deploy(cli_args, &targets, analysis).await
+    }
+}
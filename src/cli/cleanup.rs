@@ -0,0 +1,76 @@
+use super::common::{connect_ssh, parse_ssh_target, remote_only_config};
+use super::error::CliError;
+use super::parser::Cli;
+use super::ui;
+use super::ui::handle_deployer_events;
+use crate::deployer::types::DeployerEvent;
+use crate::deployer::Deployer;
+use clap::Args;
+use tokio::sync::mpsc;
+use tracing::{debug, info, instrument};
+
+#[derive(Debug, Args)]
+pub struct Cleanup {
+    /// Remote target in the format [user@]host[:port]
+    #[arg(required = true)]
+    target: String,
+
+    /// Disable interactive progress spinner and show only logs
+    #[arg(long)]
+    pub no_progress: bool,
+}
+
+impl Cleanup {
+    #[instrument(name = "cleanup", skip(self, cli_args), fields(target = %self.target))]
+    pub async fn run(&self, cli_args: &Cli) -> Result<(), CliError> {
+        let target = parse_ssh_target(&self.target)?;
+        info!(
+            "Cleaning up interrupted deployment on {}",
+            ui::format_highlight(&self.target)
+        );
+        debug!(user = %target.user, port = %target.port, key = ?cli_args.identity_file, "SSH details");
+
+        let (progress_sender, ui_update_task_handle) = if !self.no_progress {
+            let (sender, receiver) = mpsc::channel::<DeployerEvent>(32);
+            let pb = ui::create_spinner("Connecting...");
+            let ui_task = tokio::spawn(handle_deployer_events(receiver, pb.clone()));
+            (Some(sender), Some((ui_task, pb)))
+        } else {
+            (None, None)
+        };
+
+        let mut executor = connect_ssh(cli_args, &target).await.map_err(|e| {
+            if let Some((_, pb)) = ui_update_task_handle.as_ref() {
+                pb.finish_with_message("❌ SSH connection failed".to_string());
+            }
+            CliError::OperationFailed(format!("SSH connection failed: {}", e))
+        })?;
+
+        // Guided by the remote state file, so the local checkout is not needed
+        let mut deployer = Deployer::new(
+            remote_only_config(cli_args)?,
+            &mut executor,
+            progress_sender,
+        );
+        let cleanup_result = deployer.cleanup().await;
+        drop(deployer);
+
+        if let Some((ui_task, pb)) = ui_update_task_handle {
+            if let Err(e) = ui_task.await {
+                tracing::error!("UI update task failed: {}", e);
+            }
+            match &cleanup_result {
+                Ok(_) => pb.finish_with_message("✅ Cleanup finished."),
+                Err(_) => pb.finish_with_message("❌ Cleanup failed.".to_string()),
+            }
+        }
+
+        let status = cleanup_result
+            .map_err(|e| CliError::OperationFailed(format!("Cleanup failed: {}", e)))?;
+        info!("{}", ui::format_success(&status.message));
+        if status.files_changed {
+            info!("Run `dcd up` to deploy again.");
+        }
+        Ok(())
+    }
+}
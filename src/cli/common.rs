@@ -6,23 +6,44 @@ use crate::composer::{
     errors::ComposerError,
     types::{ComposerConfig, ComposerOutput},
 };
-use crate::executor::LocalCommandExecutor;
+use crate::config::ProjectConfig;
+use crate::deployer::docker_manager::use_rootless_docker;
+use crate::deployer::types::DeploymentConfig;
+use crate::executor::{
+    session,
+    ssh_config::{SshConfig, SshHostConfig},
+    ExecutorError, HostKeyPolicy, JumpHost, LocalCommandExecutor, RecordingExecutor, SshAuth,
+    SshCommandExecutor,
+};
 use anyhow::Result;
 use colored::Colorize;
-use std::path::PathBuf;
+use std::io::Read;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
 use tabled::{
     settings::{object::Rows, Color, Modify, Style},
     Table, Tabled,
 };
-use tracing::info;
+use tracing::{debug, info, warn};
 use url::Url;
 
+/// Target that deploys on the machine dcd runs on, without SSH.
+pub const LOCAL_TARGET: &str = "local";
+
 // Helper struct to hold parsed SSH target details
 #[derive(Debug)]
 pub struct SshTarget {
     pub user: String,
     pub host: String,
     pub port: u16,
+    /// The `local` target: commands run here, files are copied
+    pub local: bool,
+    /// `IdentityFile` from ~/.ssh/config, used when no other key is given
+    pub identity: Option<PathBuf>,
+    /// `ProxyJump` from ~/.ssh/config
+    pub jump: Option<String>,
 }
 
 // Parsing function using the url crate
@@ -30,6 +51,17 @@ pub fn parse_ssh_target(target_str: &str) -> Result<SshTarget, CliError> {
     let default_user = "root";
     let default_port = 22;
 
+    if target_str == LOCAL_TARGET {
+        return Ok(SshTarget {
+            user: std::env::var("USER").unwrap_or_else(|_| default_user.to_string()),
+            host: LOCAL_TARGET.to_string(),
+            port: 0,
+            local: true,
+            identity: None,
+            jump: None,
+        });
+    }
+
     // Prepend scheme if missing to satisfy Url::parse requirements
     let parse_input =
         if !target_str.contains("://") && !target_str.contains('@') && !target_str.contains(':') {
@@ -45,38 +77,397 @@ pub fn parse_ssh_target(target_str: &str) -> Result<SshTarget, CliError> {
         CliError::ConfigError(format!("Invalid target format '{}': {}", target_str, e))
     })?;
 
+    let alias = url
+        .host_str()
+        .ok_or_else(|| CliError::ConfigError(format!("Missing host in target '{}'", target_str)))?;
+
+    // Like ssh, the target may be a Host alias; what the target spells out wins
+    let ssh_config =
+        SshConfig::load_default().host(alias.trim_start_matches('[').trim_end_matches(']'));
+    if ssh_config != SshHostConfig::default() {
+        debug!("~/.ssh/config for {}: {:?}", alias, ssh_config);
+    }
+
     let user = if url.username().is_empty() {
-        default_user.to_string()
+        ssh_config.user.unwrap_or_else(|| default_user.to_string())
     } else {
         url.username().to_string()
     };
 
-    let host = url
-        .host_str()
-        .ok_or_else(|| CliError::ConfigError(format!("Missing host in target '{}'", target_str)))?
-        .to_string();
+    let host = ssh_config.hostname.unwrap_or_else(|| alias.to_string());
+
+    let port = url.port().or(ssh_config.port).unwrap_or(default_port);
+
+    Ok(SshTarget {
+        user,
+        host,
+        port,
+        local: false,
+        identity: ssh_config.identity_file,
+        jump: ssh_config.proxy_jump,
+    })
+}
+
+/// Executor for a remote target, recording the session if `--record-session` is set.
+pub type RemoteExecutor = RecordingExecutor<SshCommandExecutor>;
+
+// Helper to open an SSH connection to a parsed target using the global CLI options
+pub async fn connect_ssh(cli: &Cli, target: &SshTarget) -> Result<RemoteExecutor, ExecutorError> {
+    if let (Some(idle), false) = (cli.reuse_connection, target.local) {
+        match shared_ssh(cli, target, idle).await {
+            Ok(executor) => {
+                return Ok(with_rootless_docker(record_session(cli, target, executor)?).await)
+            }
+            Err(e) => warn!("Could not reuse a connection, connecting directly: {}", e),
+        }
+    }
+    direct_ssh(cli, target).await
+}
+
+// A new SSH connection owned by this process
+pub async fn direct_ssh(cli: &Cli, target: &SshTarget) -> Result<RemoteExecutor, ExecutorError> {
+    let executor = open_ssh(cli, target).await?;
+    Ok(with_rootless_docker(record_session(cli, target, executor)?).await)
+}
+
+/// Point docker commands at the login user's rootless daemon if it has one.
+async fn with_rootless_docker(mut executor: RemoteExecutor) -> RemoteExecutor {
+    match use_rootless_docker(&mut executor).await {
+        Ok(Some(rootless)) => info!("Using the rootless docker daemon at {}", rootless.socket),
+        Ok(None) => {}
+        Err(e) => debug!("Rootless docker detection skipped: {}", e),
+    }
+    executor
+}
+
+/// Connect to `target` without recording, e.g. for the background session.
+pub async fn open_ssh(cli: &Cli, target: &SshTarget) -> Result<SshCommandExecutor, ExecutorError> {
+    let addr_str = format!("{}:{}", target.host, target.port);
+    let jump = jump_host(cli, target).map_err(|e| ExecutorError::SshError(e.to_string()))?;
+    let executor = SshCommandExecutor::connect(
+        &ssh_auth(cli, target),
+        &target.user,
+        &addr_str,
+        Duration::from_secs(30),
+        host_key_policy(cli),
+        known_hosts_path(cli).as_deref(),
+        jump.as_ref(),
+    )
+    .await?;
+    Ok(match cli.ssh_channels {
+        Some(channels) => executor.with_max_channels(channels.into()),
+        None => executor,
+    })
+}
+
+/// `--jump` / `DCD_JUMP`, then the target's `ProxyJump` in ~/.ssh/config,
+/// then `jump` in dcd.toml.
+fn jump_target(cli: &Cli, target: &SshTarget) -> Option<String> {
+    cli.jump
+        .clone()
+        .or_else(|| target.jump.clone())
+        .or_else(|| local_project_config().jump)
+}
+
+/// Bastion to connect to `target` through, if one is configured.
+pub fn jump_host(cli: &Cli, target: &SshTarget) -> Result<Option<JumpHost>, CliError> {
+    let Some(jump) = jump_target(cli, target) else {
+        return Ok(None);
+    };
+    if jump.contains(',') {
+        return Err(CliError::ConfigError(format!(
+            "Jump host chains are not supported ('{}'); give a single jump host",
+            jump
+        )));
+    }
+    let target = parse_ssh_target(&jump)?;
+    if target.local {
+        return Err(CliError::ConfigError(
+            "The jump host can't be 'local'".to_string(),
+        ));
+    }
+    let host = if target.host.contains(':') {
+        format!("[{}]", target.host)
+    } else {
+        target.host
+    };
+    Ok(Some(JumpHost {
+        user: target.user,
+        addr: format!("{}:{}", host, target.port),
+    }))
+}
+
+fn record_session(
+    cli: &Cli,
+    target: &SshTarget,
+    executor: SshCommandExecutor,
+) -> Result<RemoteExecutor, ExecutorError> {
+    let executor = match &cli.record_session {
+        Some(path) => {
+            debug!("Recording the session to {}", path.display());
+            let name = format!("{}@{}:{}", target.user, target.host, target.port);
+            RecordingExecutor::new(executor, path, &name)?
+        }
+        None => RecordingExecutor::passthrough(executor),
+    };
+    if cli.show_commands {
+        return Ok(executor.with_echo(Box::new(ui::print_command_echo)));
+    }
+    Ok(executor)
+}
+
+/// Key for `target`: `--identity` (or `identity` in dcd.toml), then its
+/// `IdentityFile` in ~/.ssh/config.
+fn identity_path(cli: &Cli, target: &SshTarget) -> Option<PathBuf> {
+    cli.identity_file
+        .clone()
+        .or_else(|| target.identity.clone())
+}
+
+/// Authentication settings for `target` from `--auth` and `--identity`.
+pub fn ssh_auth(cli: &Cli, target: &SshTarget) -> SshAuth {
+    let mut auth = SshAuth {
+        key_path: identity_path(cli, target),
+        password: Some(ui::ssh_password),
+        ..Default::default()
+    };
+    if !cli.auth.is_empty() {
+        auth.methods = cli.auth.clone();
+    }
+    auth
+}
+
+/// What to do with hosts missing from known_hosts, from `--strict-host-key-checking`,
+/// `--accept-new` and `--no-warnings`.
+pub fn host_key_policy(cli: &Cli) -> HostKeyPolicy {
+    if cli.strict_host_key_checking {
+        HostKeyPolicy::Strict
+    } else if cli.accept_new {
+        HostKeyPolicy::AcceptNew
+    } else if cli.no_warnings {
+        HostKeyPolicy::Accept
+    } else {
+        HostKeyPolicy::Warn
+    }
+}
+
+/// Socket of the shared session for `target` with the current SSH options.
+pub fn session_socket(cli: &Cli, target: &SshTarget) -> Result<PathBuf, ExecutorError> {
+    session::socket_path(
+        &target.user,
+        &format!("{}:{}", target.host, target.port),
+        identity_path(cli, target).as_deref(),
+        known_hosts_path(cli).as_deref(),
+        host_key_policy(cli),
+        jump_target(cli, target).as_deref(),
+    )
+}
+
+// Attach to the background session for `target`, starting one if needed
+async fn shared_ssh(
+    cli: &Cli,
+    target: &SshTarget,
+    idle: u64,
+) -> Result<SshCommandExecutor, ExecutorError> {
+    let socket = session_socket(cli, target)?;
+    if let Ok(executor) = SshCommandExecutor::attach(&socket).await {
+        debug!("Reusing SSH session {}", socket.display());
+        return Ok(executor);
+    }
+
+    let exe = std::env::current_exe().map_err(|e| ExecutorError::Other(e.to_string()))?;
+    let mut command = std::process::Command::new(exe);
+    // The session process is given the resolved host, so pass on what
+    // ~/.ssh/config said about the alias
+    if let Some(key) = identity_path(cli, target) {
+        command.arg("--identity").arg(key);
+    }
+    if let Some(known_hosts) = known_hosts_path(cli) {
+        command.arg("--known-hosts").arg(known_hosts);
+    }
+    if let Some(jump) = jump_target(cli, target) {
+        command.arg("--jump").arg(jump);
+    }
+    match host_key_policy(cli) {
+        HostKeyPolicy::Strict => {
+            command.arg("--strict-host-key-checking");
+        }
+        HostKeyPolicy::AcceptNew => {
+            command.arg("--accept-new");
+        }
+        HostKeyPolicy::Accept => {
+            command.arg("--no-warnings");
+        }
+        HostKeyPolicy::Warn => {}
+    }
+    if !cli.auth.is_empty() {
+        let methods: Vec<&str> = cli.auth.iter().map(|method| method.as_str()).collect();
+        command.arg("--auth").arg(methods.join(","));
+    }
+    let host = if target.host.contains(':') {
+        format!("[{}]", target.host)
+    } else {
+        target.host.clone()
+    };
+    command
+        .arg("session")
+        .arg(format!("{}@{}:{}", target.user, host, target.port))
+        .arg("--idle")
+        .arg(idle.to_string())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        // Own process group, so Ctrl-C in this terminal leaves it running
+        .process_group(0);
+    let mut child = command
+        .spawn()
+        .map_err(|e| ExecutorError::Other(format!("Failed to start session: {}", e)))?;
+    debug!("Started SSH session process {}", child.id());
+
+    // Same budget as a direct connection attempt
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        if let Ok(executor) = SshCommandExecutor::attach(&socket).await {
+            return Ok(executor);
+        }
+        if let Ok(Some(status)) = child.try_wait() {
+            return Err(ExecutorError::Other(format!(
+                "session process exited ({})",
+                status
+            )));
+        }
+    }
+    Err(ExecutorError::Other(
+        "timed out waiting for the session process".to_string(),
+    ))
+}
+
+/// known_hosts file to use: `--known-hosts` / `DCD_KNOWN_HOSTS`, then
+/// `known_hosts` in dcd.toml, otherwise the default `~/.ssh/known_hosts`.
+pub fn known_hosts_path(cli: &Cli) -> Option<PathBuf> {
+    if let Some(path) = &cli.known_hosts {
+        return Some(path.clone());
+    }
+    local_project_config().known_hosts
+}
+
+/// Files uploaded at once per synced directory: `--sftp-streams`, then
+/// `configured` (`sync_concurrency` in dcd.toml).
+pub fn sync_concurrency(cli: &Cli, configured: Option<usize>) -> Option<usize> {
+    cli.sftp_streams.map(usize::from).or(configured)
+}
+
+/// `age_identity` from dcd.toml, needed by every command that talks to a
+/// deployment whose env is stored encrypted.
+pub fn age_identity_path() -> Option<PathBuf> {
+    local_project_config().age_identity
+}
+
+/// The project's dcd.toml / .dcd.yaml in the current directory, or defaults
+/// if it is missing or invalid.
+pub fn local_project_config() -> ProjectConfig {
+    ProjectConfig::load(Path::new(".")).unwrap_or_else(|e| {
+        // Commands that rely on dcd.toml report this themselves
+        debug!("Ignoring invalid dcd.toml: {}", e);
+        ProjectConfig::default()
+    })
+}
 
-    let port = url.port().unwrap_or(default_port);
+/// Export `--set` / `--set-stdin` values into this process, where analysis
+/// treats them like shell variables (overriding env files). `@path` values
+/// are read from the file so secrets stay out of shell history and `ps`.
+pub fn apply_variable_overrides(cli: &Cli) -> Result<(), CliError> {
+    for (name, value) in &cli.set_vars {
+        let value = if let Some(literal) = value.strip_prefix("@@") {
+            format!("@{}", literal)
+        } else if let Some(path) = value.strip_prefix('@') {
+            let content = std::fs::read_to_string(path).map_err(|e| {
+                CliError::ConfigError(format!(
+                    "Failed to read value of {} from {}: {}",
+                    name, path, e
+                ))
+            })?;
+            trim_line_ending(content)
+        } else {
+            value.clone()
+        };
+        std::env::set_var(name, value);
+    }
+    if let Some(name) = &cli.set_stdin {
+        let mut content = String::new();
+        std::io::stdin().read_to_string(&mut content).map_err(|e| {
+            CliError::ConfigError(format!("Failed to read {} from stdin: {}", name, e))
+        })?;
+        std::env::set_var(name, trim_line_ending(content));
+    }
+    Ok(())
+}
+
+// Values read from files usually end with a newline that is not part of the value
+fn trim_line_ending(mut value: String) -> String {
+    if value.ends_with('\n') {
+        value.pop();
+        if value.ends_with('\r') {
+            value.pop();
+        }
+    }
+    value
+}
 
-    Ok(SshTarget { user, host, port })
+// Deployment config for commands that skip local analysis (--remote-only);
+// the compose context is filled in from the remote directory later
+pub fn remote_only_config(cli: &Cli) -> Result<DeploymentConfig, CliError> {
+    let project_dir = std::fs::canonicalize("./").map_err(|e| {
+        CliError::ConfigError(format!("Failed to resolve current directory: {}", e))
+    })?;
+    Ok(DeploymentConfig {
+        project_dir,
+        remote_dir: cli.remote_dir.clone(),
+        age_identity: age_identity_path(),
+        ..Default::default()
+    })
 }
 
 // Helper to perform local analysis
 pub async fn get_analysis(cli: &Cli) -> Result<ComposerOutput, ComposerError> {
+    local_composer(cli).await?.analyze().await
+}
+
+// Composer for the local project selected by -f/-e
+pub async fn local_composer(cli: &Cli) -> Result<Composer<LocalCommandExecutor>, ComposerError> {
+    composer_for(cli.compose_files.clone(), cli.env_files.clone()).await
+}
+
+// Composer for the local project with the given compose and env files
+pub async fn composer_for(
+    compose_files: Vec<PathBuf>,
+    env_files: Vec<PathBuf>,
+) -> Result<Composer<LocalCommandExecutor>, ComposerError> {
+    composer_in(PathBuf::from("./"), compose_files, env_files).await
+}
+
+// Composer for the project in `project_dir`, e.g. a stack of a monorepo; the
+// files are relative to the current directory like -f/-e
+pub async fn composer_in(
+    project_dir: PathBuf,
+    compose_files: Vec<PathBuf>,
+    env_files: Vec<PathBuf>,
+) -> Result<Composer<LocalCommandExecutor>, ComposerError> {
     let executor = LocalCommandExecutor::new();
     let composer_config = ComposerConfig {
-        project_dir: PathBuf::from("./"), // TODO: Consider making this configurable or smarter
-        compose_files: cli.compose_files.clone(),
-        env_files: cli.env_files.clone(),
+        project_dir,
+        compose_files,
+        env_files,
     };
 
-    let mut composer = Composer::try_new(executor, composer_config).await?;
+    let composer = Composer::try_new(executor, composer_config).await?;
     info!(
         "Using local {} version {}",
         composer.compose_command.command_string(),
         composer.compose_version
     );
-    composer.analyze().await
+    Ok(composer)
 }
 
 #[derive(Tabled)]
@@ -229,4 +620,64 @@ pub fn print_analysis_results(analysis: &ComposerOutput) {
             ui::format_warning("No profiles currently active (set COMPOSE_PROFILES to activate)")
         );
     }
+
+    if !analysis.cron_jobs.is_empty() {
+        println!("\n{}", ui::format_header("Cron jobs:"));
+        for job in &analysis.cron_jobs {
+            println!(
+                "  - {} {} {}",
+                ui::format_highlight(&job.schedule),
+                job.service,
+                job.command.as_deref().unwrap_or("(default command)")
+            );
+        }
+    }
+
+    if !analysis.gpu_services.is_empty() {
+        println!("\n{}", ui::format_header("GPU services:"));
+        for service in &analysis.gpu_services {
+            println!("  - {}", service);
+        }
+    }
+
+    if !analysis.host_network_services.is_empty() {
+        println!("\n{}", ui::format_header("Host network services:"));
+        for service in &analysis.host_network_services {
+            println!("  - {}", service);
+        }
+    }
+
+    if !analysis.service_hints.is_empty() {
+        println!("\n{}", ui::format_header("Deployment hints (x-dcd):"));
+        for (service, hints) in &analysis.service_hints {
+            let mut details = Vec::new();
+            if hints.skip_health_check {
+                details.push("skip health check".to_string());
+            }
+            if let Some(firewall) = hints.firewall.as_ref().filter(|f| !f.allow.is_empty()) {
+                details.push(format!("firewall allow {}", firewall.allow.join(", ")));
+            }
+            if let Some(url) = &hints.smoke_test {
+                details.push(format!("smoke test {}", url));
+            }
+            if !hints.host_ports.is_empty() {
+                details.push(format!("host ports {}", hints.host_ports.join(", ")));
+            }
+            if let Some(backup) = hints.backup.as_ref().filter(|b| !b.paths.is_empty()) {
+                details.push(format!("backup {}", backup.paths.join(", ")));
+            }
+            println!(
+                "  - {}: {}",
+                ui::format_highlight(service),
+                details.join("; ")
+            );
+        }
+    }
+
+    if !analysis.warnings.is_empty() {
+        println!("\n{}", ui::format_header("Warnings:"));
+        for warning in &analysis.warnings {
+            println!("  - {}", ui::format_warning(warning));
+        }
+    }
 }
@@ -1,111 +1,218 @@
-use super::common::{get_analysis, parse_ssh_target};
+use super::common::{
+    age_identity_path, direct_ssh, get_analysis, parse_ssh_target, remote_only_config,
+};
 use super::error::CliError;
+use super::hosts::{on_each_host, report_hosts, resolve_targets};
 use super::parser::Cli;
 use super::ui;
 use super::ui::handle_deployer_events;
-use crate::deployer::types::DeployerEvent;
+use crate::composer::types::ComposerOutput;
+use crate::deployer::dry_run::DryRunReport;
+use crate::deployer::types::{DeployerEvent, DeploymentStatus};
 use crate::deployer::{types::DeploymentConfig, Deployer};
-use crate::executor::SshCommandExecutor;
 use clap::Args;
-use dialoguer::Confirm;
+use indicatif::MultiProgress;
 use std::path::PathBuf;
-use std::time::Duration;
 use tokio::sync::mpsc;
-use tracing::{debug, info, instrument, warn};
+use tracing::{debug, info, instrument, warn, Instrument};
 
 #[derive(Debug, Args)]
 pub struct Destroy {
-    /// Remote target in the format [user@]host[:port]
-    #[arg(required = true)]
-    target: String,
+    /// Remote target(s) in the format [user@]host[:port]
+    #[arg(value_name = "TARGET", required_unless_present = "hosts_file")]
+    targets: Vec<String>,
+
+    /// File listing more targets, one per line (`#` starts a comment)
+    #[arg(long, value_name = "PATH")]
+    hosts_file: Option<PathBuf>,
 
     /// Force destruction without confirmation and remove volumes
     #[arg(long)]
     force: bool,
 
+    /// Skip the confirmation prompt (volumes are kept unless --force is given)
+    #[arg(short = 'y', long)]
+    yes: bool,
+
+    /// Skip local analysis and tear down what is deployed in the remote directory
+    #[arg(long)]
+    remote_only: bool,
+
     /// Disable interactive progress spinner and show only logs
     #[arg(long)]
     pub no_progress: bool,
+
+    /// Show the containers, volumes and commands a destroy would remove, without changing anything
+    #[arg(long)]
+    pub dry_run: bool,
 }
 
 impl Destroy {
-    #[instrument(name = "destroy", skip(self, cli_args), fields(target = %self.target))]
+    #[instrument(name = "destroy", skip(self, cli_args), fields(targets = ?self.targets))]
     pub async fn run(&self, cli_args: &Cli) -> Result<(), CliError> {
-        let target = parse_ssh_target(&self.target)?;
-        info!(
-            "Destroying deployment on {}",
-            ui::format_highlight(&self.target)
-        );
-        debug!(user = %target.user, port = %target.port, key = ?cli_args.identity_file, "SSH details");
-
-        // --- Setup Progress Reporting ---
-        let (progress_sender, ui_update_task_handle) = if !self.no_progress {
-            let (sender, receiver) = mpsc::channel::<DeployerEvent>(32);
-            // Use a more neutral initial message as confirmation happens first
-            let pb = ui::create_spinner("Preparing destruction...");
-            let ui_task = tokio::spawn(handle_deployer_events(receiver, pb.clone()));
-            (Some(sender), Some((ui_task, pb)))
-        } else {
-            info!("Progress spinner disabled via --no-progress.");
-            (None, None)
-        };
+        let targets = resolve_targets(&self.targets, self.hosts_file.as_deref())?;
+        let shown = targets.join(", ");
+        info!("Destroying deployment on {}", ui::format_highlight(&shown));
 
         // --- Confirmation Prompt ---
-        if !self.force {
+        if self.dry_run {
+            info!("--dry-run provided. Nothing will be removed.");
+        } else if self.force {
+            warn!("--force flag provided. Skipping confirmation and forcing volume removal.");
+        } else if self.yes {
+            info!("--yes flag provided. Skipping confirmation.");
+        } else {
             warn!(
                 "This action will stop and remove containers, networks, and potentially volumes."
             );
-            if !Confirm::new()
-                .with_prompt(format!(
+            if !ui::confirm(
+                &format!(
                     "Are you sure you want to destroy the deployment on {}?",
-                    ui::format_highlight(&self.target)
-                ))
-                .interact()
-                .map_err(|e| {
-                    CliError::OperationFailed(format!("Failed to get confirmation: {}", e))
-                })?
-            {
-                // If user cancels, ensure progress bar (if exists) is cleared
+                    ui::format_highlight(&shown)
+                ),
+                "--yes (or --force to also remove volumes)",
+            )? {
                 info!("Destruction cancelled by user.");
-                if let Some((_, pb)) = ui_update_task_handle {
-                    pb.finish_and_clear(); // Clear spinner on cancellation
-                }
                 return Ok(());
             }
-            // If proceeding, update spinner message if it exists
-            if let Some((_, pb)) = &ui_update_task_handle {
-                pb.set_message("Proceeding with destruction...");
-            } else {
-                info!("Proceeding with destruction..."); // Log if no spinner
-            }
-        } else {
-            warn!("--force flag provided. Skipping confirmation and forcing volume removal.");
+            info!("Proceeding with destruction...");
         }
 
         // --- Local Analysis (Minimal) ---
         // Needed to determine project context and potentially volumes to remove
-        info!("Performing local analysis to determine project context..."); // Use info log
-        let analysis = get_analysis(cli_args).await.map_err(|e| {
-            if let Some((_, pb)) = ui_update_task_handle.as_ref() {
-                // Borrow handle
-                pb.finish_with_message("❌ Local analysis failed".to_string());
+        let analysis = if self.remote_only {
+            info!("--remote-only provided. Skipping local analysis.");
+            None
+        } else {
+            info!("Performing local analysis to determine project context..."); // Use info log
+            let analysis = get_analysis(cli_args).await.map_err(|e| {
+                CliError::OperationFailed(format!(
+                    "Local analysis failed: {} (use --remote-only to destroy without it)",
+                    e
+                ))
+            })?;
+            info!("Local analysis complete.");
+            Some(analysis)
+        };
+
+        if self.dry_run {
+            return self.dry_run(cli_args, &targets, analysis).await;
+        }
+
+        if let ([target], false) = (targets.as_slice(), cli_args.json()) {
+            let status = self.destroy_host(cli_args, target, analysis, None).await?;
+            info!(
+                "{}",
+                ui::format_success("Deployment destroyed successfully!")
+            );
+            if !status.message.is_empty() {
+                info!("Details:\n{}", status.message.trim());
             }
-            CliError::OperationFailed(format!("Local analysis failed: {}", e))
-        })?;
-        info!("Local analysis complete.");
+            return Ok(());
+        }
 
-        // --- SSH Connection ---
-        info!("Connecting to {}...", ui::format_highlight(&target.host));
-        let addr_str = format!("{}:{}", target.host, target.port);
-        let mut executor = SshCommandExecutor::connect(
-            cli_args.identity_file.as_ref(),
-            &target.user,
-            &addr_str,
-            Duration::from_secs(30),
-            cli_args.no_warnings,
+        let multi = MultiProgress::new();
+        let results = on_each_host(cli_args, &targets, |target| {
+            let analysis = analysis.clone();
+            let multi = &multi;
+            async move {
+                self.destroy_host(cli_args, &target, analysis, Some(multi))
+                    .await
+            }
+        })
+        .await;
+        report_hosts(
+            "Destruction",
+            &results,
+            |_| Ok("destroyed".to_string()),
+            cli_args,
         )
+    }
+
+    /// Report what destroying the deployment on `targets` would remove.
+    async fn dry_run(
+        &self,
+        cli_args: &Cli,
+        targets: &[String],
+        analysis: Option<ComposerOutput>,
+    ) -> Result<(), CliError> {
+        let mut results = on_each_host(cli_args, targets, |target| {
+            let analysis = analysis.clone();
+            async move { self.dry_run_host(cli_args, &target, analysis).await }
+        })
+        .await;
+        if !cli_args.json() && !cli_args.quiet {
+            for (target, result) in &results {
+                if let Ok(report) = result {
+                    ui::print_dry_run(target, report);
+                }
+            }
+        }
+        if let ([_], false) = (targets, cli_args.json()) {
+            return results
+                .pop()
+                .map_or(Ok(()), |(_, result)| result.map(|_| ()));
+        }
+        report_hosts("Dry run", &results, |report| Ok(report.summary()), cli_args)
+    }
+
+    /// Connect to one host and find out what destroying there would remove.
+    async fn dry_run_host(
+        &self,
+        cli_args: &Cli,
+        target_str: &str,
+        analysis: Option<ComposerOutput>,
+    ) -> Result<DryRunReport, CliError> {
+        let target = parse_ssh_target(target_str)?;
+        info!(
+            "Checking what would be removed on {}...",
+            ui::format_highlight(&target.host)
+        );
+        let mut executor = direct_ssh(cli_args, &target)
+            .await
+            .map_err(|e| CliError::OperationFailed(format!("SSH connection failed: {}", e)))?;
+        let mut deployer = Deployer::new(destroy_config(cli_args, analysis)?, &mut executor, None);
+        async {
+            if self.remote_only {
+                deployer.use_remote_project().await?;
+            }
+            deployer
+                .plan_destroy(self.force, self.force, self.force)
+                .await
+        }
         .await
-        .map_err(|e| {
+        .map_err(|e| CliError::OperationFailed(format!("Dry run failed: {}", e)))
+    }
+
+    async fn destroy_host(
+        &self,
+        cli_args: &Cli,
+        target_str: &str,
+        analysis: Option<ComposerOutput>,
+        multi: Option<&MultiProgress>,
+    ) -> Result<DeploymentStatus, CliError> {
+        let target = parse_ssh_target(target_str)?;
+        debug!(user = %target.user, port = %target.port, key = ?cli_args.identity_file, "SSH details");
+
+        // --- Setup Progress Reporting ---
+        let (progress_sender, ui_update_task_handle) = if !self.no_progress {
+            let (sender, receiver) = mpsc::channel::<DeployerEvent>(32);
+            let message = "Proceeding with destruction...";
+            let pb = match multi {
+                Some(multi) => ui::create_host_spinner(multi, target_str, message),
+                None => ui::create_spinner(message),
+            };
+            let ui_task =
+                tokio::spawn(handle_deployer_events(receiver, pb.clone()).in_current_span());
+            (Some(sender), Some((ui_task, pb)))
+        } else {
+            info!("Progress spinner disabled via --no-progress.");
+            (None, None)
+        };
+
+        // --- SSH Connection ---
+        info!("Connecting to {}...", ui::format_highlight(&target.host));
+        let mut executor = direct_ssh(cli_args, &target).await.map_err(|e| {
             if let Some((_, pb)) = ui_update_task_handle.as_ref() {
                 // Borrow handle
                 pb.finish_with_message("❌ SSH connection failed".to_string());
@@ -115,30 +222,22 @@ impl Destroy {
         info!("Connected to {}.", ui::format_highlight(&target.host));
 
         // --- Destruction ---
-        let deploy_config = DeploymentConfig {
-            project_dir: analysis.resolved_project_dir.clone(),
-            remote_dir: cli_args.remote_dir.clone(),
-            compose_files: analysis.resolved_compose_files.clone(),
-            env_files: analysis.resolved_env_files.clone(),
-            // Pass other analysis results needed for potential volume cleanup etc.
-            consumed_env: analysis.consumed_env,
-            exposed_ports: analysis.exposed_ports,
-            local_references: analysis
-                .local_references
-                .iter()
-                .map(PathBuf::from)
-                .collect(),
-            volumes: analysis.volumes,
-        };
+        let deploy_config = destroy_config(cli_args, analysis)?;
 
         // Instantiate Deployer, passing the sender
         let mut deployer = Deployer::new(deploy_config, &mut executor, progress_sender);
 
         // Destroy deployment. Pass `force` to control volume removal.
         let remove_volumes = self.force; // Only remove volumes if --force is used
-        let destroy_result = deployer
-            .destroy(remove_volumes, self.force, self.force) // Assuming second self.force maps to remove_images for now
-            .await;
+        let destroy_result = async {
+            if self.remote_only {
+                deployer.use_remote_project().await?;
+            }
+            deployer
+                .destroy(remove_volumes, self.force, self.force) // Assuming second self.force maps to remove_images for now
+                .await
+        }
+        .await;
 
         // Drop deployer to close channel
         drop(deployer);
@@ -155,17 +254,38 @@ impl Destroy {
         }
 
         // Handle the result after UI is done
-        let status = destroy_result
-            .map_err(|e| CliError::OperationFailed(format!("Destruction failed: {}", e)))?;
-
-        info!(
-            "{}",
-            ui::format_success("Deployment destroyed successfully!")
-        );
-        if !status.message.is_empty() {
-            info!("Details:\n{}", status.message.trim());
-        }
-
-        Ok(())
+        destroy_result.map_err(|e| CliError::OperationFailed(format!("Destruction failed: {}", e)))
     }
 }
+
+/// Deployment settings for tearing down the project: from the local analysis,
+/// or read from the remote directory when there is none.
+fn destroy_config(
+    cli_args: &Cli,
+    analysis: Option<ComposerOutput>,
+) -> Result<DeploymentConfig, CliError> {
+    Ok(match analysis {
+        Some(analysis) => DeploymentConfig {
+            project_dir: analysis.resolved_project_dir.clone(),
+            age_identity: age_identity_path(),
+            remote_dir: cli_args.remote_dir.clone(),
+            compose_files: analysis.resolved_compose_files.clone(),
+            env_files: analysis.resolved_env_files.clone(),
+            // Pass other analysis results needed for potential volume cleanup etc.
+            consumed_env: analysis.consumed_env,
+            exposed_ports: analysis.exposed_ports,
+            local_references: analysis
+                .local_references
+                .iter()
+                .map(PathBuf::from)
+                .collect(),
+            volumes: analysis.volumes,
+            service_dependencies: analysis.service_dependencies,
+            profiles: analysis.active_profiles,
+            service_hints: analysis.service_hints,
+            ..Default::default()
+        },
+        // Compose files and profiles are read from the remote directory
+        None => remote_only_config(cli_args)?,
+    })
+}
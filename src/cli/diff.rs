@@ -0,0 +1,93 @@
+use super::common::{direct_ssh, local_composer, parse_ssh_target, sync_concurrency};
+use super::error::CliError;
+use super::parser::Cli;
+use super::ui;
+use crate::config::ProjectConfig;
+use crate::deployer::types::DeploymentConfig;
+use crate::deployer::Deployer;
+use clap::Args;
+use std::path::PathBuf;
+use tracing::{debug, info, instrument};
+
+#[derive(Debug, Args)]
+pub struct Diff {
+    /// Remote target in the format [user@]host[:port]
+    #[arg(required = true)]
+    target: String,
+
+    /// Also compare hidden files and directories (dotfiles), as with `up --include-hidden`
+    #[arg(long)]
+    include_hidden: bool,
+
+    /// Exit with an error when the deployment differs from the local project
+    #[arg(long)]
+    exit_code: bool,
+}
+
+impl Diff {
+    #[instrument(name = "diff", skip(self, cli_args), fields(target = %self.target))]
+    pub async fn run(&self, cli_args: &Cli) -> Result<(), CliError> {
+        let target = parse_ssh_target(&self.target)?;
+        debug!(user = %target.user, port = %target.port, key = ?cli_args.identity_file, "SSH details");
+
+        let pb = ui::create_spinner("Performing local analysis...");
+        let result = async {
+            let mut composer = local_composer(cli_args).await?;
+            let analysis = composer.analyze().await?;
+            let rendered = composer.resolved_config(false).await?;
+            Ok::<_, crate::composer::errors::ComposerError>((analysis, rendered))
+        }
+        .await;
+        let (analysis, rendered) = result.map_err(|e| {
+            pb.finish_and_clear();
+            CliError::OperationFailed(format!("Local analysis failed: {}", e))
+        })?;
+        let project_config = ProjectConfig::load(&analysis.resolved_project_dir)
+            .map_err(|e| CliError::ConfigError(e.to_string()))?;
+
+        pb.set_message(format!("Comparing with {}...", self.target));
+        let mut executor = direct_ssh(cli_args, &target).await.map_err(|e| {
+            pb.finish_and_clear();
+            CliError::OperationFailed(format!("SSH connection failed: {}", e))
+        })?;
+
+        let deploy_config = DeploymentConfig {
+            project_dir: analysis.resolved_project_dir,
+            remote_dir: cli_args.remote_dir.clone(),
+            compose_files: analysis.resolved_compose_files,
+            env_files: analysis.resolved_env_files,
+            consumed_env: analysis.consumed_env,
+            local_references: analysis
+                .local_references
+                .iter()
+                .map(PathBuf::from)
+                .collect(),
+            profiles: analysis.active_profiles,
+            age_identity: project_config.age_identity,
+            sync_concurrency: sync_concurrency(cli_args, project_config.sync_concurrency),
+            secrets: project_config.secrets,
+            include_hidden: self.include_hidden,
+            ..Default::default()
+        };
+        let mut deployer = Deployer::new(deploy_config, &mut executor, None);
+        let result = deployer.diff_project(&rendered).await;
+        pb.finish_and_clear();
+        let diff = result.map_err(|e| CliError::OperationFailed(format!("Diff failed: {}", e)))?;
+
+        if !cli_args.quiet {
+            ui::print_project_diff(&self.target, &diff);
+        }
+        if diff.is_empty() {
+            info!(
+                "{}",
+                ui::format_success("The deployment matches the local project.")
+            );
+        } else if self.exit_code {
+            return Err(CliError::OperationFailed(format!(
+                "The deployment on {} differs from the local project",
+                self.target
+            )));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,87 @@
+use super::common::{connect_ssh, parse_ssh_target};
+use super::error::CliError;
+use super::parser::Cli;
+use super::ui;
+use crate::deployer::doctor::{Finding, HostDoctor, Severity};
+use clap::Args;
+use colored::*;
+use tracing::{debug, info, instrument};
+
+#[derive(Debug, Args)]
+pub struct Doctor {
+    /// Remote target in the format [user@]host[:port]
+    #[arg(required = true)]
+    target: String,
+}
+
+impl Doctor {
+    #[instrument(name = "doctor", skip(self, cli_args), fields(target = %self.target))]
+    pub async fn run(&self, cli_args: &Cli) -> Result<(), CliError> {
+        let target = parse_ssh_target(&self.target)?;
+        info!("Checking host {}", ui::format_highlight(&self.target));
+        debug!(user = %target.user, port = %target.port, key = ?cli_args.identity_file, "SSH details");
+
+        let pb = ui::create_spinner(&format!(
+            "Connecting to {}...",
+            ui::format_highlight(&target.host)
+        ));
+        let mut executor = connect_ssh(cli_args, &target).await.map_err(|e| {
+            pb.finish_and_clear();
+            CliError::OperationFailed(format!("SSH connection failed: {}", e))
+        })?;
+        pb.set_message("Running host checks...");
+
+        let findings = HostDoctor::new(&mut executor)
+            .run_all()
+            .await
+            .map_err(|e| {
+                pb.finish_and_clear();
+                CliError::OperationFailed(format!("Host checks failed: {}", e))
+            })?;
+        pb.finish_and_clear();
+
+        if !cli_args.quiet {
+            println!(
+                "\n{}",
+                ui::format_header(&format!("Host checks for {}:", self.target))
+            );
+            print_findings(&findings);
+        }
+
+        let errors = findings
+            .iter()
+            .filter(|f| f.severity == Severity::Error)
+            .count();
+        if errors > 0 {
+            return Err(CliError::OperationFailed(format!(
+                "{} host check(s) failed",
+                errors
+            )));
+        }
+        Ok(())
+    }
+}
+
+pub fn print_findings(findings: &[Finding]) {
+    for finding in findings {
+        let marker = match finding.severity {
+            Severity::Ok => "✅".to_string(),
+            Severity::Warning => "⚠️ ".to_string(),
+            Severity::Error => "❌".to_string(),
+        };
+        let message = match finding.severity {
+            Severity::Ok => finding.message.normal(),
+            Severity::Warning => finding.message.yellow(),
+            Severity::Error => finding.message.red(),
+        };
+        println!(
+            "  {} {:<8} {}",
+            marker,
+            ui::format_highlight(&finding.check),
+            message
+        );
+        if let Some(suggestion) = &finding.suggestion {
+            println!("     {} {}", "→".dimmed(), suggestion);
+        }
+    }
+}
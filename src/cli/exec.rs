@@ -0,0 +1,65 @@
+use super::common::{connect_ssh, parse_ssh_target, remote_only_config};
+use super::error::CliError;
+use super::parser::Cli;
+use super::ui;
+use crate::deployer::Deployer;
+use clap::Args;
+use std::io::IsTerminal;
+use tracing::{debug, info, instrument};
+
+#[derive(Debug, Args)]
+pub struct Exec {
+    /// Remote target in the format [user@]host[:port]
+    #[arg(required = true)]
+    target: String,
+
+    /// Running service to execute the command in
+    #[arg(required = true)]
+    service: String,
+
+    /// Command and arguments (e.g. `sh`)
+    #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+    command: Vec<String>,
+
+    /// Disable pseudo-TTY allocation (default when stdin is not a terminal)
+    #[arg(short = 'T', long)]
+    no_tty: bool,
+}
+
+impl Exec {
+    #[instrument(name = "exec", skip(self, cli_args), fields(target = %self.target))]
+    pub async fn run(&self, cli_args: &Cli) -> Result<(), CliError> {
+        let target = parse_ssh_target(&self.target)?;
+        info!(
+            "Executing in {} on {}",
+            ui::format_highlight(&self.service),
+            ui::format_highlight(&self.target)
+        );
+        debug!(user = %target.user, port = %target.port, key = ?cli_args.identity_file, "SSH details");
+
+        let mut executor = connect_ssh(cli_args, &target)
+            .await
+            .map_err(|e| CliError::OperationFailed(format!("SSH connection failed: {}", e)))?;
+
+        // Works on the running containers, so the files on the server are enough
+        let mut deployer = Deployer::new(remote_only_config(cli_args)?, &mut executor, None);
+        deployer
+            .use_remote_project()
+            .await
+            .map_err(|e| CliError::OperationFailed(e.to_string()))?;
+
+        let tty = !self.no_tty && std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
+        let exit_code = deployer
+            .exec_service(&self.service, &self.command, tty)
+            .await
+            .map_err(|e| CliError::OperationFailed(e.to_string()))?;
+
+        if exit_code != 0 {
+            return Err(CliError::OperationFailed(format!(
+                "Command in service '{}' exited with status {}",
+                self.service, exit_code
+            )));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+use super::common::local_composer;
+use super::error::CliError;
+use super::parser::Cli;
+use super::ui;
+use crate::composer::errors::ComposerError;
+use crate::deployer::sync::EnvFileManager;
+use crate::executor::{CommandExecutor, LocalCommandExecutor};
+use clap::Args;
+use std::path::{Path, PathBuf};
+use tracing::{info, instrument, warn};
+
+/// Compose file name inside the frozen bundle.
+const FROZEN_COMPOSE_FILE: &str = "compose.yaml";
+/// Env snapshot inside the frozen bundle.
+const FROZEN_ENV_FILE: &str = ".env";
+
+#[derive(Debug, Args)]
+pub struct Freeze {
+    /// Directory to write the frozen configuration to
+    #[arg(short, long, default_value = "dcd-freeze", value_name = "DIR")]
+    output: PathBuf,
+
+    /// Also pack the directory into <DIR>.tar.gz
+    #[arg(long)]
+    tar: bool,
+
+    /// Keep image tags instead of pinning them to registry digests
+    /// (needed for images that are only built locally)
+    #[arg(long)]
+    no_pin: bool,
+}
+
+impl Freeze {
+    #[instrument(name = "freeze", skip(self, cli_args), fields(output = %self.output.display()))]
+    pub async fn run(&self, cli_args: &Cli) -> Result<(), CliError> {
+        let pb = ui::create_spinner("Resolving compose configuration...");
+        let result = async {
+            let mut composer = local_composer(cli_args).await?;
+            let analysis = composer.analyze().await?;
+            let compose = composer.resolved_config(!self.no_pin).await?;
+            Ok::<_, ComposerError>((analysis, compose))
+        }
+        .await;
+        pb.finish_and_clear();
+        let (analysis, compose) = result
+            .map_err(|e| CliError::OperationFailed(format!("Local analysis failed: {}", e)))?;
+
+        std::fs::create_dir_all(&self.output).map_err(|e| write_error(&self.output, e))?;
+        let compose_path = self.output.join(FROZEN_COMPOSE_FILE);
+        std::fs::write(&compose_path, compose).map_err(|e| write_error(&compose_path, e))?;
+        info!(
+            "Wrote {}",
+            ui::format_highlight(&compose_path.display().to_string())
+        );
+
+        let env = EnvFileManager::new(analysis.consumed_env, &analysis.resolved_project_dir);
+        if let Some(content) = env.render_dcd_env() {
+            let env_path = self.output.join(FROZEN_ENV_FILE);
+            write_private(&env_path, &content).map_err(|e| write_error(&env_path, e))?;
+            info!(
+                "Wrote {}",
+                ui::format_highlight(&env_path.display().to_string())
+            );
+            warn!(
+                "{}",
+                ui::format_warning(
+                    "The env snapshot contains variable values in plain text; keep it private."
+                )
+            );
+        }
+
+        if self.tar {
+            let archive = PathBuf::from(format!("{}.tar.gz", self.output.display()));
+            let cmd = format!(
+                "tar -czf '{}' -C '{}' .",
+                archive.display(),
+                self.output.display()
+            );
+            let result = LocalCommandExecutor::new()
+                .execute_command(&cmd)
+                .await
+                .map_err(|e| CliError::OperationFailed(format!("Failed to run tar: {}", e)))?;
+            if !result.is_success() {
+                return Err(CliError::OperationFailed(format!(
+                    "Failed to create {}: {}",
+                    archive.display(),
+                    result.output.to_stderr_string().unwrap_or_default().trim()
+                )));
+            }
+            info!(
+                "Wrote {}",
+                ui::format_highlight(&archive.display().to_string())
+            );
+        }
+
+        info!(
+            "{}",
+            ui::format_success(&format!(
+                "Frozen configuration written; apply it with `docker compose -f {} up -d`",
+                compose_path.display()
+            ))
+        );
+        Ok(())
+    }
+}
+
+fn write_error(path: &Path, e: std::io::Error) -> CliError {
+    CliError::OperationFailed(format!("Failed to write {}: {}", path.display(), e))
+}
+
+// Write a file readable by its owner only, since it holds secrets
+fn write_private(path: &Path, content: &str) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    file.write_all(content.as_bytes())
+}
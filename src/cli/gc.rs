@@ -0,0 +1,115 @@
+use super::common::{connect_ssh, parse_ssh_target, remote_only_config};
+use super::error::CliError;
+use super::hosts::print_json;
+use super::parser::Cli;
+use super::ui;
+use super::ui::handle_deployer_events;
+use crate::composer::config::duration::parse_duration;
+use crate::deployer::types::DeployerEvent;
+use crate::deployer::Deployer;
+use clap::Args;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{debug, info, instrument};
+
+#[derive(Debug, Args)]
+pub struct Gc {
+    /// Remote target in the format [user@]host[:port]
+    #[arg(required = true)]
+    target: String,
+
+    /// Keep the rollback point this long after the deployment that replaced it (e.g. 72h, 0 to drop it)
+    #[arg(
+        long,
+        value_name = "DURATION",
+        default_value = "720h",
+        value_parser = parse_retention
+    )]
+    rollback_retention: Duration,
+
+    /// Only list what would be removed
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Disable interactive progress spinner and show only logs
+    #[arg(long)]
+    pub no_progress: bool,
+}
+
+impl Gc {
+    #[instrument(name = "gc", skip(self, cli_args), fields(target = %self.target))]
+    pub async fn run(&self, cli_args: &Cli) -> Result<(), CliError> {
+        let target = parse_ssh_target(&self.target)?;
+        info!(
+            "Collecting stale dcd files on {}",
+            ui::format_highlight(&self.target)
+        );
+        debug!(user = %target.user, port = %target.port, key = ?cli_args.identity_file, "SSH details");
+
+        let (progress_sender, ui_update_task_handle) = if !self.no_progress {
+            let (sender, receiver) = mpsc::channel::<DeployerEvent>(32);
+            let pb = ui::create_spinner("Connecting...");
+            let ui_task = tokio::spawn(handle_deployer_events(receiver, pb.clone()));
+            (Some(sender), Some((ui_task, pb)))
+        } else {
+            (None, None)
+        };
+
+        let mut executor = connect_ssh(cli_args, &target).await.map_err(|e| {
+            if let Some((_, pb)) = ui_update_task_handle.as_ref() {
+                pb.finish_with_message("❌ SSH connection failed".to_string());
+            }
+            CliError::OperationFailed(format!("SSH connection failed: {}", e))
+        })?;
+
+        let mut deployer = Deployer::new(
+            remote_only_config(cli_args)?,
+            &mut executor,
+            progress_sender,
+        );
+        let gc_result = deployer.gc(self.rollback_retention, self.dry_run).await;
+        drop(deployer);
+
+        if let Some((ui_task, pb)) = ui_update_task_handle {
+            if let Err(e) = ui_task.await {
+                tracing::error!("UI update task failed: {}", e);
+            }
+            match &gc_result {
+                Ok(_) => pb.finish_and_clear(),
+                Err(_) => pb.finish_with_message("❌ Garbage collection failed.".to_string()),
+            }
+        }
+
+        let report = gc_result
+            .map_err(|e| CliError::OperationFailed(format!("Garbage collection failed: {}", e)))?;
+        if cli_args.json() {
+            return print_json(&report);
+        }
+        let verb = if report.dry_run {
+            "Would remove"
+        } else {
+            "Removed"
+        };
+        for path in &report.removed {
+            info!("{} {}", verb, path.display());
+        }
+        if report.rollback_expired {
+            info!(
+                "{}",
+                ui::format_warning(
+                    "The rollback point is past its retention; `dcd rollback` has nothing to return to"
+                )
+            );
+        }
+        info!(
+            "{}",
+            ui::format_success(&format!("{} {} file(s).", verb, report.removed.len()))
+        );
+        Ok(())
+    }
+}
+
+fn parse_retention(value: &str) -> Result<Duration, String> {
+    parse_duration(value)
+        .ok_or_else(|| format!("invalid duration '{}' (expected e.g. 30m, 72h)", value))
+}
@@ -0,0 +1,64 @@
+use super::common::{connect_ssh, parse_ssh_target, remote_only_config};
+use super::error::CliError;
+use super::parser::Cli;
+use super::ui;
+use crate::deployer::docker_manager::HealthCheckResult;
+use crate::deployer::Deployer;
+use clap::Args;
+use tracing::{debug, instrument};
+
+#[derive(Debug, Args)]
+pub struct Health {
+    /// Remote target in the format [user@]host[:port]
+    #[arg(required = true)]
+    target: String,
+}
+
+impl Health {
+    #[instrument(name = "health", skip(self, cli_args), fields(target = %self.target))]
+    pub async fn run(&self, cli_args: &Cli) -> Result<(), CliError> {
+        let target = parse_ssh_target(&self.target)?;
+        debug!(user = %target.user, port = %target.port, key = ?cli_args.identity_file, "SSH details");
+
+        let mut executor = connect_ssh(cli_args, &target)
+            .await
+            .map_err(|e| CliError::OperationFailed(format!("SSH connection failed: {}", e)))?;
+
+        // Works from the files on the server, so no local analysis is needed
+        let mut deployer = Deployer::new(remote_only_config(cli_args)?, &mut executor, None);
+        let result = async {
+            deployer.use_remote_project().await?;
+            deployer.check_health().await
+        }
+        .await
+        .map_err(|e| CliError::OperationFailed(format!("Health check failed: {}", e)))?;
+
+        match result {
+            HealthCheckResult::Healthy => {
+                if !cli_args.quiet {
+                    println!("{}", ui::format_success("All services are healthy."));
+                }
+                Ok(())
+            }
+            HealthCheckResult::NoServices => Err(CliError::OperationFailed(
+                "No services are running".to_string(),
+            )),
+            HealthCheckResult::Starting(services) | HealthCheckResult::Failed(services) => {
+                if !cli_args.quiet {
+                    for service in &services {
+                        println!(
+                            "{} {} ({})",
+                            ui::format_warning("✗"),
+                            ui::format_highlight(&service.name),
+                            service.status
+                        );
+                    }
+                }
+                Err(CliError::OperationFailed(format!(
+                    "{} service(s) not healthy",
+                    services.len()
+                )))
+            }
+        }
+    }
+}
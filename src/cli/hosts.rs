@@ -0,0 +1,186 @@
+use super::common::parse_ssh_target;
+use super::error::CliError;
+use super::parser::Cli;
+use super::ui;
+use colored::Colorize;
+use futures::stream::{self, StreamExt};
+use serde::Serialize;
+use std::future::Future;
+use std::path::Path;
+use tabled::{
+    settings::{object::Rows, Color, Modify, Style},
+    Table, Tabled,
+};
+use tracing::Instrument;
+
+/// Targets given on the command line followed by those listed in
+/// `hosts_file` (one per line, `#` starts a comment), without duplicates.
+pub fn resolve_targets(
+    targets: &[String],
+    hosts_file: Option<&Path>,
+) -> Result<Vec<String>, CliError> {
+    let listed = match hosts_file {
+        Some(path) => std::fs::read_to_string(path).map_err(|e| {
+            CliError::ConfigError(format!(
+                "Failed to read hosts file {}: {}",
+                path.display(),
+                e
+            ))
+        })?,
+        None => String::new(),
+    };
+    let listed = listed
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        .map(str::to_string);
+
+    let mut resolved: Vec<String> = Vec::new();
+    for target in targets.iter().cloned().chain(listed) {
+        parse_ssh_target(&target)?;
+        if !resolved.contains(&target) {
+            resolved.push(target);
+        }
+    }
+    if resolved.is_empty() {
+        return Err(CliError::ConfigError(
+            "No target given (pass [user@]host[:port] or --hosts-file)".into(),
+        ));
+    }
+    Ok(resolved)
+}
+
+/// Run `task` for the targets concurrently, at most `--parallel-hosts` at
+/// a time (all by default). Each run gets a `host` span so interleaved log
+/// lines can be told apart. Results keep the target order.
+pub async fn on_each_host<T, F, Fut>(
+    cli: &Cli,
+    targets: &[String],
+    task: F,
+) -> Vec<(String, Result<T, CliError>)>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<T, CliError>>,
+{
+    let parallel = cli.parallel_hosts.map_or(targets.len(), usize::from).max(1);
+    let runs = targets.iter().map(|target| {
+        let run = task(target.clone()).instrument(tracing::info_span!("host", target = %target));
+        async move { (target.clone(), run.await) }
+    });
+    stream::iter(runs).buffered(parallel).collect().await
+}
+
+#[derive(Tabled)]
+struct HostRow {
+    #[tabled(rename = "Host")]
+    host: String,
+    #[tabled(rename = "Result")]
+    result: String,
+    #[tabled(rename = "Details")]
+    details: String,
+}
+
+#[derive(Serialize)]
+struct HostReport<'a, T> {
+    target: &'a str,
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<&'a T>,
+}
+
+#[derive(Serialize)]
+struct HostsReport<'a, T> {
+    success: bool,
+    hosts: Vec<HostReport<'a, T>>,
+}
+
+/// Report per-host results, as a summary table or, with `--output json`, as
+/// one JSON document on stdout. `judge` turns a result into a short detail,
+/// or the reason it counts as a failure. Fails if any host failed.
+pub fn report_hosts<T: Serialize>(
+    action: &str,
+    results: &[(String, Result<T, CliError>)],
+    judge: impl Fn(&T) -> Result<String, String>,
+    cli: &Cli,
+) -> Result<(), CliError> {
+    let outcomes: Vec<Result<String, String>> = results
+        .iter()
+        .map(|(_, result)| match result {
+            Ok(value) => judge(value),
+            Err(CliError::OperationFailed(message) | CliError::ConfigError(message)) => {
+                Err(message.clone())
+            }
+        })
+        .collect();
+
+    if cli.json() {
+        let hosts: Vec<HostReport<T>> = results
+            .iter()
+            .zip(&outcomes)
+            .map(|((target, result), outcome)| HostReport {
+                target,
+                success: outcome.is_ok(),
+                error: outcome.as_ref().err().map(String::as_str),
+                result: result.as_ref().ok(),
+            })
+            .collect();
+        print_json(&HostsReport {
+            success: outcomes.iter().all(Result::is_ok),
+            hosts,
+        })?;
+    } else if !cli.quiet {
+        let rows = results
+            .iter()
+            .zip(&outcomes)
+            .map(|((host, _), outcome)| match outcome {
+                Ok(details) => HostRow {
+                    host: host.clone(),
+                    result: "ok".green().to_string(),
+                    details: details.clone(),
+                },
+                Err(error) => HostRow {
+                    host: host.clone(),
+                    result: "failed".red().to_string(),
+                    details: error.clone(),
+                },
+            });
+        let mut table = Table::new(rows);
+        table
+            .with(Style::blank())
+            .with(Modify::new(Rows::first()).with(Color::FG_BLUE))
+            .with(
+                Modify::new(Rows::first())
+                    .with(tabled::settings::Format::content(|s| s.bold().to_string())),
+            );
+        println!("\n{}", ui::format_header(&format!("{} summary:", action)));
+        println!("{}", table);
+    }
+
+    let failed: Vec<&str> = results
+        .iter()
+        .zip(&outcomes)
+        .filter(|(_, outcome)| outcome.is_err())
+        .map(|((host, _), _)| host.as_str())
+        .collect();
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(CliError::OperationFailed(format!(
+            "{} failed on {} of {} host(s): {}",
+            action,
+            failed.len(),
+            results.len(),
+            failed.join(", ")
+        )))
+    }
+}
+
+/// Pretty-print `value` as JSON on stdout.
+pub fn print_json(value: &impl Serialize) -> Result<(), CliError> {
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|e| CliError::OperationFailed(format!("Failed to serialize result: {}", e)))?;
+    println!("{}", json);
+    Ok(())
+}
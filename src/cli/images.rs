@@ -0,0 +1,129 @@
+use super::common::{connect_ssh, parse_ssh_target, remote_only_config};
+use super::error::CliError;
+use super::hosts::print_json;
+use super::parser::Cli;
+use super::ui;
+use crate::deployer::images::ProjectImage;
+use crate::deployer::Deployer;
+use clap::Args;
+use colored::*;
+use tabled::{
+    settings::{object::Rows, Color, Modify, Style},
+    Table, Tabled,
+};
+use tracing::{debug, instrument};
+
+#[derive(Debug, Args)]
+pub struct Images {
+    /// Remote target in the format [user@]host[:port]
+    #[arg(required = true)]
+    target: String,
+}
+
+#[derive(Tabled)]
+struct ImageRow {
+    #[tabled(rename = "Repository")]
+    repository: String,
+    #[tabled(rename = "Tag")]
+    tag: String,
+    #[tabled(rename = "Image ID")]
+    id: String,
+    #[tabled(rename = "Digest")]
+    digest: String,
+    #[tabled(rename = "Size")]
+    size: String,
+    #[tabled(rename = "Created")]
+    created: String,
+    #[tabled(rename = "In use")]
+    in_use: String,
+}
+
+impl Images {
+    #[instrument(name = "images", skip(self, cli_args), fields(target = %self.target))]
+    pub async fn run(&self, cli_args: &Cli) -> Result<(), CliError> {
+        let target = parse_ssh_target(&self.target)?;
+        debug!(user = %target.user, port = %target.port, key = ?cli_args.identity_file, "SSH details");
+
+        let pb = ui::create_spinner(&format!(
+            "Connecting to {}...",
+            ui::format_highlight(&target.host)
+        ));
+        let mut executor = connect_ssh(cli_args, &target).await.map_err(|e| {
+            pb.finish_and_clear();
+            CliError::OperationFailed(format!("SSH connection failed: {}", e))
+        })?;
+        pb.set_message("Listing images...");
+
+        // Works from the files on the server, so no local analysis is needed
+        let mut deployer = Deployer::new(remote_only_config(cli_args)?, &mut executor, None);
+        let images = async {
+            deployer.use_remote_project().await?;
+            deployer.list_images().await
+        }
+        .await
+        .map_err(|e| {
+            pb.finish_and_clear();
+            CliError::OperationFailed(format!("Failed to list images: {}", e))
+        })?;
+        pb.finish_and_clear();
+
+        if cli_args.json() {
+            print_json(&images)?;
+        } else if !cli_args.quiet {
+            println!(
+                "\n{}",
+                ui::format_header(&format!("Project images on {}:", self.target))
+            );
+            print_images(&images);
+        }
+        Ok(())
+    }
+}
+
+fn print_images(images: &[ProjectImage]) {
+    if images.is_empty() {
+        println!("  {}", ui::format_warning("(None)"));
+        return;
+    }
+    let rows = images.iter().map(|image| ImageRow {
+        repository: image.repository.clone(),
+        tag: image.tag.clone(),
+        id: short_id(&image.id),
+        digest: short_id(&image.digest),
+        size: image.size.clone(),
+        created: image.created.clone(),
+        in_use: if image.dangling {
+            "dangling".yellow().to_string()
+        } else if image.in_use {
+            image.containers.join(", ")
+        } else {
+            "no".to_string()
+        },
+    });
+    let mut table = Table::new(rows);
+    table
+        .with(Style::blank())
+        .with(Modify::new(Rows::first()).with(Color::FG_BLUE))
+        .with(
+            Modify::new(Rows::first())
+                .with(tabled::settings::Format::content(|s| s.bold().to_string())),
+        );
+    println!("{}", table);
+
+    let dangling = images.iter().filter(|image| image.dangling).count();
+    if dangling > 0 {
+        println!(
+            "\n{}",
+            ui::format_warning(&format!(
+                "{} dangling image(s) will be removed by the image prune of the next `up`",
+                dangling
+            ))
+        );
+    }
+}
+
+/// First 12 hex digits of an id or digest, as `docker image ls` shows them.
+fn short_id(id: &str) -> String {
+    let hex = id.strip_prefix("sha256:").unwrap_or(id);
+    hex.chars().take(12).collect()
+}
@@ -0,0 +1,112 @@
+use super::common::{connect_ssh, parse_ssh_target, remote_only_config};
+use super::error::CliError;
+use super::hosts::print_json;
+use super::parser::Cli;
+use super::ui;
+use crate::deployer::registry::DeployedProject;
+use crate::deployer::Deployer;
+use clap::Args;
+use colored::*;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tabled::{
+    settings::{object::Rows, Color, Modify, Style},
+    Table, Tabled,
+};
+use tracing::{debug, instrument};
+
+#[derive(Debug, Args)]
+pub struct List {
+    /// Remote target in the format [user@]host[:port]
+    #[arg(required = true)]
+    target: String,
+}
+
+#[derive(Tabled)]
+struct DeploymentRow {
+    #[tabled(rename = "Project")]
+    project: String,
+    #[tabled(rename = "Directory")]
+    directory: String,
+    #[tabled(rename = "Compose files")]
+    compose_files: String,
+    #[tabled(rename = "Containers")]
+    containers: String,
+    #[tabled(rename = "Last deploy")]
+    last_deploy: String,
+}
+
+impl List {
+    #[instrument(name = "list", skip(self, cli_args), fields(target = %self.target))]
+    pub async fn run(&self, cli_args: &Cli) -> Result<(), CliError> {
+        let target = parse_ssh_target(&self.target)?;
+        debug!(user = %target.user, port = %target.port, key = ?cli_args.identity_file, "SSH details");
+
+        let pb = ui::create_spinner(&format!(
+            "Connecting to {}...",
+            ui::format_highlight(&target.host)
+        ));
+        let mut executor = connect_ssh(cli_args, &target).await.map_err(|e| {
+            pb.finish_and_clear();
+            CliError::OperationFailed(format!("SSH connection failed: {}", e))
+        })?;
+        pb.set_message("Looking for deployments...");
+
+        let mut deployer = Deployer::new(remote_only_config(cli_args)?, &mut executor, None);
+        let deployments = deployer.list_deployments().await.map_err(|e| {
+            pb.finish_and_clear();
+            CliError::OperationFailed(format!("Failed to list deployments: {}", e))
+        })?;
+        pb.finish_and_clear();
+
+        if cli_args.json() {
+            print_json(&deployments)?;
+        } else if !cli_args.quiet {
+            println!(
+                "\n{}",
+                ui::format_header(&format!("dcd deployments on {}:", self.target))
+            );
+            print_deployments(&deployments);
+        }
+        Ok(())
+    }
+}
+
+fn print_deployments(deployments: &[DeployedProject]) {
+    if deployments.is_empty() {
+        println!("  {}", ui::format_warning("(None)"));
+        return;
+    }
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let rows = deployments.iter().map(|deployment| DeploymentRow {
+        project: deployment.project.clone(),
+        directory: deployment.remote_dir.display().to_string(),
+        compose_files: deployment
+            .compose_files
+            .iter()
+            .map(|file| file.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+        containers: if deployment.containers == 0 {
+            "none".yellow().to_string()
+        } else {
+            deployment.containers.to_string()
+        },
+        last_deploy: match (&deployment.last_deploy, deployment.pending) {
+            (_, true) => "in progress or interrupted".yellow().to_string(),
+            (Some(record), false) => record.summary(now),
+            (None, false) => "unknown".to_string(),
+        },
+    });
+    let mut table = Table::new(rows);
+    table
+        .with(Style::blank())
+        .with(Modify::new(Rows::first()).with(Color::FG_BLUE))
+        .with(
+            Modify::new(Rows::first())
+                .with(tabled::settings::Format::content(|s| s.bold().to_string())),
+        );
+    println!("{}", table);
+}
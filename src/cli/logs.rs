@@ -0,0 +1,55 @@
+use super::common::{connect_ssh, parse_ssh_target, remote_only_config};
+use super::error::CliError;
+use super::parser::Cli;
+use crate::deployer::Deployer;
+use clap::Args;
+use tracing::{debug, instrument};
+
+#[derive(Debug, Args)]
+pub struct Logs {
+    /// Remote target in the format [user@]host[:port]
+    #[arg(required = true)]
+    target: String,
+
+    /// Services to show logs for (defaults to all)
+    services: Vec<String>,
+
+    /// Keep streaming new log output
+    #[arg(short, long)]
+    follow: bool,
+
+    /// Number of lines to show from the end of each service's logs
+    #[arg(short = 'n', long, value_name = "N")]
+    tail: Option<u32>,
+}
+
+impl Logs {
+    #[instrument(name = "logs", skip(self, cli_args), fields(target = %self.target))]
+    pub async fn run(&self, cli_args: &Cli) -> Result<(), CliError> {
+        let target = parse_ssh_target(&self.target)?;
+        debug!(user = %target.user, port = %target.port, key = ?cli_args.identity_file, "SSH details");
+
+        let mut executor = connect_ssh(cli_args, &target)
+            .await
+            .map_err(|e| CliError::OperationFailed(format!("SSH connection failed: {}", e)))?;
+
+        // Works from the files on the server, so no local analysis is needed
+        let mut deployer = Deployer::new(remote_only_config(cli_args)?, &mut executor, None);
+        deployer
+            .use_remote_project()
+            .await
+            .map_err(|e| CliError::OperationFailed(e.to_string()))?;
+        let exit_code = deployer
+            .service_logs(&self.services, self.follow, self.tail)
+            .await
+            .map_err(|e| CliError::OperationFailed(e.to_string()))?;
+
+        if exit_code != 0 {
+            return Err(CliError::OperationFailed(format!(
+                "docker compose logs exited with status {}",
+                exit_code
+            )));
+        }
+        Ok(())
+    }
+}
@@ -1,28 +1,85 @@
 mod analyze;
+mod apply;
+mod cleanup;
 mod common;
 mod destroy;
+mod diff;
+mod doctor;
 mod error;
+mod exec;
+mod freeze;
+mod gc;
+mod health;
+mod hosts;
+mod images;
+mod list;
+mod logs;
+mod network;
 pub mod parser;
+mod plan;
+mod ps;
+mod pull;
+mod reboot;
+mod restart;
+mod rollback;
+mod run;
+mod session;
 mod status;
+mod stop;
+mod support_bundle;
+mod sync_only;
 mod ui;
 mod up;
 
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches};
 use error::CliError;
 use parser::Cli;
 
 // Helper function to parse args
 pub fn parse_args() -> Cli {
-    Cli::parse()
+    let command = parser::with_project_defaults(Cli::command(), &common::local_project_config());
+    Cli::from_arg_matches(&command.get_matches()).unwrap_or_else(|e| e.exit())
 }
 
 // Main CLI execution function, receives parsed args
 pub async fn run(cli: Cli) -> Result<(), CliError> {
+    // Spinners would end up between the JSON and the logs
+    ui::set_quiet(cli.quiet || cli.json());
+    if cli.read_only && !cli.command.is_read_only() {
+        return Err(CliError::ConfigError(format!(
+            "`{}` changes the remote host and is not available with --read-only",
+            cli.command.name()
+        )));
+    }
+    common::apply_variable_overrides(&cli)?;
     // Match the command and call its specific run method
     match &cli.command {
         parser::Commands::Analyze(cmd) => cmd.run(&cli).await,
         parser::Commands::Up(cmd) => cmd.run(&cli).await,
+        parser::Commands::Plan(cmd) => cmd.run(&cli).await,
+        parser::Commands::Apply(cmd) => cmd.run(&cli).await,
+        parser::Commands::Diff(cmd) => cmd.run(&cli).await,
         parser::Commands::Status(cmd) => cmd.run(&cli).await,
+        parser::Commands::Ps(cmd) => cmd.run(&cli).await,
+        parser::Commands::Health(cmd) => cmd.run(&cli).await,
+        parser::Commands::Logs(cmd) => cmd.run(&cli).await,
+        parser::Commands::Images(cmd) => cmd.run(&cli).await,
+        parser::Commands::List(cmd) => cmd.run(&cli).await,
+        parser::Commands::Network(cmd) => cmd.run(&cli).await,
         parser::Commands::Destroy(cmd) => cmd.run(&cli).await,
+        parser::Commands::SyncOnly(cmd) => cmd.run(&cli).await,
+        parser::Commands::Pull(cmd) => cmd.run(&cli).await,
+        parser::Commands::SupportBundle(cmd) => cmd.run(&cli).await,
+        parser::Commands::Cleanup(cmd) => cmd.run(&cli).await,
+        parser::Commands::Rollback(cmd) => cmd.run(&cli).await,
+        parser::Commands::Gc(cmd) => cmd.run(&cli).await,
+        parser::Commands::Doctor(cmd) => cmd.run(&cli).await,
+        parser::Commands::Freeze(cmd) => cmd.run(&cli).await,
+        parser::Commands::Restart(cmd) => cmd.run(&cli).await,
+        parser::Commands::Stop(cmd) => cmd.run(&cli).await,
+        parser::Commands::Reboot(cmd) => cmd.run(&cli).await,
+        parser::Commands::Run(cmd) => cmd.run(&cli).await,
+        parser::Commands::Exec(cmd) => cmd.run(&cli).await,
+        parser::Commands::Session(cmd) => cmd.run(&cli).await,
     }
 }
@@ -0,0 +1,203 @@
+use super::common::{connect_ssh, parse_ssh_target, remote_only_config};
+use super::error::CliError;
+use super::hosts::print_json;
+use super::parser::Cli;
+use super::ui;
+use crate::deployer::networks::ProjectNetwork;
+use crate::deployer::Deployer;
+use clap::{Args, Subcommand};
+use colored::*;
+use tabled::{
+    settings::{object::Rows, Color, Modify, Style},
+    Table, Tabled,
+};
+use tracing::{debug, instrument};
+
+#[derive(Debug, Args)]
+pub struct Network {
+    #[command(subcommand)]
+    action: NetworkAction,
+}
+
+#[derive(Debug, Subcommand)]
+enum NetworkAction {
+    /// List the project's networks with their subnets and subnet conflicts
+    Ls {
+        /// Remote target in the format [user@]host[:port]
+        target: String,
+    },
+
+    /// Show subnets, gateways and attached containers of the project's networks
+    Inspect {
+        /// Remote target in the format [user@]host[:port]
+        target: String,
+
+        /// Network to show (defaults to all of the project's networks)
+        network: Option<String>,
+    },
+}
+
+#[derive(Tabled)]
+struct NetworkRow {
+    #[tabled(rename = "Network")]
+    name: String,
+    #[tabled(rename = "Driver")]
+    driver: String,
+    #[tabled(rename = "Subnets")]
+    subnets: String,
+    #[tabled(rename = "Containers")]
+    containers: usize,
+    #[tabled(rename = "Conflicts")]
+    conflicts: String,
+}
+
+impl Network {
+    fn target(&self) -> &str {
+        match &self.action {
+            NetworkAction::Ls { target } | NetworkAction::Inspect { target, .. } => target,
+        }
+    }
+
+    #[instrument(name = "network", skip(self, cli_args), fields(target = %self.target()))]
+    pub async fn run(&self, cli_args: &Cli) -> Result<(), CliError> {
+        let target = parse_ssh_target(self.target())?;
+        debug!(user = %target.user, port = %target.port, key = ?cli_args.identity_file, "SSH details");
+
+        let pb = ui::create_spinner(&format!(
+            "Connecting to {}...",
+            ui::format_highlight(&target.host)
+        ));
+        let mut executor = connect_ssh(cli_args, &target).await.map_err(|e| {
+            pb.finish_and_clear();
+            CliError::OperationFailed(format!("SSH connection failed: {}", e))
+        })?;
+        pb.set_message("Inspecting networks...");
+
+        // The project name comes from the remote directory alone
+        let mut deployer = Deployer::new(remote_only_config(cli_args)?, &mut executor, None);
+        let networks = deployer.list_networks().await.map_err(|e| {
+            pb.finish_and_clear();
+            CliError::OperationFailed(format!("Failed to inspect networks: {}", e))
+        })?;
+        pb.finish_and_clear();
+
+        let networks = match &self.action {
+            NetworkAction::Inspect {
+                network: Some(name),
+                ..
+            } => {
+                let found: Vec<ProjectNetwork> = networks
+                    .iter()
+                    .filter(|network| network.name == *name || network.id.starts_with(name))
+                    .cloned()
+                    .collect();
+                if found.is_empty() {
+                    let known: Vec<&str> = networks.iter().map(|n| n.name.as_str()).collect();
+                    return Err(CliError::OperationFailed(format!(
+                        "'{}' is not a network of the project (found: {})",
+                        name,
+                        if known.is_empty() {
+                            "none".to_string()
+                        } else {
+                            known.join(", ")
+                        }
+                    )));
+                }
+                found
+            }
+            _ => networks,
+        };
+
+        if cli_args.json() {
+            print_json(&networks)?;
+        } else if !cli_args.quiet {
+            println!(
+                "\n{}",
+                ui::format_header(&format!("Project networks on {}:", self.target()))
+            );
+            match self.action {
+                NetworkAction::Ls { .. } => print_networks(&networks),
+                NetworkAction::Inspect { .. } => networks.iter().for_each(print_network),
+            }
+            print_conflicts(&networks);
+        }
+        Ok(())
+    }
+}
+
+fn print_networks(networks: &[ProjectNetwork]) {
+    if networks.is_empty() {
+        println!("  {}", ui::format_warning("(None)"));
+        return;
+    }
+    let rows = networks.iter().map(|network| NetworkRow {
+        name: display_name(network),
+        driver: network.driver.clone(),
+        subnets: network
+            .subnets
+            .iter()
+            .map(|subnet| subnet.subnet.as_str())
+            .collect::<Vec<_>>()
+            .join(", "),
+        containers: network.containers.len(),
+        conflicts: if network.conflicts.is_empty() {
+            "none".to_string()
+        } else {
+            network.conflicts.len().to_string().yellow().to_string()
+        },
+    });
+    let mut table = Table::new(rows);
+    table
+        .with(Style::blank())
+        .with(Modify::new(Rows::first()).with(Color::FG_BLUE))
+        .with(
+            Modify::new(Rows::first())
+                .with(tabled::settings::Format::content(|s| s.bold().to_string())),
+        );
+    println!("{}", table);
+}
+
+fn print_network(network: &ProjectNetwork) {
+    println!(
+        "\n{} ({})",
+        ui::format_highlight(&display_name(network)),
+        network.driver
+    );
+    println!("  ID: {}", &network.id[..network.id.len().min(12)]);
+    for subnet in &network.subnets {
+        match &subnet.gateway {
+            Some(gateway) => println!("  Subnet: {} (gateway {})", subnet.subnet, gateway),
+            None => println!("  Subnet: {}", subnet.subnet),
+        }
+    }
+    if network.containers.is_empty() {
+        println!("  Containers: none");
+    } else {
+        println!("  Containers:");
+        for container in &network.containers {
+            println!("    {:<30} {}", container.name, container.ipv4_address);
+        }
+    }
+}
+
+fn print_conflicts(networks: &[ProjectNetwork]) {
+    for network in networks {
+        for conflict in &network.conflicts {
+            println!(
+                "{}",
+                ui::format_warning(&format!(
+                    "⚠️  {} of {} overlaps {} ({}); traffic to it may not reach the containers",
+                    conflict.subnet, network.name, conflict.other_subnet, conflict.with
+                ))
+            );
+        }
+    }
+}
+
+fn display_name(network: &ProjectNetwork) -> String {
+    if network.managed {
+        network.name.clone()
+    } else {
+        format!("{} (external)", network.name)
+    }
+}
@@ -1,5 +1,12 @@
-use super::{analyze, destroy, status, up};
-use clap::{ArgAction, Parser, Subcommand};
+use super::{
+    analyze, apply, cleanup, destroy, diff, doctor, exec, freeze, gc, health, images, list, logs,
+    network, plan, ps, pull, reboot, restart, rollback, run, session, status, stop, support_bundle,
+    sync_only, up,
+};
+use crate::config::ProjectConfig;
+use crate::executor::AuthMethod;
+use clap::builder::{ArgPredicate, Resettable};
+use clap::{ArgAction, Command, Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 const VERSION_INFO: &str = env!("DCD_BUILD_VERSION");
@@ -17,10 +24,29 @@ pub struct Cli {
     #[arg(short = 'e', long = "env-file")]
     pub env_files: Vec<PathBuf>,
 
+    /// Set a variable for this run (VAR=value; VAR=@path reads the value from a file, @@ escapes a literal @)
+    #[arg(long = "set", value_name = "VAR=VALUE", value_parser = parse_assignment)]
+    pub set_vars: Vec<(String, String)>,
+
+    /// Read the value of a variable from stdin
+    #[arg(long = "set-stdin", value_name = "VAR")]
+    pub set_stdin: Option<String>,
+
     /// SSH private key path (auto-detects ~/.ssh/id_rsa or ~/.ssh/id_ed25519 if not specified)
     #[arg(short = 'i', long = "identity")]
     pub identity_file: Option<PathBuf>,
 
+    /// SSH authentication methods to try, in order (comma-separated: key, agent, password;
+    /// default: key,agent). Passwords come from DCD_SSH_PASSWORD or a prompt
+    #[arg(
+        long,
+        global = true,
+        env = "DCD_SSH_AUTH",
+        value_delimiter = ',',
+        value_name = "METHODS"
+    )]
+    pub auth: Vec<AuthMethod>,
+
     /// Remote working directory
     #[arg(short = 'w', long = "workdir")]
     pub remote_dir: Option<PathBuf>,
@@ -28,25 +54,352 @@ pub struct Cli {
     /// Increase message verbosity (-v for debug, -vv for trace)
     #[arg(short, long, action = ArgAction::Count, global = true)]
     pub verbose: u8,
+    /// Print only a single result line; suppress logs, progress and reports
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+    /// Format of command results on stdout; `json` is meant for scripts and CI
+    /// (analyze, status, up, destroy, images, list and network)
+    // Not global: `freeze` has its own `-o/--output`
+    #[arg(
+        long,
+        env = "DCD_OUTPUT",
+        value_enum,
+        value_name = "FORMAT",
+        default_value_t = OutputFormat::Text
+    )]
+    pub output: OutputFormat,
     /// Disable host-key warnings (unknown-host warning)
     #[arg(long, global = true)]
     pub no_warnings: bool,
 
+    /// known_hosts file to verify host keys against (defaults to ~/.ssh/known_hosts)
+    #[arg(long, global = true, env = "DCD_KNOWN_HOSTS", value_name = "PATH")]
+    pub known_hosts: Option<PathBuf>,
+
+    /// Refuse to connect to hosts whose key is not in known_hosts
+    #[arg(
+        long,
+        global = true,
+        env = "DCD_STRICT_HOST_KEY_CHECKING",
+        conflicts_with = "accept_new"
+    )]
+    pub strict_host_key_checking: bool,
+
+    /// Add the key of hosts not in known_hosts to it and connect (like ssh's
+    /// StrictHostKeyChecking=accept-new); changed keys are still refused
+    #[arg(long, global = true, env = "DCD_ACCEPT_NEW")]
+    pub accept_new: bool,
+
+    /// Bastion host to tunnel SSH connections through, like ssh's ProxyJump
+    #[arg(
+        long,
+        global = true,
+        env = "DCD_JUMP",
+        value_name = "[USER@]HOST[:PORT]"
+    )]
+    pub jump: Option<String>,
+
+    /// Only allow commands that inspect the deployment (status, ps, logs, images, list, network, health) and
+    /// skip host checks, so low-privilege monitoring accounts can use dcd
+    #[arg(long, global = true, env = "DCD_READ_ONLY")]
+    pub read_only: bool,
+
+    /// Keep the SSH connection open in the background and reuse it from later
+    /// invocations until it has been idle for SECS seconds (default 60)
+    #[arg(
+        long,
+        global = true,
+        env = "DCD_REUSE_CONNECTION",
+        value_name = "SECS",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "60"
+    )]
+    pub reuse_connection: Option<u64>,
+
+    /// Append every remote command, its output and each upload to PATH (JSON lines),
+    /// e.g. to attach to a bug report; values of secrets and uploaded files are left out
+    #[arg(long, global = true, env = "DCD_RECORD_SESSION", value_name = "PATH")]
+    pub record_session: Option<PathBuf>,
+
+    /// Print each remote command as it runs, with its exit code and duration,
+    /// whatever the log level; values of secrets are masked
+    #[arg(long, global = true, env = "DCD_SHOW_COMMANDS")]
+    pub show_commands: bool,
+
+    /// SSH channels a direct connection budgets for uploads: SFTP upload
+    /// channels are capped at one less, leaving one for commands (default 5).
+    /// Lower it for small hosts or a low sshd MaxSessions
+    #[arg(
+        long,
+        global = true,
+        env = "DCD_SSH_CHANNELS",
+        value_name = "N",
+        value_parser = clap::value_parser!(u16).range(2..)
+    )]
+    pub ssh_channels: Option<u16>,
+
+    /// Files uploaded over SFTP at once when syncing a directory (default 8,
+    /// or `sync_concurrency` in dcd.toml)
+    #[arg(
+        long,
+        global = true,
+        env = "DCD_SFTP_STREAMS",
+        value_name = "N",
+        value_parser = clap::value_parser!(u16).range(1..)
+    )]
+    pub sftp_streams: Option<u16>,
+
+    /// Hosts worked on at once with several targets or --hosts-file (default all)
+    #[arg(
+        long,
+        global = true,
+        env = "DCD_PARALLEL_HOSTS",
+        value_name = "N",
+        value_parser = clap::value_parser!(u16).range(1..)
+    )]
+    pub parallel_hosts: Option<u16>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
 
 #[derive(Subcommand, Debug)]
 pub enum Commands {
-    /// Analyze docker-compose configuration without deploying
+    /// Analyze docker-compose configuration without deploying, and optionally the target host
     Analyze(analyze::Analyze),
 
     /// Deploy or update services
     Up(up::Up),
 
+    /// Record what `up` would deploy to a plan file for review
+    Plan(plan::Plan),
+
+    /// Deploy a plan file, refusing if the local project changed since it was written
+    Apply(apply::Apply),
+
+    /// Show what `up` would change on the server: files to upload and diffs of the
+    /// compose files, env and rendered config (secrets masked)
+    Diff(diff::Diff),
+
     /// Show service status
     Status(status::Status),
 
+    /// List the project's containers with image, state, health, ports and uptime
+    Ps(ps::Ps),
+
+    /// Check service health once and exit non-zero if anything is unhealthy
+    Health(health::Health),
+
+    /// Show (and optionally follow) the logs of the deployed services
+    Logs(logs::Logs),
+
+    /// List the project's images on the host and the dangling ones the next prune removes
+    Images(images::Images),
+
+    /// List the dcd deployments on a host with their last deployment
+    List(list::List),
+
+    /// Show the project's networks and subnets that clash with the host's (ls, inspect)
+    Network(network::Network),
+
     /// Destroy deployment completely
     Destroy(destroy::Destroy),
+
+    /// Sync compose, env and referenced files without restarting services
+    SyncOnly(sync_only::SyncOnly),
+
+    /// Download files from the server into the local project (certificates, databases, .env.dcd)
+    Pull(pull::Pull),
+
+    /// Pack compose config, ps, logs, docker info, disk usage and dcd state from the server into a tarball (secrets masked)
+    SupportBundle(support_bundle::SupportBundle),
+
+    /// Roll back an interrupted deployment (restore backups, remove leftovers)
+    Cleanup(cleanup::Cleanup),
+
+    /// Return to the previous deployment and recreate its services
+    Rollback(rollback::Rollback),
+
+    /// Remove leftover backups and expired rollback copies from the server
+    Gc(gc::Gc),
+
+    /// Run sanity checks against the remote host
+    Doctor(doctor::Doctor),
+
+    /// Write the resolved, digest-pinned compose file and env snapshot for plain docker compose
+    Freeze(freeze::Freeze),
+
+    /// Run a one-off command in a service container (docker compose run --rm)
+    Run(run::Run),
+
+    /// Run a command in a running service container (docker compose exec)
+    Exec(exec::Exec),
+
+    /// Restart the deployed services, or some of them, and wait until they are healthy
+    Restart(restart::Restart),
+
+    /// Stop the deployed services, or some of them, keeping their containers
+    Stop(stop::Stop),
+
+    /// Stop services, reboot the host and optionally wait for it to come back
+    Reboot(reboot::Reboot),
+
+    /// Serve a shared SSH connection for --reuse-connection
+    #[command(hide = true)]
+    Session(session::Session),
+}
+
+/// Use the values of the project's dcd.toml / .dcd.yaml as defaults of the
+/// matching options, so anything given on the command line still wins.
+pub fn with_project_defaults(mut command: Command, config: &ProjectConfig) -> Command {
+    if !config.compose_files.is_empty() {
+        let files = config.compose_files.iter().map(|f| f.display().to_string());
+        command = command.mut_arg("compose_files", |arg| arg.default_values(files));
+    }
+    if !config.env_files.is_empty() {
+        let files = config.env_files.iter().map(|f| f.display().to_string());
+        command = command.mut_arg("env_files", |arg| arg.default_values(files));
+    }
+    if let Some(identity) = &config.identity {
+        let identity = identity.display().to_string();
+        command = command.mut_arg("identity_file", |arg| arg.default_value(identity));
+    }
+    if let Some(workdir) = &config.workdir {
+        let workdir = workdir.display().to_string();
+        command = command.mut_arg("remote_dir", |arg| arg.default_value(workdir));
+    }
+    if let Some(channels) = config.ssh_channels {
+        command = command.mut_arg("ssh_channels", |arg| {
+            arg.default_value(channels.to_string())
+        });
+    }
+    if let Some(hosts) = config.parallel_hosts {
+        command = command.mut_arg("parallel_hosts", |arg| arg.default_value(hosts.to_string()));
+    }
+    match &config.target {
+        Some(target) => with_default_target(command, target),
+        None => command,
+    }
+}
+
+// Only where the target is the sole positional argument, so that it never
+// takes the place of a service name (`run`, `exec`, `logs`)
+fn with_default_target(mut command: Command, target: &str) -> Command {
+    let names: Vec<String> = command
+        .get_subcommands()
+        .map(|sub| sub.get_name().to_string())
+        .collect();
+    for name in names {
+        command = command.mut_subcommand(name, |sub| {
+            let positionals: Vec<String> = sub
+                .get_positionals()
+                .map(|arg| arg.get_id().to_string())
+                .collect();
+            let has_hosts_file = sub.get_arguments().any(|arg| arg.get_id() == "hosts_file");
+            let sub = with_default_target(sub, target);
+            match positionals.as_slice() {
+                [id] if id == "target" || id == "targets" => sub.mut_arg(id, |arg| {
+                    let arg = arg
+                        .required(false)
+                        .required_unless_present(Resettable::Reset)
+                        .default_value(target.to_string());
+                    // An explicit host list replaces the default host
+                    if has_hosts_file {
+                        arg.default_value_if("hosts_file", ArgPredicate::IsPresent, None)
+                    } else {
+                        arg
+                    }
+                }),
+                _ => sub,
+            }
+        });
+    }
+    command
+}
+
+fn parse_assignment(arg: &str) -> Result<(String, String), String> {
+    let (name, value) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected VAR=VALUE, got '{}'", arg))?;
+    if name.is_empty() {
+        return Err(format!("missing variable name in '{}'", arg));
+    }
+    Ok((name.to_string(), value.to_string()))
+}
+
+impl Cli {
+    /// Whether results go to stdout as JSON instead of human-readable text.
+    pub fn json(&self) -> bool {
+        self.output == OutputFormat::Json
+    }
+}
+
+/// How command results are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+impl Commands {
+    /// Subcommand name as typed on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Analyze(_) => "analyze",
+            Self::Up(_) => "up",
+            Self::Plan(_) => "plan",
+            Self::Apply(_) => "apply",
+            Self::Diff(_) => "diff",
+            Self::Status(_) => "status",
+            Self::Ps(_) => "ps",
+            Self::Health(_) => "health",
+            Self::Logs(_) => "logs",
+            Self::Images(_) => "images",
+            Self::List(_) => "list",
+            Self::Network(_) => "network",
+            Self::Destroy(_) => "destroy",
+            Self::SyncOnly(_) => "sync-only",
+            Self::Pull(_) => "pull",
+            Self::SupportBundle(_) => "support-bundle",
+            Self::Cleanup(_) => "cleanup",
+            Self::Rollback(_) => "rollback",
+            Self::Gc(_) => "gc",
+            Self::Doctor(_) => "doctor",
+            Self::Freeze(_) => "freeze",
+            Self::Run(_) => "run",
+            Self::Exec(_) => "exec",
+            Self::Restart(_) => "restart",
+            Self::Stop(_) => "stop",
+            Self::Reboot(_) => "reboot",
+            Self::Session(_) => "session",
+        }
+    }
+
+    /// Whether the command leaves the remote host untouched (no uploads,
+    /// installs or container changes), as required by `--read-only`.
+    pub fn is_read_only(&self) -> bool {
+        match self {
+            Self::Up(up) | Self::Apply(apply::Apply { up, .. }) => up.dry_run,
+            Self::Destroy(destroy) => destroy.dry_run,
+            _ => matches!(
+                self,
+                Self::Analyze(_)
+                    | Self::Plan(_)
+                    | Self::Diff(_)
+                    | Self::Status(_)
+                    | Self::Ps(_)
+                    | Self::Health(_)
+                    | Self::Logs(_)
+                    | Self::Images(_)
+                    | Self::List(_)
+                    | Self::Network(_)
+                    | Self::Freeze(_)
+                    | Self::Pull(_)
+                    | Self::SupportBundle(_)
+                    // Only runs what read-only clients send it
+                    | Self::Session(_)
+            ),
+        }
+    }
 }
@@ -0,0 +1,105 @@
+use super::common::composer_for;
+use super::error::CliError;
+use super::hosts::print_json;
+use super::parser::Cli;
+use super::ui;
+use crate::composer::types::ComposerOutput;
+use crate::deployer::plan::DeployPlan;
+use clap::Args;
+use std::path::PathBuf;
+use tracing::{info, instrument};
+
+#[derive(Debug, Args)]
+pub struct Plan {
+    /// File to write the plan to
+    #[arg(short, long, default_value = "dcd-plan.json", value_name = "FILE")]
+    output: PathBuf,
+
+    /// Record hidden files of referenced directories too, as `up
+    /// --include-hidden` syncs them
+    #[arg(long)]
+    include_hidden: bool,
+}
+
+impl Plan {
+    #[instrument(name = "plan", skip(self, cli_args), fields(output = %self.output.display()))]
+    pub async fn run(&self, cli_args: &Cli) -> Result<(), CliError> {
+        let (_, plan) = current_plan(
+            cli_args.compose_files.clone(),
+            cli_args.env_files.clone(),
+            self.include_hidden,
+        )
+        .await?;
+
+        let content = serde_json::to_string_pretty(&plan)
+            .map_err(|e| CliError::OperationFailed(format!("Failed to serialize plan: {}", e)))?;
+        std::fs::write(&self.output, content + "\n").map_err(|e| {
+            CliError::OperationFailed(format!("Failed to write {}: {}", self.output.display(), e))
+        })?;
+
+        if cli_args.json() {
+            print_json(&plan)?;
+        } else if !cli_args.quiet {
+            print_plan(&plan);
+        }
+        info!(
+            "{}",
+            ui::format_success(&format!(
+                "Plan written to {}; deploy it with `dcd apply {} <target>`",
+                self.output.display(),
+                self.output.display()
+            ))
+        );
+        Ok(())
+    }
+}
+
+/// Analyse the local project with the given files and record what deploying
+/// it would consist of.
+pub(super) async fn current_plan(
+    compose_files: Vec<PathBuf>,
+    env_files: Vec<PathBuf>,
+    include_hidden: bool,
+) -> Result<(ComposerOutput, DeployPlan), CliError> {
+    let pb = ui::create_spinner("Performing local analysis...");
+    let result = async {
+        let mut composer = composer_for(compose_files.clone(), env_files.clone()).await?;
+        let analysis = composer.analyze().await?;
+        let config = composer.resolved_config(false).await?;
+        Ok::<_, crate::composer::errors::ComposerError>((analysis, config))
+    }
+    .await;
+    pb.finish_and_clear();
+    let (analysis, config) =
+        result.map_err(|e| CliError::OperationFailed(format!("Local analysis failed: {}", e)))?;
+
+    let plan = DeployPlan::build(&analysis, &config, compose_files, env_files, include_hidden)
+        .await
+        .map_err(|e| CliError::OperationFailed(format!("Failed to build plan: {}", e)))?;
+    Ok((analysis, plan))
+}
+
+fn print_plan(plan: &DeployPlan) {
+    println!("\n{}", ui::format_header("Services:"));
+    for (service, image) in &plan.services {
+        println!("  {:<24} {}", ui::format_highlight(service), image);
+    }
+    println!("\n{}", ui::format_header("Published ports:"));
+    if plan.ports.is_empty() {
+        println!("  {}", ui::format_warning("(None)"));
+    }
+    for port in &plan.ports {
+        println!("  {}", port);
+    }
+    if !plan.profiles.is_empty() {
+        println!(
+            "\n{} {}",
+            ui::format_header("Profiles:"),
+            plan.profiles.join(", ")
+        );
+    }
+    println!("\n{}", ui::format_header("Files to sync:"));
+    for path in plan.files.keys() {
+        println!("  {}", path.display());
+    }
+}
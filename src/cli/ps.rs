@@ -0,0 +1,167 @@
+use super::common::{connect_ssh, parse_ssh_target, remote_only_config};
+use super::error::CliError;
+use super::hosts::print_json;
+use super::parser::Cli;
+use super::ui;
+use crate::deployer::docker_manager::ServiceStatus;
+use crate::deployer::Deployer;
+use clap::Args;
+use colored::*;
+use serde::Serialize;
+use tabled::{
+    settings::{object::Rows, Color, Modify, Style},
+    Table, Tabled,
+};
+use tracing::{debug, instrument};
+
+#[derive(Debug, Args)]
+pub struct Ps {
+    /// Remote target in the format [user@]host[:port]
+    #[arg(required = true)]
+    target: String,
+}
+
+/// One container of the project, as printed by `ps`.
+#[derive(Debug, Serialize)]
+struct ServiceInfo {
+    service: String,
+    container: String,
+    image: String,
+    state: String,
+    /// Empty when the service has no health check
+    health: String,
+    ports: Vec<String>,
+    /// How long a running container has been up
+    uptime: Option<String>,
+    /// Full `docker ps` status, e.g. `Exited (1) 5 minutes ago`
+    status: String,
+}
+
+impl From<&ServiceStatus> for ServiceInfo {
+    fn from(service: &ServiceStatus) -> Self {
+        Self {
+            service: service.service.clone(),
+            container: service.name.clone(),
+            image: service.image.clone(),
+            state: service.state.clone(),
+            health: service.health.clone(),
+            ports: service.published_ports(),
+            uptime: service.uptime(),
+            status: service.status.clone(),
+        }
+    }
+}
+
+#[derive(Tabled)]
+struct ServiceRow {
+    #[tabled(rename = "Service")]
+    service: String,
+    #[tabled(rename = "Image")]
+    image: String,
+    #[tabled(rename = "State")]
+    state: String,
+    #[tabled(rename = "Health")]
+    health: String,
+    #[tabled(rename = "Ports")]
+    ports: String,
+    #[tabled(rename = "Uptime")]
+    uptime: String,
+}
+
+impl Ps {
+    #[instrument(name = "ps", skip(self, cli_args), fields(target = %self.target))]
+    pub async fn run(&self, cli_args: &Cli) -> Result<(), CliError> {
+        let target = parse_ssh_target(&self.target)?;
+        debug!(user = %target.user, port = %target.port, key = ?cli_args.identity_file, "SSH details");
+
+        let pb = ui::create_spinner(&format!(
+            "Connecting to {}...",
+            ui::format_highlight(&target.host)
+        ));
+        let mut executor = connect_ssh(cli_args, &target).await.map_err(|e| {
+            pb.finish_and_clear();
+            CliError::OperationFailed(format!("SSH connection failed: {}", e))
+        })?;
+        pb.set_message("Listing services...");
+
+        // Works from the files on the server, so no local analysis is needed
+        let mut deployer = Deployer::new(remote_only_config(cli_args)?, &mut executor, None);
+        let services = async {
+            deployer.use_remote_project().await?;
+            deployer.list_services().await
+        }
+        .await
+        .map_err(|e| {
+            pb.finish_and_clear();
+            CliError::OperationFailed(format!("Failed to list services: {}", e))
+        })?;
+        pb.finish_and_clear();
+
+        let services: Vec<ServiceInfo> = services.iter().map(ServiceInfo::from).collect();
+        if cli_args.json() {
+            print_json(&services)?;
+        } else if !cli_args.quiet {
+            println!(
+                "\n{}",
+                ui::format_header(&format!("Services on {}:", self.target))
+            );
+            print_services(&services);
+        }
+        Ok(())
+    }
+}
+
+fn print_services(services: &[ServiceInfo]) {
+    if services.is_empty() {
+        println!("  {}", ui::format_warning("(None)"));
+        return;
+    }
+    let rows = services.iter().map(|service| ServiceRow {
+        service: service.service.clone(),
+        image: service.image.clone(),
+        state: state_cell(&service.state),
+        health: health_cell(&service.health),
+        ports: ports_cell(&service.ports),
+        uptime: service.uptime.clone().unwrap_or_else(|| "-".to_string()),
+    });
+    print_table(Table::new(rows));
+}
+
+/// Container state, green when running.
+pub(super) fn state_cell(state: &str) -> String {
+    match state {
+        "running" => state.green().to_string(),
+        "restarting" | "paused" => state.yellow().to_string(),
+        _ => state.red().to_string(),
+    }
+}
+
+/// Health check status, `-` without a health check.
+pub(super) fn health_cell(health: &str) -> String {
+    match health {
+        "" => "-".to_string(),
+        "healthy" => health.green().to_string(),
+        "starting" => health.yellow().to_string(),
+        _ => health.red().to_string(),
+    }
+}
+
+pub(super) fn ports_cell(ports: &[String]) -> String {
+    if ports.is_empty() {
+        "-".to_string()
+    } else {
+        ports.join(", ")
+    }
+}
+
+/// Print a service table with a bold blue header.
+pub(super) fn print_table(mut table: Table) {
+    table
+        .with(Style::blank())
+        .with(Modify::new(Rows::first()).with(Color::FG_BLUE))
+        .with(
+            Modify::new(Rows::first())
+                .with(tabled::settings::Format::content(|s| s.bold().to_string())),
+        );
+    println!("{}", table);
+}
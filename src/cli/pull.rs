@@ -0,0 +1,82 @@
+use super::common::{connect_ssh, parse_ssh_target};
+use super::error::CliError;
+use super::parser::Cli;
+use super::ui;
+use crate::deployer::default_remote_dir;
+use crate::deployer::pull::pull_files;
+use clap::Args;
+use std::path::PathBuf;
+use tracing::{debug, info, instrument};
+
+#[derive(Debug, Args)]
+pub struct Pull {
+    /// Remote target in the format [user@]host[:port]
+    #[arg(required = true)]
+    target: String,
+
+    /// Files or directories to fetch, relative to the remote project
+    /// directory (e.g. `certs`, `data/app.db`, `.env.dcd`) or absolute
+    #[arg(required = true, value_name = "PATH")]
+    paths: Vec<PathBuf>,
+
+    /// Local directory to put them in (default: the project directory)
+    #[arg(short = 'o', long = "output", value_name = "DIR")]
+    output: Option<PathBuf>,
+
+    /// Replace local files that exist already
+    #[arg(long)]
+    force: bool,
+}
+
+impl Pull {
+    #[instrument(name = "pull", skip(self, cli_args), fields(target = %self.target))]
+    pub async fn run(&self, cli_args: &Cli) -> Result<(), CliError> {
+        let target = parse_ssh_target(&self.target)?;
+        debug!(user = %target.user, port = %target.port, key = ?cli_args.identity_file, "SSH details");
+
+        let project_dir = std::fs::canonicalize("./").map_err(|e| {
+            CliError::ConfigError(format!("Failed to resolve current directory: {}", e))
+        })?;
+        let remote_dir = cli_args
+            .remote_dir
+            .clone()
+            .unwrap_or_else(|| default_remote_dir(&project_dir));
+        let local_dir = self.output.clone().unwrap_or(project_dir);
+
+        let pb = ui::create_spinner(&format!(
+            "Connecting to {}...",
+            ui::format_highlight(&target.host)
+        ));
+        let mut executor = connect_ssh(cli_args, &target).await.map_err(|e| {
+            pb.finish_and_clear();
+            CliError::OperationFailed(format!("SSH connection failed: {}", e))
+        })?;
+
+        pb.set_message("Downloading files...");
+        let pulled = pull_files(
+            &mut executor,
+            &remote_dir,
+            &self.paths,
+            &local_dir,
+            self.force,
+        )
+        .await;
+        pb.finish_and_clear();
+        let pulled = pulled
+            .map_err(|e| CliError::OperationFailed(format!("Failed to pull files: {}", e)))?;
+
+        for path in &pulled {
+            debug!("Wrote {}", path.display());
+        }
+        info!(
+            "{}",
+            ui::format_success(&format!(
+                "Pulled {} file(s) from {} into {}",
+                pulled.len(),
+                remote_dir.display(),
+                local_dir.display()
+            ))
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+use super::common::{
+    age_identity_path, connect_ssh, get_analysis, parse_ssh_target, RemoteExecutor, SshTarget,
+};
+use super::error::CliError;
+use super::parser::Cli;
+use super::ui;
+use super::ui::handle_deployer_events;
+use crate::config::ProjectConfig;
+use crate::deployer::reboot::{boot_id, schedule_reboot};
+use crate::deployer::types::DeployerEvent;
+use crate::deployer::{types::DeploymentConfig, Deployer};
+use crate::executor::CommandExecutor;
+use clap::Args;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::{debug, info, instrument, warn};
+
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Args)]
+pub struct Reboot {
+    /// Remote target in the format [user@]host[:port]
+    #[arg(required = true)]
+    target: String,
+
+    /// Wait for the host to come back and verify services are healthy
+    #[arg(long)]
+    wait: bool,
+
+    /// Seconds to wait for SSH to become available again
+    #[arg(long, default_value_t = 300, value_name = "SECONDS")]
+    timeout: u64,
+
+    /// Skip the confirmation prompt
+    #[arg(long, short = 'y', visible_alias = "yes")]
+    force: bool,
+
+    /// Disable interactive progress spinner and show only logs
+    #[arg(long)]
+    pub no_progress: bool,
+}
+
+impl Reboot {
+    #[instrument(name = "reboot", skip(self, cli_args), fields(target = %self.target))]
+    pub async fn run(&self, cli_args: &Cli) -> Result<(), CliError> {
+        let target = parse_ssh_target(&self.target)?;
+        if target.local {
+            return Err(CliError::ConfigError(
+                "Refusing to reboot the machine dcd runs on".to_string(),
+            ));
+        }
+        info!("Rebooting {}", ui::format_highlight(&self.target));
+        debug!(user = %target.user, port = %target.port, key = ?cli_args.identity_file, "SSH details");
+
+        if !self.force
+            && !ui::confirm(
+                &format!(
+                    "Stop the stack and reboot {}?",
+                    ui::format_highlight(&self.target)
+                ),
+                "--yes",
+            )?
+        {
+            info!("Reboot cancelled by user.");
+            return Ok(());
+        }
+
+        let analysis = get_analysis(cli_args)
+            .await
+            .map_err(|e| CliError::OperationFailed(format!("Local analysis failed: {}", e)))?;
+        let deploy_config = DeploymentConfig {
+            project_dir: analysis.resolved_project_dir.clone(),
+            age_identity: age_identity_path(),
+            remote_dir: cli_args.remote_dir.clone(),
+            compose_files: analysis.resolved_compose_files.clone(),
+            env_files: analysis.resolved_env_files.clone(),
+            // Needed to tell whether a .env.dcd was deployed
+            consumed_env: analysis.consumed_env,
+            service_dependencies: analysis.service_dependencies,
+            profiles: analysis.active_profiles,
+            service_hints: analysis.service_hints,
+            wait_strategies: ProjectConfig::load(&analysis.resolved_project_dir)
+                .map_err(|e| CliError::ConfigError(e.to_string()))?
+                .wait,
+            // Remaining fields are only needed when deploying
+            ..Default::default()
+        };
+
+        // --- Stop and reboot ---
+        let pb = ui::create_spinner(&format!(
+            "Connecting to {}...",
+            ui::format_highlight(&target.host)
+        ));
+        let mut executor = connect_ssh(cli_args, &target).await.map_err(|e| {
+            pb.finish_and_clear();
+            CliError::OperationFailed(format!("SSH connection failed: {}", e))
+        })?;
+
+        pb.set_message("Stopping services...");
+        let mut deployer = Deployer::new(deploy_config.clone(), &mut executor, None);
+        deployer.stop_services(&[]).await.map_err(|e| {
+            pb.finish_with_message("❌ Failed to stop services");
+            CliError::OperationFailed(format!("Failed to stop services: {}", e))
+        })?;
+        drop(deployer);
+
+        let previous_boot = boot_id(&mut executor)
+            .await
+            .map_err(|e| CliError::OperationFailed(e.to_string()))?;
+        pb.set_message("Rebooting host...");
+        schedule_reboot(&mut executor)
+            .await
+            .map_err(|e| CliError::OperationFailed(e.to_string()))?;
+        if let Err(e) = executor.close().await {
+            debug!("Closing SSH session before reboot failed: {}", e);
+        }
+
+        if !self.wait {
+            pb.finish_with_message("✅ Reboot requested.");
+            info!(
+                "{}",
+                ui::format_warning("Not waiting for the host; use --wait to verify services.")
+            );
+            return Ok(());
+        }
+
+        // --- Wait for the host to return ---
+        pb.set_message("Waiting for host to come back...");
+        let mut executor = self
+            .wait_for_reboot(cli_args, &target, &previous_boot)
+            .await
+            .inspect_err(|_| pb.finish_with_message("❌ Host did not come back"))?;
+        pb.finish_with_message("✅ Host is back online.");
+
+        // --- Verify services ---
+        let (progress_sender, ui_update_task_handle) = if !self.no_progress {
+            let (sender, receiver) = mpsc::channel::<DeployerEvent>(32);
+            let pb = ui::create_spinner("Starting services...");
+            let ui_task = tokio::spawn(handle_deployer_events(receiver, pb.clone()));
+            (Some(sender), Some((ui_task, pb)))
+        } else {
+            (None, None)
+        };
+        let mut deployer = Deployer::new(deploy_config, &mut executor, progress_sender);
+        let start_result = deployer.start_services().await;
+        drop(deployer);
+
+        if let Some((ui_task, pb)) = ui_update_task_handle {
+            if let Err(e) = ui_task.await {
+                tracing::error!("UI update task failed: {}", e);
+            }
+            match &start_result {
+                Ok(status) if status.services_healthy => {
+                    pb.finish_with_message("✅ Services are healthy.")
+                }
+                _ => pb.finish_with_message("❌ Services did not come back healthy."),
+            }
+        }
+
+        let status = start_result
+            .map_err(|e| CliError::OperationFailed(format!("Failed to start services: {}", e)))?;
+        if !status.services_healthy {
+            return Err(CliError::OperationFailed(format!(
+                "Services are not healthy after reboot: {}",
+                status.message
+            )));
+        }
+
+        info!(
+            "{}",
+            ui::format_success("Reboot complete, services healthy.")
+        );
+        Ok(())
+    }
+
+    /// Reconnect until the host answers with a boot id different from the
+    /// one seen before the reboot.
+    async fn wait_for_reboot(
+        &self,
+        cli_args: &Cli,
+        target: &SshTarget,
+        previous_boot: &str,
+    ) -> Result<RemoteExecutor, CliError> {
+        let deadline = Instant::now() + Duration::from_secs(self.timeout);
+        while Instant::now() < deadline {
+            tokio::time::sleep(RECONNECT_INTERVAL).await;
+            let mut executor = match connect_ssh(cli_args, target).await {
+                Ok(executor) => executor,
+                Err(e) => {
+                    debug!("Host not reachable yet: {}", e);
+                    continue;
+                }
+            };
+            match boot_id(&mut executor).await {
+                Ok(id) if id != previous_boot => return Ok(executor),
+                Ok(_) => debug!("Host has not restarted yet"),
+                Err(e) => warn!("Could not read boot id: {}", e),
+            }
+            let _ = executor.close().await;
+        }
+        Err(CliError::OperationFailed(format!(
+            "Host did not come back within {}s",
+            self.timeout
+        )))
+    }
+}
@@ -0,0 +1,85 @@
+use super::common::{connect_ssh, parse_ssh_target, remote_only_config};
+use super::error::CliError;
+use super::parser::Cli;
+use super::ui;
+use super::ui::handle_deployer_events;
+use crate::deployer::types::{DeployStage, DeployerEvent};
+use crate::deployer::Deployer;
+use clap::Args;
+use tokio::sync::mpsc;
+use tracing::{debug, info, instrument};
+
+#[derive(Debug, Args)]
+pub struct Restart {
+    /// Remote target in the format [user@]host[:port]
+    #[arg(required = true)]
+    target: String,
+
+    /// Services to restart (defaults to all)
+    services: Vec<String>,
+
+    /// Don't verify service health after restarting
+    #[arg(long)]
+    no_health_check: bool,
+
+    /// Disable interactive progress spinner and show only logs
+    #[arg(long)]
+    pub no_progress: bool,
+}
+
+impl Restart {
+    #[instrument(name = "restart", skip(self, cli_args), fields(target = %self.target))]
+    pub async fn run(&self, cli_args: &Cli) -> Result<(), CliError> {
+        let target = parse_ssh_target(&self.target)?;
+        debug!(user = %target.user, port = %target.port, key = ?cli_args.identity_file, "SSH details");
+
+        let (progress_sender, ui_update_task_handle) = if !self.no_progress {
+            let (sender, receiver) = mpsc::channel::<DeployerEvent>(32);
+            let pb = ui::create_spinner("Connecting...");
+            let ui_task = tokio::spawn(handle_deployer_events(receiver, pb.clone()));
+            (Some(sender), Some((ui_task, pb)))
+        } else {
+            (None, None)
+        };
+
+        let mut executor = connect_ssh(cli_args, &target).await.map_err(|e| {
+            if let Some((_, pb)) = ui_update_task_handle.as_ref() {
+                pb.finish_with_message("❌ SSH connection failed".to_string());
+            }
+            CliError::OperationFailed(format!("SSH connection failed: {}", e))
+        })?;
+
+        // Works from the files on the server, so no local analysis is needed
+        let mut config = remote_only_config(cli_args)?;
+        if self.no_health_check {
+            config.skip_stages.insert(DeployStage::Health);
+        }
+        let mut deployer = Deployer::new(config, &mut executor, progress_sender);
+        let restart_result = match deployer.use_remote_project().await {
+            Ok(()) => deployer.restart_services(&self.services).await,
+            Err(e) => Err(e),
+        };
+        drop(deployer);
+
+        if let Some((ui_task, pb)) = ui_update_task_handle {
+            if let Err(e) = ui_task.await {
+                tracing::error!("UI update task failed: {}", e);
+            }
+            match &restart_result {
+                Ok(_) => pb.finish_with_message("✅ Restart finished."),
+                Err(_) => pb.finish_with_message("❌ Restart failed.".to_string()),
+            }
+        }
+
+        let status = restart_result
+            .map_err(|e| CliError::OperationFailed(format!("Restart failed: {}", e)))?;
+        if !self.no_health_check && !status.services_healthy {
+            return Err(CliError::OperationFailed(format!(
+                "Services are not healthy after the restart: {}",
+                status.message
+            )));
+        }
+        info!("{}", ui::format_success("Restart successful!"));
+        Ok(())
+    }
+}
@@ -0,0 +1,91 @@
+use super::common::{connect_ssh, parse_ssh_target, remote_only_config};
+use super::error::CliError;
+use super::parser::Cli;
+use super::ui;
+use super::ui::handle_deployer_events;
+use crate::deployer::types::{DeployStage, DeployerEvent};
+use crate::deployer::Deployer;
+use clap::Args;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tracing::{debug, info, instrument};
+
+#[derive(Debug, Args)]
+pub struct Rollback {
+    /// Remote target in the format [user@]host[:port]
+    #[arg(required = true)]
+    target: String,
+
+    /// Don't verify service health after rolling back
+    #[arg(long)]
+    no_health_check: bool,
+
+    /// Disable interactive progress spinner and show only logs
+    #[arg(long)]
+    pub no_progress: bool,
+}
+
+impl Rollback {
+    #[instrument(name = "rollback", skip(self, cli_args), fields(target = %self.target))]
+    pub async fn run(&self, cli_args: &Cli) -> Result<(), CliError> {
+        let target = parse_ssh_target(&self.target)?;
+        info!(
+            "Rolling back the deployment on {}",
+            ui::format_highlight(&self.target)
+        );
+        debug!(user = %target.user, port = %target.port, key = ?cli_args.identity_file, "SSH details");
+
+        let (progress_sender, ui_update_task_handle) = if !self.no_progress {
+            let (sender, receiver) = mpsc::channel::<DeployerEvent>(32);
+            let pb = ui::create_spinner("Connecting...");
+            let ui_task = tokio::spawn(handle_deployer_events(receiver, pb.clone()));
+            (Some(sender), Some((ui_task, pb)))
+        } else {
+            (None, None)
+        };
+
+        let mut executor = connect_ssh(cli_args, &target).await.map_err(|e| {
+            if let Some((_, pb)) = ui_update_task_handle.as_ref() {
+                pb.finish_with_message("❌ SSH connection failed".to_string());
+            }
+            CliError::OperationFailed(format!("SSH connection failed: {}", e))
+        })?;
+
+        // Everything to go back to is recorded on the server
+        let mut config = remote_only_config(cli_args)?;
+        if self.no_health_check {
+            config.skip_stages.insert(DeployStage::Health);
+        }
+        let mut deployer = Deployer::new(config, &mut executor, progress_sender);
+        let rollback_result = deployer.rollback().await;
+        drop(deployer);
+
+        if let Some((ui_task, pb)) = ui_update_task_handle {
+            if let Err(e) = ui_task.await {
+                tracing::error!("UI update task failed: {}", e);
+            }
+            match &rollback_result {
+                Ok(_) => pb.finish_with_message("✅ Rollback finished."),
+                Err(_) => pb.finish_with_message("❌ Rollback failed.".to_string()),
+            }
+        }
+
+        let status = rollback_result
+            .map_err(|e| CliError::OperationFailed(format!("Rollback failed: {}", e)))?;
+        info!("{}", status.message);
+        if let Some(record) = &status.last_deploy {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default();
+            info!("Now running the deployment from {}", record.summary(now));
+        }
+        if !self.no_health_check && !status.services_healthy {
+            return Err(CliError::OperationFailed(
+                ui::format_warning("Some services are not healthy after the rollback.").to_string(),
+            ));
+        }
+        info!("{}", ui::format_success("Rollback successful!"));
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+use super::common::{age_identity_path, connect_ssh, get_analysis, parse_ssh_target};
+use super::error::CliError;
+use super::parser::Cli;
+use super::ui;
+use crate::deployer::{types::DeploymentConfig, Deployer};
+use clap::Args;
+use std::io::IsTerminal;
+use tracing::{debug, info, instrument};
+
+#[derive(Debug, Args)]
+pub struct Run {
+    /// Remote target in the format [user@]host[:port]
+    #[arg(required = true)]
+    target: String,
+
+    /// Service to run the command in
+    #[arg(required = true)]
+    service: String,
+
+    /// Command and arguments (defaults to the service's command)
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    command: Vec<String>,
+
+    /// Compose profile to enable (can be repeated)
+    #[arg(long = "profile", value_name = "PROFILE")]
+    profiles: Vec<String>,
+
+    /// Disable pseudo-TTY allocation (default when stdin is not a terminal)
+    #[arg(short = 'T', long)]
+    no_tty: bool,
+}
+
+impl Run {
+    #[instrument(name = "run", skip(self, cli_args), fields(target = %self.target))]
+    pub async fn run(&self, cli_args: &Cli) -> Result<(), CliError> {
+        let target = parse_ssh_target(&self.target)?;
+        info!(
+            "Running one-off command in {} on {}",
+            ui::format_highlight(&self.service),
+            ui::format_highlight(&self.target)
+        );
+        debug!(user = %target.user, port = %target.port, key = ?cli_args.identity_file, "SSH details");
+
+        let analysis = get_analysis(cli_args)
+            .await
+            .map_err(|e| CliError::OperationFailed(format!("Local analysis failed: {}", e)))?;
+        let deploy_config = DeploymentConfig {
+            project_dir: analysis.resolved_project_dir.clone(),
+            age_identity: age_identity_path(),
+            remote_dir: cli_args.remote_dir.clone(),
+            compose_files: analysis.resolved_compose_files.clone(),
+            env_files: analysis.resolved_env_files.clone(),
+            // Needed to tell whether a .env.dcd was deployed
+            consumed_env: analysis.consumed_env,
+            // Remaining fields are only needed when deploying
+            ..Default::default()
+        };
+
+        let mut executor = connect_ssh(cli_args, &target)
+            .await
+            .map_err(|e| CliError::OperationFailed(format!("SSH connection failed: {}", e)))?;
+
+        let tty = !self.no_tty && std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
+        let mut deployer = Deployer::new(deploy_config, &mut executor, None);
+        let exit_code = deployer
+            .run_service(&self.service, &self.command, tty, &self.profiles)
+            .await
+            .map_err(|e| CliError::OperationFailed(e.to_string()))?;
+
+        if exit_code != 0 {
+            return Err(CliError::OperationFailed(format!(
+                "Command in service '{}' exited with status {}",
+                self.service, exit_code
+            )));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,44 @@
+use super::common::{open_ssh, parse_ssh_target, session_socket};
+use super::error::CliError;
+use super::parser::Cli;
+use crate::executor::session;
+use clap::Args;
+use std::time::Duration;
+use tracing::{debug, instrument};
+
+/// Background process behind `--reuse-connection`; not meant to be run by hand.
+#[derive(Debug, Args)]
+pub struct Session {
+    /// Remote target in the format [user@]host[:port]
+    #[arg(required = true)]
+    target: String,
+
+    /// Exit after this many seconds without a request
+    #[arg(long, value_name = "SECS", default_value_t = 60)]
+    idle: u64,
+}
+
+impl Session {
+    #[instrument(name = "session", skip(self, cli_args), fields(target = %self.target))]
+    pub async fn run(&self, cli_args: &Cli) -> Result<(), CliError> {
+        let target = parse_ssh_target(&self.target)?;
+        if target.local {
+            return Err(CliError::ConfigError(
+                "The local target runs without SSH and has no session to share".to_string(),
+            ));
+        }
+        let socket = session_socket(cli_args, &target)
+            .map_err(|e| CliError::OperationFailed(e.to_string()))?;
+        let executor = open_ssh(cli_args, &target)
+            .await
+            .map_err(|e| CliError::OperationFailed(format!("SSH connection failed: {}", e)))?;
+        debug!(
+            "Sharing SSH connection to {} via {}",
+            self.target,
+            socket.display()
+        );
+        session::serve(executor, &socket, Duration::from_secs(self.idle))
+            .await
+            .map_err(|e| CliError::OperationFailed(format!("Session failed: {}", e)))
+    }
+}
@@ -1,22 +1,40 @@
-use super::common::{get_analysis, parse_ssh_target};
+use super::common::{
+    age_identity_path, connect_ssh, get_analysis, parse_ssh_target, remote_only_config,
+};
+use super::doctor::print_findings;
 use super::error::CliError;
+use super::hosts::{on_each_host, report_hosts, resolve_targets};
 use super::parser::Cli;
+use super::ps::{health_cell, ports_cell, print_table, state_cell};
 use super::ui;
 use super::ui::handle_deployer_events;
-use crate::deployer::types::DeployerEvent;
+use crate::composer::types::ComposerOutput;
+use crate::deployer::doctor::{Finding, HostDoctor};
+use crate::deployer::types::{DeployerEvent, DeploymentStatus, ServiceDetail};
 use crate::deployer::{types::DeploymentConfig, Deployer};
-use crate::executor::SshCommandExecutor;
 use clap::Args;
 use colored::*;
-use std::time::Duration;
+use indicatif::MultiProgress;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tabled::{Table, Tabled};
 use tokio::sync::mpsc;
-use tracing::{debug, info, instrument};
+use tracing::{debug, info, instrument, warn, Instrument};
 
 #[derive(Debug, Args)]
 pub struct Status {
-    /// Remote target in the format [user@]host[:port]
-    #[arg(required = true)]
-    target: String,
+    /// Remote target(s) in the format [user@]host[:port]
+    #[arg(value_name = "TARGET", required_unless_present = "hosts_file")]
+    targets: Vec<String>,
+
+    /// File listing more targets, one per line (`#` starts a comment)
+    #[arg(long, value_name = "PATH")]
+    hosts_file: Option<PathBuf>,
+
+    /// Skip local analysis and inspect the compose project found in the remote directory
+    #[arg(long)]
+    remote_only: bool,
 
     /// Disable interactive progress spinner and show only logs
     #[arg(long)]
@@ -24,47 +42,96 @@ pub struct Status {
 }
 
 impl Status {
-    #[instrument(name = "status", skip(self, cli_args), fields(target = %self.target))]
+    #[instrument(name = "status", skip(self, cli_args), fields(targets = ?self.targets))]
     pub async fn run(&self, cli_args: &Cli) -> Result<(), CliError> {
-        let target = parse_ssh_target(&self.target)?;
-        info!("Checking status on {}", ui::format_highlight(&self.target));
+        let targets = resolve_targets(&self.targets, self.hosts_file.as_deref())?;
+        info!(
+            "Checking status on {}",
+            ui::format_highlight(&targets.join(", "))
+        );
+
+        // --- Local Analysis (Minimal) ---
+        let analysis = if self.remote_only {
+            info!("--remote-only provided. Skipping local analysis.");
+            None
+        } else {
+            info!("Performing local analysis to determine project context..."); // Use info log
+            let analysis = get_analysis(cli_args)
+                .await
+                .map_err(|e| CliError::OperationFailed(format!("Local analysis failed: {}", e)))?;
+            info!("Local analysis complete."); // Use info log
+            Some(analysis)
+        };
+
+        if let ([target], false) = (targets.as_slice(), cli_args.json()) {
+            let host = self.check_host(cli_args, target, analysis, None).await?;
+            self.print_host(cli_args, target, &host);
+            return Ok(());
+        }
+
+        let multi = MultiProgress::new();
+        let results = on_each_host(cli_args, &targets, |target| {
+            let analysis = analysis.clone();
+            let multi = &multi;
+            async move {
+                self.check_host(cli_args, &target, analysis, Some(multi))
+                    .await
+            }
+        })
+        .await;
+
+        if !cli_args.json() {
+            for (target, result) in &results {
+                if let Ok(host) = result {
+                    self.print_host(cli_args, target, host);
+                }
+            }
+        }
+        report_hosts(
+            "Status",
+            &results,
+            |host| {
+                if host.status.services_healthy {
+                    Ok("healthy".to_string())
+                } else {
+                    Err("services not healthy".to_string())
+                }
+            },
+            cli_args,
+        )
+    }
+
+    /// Query one host: the deployment status plus, unless read-only, its
+    /// pending updates.
+    async fn check_host(
+        &self,
+        cli_args: &Cli,
+        target_str: &str,
+        analysis: Option<ComposerOutput>,
+        multi: Option<&MultiProgress>,
+    ) -> Result<HostStatus, CliError> {
+        let target = parse_ssh_target(target_str)?;
         debug!(user = %target.user, port = %target.port, key = ?cli_args.identity_file, "SSH details");
 
         // --- Setup Progress Reporting ---
         let (progress_sender, ui_update_task_handle) = if !self.no_progress {
             let (sender, receiver) = mpsc::channel::<DeployerEvent>(32);
-            let pb = ui::create_spinner("Initializing status check..."); // Initial message
-            let ui_task = tokio::spawn(handle_deployer_events(receiver, pb.clone()));
+            let message = "Initializing status check..."; // Initial message
+            let pb = match multi {
+                Some(multi) => ui::create_host_spinner(multi, target_str, message),
+                None => ui::create_spinner(message),
+            };
+            let ui_task =
+                tokio::spawn(handle_deployer_events(receiver, pb.clone()).in_current_span());
             (Some(sender), Some((ui_task, pb)))
         } else {
             info!("Progress spinner disabled via --no-progress.");
             (None, None)
         };
 
-        // --- Local Analysis (Minimal) ---
-        info!("Performing local analysis to determine project context..."); // Use info log
-        let analysis = get_analysis(cli_args).await.map_err(|e| {
-            // If progress bar exists, finish it with error before returning
-            if let Some((_, pb)) = ui_update_task_handle.as_ref() {
-                // Borrow handle
-                pb.finish_with_message("❌ Local analysis failed".to_string());
-            }
-            CliError::OperationFailed(format!("Local analysis failed: {}", e))
-        })?;
-        info!("Local analysis complete."); // Use info log
-
         // --- SSH Connection ---
         info!("Connecting to {}...", ui::format_highlight(&target.host)); // Use info log
-        let addr_str = format!("{}:{}", target.host, target.port);
-        let mut executor = SshCommandExecutor::connect(
-            cli_args.identity_file.as_ref(),
-            &target.user,
-            &addr_str,
-            Duration::from_secs(30),
-            cli_args.no_warnings,
-        )
-        .await
-        .map_err(|e| {
+        let mut executor = connect_ssh(cli_args, &target).await.map_err(|e| {
             if let Some((_, pb)) = ui_update_task_handle.as_ref() {
                 // Borrow handle
                 pb.finish_with_message("❌ SSH connection failed".to_string());
@@ -75,23 +142,35 @@ impl Status {
 
         // --- Get Status ---
         // Create deployment config with minimal required information for status check
-        let deploy_config = DeploymentConfig {
-            project_dir: analysis.resolved_project_dir.clone(),
-            remote_dir: cli_args.remote_dir.clone(),
-            compose_files: analysis.resolved_compose_files.clone(),
-            env_files: analysis.resolved_env_files.clone(),
-            // Following fields are not strictly needed for basic status but required by struct
-            consumed_env: std::collections::HashMap::new(),
-            exposed_ports: Vec::new(),
-            local_references: Vec::new(),
-            volumes: Vec::new(),
+        let deploy_config = match analysis {
+            Some(analysis) => DeploymentConfig {
+                project_dir: analysis.resolved_project_dir.clone(),
+                age_identity: age_identity_path(),
+                remote_dir: cli_args.remote_dir.clone(),
+                compose_files: analysis.resolved_compose_files.clone(),
+                env_files: analysis.resolved_env_files.clone(),
+                // Needed to tell whether a .env.dcd was deployed
+                consumed_env: analysis.consumed_env,
+                service_dependencies: analysis.service_dependencies,
+                profiles: analysis.active_profiles,
+                // Remaining fields are not needed for a status check
+                ..Default::default()
+            },
+            // Compose files and profiles are read from the remote directory
+            None => remote_only_config(cli_args)?,
         };
 
         // Instantiate Deployer, passing the sender
         let mut deployer = Deployer::new(deploy_config, &mut executor, progress_sender);
 
         // Get status
-        let status_result = deployer.get_status().await;
+        let status_result = async {
+            if self.remote_only {
+                deployer.use_remote_project().await?;
+            }
+            deployer.get_status().await
+        }
+        .await;
 
         // Drop deployer to close channel
         drop(deployer);
@@ -111,10 +190,33 @@ impl Status {
         let status = status_result
             .map_err(|e| CliError::OperationFailed(format!("Status check failed: {}", e)))?;
 
+        // --- Host maintenance state ---
+        let updates = if cli_args.read_only || cli_args.quiet {
+            debug!("Skipping host checks");
+            None
+        } else {
+            match HostDoctor::new(&mut executor).check_updates().await {
+                Ok(findings) => Some(findings),
+                Err(e) => {
+                    warn!("Could not check pending updates: {}", e);
+                    None
+                }
+            }
+        };
+
+        Ok(HostStatus { status, updates })
+    }
+
+    fn print_host(&self, cli_args: &Cli, target: &str, host: &HostStatus) {
+        if cli_args.quiet {
+            return;
+        }
+        let status = &host.status;
+
         // --- Print Status ---
         println!(
             "\n{}",
-            ui::format_header(&format!("Deployment Status on {}:", self.target))
+            ui::format_header(&format!("Deployment Status on {}:", target))
         );
         let health_status = if status.services_healthy {
             "Yes".green()
@@ -122,13 +224,70 @@ impl Status {
             "No".red()
         };
         println!("Services healthy: {}", health_status);
+        match &status.last_deploy {
+            Some(record) => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|elapsed| elapsed.as_secs())
+                    .unwrap_or_default();
+                println!("Last deployed: {}", record.summary(now));
+            }
+            None => println!("Last deployed: unknown (no deployment recorded)"),
+        }
 
+        if !status.services.is_empty() {
+            print_services(&status.services);
+        }
         if !status.message.is_empty() {
             println!("Status message:\n{}", status.message.trim());
-        } else {
+        } else if status.services.is_empty() {
             println!("(No detailed status message provided by docker compose ps)");
         }
 
-        Ok(())
+        if let Some(findings) = &host.updates {
+            println!("\n{}", ui::format_header("Host:"));
+            print_findings(findings);
+        }
     }
 }
+
+#[derive(Tabled)]
+struct ServiceRow {
+    #[tabled(rename = "Service")]
+    service: String,
+    #[tabled(rename = "State")]
+    state: String,
+    #[tabled(rename = "Health")]
+    health: String,
+    #[tabled(rename = "Ports")]
+    ports: String,
+    #[tabled(rename = "Image")]
+    image: String,
+    #[tabled(rename = "Restarts")]
+    restarts: String,
+}
+
+fn print_services(services: &[ServiceDetail]) {
+    let rows = services.iter().map(|service| ServiceRow {
+        service: service.name.clone(),
+        state: state_cell(&service.state),
+        health: health_cell(service.health.as_deref().unwrap_or_default()),
+        ports: ports_cell(&service.ports),
+        image: service.image.clone(),
+        restarts: match service.restarts {
+            0 => "0".to_string(),
+            n => n.to_string().yellow().to_string(),
+        },
+    });
+    println!();
+    print_table(Table::new(rows));
+}
+
+/// What `status` found on one host.
+#[derive(Serialize)]
+struct HostStatus {
+    status: DeploymentStatus,
+    /// Pending updates; `None` when not checked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    updates: Option<Vec<Finding>>,
+}
@@ -0,0 +1,64 @@
+use super::common::{connect_ssh, parse_ssh_target, remote_only_config};
+use super::error::CliError;
+use super::parser::Cli;
+use super::ui;
+use crate::deployer::Deployer;
+use clap::Args;
+use tracing::{debug, info, instrument};
+
+#[derive(Debug, Args)]
+pub struct Stop {
+    /// Remote target in the format [user@]host[:port]
+    #[arg(required = true)]
+    target: String,
+
+    /// Services to stop (defaults to all)
+    services: Vec<String>,
+}
+
+impl Stop {
+    #[instrument(name = "stop", skip(self, cli_args), fields(target = %self.target))]
+    pub async fn run(&self, cli_args: &Cli) -> Result<(), CliError> {
+        let target = parse_ssh_target(&self.target)?;
+        debug!(user = %target.user, port = %target.port, key = ?cli_args.identity_file, "SSH details");
+
+        let pb = ui::create_spinner(&format!(
+            "Connecting to {}...",
+            ui::format_highlight(&target.host)
+        ));
+        let mut executor = connect_ssh(cli_args, &target).await.map_err(|e| {
+            pb.finish_and_clear();
+            CliError::OperationFailed(format!("SSH connection failed: {}", e))
+        })?;
+
+        // Works from the files on the server, so no local analysis is needed
+        pb.set_message("Stopping services...");
+        let mut deployer = Deployer::new(remote_only_config(cli_args)?, &mut executor, None);
+        let result = match deployer.use_remote_project().await {
+            Ok(()) => deployer.stop_services(&self.services).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            pb.finish_with_message("❌ Failed to stop services");
+            return Err(CliError::OperationFailed(format!(
+                "Failed to stop services: {}",
+                e
+            )));
+        }
+        pb.finish_and_clear();
+
+        let stopped = if self.services.is_empty() {
+            "Services stopped".to_string()
+        } else {
+            format!("Stopped {}", self.services.join(", "))
+        };
+        info!(
+            "{}",
+            ui::format_success(&format!(
+                "{}; `dcd up` or `dcd restart` brings them back.",
+                stopped
+            ))
+        );
+        Ok(())
+    }
+}
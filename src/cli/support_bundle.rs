@@ -0,0 +1,145 @@
+use super::common::{connect_ssh, local_project_config, parse_ssh_target, remote_only_config};
+use super::error::CliError;
+use super::parser::Cli;
+use super::ui;
+use crate::deployer::support::{render_index, BundleEntry};
+use crate::deployer::{compose_project_name, default_remote_dir, Deployer};
+use crate::executor::{shell_quote, CommandExecutor, LocalCommandExecutor};
+use clap::Args;
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, info, instrument, warn};
+
+/// Index of the bundle: file, exit status and command of each entry.
+const INDEX_FILE: &str = "commands.txt";
+
+#[derive(Debug, Args)]
+pub struct SupportBundle {
+    /// Remote target in the format [user@]host[:port]
+    #[arg(required = true)]
+    target: String,
+
+    /// Number of log lines to include per service
+    #[arg(short = 'n', long, default_value_t = 500, value_name = "N")]
+    tail: u32,
+
+    /// Archive to write (default: dcd-support-<project>-<unix time>.tar.gz)
+    #[arg(short = 'o', long = "output", value_name = "FILE")]
+    output: Option<PathBuf>,
+}
+
+impl SupportBundle {
+    #[instrument(name = "support_bundle", skip(self, cli_args), fields(target = %self.target))]
+    pub async fn run(&self, cli_args: &Cli) -> Result<(), CliError> {
+        let target = parse_ssh_target(&self.target)?;
+        debug!(user = %target.user, port = %target.port, key = ?cli_args.identity_file, "SSH details");
+
+        let config = remote_only_config(cli_args)?;
+        let project = compose_project_name(
+            &config
+                .remote_dir
+                .clone()
+                .unwrap_or_else(|| default_remote_dir(&config.project_dir)),
+        );
+        let created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        let name = format!("dcd-support-{}-{}", project, created);
+        let output = self
+            .output
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(format!("{}.tar.gz", name)));
+        // Names from dcd.toml [secrets] are masked like secret-looking ones
+        let secret_names: Vec<String> = local_project_config().secrets.into_keys().collect();
+
+        let pb = ui::create_spinner(&format!(
+            "Connecting to {}...",
+            ui::format_highlight(&target.host)
+        ));
+        let mut executor = connect_ssh(cli_args, &target).await.map_err(|e| {
+            pb.finish_and_clear();
+            CliError::OperationFailed(format!("SSH connection failed: {}", e))
+        })?;
+
+        pb.set_message("Collecting diagnostics...");
+        let mut deployer = Deployer::new(config, &mut executor, None);
+        if let Err(e) = deployer.use_remote_project().await {
+            pb.finish_and_clear();
+            return Err(CliError::OperationFailed(e.to_string()));
+        }
+        let entries = deployer.support_bundle(self.tail, &secret_names).await;
+        pb.finish_and_clear();
+
+        let staging = std::env::temp_dir().join(&name);
+        let result = write_bundle(&staging, &entries, &output).await;
+        if let Err(e) = std::fs::remove_dir_all(&staging) {
+            warn!("Failed to remove {}: {}", staging.display(), e);
+        }
+        result?;
+
+        let failed = entries
+            .iter()
+            .filter(|entry| entry.exit_code != Some(0))
+            .count();
+        if failed > 0 {
+            warn!(
+                "{} of {} command(s) failed; see {} in the bundle",
+                failed,
+                entries.len(),
+                INDEX_FILE
+            );
+        }
+        info!(
+            "{}",
+            ui::format_success(&format!(
+                "Wrote {}; secret values are masked, but review it before sharing",
+                output.display()
+            ))
+        );
+        Ok(())
+    }
+}
+
+/// Write `entries` and their index into `staging` and pack it into `output`.
+async fn write_bundle(
+    staging: &Path,
+    entries: &[BundleEntry],
+    output: &Path,
+) -> Result<(), CliError> {
+    // Logs may hold personal data, so the files are kept private
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(staging)
+        .map_err(|e| write_error(staging, e))?;
+    for entry in entries {
+        let path = staging.join(entry.name);
+        std::fs::write(&path, &entry.content).map_err(|e| write_error(&path, e))?;
+    }
+    let index = staging.join(INDEX_FILE);
+    std::fs::write(&index, render_index(entries)).map_err(|e| write_error(&index, e))?;
+
+    let cmd = format!(
+        "tar -czf {} -C {} .",
+        shell_quote(&output.to_string_lossy()),
+        shell_quote(&staging.to_string_lossy())
+    );
+    let result = LocalCommandExecutor::new()
+        .execute_command(&cmd)
+        .await
+        .map_err(|e| CliError::OperationFailed(format!("Failed to run tar: {}", e)))?;
+    if !result.is_success() {
+        return Err(CliError::OperationFailed(format!(
+            "Failed to create {}: {}",
+            output.display(),
+            result.output.to_stderr_string().unwrap_or_default().trim()
+        )));
+    }
+    Ok(())
+}
+
+fn write_error(path: &Path, e: std::io::Error) -> CliError {
+    CliError::OperationFailed(format!("Failed to write {}: {}", path.display(), e))
+}
@@ -0,0 +1,141 @@
+use super::common::{direct_ssh, get_analysis, parse_ssh_target, sync_concurrency};
+use super::error::CliError;
+use super::parser::Cli;
+use super::ui;
+use super::ui::handle_deployer_events;
+use crate::config::ProjectConfig;
+use crate::deployer::types::{DeployerEvent, DeploymentConfig, EnvEditPolicy};
+use crate::deployer::{Deployer, DCD_ENV_FILE};
+use clap::Args;
+use indicatif::ProgressBar;
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+use tracing::{debug, info, instrument};
+
+#[derive(Debug, Args)]
+pub struct SyncOnly {
+    /// Remote target in the format [user@]host[:port]
+    #[arg(required = true)]
+    target: String,
+
+    /// Disable interactive progress spinner and show only logs
+    #[arg(long)]
+    pub no_progress: bool,
+
+    /// Change ownership of synced files on the remote (user[:group] or uid[:gid])
+    #[arg(long, value_name = "OWNER")]
+    chown: Option<String>,
+
+    /// Show a diff of each compose and env file that gets overwritten (values redacted)
+    #[arg(long)]
+    diff: bool,
+
+    /// Also sync hidden files and directories (dotfiles) of referenced
+    /// directories; .git, node_modules and entries matched by .dcdignore or
+    /// .dockerignore are still skipped
+    #[arg(long)]
+    include_hidden: bool,
+
+    /// What to do with env files edited on the server since the last deploy:
+    /// abort and report them, merge the edits that do not conflict with local
+    /// changes, or overwrite them
+    #[arg(long, value_name = "POLICY", default_value = "abort")]
+    env_edits: EnvEditPolicy,
+}
+
+impl SyncOnly {
+    #[instrument(name = "sync_only", skip(self, cli_args), fields(target = %self.target))]
+    pub async fn run(&self, cli_args: &Cli) -> Result<(), CliError> {
+        let target = parse_ssh_target(&self.target)?;
+        info!(
+            "Syncing project files to {}",
+            ui::format_highlight(&self.target)
+        );
+        debug!(user = %target.user, port = %target.port, key = ?cli_args.identity_file, "SSH details");
+
+        let analysis = get_analysis(cli_args)
+            .await
+            .map_err(|e| CliError::OperationFailed(format!("Local analysis failed: {}", e)))?;
+        let project_config = ProjectConfig::load(&analysis.resolved_project_dir)
+            .map_err(|e| CliError::ConfigError(e.to_string()))?;
+
+        let (progress_sender, ui_update_task_handle) = if !self.no_progress {
+            let (sender, receiver) = mpsc::channel::<DeployerEvent>(32);
+            let pb = ui::create_spinner("Connecting...");
+            let ui_task = tokio::spawn(handle_deployer_events(receiver, pb.clone()));
+            (Some(sender), Some((ui_task, pb)))
+        } else if self.diff {
+            // Diffs are still printed without the spinner
+            let (sender, receiver) = mpsc::channel::<DeployerEvent>(32);
+            let task = tokio::spawn(ui::print_file_diffs(receiver));
+            (Some(sender), Some((task, ProgressBar::hidden())))
+        } else {
+            (None, None)
+        };
+
+        let mut executor = direct_ssh(cli_args, &target).await.map_err(|e| {
+            if let Some((_, pb)) = ui_update_task_handle.as_ref() {
+                pb.finish_with_message("❌ SSH connection failed".to_string());
+            }
+            CliError::OperationFailed(format!("SSH connection failed: {}", e))
+        })?;
+
+        let project_dir = analysis.resolved_project_dir.clone();
+        let deploy_config = DeploymentConfig {
+            project_dir: analysis.resolved_project_dir,
+            remote_dir: cli_args.remote_dir.clone(),
+            compose_files: analysis.resolved_compose_files,
+            env_files: analysis.resolved_env_files,
+            consumed_env: analysis.consumed_env,
+            local_references: analysis
+                .local_references
+                .iter()
+                .map(PathBuf::from)
+                .collect(),
+            file_owner: self.chown.clone(),
+            age_identity: project_config.age_identity,
+            env_template: project_config
+                .env_template
+                .map(|template| project_dir.join(template)),
+            env_edits: self.env_edits,
+            sync_concurrency: sync_concurrency(cli_args, project_config.sync_concurrency),
+            secrets: project_config.secrets,
+            show_diff: self.diff,
+            include_hidden: self.include_hidden,
+            ..Default::default()
+        };
+        let mut deployer = Deployer::new(deploy_config, &mut executor, progress_sender);
+        let sync_result = deployer.sync_only().await;
+        drop(deployer);
+
+        if let Some((ui_task, pb)) = ui_update_task_handle {
+            if let Err(e) = ui_task.await {
+                tracing::error!("UI update task failed: {}", e);
+            }
+            match &sync_result {
+                Ok(_) => pb.finish_with_message("✅ Files synchronized."),
+                Err(_) => pb.finish_with_message("❌ File sync failed.".to_string()),
+            }
+        }
+
+        let status = sync_result
+            .map_err(|e| CliError::OperationFailed(format!("File sync failed: {}", e)))?;
+        if status.synced_files.is_empty() {
+            info!("All project files were already up to date.");
+        } else {
+            info!("Updated {} file(s):", status.synced_files.len());
+            for path in &status.synced_files {
+                let shown = path.strip_prefix(&project_dir).unwrap_or(path);
+                info!("  {}", shown.display());
+            }
+        }
+        if status.env_changed {
+            info!("Wrote {}.", DCD_ENV_FILE);
+        }
+        info!(
+            "{}",
+            ui::format_success("Sync complete; running services were not restarted.")
+        );
+        Ok(())
+    }
+}
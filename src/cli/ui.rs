@@ -1,54 +1,423 @@
+use super::error::CliError;
+use crate::deployer::dry_run::{DryRunReport, ProjectDiff};
+use crate::deployer::sync::{looks_secret, DiffLine, FileChange, FileDiff};
 use crate::deployer::types::DeployerEvent;
+use crate::executor::recording::EchoEvent;
 use colored::*;
-use indicatif::{ProgressBar, ProgressStyle};
+use dialoguer::{Confirm, Input, Password};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle, WeakProgressBar};
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 use std::time::Duration;
 use tokio::sync::mpsc;
+use tracing::info;
 
-/// Creates a standard spinner ProgressBar.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Template of the step spinner
+const SPINNER_TEMPLATE: &str = "{spinner:.blue.bold} {msg}";
+
+/// Spinners shown so far, to print command echoes above them
+static SPINNERS: Mutex<Vec<WeakProgressBar>> = Mutex::new(Vec::new());
+
+/// Hide all spinners for this run (`--quiet`).
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Whether stdout is a terminal. In CI and pipes there is nobody to answer
+/// prompts and spinners only produce noise, so progress is logged line by line.
+pub fn is_interactive() -> bool {
+    std::io::stdout().is_terminal()
+}
+
+/// Creates a standard spinner ProgressBar. When not attached to a terminal
+/// the spinner is hidden and the message is logged instead.
 pub fn create_spinner(message: &str) -> ProgressBar {
+    if QUIET.load(Ordering::Relaxed) {
+        return ProgressBar::hidden();
+    }
+    if !is_interactive() {
+        info!("{}", message);
+        return ProgressBar::hidden();
+    }
     let pb = ProgressBar::new_spinner();
-    pb.set_style(
-        ProgressStyle::with_template("{spinner:.blue.bold} {msg}")
-            .unwrap()
-            // More templates: https://docs.rs/indicatif/#templates
-            .tick_strings(&[
-                "▹▹▹▹▹",
-                "▸▹▹▹▹",
-                "▹▸▹▹▹",
-                "▹▹▸▹▹",
-                "▹▹▹▸▹",
-                "▹▹▹▹▸",
-                "▪▪▪▪▪",
-            ]),
-    );
+    pb.set_style(spinner_style(SPINNER_TEMPLATE));
     pb.set_message(message.to_string());
     pb.enable_steady_tick(Duration::from_millis(120));
+    if let Ok(mut spinners) = SPINNERS.lock() {
+        spinners.retain(|spinner| spinner.upgrade().is_some_and(|pb| !pb.is_finished()));
+        spinners.push(pb.downgrade());
+    }
+    pb
+}
+
+/// Print a remote command on stderr as it starts and ends (`--show-commands`),
+/// above the spinner if one is running.
+pub fn print_command_echo(event: &EchoEvent<'_>) {
+    let line = match event {
+        EchoEvent::Started { command } => format!("{} {}", "$".dimmed(), command),
+        EchoEvent::Finished {
+            command,
+            exit_code,
+            duration,
+        } => {
+            let status = match exit_code {
+                Some(0) => "exit 0".green(),
+                Some(code) => format!("exit {}", code).red(),
+                None => "not run".red(),
+            };
+            format!(
+                "{} {} {}",
+                "=>".dimmed(),
+                status,
+                format!("in {:.2?}: {}", duration, command).dimmed()
+            )
+        }
+    };
+    let spinner = SPINNERS.lock().ok().and_then(|spinners| {
+        spinners
+            .iter()
+            .rev()
+            .filter_map(WeakProgressBar::upgrade)
+            .find(|pb| !pb.is_finished())
+    });
+    match spinner {
+        Some(pb) => pb.suspend(|| eprintln!("{}", line)),
+        None => eprintln!("{}", line),
+    }
+}
+
+/// Spinner for one of several hosts handled at once, stacked in `multi` and
+/// labelled with the host.
+pub fn create_host_spinner(multi: &MultiProgress, host: &str, message: &str) -> ProgressBar {
+    let pb = create_spinner(message);
+    if pb.is_hidden() {
+        return pb;
+    }
+    let pb = multi.add(pb);
+    pb.set_style(spinner_style("{spinner:.blue.bold} {prefix:.cyan} {msg}"));
+    pb.set_prefix(host.to_string());
     pb
 }
 
+fn spinner_style(template: &str) -> ProgressStyle {
+    ProgressStyle::with_template(template)
+        .unwrap()
+        // More templates: https://docs.rs/indicatif/#templates
+        .tick_strings(&[
+            "▹▹▹▹▹",
+            "▸▹▹▹▹",
+            "▹▸▹▹▹",
+            "▹▹▸▹▹",
+            "▹▹▹▸▹",
+            "▹▹▹▹▸",
+            "▪▪▪▪▪",
+        ])
+}
+
 /// Handles receiving DeployerEvents and updating a ProgressBar.
+/// A hidden ProgressBar (non-interactive mode) gets one log line per event.
 pub async fn handle_deployer_events(mut receiver: mpsc::Receiver<DeployerEvent>, pb: ProgressBar) {
+    let plain = pb.is_hidden();
+    let show = |msg: String| {
+        if plain {
+            info!("{}", msg);
+        } else {
+            pb.set_message(msg);
+        }
+    };
+    // The running step, shown with the compose output it produces
+    let mut step = String::new();
+    let mut last_pull = (0, 0);
+    // File being uploaded and the quarter of it logged last
+    let mut last_upload = (std::path::PathBuf::new(), u64::MAX);
+    // Whether the spinner currently shows an upload progress bar
+    let mut uploading = false;
     while let Some(event) = receiver.recv().await {
+        if uploading && !matches!(event, DeployerEvent::UploadProgress(..)) {
+            pb.set_style(spinner_style(SPINNER_TEMPLATE));
+            uploading = false;
+        }
         match event {
             DeployerEvent::StepStarted(msg) => {
-                pb.set_message(format!("⏳ {}", msg));
+                show(format!("⏳ {}", msg));
+                step = msg;
             }
             DeployerEvent::StepCompleted(msg) => {
-                pb.set_message(format!("✅ {}", msg));
-                tokio::time::sleep(Duration::from_millis(150)).await;
+                show(format!("✅ {}", msg));
+                if !plain {
+                    tokio::time::sleep(Duration::from_millis(150)).await;
+                }
             }
             DeployerEvent::StepFailed(step, err) => {
-                pb.set_message(format!("❌ Error during {}: {}", step, err));
+                show(format!("❌ Error during {}: {}", step, err));
                 break;
             }
             DeployerEvent::HealthCheckAttempt(attempt, total) => {
-                pb.set_message(format!("🩺 Health check ({}/{})", attempt, total));
+                show(format!("🩺 Health check ({}/{})", attempt, total));
+            }
+            DeployerEvent::StepProgress(msg, current, total) => {
+                show(format!("⏳ {} ({}/{})", msg, current, total));
             }
             DeployerEvent::HealthCheckStatus(status_msg) => {
-                pb.set_message(format!("🩺 {}", status_msg));
+                show(format!("🩺 {}", status_msg));
+            }
+            DeployerEvent::FileDiff(diff) => {
+                if !QUIET.load(Ordering::Relaxed) {
+                    pb.suspend(|| print_file_diff(&diff));
+                }
+            }
+            DeployerEvent::PullProgress(current, total, percent) => {
+                // One log line per image rather than per downloaded chunk
+                if plain && last_pull == (current, total) {
+                    continue;
+                }
+                last_pull = (current, total);
+                show(match percent {
+                    Some(percent) => {
+                        format!("⏳ Pulling image {}/{} ({}%)", current, total, percent)
+                    }
+                    None => format!("⏳ Pulling image {}/{}", current, total),
+                });
+            }
+            DeployerEvent::ComposeOutput(line) => {
+                if plain {
+                    show(line);
+                } else {
+                    show(format!("⏳ {}: {}", step, line));
+                }
+            }
+            DeployerEvent::UploadProgress(path, sent, total) => {
+                let done = sent >= total;
+                if plain {
+                    // One log line per quarter of the file
+                    let quarter = sent * 4 / total.max(1);
+                    if last_upload.0 == path && last_upload.1 == quarter {
+                        continue;
+                    }
+                    show(format!(
+                        "⏳ Uploading {} ({}%)",
+                        path.display(),
+                        quarter * 25
+                    ));
+                    last_upload = (path, quarter);
+                } else if done {
+                    pb.set_style(spinner_style(SPINNER_TEMPLATE));
+                    uploading = false;
+                } else {
+                    if !uploading {
+                        pb.set_style(upload_style());
+                        pb.set_message(format!("⏳ Uploading {}", path.display()));
+                        uploading = true;
+                    }
+                    pb.set_length(total);
+                    pb.set_position(sent);
+                }
+            }
+        }
+    }
+}
+
+/// Byte progress bar replacing the spinner while a large file uploads.
+fn upload_style() -> ProgressStyle {
+    ProgressStyle::with_template(
+        "{msg} [{bar:30.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})",
+    )
+    .unwrap()
+    .progress_chars("=> ")
+}
+
+/// Print the file diffs among deployer events when there is no progress UI
+/// to handle them (`--diff --no-progress`).
+pub async fn print_file_diffs(mut receiver: mpsc::Receiver<DeployerEvent>) {
+    while let Some(event) = receiver.recv().await {
+        if let DeployerEvent::FileDiff(diff) = event {
+            if !QUIET.load(Ordering::Relaxed) {
+                print_file_diff(&diff);
+            }
+        }
+    }
+}
+
+/// Print a diff of a file about to be synced, colored like `git diff`.
+fn print_file_diff(diff: &FileDiff) {
+    let old = format!("{} (remote)", diff.remote_path.display());
+    let new = format!("{} (local)", diff.local_path.display());
+    if diff.lines.is_empty() {
+        print_diff(
+            &old,
+            &new,
+            &[DiffLine::Hunk(
+                "(too large to compare line by line)".to_string(),
+            )],
+        );
+    } else {
+        print_diff(&old, &new, &diff.lines);
+    }
+}
+
+/// Print the diff `lines` from `old` to `new`, colored like `git diff`.
+fn print_diff(old: &str, new: &str, lines: &[DiffLine]) {
+    println!("{}", format!("--- {}", old).bold());
+    println!("{}", format!("+++ {}", new).bold());
+    for line in lines {
+        match line {
+            DiffLine::Hunk(text) => println!("{}", text.cyan()),
+            DiffLine::Context(text) => println!(" {}", text),
+            DiffLine::Removed(text) => println!("{}", format!("-{}", text).red()),
+            DiffLine::Added(text) => println!("{}", format!("+{}", text).green()),
+        }
+    }
+}
+
+/// Print what a dry run found: uploads, firewall rules and remote commands.
+pub fn print_dry_run(target: &str, report: &DryRunReport) {
+    println!(
+        "\n{}",
+        format_header(&format!("Dry run on {} (nothing was changed):", target))
+    );
+    if !report.files.is_empty() {
+        println!(
+            "\n{}",
+            format_header(&format!("Files in {}:", report.remote_dir.display()))
+        );
+        for file in &report.files {
+            let path = file
+                .remote_path
+                .strip_prefix(&report.remote_dir)
+                .unwrap_or(&file.remote_path)
+                .display();
+            match file.change {
+                FileChange::New => println!("  {} {} (new)", "+".green(), path),
+                FileChange::Changed => println!("  {} {} (changed)", "~".yellow(), path),
+                FileChange::Unchanged => {}
             }
         }
+        let unchanged = report.files.len() - report.uploads();
+        if unchanged > 0 {
+            println!("  {} unchanged file(s) are skipped", unchanged);
+        }
+    }
+    println!("\n{}", format_header("Firewall rules to add:"));
+    if report.firewall_rules.is_empty() {
+        println!("  {}", format_warning("(None)"));
+    }
+    for rule in &report.firewall_rules {
+        println!("  {}", rule);
+    }
+    println!("\n{}", format_header("Remote commands:"));
+    if report.commands.is_empty() {
+        println!("  {}", format_warning("(None)"));
+    }
+    for command in &report.commands {
+        println!("  {}", command);
+    }
+}
+
+/// Print how the local project differs from its deployment: files to
+/// upload, diffs of the compose and env files and of the compose config.
+pub fn print_project_diff(target: &str, diff: &ProjectDiff) {
+    println!(
+        "\n{}",
+        format_header(&format!(
+            "Files to upload to {} on {}:",
+            diff.remote_dir.display(),
+            target
+        ))
+    );
+    let mut uploads = 0;
+    for file in diff.uploads() {
+        uploads += 1;
+        let path = file
+            .remote_path
+            .strip_prefix(&diff.remote_dir)
+            .unwrap_or(&file.remote_path)
+            .display();
+        match file.change {
+            FileChange::New => println!("  {} {} (new)", "+".green(), path),
+            _ => println!("  {} {} (changed)", "~".yellow(), path),
+        }
+    }
+    if uploads == 0 {
+        println!("  {}", format_warning("(None)"));
+    }
+    for file_diff in &diff.file_diffs {
+        println!();
+        print_file_diff(file_diff);
+    }
+    println!("\n{}", format_header("Compose config:"));
+    match &diff.config {
+        None => println!(
+            "  {}",
+            format_warning("(not compared: nothing deployed, or it could not be rendered)")
+        ),
+        Some(lines) if lines.is_empty() => println!("  unchanged"),
+        Some(lines) => print_diff(
+            "docker compose config (deployed)",
+            "docker compose config (local)",
+            lines,
+        ),
+    }
+}
+
+/// Ask a yes/no question. Without a terminal to answer on, fail and point at
+/// `skip_flag` instead of waiting for input that can never arrive.
+pub fn confirm(prompt: &str, skip_flag: &str) -> Result<bool, CliError> {
+    if !is_interactive() {
+        return Err(CliError::OperationFailed(format!(
+            "Confirmation required but not running in a terminal; pass {} to proceed",
+            skip_flag
+        )));
+    }
+    Confirm::new()
+        .with_prompt(prompt)
+        .interact()
+        .map_err(|e| CliError::OperationFailed(format!("Failed to get confirmation: {}", e)))
+}
+
+/// Ask for the value of each missing variable and export it into this process,
+/// where analysis picks it up like any other shell variable (and from there
+/// into the env uploaded for this deployment). Secret-looking names use hidden input.
+pub fn prompt_missing_variables(names: &[String]) -> Result<(), CliError> {
+    if !is_interactive() {
+        return Err(CliError::OperationFailed(format!(
+            "Required variable(s) missing and not running in a terminal to ask for them: {}",
+            names.join(", ")
+        )));
+    }
+    for name in names {
+        let value = if looks_secret(name) {
+            Password::new()
+                .with_prompt(format!("{} (hidden)", name))
+                .allow_empty_password(true)
+                .interact()
+        } else {
+            Input::<String>::new()
+                .with_prompt(name.as_str())
+                .allow_empty(true)
+                .interact_text()
+        }
+        .map_err(|e| CliError::OperationFailed(format!("Failed to read {}: {}", name, e)))?;
+        std::env::set_var(name, value);
+    }
+    Ok(())
+}
+
+/// Password for SSH authentication as `login`: `DCD_SSH_PASSWORD` if set,
+/// otherwise asked for when running in a terminal.
+pub fn ssh_password(login: &str) -> Option<String> {
+    if let Ok(password) = std::env::var("DCD_SSH_PASSWORD") {
+        return Some(password);
+    }
+    if !is_interactive() {
+        return None;
     }
+    Password::new()
+        .with_prompt(format!("Password for {}", login))
+        .interact()
+        .ok()
 }
 
 pub fn format_header(text: &str) -> String {
@@ -1,111 +1,557 @@
-use super::common::{get_analysis, parse_ssh_target, print_analysis_results};
+use super::common::{
+    composer_in, direct_ssh, get_analysis, parse_ssh_target, print_analysis_results,
+    sync_concurrency,
+};
 use super::error::CliError;
+use super::hosts::{on_each_host, report_hosts, resolve_targets};
 use super::parser::Cli;
 use super::ui;
 use super::ui::handle_deployer_events;
-use crate::deployer::{types::DeploymentConfig, Deployer};
-use crate::executor::SshCommandExecutor;
+use crate::composer::config::duration::parse_duration;
+use crate::composer::{errors::ComposerError, types::ComposerOutput};
+use crate::config::{
+    is_valid_stack_name, parse_hook_arg, Hook, HookStage, Hooks, ProjectConfig, StackConfig,
+};
+use crate::deployer::{
+    dry_run::DryRunReport,
+    report::{DeployReport, ReportFormat},
+    restart::{validate_restart_policy, DEFAULT_RESTART_POLICY},
+    types::{DeployStage, DeploymentConfig, DeploymentStatus, EnvEditPolicy, HealthCheckSettings},
+    Deployer,
+};
 use clap::Args;
-use std::path::PathBuf;
-use std::time::Duration;
+use indicatif::{MultiProgress, ProgressBar};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 use tokio::sync::mpsc;
-use tracing::{debug, info, instrument};
+use tracing::{debug, info, instrument, warn, Instrument};
 
 #[derive(Debug, Args)]
 pub struct Up {
-    /// Remote target in the format [user@]host[:port]
-    #[arg(required = true)]
-    target: String,
+    /// Remote target(s) in the format [user@]host[:port]
+    #[arg(value_name = "TARGET", required_unless_present = "hosts_file")]
+    targets: Vec<String>,
+
+    /// File listing more targets, one per line (`#` starts a comment)
+    #[arg(long, value_name = "PATH")]
+    hosts_file: Option<PathBuf>,
 
     /// Don't verify service health after deployment
     #[arg(long)]
     no_health_check: bool,
 
+    /// How long services may stay in the starting state (e.g. 5m; default 150s)
+    #[arg(long, value_name = "DURATION", value_parser = parse_health_duration)]
+    health_timeout: Option<Duration>,
+
+    /// Health check attempts before unhealthy services fail the deployment (default 5)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    health_retries: Option<u32>,
+
+    /// Pause between health check attempts (e.g. 5s; default 10s)
+    #[arg(long, value_name = "DURATION", value_parser = parse_health_duration)]
+    health_interval: Option<Duration>,
+
+    /// Check health again as soon as a container starts, dies or changes
+    /// health (watching Docker events) rather than only every interval
+    #[arg(long)]
+    health_watch: bool,
+
     /// Disable interactive progress spinner and show only logs
     #[arg(long)]
     pub no_progress: bool,
+
+    /// Change ownership of synced files on the remote (user[:group] or uid[:gid])
+    #[arg(long, value_name = "OWNER")]
+    chown: Option<String>,
+
+    /// Install a systemd unit that starts the stack on boot and stops it on shutdown
+    #[arg(long)]
+    systemd: bool,
+
+    /// Ask for required variables that are not set instead of failing
+    #[arg(long)]
+    prompt_missing: bool,
+
+    /// Install the NVIDIA container toolkit on supported distros if GPU services need it
+    #[arg(long)]
+    install_gpu_toolkit: bool,
+
+    /// Give services without a restart policy this one (default unless-stopped) via an override file
+    #[arg(
+        long,
+        value_name = "POLICY",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = DEFAULT_RESTART_POLICY,
+        value_parser = parse_restart_policy
+    )]
+    enforce_restart_policy: Option<String>,
+
+    /// Show a diff of each compose and env file the deploy overwrites (values redacted)
+    #[arg(long)]
+    diff: bool,
+
+    /// Also sync hidden files and directories (dotfiles) of referenced
+    /// directories; .git, node_modules and entries matched by .dcdignore or
+    /// .dockerignore are still skipped
+    #[arg(long)]
+    pub include_hidden: bool,
+
+    /// Regenerate the remote .env from `env_template` in dcd.toml, discarding
+    /// edits made to it on the server (a backup is kept until the deploy succeeds)
+    #[arg(long)]
+    pub force_env: bool,
+
+    /// What to do with env files edited on the server since the last deploy:
+    /// abort and report them, merge the edits that do not conflict with local
+    /// changes, or overwrite them
+    #[arg(long, value_name = "POLICY", default_value = "abort")]
+    pub env_edits: EnvEditPolicy,
+
+    /// Rebuild images of services with a `build:` section on the server from
+    /// scratch: pull newer base images and skip the build cache
+    #[arg(long)]
+    pub build: bool,
+
+    /// Run only these stages (comma-separated: env, sync, firewall, pull, up, health)
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_name = "STAGES",
+        conflicts_with = "skip"
+    )]
+    only: Vec<DeployStage>,
+
+    /// Skip these stages (comma-separated: env, sync, firewall, pull, up, health)
+    #[arg(long, value_delimiter = ',', value_name = "STAGES")]
+    skip: Vec<DeployStage>,
+
+    /// Run COMMAND on the server at STAGE (pre-sync, post-sync, pre-up, post-up, post-healthy); repeatable, after the dcd.toml hooks
+    #[arg(long = "hook", value_name = "STAGE=COMMAND", value_parser = parse_hook_arg)]
+    hooks: Vec<(HookStage, String)>,
+
+    /// Like --hook, but run COMMAND on this machine in the project directory
+    #[arg(long = "local-hook", value_name = "STAGE=COMMAND", value_parser = parse_hook_arg)]
+    local_hooks: Vec<(HookStage, String)>,
+
+    /// Pull, start and check only this service (repeatable; default all)
+    #[arg(long = "service", value_name = "SERVICE")]
+    services: Vec<String>,
+
+    /// Deploy this stack of a monorepo instead of the project in the current directory: a name from `[stacks]` in dcd.toml or a subdirectory (repeatable, deployed in order)
+    #[arg(long = "stack", value_name = "STACK")]
+    pub stacks: Vec<String>,
+
+    /// Show the files, firewall rules and compose commands a deploy would change, without changing anything
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Write a report of the deployment (synced files, variable names, opened ports, images, health, stage durations) to FILE as JSON, or YAML for .yml/.yaml; with several hosts or stacks their names are added to the file name
+    #[arg(long, value_name = "FILE", conflicts_with = "dry_run")]
+    report_path: Option<PathBuf>,
+
+    /// Also keep the report as .dcd-report.json in the remote project directory
+    #[arg(long, conflicts_with = "dry_run")]
+    remote_report: bool,
 }
 
 impl Up {
-    #[instrument(name = "up", skip(self, cli_args), fields(target = %self.target))]
+    #[instrument(name = "up", skip(self, cli_args), fields(targets = ?self.targets))]
     pub async fn run(&self, cli_args: &Cli) -> Result<(), CliError> {
-        let target = parse_ssh_target(&self.target)?;
+        let targets = self.targets()?;
         info!(
             "Deploying services to {}",
-            ui::format_highlight(&self.target)
+            ui::format_highlight(&targets.join(", "))
         );
-        debug!(user = %target.user, port = %target.port, key = ?cli_args.identity_file, "SSH details");
+
+        if !self.stacks.is_empty() {
+            return self.deploy_stacks(cli_args, &targets).await;
+        }
 
         // --- Local Analysis ---
-        let analysis_pb = ui::create_spinner("Performing local analysis...");
-        let analysis = get_analysis(cli_args).await.map_err(|e| {
+        let analysis = self.analyze(cli_args, None).await?;
+        self.deploy(cli_args, &targets, analysis).await
+    }
+
+    /// Analyse the project in the current directory, or the one of `stack`.
+    async fn analyze(
+        &self,
+        cli_args: &Cli,
+        stack: Option<&Stack>,
+    ) -> Result<ComposerOutput, CliError> {
+        let analysis_pb = ui::create_spinner(&match stack {
+            Some(stack) => format!("Analysing stack {}...", stack.name),
+            None => "Performing local analysis...".to_string(),
+        });
+        let analyze = || async {
+            match stack {
+                Some(stack) => stack.analyze().await,
+                None => get_analysis(cli_args).await,
+            }
+        };
+        let analysis = match analyze().await {
+            Err(ComposerError::MissingEnvVars(names)) if self.prompt_missing => {
+                analysis_pb.finish_and_clear();
+                ui::prompt_missing_variables(&names)?;
+                analyze().await
+            }
+            result => result,
+        }
+        .map_err(|e| {
             analysis_pb.finish_and_clear(); // Clear spinner on error
             CliError::OperationFailed(format!("Local analysis failed: {}", e))
         })?;
         analysis_pb.finish_with_message("Local analysis complete.");
-        print_analysis_results(&analysis); // Keep this direct output for now
+        Ok(analysis)
+    }
 
-        // --- SSH Connection ---
-        let ssh_pb = ui::create_spinner(&format!(
-            "Connecting to {}...",
-            ui::format_highlight(&target.host)
-        ));
-        let addr_str = format!("{}:{}", target.host, target.port);
-        let mut executor = SshCommandExecutor::connect(
-            cli_args.identity_file.as_ref(),
-            &target.user,
-            &addr_str,
-            Duration::from_secs(30), // TODO: Make timeout configurable
-            cli_args.no_warnings,
+    /// Stacks selected with --stack, looked up in dcd.toml first.
+    fn resolve_stacks(&self) -> Result<Vec<Stack>, CliError> {
+        let configured = ProjectConfig::load(Path::new("."))
+            .map_err(|e| CliError::ConfigError(e.to_string()))?
+            .stacks;
+        let mut stacks: Vec<Stack> = Vec::new();
+        for arg in &self.stacks {
+            let stack = match configured.get(arg) {
+                Some(config) => Stack {
+                    name: arg.clone(),
+                    dir: config.dir(arg),
+                    config: config.clone(),
+                },
+                None if Path::new(arg).is_dir() => Stack {
+                    name: stack_name(Path::new(arg))?,
+                    dir: PathBuf::from(arg),
+                    config: StackConfig::default(),
+                },
+                None => {
+                    return Err(CliError::ConfigError(format!(
+                        "Unknown stack '{}': neither in [stacks] of dcd.toml nor a directory",
+                        arg
+                    )))
+                }
+            };
+            if !stack.dir.is_dir() {
+                return Err(CliError::ConfigError(format!(
+                    "Directory {} of stack '{}' does not exist",
+                    stack.dir.display(),
+                    stack.name
+                )));
+            }
+            if stacks.iter().any(|other| other.name == stack.name) {
+                return Err(CliError::ConfigError(format!(
+                    "Stack '{}' is selected twice",
+                    stack.name
+                )));
+            }
+            stacks.push(stack);
+        }
+        Ok(stacks)
+    }
+
+    /// Deploy each stack to `targets`, one stack after the other, and report
+    /// the outcome of every stack on every host together.
+    async fn deploy_stacks(&self, cli_args: &Cli, targets: &[String]) -> Result<(), CliError> {
+        let stacks = self.resolve_stacks()?;
+        let mut configs = Vec::new();
+        for stack in &stacks {
+            let config = async {
+                let analysis = self.analyze(cli_args, Some(stack)).await?;
+                let mut config = self.prepare(cli_args, analysis)?;
+                config.remote_dir = stack.remote_dir(cli_args.remote_dir.as_deref());
+                Ok(config)
+            }
+            .await;
+            configs.push((stack.name.as_str(), config));
+        }
+
+        if self.dry_run {
+            let mut results = Vec::new();
+            for (stack, config) in configs {
+                match config {
+                    Ok(config) => results.extend(label_stack(
+                        stack,
+                        self.dry_run_hosts(cli_args, targets, config).await,
+                    )),
+                    Err(e) => results.push((format!("[{}]", stack), Err(e))),
+                }
+            }
+            if !cli_args.json() && !cli_args.quiet {
+                for (target, result) in &results {
+                    if let Ok(report) = result {
+                        ui::print_dry_run(target, report);
+                    }
+                }
+            }
+            return report_hosts("Dry run", &results, |report| Ok(report.summary()), cli_args);
+        }
+
+        let multi = MultiProgress::new();
+        let mut results = Vec::new();
+        for (stack, config) in configs {
+            let config = match config {
+                Ok(config) => config,
+                Err(e) => {
+                    results.push((format!("[{}]", stack), Err(e)));
+                    continue;
+                }
+            };
+            info!("Deploying stack {}", ui::format_highlight(stack));
+            let stack_results = on_each_host(cli_args, targets, |target| {
+                let config = config.clone();
+                let multi = &multi;
+                async move {
+                    let report_path = self.report_file(&target, targets.len() > 1, Some(stack));
+                    self.deploy_host(cli_args, &target, config, Some(multi), report_path)
+                        .await
+                }
+            })
+            .await;
+            results.extend(label_stack(stack, stack_results));
+        }
+        self.report_hosts(cli_args, &results)
+    }
+
+    /// Targets given on the command line and in --hosts-file.
+    pub(super) fn targets(&self) -> Result<Vec<String>, CliError> {
+        resolve_targets(&self.targets, self.hosts_file.as_deref())
+    }
+
+    /// Deploy the analysed project to `targets`.
+    pub(super) async fn deploy(
+        &self,
+        cli_args: &Cli,
+        targets: &[String],
+        analysis: ComposerOutput,
+    ) -> Result<(), CliError> {
+        let deploy_config = self.prepare(cli_args, analysis)?;
+        if self.dry_run {
+            return self.dry_run(cli_args, targets, deploy_config).await;
+        }
+
+        if let ([target], false) = (targets, cli_args.json()) {
+            let report_path = self.report_file(target, false, None);
+            let status = self
+                .deploy_host(cli_args, target, deploy_config, None, report_path)
+                .await?;
+            return self.report(&status);
+        }
+
+        // --- Several hosts at once (or JSON output), one spinner each ---
+        let multi = MultiProgress::new();
+        let results = on_each_host(cli_args, targets, |target| {
+            let config = deploy_config.clone();
+            let multi = &multi;
+            async move {
+                let report_path = self.report_file(&target, targets.len() > 1, None);
+                self.deploy_host(cli_args, &target, config, Some(multi), report_path)
+                    .await
+            }
+        })
+        .await;
+        self.report_hosts(cli_args, &results)
+    }
+
+    /// Summary of a deployment to several hosts (or stacks).
+    fn report_hosts(
+        &self,
+        cli_args: &Cli,
+        results: &[(String, Result<DeploymentStatus, CliError>)],
+    ) -> Result<(), CliError> {
+        let health_checked = !self.skipped_stages().contains(&DeployStage::Health);
+        report_hosts(
+            "Deployment",
+            results,
+            |status| {
+                if !status.services_healthy && health_checked {
+                    Err("services not healthy".to_string())
+                } else {
+                    Ok(summary_details(status))
+                }
+            },
+            cli_args,
         )
+    }
+
+    /// Show the analysis and turn it into the deployment configuration.
+    fn prepare(
+        &self,
+        cli_args: &Cli,
+        analysis: ComposerOutput,
+    ) -> Result<DeploymentConfig, CliError> {
+        if !cli_args.quiet && !cli_args.json() {
+            print_analysis_results(&analysis); // Keep this direct output for now
+        }
+        if let Some(unknown) = self
+            .services
+            .iter()
+            .find(|service| !analysis.services.contains(service))
+        {
+            return Err(CliError::ConfigError(format!(
+                "Unknown service '{}'. Available services: {}",
+                unknown,
+                analysis.services.join(", ")
+            )));
+        }
+        let project_config = ProjectConfig::load(&analysis.resolved_project_dir)
+            .map_err(|e| CliError::ConfigError(e.to_string()))?;
+        Ok(self.deploy_config(cli_args, analysis, project_config))
+    }
+
+    /// Report what deploying to `targets` would change on each of them.
+    async fn dry_run(
+        &self,
+        cli_args: &Cli,
+        targets: &[String],
+        deploy_config: DeploymentConfig,
+    ) -> Result<(), CliError> {
+        let mut results = self.dry_run_hosts(cli_args, targets, deploy_config).await;
+        if !cli_args.json() && !cli_args.quiet {
+            for (target, result) in &results {
+                if let Ok(report) = result {
+                    ui::print_dry_run(target, report);
+                }
+            }
+        }
+        if let ([_], false) = (targets, cli_args.json()) {
+            return results
+                .pop()
+                .map_or(Ok(()), |(_, result)| result.map(|_| ()));
+        }
+        report_hosts("Dry run", &results, |report| Ok(report.summary()), cli_args)
+    }
+
+    /// What deploying `deploy_config` would change on each of `targets`.
+    async fn dry_run_hosts(
+        &self,
+        cli_args: &Cli,
+        targets: &[String],
+        deploy_config: DeploymentConfig,
+    ) -> Vec<(String, Result<DryRunReport, CliError>)> {
+        on_each_host(cli_args, targets, |target| {
+            let config = deploy_config.clone();
+            async move { self.dry_run_host(cli_args, &target, config).await }
+        })
         .await
-        .map_err(|e| {
-            ssh_pb.finish_and_clear();
+    }
+
+    /// Connect to one host and find out what deploying there would change.
+    async fn dry_run_host(
+        &self,
+        cli_args: &Cli,
+        target_str: &str,
+        mut deploy_config: DeploymentConfig,
+    ) -> Result<DryRunReport, CliError> {
+        let target = parse_ssh_target(target_str)?;
+        if target.local {
+            deploy_on_local_machine(&mut deploy_config);
+        }
+        info!(
+            "Checking what would change on {}...",
+            ui::format_highlight(&target.host)
+        );
+        let mut executor = direct_ssh(cli_args, &target)
+            .await
+            .map_err(|e| CliError::OperationFailed(format!("SSH connection failed: {}", e)))?;
+        Deployer::new(deploy_config, &mut executor, None)
+            .plan_deploy()
+            .await
+            .map_err(|e| CliError::OperationFailed(format!("Dry run failed: {}", e)))
+    }
+
+    /// Connect to one host and run the deployment there. With `multi` the
+    /// host gets a single labelled spinner among the others.
+    async fn deploy_host(
+        &self,
+        cli_args: &Cli,
+        target_str: &str,
+        mut deploy_config: DeploymentConfig,
+        multi: Option<&MultiProgress>,
+        report_path: Option<PathBuf>,
+    ) -> Result<DeploymentStatus, CliError> {
+        let target = parse_ssh_target(target_str)?;
+        debug!(user = %target.user, port = %target.port, key = ?cli_args.identity_file, "SSH details");
+        if target.local {
+            // The ports are this machine's own business
+            info!("Deploying on this machine; skipping firewall configuration");
+            deploy_on_local_machine(&mut deploy_config);
+        }
+
+        // --- SSH Connection ---
+        let connecting = format!("Connecting to {}...", ui::format_highlight(&target.host));
+        let ssh_pb = match multi {
+            Some(multi) => ui::create_host_spinner(multi, target_str, &connecting),
+            None => ui::create_spinner(&connecting),
+        };
+        let mut executor = direct_ssh(cli_args, &target).await.map_err(|e| {
+            match multi {
+                Some(_) => ssh_pb.finish_with_message("❌ SSH connection failed"),
+                None => ssh_pb.finish_and_clear(),
+            }
             CliError::OperationFailed(format!("SSH connection failed: {}", e))
         })?;
-        ssh_pb.finish_with_message(format!(
-            "Connected to {}.",
-            ui::format_highlight(&target.host)
-        ));
 
         // --- Deployment ---
         let (progress_sender, ui_update_task_handle) = if !self.no_progress {
             // Create a channel for progress updates
             let (sender, receiver) = mpsc::channel::<crate::deployer::types::DeployerEvent>(32); // Buffer size 32
-            let deploy_pb = ui::create_spinner("Initializing deployment..."); // Initial message
+            let deploy_pb = if multi.is_some() {
+                // Keep using the host's spinner
+                ssh_pb.set_message("Initializing deployment...");
+                ssh_pb.clone()
+            } else {
+                ssh_pb.finish_with_message(format!(
+                    "Connected to {}.",
+                    ui::format_highlight(&target.host)
+                ));
+                ui::create_spinner("Initializing deployment...")
+            };
 
             // Spawn a task to listen for progress events and update the UI
             // Clone the ProgressBar for the task.
-            let ui_task = tokio::spawn(handle_deployer_events(receiver, deploy_pb.clone()));
+            let ui_task =
+                tokio::spawn(handle_deployer_events(receiver, deploy_pb.clone()).in_current_span());
 
             // Return the sender and the task handle (wrapped in Some)
             (Some(sender), Some((ui_task, deploy_pb))) // Store pb too
         } else {
+            ssh_pb.finish_with_message(format!(
+                "Connected to {}.",
+                ui::format_highlight(&target.host)
+            ));
             info!("Progress spinner disabled via --no-progress.");
-            // No progress UI needed
-            (None, None)
-        };
-        let deploy_config = DeploymentConfig {
-            project_dir: analysis.resolved_project_dir.clone(),
-            remote_dir: cli_args.remote_dir.clone(),
-            compose_files: analysis.resolved_compose_files.clone(),
-            env_files: analysis.resolved_env_files.clone(),
-            consumed_env: analysis.consumed_env,
-            exposed_ports: analysis.exposed_ports,
-            local_references: analysis
-                .local_references
-                .iter()
-                .map(PathBuf::from)
-                .collect(),
-            volumes: analysis.volumes,
+            if self.diff {
+                // Diffs are still printed without the spinner
+                let (sender, receiver) = mpsc::channel(32);
+                let task = tokio::spawn(ui::print_file_diffs(receiver).in_current_span());
+                (Some(sender), Some((task, ProgressBar::hidden())))
+            } else {
+                // No progress UI needed
+                (None, None)
+            }
         };
 
         // Instantiate Deployer, passing the sender end of the channel
         let mut deployer = Deployer::new(deploy_config, &mut executor, progress_sender);
 
         // Deploy with progress reporting
+        let started = SystemTime::now();
         let deploy_result = deployer.deploy().await;
 
+        let report = (report_path.is_some() || self.remote_report).then(|| {
+            deployer.report(
+                target_str,
+                started,
+                deploy_result.as_ref().map_err(|e| e.to_string()),
+            )
+        });
+        if let (Some(report), true) = (&report, self.remote_report) {
+            if let Err(e) = deployer.upload_report(report).await {
+                warn!("Failed to keep the report on {}: {}", target_str, e);
+            }
+        }
+
         // Drop the deployer to release the progress_sender
         // This will close the channel and allow the ui_update_task to complete
         drop(deployer);
@@ -129,28 +575,290 @@ impl Up {
             }
         }
 
-        let status = match deploy_result {
-            Ok(status) => status,
-            Err(e) => {
-                return Err(CliError::OperationFailed(format!(
-                    "Deployment failed: {}",
-                    e
-                )));
-            }
+        let written = match (&report, &report_path) {
+            (Some(report), Some(path)) => write_report(report, path),
+            _ => Ok(()),
         };
+        let status = deploy_result
+            .map_err(|e| CliError::OperationFailed(format!("Deployment failed: {}", e)))?;
+        written?;
+        Ok(status)
+    }
 
-        // --- Health Check ---
-        if !status.services_healthy && !self.no_health_check {
+    /// Where the report of deploying to `target` goes: --report-path as
+    /// given for a single deployment, else with the host (if `several_targets`)
+    /// and `stack` added to the file name.
+    fn report_file(
+        &self,
+        target: &str,
+        several_targets: bool,
+        stack: Option<&str>,
+    ) -> Option<PathBuf> {
+        let path = self.report_path.as_ref()?;
+        let mut suffix = String::new();
+        if several_targets {
+            suffix.push('-');
+            suffix.extend(target.chars().map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' | '@' => c,
+                _ => '_',
+            }));
+        }
+        if let Some(stack) = stack {
+            suffix.push('-');
+            suffix.push_str(stack);
+        }
+        if suffix.is_empty() {
+            return Some(path.clone());
+        }
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let name = match path.extension() {
+            Some(ext) => format!("{}{}.{}", stem, suffix, ext.to_string_lossy()),
+            None => format!("{}{}", stem, suffix),
+        };
+        Some(path.with_file_name(name))
+    }
+
+    fn deploy_config(
+        &self,
+        cli_args: &Cli,
+        analysis: ComposerOutput,
+        project_config: ProjectConfig,
+    ) -> DeploymentConfig {
+        let health_check = self.health_check(&project_config);
+        if self.force_env && project_config.env_template.is_none() {
+            tracing::warn!("--force-env has no effect without env_template in dcd.toml");
+        }
+        DeploymentConfig {
+            project_dir: analysis.resolved_project_dir.clone(),
+            remote_dir: cli_args.remote_dir.clone(),
+            compose_files: analysis.resolved_compose_files.clone(),
+            env_files: analysis.resolved_env_files.clone(),
+            consumed_env: analysis.consumed_env,
+            exposed_ports: analysis.exposed_ports,
+            local_references: analysis
+                .local_references
+                .iter()
+                .map(PathBuf::from)
+                .collect(),
+            volumes: analysis.volumes,
+            service_dependencies: analysis.service_dependencies,
+            profiles: analysis.active_profiles,
+            services: self.services.clone(),
+            cron_jobs: analysis.cron_jobs,
+            file_owner: self.chown.clone(),
+            systemd_unit: self.systemd,
+            wait_strategies: project_config.wait,
+            hooks: self.hooks(project_config.hooks),
+            external_networks: project_config.networks,
+            host_packages: project_config.host_packages,
+            sysctls: project_config.sysctl,
+            ulimits: project_config.ulimits,
+            skip_stages: self.skipped_stages(),
+            gpu_services: analysis.gpu_services,
+            install_gpu_toolkit: self.install_gpu_toolkit,
+            age_identity: project_config.age_identity,
+            env_template: project_config
+                .env_template
+                .map(|template| analysis.resolved_project_dir.join(template)),
+            force_env: self.force_env,
+            env_edits: self.env_edits,
+            sync_concurrency: sync_concurrency(cli_args, project_config.sync_concurrency),
+            secrets: project_config.secrets,
+            show_diff: self.diff,
+            include_hidden: self.include_hidden,
+            fresh_build: self.build,
+            restart_policy: self.enforce_restart_policy.clone(),
+            services_without_restart: analysis.services_without_restart,
+            service_hints: analysis.service_hints,
+            health_check,
+            // Set per target by `deploy_host`
+            on_local_machine: false,
+        }
+    }
+
+    /// Hooks from dcd.toml followed by the ones given with --hook and --local-hook.
+    fn hooks(&self, mut hooks: Hooks) -> Hooks {
+        for (stage, command) in &self.hooks {
+            hooks.push(*stage, Hook::remote(command));
+        }
+        for (stage, command) in &self.local_hooks {
+            hooks.push(*stage, Hook::local(command));
+        }
+        hooks
+    }
+
+    /// Health check timing: flags win over dcd.toml, which wins over the defaults.
+    fn health_check(&self, project_config: &ProjectConfig) -> HealthCheckSettings {
+        let defaults = HealthCheckSettings::default();
+        HealthCheckSettings {
+            retries: self
+                .health_retries
+                .or(project_config.health_retries)
+                .unwrap_or(defaults.retries),
+            interval: self
+                .health_interval
+                .or(project_config.health_interval())
+                .unwrap_or(defaults.interval),
+            timeout: self
+                .health_timeout
+                .or(project_config.health_timeout())
+                .unwrap_or(defaults.timeout),
+            watch_events: self.health_watch || project_config.health_watch,
+        }
+    }
+
+    /// Fail when services are unhealthy, unless the check was skipped.
+    fn check_health(&self, status: &DeploymentStatus) -> Result<(), CliError> {
+        let health_checked = !self.skipped_stages().contains(&DeployStage::Health);
+        if !status.services_healthy && health_checked {
+            for timeline in &status.health_timelines {
+                info!("  {}: {}", timeline.service, timeline.summary());
+            }
             return Err(CliError::OperationFailed(
                 ui::format_warning("Some services are not healthy after deployment.").to_string(),
             ));
-        } else if status.services_healthy {
+        }
+        Ok(())
+    }
+
+    fn report(&self, status: &DeploymentStatus) -> Result<(), CliError> {
+        // --- Health Check ---
+        self.check_health(status)?;
+        if status.services_healthy {
             info!("{}", ui::format_success("All services reported healthy."));
-        } else if self.no_health_check {
+        } else {
             info!("{}", ui::format_warning("Skipped service health check."));
         }
 
+        if !status.image_changes.is_empty() {
+            info!("Image changes:");
+            for change in &status.image_changes {
+                info!("  {}", change);
+            }
+        }
+
         info!("{}", ui::format_success("Deployment successful!"));
         Ok(())
     }
+
+    /// Stages left out by --only/--skip; --no-health-check skips `health`.
+    fn skipped_stages(&self) -> BTreeSet<DeployStage> {
+        let mut skipped: BTreeSet<DeployStage> = if self.only.is_empty() {
+            self.skip.iter().copied().collect()
+        } else {
+            DeployStage::ALL
+                .into_iter()
+                .filter(|stage| !self.only.contains(stage))
+                .collect()
+        };
+        if self.no_health_check {
+            skipped.insert(DeployStage::Health);
+        }
+        skipped
+    }
+}
+
+/// A compose project in a subdirectory selected with --stack.
+struct Stack {
+    name: String,
+    /// Local directory, relative to the current one
+    dir: PathBuf,
+    config: StackConfig,
+}
+
+impl Stack {
+    async fn analyze(&self) -> Result<ComposerOutput, ComposerError> {
+        let in_dir = |files: &[PathBuf]| files.iter().map(|file| self.dir.join(file)).collect();
+        composer_in(
+            self.dir.clone(),
+            in_dir(&self.config.compose_files),
+            in_dir(&self.config.env_files),
+        )
+        .await?
+        .analyze()
+        .await
+    }
+
+    /// The stack's own workdir, else a directory named after it inside -w;
+    /// without either the deployer picks `/opt/<directory name>`.
+    fn remote_dir(&self, workdir: Option<&Path>) -> Option<PathBuf> {
+        self.config
+            .workdir
+            .clone()
+            .or_else(|| workdir.map(|workdir| workdir.join(&self.name)))
+    }
+}
+
+/// Label the results of one stack `<target> [<stack>]`.
+fn label_stack<T>(
+    stack: &str,
+    results: Vec<(String, Result<T, CliError>)>,
+) -> Vec<(String, Result<T, CliError>)> {
+    results
+        .into_iter()
+        .map(|(target, result)| (format!("{} [{}]", target, stack), result))
+        .collect()
+}
+
+/// Write `report` to `path`, in the format its extension asks for.
+fn write_report(report: &DeployReport, path: &Path) -> Result<(), CliError> {
+    let content = report
+        .render(ReportFormat::for_path(path))
+        .map_err(|e| CliError::OperationFailed(e.to_string()))?;
+    std::fs::write(path, content).map_err(|e| {
+        CliError::OperationFailed(format!("Failed to write {}: {}", path.display(), e))
+    })?;
+    info!("Wrote deployment report to {}", path.display());
+    Ok(())
+}
+
+/// Name of a stack given as a directory: its last component.
+fn stack_name(dir: &Path) -> Result<String, CliError> {
+    let name = std::fs::canonicalize(dir)
+        .ok()
+        .and_then(|dir| {
+            dir.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+        .filter(|name| is_valid_stack_name(name));
+    name.ok_or_else(|| {
+        CliError::ConfigError(format!(
+            "Cannot use {} as a stack: its name must only contain letters, digits, '-' and '_'",
+            dir.display()
+        ))
+    })
+}
+
+/// The ports of the machine dcd runs on are its own business, and it is
+/// known to run Linux.
+fn deploy_on_local_machine(config: &mut DeploymentConfig) {
+    config.skip_stages.insert(DeployStage::Firewall);
+    config.on_local_machine = true;
+}
+
+fn parse_health_duration(value: &str) -> Result<Duration, String> {
+    parse_duration(value)
+        .filter(|duration| !duration.is_zero())
+        .ok_or_else(|| format!("invalid duration '{}' (expected e.g. 30s, 5m)", value))
+}
+
+fn parse_restart_policy(policy: &str) -> Result<String, String> {
+    validate_restart_policy(policy)?;
+    Ok(policy.to_string())
+}
+
+/// One-line outcome of a successful deployment for the hosts summary.
+fn summary_details(status: &DeploymentStatus) -> String {
+    let mut details = if status.services_healthy {
+        "healthy".to_string()
+    } else {
+        "health check skipped".to_string()
+    };
+    if !status.image_changes.is_empty() {
+        details.push_str(&format!(", {} image change(s)", status.image_changes.len()));
+    }
+    details
 }
@@ -0,0 +1,116 @@
+use crate::composer::{
+    errors::ComposerError,
+    types::{ComposerResult, CronJob, Service},
+};
+
+pub const CRON_SCHEDULE_LABEL: &str = "dcd.cron.schedule";
+pub const CRON_COMMAND_LABEL: &str = "dcd.cron.command";
+
+const SCHEDULE_SHORTCUTS: &[&str] = &[
+    "@yearly",
+    "@annually",
+    "@monthly",
+    "@weekly",
+    "@daily",
+    "@midnight",
+    "@hourly",
+];
+
+pub struct CronParser;
+
+impl CronParser {
+    /// Extract a cron job from the `dcd.cron.*` labels of a service.
+    pub fn parse(service_name: &str, service: &Service) -> ComposerResult<Option<CronJob>> {
+        let Some(labels) = &service.labels else {
+            return Ok(None);
+        };
+        let schedule = labels.get(CRON_SCHEDULE_LABEL);
+        let command = labels.get(CRON_COMMAND_LABEL);
+
+        let invalid = |details: String| {
+            ComposerError::ConfigurationError(format!(
+                "Invalid cron labels on service '{}': {}",
+                service_name, details
+            ))
+        };
+
+        let schedule = match (schedule, &command) {
+            (None, None) => return Ok(None),
+            (None, Some(_)) => {
+                return Err(invalid(format!(
+                    "'{}' is set without '{}'",
+                    CRON_COMMAND_LABEL, CRON_SCHEDULE_LABEL
+                )))
+            }
+            (Some(schedule), _) => schedule.trim().to_string(),
+        };
+
+        if !Self::is_valid_schedule(&schedule) {
+            return Err(invalid(format!(
+                "schedule '{}' must have five fields or be one of {}",
+                schedule,
+                SCHEDULE_SHORTCUTS.join(", ")
+            )));
+        }
+        if let Some(command) = &command {
+            if command.contains('\n') || command.trim().is_empty() {
+                return Err(invalid("command must be a single non-empty line".into()));
+            }
+        }
+
+        Ok(Some(CronJob {
+            service: service_name.to_string(),
+            schedule,
+            command: command.map(|c| c.trim().to_string()),
+        }))
+    }
+
+    fn is_valid_schedule(schedule: &str) -> bool {
+        if schedule.starts_with('@') {
+            return SCHEDULE_SHORTCUTS.contains(&schedule);
+        }
+        let fields: Vec<&str> = schedule.split_whitespace().collect();
+        fields.len() == 5
+            && fields.iter().all(|field| {
+                field
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '*' | ',' | '-' | '/'))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(labels: &str) -> Service {
+        serde_yaml::from_str(&format!("image: app\nlabels:\n{}", labels)).unwrap()
+    }
+
+    #[test]
+    fn test_parse_cron_labels() {
+        let svc = service(
+            "  dcd.cron.schedule: \"0 3 * * *\"\n  dcd.cron.command: python manage.py cleanup\n",
+        );
+        let job = CronParser::parse("worker", &svc).unwrap().unwrap();
+        assert_eq!(job.schedule, "0 3 * * *");
+        assert_eq!(job.command.as_deref(), Some("python manage.py cleanup"));
+
+        let list_form = service("  - dcd.cron.schedule=@hourly\n");
+        let job = CronParser::parse("worker", &list_form).unwrap().unwrap();
+        assert_eq!(job.schedule, "@hourly");
+        assert!(job.command.is_none());
+    }
+
+    #[test]
+    fn test_parse_cron_labels_invalid() {
+        let bad_schedule = service("  dcd.cron.schedule: \"every day\"\n");
+        assert!(CronParser::parse("worker", &bad_schedule).is_err());
+
+        let missing_schedule = service("  dcd.cron.command: echo hi\n");
+        assert!(CronParser::parse("worker", &missing_schedule).is_err());
+
+        let unrelated = service("  traefik.enable: \"true\"\n");
+        assert!(CronParser::parse("web", &unrelated).unwrap().is_none());
+    }
+}
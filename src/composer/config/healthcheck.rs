@@ -0,0 +1,127 @@
+use super::duration::parse_duration;
+use crate::composer::{
+    errors::ComposerError,
+    types::{ComposerResult, HealthcheckConfig, HealthcheckTest, Service},
+};
+
+pub struct HealthcheckValidator;
+
+impl HealthcheckValidator {
+    /// Validate a service's healthcheck. Syntax errors are returned as errors;
+    /// a missing or disabled healthcheck yields a warning, since dcd can then
+    /// only tell whether the container is running.
+    pub fn validate(service_name: &str, service: &Service) -> ComposerResult<Option<String>> {
+        let Some(healthcheck) = &service.healthcheck else {
+            return Ok(Some(format!(
+                "Service '{}' has no healthcheck in the compose file; unless its image defines one, health verification only checks that it is running",
+                service_name
+            )));
+        };
+
+        if healthcheck.disable == Some(true) {
+            return Ok(Some(format!(
+                "Service '{}' has its healthcheck disabled",
+                service_name
+            )));
+        }
+
+        Self::validate_test(service_name, healthcheck)?;
+        Self::validate_durations(service_name, healthcheck)?;
+
+        if matches!(&healthcheck.test, Some(HealthcheckTest::List(parts)) if parts.first().map(String::as_str) == Some("NONE"))
+        {
+            return Ok(Some(format!(
+                "Service '{}' has its healthcheck disabled",
+                service_name
+            )));
+        }
+        Ok(None)
+    }
+
+    fn validate_test(service_name: &str, healthcheck: &HealthcheckConfig) -> ComposerResult<()> {
+        let invalid = |details: String| {
+            ComposerError::ConfigurationError(format!(
+                "Invalid healthcheck for service '{}': {}",
+                service_name, details
+            ))
+        };
+
+        match &healthcheck.test {
+            // Inherits the test from the image
+            None => Ok(()),
+            Some(HealthcheckTest::Shell(command)) if command.trim().is_empty() => {
+                Err(invalid("test command is empty".into()))
+            }
+            Some(HealthcheckTest::Shell(_)) => Ok(()),
+            Some(HealthcheckTest::List(parts)) => match parts.first().map(String::as_str) {
+                Some("NONE") => Ok(()),
+                Some("CMD") | Some("CMD-SHELL") if parts.len() < 2 => {
+                    Err(invalid(format!("test '{}' requires a command", parts[0])))
+                }
+                Some("CMD") | Some("CMD-SHELL") => Ok(()),
+                Some(other) => Err(invalid(format!(
+                    "test must start with CMD, CMD-SHELL or NONE, found '{}'",
+                    other
+                ))),
+                None => Err(invalid("test is empty".into())),
+            },
+        }
+    }
+
+    fn validate_durations(
+        service_name: &str,
+        healthcheck: &HealthcheckConfig,
+    ) -> ComposerResult<()> {
+        let durations = [
+            ("interval", &healthcheck.interval),
+            ("timeout", &healthcheck.timeout),
+            ("start_period", &healthcheck.start_period),
+            ("start_interval", &healthcheck.start_interval),
+        ];
+        for (field, value) in durations {
+            if let Some(value) = value {
+                if parse_duration(value).is_none() {
+                    return Err(ComposerError::ConfigurationError(format!(
+                        "Invalid healthcheck for service '{}': {} '{}' is not a valid duration (e.g. 30s, 1m30s)",
+                        service_name, field, value
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(healthcheck: &str) -> Service {
+        serde_yaml::from_str(&format!("image: nginx\n{}", healthcheck)).unwrap()
+    }
+
+    #[test]
+    fn test_missing_healthcheck_warns() {
+        let warning = HealthcheckValidator::validate("web", &service("")).unwrap();
+        assert!(warning.unwrap().contains("no healthcheck"));
+    }
+
+    #[test]
+    fn test_valid_healthcheck() {
+        let svc = service(
+            "healthcheck:\n  test: [\"CMD\", \"curl\", \"-f\", \"http://localhost\"]\n  interval: 1m30s\n  timeout: 10s\n  retries: 3\n",
+        );
+        assert!(HealthcheckValidator::validate("web", &svc)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_invalid_healthcheck() {
+        let bad_form = service("healthcheck:\n  test: [\"CMDSHELL\", \"true\"]\n");
+        assert!(HealthcheckValidator::validate("web", &bad_form).is_err());
+
+        let bad_interval = service("healthcheck:\n  test: [\"CMD\", \"true\"]\n  interval: 10\n");
+        assert!(HealthcheckValidator::validate("web", &bad_interval).is_err());
+    }
+}
@@ -0,0 +1,128 @@
+use crate::composer::{
+    errors::ComposerError,
+    types::{ComposerResult, Service, ServiceHints},
+};
+use std::net::IpAddr;
+
+pub const HINTS_KEY: &str = "x-dcd";
+
+pub struct HintsParser;
+
+impl HintsParser {
+    /// Validate the `x-dcd` hints of a service, if it has any.
+    pub fn parse(service_name: &str, service: &Service) -> ComposerResult<Option<ServiceHints>> {
+        let Some(hints) = &service.x_dcd else {
+            return Ok(None);
+        };
+        let invalid = |details: String| {
+            ComposerError::ConfigurationError(format!(
+                "Invalid {} hints on service '{}': {}",
+                HINTS_KEY, service_name, details
+            ))
+        };
+
+        if let Some(url) = &hints.smoke_test {
+            let parsed = url::Url::parse(url)
+                .map_err(|e| invalid(format!("smoke-test url '{}': {}", url, e)))?;
+            if !matches!(parsed.scheme(), "http" | "https") {
+                return Err(invalid(format!(
+                    "smoke-test url must be http(s), got '{}'",
+                    url
+                )));
+            }
+        }
+        if let Some(firewall) = &hints.firewall {
+            if let Some(source) = firewall.allow.iter().find(|s| !is_valid_source(s)) {
+                return Err(invalid(format!(
+                    "firewall source '{}' is not an IP address or CIDR range",
+                    source
+                )));
+            }
+            if !firewall.allow.is_empty()
+                && service.ports.as_ref().is_none_or(Vec::is_empty)
+                && hints.host_ports.is_empty()
+            {
+                return Err(invalid(
+                    "firewall allowlist set but no ports are published".into(),
+                ));
+            }
+        }
+        if !hints.host_ports.is_empty() && !service.uses_host_network() {
+            return Err(invalid(
+                "host-ports only apply to services with `network_mode: host`".into(),
+            ));
+        }
+        if let Some(backup) = &hints.backup {
+            if let Some(path) = backup.paths.iter().find(|p| !p.starts_with('/')) {
+                return Err(invalid(format!(
+                    "backup path '{}' must be an absolute path in the container",
+                    path
+                )));
+            }
+        }
+        Ok(Some(hints.clone()))
+    }
+}
+
+/// Whether `source` is an IP address, optionally with a prefix length.
+fn is_valid_source(source: &str) -> bool {
+    let (address, prefix) = match source.split_once('/') {
+        Some((address, prefix)) => (address, Some(prefix)),
+        None => (source, None),
+    };
+    let Ok(address) = address.parse::<IpAddr>() else {
+        return false;
+    };
+    let max = if address.is_ipv4() { 32 } else { 128 };
+    prefix.is_none_or(|prefix| prefix.parse::<u8>().is_ok_and(|len| len <= max))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(hints: &str) -> Service {
+        serde_yaml::from_str(&format!(
+            "image: app\nports:\n  - target: 80\n    published: \"8080\"\nx-dcd:\n{}",
+            hints
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_hints() {
+        let svc = service(
+            "  skip-health-check: true\n  firewall:\n    allow: [10.0.0.0/8, \"2001:db8::1\"]\n  \
+             smoke-test: http://localhost:8080/health\n  backup:\n    paths: [/data]\n",
+        );
+        let hints = HintsParser::parse("web", &svc).unwrap().unwrap();
+        assert!(hints.skip_health_check);
+        assert_eq!(hints.firewall.unwrap().allow.len(), 2);
+        assert_eq!(
+            hints.smoke_test.as_deref(),
+            Some("http://localhost:8080/health")
+        );
+        assert_eq!(hints.backup.unwrap().paths, vec!["/data".to_string()]);
+
+        let plain: Service = serde_yaml::from_str("image: app\n").unwrap();
+        assert!(HintsParser::parse("web", &plain).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_hints_invalid() {
+        let bad_source = service("  firewall:\n    allow: [10.0.0.0/33]\n");
+        assert!(HintsParser::parse("web", &bad_source).is_err());
+
+        let bad_url = service("  smoke-test: ftp://localhost/\n");
+        assert!(HintsParser::parse("web", &bad_url).is_err());
+
+        let relative = service("  backup:\n    paths: [data]\n");
+        assert!(HintsParser::parse("web", &relative).is_err());
+
+        let bridged = service("  host-ports: [\"8080\"]\n");
+        assert!(HintsParser::parse("web", &bridged).is_err());
+
+        let unknown = format!("image: app\n{}:\n  skip-healthcheck: true\n", HINTS_KEY);
+        assert!(serde_yaml::from_str::<Service>(&unknown).is_err());
+    }
+}
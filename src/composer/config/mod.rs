@@ -1,7 +1,14 @@
+pub mod cron;
+pub mod duration;
+pub mod healthcheck;
+pub mod hints;
 pub mod parser;
 pub mod ports;
 pub mod volumes;
 
+pub use cron::CronParser;
+pub use healthcheck::HealthcheckValidator;
+pub use hints::HintsParser;
 pub use parser::ConfigParser;
 pub use ports::PortsParser;
 pub use volumes::VolumesParser;
@@ -140,6 +140,7 @@ impl ConfigParser {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::composer::types::DependencyCondition;
 
     #[test]
     fn test_parse_basic_config() {
@@ -275,6 +276,76 @@ services:
         }
     }
 
+    #[test]
+    fn test_parse_config_with_depends_on() {
+        let config = r#"
+services:
+  api:
+    image: api:latest
+    depends_on:
+      db:
+        condition: service_healthy
+        required: true
+      migrate:
+        condition: service_completed_successfully
+        required: false
+  worker:
+    image: worker:latest
+    depends_on:
+      - db
+  db:
+    image: postgres:16
+"#;
+
+        let result = ConfigParser::parse_config(config).unwrap();
+
+        let api_deps = result.services["api"].dependencies();
+        assert_eq!(api_deps.len(), 2);
+        assert_eq!(api_deps[0].service, "db");
+        assert_eq!(api_deps[0].condition, DependencyCondition::Healthy);
+        assert!(api_deps[0].required);
+        assert_eq!(api_deps[1].service, "migrate");
+        assert_eq!(
+            api_deps[1].condition,
+            DependencyCondition::CompletedSuccessfully
+        );
+        assert!(!api_deps[1].required);
+
+        let worker_deps = result.services["worker"].dependencies();
+        assert_eq!(worker_deps[0].condition, DependencyCondition::Started);
+        assert!(result.services["db"].dependencies().is_empty());
+    }
+
+    #[test]
+    fn test_parse_config_with_gpu_requests() {
+        let config = r#"
+services:
+  trainer:
+    image: pytorch/pytorch
+    deploy:
+      resources:
+        reservations:
+          devices:
+            - driver: nvidia
+              count: 1
+              capabilities: [gpu]
+  inference:
+    image: vllm/vllm-openai
+    gpus: all
+  web:
+    image: nginx
+    deploy:
+      resources:
+        limits:
+          memory: 512M
+"#;
+
+        let result = ConfigParser::parse_config(config).unwrap();
+        assert!(result.services["trainer"].requests_gpu());
+        assert!(result.services["inference"].requests_gpu());
+        assert!(!result.services["web"].requests_gpu());
+    }
+
     #[test]
     fn test_extract_local_references_empty() {
         let config = r#"
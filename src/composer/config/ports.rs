@@ -1,3 +1,4 @@
+use crate::composer::errors::ComposerError;
 use crate::composer::types::{ComposerResult, PortMapping};
 
 pub struct PortsParser;
@@ -6,4 +7,61 @@ impl PortsParser {
     pub fn parse_ports(ports: &[PortMapping]) -> ComposerResult<Vec<PortMapping>> {
         Ok(ports.to_vec())
     }
+
+    /// Parse the `port[/protocol]` entries of the `host-ports` hint into
+    /// mappings of each port onto itself.
+    pub fn parse_host_ports(ports: &[String]) -> ComposerResult<Vec<PortMapping>> {
+        ports
+            .iter()
+            .map(|entry| {
+                let (port, protocol) = match entry.split_once('/') {
+                    Some((port, protocol)) => (port, Some(protocol.to_lowercase())),
+                    None => (entry.as_str(), None),
+                };
+                let invalid = || {
+                    ComposerError::ConfigurationError(format!(
+                        "Invalid host port '{}': expected port[/tcp|udp]",
+                        entry
+                    ))
+                };
+                let port: u16 = port.trim().parse().map_err(|_| invalid())?;
+                if port == 0
+                    || protocol
+                        .as_deref()
+                        .is_some_and(|p| p != "tcp" && p != "udp")
+                {
+                    return Err(invalid());
+                }
+                Ok(PortMapping {
+                    mode: Some("host".to_string()),
+                    target: port,
+                    published: port.to_string(),
+                    protocol,
+                    allow_from: Vec::new(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_host_ports() {
+        let ports =
+            PortsParser::parse_host_ports(&["8080".to_string(), "53/UDP".to_string()]).unwrap();
+        assert_eq!(ports.len(), 2);
+        assert_eq!(
+            (ports[0].target, ports[0].published.as_str()),
+            (8080, "8080")
+        );
+        assert_eq!(ports[0].protocol, None);
+        assert_eq!(ports[1].protocol.as_deref(), Some("udp"));
+
+        for bad in ["http", "0", "70000", "53/sctp"] {
+            assert!(PortsParser::parse_host_ports(&[bad.to_string()]).is_err());
+        }
+    }
 }
@@ -1,10 +1,13 @@
 use crate::composer::{
+    config::cron::CronParser,
+    config::healthcheck::HealthcheckValidator,
+    config::hints::HintsParser,
     config::parser::ConfigParser,
     config::ports::PortsParser,
     config::volumes::VolumesParser,
     detection::{detect_compose_command, ComposeCommand, DetectionError},
     errors::ComposerError,
-    types::{ComposeFile, ComposerConfig, ComposerOutput, ComposerResult},
+    types::{ComposeFile, ComposerConfig, ComposerOutput, ComposerResult, DependencyCondition},
     variables::availability::EnvironmentChecker,
     variables::availability::EnvironmentStatus,
     variables::parser::VariablesParser,
@@ -236,6 +239,30 @@ impl<T: CommandExecutor> Composer<T> {
         ConfigParser::parse_config(&result.output.to_stdout_string()?)
     }
 
+    /// Fully resolved compose file as printed by `docker compose config`:
+    /// variables substituted, env files inlined and, with `pin_digests`, every
+    /// image reference pinned to its registry digest.
+    pub async fn resolved_config(&mut self, pin_digests: bool) -> ComposerResult<String> {
+        let subcommand = if pin_digests {
+            "config --resolve-image-digests"
+        } else {
+            "config"
+        };
+        let config_cmd = self.build_compose_command(subcommand)?;
+        let result = self
+            .executor
+            .execute_command(&config_cmd)
+            .await
+            .map_err(|e| ComposerError::CommandExecutionError(e.to_string()))?;
+        if !result.is_success() {
+            return Err(ComposerError::command_error(format!(
+                "Failed to resolve compose configuration: {}",
+                result.output.to_stderr_string()?.trim()
+            )));
+        }
+        Ok(result.output.to_stdout_string()?)
+    }
+
     /// Process the compose file to extract all required information
     fn process_compose_file(&self, compose_file: &ComposeFile) -> ComposerResult<ComposerOutput> {
         let mut output = ComposerOutput::new();
@@ -254,13 +281,67 @@ impl<T: CommandExecutor> Composer<T> {
         let profiles_handler = ProfilesHandler::new();
         output.active_profiles = profiles_handler.get_active_profiles();
 
+        output.services = compose_file.services.keys().cloned().collect();
+        output.services.sort();
+
         // Extract ports and volumes from all services (profiles are handled by docker-compose itself)
-        for service in compose_file.services.values() {
+        for (name, service) in &compose_file.services {
+            let dependencies = service.dependencies();
+            if !dependencies.is_empty() {
+                output
+                    .service_dependencies
+                    .insert(name.clone(), dependencies);
+            }
+
+            if let Some(job) = CronParser::parse(name, service)? {
+                output.cron_jobs.push(job);
+            }
+
+            let hints = HintsParser::parse(name, service)?;
+            let warning = HealthcheckValidator::validate(name, service)?;
+            // Nothing to warn about for services left out of the health check
+            if let Some(warning) =
+                warning.filter(|_| !hints.as_ref().is_some_and(|h| h.skip_health_check))
+            {
+                output.warnings.push(warning);
+            }
+
+            if service.requests_gpu() {
+                output.gpu_services.push(name.clone());
+            }
+
             if let Some(ports) = &service.ports {
-                let parsed_ports = PortsParser::parse_ports(ports)?;
+                let mut parsed_ports = PortsParser::parse_ports(ports)?;
+                if let Some(firewall) = hints.as_ref().and_then(|h| h.firewall.as_ref()) {
+                    for port in &mut parsed_ports {
+                        port.allow_from = firewall.allow.clone();
+                    }
+                }
+                output.exposed_ports.extend(parsed_ports);
+            }
+
+            if service.uses_host_network() {
+                let host_ports = hints.as_ref().map(|h| &h.host_ports[..]).unwrap_or(&[]);
+                if host_ports.is_empty() {
+                    output.warnings.push(format!(
+                        "Service '{}' uses `network_mode: host`; the ports it listens on are unknown to dcd and not opened in the firewall (list them under `x-dcd: host-ports`)",
+                        name
+                    ));
+                }
+                let mut parsed_ports = PortsParser::parse_host_ports(host_ports)?;
+                if let Some(firewall) = hints.as_ref().and_then(|h| h.firewall.as_ref()) {
+                    for port in &mut parsed_ports {
+                        port.allow_from = firewall.allow.clone();
+                    }
+                }
+                output.host_network_services.push(name.clone());
                 output.exposed_ports.extend(parsed_ports);
             }
 
+            if let Some(hints) = hints {
+                output.service_hints.insert(name.clone(), hints);
+            }
+
             // Extract volumes
             if let Some(volumes) = &service.volumes {
                 let parsed_volumes =
@@ -269,11 +350,34 @@ impl<T: CommandExecutor> Composer<T> {
             }
         }
 
+        // Init-style services that others wait to complete are meant to exit
+        let run_once: std::collections::HashSet<&String> = output
+            .service_dependencies
+            .values()
+            .flatten()
+            .filter(|dep| dep.condition == DependencyCondition::CompletedSuccessfully)
+            .map(|dep| &dep.service)
+            .collect();
+        for (name, service) in &compose_file.services {
+            if !service.has_restart_policy() && !run_once.contains(name) {
+                output.services_without_restart.push(name.clone());
+                output.warnings.push(format!(
+                    "Service '{}' has no restart policy; it will not come back after the docker daemon restarts (set `restart:` or use --enforce-restart-policy)",
+                    name
+                ));
+            }
+        }
+
         // Extract local references
         let references = ConfigParser::extract_local_references(compose_file);
         output
             .local_references
             .extend(references.into_iter().map(PathBuf::from));
+        output.warnings.sort();
+        output.cron_jobs.sort_by(|a, b| a.service.cmp(&b.service));
+        output.gpu_services.sort();
+        output.host_network_services.sort();
+        output.services_without_restart.sort();
 
         Ok(output)
     }
@@ -312,85 +416,51 @@ mod tests {
         detection::ComposeCommand,
         types::{PortMapping, Service, VolumeMapping},
     };
-    use crate::executor::{CommandResult, ExecutorError};
-    use async_trait::async_trait;
+    use crate::executor::ExecutorError;
+    use crate::testing::{success, MockExecutor};
     use std::collections::HashMap;
     use std::fs;
     use std::io::Write;
     use tempfile::TempDir;
 
-    struct MockExecutor {
-        // Store Result directly to simulate execution errors
-        responses: HashMap<String, Result<CommandResult, ExecutorError>>,
-        commands: Vec<String>,
-    }
-
-    impl MockExecutor {
-        fn new() -> Self {
-            Self {
-                responses: HashMap::new(),
-                commands: Vec::new(),
-            }
-        }
-
-        fn add_response(&mut self, command: &str, result: Result<CommandResult, ExecutorError>) {
-            self.responses.insert(command.to_string(), result);
-        }
-
-        // Helper to set up standard successful detection (e.g., plugin)
-        fn setup_successful_plugin_detection(&mut self) {
-            self.add_response(
+    // Standard successful detection (e.g., plugin)
+    fn setup_successful_plugin_detection(executor: &mut MockExecutor) {
+        executor
+            .respond_ok(
                 "docker compose version --format json",
-                create_success_result("{\"version\":\"v2.5.1\"}"),
-            );
-            // Optionally add a failure for standalone if needed by a specific test
-            self.add_response(
+                "{\"version\":\"v2.5.1\"}",
+            )
+            // Standalone is missing
+            .respond(
                 "docker-compose --version",
-                Err(ExecutorError::Other("command not found".into())), // Simulate execution error
+                Err(ExecutorError::Other("command not found".into())),
             );
-        }
+    }
 
-        // Helper to set up standard successful detection (e.g., standalone)
-        fn setup_successful_standalone_detection(&mut self) {
-            self.add_response(
+    // Standard successful detection (e.g., standalone)
+    fn setup_successful_standalone_detection(executor: &mut MockExecutor) {
+        executor
+            .respond(
                 "docker compose version --format json",
                 Err(ExecutorError::Other("command not found".into())),
-            );
-            self.add_response(
+            )
+            .respond_ok(
                 "docker-compose --version",
-                create_success_result("docker-compose version v1.29.2, build abcdef"),
+                "docker-compose version v1.29.2, build abcdef",
             );
-        }
+    }
 
-        // Helper to set up failed detection (both commands fail)
-        fn setup_failed_detection(&mut self) {
-            self.add_response(
+    // Failed detection (both commands fail)
+    fn setup_failed_detection(executor: &mut MockExecutor) {
+        executor
+            .respond(
                 "docker compose version --format json",
                 Err(ExecutorError::Other("command not found".into())),
-            );
-            self.add_response(
+            )
+            .respond(
                 "docker-compose --version",
                 Err(ExecutorError::Other("command not found".into())),
             );
-        }
-    }
-
-    #[async_trait]
-    impl CommandExecutor for MockExecutor {
-        async fn execute_command(&mut self, command: &str) -> Result<CommandResult, ExecutorError> {
-            self.commands.push(command.to_string());
-            // Clone the result from the map, or return an error if not found
-            let response = self.responses.get(command).cloned().ok_or_else(|| {
-                ExecutorError::Other(format!("Mock response not found for command: {}", command))
-            })?;
-
-            let result = response?; // Propagate potential ExecutorError stored in the map value
-            Ok(result)
-        }
-
-        async fn close(&mut self) -> Result<(), ExecutorError> {
-            Ok(())
-        }
     }
 
     fn create_test_environment() -> (TempDir, ComposerConfig) {
@@ -414,18 +484,11 @@ mod tests {
         env_path
     }
 
-    fn create_success_result(stdout: &str) -> Result<CommandResult, ExecutorError> {
-        let mut result = CommandResult::new("mock_command");
-        result.output.stdout = stdout.as_bytes().to_vec();
-        result.output.exit_code = 0;
-        Ok(result)
-    }
-
     #[tokio::test]
     async fn test_composer_try_new_success_plugin() {
         let (temp_dir, config) = create_test_environment();
         let mut executor = MockExecutor::new();
-        executor.setup_successful_plugin_detection(); // Setup mock for successful plugin detection
+        setup_successful_plugin_detection(&mut executor); // Setup mock for successful plugin detection
 
         let composer_result = Composer::try_new(executor, config).await;
         assert!(composer_result.is_ok());
@@ -450,7 +513,7 @@ mod tests {
     async fn test_composer_try_new_success_standalone() {
         let (_temp_dir, config) = create_test_environment();
         let mut executor = MockExecutor::new();
-        executor.setup_successful_standalone_detection(); // Setup mock for successful standalone detection
+        setup_successful_standalone_detection(&mut executor); // Setup mock for successful standalone detection
 
         let composer_result = Composer::try_new(executor, config).await;
         assert!(composer_result.is_ok());
@@ -471,7 +534,7 @@ mod tests {
         config.env_files.push(env_path);
 
         let mut executor = MockExecutor::new();
-        executor.setup_successful_plugin_detection();
+        setup_successful_plugin_detection(&mut executor);
         let composer = Composer::try_new(executor, config).await.unwrap();
 
         let cmd = composer.build_compose_command("config").unwrap();
@@ -515,13 +578,13 @@ volumes:
 "#;
 
         let mut executor = MockExecutor::new();
-        executor.setup_successful_plugin_detection(); // Need detection to succeed
-                                                      // build_compose_command will construct the command with the absolute path
+        setup_successful_plugin_detection(&mut executor); // Need detection to succeed
+                                                          // build_compose_command will construct the command with the absolute path
         let expected_config_cmd = format!(
             "docker compose -f {} config",
             config.compose_files[0].display()
         );
-        executor.add_response(&expected_config_cmd, create_success_result(config_output)); // Mock the config command
+        executor.respond(expected_config_cmd.as_str(), success(config_output)); // Mock the config command
         let mut composer = Composer::try_new(executor, config).await.unwrap();
 
         let compose_file = composer.get_compose_config().await.unwrap();
@@ -562,6 +625,7 @@ volumes:
                 target: 5432,
                 published: "5432".to_string(),
                 protocol: None,
+                allow_from: Vec::new(),
             }]),
             volumes: Some(vec![VolumeMapping {
                 r#type: "bind".to_string(),
@@ -572,6 +636,14 @@ volumes:
             configs: None,
             env_file: None,
             profiles: None,
+            depends_on: None,
+            healthcheck: None,
+            labels: None,
+            deploy: None,
+            restart: None,
+            gpus: None,
+            network_mode: None,
+            x_dcd: None,
         };
 
         services.insert("db".to_string(), db_service);
@@ -583,7 +655,7 @@ volumes:
 
         // Need a Composer instance, detection doesn't matter for this test function itself
         let mut executor = MockExecutor::new();
-        executor.setup_successful_plugin_detection(); // Provide detection mocks
+        setup_successful_plugin_detection(&mut executor); // Provide detection mocks
         let composer = Composer::try_new(executor, config).await.unwrap();
 
         let output = composer.process_compose_file(&compose_file).unwrap();
@@ -601,6 +673,88 @@ volumes:
 
         // Check local references (from bind mount source)
         assert_eq!(output.local_references.len(), 1);
+
+        // No restart policy set
+        assert_eq!(output.services_without_restart, vec!["db".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_services_without_restart() {
+        let (_temp_dir, config) = create_test_environment();
+        let compose_file: ComposeFile = serde_yaml::from_str(
+            r#"
+services:
+  web:
+    image: nginx
+    depends_on:
+      migrate:
+        condition: service_completed_successfully
+  worker:
+    image: app
+    restart: unless-stopped
+  swarm:
+    image: app
+    deploy:
+      restart_policy:
+        condition: on-failure
+  migrate:
+    image: app
+"#,
+        )
+        .unwrap();
+
+        let mut executor = MockExecutor::new();
+        setup_successful_plugin_detection(&mut executor);
+        let composer = Composer::try_new(executor, config).await.unwrap();
+        let output = composer.process_compose_file(&compose_file).unwrap();
+
+        // `migrate` is expected to exit, so it needs no restart policy
+        assert_eq!(output.services_without_restart, vec!["web".to_string()]);
+        assert!(output
+            .warnings
+            .iter()
+            .any(|w| w.starts_with("Service 'web' has no restart policy")));
+    }
+
+    #[tokio::test]
+    async fn test_host_network_services() {
+        let (_temp_dir, config) = create_test_environment();
+        let compose_file: ComposeFile = serde_yaml::from_str(
+            r#"
+services:
+  dns:
+    image: coredns
+    restart: always
+    network_mode: host
+    x-dcd:
+      host-ports: ["53/udp", "53/tcp"]
+      firewall:
+        allow: [10.0.0.0/8]
+  agent:
+    image: agent
+    restart: always
+    network_mode: host
+"#,
+        )
+        .unwrap();
+
+        let mut executor = MockExecutor::new();
+        setup_successful_plugin_detection(&mut executor);
+        let composer = Composer::try_new(executor, config).await.unwrap();
+        let output = composer.process_compose_file(&compose_file).unwrap();
+
+        assert_eq!(output.host_network_services, ["agent", "dns"]);
+        assert_eq!(output.exposed_ports.len(), 2);
+        assert_eq!(output.exposed_ports[0].target, 53);
+        assert_eq!(output.exposed_ports[0].allow_from, ["10.0.0.0/8"]);
+        // Only the service without declared ports is warned about
+        let warned: Vec<_> = output
+            .warnings
+            .iter()
+            .filter(|w| w.contains("network_mode: host"))
+            .collect();
+        assert_eq!(warned.len(), 1);
+        assert!(warned[0].starts_with("Service 'agent' "));
     }
 
     #[tokio::test]
@@ -608,7 +762,7 @@ volumes:
         let (_temp_dir, config) = create_test_environment();
 
         let mut executor = MockExecutor::new();
-        executor.setup_failed_detection(); // Setup mock for failed detection
+        setup_failed_detection(&mut executor); // Setup mock for failed detection
 
         let result = Composer::try_new(executor, config).await;
 
@@ -626,14 +780,14 @@ volumes:
         let (_temp_dir, config) = create_test_environment();
         let mut executor = MockExecutor::new();
         // Mock plugin detection failing
-        executor.add_response(
+        executor.respond(
             "docker compose version --format json",
             Err(ExecutorError::Other("command not found".into())),
         );
         // Mock standalone detection succeeding but with an old version
-        executor.add_response(
+        executor.respond(
             "docker-compose --version",
-            create_success_result("docker-compose version v1.20.0, build abcdef"),
+            success("docker-compose version v1.20.0, build abcdef"),
         );
 
         let result = Composer::try_new(executor, config).await;
@@ -661,7 +815,7 @@ volumes:
         };
 
         let mut executor = MockExecutor::new();
-        executor.setup_successful_plugin_detection();
+        setup_successful_plugin_detection(&mut executor);
 
         let result = Composer::try_new(executor, config).await;
         assert!(result.is_err());
@@ -684,7 +838,7 @@ volumes:
         };
 
         let mut executor = MockExecutor::new();
-        executor.setup_successful_plugin_detection();
+        setup_successful_plugin_detection(&mut executor);
 
         let result = Composer::try_new(executor, config).await;
         assert!(result.is_err());
@@ -709,14 +863,14 @@ volumes:
         };
 
         let mut executor = MockExecutor::new();
-        executor.setup_successful_plugin_detection();
+        setup_successful_plugin_detection(&mut executor);
 
         // Mock the config command that will be called after detection
         let expected_config_cmd = format!(
             "docker compose -f {} config",
             temp_dir.path().join("docker-compose.yml").display()
         );
-        executor.add_response(&expected_config_cmd, create_success_result("services: {}"));
+        executor.respond(expected_config_cmd.as_str(), success("services: {}"));
 
         let result = Composer::try_new(executor, config).await;
         assert!(result.is_ok());
@@ -1,6 +1,6 @@
 use crate::composer::errors::ComposerError;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 
 pub type ComposerResult<T> = Result<T, ComposerError>;
@@ -19,6 +19,10 @@ pub struct PortMapping {
     pub target: u16,
     pub published: String,
     pub protocol: Option<String>,
+    /// Sources allowed through the firewall, from the service's `x-dcd`
+    /// hints; anywhere when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow_from: Vec<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -41,6 +45,237 @@ pub struct Service {
     #[serde(rename = "env_file")]
     pub env_file: Option<EnvFiles>,
     pub profiles: Option<Vec<String>>,
+    pub depends_on: Option<DependsOn>,
+    pub healthcheck: Option<HealthcheckConfig>,
+    pub labels: Option<Labels>,
+    pub deploy: Option<DeploySpec>,
+    pub restart: Option<String>,
+    /// Short-hand GPU request (`gpus: all` or a list of device requests)
+    pub gpus: Option<serde_yaml::Value>,
+    pub network_mode: Option<String>,
+    /// Deployment hints for dcd
+    #[serde(rename = "x-dcd")]
+    pub x_dcd: Option<ServiceHints>,
+}
+
+impl Service {
+    /// Whether the service shares the host's network stack, so that it
+    /// listens on host ports without publishing them.
+    pub fn uses_host_network(&self) -> bool {
+        self.network_mode.as_deref() == Some("host")
+    }
+
+    /// Dependencies declared via `depends_on`, sorted by service name.
+    pub fn dependencies(&self) -> Vec<ServiceDependency> {
+        let mut deps: Vec<ServiceDependency> = match &self.depends_on {
+            None => Vec::new(),
+            Some(DependsOn::List(names)) => names
+                .iter()
+                .map(|name| ServiceDependency {
+                    service: name.clone(),
+                    condition: DependencyCondition::Started,
+                    required: true,
+                })
+                .collect(),
+            Some(DependsOn::Conditions(map)) => map
+                .iter()
+                .map(|(name, cond)| ServiceDependency {
+                    service: name.clone(),
+                    condition: cond
+                        .condition
+                        .as_deref()
+                        .map(DependencyCondition::from)
+                        .unwrap_or(DependencyCondition::Started),
+                    required: cond.required.unwrap_or(true),
+                })
+                .collect(),
+        };
+        deps.sort_by(|a, b| a.service.cmp(&b.service));
+        deps
+    }
+
+    /// Whether the service says how it should be restarted, via `restart:`
+    /// or `deploy.restart_policy` (an explicit `restart: "no"` counts).
+    pub fn has_restart_policy(&self) -> bool {
+        self.restart.is_some()
+            || self
+                .deploy
+                .as_ref()
+                .is_some_and(|deploy| deploy.restart_policy.is_some())
+    }
+
+    /// Whether the service reserves GPUs, via `gpus:` or a device reservation
+    /// with the `gpu` capability or the `nvidia` driver.
+    pub fn requests_gpu(&self) -> bool {
+        let devices = self
+            .deploy
+            .as_ref()
+            .and_then(|d| d.resources.as_ref())
+            .and_then(|r| r.reservations.as_ref())
+            .and_then(|r| r.devices.as_ref());
+        self.gpus.is_some()
+            || devices.is_some_and(|devices| {
+                devices.iter().any(|device| {
+                    device.driver.as_deref() == Some("nvidia")
+                        || device.capabilities.iter().any(|c| c == "gpu")
+                })
+            })
+    }
+}
+
+/// `deploy:` section of a service; only the parts dcd acts on are modelled.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeploySpec {
+    pub resources: Option<ResourcesSpec>,
+    pub restart_policy: Option<serde_yaml::Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ResourcesSpec {
+    pub reservations: Option<ReservationsSpec>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReservationsSpec {
+    pub devices: Option<Vec<DeviceRequest>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceRequest {
+    pub driver: Option<String>,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+}
+
+/// Service labels as a mapping or a list of `key=value` strings.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Labels {
+    Map(HashMap<String, String>),
+    List(Vec<String>),
+}
+
+impl Labels {
+    pub fn get(&self, key: &str) -> Option<String> {
+        match self {
+            Labels::Map(map) => map.get(key).cloned(),
+            Labels::List(list) => list.iter().find_map(|entry| {
+                entry
+                    .split_once('=')
+                    .filter(|(k, _)| *k == key)
+                    .map(|(_, v)| v.to_string())
+            }),
+        }
+    }
+}
+
+/// `x-dcd:` extension of a service: deployment hints kept in the compose file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ServiceHints {
+    /// Leave the service out of the post-deploy health check
+    #[serde(default)]
+    pub skip_health_check: bool,
+    pub firewall: Option<FirewallHints>,
+    /// URL probed on the host once the service is healthy
+    pub smoke_test: Option<String>,
+    pub backup: Option<BackupHints>,
+    /// Ports a `network_mode: host` service listens on, as `port[/protocol]`;
+    /// opened in the firewall like published ports
+    #[serde(default)]
+    pub host_ports: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct FirewallHints {
+    /// Addresses or CIDR ranges allowed to reach the published ports
+    #[serde(default)]
+    pub allow: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct BackupHints {
+    /// Paths inside the container that hold data worth keeping
+    #[serde(default)]
+    pub paths: Vec<String>,
+}
+
+/// Periodic job declared via `dcd.cron.*` labels, run with `docker compose run`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CronJob {
+    pub service: String,
+    /// Five-field cron expression or a `@daily`-style shortcut
+    pub schedule: String,
+    /// Command passed to `docker compose run`; the service default when absent
+    pub command: Option<String>,
+}
+
+/// `depends_on` in either short (list) or long (mapping with conditions) syntax.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum DependsOn {
+    List(Vec<String>),
+    Conditions(HashMap<String, DependsOnEntry>),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DependsOnEntry {
+    pub condition: Option<String>,
+    pub required: Option<bool>,
+}
+
+/// Condition a dependent service waits for before it is started.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum DependencyCondition {
+    Started,
+    Healthy,
+    CompletedSuccessfully,
+}
+
+impl From<&str> for DependencyCondition {
+    fn from(s: &str) -> Self {
+        match s {
+            "service_healthy" => DependencyCondition::Healthy,
+            "service_completed_successfully" => DependencyCondition::CompletedSuccessfully,
+            _ => DependencyCondition::Started,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ServiceDependency {
+    pub service: String,
+    pub condition: DependencyCondition,
+    /// `false` for `required: false`: compose starts the dependent even when
+    /// this dependency fails to start
+    #[serde(default = "default_required")]
+    pub required: bool,
+}
+
+fn default_required() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HealthcheckConfig {
+    pub test: Option<HealthcheckTest>,
+    // Durations are kept as strings so typos can be reported with context
+    pub interval: Option<String>,
+    pub timeout: Option<String>,
+    pub start_period: Option<String>,
+    pub start_interval: Option<String>,
+    pub retries: Option<u64>,
+    pub disable: Option<bool>,
+}
+
+/// Healthcheck `test` as a shell string or an exec-form list (`CMD`, `CMD-SHELL`, `NONE`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum HealthcheckTest {
+    Shell(String),
+    List(Vec<String>),
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -82,7 +317,7 @@ pub struct ComposerConfig {
     pub env_files: Vec<PathBuf>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ComposerOutput {
     pub consumed_env: HashMap<String, String>,
     pub missing_env: Vec<String>,
@@ -94,6 +329,22 @@ pub struct ComposerOutput {
     pub resolved_env_files: Vec<PathBuf>,
     pub available_profiles: Vec<String>,
     pub active_profiles: Vec<String>,
+    /// Services of the resolved configuration, sorted by name
+    pub services: Vec<String>,
+    /// `depends_on` relations keyed by dependent service name
+    pub service_dependencies: HashMap<String, Vec<ServiceDependency>>,
+    /// Non-fatal problems found during analysis
+    pub warnings: Vec<String>,
+    /// Periodic jobs declared via service labels
+    pub cron_jobs: Vec<CronJob>,
+    /// Services that reserve GPUs
+    pub gpu_services: Vec<String>,
+    /// Services with `network_mode: host`
+    pub host_network_services: Vec<String>,
+    /// Long-running services without a restart policy
+    pub services_without_restart: Vec<String>,
+    /// `x-dcd` hints keyed by service name
+    pub service_hints: BTreeMap<String, ServiceHints>,
 }
 
 impl Default for ComposerOutput {
@@ -115,6 +366,14 @@ impl ComposerOutput {
             resolved_env_files: Vec::new(),
             available_profiles: Vec::new(),
             active_profiles: Vec::new(),
+            services: Vec::new(),
+            service_dependencies: HashMap::new(),
+            warnings: Vec::new(),
+            cron_jobs: Vec::new(),
+            gpu_services: Vec::new(),
+            host_network_services: Vec::new(),
+            services_without_restart: Vec::new(),
+            service_hints: BTreeMap::new(),
         }
     }
 }
@@ -0,0 +1,195 @@
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// Points of the deploy pipeline where hooks run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HookStage {
+    /// Before project files are synced
+    PreSync,
+    /// After project files are synced
+    PostSync,
+    /// After images are pulled and built, right before `compose up`
+    PreUp,
+    /// Right after `compose up`
+    PostUp,
+    /// Once the services passed the health check
+    PostHealthy,
+}
+
+impl HookStage {
+    pub const ALL: [HookStage; 5] = [
+        Self::PreSync,
+        Self::PostSync,
+        Self::PreUp,
+        Self::PostUp,
+        Self::PostHealthy,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::PreSync => "pre-sync",
+            Self::PostSync => "post-sync",
+            Self::PreUp => "pre-up",
+            Self::PostUp => "post-up",
+            Self::PostHealthy => "post-healthy",
+        }
+    }
+}
+
+impl fmt::Display for HookStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for HookStage {
+    type Err = String;
+
+    /// Accepts `post-up` as well as the `post_up` spelling of dcd.toml.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().replace('_', "-");
+        Self::ALL
+            .into_iter()
+            .find(|stage| stage.as_str() == name)
+            .ok_or_else(|| {
+                format!(
+                    "unknown hook stage '{}' (expected one of: {})",
+                    s,
+                    Self::ALL.map(|stage| stage.as_str()).join(", ")
+                )
+            })
+    }
+}
+
+/// A command run at one stage of a deployment.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(from = "HookSpec")]
+pub struct Hook {
+    /// Shell command
+    pub run: String,
+    /// Run on the machine dcd runs on instead of the server
+    pub local: bool,
+    /// Fail the deployment when the command exits non-zero
+    pub required: bool,
+}
+
+impl Hook {
+    /// A required hook run on the server.
+    pub fn remote(run: impl Into<String>) -> Self {
+        Self {
+            run: run.into(),
+            local: false,
+            required: true,
+        }
+    }
+
+    /// A required hook run on the machine dcd runs on.
+    pub fn local(run: impl Into<String>) -> Self {
+        Self {
+            local: true,
+            ..Self::remote(run)
+        }
+    }
+}
+
+/// A hook is either just its command or a table with the options.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum HookSpec {
+    Command(String),
+    Table(HookTable),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HookTable {
+    run: String,
+    #[serde(default)]
+    local: bool,
+    #[serde(default = "default_required")]
+    required: bool,
+}
+
+fn default_required() -> bool {
+    true
+}
+
+impl From<HookSpec> for Hook {
+    fn from(spec: HookSpec) -> Self {
+        match spec {
+            HookSpec::Command(run) => Hook::remote(run),
+            HookSpec::Table(table) => Hook {
+                run: table.run,
+                local: table.local,
+                required: table.required,
+            },
+        }
+    }
+}
+
+/// Hooks of the `[hooks]` table, in the order they run within each stage.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Hooks {
+    #[serde(default)]
+    pub pre_sync: Vec<Hook>,
+    #[serde(default)]
+    pub post_sync: Vec<Hook>,
+    #[serde(default)]
+    pub pre_up: Vec<Hook>,
+    #[serde(default)]
+    pub post_up: Vec<Hook>,
+    #[serde(default)]
+    pub post_healthy: Vec<Hook>,
+}
+
+impl Hooks {
+    pub fn get(&self, stage: HookStage) -> &[Hook] {
+        match stage {
+            HookStage::PreSync => &self.pre_sync,
+            HookStage::PostSync => &self.post_sync,
+            HookStage::PreUp => &self.pre_up,
+            HookStage::PostUp => &self.post_up,
+            HookStage::PostHealthy => &self.post_healthy,
+        }
+    }
+
+    /// Add `hook` after the ones already configured for `stage`.
+    pub fn push(&mut self, stage: HookStage, hook: Hook) {
+        match stage {
+            HookStage::PreSync => self.pre_sync.push(hook),
+            HookStage::PostSync => self.post_sync.push(hook),
+            HookStage::PreUp => self.pre_up.push(hook),
+            HookStage::PostUp => self.post_up.push(hook),
+            HookStage::PostHealthy => self.post_healthy.push(hook),
+        }
+    }
+
+    pub(super) fn validate(&self) -> Result<(), String> {
+        for stage in HookStage::ALL {
+            if self
+                .get(stage)
+                .iter()
+                .any(|hook| hook.run.trim().is_empty())
+            {
+                return Err(format!(
+                    "{}: hook command is empty",
+                    stage.as_str().replace('-', "_")
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse a `STAGE=COMMAND` hook given on the command line.
+pub fn parse_hook_arg(arg: &str) -> Result<(HookStage, String), String> {
+    let (stage, command) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected STAGE=COMMAND, got '{}'", arg))?;
+    if command.trim().is_empty() {
+        return Err(format!("hook command for '{}' is empty", stage));
+    }
+    Ok((stage.parse()?, command.to_string()))
+}
@@ -0,0 +1,83 @@
+use serde::Deserialize;
+use std::fmt;
+
+/// Value of a sysctl or ulimit entry; TOML allows either form.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum HostValue {
+    Int(u64),
+    Text(String),
+}
+
+impl fmt::Display for HostValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HostValue::Int(value) => write!(f, "{}", value),
+            HostValue::Text(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// Validate a sysctl key/value pair, e.g. `vm.max_map_count = 262144` or
+/// `net.ipv4.tcp_rmem = "4096 87380 6291456"`.
+pub(super) fn validate_sysctl(key: &str, value: &HostValue) -> Result<(), String> {
+    if key.is_empty()
+        || !key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '/'))
+    {
+        return Err(format!("invalid sysctl key '{}'", key));
+    }
+    let value = value.to_string();
+    if value.trim().is_empty()
+        || !value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, ' ' | '.' | '_' | '-' | ':'))
+    {
+        return Err(format!("invalid value '{}' for {}", value, key));
+    }
+    Ok(())
+}
+
+/// systemd `Limit*` directive for a ulimit name (as used by docker/compose).
+pub fn ulimit_directive(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "nofile" => "LimitNOFILE",
+        "nproc" => "LimitNPROC",
+        "memlock" => "LimitMEMLOCK",
+        "core" => "LimitCORE",
+        "stack" => "LimitSTACK",
+        "fsize" => "LimitFSIZE",
+        "sigpending" => "LimitSIGPENDING",
+        "msgqueue" => "LimitMSGQUEUE",
+        _ => return None,
+    })
+}
+
+/// Render a ulimit value for systemd: a number, `soft:hard` or `unlimited`.
+pub fn ulimit_value(value: &HostValue) -> Option<String> {
+    match value {
+        HostValue::Int(value) => Some(value.to_string()),
+        HostValue::Text(text) if matches!(text.as_str(), "unlimited" | "infinity") => {
+            Some("infinity".to_string())
+        }
+        HostValue::Text(text) => {
+            let (soft, hard) = text.split_once(':').unwrap_or((text, text));
+            let valid = |v: &str| v == "infinity" || v.parse::<u64>().is_ok();
+            (valid(soft) && valid(hard)).then(|| text.clone())
+        }
+    }
+}
+
+pub(super) fn validate_ulimit(name: &str, value: &HostValue) -> Result<(), String> {
+    if ulimit_directive(name).is_none() {
+        return Err(format!("unsupported ulimit '{}'", name));
+    }
+    if ulimit_value(value).is_none() {
+        return Err(format!(
+            "invalid value '{}' for {} (use a number, soft:hard or unlimited)",
+            value, name
+        ));
+    }
+    Ok(())
+}
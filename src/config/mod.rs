@@ -0,0 +1,582 @@
+//! Optional per-project settings read from `dcd.toml` (or `.dcd.yaml`) next
+//! to the compose files.
+
+mod hooks;
+mod host;
+mod secrets;
+mod stacks;
+mod wait;
+
+use crate::composer::config::duration::parse_duration;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use thiserror::Error;
+
+pub use hooks::{parse_hook_arg, Hook, HookStage, Hooks};
+pub use host::{ulimit_directive, ulimit_value, HostValue};
+pub use secrets::SecretSource;
+pub use stacks::{is_valid_stack_name, StackConfig};
+pub use wait::{WaitKind, WaitStrategy};
+
+pub const PROJECT_CONFIG_FILE: &str = "dcd.toml";
+
+/// YAML alternative to [`PROJECT_CONFIG_FILE`]; a project may have only one.
+pub const PROJECT_CONFIG_YAML_FILE: &str = ".dcd.yaml";
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Failed to read {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Failed to parse {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error("Failed to parse {path}: {source}")]
+    ParseYaml {
+        path: PathBuf,
+        source: serde_yaml::Error,
+    },
+
+    #[error("Invalid value in {path}: {details}")]
+    Invalid { path: PathBuf, details: String },
+}
+
+pub type ConfigResult<T> = Result<T, ConfigError>;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// Default `[user@]host[:port]` for commands that take a single target
+    #[serde(default)]
+    pub target: Option<String>,
+    /// Default SSH private key (`-i`)
+    #[serde(default)]
+    pub identity: Option<PathBuf>,
+    /// Default remote working directory (`-w`)
+    #[serde(default)]
+    pub workdir: Option<PathBuf>,
+    /// Default compose files (`-f`)
+    #[serde(default)]
+    pub compose_files: Vec<PathBuf>,
+    /// Default env files (`-e`)
+    #[serde(default)]
+    pub env_files: Vec<PathBuf>,
+    /// Extra readiness checks keyed by service name
+    #[serde(default)]
+    pub wait: BTreeMap<String, WaitStrategy>,
+    /// Shared external networks (e.g. `proxy`) created on the host if missing
+    #[serde(default)]
+    pub networks: Vec<String>,
+    /// OS packages installed on the host before deploying (e.g. `nfs-common`)
+    #[serde(default)]
+    pub host_packages: Vec<String>,
+    /// Kernel parameters applied on the host (e.g. `vm.max_map_count`)
+    #[serde(default)]
+    pub sysctl: BTreeMap<String, HostValue>,
+    /// Resource limits for the docker daemon and its containers (e.g. `nofile`)
+    #[serde(default)]
+    pub ulimits: BTreeMap<String, HostValue>,
+    /// known_hosts file used instead of `~/.ssh/known_hosts`
+    #[serde(default)]
+    pub known_hosts: Option<PathBuf>,
+    /// Default bastion `[user@]host[:port]` to connect through (`--jump`)
+    #[serde(default)]
+    pub jump: Option<String>,
+    /// age identity (kept locally) used to encrypt the env stored on the server
+    #[serde(default)]
+    pub age_identity: Option<PathBuf>,
+    /// Template (e.g. `.env.example`) the remote .env is generated from on
+    /// the first deploy; edits made on the server are kept afterwards
+    #[serde(default)]
+    pub env_template: Option<PathBuf>,
+    /// Maximum number of files uploaded in parallel when syncing a directory
+    #[serde(default)]
+    pub sync_concurrency: Option<usize>,
+    /// SSH channels budgeted per direct connection, capping SFTP upload
+    /// channels (`--ssh-channels`)
+    #[serde(default)]
+    pub ssh_channels: Option<u16>,
+    /// Hosts deployed to at once in multi-host mode (`--parallel-hosts`)
+    #[serde(default)]
+    pub parallel_hosts: Option<u16>,
+    /// Variables fetched from secret stores at deploy time and written only
+    /// to the remote .env.dcd, keyed by variable name
+    #[serde(default)]
+    pub secrets: BTreeMap<String, SecretSource>,
+    /// How long services may take to leave the `starting` state (e.g. `5m`)
+    #[serde(default)]
+    pub health_timeout: Option<String>,
+    /// Health check attempts before unhealthy services fail the deployment
+    #[serde(default)]
+    pub health_retries: Option<u32>,
+    /// Pause between health check attempts (e.g. `10s`)
+    #[serde(default)]
+    pub health_interval: Option<String>,
+    /// Watch Docker events during the health check to notice containers
+    /// becoming healthy or failing without waiting for the next interval
+    #[serde(default)]
+    pub health_watch: bool,
+    /// Commands run at stages of a deployment, e.g. migrations after `compose up`
+    #[serde(default)]
+    pub hooks: Hooks,
+    /// Compose projects in subdirectories, deployed with `up --stack <name>`
+    #[serde(default)]
+    pub stacks: BTreeMap<String, StackConfig>,
+}
+
+impl ProjectConfig {
+    /// Load `dcd.toml` or `.dcd.yaml` from `project_dir`, returning defaults
+    /// if neither exists.
+    pub fn load(project_dir: &Path) -> ConfigResult<Self> {
+        let toml_path = project_dir.join(PROJECT_CONFIG_FILE);
+        let yaml_path = project_dir.join(PROJECT_CONFIG_YAML_FILE);
+        let (path, yaml) = match (toml_path.exists(), yaml_path.exists()) {
+            (false, false) => return Ok(Self::default()),
+            (true, true) => {
+                return Err(ConfigError::Invalid {
+                    path: yaml_path,
+                    details: format!("{} exists too; keep only one of them", PROJECT_CONFIG_FILE),
+                })
+            }
+            (true, false) => (toml_path, false),
+            (false, true) => (yaml_path, true),
+        };
+        let content = std::fs::read_to_string(&path).map_err(|source| ConfigError::Io {
+            path: path.clone(),
+            source,
+        })?;
+        let parsed = if yaml {
+            Self::parse_yaml(&content)
+        } else {
+            Self::parse(&content)
+        };
+        parsed.map_err(|e| match e {
+            ConfigError::Parse { source, .. } => ConfigError::Parse { path, source },
+            ConfigError::ParseYaml { source, .. } => ConfigError::ParseYaml { path, source },
+            ConfigError::Invalid { details, .. } => ConfigError::Invalid { path, details },
+            other => other,
+        })
+    }
+
+    /// Parse and validate `dcd.toml` contents.
+    pub fn parse(content: &str) -> ConfigResult<Self> {
+        let config: Self = toml::from_str(content).map_err(|source| ConfigError::Parse {
+            path: PathBuf::from(PROJECT_CONFIG_FILE),
+            source,
+        })?;
+        config.validate(PROJECT_CONFIG_FILE)
+    }
+
+    /// Parse and validate `.dcd.yaml` contents.
+    pub fn parse_yaml(content: &str) -> ConfigResult<Self> {
+        // An empty file is an empty mapping, not an error
+        let config: Self = if content.trim().is_empty() {
+            Self::default()
+        } else {
+            serde_yaml::from_str(content).map_err(|source| ConfigError::ParseYaml {
+                path: PathBuf::from(PROJECT_CONFIG_YAML_FILE),
+                source,
+            })?
+        };
+        config.validate(PROJECT_CONFIG_YAML_FILE)
+    }
+
+    pub fn health_timeout(&self) -> Option<Duration> {
+        self.health_timeout.as_deref().and_then(parse_duration)
+    }
+
+    pub fn health_interval(&self) -> Option<Duration> {
+        self.health_interval.as_deref().and_then(parse_duration)
+    }
+
+    fn validate(self, file: &str) -> ConfigResult<Self> {
+        for (service, strategy) in &self.wait {
+            strategy
+                .validate()
+                .map_err(|details| ConfigError::Invalid {
+                    path: PathBuf::from(file),
+                    details: format!("wait.{}: {}", service, details),
+                })?;
+        }
+        for network in &self.networks {
+            if !is_valid_network_name(network) {
+                return Err(ConfigError::Invalid {
+                    path: PathBuf::from(file),
+                    details: format!("networks: '{}' is not a valid network name", network),
+                });
+            }
+        }
+        for (key, value) in &self.sysctl {
+            host::validate_sysctl(key, value).map_err(|details| ConfigError::Invalid {
+                path: PathBuf::from(file),
+                details: format!("sysctl: {}", details),
+            })?;
+        }
+        for (name, value) in &self.ulimits {
+            host::validate_ulimit(name, value).map_err(|details| ConfigError::Invalid {
+                path: PathBuf::from(file),
+                details: format!("ulimits: {}", details),
+            })?;
+        }
+        if self.sync_concurrency == Some(0) {
+            return Err(ConfigError::Invalid {
+                path: PathBuf::from(file),
+                details: "sync_concurrency must be at least 1".into(),
+            });
+        }
+        if self.ssh_channels.is_some_and(|channels| channels < 2) {
+            return Err(ConfigError::Invalid {
+                path: PathBuf::from(file),
+                details: "ssh_channels must be at least 2 (one for uploads, one for commands)"
+                    .into(),
+            });
+        }
+        if self.parallel_hosts == Some(0) {
+            return Err(ConfigError::Invalid {
+                path: PathBuf::from(file),
+                details: "parallel_hosts must be at least 1".into(),
+            });
+        }
+        for (key, value, duration) in [
+            (
+                "health_timeout",
+                &self.health_timeout,
+                self.health_timeout(),
+            ),
+            (
+                "health_interval",
+                &self.health_interval,
+                self.health_interval(),
+            ),
+        ] {
+            if let Some(value) = value {
+                if duration.is_none_or(|duration| duration.is_zero()) {
+                    return Err(ConfigError::Invalid {
+                        path: PathBuf::from(file),
+                        details: format!(
+                            "{}: '{}' is not a valid duration (e.g. 30s, 5m)",
+                            key, value
+                        ),
+                    });
+                }
+            }
+        }
+        if self.health_retries == Some(0) {
+            return Err(ConfigError::Invalid {
+                path: PathBuf::from(file),
+                details: "health_retries must be at least 1".into(),
+            });
+        }
+        for (name, source) in &self.secrets {
+            if !crate::executor::traits::is_valid_env_name(name) {
+                return Err(ConfigError::Invalid {
+                    path: PathBuf::from(file),
+                    details: format!("secrets: '{}' is not a valid variable name", name),
+                });
+            }
+            source.validate().map_err(|details| ConfigError::Invalid {
+                path: PathBuf::from(file),
+                details: format!("secrets.{}: {}", name, details),
+            })?;
+        }
+        self.hooks
+            .validate()
+            .map_err(|details| ConfigError::Invalid {
+                path: PathBuf::from(file),
+                details: format!("hooks.{}", details),
+            })?;
+        for (name, stack) in &self.stacks {
+            stack
+                .validate(name)
+                .map_err(|details| ConfigError::Invalid {
+                    path: PathBuf::from(file),
+                    details: format!("stacks.{}", details),
+                })?;
+        }
+        for package in &self.host_packages {
+            if !is_valid_package_name(package) {
+                return Err(ConfigError::Invalid {
+                    path: PathBuf::from(file),
+                    details: format!("host_packages: '{}' is not a valid package name", package),
+                });
+            }
+        }
+        Ok(self)
+    }
+}
+
+/// Package names across apt/dnf/apk: alphanumerics plus `+._-`, starting
+/// with an alphanumeric.
+fn is_valid_package_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphanumeric())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '.' | '_' | '-'))
+}
+
+/// Docker network names: `[a-zA-Z0-9][a-zA-Z0-9_.-]*`.
+fn is_valid_network_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphanumeric())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_wait_strategies() {
+        let config = ProjectConfig::parse(
+            r#"
+            wait.web = { type = "http", url = "http://localhost:8080/health", timeout = "120s" }
+
+            [wait.db]
+            type = "tcp"
+            port = 5432
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.wait.len(), 2);
+        assert_eq!(
+            config.wait["web"].timeout().unwrap(),
+            std::time::Duration::from_secs(120)
+        );
+        assert!(matches!(
+            config.wait["db"].kind,
+            WaitKind::Tcp { port: 5432, .. }
+        ));
+    }
+
+    #[test]
+    fn test_parse_networks() {
+        let config = ProjectConfig::parse(r#"networks = ["proxy", "shared_db"]"#).unwrap();
+        assert_eq!(config.networks, vec!["proxy", "shared_db"]);
+        assert!(ProjectConfig::parse(r#"networks = ["proxy; rm -rf /"]"#).is_err());
+        assert!(ProjectConfig::parse(r#"networks = ["-proxy"]"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_sync_concurrency() {
+        let config = ProjectConfig::parse("sync_concurrency = 16").unwrap();
+        assert_eq!(config.sync_concurrency, Some(16));
+        assert_eq!(ProjectConfig::parse("").unwrap().sync_concurrency, None);
+        assert!(ProjectConfig::parse("sync_concurrency = 0").is_err());
+    }
+
+    #[test]
+    fn test_parse_connection_limits() {
+        let config = ProjectConfig::parse(
+            "ssh_channels = 2
+parallel_hosts = 3",
+        )
+        .unwrap();
+        assert_eq!(config.ssh_channels, Some(2));
+        assert_eq!(config.parallel_hosts, Some(3));
+        assert!(ProjectConfig::parse("ssh_channels = 1").is_err());
+        assert!(ProjectConfig::parse("parallel_hosts = 0").is_err());
+    }
+
+    #[test]
+    fn test_parse_host_packages() {
+        let config =
+            ProjectConfig::parse(r#"host_packages = ["nfs-common", "libstdc++6"]"#).unwrap();
+        assert_eq!(config.host_packages, vec!["nfs-common", "libstdc++6"]);
+        assert!(ProjectConfig::parse(r#"host_packages = ["curl && reboot"]"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_sysctl_and_ulimits() {
+        let config = ProjectConfig::parse(
+            r#"
+            [sysctl]
+            "vm.max_map_count" = 262144
+            "net.ipv4.tcp_rmem" = "4096 87380 6291456"
+
+            [ulimits]
+            nofile = "65536:65536"
+            memlock = "unlimited"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.sysctl["vm.max_map_count"].to_string(), "262144");
+        assert_eq!(
+            ulimit_value(&config.ulimits["memlock"]).as_deref(),
+            Some("infinity")
+        );
+        assert!(ProjectConfig::parse(r#"sysctl = { "vm.swappiness" = "1; reboot" }"#).is_err());
+        assert!(ProjectConfig::parse(r#"ulimits = { nofile = "lots" }"#).is_err());
+        assert!(ProjectConfig::parse(r#"ulimits = { cpu = 1 }"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_secrets() {
+        let config = ProjectConfig::parse(
+            r#"
+            [secrets]
+            DB_PASSWORD = { from = "env", var = "PROD_DB_PASSWORD" }
+            API_KEY = { from = "file", path = "~/.secrets/api_key" }
+            SMTP_PASSWORD = { from = "exec", command = "pass show smtp" }
+            STRIPE_KEY = { from = "vault", path = "secret/app", field = "stripe" }
+            JWT_SECRET = { from = "sops", file = "secrets.enc.yaml", key = "jwt" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.secrets.len(), 5);
+        assert_eq!(
+            config.secrets["STRIPE_KEY"],
+            SecretSource::Vault {
+                path: "secret/app".into(),
+                field: "stripe".into()
+            }
+        );
+
+        let yaml = ProjectConfig::parse_yaml(
+            "secrets:\n  DB_PASSWORD:\n    from: env\n    var: PROD_DB_PASSWORD\n",
+        )
+        .unwrap();
+        assert_eq!(yaml.secrets["DB_PASSWORD"], config.secrets["DB_PASSWORD"]);
+
+        assert!(ProjectConfig::parse(r#"secrets.DB = { from = "ssm", name = "db" }"#).is_err());
+        assert!(ProjectConfig::parse(r#"secrets.DB = { from = "exec", command = " " }"#).is_err());
+        assert!(
+            ProjectConfig::parse(r#"secrets."DB PASS" = { from = "env", var = "DB" }"#).is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_hooks() {
+        let config = ProjectConfig::parse(
+            r#"
+            [hooks]
+            pre_sync = [{ run = "make assets", local = true }]
+            post_up = [
+                "$DCD_COMPOSE exec -T web ./manage.py migrate",
+                { run = "curl -fsS https://hooks.example.com/deployed", required = false },
+            ]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.hooks.pre_sync, vec![Hook::local("make assets")]);
+        assert_eq!(
+            config.hooks.get(HookStage::PostUp)[0],
+            Hook::remote("$DCD_COMPOSE exec -T web ./manage.py migrate")
+        );
+        assert!(!config.hooks.post_up[1].required);
+        assert!(config.hooks.post_healthy.is_empty());
+
+        assert!(ProjectConfig::parse(r#"hooks.post_deploy = ["true"]"#).is_err());
+        assert!(ProjectConfig::parse(r#"hooks.post_up = [" "]"#).is_err());
+        assert!(ProjectConfig::parse(r#"hooks.post_up = [{ run = "x", on = "db" }]"#).is_err());
+
+        assert_eq!(
+            parse_hook_arg("post_healthy=./notify.sh --ok").unwrap(),
+            (HookStage::PostHealthy, "./notify.sh --ok".to_string())
+        );
+        assert!(parse_hook_arg("post-deploy=true").is_err());
+        assert!(parse_hook_arg("pre-up").is_err());
+    }
+
+    #[test]
+    fn test_parse_stacks() {
+        let config = ProjectConfig::parse(
+            r#"
+            [stacks.api]
+            path = "services/api"
+            compose_files = ["compose.yml", "compose.prod.yml"]
+            workdir = "/opt/api"
+
+            [stacks.web]
+            env_files = [".env.production"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.stacks.len(), 2);
+        assert_eq!(
+            config.stacks["api"].dir("api"),
+            PathBuf::from("services/api")
+        );
+        assert_eq!(config.stacks["web"].dir("web"), PathBuf::from("web"));
+        assert_eq!(config.stacks["web"].workdir, None);
+
+        assert!(ProjectConfig::parse(r#"stacks."my api" = {}"#).is_err());
+        assert!(ProjectConfig::parse(r#"stacks.api = { path = "/srv/api" }"#).is_err());
+        assert!(ProjectConfig::parse(r#"stacks.api = { dir = "api" }"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_defaults() {
+        let config = ProjectConfig::parse(
+            r#"
+            target = "deploy@example.com:2222"
+            identity = "~/.ssh/deploy_key"
+            workdir = "/opt/app"
+            compose_files = ["docker-compose.yml", "docker-compose.prod.yml"]
+            env_files = [".env.production"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.target.as_deref(), Some("deploy@example.com:2222"));
+        assert_eq!(config.workdir, Some(PathBuf::from("/opt/app")));
+        assert_eq!(config.compose_files.len(), 2);
+
+        let yaml = ProjectConfig::parse_yaml(
+            "target: deploy@example.com:2222\nidentity: ~/.ssh/deploy_key\nworkdir: /opt/app\n\
+             compose_files:\n  - docker-compose.yml\n  - docker-compose.prod.yml\n\
+             env_files: [.env.production]\n",
+        )
+        .unwrap();
+        assert_eq!(yaml.target, config.target);
+        assert_eq!(yaml.identity, config.identity);
+        assert_eq!(yaml.compose_files, config.compose_files);
+        assert_eq!(yaml.env_files, config.env_files);
+        assert!(ProjectConfig::parse_yaml("").unwrap().target.is_none());
+        assert!(ProjectConfig::parse_yaml("networks: [\"-proxy\"]").is_err());
+    }
+
+    #[test]
+    fn test_load_rejects_both_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(PROJECT_CONFIG_YAML_FILE),
+            "workdir: /opt/a\n",
+        )
+        .unwrap();
+        let config = ProjectConfig::load(dir.path()).unwrap();
+        assert_eq!(config.workdir, Some(PathBuf::from("/opt/a")));
+
+        std::fs::write(dir.path().join(PROJECT_CONFIG_FILE), "").unwrap();
+        assert!(ProjectConfig::load(dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_timeout() {
+        let err = ProjectConfig::parse(
+            r#"wait.web = { type = "http", url = "http://localhost/", timeout = "2 minutes" }"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("wait.web"));
+    }
+
+    #[test]
+    fn test_parse_health_settings() {
+        let config = ProjectConfig::parse(
+            "health_timeout = \"5m\"\nhealth_retries = 8\nhealth_interval = \"5s\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.health_timeout(), Some(Duration::from_secs(300)));
+        assert_eq!(config.health_interval(), Some(Duration::from_secs(5)));
+        assert_eq!(config.health_retries, Some(8));
+
+        assert!(ProjectConfig::parse("health_interval = \"0\"").is_err());
+        assert!(ProjectConfig::parse("health_timeout = \"5 minutes\"").is_err());
+        assert!(ProjectConfig::parse("health_retries = 0").is_err());
+    }
+}
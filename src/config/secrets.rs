@@ -0,0 +1,54 @@
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// Where the value of a `[secrets]` entry comes from. Values are fetched on
+/// the local machine at deploy time and only ever written to the remote
+/// `.env.dcd`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "from", rename_all = "lowercase", deny_unknown_fields)]
+pub enum SecretSource {
+    /// A variable of the local environment, e.g. one set by CI
+    Env { var: String },
+    /// Contents of a local file (a trailing newline is dropped)
+    File { path: PathBuf },
+    /// Standard output of a shell command run in the project directory,
+    /// e.g. `pass show app/db`
+    Exec { command: String },
+    /// A field of a HashiCorp Vault KV secret, read with the `vault` CLI
+    Vault { path: String, field: String },
+    /// A top-level key of a SOPS-encrypted file, read with the `sops` CLI
+    Sops { file: PathBuf, key: String },
+}
+
+impl SecretSource {
+    /// Short description of the source that never includes the value.
+    pub fn describe(&self) -> String {
+        match self {
+            SecretSource::Env { var } => format!("environment variable {}", var),
+            SecretSource::File { path } => format!("file {}", path.display()),
+            SecretSource::Exec { command } => format!("command `{}`", command),
+            SecretSource::Vault { path, field } => format!("vault {} (field {})", path, field),
+            SecretSource::Sops { file, key } => format!("sops {} (key {})", file.display(), key),
+        }
+    }
+
+    pub(super) fn validate(&self) -> Result<(), String> {
+        let empty = match self {
+            SecretSource::Env { var } => var.is_empty(),
+            SecretSource::File { path } => path.as_os_str().is_empty(),
+            SecretSource::Exec { command } => command.trim().is_empty(),
+            SecretSource::Vault { path, field } => path.is_empty() || field.is_empty(),
+            SecretSource::Sops { file, key } => file.as_os_str().is_empty() || key.is_empty(),
+        };
+        if empty {
+            return Err("source has an empty setting".into());
+        }
+        if let SecretSource::Sops { key, .. } = self {
+            // Interpolated into the `--extract` path expression
+            if key.contains(['"', '[', ']']) {
+                return Err(format!("invalid sops key '{}'", key));
+            }
+        }
+        Ok(())
+    }
+}
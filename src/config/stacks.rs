@@ -0,0 +1,52 @@
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// One compose project of a repository holding several (`[stacks.<name>]`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StackConfig {
+    /// Directory of the stack relative to dcd.toml (default: the stack name)
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    /// Compose files relative to the stack directory (default: its docker-compose.yml)
+    #[serde(default)]
+    pub compose_files: Vec<PathBuf>,
+    /// Env files relative to the stack directory (default: its .env)
+    #[serde(default)]
+    pub env_files: Vec<PathBuf>,
+    /// Remote directory of the stack
+    #[serde(default)]
+    pub workdir: Option<PathBuf>,
+}
+
+impl StackConfig {
+    /// Directory of the stack named `name`.
+    pub fn dir(&self, name: &str) -> PathBuf {
+        self.path.clone().unwrap_or_else(|| PathBuf::from(name))
+    }
+
+    pub(super) fn validate(&self, name: &str) -> Result<(), String> {
+        if !is_valid_stack_name(name) {
+            return Err(format!("'{}' is not a valid stack name", name));
+        }
+        let relative = |path: &Path| path.is_relative();
+        if !self.path.as_deref().is_none_or(relative)
+            || !self.compose_files.iter().all(|path| relative(path))
+            || !self.env_files.iter().all(|path| relative(path))
+        {
+            return Err(format!(
+                "{}: path, compose_files and env_files must be relative",
+                name
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Stack names: `[A-Za-z0-9_-]+`, as they end up in labels and log lines.
+pub fn is_valid_stack_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
@@ -0,0 +1,91 @@
+use crate::composer::config::duration::parse_duration;
+use serde::Deserialize;
+use std::time::Duration;
+
+const DEFAULT_WAIT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Readiness check evaluated on the remote host after `compose up`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct WaitStrategy {
+    #[serde(flatten)]
+    pub kind: WaitKind,
+    /// How long to keep probing, e.g. "120s" (default 60s)
+    pub timeout: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum WaitKind {
+    /// Succeeds once the URL answers with a 2xx/3xx status
+    Http { url: String },
+    /// Succeeds once a TCP connection can be opened
+    Tcp {
+        #[serde(default = "default_host")]
+        host: String,
+        port: u16,
+    },
+}
+
+fn default_host() -> String {
+    "localhost".to_string()
+}
+
+impl WaitStrategy {
+    pub fn timeout(&self) -> Option<Duration> {
+        match &self.timeout {
+            Some(value) => parse_duration(value),
+            None => Some(DEFAULT_WAIT_TIMEOUT),
+        }
+    }
+
+    pub(super) fn validate(&self) -> Result<(), String> {
+        if self.timeout().is_none() {
+            return Err(format!(
+                "timeout '{}' is not a valid duration (e.g. 30s, 2m)",
+                self.timeout.as_deref().unwrap_or_default()
+            ));
+        }
+        match &self.kind {
+            WaitKind::Http { url } => {
+                let parsed = url::Url::parse(url).map_err(|e| format!("invalid url: {}", e))?;
+                if !matches!(parsed.scheme(), "http" | "https") {
+                    return Err(format!("url must be http(s), got '{}'", url));
+                }
+            }
+            WaitKind::Tcp { host, .. } => {
+                if host.is_empty()
+                    || !host
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | ':'))
+                {
+                    return Err(format!("invalid host '{}'", host));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Shell command that exits 0 once the check passes. Runs on the remote host.
+    pub fn probe_command(&self) -> String {
+        match &self.kind {
+            WaitKind::Http { url } => {
+                let url = url.replace('\'', "'\\''");
+                format!(
+                    "if command -v curl >/dev/null 2>&1; then curl -fsS -o /dev/null --max-time 5 '{url}'; \
+                     else wget -q -O /dev/null -T 5 '{url}'; fi"
+                )
+            }
+            WaitKind::Tcp { host, port } => format!(
+                "if command -v nc >/dev/null 2>&1; then nc -z -w 5 {host} {port}; \
+                 else timeout 5 bash -c '</dev/tcp/{host}/{port}'; fi"
+            ),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match &self.kind {
+            WaitKind::Http { url } => format!("HTTP {}", url),
+            WaitKind::Tcp { host, port } => format!("TCP {}:{}", host, port),
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use crate::composer::types::CronJob;
+use crate::deployer::types::{ComposeExec, DeployError, DeployResult};
+use crate::deployer::{compose_command, project_slug};
+use crate::executor::shell_quote;
+use std::path::{Path, PathBuf};
+
+const CRON_DIR: &str = "/etc/cron.d";
+
+/// `/etc/cron.d` entry name for a remote project directory.
+pub fn cron_file_name(working_directory: &Path) -> String {
+    format!("dcd-{}", project_slug(working_directory))
+}
+
+/// Render the cron.d file running each job via `docker-compose run`.
+pub fn render_cron_file(
+    user: &str,
+    working_directory: &Path,
+    compose_files: &[PathBuf],
+    env_files: &[PathBuf],
+    jobs: &[CronJob],
+) -> String {
+    let log_file = format!("/var/log/{}-cron.log", cron_file_name(working_directory));
+    let mut content = String::from(
+        "# Managed by dcd, changes will be overwritten on the next deploy\n\
+         SHELL=/bin/sh\n\
+         PATH=/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin\n",
+    );
+    for job in jobs {
+        let mut run = format!("run --rm -T {}", job.service);
+        if let Some(command) = &job.command {
+            run.push(' ');
+            run.push_str(command);
+        }
+        let line = format!(
+            "{} >> {} 2>&1",
+            compose_command(working_directory, compose_files, env_files, &run),
+            log_file
+        );
+        // cron treats an unescaped % as a newline
+        content.push_str(&format!(
+            "{} {} {}\n",
+            job.schedule,
+            user,
+            line.replace('%', "\\%")
+        ));
+    }
+    content
+}
+
+pub struct CronManager<'a> {
+    executor: &'a mut (dyn ComposeExec + Send),
+}
+
+impl<'a> CronManager<'a> {
+    pub fn new(executor: &'a mut (dyn ComposeExec + Send)) -> Self {
+        Self { executor }
+    }
+
+    async fn run(&mut self, cmd: &str) -> DeployResult<String> {
+        let result = self
+            .executor
+            .execute_command(cmd)
+            .await
+            .map_err(|e| DeployError::Deployment(format!("Failed to run '{}': {}", cmd, e)))?;
+        if !result.is_success() {
+            return Err(DeployError::Deployment(format!(
+                "Command '{}' failed: {}",
+                cmd,
+                result.output.to_stderr_string()?.trim()
+            )));
+        }
+        Ok(result.output.to_stdout_string()?)
+    }
+
+    /// Make the project's cron.d file match `jobs`, removing it when there
+    /// are none. Returns whether anything changed.
+    pub async fn sync(
+        &mut self,
+        working_directory: &Path,
+        compose_files: &[PathBuf],
+        env_files: &[PathBuf],
+        jobs: &[CronJob],
+    ) -> DeployResult<bool> {
+        if jobs.is_empty() {
+            return self.remove(working_directory).await;
+        }
+
+        let user = self.run("id -un").await?.trim().to_string();
+        let content = render_cron_file(&user, working_directory, compose_files, env_files, jobs);
+        let path = Path::new(CRON_DIR).join(cron_file_name(working_directory));
+
+        let current = self
+            .executor
+            .execute_command(&format!(
+                "cat {} 2>/dev/null",
+                shell_quote(&path.to_string_lossy())
+            ))
+            .await
+            .map_err(|e| DeployError::Deployment(format!("Failed to read cron file: {}", e)))?;
+        if current.is_success() && current.output.to_stdout_string()? == content {
+            tracing::info!("Cron jobs in {} are up to date", path.display());
+            return Ok(false);
+        }
+
+        tracing::info!(
+            "Installing {} cron job(s) to {}",
+            jobs.len(),
+            path.display()
+        );
+        self.run(&format!(
+            "cat > {path} <<'DCD_CRON_EOF'\n{content}DCD_CRON_EOF\nchmod 644 {path}",
+            path = shell_quote(&path.to_string_lossy()),
+            content = content
+        ))
+        .await?;
+        Ok(true)
+    }
+
+    /// Remove the project's cron.d file if present. Returns whether it existed.
+    pub async fn remove(&mut self, working_directory: &Path) -> DeployResult<bool> {
+        let path = Path::new(CRON_DIR).join(cron_file_name(working_directory));
+        let exists = self
+            .executor
+            .execute_command(&format!("test -f {}", shell_quote(&path.to_string_lossy())))
+            .await
+            .map_err(|e| DeployError::Deployment(format!("Failed to check cron file: {}", e)))?;
+        if !exists.is_success() {
+            return Ok(false);
+        }
+        tracing::info!("Removing cron jobs in {}", path.display());
+        self.run(&format!("rm -f {}", shell_quote(&path.to_string_lossy())))
+            .await?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_cron_file() {
+        let jobs = vec![
+            CronJob {
+                service: "worker".to_string(),
+                schedule: "0 3 * * *".to_string(),
+                command: Some("date +%F".to_string()),
+            },
+            CronJob {
+                service: "backup".to_string(),
+                schedule: "@daily".to_string(),
+                command: None,
+            },
+        ];
+        let content = render_cron_file(
+            "deploy",
+            Path::new("/opt/shop"),
+            &[PathBuf::from("docker-compose.yml")],
+            &[],
+            &jobs,
+        );
+        assert!(content.contains(
+            "0 3 * * * deploy docker-compose --project-directory /opt/shop -p shop -f /opt/shop/docker-compose.yml run --rm -T worker date +\\%F >> /var/log/dcd-shop-cron.log 2>&1\n"
+        ));
+        assert!(content.contains("@daily deploy docker-compose --project-directory /opt/shop -p shop -f /opt/shop/docker-compose.yml run --rm -T backup >> "));
+        assert_eq!(cron_file_name(Path::new("/opt/my.app")), "dcd-my-app");
+    }
+}
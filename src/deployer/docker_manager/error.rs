@@ -1,3 +1,4 @@
+use super::types::SUPPORTED_DISTROS;
 use crate::executor::{ExecutorError, OutputError};
 use thiserror::Error;
 
@@ -20,6 +21,12 @@ pub enum DockerError {
     #[error("Unsupported operating system: {0}")]
     UnsupportedOS(String),
 
+    #[error(
+        "Unsupported remote OS: {0}. dcd deploys to Linux hosts and can install Docker on {distros}",
+        distros = SUPPORTED_DISTROS
+    )]
+    UnsupportedRemoteOs(String),
+
     #[error("Installation failed: {0}")]
     InstallationError(String),
 
@@ -38,6 +45,9 @@ pub enum DockerError {
     #[error("Upload error: {0}")]
     UploadError(String),
 
+    #[error("GPU support unavailable: {0}")]
+    GpuSupport(String),
+
     #[error("Docker compose error: {0}")]
     ComposeError(String),
 }
@@ -12,45 +12,27 @@ impl<'a> DockerInstaller<'a> {
     }
 
     pub async fn install_docker(&mut self, distro: &LinuxDistro) -> DockerResult<()> {
-        match distro {
-            LinuxDistro::Debian | LinuxDistro::Ubuntu => {
-                let commands = [
-                    "apt-get update",
-                    "apt-get install -y ca-certificates curl gnupg",
-                    "install -m 0755 -d /etc/apt/keyrings",
-                    "curl -fsSL https://download.docker.com/linux/debian/gpg | gpg --dearmor -o /etc/apt/keyrings/docker.gpg",
-                    "chmod a+r /etc/apt/keyrings/docker.gpg",
-                    "echo \"deb [arch=$(dpkg --print-architecture) signed-by=/etc/apt/keyrings/docker.gpg] https://download.docker.com/linux/debian $(lsb_release -cs) stable\" | tee /etc/apt/sources.list.d/docker.list > /dev/null",
-                    "apt-get update",
-                    "apt-get install -y docker-ce docker-ce-cli containerd.io docker-buildx-plugin docker-compose-plugin"
-                ];
-
-                for cmd in commands {
-                    let result = self
-                        .executor
-                        .execute_command(cmd)
-                        .await
-                        .map_err(DockerError::from)?;
-
-                    if !result.is_success() {
-                        return Err(DockerError::InstallationError(format!(
-                            "Failed to execute: {}",
-                            cmd
-                        )));
-                    }
-                }
-                Ok(())
-            }
-            LinuxDistro::Unknown(os) => Err(DockerError::UnsupportedOS(os.clone())),
-        }
+        let commands = docker_install_commands(distro)?;
+        self.run_all(&commands).await
+    }
+
+    pub async fn install_nvidia_toolkit(&mut self, distro: &LinuxDistro) -> DockerResult<()> {
+        let commands = nvidia_toolkit_commands(distro)?;
+        self.run_all(&commands).await
     }
 
-    pub async fn install_docker_compose(&mut self) -> DockerResult<()> {
+    /// Download the docker-compose binary into `bin_dir`, creating it if needed.
+    pub async fn install_docker_compose(&mut self, bin_dir: &str) -> DockerResult<()> {
         let commands = [
-            "curl -L \"https://github.com/docker/compose/releases/download/v2.32.1/docker-compose-$(uname -s)-$(uname -m)\" -o /usr/local/bin/docker-compose",
-            "chmod +x /usr/local/bin/docker-compose"
+            format!("mkdir -p {}", bin_dir),
+            format!("curl -L \"https://github.com/docker/compose/releases/download/v2.32.1/docker-compose-$(uname -s)-$(uname -m)\" -o {}/docker-compose", bin_dir),
+            format!("chmod +x {}/docker-compose", bin_dir),
         ];
 
+        self.run_all(&commands).await
+    }
+
+    async fn run_all(&mut self, commands: &[String]) -> DockerResult<()> {
         for cmd in commands {
             let result = self
                 .executor
@@ -68,3 +50,134 @@ impl<'a> DockerInstaller<'a> {
         Ok(())
     }
 }
+
+/// Package manager of RHEL-family hosts: dnf, or yum on older releases.
+const RPM_INSTALL: &str = "$(command -v dnf || command -v yum) install -y";
+
+/// Non-interactive zypper, trusting the signing keys of newly added repositories.
+const ZYPPER: &str = "zypper --non-interactive --gpg-auto-import-keys";
+
+const DOCKER_PACKAGES: &str =
+    "docker-ce docker-ce-cli containerd.io docker-buildx-plugin docker-compose-plugin";
+
+/// Commands installing Docker Engine with the compose plugin from Docker's
+/// repository (the distribution's own packages on SUSE and Alpine) and
+/// starting it on boot.
+fn docker_install_commands(distro: &LinuxDistro) -> DockerResult<Vec<String>> {
+    let commands = match distro {
+        LinuxDistro::Debian | LinuxDistro::Ubuntu => {
+            let repo = if *distro == LinuxDistro::Ubuntu {
+                "ubuntu"
+            } else {
+                "debian"
+            };
+            vec![
+                "apt-get update".to_string(),
+                "apt-get install -y ca-certificates curl gnupg".to_string(),
+                "install -m 0755 -d /etc/apt/keyrings".to_string(),
+                format!("curl -fsSL https://download.docker.com/linux/{}/gpg | gpg --dearmor --yes -o /etc/apt/keyrings/docker.gpg", repo),
+                "chmod a+r /etc/apt/keyrings/docker.gpg".to_string(),
+                // Derivatives such as Mint name the release they are based on in UBUNTU_CODENAME
+                format!("echo \"deb [arch=$(dpkg --print-architecture) signed-by=/etc/apt/keyrings/docker.gpg] https://download.docker.com/linux/{} $(. /etc/os-release && echo \"${{UBUNTU_CODENAME:-$VERSION_CODENAME}}\") stable\" | tee /etc/apt/sources.list.d/docker.list > /dev/null", repo),
+                "apt-get update".to_string(),
+                format!("apt-get install -y {}", DOCKER_PACKAGES),
+            ]
+        }
+        LinuxDistro::Fedora | LinuxDistro::Rhel | LinuxDistro::CentOs => {
+            let repo = match distro {
+                LinuxDistro::Fedora => "fedora",
+                LinuxDistro::Rhel => "rhel",
+                _ => "centos",
+            };
+            vec![
+                // Fetching the .repo file directly works with dnf, dnf5 and yum alike
+                format!("curl -fsSL https://download.docker.com/linux/{}/docker-ce.repo -o /etc/yum.repos.d/docker-ce.repo", repo),
+                format!("{} {}", RPM_INSTALL, DOCKER_PACKAGES),
+                "systemctl enable --now docker".to_string(),
+            ]
+        }
+        LinuxDistro::Suse => vec![
+            // On SLES docker comes from the Containers module; openSUSE has it in its main repository
+            "if command -v SUSEConnect >/dev/null 2>&1; then SUSEConnect -p \"sle-module-containers/$(. /etc/os-release && echo \"$VERSION_ID\")/$(uname -m)\"; fi".to_string(),
+            format!("{} refresh", ZYPPER),
+            format!("{} install docker docker-compose", ZYPPER),
+            "systemctl enable --now docker".to_string(),
+        ],
+        LinuxDistro::Alpine => vec![
+            // docker lives in the community repository, which may be commented out (but not edge)
+            "sed -i '/edge/!s|^#\\(.*/community\\)$|\\1|' /etc/apk/repositories".to_string(),
+            "apk update".to_string(),
+            "apk add --no-cache docker docker-cli-compose".to_string(),
+            "rc-update add docker default".to_string(),
+            "rc-service docker start".to_string(),
+        ],
+        LinuxDistro::Unknown(os) => return Err(DockerError::UnsupportedOS(os.clone())),
+    };
+    Ok(commands)
+}
+
+/// Commands installing the NVIDIA container toolkit and registering it with docker.
+fn nvidia_toolkit_commands(distro: &LinuxDistro) -> DockerResult<Vec<String>> {
+    let mut commands: Vec<String> = match distro {
+        LinuxDistro::Debian | LinuxDistro::Ubuntu => [
+            "apt-get update",
+            "apt-get install -y ca-certificates curl gnupg",
+            "curl -fsSL https://nvidia.github.io/libnvidia-container/gpgkey | gpg --dearmor --yes -o /usr/share/keyrings/nvidia-container-toolkit-keyring.gpg",
+            "curl -fsSL https://nvidia.github.io/libnvidia-container/stable/deb/nvidia-container-toolkit.list | sed 's#deb https://#deb [signed-by=/usr/share/keyrings/nvidia-container-toolkit-keyring.gpg] https://#g' | tee /etc/apt/sources.list.d/nvidia-container-toolkit.list > /dev/null",
+            "apt-get update",
+            "apt-get install -y nvidia-container-toolkit",
+        ]
+        .map(String::from)
+        .to_vec(),
+        LinuxDistro::Fedora | LinuxDistro::Rhel | LinuxDistro::CentOs => vec![
+            "curl -fsSL https://nvidia.github.io/libnvidia-container/stable/rpm/nvidia-container-toolkit.repo -o /etc/yum.repos.d/nvidia-container-toolkit.repo".to_string(),
+            format!("{} nvidia-container-toolkit", RPM_INSTALL),
+        ],
+        LinuxDistro::Suse => vec![
+            format!("{} addrepo --refresh https://nvidia.github.io/libnvidia-container/stable/rpm/nvidia-container-toolkit.repo", ZYPPER),
+            format!("{} install nvidia-container-toolkit", ZYPPER),
+        ],
+        LinuxDistro::Alpine => {
+            return Err(DockerError::UnsupportedOS(
+                "Alpine Linux (NVIDIA publishes no container toolkit packages for it)".into(),
+            ))
+        }
+        LinuxDistro::Unknown(os) => return Err(DockerError::UnsupportedOS(os.clone())),
+    };
+    commands.push("nvidia-ctk runtime configure --runtime=docker".to_string());
+    commands.push("systemctl restart docker".to_string());
+    Ok(commands)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_docker_install_commands() {
+        let ubuntu = docker_install_commands(&LinuxDistro::Ubuntu).unwrap();
+        assert!(ubuntu[5].contains("https://download.docker.com/linux/ubuntu $("));
+
+        let rocky = docker_install_commands(&LinuxDistro::CentOs).unwrap();
+        assert!(rocky[0].contains("/linux/centos/docker-ce.repo"));
+        assert_eq!(rocky.last().unwrap(), "systemctl enable --now docker");
+
+        let suse = docker_install_commands(&LinuxDistro::Suse).unwrap();
+        assert!(suse[0].contains("sle-module-containers/"));
+        assert!(suse
+            .iter()
+            .any(|cmd| cmd.ends_with("install docker docker-compose")));
+        assert!(nvidia_toolkit_commands(&LinuxDistro::Suse).unwrap()[0].contains(" addrepo "));
+
+        let alpine = docker_install_commands(&LinuxDistro::Alpine).unwrap();
+        assert!(alpine
+            .iter()
+            .any(|cmd| cmd.starts_with("rc-update add docker")));
+
+        assert!(matches!(
+            docker_install_commands(&LinuxDistro::Unknown("Arch Linux".into())),
+            Err(DockerError::UnsupportedOS(os)) if os == "Arch Linux"
+        ));
+        assert!(nvidia_toolkit_commands(&LinuxDistro::Alpine).is_err());
+    }
+}
@@ -1,15 +1,26 @@
 mod error;
 mod installer;
+mod progress;
+mod rootless;
 mod types;
 mod validator;
 
-use crate::deployer::types::ComposeExec;
-use crate::executor::{CommandExecutor, CommandResult, FileTransfer, OutputError};
+use crate::deployer::types::{ComposeExec, DeployerEvent};
+use crate::deployer::{compose_command, compose_project_name};
+use crate::executor::{
+    shell_quote, CommandExecutor, CommandResult, FileTransfer, OutputChunk, OutputError,
+};
 use async_trait::async_trait;
 pub use error::DockerError;
 use installer::DockerInstaller;
-use serde::{Deserialize, Serialize};
+pub use progress::LineBuffer;
+use progress::PullProgress;
+pub use rootless::{is_rootless, use_rootless_docker, RootlessDocker};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
 use types::{DockerResult, DockerVersion, LinuxDistro};
 use validator::DockerValidator;
 
@@ -25,6 +36,18 @@ pub struct UnhealthyService {
     pub status: String, // Full status string like "Exited (1)" or "Up (unhealthy)"
 }
 
+impl UnhealthyService {
+    /// Running with a health check that has not reported yet.
+    pub fn is_starting(&self) -> bool {
+        self.state == "running" && self.health == "starting"
+    }
+
+    /// Exited, dead or reported unhealthy; further waiting will not help.
+    pub fn is_terminal(&self) -> bool {
+        matches!(self.state.as_str(), "exited" | "dead") || self.health == "unhealthy"
+    }
+}
+
 /// Represents the overall health status of the deployment.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum HealthCheckResult {
@@ -40,6 +63,90 @@ pub enum HealthCheckResult {
     NoServices,
 }
 
+impl HealthCheckResult {
+    /// Classify the services of a `docker compose ps` into healthy, starting
+    /// and failed ones.
+    pub fn from_status(status: &ComposeStatus) -> Self {
+        if status.services.is_empty() {
+            tracing::warn!("No services found when checking health status.");
+            return Self::NoServices;
+        }
+
+        let mut starting_services = Vec::new();
+        let mut failed_services = Vec::new();
+
+        for s in status.services.iter() {
+            // Base definition of "healthy" - running and (no healthcheck or health='healthy')
+            let is_technically_healthy =
+                s.is_running() && (s.health.is_empty() || s.health == "healthy");
+
+            if !is_technically_healthy {
+                let unhealthy_detail = UnhealthyService {
+                    name: s.service.clone(),
+                    state: s.state.clone(),
+                    health: s.health.clone(),
+                    exit_code: s.exit_code,
+                    status: s.status.clone(),
+                };
+
+                // Categorize: Is it just starting or actually failed?
+                if unhealthy_detail.is_starting() {
+                    // It's running but health is 'starting' -> Potential recovery
+                    starting_services.push(unhealthy_detail);
+                } else {
+                    // It's exited, restarting, dead, or health='unhealthy' -> Definitive failure
+                    failed_services.push(unhealthy_detail);
+                }
+            }
+        }
+
+        if !failed_services.is_empty() {
+            // If any service has definitively failed, report Failed overall.
+            // Include starting services in the report for completeness.
+            tracing::warn!(
+                "Found definitively failed services: {:?}",
+                failed_services.iter().map(|s| &s.name).collect::<Vec<_>>()
+            );
+            failed_services.extend(starting_services); // Combine lists
+            Self::Failed(failed_services)
+        } else if !starting_services.is_empty() {
+            // No failed services, but some are still starting.
+            tracing::info!(
+                "Found services still starting: {:?}",
+                starting_services
+                    .iter()
+                    .map(|s| &s.name)
+                    .collect::<Vec<_>>()
+            );
+            Self::Starting(starting_services)
+        } else {
+            // All services are technically healthy.
+            Self::Healthy
+        }
+    }
+
+    /// The result for `services` alone; all services when empty.
+    pub fn only(self, services: &[String]) -> Self {
+        if services.is_empty() {
+            return self;
+        }
+        let (Self::Starting(unhealthy) | Self::Failed(unhealthy)) = self else {
+            return self;
+        };
+        let unhealthy: Vec<UnhealthyService> = unhealthy
+            .into_iter()
+            .filter(|service| services.contains(&service.name))
+            .collect();
+        if unhealthy.iter().any(UnhealthyService::is_terminal) {
+            Self::Failed(unhealthy)
+        } else if unhealthy.is_empty() {
+            Self::Healthy
+        } else {
+            Self::Starting(unhealthy)
+        }
+    }
+}
+
 // --- End of New Types ---
 
 #[async_trait]
@@ -50,6 +157,10 @@ pub trait DockerManager: Send {
     /// Check if Docker Compose is installed and install if not
     async fn ensure_docker_compose_installed(&mut self) -> DockerResult<()>;
 
+    /// Make sure GPU containers can run: NVIDIA driver present and the
+    /// container toolkit installed (installing it when `install` is set).
+    async fn ensure_gpu_support(&mut self, install: bool) -> DockerResult<()>;
+
     /// Get Docker version information
     async fn get_docker_version(&mut self) -> DockerResult<DockerVersion>;
 
@@ -59,8 +170,32 @@ pub trait DockerManager: Send {
     /// Get status of all services
     async fn get_services_status(&mut self) -> DockerResult<ComposeStatus>;
 
-    /// Start services using docker-compose up -d
-    async fn compose_up(&mut self) -> DockerResult<()>;
+    /// How often each of the project's containers was restarted, by container name
+    async fn restart_counts(&mut self) -> DockerResult<HashMap<String, u32>>;
+
+    /// List the images of the project's containers (docker-compose images)
+    async fn compose_images(&mut self) -> DockerResult<Vec<ComposeImage>>;
+
+    /// Prune this project's unused images and pull the latest ones of
+    /// `services` (all if empty) (docker-compose pull)
+    async fn compose_pull(&mut self, services: &[String]) -> DockerResult<()>;
+
+    /// Build images of `services` (all if empty) that have a build section
+    /// on the host, from scratch if `fresh` (docker-compose build)
+    async fn compose_build(&mut self, services: &[String], fresh: bool) -> DockerResult<()>;
+
+    /// Recreate `services` (all if empty) from the already pulled and built
+    /// images (docker-compose up -d --no-build)
+    async fn compose_up(&mut self, services: &[String]) -> DockerResult<()>;
+
+    /// Stop `services` (all if empty) without removing containers (docker-compose stop)
+    async fn compose_stop(&mut self, services: &[String]) -> DockerResult<()>;
+
+    /// Restart the containers of `services` (all if empty) as they are (docker-compose restart)
+    async fn compose_restart(&mut self, services: &[String]) -> DockerResult<()>;
+
+    /// Start existing services without pulling images (docker-compose up -d)
+    async fn compose_start(&mut self) -> DockerResult<()>;
 
     /// Upload docker-compose.yml file
     async fn upload_compose_file(
@@ -79,9 +214,19 @@ pub trait DockerManager: Send {
     async fn compose_down(&mut self, remove_volumes: bool, remove_images: bool)
         -> DockerResult<()>;
 
+    /// Create a network if it does not exist yet. Returns whether it was created.
+    async fn ensure_network(&mut self, name: &str) -> DockerResult<bool>;
+
     /// Remove a specific volume
     async fn remove_volume(&mut self, volume_name: &str) -> DockerResult<()>;
 
+    /// Stop a single container by name
+    async fn stop_container(&mut self, name: &str) -> DockerResult<()>;
+
+    /// Volumes `compose down -v` would remove: the project's named volumes
+    /// and the anonymous volumes of its containers
+    async fn project_volumes(&mut self) -> DockerResult<Vec<String>>;
+
     /// Remove unused images to save disk space
     async fn prune_images(&mut self) -> DockerResult<()>;
 }
@@ -142,6 +287,80 @@ pub struct ServiceStatus {
     pub status: String,
 }
 
+/// Image of one container as reported by `docker compose images --format json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComposeImage {
+    #[serde(rename = "ContainerName")]
+    pub container_name: String,
+    #[serde(rename = "Repository", default)]
+    pub repository: String,
+    #[serde(rename = "Tag", default)]
+    pub tag: String,
+    #[serde(rename = "ID")]
+    pub id: String,
+}
+
+/// Parse compose's `--format json` output: some versions emit a JSON array,
+/// others one JSON object per line.
+fn parse_json_records<T: DeserializeOwned>(stdout: &str) -> DockerResult<Vec<T>> {
+    let records = if stdout.trim_start().starts_with('[') {
+        serde_json::from_str(stdout)
+    } else {
+        stdout
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(serde_json::from_str)
+            .collect()
+    };
+    records.map_err(|e| DockerError::Output(OutputError::JsonError(e)))
+}
+
+/// The `docker compose build` subcommand; a `fresh` build pulls newer base
+/// images and ignores the build cache.
+pub(crate) fn build_subcommand(fresh: bool) -> &'static str {
+    if fresh {
+        "build --pull --no-cache"
+    } else {
+        "build"
+    }
+}
+
+/// `subcommand` followed by the quoted service names.
+pub(crate) fn with_services(subcommand: &str, services: &[String]) -> String {
+    services
+        .iter()
+        .fold(subcommand.to_string(), |cmd, service| {
+            format!("{} {}", cmd, shell_quote(service))
+        })
+}
+
+/// Parse `<name> <restart count>` lines of `docker inspect`; names come with a leading `/`.
+fn parse_restart_counts(stdout: &str) -> HashMap<String, u32> {
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let (name, count) = line.trim().rsplit_once(' ')?;
+            Some((
+                name.trim_start_matches('/').to_string(),
+                count.parse().ok()?,
+            ))
+        })
+        .collect()
+}
+
+/// Whether `name` is one docker generated for an anonymous volume (64 hex digits).
+fn is_anonymous_volume(name: &str) -> bool {
+    name.len() == 64 && name.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Fail with [`DockerError::UnsupportedRemoteOs`] unless the host runs Linux.
+pub async fn check_remote_os(executor: &mut (dyn ComposeExec + Send)) -> DockerResult<()> {
+    let distro = DockerValidator::new(executor).detect_distro().await?;
+    tracing::debug!("Remote distribution: {:?}", distro);
+    Ok(())
+}
+
 pub trait DockerExec: CommandExecutor + FileTransfer {}
 
 impl<T: CommandExecutor + FileTransfer> DockerExec for T {}
@@ -163,6 +382,44 @@ impl ServiceStatus {
         self.is_running() && (self.health.is_empty() || self.health == "healthy")
     }
 
+    /// Published ports as `[host:]published->target/proto`, once per port
+    /// even when bound on both IPv4 and IPv6.
+    pub fn published_ports(&self) -> Vec<String> {
+        let mut ports: Vec<String> = Vec::new();
+        for publisher in self.publishers.iter().filter(|p| p.published_port != 0) {
+            let host = match publisher.url.as_str() {
+                "" | "0.0.0.0" | "::" => String::new(),
+                url => format!("{}:", url),
+            };
+            let port = format!(
+                "{}{}->{}/{}",
+                host, publisher.published_port, publisher.target_port, publisher.protocol
+            );
+            if !ports.contains(&port) {
+                ports.push(port);
+            }
+        }
+        ports
+    }
+
+    /// State with the health check's verdict, e.g. `running (starting)` or
+    /// `exited (1)`, as shown in health check timelines.
+    pub fn describe(&self) -> String {
+        match self.state.as_str() {
+            "exited" | "dead" => format!("{} ({})", self.state, self.exit_code),
+            _ if !self.health.is_empty() => format!("{} ({})", self.state, self.health),
+            _ => self.state.clone(),
+        }
+    }
+
+    /// How long a running container has been up, from its status (e.g.
+    /// `Up 3 hours (healthy)` gives `3 hours`).
+    pub fn uptime(&self) -> Option<String> {
+        let up = self.status.strip_prefix("Up ")?;
+        let up = up.split(" (").next().unwrap_or(up);
+        Some(up.trim().to_string())
+    }
+
     pub fn get_ports(&self) -> Vec<(u16, u16)> {
         self.publishers
             .iter()
@@ -191,12 +448,17 @@ impl ComposeStatus {
 
 pub struct SshDockerManager<'a> {
     executor: &'a mut (dyn ComposeExec + Send),
-    distro: LinuxDistro,
+    /// Detected on first use; only installers need it
+    distro: Option<LinuxDistro>,
     working_directory: PathBuf,
     /// List of compose files (relative to working directory or absolute paths)
     compose_files: Vec<PathBuf>,
     /// List of env files
     env_files: Vec<PathBuf>,
+    /// Extra environment for compose commands (e.g. COMPOSE_PROJECT_NAME, DOCKER_CONFIG)
+    compose_env: HashMap<String, String>,
+    /// Where pull progress and the output of `up` go while they run
+    progress_sender: Option<mpsc::Sender<DeployerEvent>>,
 }
 
 impl<'a> SshDockerManager<'a> {
@@ -207,15 +469,14 @@ impl<'a> SshDockerManager<'a> {
         compose_files: Vec<PathBuf>,
         env_files: Vec<PathBuf>,
     ) -> DockerResult<Self> {
-        let mut validator = DockerValidator::new(executor);
-        let distro = validator.detect_distro().await?;
-
         let mut manager = Self {
             executor,
-            distro,
+            distro: None,
             working_directory,
             compose_files,
             env_files,
+            compose_env: HashMap::new(),
+            progress_sender: None,
         };
 
         // Verify working directory exists
@@ -224,10 +485,36 @@ impl<'a> SshDockerManager<'a> {
         Ok(manager)
     }
 
+    async fn distro(&mut self) -> DockerResult<LinuxDistro> {
+        if let Some(distro) = &self.distro {
+            return Ok(distro.clone());
+        }
+        let distro = DockerValidator::new(self.executor).detect_distro().await?;
+        self.distro = Some(distro.clone());
+        Ok(distro)
+    }
+
+    /// The executor compose commands run on, for commands that are not compose's.
+    pub fn executor(&mut self) -> &mut (dyn ComposeExec + Send) {
+        &mut *self.executor
+    }
+
+    /// Set environment variables passed to every compose command.
+    pub fn with_env(mut self, env: HashMap<String, String>) -> Self {
+        self.compose_env = env;
+        self
+    }
+
+    /// Report pull progress and the output of `up` to `sender` as they run.
+    pub fn with_progress(mut self, sender: Option<mpsc::Sender<DeployerEvent>>) -> Self {
+        self.progress_sender = sender;
+        self
+    }
+
     async fn verify_working_directory(&mut self) -> DockerResult<()> {
         let cmd = format!(
             "test -d {} && echo 'exists'",
-            self.working_directory.display()
+            shell_quote(&self.working_directory.to_string_lossy())
         );
         let result = self.executor.execute_command(&cmd).await?;
         if !result.is_success() {
@@ -239,27 +526,105 @@ impl<'a> SshDockerManager<'a> {
     }
 
     async fn execute_compose_command(&mut self, cmd: &str) -> DockerResult<CommandResult> {
-        let full_cmd = format!("cd {} && {}", self.working_directory.display(), cmd);
         self.executor
-            .execute_command(&full_cmd)
+            .execute_command_with_env(cmd, &self.compose_env)
             .await
             .map_err(DockerError::from)
     }
 
-    /// Build a docker-compose command string with configured compose files and env files.
-    fn format_docker_compose_command(&self, subcommand: &str) -> String {
-        let mut cmd = String::from("docker-compose");
-        for cf in &self.compose_files {
-            cmd.push_str(" -f ");
-            cmd.push_str(&cf.to_string_lossy());
+    /// Run a compose subcommand, turning a non-zero exit into a `CommandError`.
+    async fn run_compose_subcommand(&mut self, subcommand: &str) -> DockerResult<()> {
+        let cmd = self.format_docker_compose_command(subcommand);
+        tracing::info!("Executing compose command: '{}'", cmd);
+        let result = self.execute_compose_command(&cmd).await?;
+        if !result.is_success() {
+            return Err(DockerError::CommandError {
+                cmd,
+                message: result.output.to_stderr_string()?,
+            });
         }
-        for ef in &self.env_files {
-            cmd.push_str(" --env-file ");
-            cmd.push_str(&ef.to_string_lossy());
+        Ok(())
+    }
+
+    /// Like [`Self::run_compose_subcommand`], handing each line of output to
+    /// `on_line` as it arrives; events it returns go to the progress sender.
+    async fn stream_compose_subcommand(
+        &mut self,
+        subcommand: &str,
+        mut on_line: impl FnMut(&str) -> Option<DeployerEvent> + Send,
+    ) -> DockerResult<()> {
+        let Some(sender) = self.progress_sender.clone() else {
+            return self.run_compose_subcommand(subcommand).await;
+        };
+        let cmd = self.format_docker_compose_command(subcommand);
+        tracing::info!("Executing compose command: '{}'", cmd);
+        let (output_tx, mut output) = mpsc::channel::<OutputChunk>(64);
+        let run = self
+            .executor
+            .execute_command_streaming(&cmd, &self.compose_env, output_tx);
+        let follow = async {
+            let mut lines = LineBuffer::default();
+            while let Some(chunk) = output.recv().await {
+                for line in lines.push(chunk) {
+                    tracing::debug!("compose: {}", line);
+                    if let Some(event) = on_line(&line) {
+                        let _ = sender.send(event).await;
+                    }
+                }
+            }
+            for line in lines.finish() {
+                if let Some(event) = on_line(&line) {
+                    let _ = sender.send(event).await;
+                }
+            }
+        };
+        let (result, ()) = tokio::join!(run, follow);
+        let result = result?;
+        if !result.is_success() {
+            return Err(DockerError::CommandError {
+                cmd,
+                message: result.output.to_stderr_string()?,
+            });
         }
-        cmd.push(' ');
-        cmd.push_str(subcommand);
-        cmd
+        Ok(())
+    }
+
+    /// Run `up` with the given arguments, reporting its output as it goes.
+    async fn compose_up_streamed(&mut self, subcommand: &str) -> DockerResult<()> {
+        self.stream_compose_subcommand(subcommand, |line| {
+            Some(DeployerEvent::ComposeOutput(line.to_string()))
+        })
+        .await
+    }
+
+    /// Wait up to `timeout` for a container of the project to start, die or
+    /// change health. Returns whether one did, so health checks can poll as
+    /// soon as something happens instead of on a fixed interval.
+    pub async fn wait_for_container_event(&mut self, timeout: Duration) -> DockerResult<bool> {
+        let project = self
+            .compose_env
+            .get("COMPOSE_PROJECT_NAME")
+            .cloned()
+            .unwrap_or_else(|| compose_project_name(&self.working_directory));
+        let cmd = container_events_command(&project, timeout);
+        let result = self.executor.execute_command(&cmd).await?;
+        if !result.is_success() {
+            return Err(DockerError::CommandError {
+                cmd,
+                message: result.output.to_stderr_string()?,
+            });
+        }
+        Ok(!result.output.to_stdout_string()?.trim().is_empty())
+    }
+
+    /// Build a docker-compose command string with configured compose files and env files.
+    fn format_docker_compose_command(&self, subcommand: &str) -> String {
+        compose_command(
+            &self.working_directory,
+            &self.compose_files,
+            &self.env_files,
+            subcommand,
+        )
     }
 }
 
@@ -269,8 +634,9 @@ impl DockerManager for SshDockerManager<'_> {
     async fn ensure_docker_installed(&mut self) -> DockerResult<()> {
         let mut validator = DockerValidator::new(self.executor);
         if !validator.is_docker_installed().await? {
+            let distro = self.distro().await?;
             let mut installer = DockerInstaller::new(self.executor);
-            installer.install_docker(&self.distro).await?;
+            installer.install_docker(&distro).await?;
         }
         Ok(())
     }
@@ -279,12 +645,39 @@ impl DockerManager for SshDockerManager<'_> {
     async fn ensure_docker_compose_installed(&mut self) -> DockerResult<()> {
         let mut validator = DockerValidator::new(self.executor);
         if !validator.is_docker_compose_installed().await? {
+            // Without root the binary goes to a directory on the rootless PATH
+            let bin_dir = if is_rootless(self.executor) {
+                "$HOME/.local/bin"
+            } else {
+                "/usr/local/bin"
+            };
             let mut installer = DockerInstaller::new(self.executor);
-            installer.install_docker_compose().await?;
+            installer.install_docker_compose(bin_dir).await?;
         }
         Ok(())
     }
 
+    async fn ensure_gpu_support(&mut self, install: bool) -> DockerResult<()> {
+        let mut validator = DockerValidator::new(self.executor);
+        if !validator.has_nvidia_gpu().await? {
+            return Err(DockerError::GpuSupport(
+                "no NVIDIA GPU or driver found on the host (nvidia-smi failed)".into(),
+            ));
+        }
+        if validator.is_nvidia_toolkit_installed().await? {
+            return Ok(());
+        }
+        if !install {
+            return Err(DockerError::GpuSupport(
+                "the NVIDIA container toolkit is not installed; install it or re-run with --install-gpu-toolkit".into(),
+            ));
+        }
+        tracing::info!("Installing NVIDIA container toolkit...");
+        let distro = self.distro().await?;
+        let mut installer = DockerInstaller::new(self.executor);
+        installer.install_nvidia_toolkit(&distro).await
+    }
+
     #[inline]
     async fn get_docker_version(&mut self) -> DockerResult<DockerVersion> {
         let mut validator = DockerValidator::new(self.executor);
@@ -304,118 +697,77 @@ impl DockerManager for SshDockerManager<'_> {
         }
 
         // Parse the JSON output into ServiceStatus structs.
-        // Some versions emit a JSON array, others emit one JSON object per line.
-        let stdout = result.output.to_stdout_string()?;
-        let services: Vec<ServiceStatus> = if stdout.trim_start().starts_with('[') {
-            // entire array as JSON
-            serde_json::from_str(&stdout)
-                .map_err(|e| DockerError::Output(OutputError::JsonError(e)))?
-        } else {
-            // line-delimited JSON objects
-            let mut v = Vec::new();
-            for line in stdout.lines() {
-                let line = line.trim();
-                if line.is_empty() {
-                    continue;
-                }
-                let svc: ServiceStatus = serde_json::from_str(line)
-                    .map_err(|e| DockerError::Output(OutputError::JsonError(e)))?;
-                v.push(svc);
-            }
-            v
-        };
+        let services = parse_json_records(&result.output.to_stdout_string()?)?;
         Ok(ComposeStatus { services })
     }
 
-    async fn compose_up(&mut self) -> DockerResult<()> {
+    async fn compose_images(&mut self) -> DockerResult<Vec<ComposeImage>> {
+        let cmd = self.format_docker_compose_command("images --format json");
+        let result = self.execute_compose_command(&cmd).await?;
+        if !result.is_success() {
+            return Err(DockerError::CommandError {
+                cmd: "docker-compose images".to_string(),
+                message: result.output.to_stderr_string()?,
+            });
+        }
+        parse_json_records(&result.output.to_stdout_string()?)
+    }
+
+    async fn compose_pull(&mut self, services: &[String]) -> DockerResult<()> {
         // First prune unused images to save disk space
         self.prune_images().await?;
 
-        // Pull latest images and start services with configured compose and env files
-        let pull_cmd = self.format_docker_compose_command("pull");
-        let up_cmd = self.format_docker_compose_command("up -d --remove-orphans");
-        let commands = [pull_cmd, up_cmd];
-
-        for cmd in &commands {
-            tracing::info!("Executing compose command: '{}'", cmd);
-            let result = self.execute_compose_command(cmd).await?;
-
-            if !result.is_success() {
-                let error_msg = result.output.to_stderr_string()?;
-                tracing::error!("Compose command failed: '{}'. Error: {}", cmd, error_msg);
-                return Err(DockerError::CommandError {
-                    cmd: cmd.to_string(),
-                    message: error_msg,
-                });
-            }
-        }
-        Ok(())
+        // Pull latest images with configured compose and env files
+        let mut progress = PullProgress::default();
+        self.stream_compose_subcommand(&with_services("pull", services), |line| {
+            progress.update(line).then(|| {
+                DeployerEvent::PullProgress(
+                    progress.current(),
+                    progress.total(),
+                    progress.percent(),
+                )
+            })
+        })
+        .await
     }
 
-    async fn verify_services_healthy(&mut self) -> DockerResult<HealthCheckResult> {
-        let status = self.get_services_status().await?;
-
-        if status.services.is_empty() {
-            tracing::warn!("No services found when checking health status.");
-            return Ok(HealthCheckResult::NoServices);
-        }
+    async fn compose_build(&mut self, services: &[String], fresh: bool) -> DockerResult<()> {
+        // Build steps can take minutes, so their output is shown as it comes
+        self.compose_up_streamed(&with_services(build_subcommand(fresh), services))
+            .await
+    }
 
-        let mut starting_services = Vec::new();
-        let mut failed_services = Vec::new();
+    async fn compose_up(&mut self, services: &[String]) -> DockerResult<()> {
+        // Start services with configured compose and env files
+        self.compose_up_streamed(&with_services(
+            "up -d --remove-orphans --no-build",
+            services,
+        ))
+        .await
+    }
 
-        for s in status.services.iter() {
-            // Base definition of "healthy" - running and (no healthcheck or health='healthy')
-            let is_technically_healthy =
-                s.is_running() && (s.health.is_empty() || s.health == "healthy");
+    async fn compose_stop(&mut self, services: &[String]) -> DockerResult<()> {
+        self.run_compose_subcommand(&with_services("stop", services))
+            .await
+    }
 
-            if !is_technically_healthy {
-                let unhealthy_detail = UnhealthyService {
-                    name: s.service.clone(),
-                    state: s.state.clone(),
-                    health: s.health.clone(),
-                    exit_code: s.exit_code,
-                    status: s.status.clone(),
-                };
+    async fn compose_restart(&mut self, services: &[String]) -> DockerResult<()> {
+        self.run_compose_subcommand(&with_services("restart", services))
+            .await
+    }
 
-                // Categorize: Is it just starting or actually failed?
-                if s.is_running() && s.health == "starting" {
-                    // It's running but health is 'starting' -> Potential recovery
-                    starting_services.push(unhealthy_detail);
-                } else {
-                    // It's exited, restarting, dead, or health='unhealthy' -> Definitive failure
-                    failed_services.push(unhealthy_detail);
-                }
-            }
-        }
+    async fn compose_start(&mut self) -> DockerResult<()> {
+        self.compose_up_streamed("up -d").await
+    }
 
-        if !failed_services.is_empty() {
-            // If any service has definitively failed, report Failed overall.
-            // Include starting services in the report for completeness.
-            tracing::warn!(
-                "Found definitively failed services: {:?}",
-                failed_services.iter().map(|s| &s.name).collect::<Vec<_>>()
-            );
-            failed_services.extend(starting_services); // Combine lists
-            Ok(HealthCheckResult::Failed(failed_services))
-        } else if !starting_services.is_empty() {
-            // No failed services, but some are still starting.
-            tracing::info!(
-                "Found services still starting: {:?}",
-                starting_services
-                    .iter()
-                    .map(|s| &s.name)
-                    .collect::<Vec<_>>()
-            );
-            Ok(HealthCheckResult::Starting(starting_services))
-        } else {
-            // All services are technically healthy.
-            Ok(HealthCheckResult::Healthy)
-        }
+    async fn verify_services_healthy(&mut self) -> DockerResult<HealthCheckResult> {
+        let status = self.get_services_status().await?;
+        Ok(HealthCheckResult::from_status(&status))
     }
 
     async fn verify_compose_file(&mut self) -> DockerResult<()> {
         let compose_path = self.working_directory.join("docker-compose.yml");
-        let cmd = format!("test -f {}", compose_path.display());
+        let cmd = format!("test -f {}", shell_quote(&compose_path.to_string_lossy()));
 
         let result = self
             .executor
@@ -434,7 +786,7 @@ impl DockerManager for SshDockerManager<'_> {
     async fn upload_compose_file(&mut self, local_path: &Path) -> DockerResult<()> {
         let remote_path = self.working_directory.join("docker-compose.yml");
         self.executor
-            .upload_file(local_path, remote_path.as_ref())
+            .upload_file(local_path, remote_path.as_ref(), None)
             .await
             .map_err(DockerError::from)?;
         self.verify_compose_file().await
@@ -471,6 +823,22 @@ impl DockerManager for SshDockerManager<'_> {
         Ok(())
     }
 
+    async fn ensure_network(&mut self, name: &str) -> DockerResult<bool> {
+        let inspect = format!("docker network inspect {} >/dev/null 2>&1", name);
+        if self.executor.execute_command(&inspect).await?.is_success() {
+            return Ok(false);
+        }
+        let cmd = format!("docker network create {}", name);
+        let result = self.executor.execute_command(&cmd).await?;
+        if !result.is_success() {
+            return Err(DockerError::CommandError {
+                cmd,
+                message: result.output.to_stderr_string()?,
+            });
+        }
+        Ok(true)
+    }
+
     async fn remove_volume(&mut self, volume_name: &str) -> DockerResult<()> {
         let cmd = format!("docker volume rm {}", volume_name);
         let result = self.executor.execute_command(&cmd).await?;
@@ -485,15 +853,76 @@ impl DockerManager for SshDockerManager<'_> {
         Ok(())
     }
 
+    async fn stop_container(&mut self, name: &str) -> DockerResult<()> {
+        let cmd = format!("docker stop {}", shell_quote(name));
+        let result = self.executor.execute_command(&cmd).await?;
+        if !result.is_success() {
+            return Err(DockerError::CommandError {
+                cmd,
+                message: result.output.to_stderr_string()?,
+            });
+        }
+        Ok(())
+    }
+
+    async fn restart_counts(&mut self) -> DockerResult<HashMap<String, u32>> {
+        let cmd = format!(
+            "docker ps -aq --filter label=com.docker.compose.project={} | xargs -r docker inspect --format '{{{{.Name}}}} {{{{.RestartCount}}}}'",
+            shell_quote(&compose_project_name(&self.working_directory))
+        );
+        let result = self.executor.execute_command(&cmd).await?;
+        if !result.is_success() {
+            return Err(DockerError::CommandError {
+                cmd,
+                message: result.output.to_stderr_string()?,
+            });
+        }
+        Ok(parse_restart_counts(&result.output.to_stdout_string()?))
+    }
+
+    async fn project_volumes(&mut self) -> DockerResult<Vec<String>> {
+        let label = format!(
+            "label=com.docker.compose.project={}",
+            shell_quote(&compose_project_name(&self.working_directory))
+        );
+        let named = format!("docker volume ls -q --filter {}", label);
+        // External volumes are mounted by name too, only anonymous ones count
+        let mounted = format!(
+            "docker ps -aq --filter {} | xargs -r docker inspect --format \
+             '{{{{range .Mounts}}}}{{{{if eq .Type \"volume\"}}}}{{{{println .Name}}}}{{{{end}}}}{{{{end}}}}'",
+            label
+        );
+        let mut volumes = Vec::new();
+        for (cmd, anonymous_only) in [(named, false), (mounted, true)] {
+            let result = self.executor.execute_command(&cmd).await?;
+            if !result.is_success() {
+                return Err(DockerError::CommandError {
+                    cmd,
+                    message: result.output.to_stderr_string()?,
+                });
+            }
+            volumes.extend(
+                result
+                    .output
+                    .to_stdout_string()?
+                    .lines()
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .filter(|name| !anonymous_only || is_anonymous_volume(name))
+                    .map(String::from),
+            );
+        }
+        volumes.sort();
+        volumes.dedup();
+        Ok(volumes)
+    }
+
     async fn prune_images(&mut self) -> DockerResult<()> {
         tracing::info!("Pruning unused images for current project to save disk space...");
 
-        // Get the project name from the working directory (used by docker-compose for labeling)
-        let project_name = self
-            .working_directory
-            .file_name()
-            .map(|name| name.to_string_lossy().into_owned())
-            .unwrap_or_else(|| "default".to_string());
+        // Use the project name docker compose labels resources with, so only
+        // this stack's images match the filter
+        let project_name = compose_project_name(&self.working_directory);
 
         // First, try to get current project images to understand what we're working with
         let images_cmd = self.format_docker_compose_command("images --format json");
@@ -532,3 +961,193 @@ impl DockerManager for SshDockerManager<'_> {
         Ok(())
     }
 }
+
+/// Shell command printing the first container events of `project` within
+/// `timeout` (nothing if there were none). `docker events` is left running
+/// in the background until its own timeout, as the command returns on the
+/// first event and has no way to stop it.
+fn container_events_command(project: &str, timeout: Duration) -> String {
+    let secs = timeout.as_secs().max(1);
+    format!(
+        "f=$(mktemp) && \
+         (timeout {secs} docker events --filter type=container \
+         --filter label=com.docker.compose.project={project} \
+         --filter event=start --filter event=die --filter event=health_status \
+         --format '{{{{.Action}}}}' >\"$f\" 2>/dev/null </dev/null &) ; \
+         i=0; while [ ! -s \"$f\" ] && [ $i -lt {ticks} ]; do sleep 0.2; i=$((i+1)); done; \
+         cat \"$f\"; rm -f \"$f\"",
+        secs = secs,
+        project = shell_quote(project),
+        ticks = secs * 5,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_status_columns() {
+        let services: Vec<ServiceStatus> = parse_json_records(
+            r#"{"Command":"nginx","CreatedAt":"2024-05-01","ExitCode":0,"Health":"healthy","ID":"abc","Image":"nginx:1.27","Labels":"","LocalVolumes":"0","Mounts":"","Name":"app-web-1","Names":"app-web-1","Networks":"app_default","Ports":"","Project":"app","Publishers":[{"URL":"0.0.0.0","TargetPort":80,"PublishedPort":8080,"Protocol":"tcp"},{"URL":"::","TargetPort":80,"PublishedPort":8080,"Protocol":"tcp"},{"URL":"127.0.0.1","TargetPort":9000,"PublishedPort":9000,"Protocol":"tcp"},{"URL":"","TargetPort":443,"PublishedPort":0,"Protocol":"tcp"}],"RunningFor":"3 hours ago","Service":"web","Size":"0B","State":"running","Status":"Up 3 hours (healthy)"}
+{"Command":"worker","CreatedAt":"2024-05-01","ExitCode":1,"Health":"","ID":"def","Image":"app-worker","Labels":"","LocalVolumes":"0","Mounts":"","Name":"app-worker-1","Names":"app-worker-1","Networks":"app_default","Ports":"","Project":"app","Publishers":[],"RunningFor":"1 hour ago","Service":"worker","Size":"0B","State":"exited","Status":"Exited (1) 5 minutes ago"}"#,
+        )
+        .unwrap();
+        assert_eq!(services.len(), 2);
+        assert_eq!(
+            services[0].published_ports(),
+            vec!["8080->80/tcp", "127.0.0.1:9000->9000/tcp"]
+        );
+        assert_eq!(services[0].uptime().as_deref(), Some("3 hours"));
+        assert_eq!(services[1].uptime(), None);
+        assert_eq!(services[0].describe(), "running (healthy)");
+        assert_eq!(services[1].describe(), "exited (1)");
+        assert!(services[1].published_ports().is_empty());
+    }
+
+    #[test]
+    fn test_container_events_command() {
+        let cmd = container_events_command("my app", Duration::from_millis(2500));
+        assert!(cmd.contains("timeout 2 docker events"));
+        assert!(cmd.contains("label=com.docker.compose.project='my app'"));
+        assert!(cmd.contains("--format '{{.Action}}'"));
+        assert!(cmd.contains("[ $i -lt 10 ]"));
+    }
+
+    #[test]
+    fn test_parse_restart_counts() {
+        let counts = parse_restart_counts("/app-web-1 0\n/app-worker-1 7\n\n");
+        assert_eq!(counts.get("app-web-1"), Some(&0));
+        assert_eq!(counts.get("app-worker-1"), Some(&7));
+        assert_eq!(counts.len(), 2);
+    }
+
+    #[test]
+    fn test_is_anonymous_volume() {
+        assert!(is_anonymous_volume(&"0123456789abcdef".repeat(4)));
+        assert!(!is_anonymous_volume("app_db-data"));
+        assert!(!is_anonymous_volume(&"g".repeat(64)));
+    }
+
+    #[test]
+    fn test_with_services() {
+        assert_eq!(with_services("stop", &[]), "stop");
+        assert_eq!(
+            with_services(build_subcommand(true), &["api".to_string()]),
+            "build --pull --no-cache api"
+        );
+        assert_eq!(
+            with_services("restart", &["web".to_string(), "my worker".to_string()]),
+            "restart web 'my worker'"
+        );
+    }
+
+    #[test]
+    fn test_health_of_selected_services() {
+        let service = |name: &str, state: &str, health: &str| UnhealthyService {
+            name: name.into(),
+            state: state.into(),
+            health: health.into(),
+            exit_code: 0,
+            status: String::new(),
+        };
+        let failed = HealthCheckResult::Failed(vec![
+            service("worker", "exited", ""),
+            service("web", "running", "starting"),
+        ]);
+        assert_eq!(failed.clone().only(&[]), failed);
+        assert_eq!(
+            failed.clone().only(&["web".to_string()]),
+            HealthCheckResult::Starting(vec![service("web", "running", "starting")])
+        );
+        assert_eq!(
+            failed.clone().only(&["db".to_string()]),
+            HealthCheckResult::Healthy
+        );
+        assert!(matches!(
+            failed.only(&["worker".to_string(), "web".to_string()]),
+            HealthCheckResult::Failed(services) if services.len() == 2
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_check_remote_os() {
+        use crate::testing::{failure, success, MockExecutor};
+
+        let mut linux = MockExecutor::new();
+        linux.fs().insert("/etc/os-release", "ID=debian\n");
+        assert!(check_remote_os(&mut linux).await.is_ok());
+
+        let no_os_release = |uname| {
+            let mut executor = MockExecutor::new();
+            executor
+                .respond(
+                    "cat /etc/os-release",
+                    failure(1, "No such file or directory"),
+                )
+                .respond("uname -s", uname);
+            executor
+        };
+        assert!(check_remote_os(&mut no_os_release(success("Linux\n")))
+            .await
+            .is_ok());
+
+        let err = check_remote_os(&mut no_os_release(success("Darwin\n")))
+            .await
+            .unwrap_err();
+        assert!(matches!(&err, DockerError::UnsupportedRemoteOs(os) if os == "Darwin"));
+        assert!(err.to_string().contains("Debian, Ubuntu"));
+
+        // Windows: neither command exists
+        let mut windows = no_os_release(failure(1, "'uname' is not recognized"));
+        assert!(matches!(
+            check_remote_os(&mut windows).await,
+            Err(DockerError::UnsupportedRemoteOs(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_compose_pull_progress() {
+        use crate::testing::{failure, success, CommandMatch, MockExecutor};
+
+        let mut executor = MockExecutor::new();
+        executor
+            .respond(CommandMatch::Prefix("test -d".into()), success("exists"))
+            .respond(CommandMatch::Contains("image prune".into()), success(""))
+            .respond(
+                CommandMatch::Contains(" pull".into()),
+                failure(
+                    1,
+                    " web Pulling \n db Pulling \n 5d0aeceef7ee Downloading [=> ]  1MB/4MB\n web Pulled \n db Error manifest unknown\n",
+                ),
+            );
+        let (sender, mut events) = mpsc::channel(16);
+        let mut manager = SshDockerManager::new(
+            &mut executor,
+            PathBuf::from("/opt/shop"),
+            vec![PathBuf::from("docker-compose.yml")],
+            Vec::new(),
+        )
+        .await
+        .unwrap()
+        .with_progress(Some(sender));
+
+        let err = manager.compose_pull(&[]).await.unwrap_err();
+        assert!(err.to_string().contains("manifest unknown"));
+        drop(manager);
+        let mut seen = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            seen.push(event.to_string());
+        }
+        assert_eq!(
+            seen,
+            vec![
+                "Pulling image 1/1",
+                "Pulling image 1/2",
+                "Pulling image 1/2 (25%)",
+                "Pulling image 2/2 (25%)",
+                "Pulling image 2/2 (25%)",
+            ]
+        );
+    }
+}
@@ -0,0 +1,209 @@
+//! Following the output of `docker compose pull` and `up` as it arrives.
+
+use crate::executor::OutputChunk;
+use std::collections::BTreeMap;
+
+/// Splits streamed output into lines. Compose redraws progress with `\r`,
+/// so that ends a line too.
+#[derive(Debug, Default)]
+pub struct LineBuffer {
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+}
+
+impl LineBuffer {
+    /// Add a chunk and return the lines it completes, trimmed and non-empty.
+    pub fn push(&mut self, chunk: OutputChunk) -> Vec<String> {
+        let (buffer, data) = match chunk {
+            OutputChunk::Stdout(data) => (&mut self.stdout, data),
+            OutputChunk::Stderr(data) => (&mut self.stderr, data),
+        };
+        buffer.extend_from_slice(&data);
+        let mut lines = Vec::new();
+        while let Some(end) = buffer.iter().position(|&b| b == b'\n' || b == b'\r') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            push_line(&mut lines, &line);
+        }
+        lines
+    }
+
+    /// Lines left without a line ending when the command finished.
+    pub fn finish(&mut self) -> Vec<String> {
+        let mut lines = Vec::new();
+        push_line(&mut lines, &std::mem::take(&mut self.stdout));
+        push_line(&mut lines, &std::mem::take(&mut self.stderr));
+        lines
+    }
+}
+
+fn push_line(lines: &mut Vec<String>, line: &[u8]) {
+    let line = String::from_utf8_lossy(line).trim().to_string();
+    if !line.is_empty() {
+        lines.push(line);
+    }
+}
+
+/// Progress of a `docker compose pull`, from lines like ` web Pulling`,
+/// ` 5d0aeceef7ee Downloading [==>   ]  1.2MB/3.4MB` and ` web Pulled`.
+#[derive(Debug, Default)]
+pub struct PullProgress {
+    /// Whether each service seen so far is done pulling
+    images: BTreeMap<String, bool>,
+    /// Downloaded and total bytes of each layer with a known size
+    layers: BTreeMap<String, (u64, u64)>,
+}
+
+impl PullProgress {
+    /// Take in a line of output. Returns whether the progress changed.
+    pub fn update(&mut self, line: &str) -> bool {
+        let Some((id, status)) = line.trim().split_once(char::is_whitespace) else {
+            return false;
+        };
+        let status = status.trim();
+        if is_layer_id(id) {
+            return self.update_layer(id, status);
+        }
+        let done = if status.starts_with("Pulling") || status.starts_with("Waiting") {
+            false
+        } else if status.starts_with("Pulled")
+            || status.starts_with("Skipped")
+            || status.starts_with("Error")
+        {
+            true
+        } else {
+            return false;
+        };
+        self.images.insert(id.to_string(), done) != Some(done)
+    }
+
+    fn update_layer(&mut self, id: &str, status: &str) -> bool {
+        if let Some(progress) = status.strip_prefix("Downloading") {
+            let Some((current, total)) = progress
+                .rsplit(']')
+                .next()
+                .and_then(|sizes| sizes.trim().split_once('/'))
+                .and_then(|(current, total)| Some((parse_size(current)?, parse_size(total)?)))
+            else {
+                return false;
+            };
+            return self.layers.insert(id.to_string(), (current, total)) != Some((current, total));
+        }
+        let finished = [
+            "Download complete",
+            "Verifying Checksum",
+            "Extracting",
+            "Pull complete",
+        ];
+        if finished.iter().any(|prefix| status.starts_with(prefix)) {
+            if let Some((current, total)) = self.layers.get_mut(id) {
+                if current != total {
+                    *current = *total;
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// Number of images seen so far.
+    pub fn total(&self) -> usize {
+        self.images.len()
+    }
+
+    /// The image being pulled, counting from 1: one past those done.
+    pub fn current(&self) -> usize {
+        let done = self.images.values().filter(|&&done| done).count();
+        (done + 1).min(self.total())
+    }
+
+    /// Downloaded share of the layers with a known size, in percent.
+    pub fn percent(&self) -> Option<u8> {
+        let (current, total) = self
+            .layers
+            .values()
+            .fold((0, 0), |(c, t), (current, total)| (c + current, t + total));
+        (total > 0).then(|| (current.min(total) * 100 / total) as u8)
+    }
+}
+
+/// Layers are shown by the first 12 hex digits of their digest.
+fn is_layer_id(id: &str) -> bool {
+    id.len() == 12 && id.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Parse a size as docker prints it, e.g. `32.77kB` or `1.2GB`.
+fn parse_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(split);
+    let factor = match unit {
+        "" | "B" => 1.0,
+        "kB" | "KB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "TB" => 1e12,
+        _ => return None,
+    };
+    let number: f64 = number.parse().ok()?;
+    Some((number * factor).round() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_buffer() {
+        let mut buffer = LineBuffer::default();
+        assert!(buffer
+            .push(OutputChunk::Stderr(b" web Pul".to_vec()))
+            .is_empty());
+        assert_eq!(
+            buffer.push(OutputChunk::Stderr(
+                b"ling \r\n db Pulling\n cache".to_vec()
+            )),
+            vec!["web Pulling", "db Pulling"]
+        );
+        assert_eq!(
+            buffer.push(OutputChunk::Stdout(b"done\n".to_vec())),
+            vec!["done"]
+        );
+        assert_eq!(buffer.finish(), vec!["cache"]);
+    }
+
+    #[test]
+    fn test_pull_progress() {
+        let mut progress = PullProgress::default();
+        assert_eq!(progress.percent(), None);
+        for line in [
+            " web Pulling ",
+            " db Pulling ",
+            " cache Skipped - Image is already being pulled by web ",
+            " 5d0aeceef7ee Pulling fs layer ",
+        ] {
+            progress.update(line);
+        }
+        assert_eq!((progress.current(), progress.total()), (2, 3));
+
+        assert!(progress.update(" 5d0aeceef7ee Downloading [=>    ]  1MB/4MB"));
+        assert!(progress.update(" 0a1b2c3d4e5f Downloading [=====>]  2MB/4MB"));
+        assert!(!progress.update(" 0a1b2c3d4e5f Downloading [=====>]  2MB/4MB"));
+        assert_eq!(progress.percent(), Some(37));
+        assert!(progress.update(" 5d0aeceef7ee Pull complete "));
+        assert_eq!(progress.percent(), Some(75));
+
+        assert!(progress.update(" web Pulled "));
+        assert!(!progress.update("Some unrelated line"));
+        assert_eq!((progress.current(), progress.total()), (3, 3));
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("32.77kB"), Some(32_770));
+        assert_eq!(parse_size(" 3.398MB"), Some(3_398_000));
+        assert_eq!(parse_size("512B"), Some(512));
+        assert_eq!(parse_size("1.5XB"), None);
+    }
+}
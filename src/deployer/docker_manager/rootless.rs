@@ -0,0 +1,118 @@
+use super::types::DockerResult;
+use crate::deployer::types::ComposeExec;
+use std::collections::HashMap;
+
+/// Finds the socket of a rootless daemon for a non-root login user: one
+/// that is listening, or one a user systemd unit can start.
+const DETECT_SCRIPT: &str = r#"[ "$(id -u)" = 0 ] && exit 0
+sock="${XDG_RUNTIME_DIR:-/run/user/$(id -u)}/docker.sock"
+if [ -S "$sock" ] || [ -f "$HOME/.config/systemd/user/docker.service" ] || systemctl --user cat docker.service >/dev/null 2>&1; then
+  echo "socket=$sock"
+  echo "home=$HOME"
+  echo "path=$PATH"
+fi"#;
+
+/// A docker daemon running as the login user instead of root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RootlessDocker {
+    /// Path of the daemon's unix socket
+    pub socket: String,
+    home: String,
+    path: String,
+}
+
+impl RootlessDocker {
+    /// Parse the `key=value` lines printed by the detection script.
+    fn parse(output: &str) -> Option<Self> {
+        let mut socket = None;
+        let mut home = String::new();
+        let mut path = String::new();
+        for line in output.lines() {
+            match line.split_once('=') {
+                Some(("socket", value)) if !value.is_empty() => socket = Some(value.to_string()),
+                Some(("home", value)) => home = value.to_string(),
+                Some(("path", value)) => path = value.to_string(),
+                _ => {}
+            }
+        }
+        Some(Self {
+            socket: socket?,
+            home,
+            path,
+        })
+    }
+
+    /// Variables pointing docker at the daemon, with the rootless install
+    /// script's `~/bin` and dcd's `~/.local/bin` on the PATH.
+    pub fn env(&self) -> HashMap<String, String> {
+        HashMap::from([
+            ("DOCKER_HOST".to_string(), format!("unix://{}", self.socket)),
+            (
+                "PATH".to_string(),
+                format!("{0}/bin:{0}/.local/bin:{1}", self.home, self.path),
+            ),
+        ])
+    }
+}
+
+/// Look for a rootless docker daemon of the login user and, if there is one,
+/// point every later command of `executor` at it. Root never uses one.
+pub async fn use_rootless_docker(
+    executor: &mut (dyn ComposeExec + Send),
+) -> DockerResult<Option<RootlessDocker>> {
+    let result = executor.execute_command(DETECT_SCRIPT).await?;
+    if !result.is_success() {
+        return Ok(None);
+    }
+    let rootless = RootlessDocker::parse(&result.output.to_stdout_string()?);
+    if let Some(rootless) = &rootless {
+        let mut env = executor.command_env().clone();
+        env.extend(rootless.env());
+        executor.set_command_env(env);
+    }
+    Ok(rootless)
+}
+
+/// Whether commands of `executor` go to a rootless daemon, so steps that
+/// need root (firewall, host packages, sysctls, systemd units) can't run.
+pub fn is_rootless(executor: &(dyn ComposeExec + Send)) -> bool {
+    executor.command_env().contains_key("DOCKER_HOST")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{CommandMatch, MockExecutor};
+
+    #[test]
+    fn test_parse_rootless_docker() {
+        let rootless = RootlessDocker::parse(
+            "socket=/run/user/1000/docker.sock\nhome=/home/deploy\npath=/usr/bin:/bin\n",
+        )
+        .unwrap();
+        assert_eq!(rootless.socket, "/run/user/1000/docker.sock");
+        let env = rootless.env();
+        assert_eq!(env["DOCKER_HOST"], "unix:///run/user/1000/docker.sock");
+        assert_eq!(
+            env["PATH"],
+            "/home/deploy/bin:/home/deploy/.local/bin:/usr/bin:/bin"
+        );
+        assert_eq!(RootlessDocker::parse(""), None);
+    }
+
+    #[tokio::test]
+    async fn test_use_rootless_docker() {
+        let mut root = MockExecutor::new();
+        root.respond_ok(CommandMatch::Contains("docker.sock".into()), "");
+        assert_eq!(use_rootless_docker(&mut root).await.unwrap(), None);
+        assert!(!is_rootless(&root));
+
+        let mut user = MockExecutor::new();
+        user.respond_ok(
+            CommandMatch::Contains("docker.sock".into()),
+            "socket=/run/user/1000/docker.sock\nhome=/home/deploy\npath=/usr/bin\n",
+        );
+        assert!(use_rootless_docker(&mut user).await.unwrap().is_some());
+        assert!(is_rootless(&user));
+    }
+}
@@ -1,5 +1,9 @@
 use super::error::DockerError;
 
+/// Distributions dcd can install Docker on, for error messages.
+pub const SUPPORTED_DISTROS: &str =
+    "Debian, Ubuntu, Fedora, RHEL, CentOS, Rocky Linux, AlmaLinux, SLES, openSUSE and Alpine";
+
 pub struct DockerVersion {
     pub version: String,
 }
@@ -8,7 +12,98 @@ pub struct DockerVersion {
 pub enum LinuxDistro {
     Debian,
     Ubuntu,
+    Fedora,
+    /// Red Hat Enterprise Linux
+    Rhel,
+    /// CentOS and its rebuilds (Rocky Linux, AlmaLinux)
+    CentOs,
+    /// SUSE Linux Enterprise Server and openSUSE (Leap, Tumbleweed)
+    Suse,
+    Alpine,
     Unknown(String),
 }
 
+impl LinuxDistro {
+    /// Identify the distribution from the contents of `/etc/os-release`,
+    /// falling back to `ID_LIKE` for derivatives (e.g. Linux Mint, Oracle Linux).
+    pub fn from_os_release(os_release: &str) -> Self {
+        let field = |key: &str| {
+            os_release.lines().find_map(|line| {
+                let value = line.strip_prefix(key)?.strip_prefix('=')?;
+                Some(value.trim().trim_matches('"').to_string())
+            })
+        };
+        let id = field("ID").unwrap_or_default().to_lowercase();
+        let like = field("ID_LIKE").unwrap_or_default().to_lowercase();
+        let known = std::iter::once(id.as_str())
+            .chain(like.split_whitespace())
+            .find_map(|id| match id {
+                "debian" => Some(Self::Debian),
+                "ubuntu" => Some(Self::Ubuntu),
+                "fedora" => Some(Self::Fedora),
+                "rhel" => Some(Self::Rhel),
+                "centos" | "rocky" | "almalinux" => Some(Self::CentOs),
+                "sles" | "sled" | "suse" | "opensuse" => Some(Self::Suse),
+                id if id.starts_with("opensuse-") => Some(Self::Suse),
+                "alpine" => Some(Self::Alpine),
+                _ => None,
+            });
+        known.unwrap_or_else(|| {
+            Self::Unknown(
+                field("PRETTY_NAME")
+                    .filter(|name| !name.is_empty())
+                    .unwrap_or_else(|| os_release.trim().to_string()),
+            )
+        })
+    }
+}
+
 pub type DockerResult<T> = Result<T, DockerError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_os_release() {
+        let distro = |s| LinuxDistro::from_os_release(s);
+        assert_eq!(
+            distro("NAME=\"Ubuntu\"\nID=ubuntu\nID_LIKE=debian\n"),
+            LinuxDistro::Ubuntu
+        );
+        assert_eq!(distro("ID=debian\n"), LinuxDistro::Debian);
+        assert_eq!(distro("ID=fedora\nVERSION_ID=40\n"), LinuxDistro::Fedora);
+        assert_eq!(
+            distro("ID=\"rhel\"\nID_LIKE=\"fedora\"\n"),
+            LinuxDistro::Rhel
+        );
+        assert_eq!(
+            distro("ID=\"rocky\"\nID_LIKE=\"rhel centos fedora\"\n"),
+            LinuxDistro::CentOs
+        );
+        assert_eq!(
+            distro("ID=\"almalinux\"\nID_LIKE=\"rhel centos fedora\"\n"),
+            LinuxDistro::CentOs
+        );
+        assert_eq!(
+            distro("ID=linuxmint\nID_LIKE=\"ubuntu debian\"\n"),
+            LinuxDistro::Ubuntu
+        );
+        assert_eq!(
+            distro("ID=alpine\nVERSION_ID=3.20.1\n"),
+            LinuxDistro::Alpine
+        );
+        assert_eq!(
+            distro("ID=\"opensuse-leap\"\nID_LIKE=\"suse opensuse\"\n"),
+            LinuxDistro::Suse
+        );
+        assert_eq!(
+            distro("NAME=\"SLES\"\nVERSION_ID=\"15.6\"\nID=\"sles\"\nID_LIKE=\"suse\"\n"),
+            LinuxDistro::Suse
+        );
+        assert_eq!(
+            distro("PRETTY_NAME=\"Arch Linux\"\nID=arch\n"),
+            LinuxDistro::Unknown("Arch Linux".into())
+        );
+    }
+}
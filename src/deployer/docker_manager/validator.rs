@@ -11,6 +11,8 @@ impl<'a> DockerValidator<'a> {
         Self { executor }
     }
 
+    /// Identify the host's Linux distribution. Fails with
+    /// [`DockerError::UnsupportedRemoteOs`] if the host does not run Linux.
     pub async fn detect_distro(&mut self) -> DockerResult<LinuxDistro> {
         let result = self
             .executor
@@ -18,21 +20,33 @@ impl<'a> DockerValidator<'a> {
             .await
             .map_err(DockerError::from)?;
 
-        if !result.is_success() {
-            return Ok(LinuxDistro::Unknown("Failed to detect".to_string()));
+        if result.is_success() {
+            let output = result
+                .output
+                .to_stdout_string()
+                .map_err(DockerError::from)?;
+            return Ok(LinuxDistro::from_os_release(&output));
         }
 
-        let output = result
-            .output
-            .to_stdout_string()
-            .map_err(DockerError::from)?;
-
-        if output.contains("debian") {
-            Ok(LinuxDistro::Debian)
-        } else if output.contains("ubuntu") {
-            Ok(LinuxDistro::Ubuntu)
-        } else {
-            Ok(LinuxDistro::Unknown(output))
+        // No os-release: ask the kernel what this is. Windows shells have
+        // neither, so a failure here means the host is not a Unix at all
+        let kernel = self
+            .executor
+            .execute_command("uname -s")
+            .await
+            .ok()
+            .filter(|result| result.is_success())
+            .and_then(|result| result.output.to_stdout_string().ok())
+            .map(|kernel| kernel.trim().to_string())
+            .unwrap_or_default();
+        match kernel.as_str() {
+            "Linux" => Ok(LinuxDistro::Unknown(
+                "Linux without /etc/os-release".to_string(),
+            )),
+            "" => Err(DockerError::UnsupportedRemoteOs(
+                "neither /etc/os-release nor uname is available (Windows?)".to_string(),
+            )),
+            other => Err(DockerError::UnsupportedRemoteOs(other.to_string())),
         }
     }
 
@@ -56,6 +70,30 @@ impl<'a> DockerValidator<'a> {
         Ok(result.is_success())
     }
 
+    /// NVIDIA container toolkit installed and registered as a docker runtime.
+    pub async fn is_nvidia_toolkit_installed(&mut self) -> DockerResult<bool> {
+        let result = self
+            .executor
+            .execute_command(
+                "command -v nvidia-ctk >/dev/null && docker info --format '{{json .Runtimes}}' | grep -q nvidia",
+            )
+            .await
+            .map_err(DockerError::from)?;
+
+        Ok(result.is_success())
+    }
+
+    /// NVIDIA driver loaded and at least one GPU visible.
+    pub async fn has_nvidia_gpu(&mut self) -> DockerResult<bool> {
+        let result = self
+            .executor
+            .execute_command("nvidia-smi -L")
+            .await
+            .map_err(DockerError::from)?;
+
+        Ok(result.is_success())
+    }
+
     pub async fn get_docker_version(&mut self) -> DockerResult<DockerVersion> {
         let result = self
             .executor
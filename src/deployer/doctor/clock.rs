@@ -0,0 +1,151 @@
+use super::Finding;
+use crate::deployer::types::{ComposeExec, DeployError, DeployResult};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Skew above which TLS handshakes and token validation start to fail.
+const MAX_CLOCK_SKEW_SECS: f64 = 30.0;
+
+/// Time synchronization state reported by the remote host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NtpState {
+    Synchronized,
+    NotSynchronized,
+    /// No time sync daemon or timedatectl could be found
+    Unavailable,
+}
+
+pub(super) async fn check_clock(
+    executor: &mut (dyn ComposeExec + Send),
+) -> DeployResult<Vec<Finding>> {
+    let before = unix_now();
+    let result = executor
+        .execute_command("date -u +%s && date +%Z")
+        .await
+        .map_err(|e| DeployError::Deployment(format!("Failed to read remote clock: {}", e)))?;
+    let after = unix_now();
+
+    if !result.is_success() {
+        return Ok(vec![Finding::warning(
+            "clock",
+            "Could not read the remote clock ('date' failed)",
+        )]);
+    }
+
+    let lines = result.output.stdout_lines()?;
+    let remote_secs = lines
+        .first()
+        .and_then(|l| l.parse::<i64>().ok())
+        .ok_or_else(|| DeployError::Deployment("Unexpected output from remote 'date'".into()))?;
+    let timezone = lines.get(1).cloned().unwrap_or_default();
+
+    let ntp = detect_ntp_state(executor).await?;
+    // The remote timestamp was taken somewhere between `before` and `after`.
+    let local_secs = (before + after) / 2.0;
+    let mut findings = evaluate_clock(local_secs, remote_secs, &ntp);
+    if !timezone.is_empty() {
+        if let Some(first) = findings.first_mut() {
+            first
+                .message
+                .push_str(&format!(" (remote timezone: {})", timezone));
+        }
+    }
+    Ok(findings)
+}
+
+async fn detect_ntp_state(executor: &mut (dyn ComposeExec + Send)) -> DeployResult<NtpState> {
+    let result = executor
+        .execute_command("timedatectl show -p NTPSynchronized --value")
+        .await
+        .map_err(|e| DeployError::Deployment(e.to_string()))?;
+    if result.is_success() {
+        let value = result.output.to_stdout_string()?;
+        return Ok(if value.trim() == "yes" {
+            NtpState::Synchronized
+        } else {
+            NtpState::NotSynchronized
+        });
+    }
+
+    // No systemd: look for a running time daemon instead
+    let result = executor
+        .execute_command("pgrep -x 'chronyd|ntpd|openntpd|systemd-timesyn'")
+        .await
+        .map_err(|e| DeployError::Deployment(e.to_string()))?;
+    Ok(if result.is_success() {
+        NtpState::Synchronized
+    } else {
+        NtpState::Unavailable
+    })
+}
+
+/// Turn raw clock readings into findings.
+pub fn evaluate_clock(local_secs: f64, remote_secs: i64, ntp: &NtpState) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let skew = remote_secs as f64 - local_secs;
+
+    if skew.abs() > MAX_CLOCK_SKEW_SECS {
+        findings.push(
+            Finding::warning(
+                "clock",
+                format!(
+                    "Remote clock is {:.0}s {} local time; TLS and token-based auth in containers may fail",
+                    skew.abs(),
+                    if skew > 0.0 { "ahead of" } else { "behind" }
+                ),
+            )
+            .with_suggestion("Sync the clock: 'timedatectl set-ntp true' or install chrony"),
+        );
+    } else {
+        findings.push(Finding::ok(
+            "clock",
+            format!(
+                "Remote clock within {:.0}s of local time",
+                skew.abs().ceil()
+            ),
+        ));
+    }
+
+    match ntp {
+        NtpState::Synchronized => findings.push(Finding::ok("ntp", "Time synchronization active")),
+        NtpState::NotSynchronized => findings.push(
+            Finding::warning("ntp", "Remote clock is not synchronized via NTP")
+                .with_suggestion("Enable time sync: 'timedatectl set-ntp true'"),
+        ),
+        NtpState::Unavailable => findings.push(
+            Finding::warning("ntp", "No time synchronization service found on remote")
+                .with_suggestion("Install and enable chrony or systemd-timesyncd"),
+        ),
+    }
+
+    findings
+}
+
+fn unix_now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deployer::doctor::Severity;
+
+    #[test]
+    fn test_evaluate_clock_in_sync() {
+        let findings = evaluate_clock(1_000.4, 1_000, &NtpState::Synchronized);
+        assert!(findings.iter().all(|f| f.severity == Severity::Ok));
+    }
+
+    #[test]
+    fn test_evaluate_clock_skew_and_missing_ntp() {
+        let findings = evaluate_clock(1_000.0, 1_120, &NtpState::Unavailable);
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].severity, Severity::Warning);
+        assert!(findings[0].message.contains("120s ahead of"));
+        assert!(findings[0].suggestion.is_some());
+        assert_eq!(findings[1].check, "ntp");
+        assert_eq!(findings[1].severity, Severity::Warning);
+    }
+}
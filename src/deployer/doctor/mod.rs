@@ -0,0 +1,100 @@
+mod clock;
+mod updates;
+
+use crate::deployer::types::{ComposeExec, DeployResult};
+use serde::Serialize;
+use std::fmt;
+
+pub use clock::{evaluate_clock, NtpState};
+pub use updates::{count_security_updates, evaluate_updates, UpdateState};
+
+/// How serious a doctor finding is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Ok,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Ok => write!(f, "ok"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Result of a single host sanity check.
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    /// Short check identifier, e.g. "clock"
+    pub check: String,
+    pub severity: Severity,
+    pub message: String,
+    /// Suggested fix, shown for warnings and errors
+    pub suggestion: Option<String>,
+}
+
+impl Finding {
+    pub fn ok(check: &str, message: impl Into<String>) -> Self {
+        Self::new(check, Severity::Ok, message)
+    }
+
+    pub fn warning(check: &str, message: impl Into<String>) -> Self {
+        Self::new(check, Severity::Warning, message)
+    }
+
+    pub fn error(check: &str, message: impl Into<String>) -> Self {
+        Self::new(check, Severity::Error, message)
+    }
+
+    fn new(check: &str, severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            check: check.to_string(),
+            severity,
+            message: message.into(),
+            suggestion: None,
+        }
+    }
+
+    pub fn with_suggestion(mut self, suggestion: impl Into<String>) -> Self {
+        self.suggestion = Some(suggestion.into());
+        self
+    }
+
+    pub fn is_problem(&self) -> bool {
+        self.severity != Severity::Ok
+    }
+}
+
+/// Runs read-only sanity checks against the remote host.
+pub struct HostDoctor<'a> {
+    executor: &'a mut (dyn ComposeExec + Send),
+}
+
+impl<'a> HostDoctor<'a> {
+    pub fn new(executor: &'a mut (dyn ComposeExec + Send)) -> Self {
+        Self { executor }
+    }
+
+    /// Run every available check.
+    pub async fn run_all(&mut self) -> DeployResult<Vec<Finding>> {
+        let mut findings = Vec::new();
+        findings.extend(self.check_clock().await?);
+        findings.extend(self.check_updates().await?);
+        Ok(findings)
+    }
+
+    /// Compare the remote clock with the local one and check time synchronization.
+    pub async fn check_clock(&mut self) -> DeployResult<Vec<Finding>> {
+        clock::check_clock(self.executor).await
+    }
+
+    /// Report pending security updates and whether a reboot is required (apt only).
+    pub async fn check_updates(&mut self) -> DeployResult<Vec<Finding>> {
+        updates::check_updates(self.executor).await
+    }
+}
@@ -0,0 +1,127 @@
+use super::Finding;
+use crate::deployer::types::{ComposeExec, DeployError, DeployResult};
+
+/// Pending package state of an apt-based host.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UpdateState {
+    /// Number of upgradable packages coming from a security pocket
+    pub security_updates: usize,
+    /// Whether `/var/run/reboot-required` exists
+    pub reboot_required: bool,
+    /// Packages listed in `/var/run/reboot-required.pkgs`
+    pub reboot_packages: Vec<String>,
+}
+
+pub(super) async fn check_updates(
+    executor: &mut (dyn ComposeExec + Send),
+) -> DeployResult<Vec<Finding>> {
+    let apt = executor
+        .execute_command("command -v apt-get")
+        .await
+        .map_err(|e| DeployError::Deployment(e.to_string()))?;
+    if !apt.is_success() {
+        return Ok(vec![Finding::ok(
+            "updates",
+            "Not an apt-based host, skipping update checks",
+        )]);
+    }
+
+    // Simulated upgrade: no locks taken, nothing installed
+    let simulated = executor
+        .execute_command("apt-get -s -o Debug::NoLocking=true upgrade 2>/dev/null")
+        .await
+        .map_err(|e| DeployError::Deployment(format!("Failed to list pending updates: {}", e)))?;
+    let security_updates = count_security_updates(&simulated.output.to_stdout_string()?);
+
+    let reboot = executor
+        .execute_command(
+            "test -f /var/run/reboot-required && cat /var/run/reboot-required.pkgs 2>/dev/null; \
+             test -f /var/run/reboot-required",
+        )
+        .await
+        .map_err(|e| DeployError::Deployment(e.to_string()))?;
+    let reboot_packages = if reboot.is_success() {
+        reboot.output.stdout_lines()?
+    } else {
+        Vec::new()
+    };
+
+    Ok(evaluate_updates(&UpdateState {
+        security_updates,
+        reboot_required: reboot.is_success(),
+        reboot_packages,
+    }))
+}
+
+/// Count `Inst` lines of a simulated `apt-get upgrade` that come from a
+/// `-security` suite.
+pub fn count_security_updates(simulated_upgrade: &str) -> usize {
+    simulated_upgrade
+        .lines()
+        .filter(|line| line.starts_with("Inst ") && line.contains("-security"))
+        .count()
+}
+
+/// Turn the pending update state into findings.
+pub fn evaluate_updates(state: &UpdateState) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    if state.security_updates > 0 {
+        findings.push(
+            Finding::warning(
+                "updates",
+                format!("{} pending security update(s)", state.security_updates),
+            )
+            .with_suggestion("Install them: 'apt-get update && apt-get upgrade'"),
+        );
+    } else {
+        findings.push(Finding::ok("updates", "No pending security updates"));
+    }
+
+    if state.reboot_required {
+        let mut message = "Reboot required".to_string();
+        if !state.reboot_packages.is_empty() {
+            message.push_str(&format!(" (by {})", state.reboot_packages.join(", ")));
+        }
+        findings.push(
+            Finding::warning("reboot", message)
+                .with_suggestion("Reboot during a quiet period: 'dcd reboot <target> --wait'"),
+        );
+    } else {
+        findings.push(Finding::ok("reboot", "No reboot required"));
+    }
+
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deployer::doctor::Severity;
+
+    #[test]
+    fn test_count_security_updates() {
+        let output = "\
+Reading package lists...
+Inst libssl3 [3.0.2-0ubuntu1.10] (3.0.2-0ubuntu1.12 Ubuntu:22.04/jammy-security [amd64])
+Inst tzdata [2023c-0ubuntu0.22.04.2] (2024a-0ubuntu0.22.04 Ubuntu:22.04/jammy-updates [all])
+Conf libssl3 (3.0.2-0ubuntu1.12 Ubuntu:22.04/jammy-security [amd64])
+";
+        assert_eq!(count_security_updates(output), 1);
+        assert_eq!(count_security_updates(""), 0);
+    }
+
+    #[test]
+    fn test_evaluate_updates_reboot_required() {
+        let findings = evaluate_updates(&UpdateState {
+            security_updates: 3,
+            reboot_required: true,
+            reboot_packages: vec!["linux-image-6.8.0-40-generic".to_string()],
+        });
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].severity, Severity::Warning);
+        assert!(findings[0].message.starts_with("3 pending"));
+        assert_eq!(findings[1].check, "reboot");
+        assert!(findings[1].message.contains("linux-image"));
+    }
+}
@@ -0,0 +1,107 @@
+use crate::deployer::sync::{DiffLine, FileChange, FileDiff, PlannedFile};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// What `up --dry-run` or `destroy --dry-run` found a real run would do on
+/// the server, gathered with read-only commands.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct DryRunReport {
+    /// Remote project directory the commands run in
+    pub remote_dir: PathBuf,
+    /// Files a sync would upload or leave alone, with their checksum status
+    pub files: Vec<PlannedFile>,
+    /// Firewall rules that would be added, e.g. `ufw: allow 443/tcp`
+    pub firewall_rules: Vec<String>,
+    /// Mutating commands and other changing steps that would run, in order
+    pub commands: Vec<String>,
+}
+
+impl DryRunReport {
+    /// Number of files that would be uploaded.
+    pub fn uploads(&self) -> usize {
+        self.files
+            .iter()
+            .filter(|file| file.change != FileChange::Unchanged)
+            .count()
+    }
+
+    /// One line for a host summary, e.g. `2 upload(s), 1 firewall rule(s), 3 command(s)`.
+    pub fn summary(&self) -> String {
+        format!(
+            "{} upload(s), {} firewall rule(s), {} command(s)",
+            self.uploads(),
+            self.firewall_rules.len(),
+            self.commands.len()
+        )
+    }
+}
+
+/// How the local project differs from its deployment (`dcd diff`).
+#[derive(Debug, Default, Clone)]
+pub struct ProjectDiff {
+    /// Remote project directory that was compared
+    pub remote_dir: PathBuf,
+    /// Files a sync would upload or leave alone, with their checksum status
+    pub files: Vec<PlannedFile>,
+    /// Line diffs of the compose and env files a deploy would overwrite
+    pub file_diffs: Vec<FileDiff>,
+    /// `docker compose config` of the deployment against the local one;
+    /// `None` when nothing is deployed or it could not be rendered
+    pub config: Option<Vec<DiffLine>>,
+}
+
+impl ProjectDiff {
+    /// Files that would be uploaded.
+    pub fn uploads(&self) -> impl Iterator<Item = &PlannedFile> {
+        self.files
+            .iter()
+            .filter(|file| file.change != FileChange::Unchanged)
+    }
+
+    /// Whether a deploy would change nothing that was compared.
+    pub fn is_empty(&self) -> bool {
+        self.uploads().next().is_none() && self.config.as_ref().is_none_or(|lines| lines.is_empty())
+    }
+}
+
+/// `docker compose config` rendered in `local_dir` as it reads when rendered
+/// in `remote_dir` for `project`: the project directory is replaced in paths
+/// and the project name set.
+pub fn localize_config(
+    rendered: &str,
+    local_dir: &Path,
+    remote_dir: &Path,
+    project: &str,
+) -> String {
+    let local_dir = local_dir.to_string_lossy();
+    let remote_dir = remote_dir.to_string_lossy();
+    rendered
+        .lines()
+        .map(|line| {
+            if line.starts_with("name: ") {
+                format!("name: {}\n", project)
+            } else {
+                line.replace(local_dir.as_ref(), &remote_dir) + "\n"
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_localize_config() {
+        let rendered = "name: myapp\nservices:\n  web:\n    volumes:\n      - source: /home/me/myapp/html\n        target: /usr/share/nginx/html\n";
+        assert_eq!(
+            localize_config(
+                rendered,
+                Path::new("/home/me/myapp"),
+                Path::new("/opt/shop"),
+                "shop"
+            ),
+            "name: shop\nservices:\n  web:\n    volumes:\n      - source: /opt/shop/html\n        target: /usr/share/nginx/html\n"
+        );
+    }
+}
@@ -0,0 +1,119 @@
+use super::{run_checked, tcp_port_open, FirewallBackend, FirewallManager};
+use crate::deployer::types::{ComposeExec, DeployError, DeployResult};
+use async_trait::async_trait;
+use std::collections::HashSet;
+
+/// Opens ports in the default zone of firewalld (RHEL, Fedora, CentOS).
+pub struct FirewalldManager<'a> {
+    executor: &'a mut (dyn ComposeExec + Send),
+}
+
+impl<'a> FirewalldManager<'a> {
+    pub fn new(executor: &'a mut (dyn ComposeExec + Send)) -> Self {
+        Self { executor }
+    }
+}
+
+#[async_trait]
+impl FirewallManager for FirewalldManager<'_> {
+    fn backend(&self) -> FirewallBackend {
+        FirewallBackend::Firewalld
+    }
+
+    async fn ensure_enabled(&mut self) -> DeployResult<()> {
+        let installed = self
+            .executor
+            .execute_command("command -v firewall-cmd")
+            .await
+            .map_err(|e| DeployError::Firewall(format!("Failed to check firewalld: {}", e)))?;
+        if !installed.is_success() {
+            run_checked(
+                self.executor,
+                "dnf install -y firewalld || yum install -y firewalld \
+                 || zypper --non-interactive install firewalld",
+                "Failed to install firewalld",
+            )
+            .await?;
+        }
+
+        let state = self
+            .executor
+            .execute_command("firewall-cmd --state")
+            .await
+            .map_err(|e| DeployError::Firewall(format!("Failed to check firewalld: {}", e)))?;
+        if !state.is_success() {
+            // Allow SSH in the stored config before filtering starts
+            run_checked(
+                self.executor,
+                "firewall-offline-cmd --add-service=ssh && systemctl enable --now firewalld",
+                "Failed to enable firewalld",
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Ports of the permanent config, which `apply` loads
+    async fn opened_ports(&mut self) -> DeployResult<HashSet<String>> {
+        let output = run_checked(
+            self.executor,
+            "firewall-cmd --permanent --list-ports",
+            "Failed to list firewalld ports",
+        )
+        .await?;
+        Ok(output.split_whitespace().map(str::to_string).collect())
+    }
+
+    /// firewalld has no rule comments, so `comment` is only logged
+    async fn allow_port(&mut self, port: u16, protocol: &str, comment: &str) -> DeployResult<()> {
+        tracing::debug!("Opening {}/{} in firewalld ({})", port, protocol, comment);
+        run_checked(
+            self.executor,
+            &format!("firewall-cmd --permanent --add-port={}/{}", port, protocol),
+            &format!("Failed to add port rule {}/{}", port, protocol),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn allow_port_from(
+        &mut self,
+        port: u16,
+        protocol: &str,
+        source: &str,
+        comment: &str,
+    ) -> DeployResult<()> {
+        tracing::debug!(
+            "Opening {}/{} from {} in firewalld ({})",
+            port,
+            protocol,
+            source,
+            comment
+        );
+        let family = if source.contains(':') { "ipv6" } else { "ipv4" };
+        run_checked(
+            self.executor,
+            &format!(
+                "firewall-cmd --permanent --add-rich-rule='rule family=\"{}\" source address=\"{}\" port port=\"{}\" protocol=\"{}\" accept'",
+                family, source, port, protocol
+            ),
+            &format!("Failed to add port rule {}/{} from {}", port, protocol, source),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn apply(&mut self) -> DeployResult<()> {
+        run_checked(
+            self.executor,
+            "firewall-cmd --reload",
+            "Failed to reload firewalld",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn tcp_port_open(&mut self, port: u16) -> DeployResult<bool> {
+        tcp_port_open(self.executor, port).await
+    }
+}
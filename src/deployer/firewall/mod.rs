@@ -1,6 +1,15 @@
+mod firewalld;
+mod nftables;
 mod ufw;
+use crate::composer::types::PortMapping;
+use crate::deployer::types::{ComposeExec, DeployError, DeployResult};
+use async_trait::async_trait;
+use serde::Serialize;
+use std::collections::HashSet;
 use std::fmt;
 
+pub use firewalld::FirewalldManager;
+pub use nftables::NftablesManager;
 pub use ufw::UfwManager;
 
 #[derive(Debug, Clone)]
@@ -8,6 +17,23 @@ pub struct PortConfig {
     pub port: u16,
     pub protocol: Protocol,
     pub description: String,
+    /// Addresses or CIDR ranges allowed to connect; anywhere when empty
+    pub sources: Vec<String>,
+}
+
+impl PortConfig {
+    /// Firewall openings for the ports the services publish.
+    pub fn for_published(ports: &[PortMapping]) -> Vec<Self> {
+        ports
+            .iter()
+            .map(|port| PortConfig {
+                port: port.target,
+                protocol: Protocol::from(port.protocol.as_deref().unwrap_or("tcp")),
+                description: format!("Docker service port {}", port.published),
+                sources: port.allow_from.clone(),
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -36,3 +62,328 @@ impl fmt::Display for Protocol {
         }
     }
 }
+
+impl Protocol {
+    /// The single protocols a rule has to cover.
+    fn single(&self) -> &'static [&'static str] {
+        match self {
+            Protocol::Tcp => &["tcp"],
+            Protocol::Udp => &["udp"],
+            Protocol::Both => &["tcp", "udp"],
+        }
+    }
+}
+
+/// A rule opening a port over a single protocol, from anywhere or one source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortRule {
+    pub port: u16,
+    pub protocol: &'static str,
+    pub source: Option<String>,
+    pub comment: String,
+}
+
+impl fmt::Display for PortRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.port, self.protocol)?;
+        if let Some(source) = &self.source {
+            write!(f, " from {}", source)?;
+        }
+        Ok(())
+    }
+}
+
+/// Rules `ports` need whose port is not in `opened` (`port/protocol`) yet.
+pub fn missing_rules(ports: &[PortConfig], opened: &HashSet<String>) -> Vec<PortRule> {
+    let mut rules = Vec::new();
+    for config in ports {
+        let comment = if config.description.is_empty() {
+            "Managed by DCD".to_string()
+        } else {
+            format!("DCD: {}", config.description)
+        };
+        for protocol in config.protocol.single() {
+            if opened.contains(&format!("{}/{}", config.port, protocol)) {
+                continue;
+            }
+            let rule = |source: Option<&String>| PortRule {
+                port: config.port,
+                protocol,
+                source: source.cloned(),
+                comment: comment.clone(),
+            };
+            if config.sources.is_empty() {
+                rules.push(rule(None));
+            }
+            rules.extend(config.sources.iter().map(|source| rule(Some(source))));
+        }
+    }
+    rules
+}
+
+/// Firewall implementations dcd can configure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FirewallBackend {
+    Ufw,
+    Firewalld,
+    Nftables,
+}
+
+impl fmt::Display for FirewallBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FirewallBackend::Ufw => write!(f, "ufw"),
+            FirewallBackend::Firewalld => write!(f, "firewalld"),
+            FirewallBackend::Nftables => write!(f, "nftables"),
+        }
+    }
+}
+
+/// Opens the ports of the deployed services on the host firewall.
+#[async_trait]
+pub trait FirewallManager: Send {
+    fn backend(&self) -> FirewallBackend;
+
+    /// Install and enable the firewall if needed, keeping SSH reachable
+    async fn ensure_enabled(&mut self) -> DeployResult<()>;
+
+    /// Ports currently allowed, as `port/protocol`
+    async fn opened_ports(&mut self) -> DeployResult<HashSet<String>>;
+
+    /// Allow incoming traffic to `port` over a single `protocol`
+    async fn allow_port(&mut self, port: u16, protocol: &str, comment: &str) -> DeployResult<()>;
+
+    /// Like [`Self::allow_port`], only for connections from `source`
+    async fn allow_port_from(
+        &mut self,
+        port: u16,
+        protocol: &str,
+        source: &str,
+        comment: &str,
+    ) -> DeployResult<()>;
+
+    /// Make the rules added by `allow_port` take effect and persist
+    async fn apply(&mut self) -> DeployResult<()> {
+        Ok(())
+    }
+
+    /// Undo what this run changed: delete the rules it added and disable the
+    /// firewall again if it enabled it
+    async fn rollback(&mut self) -> DeployResult<()> {
+        Ok(())
+    }
+
+    /// Whether something accepts TCP connections on `port` on the host
+    async fn tcp_port_open(&mut self, port: u16) -> DeployResult<bool>;
+
+    /// Verify port is accessible
+    async fn verify_port(&mut self, port: u16, protocol: &Protocol) -> DeployResult<bool> {
+        // For TCP, we can use nc to test
+        if matches!(protocol, Protocol::Tcp | Protocol::Both) && !self.tcp_port_open(port).await? {
+            return Ok(false);
+        }
+
+        // For UDP, we can only verify the rule exists
+        if matches!(protocol, Protocol::Udp | Protocol::Both) {
+            let ports = self.opened_ports().await?;
+            if !ports.contains(&format!("{}/udp", port)) {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Open the ports that are not allowed yet. A failure reverts what was
+    /// changed so far, so a half-configured firewall cannot lock out SSH.
+    async fn configure_ports(&mut self, ports: &[PortConfig]) -> DeployResult<()> {
+        let result: DeployResult<()> = async {
+            self.ensure_enabled().await?;
+            let current_ports = self.opened_ports().await?;
+
+            let rules = missing_rules(ports, &current_ports);
+            for rule in &rules {
+                match &rule.source {
+                    Some(source) => {
+                        self.allow_port_from(rule.port, rule.protocol, source, &rule.comment)
+                            .await?
+                    }
+                    None => {
+                        self.allow_port(rule.port, rule.protocol, &rule.comment)
+                            .await?
+                    }
+                }
+            }
+            if !rules.is_empty() {
+                self.apply().await?;
+            }
+            Ok(())
+        }
+        .await;
+        if let Err(e) = &result {
+            tracing::warn!(
+                "Firewall configuration failed, reverting this run's changes: {}",
+                e
+            );
+            if let Err(rollback) = self.rollback().await {
+                tracing::error!("{}", rollback);
+            }
+        }
+        result
+    }
+
+    /// Rules [`Self::configure_ports`] would add, without changing anything.
+    /// A firewall that is not installed or enabled yet has no ports open.
+    async fn planned_rules(&mut self, ports: &[PortConfig]) -> Vec<PortRule> {
+        let current_ports = self.opened_ports().await.unwrap_or_default();
+        missing_rules(ports, &current_ports)
+    }
+}
+
+/// Lists the firewall tools and package managers on the host, whether
+/// firewalld is running and whether an nftables ruleset is loaded at boot.
+const DETECT_COMMAND: &str = "for tool in firewall-cmd ufw nft apt-get dnf yum zypper apk; do \
+     command -v $tool >/dev/null 2>&1 && echo $tool; done; \
+     firewall-cmd --state 2>/dev/null; \
+     systemctl is-active --quiet nftables 2>/dev/null && echo nftables-active; true";
+
+/// Pick the firewall to configure on the host. One that is already in use
+/// wins (running firewalld, installed ufw, active nftables service), then an
+/// installed firewalld, then what fits the package manager: ufw on apt,
+/// firewalld on dnf/yum/zypper and plain nftables elsewhere.
+pub async fn detect_backend(
+    executor: &mut (dyn ComposeExec + Send),
+) -> DeployResult<FirewallBackend> {
+    let result = executor
+        .execute_command(DETECT_COMMAND)
+        .await
+        .map_err(|e| DeployError::Firewall(format!("Failed to detect firewall: {}", e)))?;
+    choose_backend(&result.output.to_stdout_string()?).ok_or_else(|| {
+        DeployError::Firewall(
+            "No supported firewall found (ufw, firewalld or nftables) and no package manager \
+             to install one; skip the stage with --skip firewall"
+                .into(),
+        )
+    })
+}
+
+fn choose_backend(detected: &str) -> Option<FirewallBackend> {
+    let found: HashSet<&str> = detected.lines().map(str::trim).collect();
+    let has = |tool: &str| found.contains(tool);
+    if has("firewall-cmd") && has("running") {
+        Some(FirewallBackend::Firewalld)
+    } else if has("ufw") {
+        Some(FirewallBackend::Ufw)
+    } else if has("nft") && has("nftables-active") {
+        Some(FirewallBackend::Nftables)
+    } else if has("firewall-cmd") {
+        Some(FirewallBackend::Firewalld)
+    } else if has("apt-get") {
+        Some(FirewallBackend::Ufw)
+    } else if has("dnf") || has("yum") || has("zypper") {
+        Some(FirewallBackend::Firewalld)
+    } else if has("nft") || has("apk") {
+        Some(FirewallBackend::Nftables)
+    } else {
+        None
+    }
+}
+
+/// Manager for the firewall detected on the host.
+pub async fn firewall_manager<'a>(
+    executor: &'a mut (dyn ComposeExec + Send),
+) -> DeployResult<Box<dyn FirewallManager + 'a>> {
+    Ok(match detect_backend(executor).await? {
+        FirewallBackend::Ufw => Box::new(UfwManager::new(executor)),
+        FirewallBackend::Firewalld => Box::new(FirewalldManager::new(executor)),
+        FirewallBackend::Nftables => Box::new(NftablesManager::new(executor)),
+    })
+}
+
+async fn tcp_port_open(executor: &mut (dyn ComposeExec + Send), port: u16) -> DeployResult<bool> {
+    let result = executor
+        .execute_command(&format!("nc -z -v localhost {}", port))
+        .await
+        .map_err(|e| DeployError::Firewall(e.to_string()))?;
+    Ok(result.is_success())
+}
+
+/// Run a firewall command, failing with `what` when it exits non-zero.
+async fn run_checked(
+    executor: &mut (dyn ComposeExec + Send),
+    cmd: &str,
+    what: &str,
+) -> DeployResult<String> {
+    let result = executor
+        .execute_command(cmd)
+        .await
+        .map_err(|e| DeployError::Firewall(format!("{}: {}", what, e)))?;
+    if !result.is_success() {
+        return Err(DeployError::Firewall(format!(
+            "{}: {}",
+            what,
+            result.output.to_stderr_string()?.trim()
+        )));
+    }
+    Ok(result.output.to_stdout_string()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_choose_backend() {
+        assert_eq!(
+            choose_backend("firewall-cmd\nufw\ndnf\nrunning\n"),
+            Some(FirewallBackend::Firewalld)
+        );
+        assert_eq!(
+            choose_backend("firewall-cmd\nufw\nnot running\n"),
+            Some(FirewallBackend::Ufw)
+        );
+        // Debian ships nft; without a loaded ruleset ufw is installed as before
+        assert_eq!(choose_backend("nft\napt-get\n"), Some(FirewallBackend::Ufw));
+        assert_eq!(
+            choose_backend("nft\napt-get\nnftables-active\n"),
+            Some(FirewallBackend::Nftables)
+        );
+        assert_eq!(choose_backend("dnf\n"), Some(FirewallBackend::Firewalld));
+        assert_eq!(choose_backend("zypper\n"), Some(FirewallBackend::Firewalld));
+        assert_eq!(choose_backend("apk\n"), Some(FirewallBackend::Nftables));
+        assert_eq!(choose_backend(""), None);
+    }
+
+    #[test]
+    fn test_missing_rules() {
+        let ports = [
+            PortConfig {
+                port: 80,
+                protocol: Protocol::Both,
+                description: String::new(),
+                sources: Vec::new(),
+            },
+            PortConfig {
+                port: 5432,
+                protocol: Protocol::Tcp,
+                description: "db".into(),
+                sources: vec!["10.0.0.0/8".into(), "192.168.1.5".into()],
+            },
+        ];
+        let opened = HashSet::from(["80/tcp".to_string()]);
+        let rules: Vec<String> = missing_rules(&ports, &opened)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            rules,
+            vec![
+                "80/udp",
+                "5432/tcp from 10.0.0.0/8",
+                "5432/tcp from 192.168.1.5"
+            ]
+        );
+        assert_eq!(missing_rules(&ports, &opened)[1].comment, "DCD: db");
+    }
+}
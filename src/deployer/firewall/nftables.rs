@@ -0,0 +1,219 @@
+use super::{run_checked, tcp_port_open, FirewallBackend, FirewallManager};
+use crate::deployer::types::{ComposeExec, DeployError, DeployResult};
+use crate::executor::shell_quote;
+use async_trait::async_trait;
+use std::collections::HashSet;
+
+/// Chain the rules go to: the `input` chain of the conventional `inet
+/// filter` table most distro rulesets define.
+const CHAIN: &str = "inet filter input";
+
+/// Drop-in loaded by the stock ruleset on Alpine; elsewhere the rules only
+/// last until the ruleset is reloaded.
+const PERSIST_DIR: &str = "/etc/nftables.d";
+
+/// Opens ports with plain nftables rules.
+pub struct NftablesManager<'a> {
+    executor: &'a mut (dyn ComposeExec + Send),
+}
+
+impl<'a> NftablesManager<'a> {
+    pub fn new(executor: &'a mut (dyn ComposeExec + Send)) -> Self {
+        Self { executor }
+    }
+}
+
+#[async_trait]
+impl FirewallManager for NftablesManager<'_> {
+    fn backend(&self) -> FirewallBackend {
+        FirewallBackend::Nftables
+    }
+
+    /// Creates the chain with an accept policy when the host has none, so
+    /// nothing gets blocked that was reachable before.
+    async fn ensure_enabled(&mut self) -> DeployResult<()> {
+        let installed = self
+            .executor
+            .execute_command("command -v nft")
+            .await
+            .map_err(|e| DeployError::Firewall(format!("Failed to check nftables: {}", e)))?;
+        if !installed.is_success() {
+            run_checked(
+                self.executor,
+                "apk add nftables || apt-get install -y nftables || dnf install -y nftables \
+                 || zypper --non-interactive install nftables",
+                "Failed to install nftables",
+            )
+            .await?;
+        }
+
+        let cmd = format!(
+            "nft list chain {chain} >/dev/null 2>&1 || nft {}",
+            shell_quote(
+                "add table inet filter; \
+                 add chain inet filter input { type filter hook input priority 0; policy accept; }"
+            ),
+            chain = CHAIN
+        );
+        run_checked(
+            self.executor,
+            &cmd,
+            "Failed to create the nftables input chain",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn opened_ports(&mut self) -> DeployResult<HashSet<String>> {
+        let output = run_checked(
+            self.executor,
+            &format!("nft list chain {}", CHAIN),
+            "Failed to list nftables rules",
+        )
+        .await?;
+        Ok(accepted_ports(&output))
+    }
+
+    /// Inserted at the top so later drop rules in the chain don't shadow it
+    async fn allow_port(&mut self, port: u16, protocol: &str, comment: &str) -> DeployResult<()> {
+        let comment = comment.replace(['"', '\''], "");
+        run_checked(
+            self.executor,
+            &format!(
+                "nft insert rule {} {} dport {} accept comment '\"{}\"'",
+                CHAIN, protocol, port, comment
+            ),
+            &format!("Failed to add port rule {}/{}", port, protocol),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn allow_port_from(
+        &mut self,
+        port: u16,
+        protocol: &str,
+        source: &str,
+        comment: &str,
+    ) -> DeployResult<()> {
+        let comment = comment.replace(['"', '\''], "");
+        let family = if source.contains(':') { "ip6" } else { "ip" };
+        run_checked(
+            self.executor,
+            &format!(
+                "nft insert rule {} {} saddr {} {} dport {} accept comment '\"{}\"'",
+                CHAIN, family, source, protocol, port, comment
+            ),
+            &format!(
+                "Failed to add port rule {}/{} from {}",
+                port, protocol, source
+            ),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Write dcd's rules to the drop-in directory when the host has one
+    async fn apply(&mut self) -> DeployResult<()> {
+        let listing = run_checked(
+            self.executor,
+            &format!("nft list chain {}", CHAIN),
+            "Failed to list nftables rules",
+        )
+        .await?;
+        let file = format!("{}/dcd.nft", PERSIST_DIR);
+        let cmd = format!(
+            "if [ -d {dir} ]; then printf '%s' {} > {file}; else echo missing; fi",
+            shell_quote(&persisted_rules(&listing)),
+            dir = PERSIST_DIR,
+            file = file
+        );
+        let output = run_checked(self.executor, &cmd, "Failed to save nftables rules").await?;
+        if output.trim() == "missing" {
+            tracing::warn!(
+                "nftables rules added by dcd are not persisted ({} does not exist); \
+                 add them to your ruleset to keep them after a reboot",
+                PERSIST_DIR
+            );
+        }
+        Ok(())
+    }
+
+    async fn tcp_port_open(&mut self, port: u16) -> DeployResult<bool> {
+        tcp_port_open(self.executor, port).await
+    }
+}
+
+/// `port/protocol` of the rules accepting a destination port, including
+/// port sets like `tcp dport { 80, 443 } accept`.
+fn accepted_ports(listing: &str) -> HashSet<String> {
+    let mut ports = HashSet::new();
+    for line in listing.lines().filter(|line| line.contains("accept")) {
+        let tokens: Vec<&str> = line
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|token| !token.is_empty())
+            .collect();
+        for (i, pair) in tokens.windows(2).enumerate() {
+            let protocol = pair[0];
+            if !matches!(protocol, "tcp" | "udp") || pair[1] != "dport" {
+                continue;
+            }
+            let values = &tokens[i + 2..];
+            let values = match values.first() {
+                Some(&"{") => &values[1..values.iter().position(|t| *t == "}").unwrap_or(1)],
+                _ => &values[..values.len().min(1)],
+            };
+            for value in values {
+                if let Ok(port) = value.parse::<u16>() {
+                    ports.insert(format!("{}/{}", port, protocol));
+                }
+            }
+        }
+    }
+    ports
+}
+
+/// Ruleset file re-adding the rules dcd created (those with a dcd comment).
+fn persisted_rules(listing: &str) -> String {
+    let mut content = String::from("# Generated by dcd\n");
+    for line in listing.lines().map(str::trim) {
+        if line.contains("comment \"DCD") || line.contains("comment \"Managed by DCD") {
+            content.push_str(&format!("insert rule {} {}\n", CHAIN, line));
+        }
+    }
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LISTING: &str = r#"table inet filter {
+	chain input {
+		type filter hook input priority filter; policy drop;
+		udp dport 51820 accept comment "DCD: Docker service port 51820"
+		tcp dport 8080 accept comment "Managed by DCD"
+		ct state established,related accept
+		tcp dport { 22, 443 } accept
+		tcp dport 25 drop
+	}
+}"#;
+
+    #[test]
+    fn test_accepted_ports() {
+        let ports = accepted_ports(LISTING);
+        let mut ports: Vec<&str> = ports.iter().map(String::as_str).collect();
+        ports.sort();
+        assert_eq!(ports, vec!["22/tcp", "443/tcp", "51820/udp", "8080/tcp"]);
+    }
+
+    #[test]
+    fn test_persisted_rules() {
+        assert_eq!(
+            persisted_rules(LISTING),
+            "# Generated by dcd\n\
+             insert rule inet filter input udp dport 51820 accept comment \"DCD: Docker service port 51820\"\n\
+             insert rule inet filter input tcp dport 8080 accept comment \"Managed by DCD\"\n"
+        );
+    }
+}
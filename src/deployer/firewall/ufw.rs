@@ -1,14 +1,33 @@
-use super::{PortConfig, Protocol};
+use super::{run_checked, tcp_port_open, FirewallBackend, FirewallManager};
 use crate::deployer::types::{ComposeExec, DeployError, DeployResult};
+use async_trait::async_trait;
 use std::collections::HashSet;
 
+/// How long `ufw enable` may take before it counts as hung.
+const ENABLE_TIMEOUT_SECS: u32 = 30;
+
+/// Delay after which the watchdog started before `ufw enable` disables the
+/// firewall again, unless the SSH session survives enabling and cancels it.
+const WATCHDOG_SECS: u32 = 120;
+
 pub struct UfwManager<'a> {
     executor: &'a mut (dyn ComposeExec + Send),
+    /// Rules this run added, as `ufw` arguments, newest last
+    added_rules: Vec<String>,
+    /// Whether this run (tried to) enable ufw
+    enabled: bool,
+    /// Process that disables ufw unless killed in time
+    watchdog: Option<u32>,
 }
 
 impl<'a> UfwManager<'a> {
     pub fn new(executor: &'a mut (dyn ComposeExec + Send)) -> Self {
-        Self { executor }
+        Self {
+            executor,
+            added_rules: Vec::new(),
+            enabled: false,
+            watchdog: None,
+        }
     }
 
     /// Ensure UFW is installed and enabled
@@ -37,42 +56,114 @@ impl<'a> UfwManager<'a> {
             .map_err(|e| DeployError::Firewall(format!("Failed to check UFW status: {}", e)))?;
 
         if !status.output.to_stdout_string()?.contains("Status: active") {
-            // Allow SSH first to prevent lockout
-            self.executor
-                .execute_command("ufw allow 22/tcp")
-                .await
-                .map_err(|e| DeployError::Firewall(format!("Failed to allow SSH: {}", e)))?;
-
-            // Enable UFW
-            self.executor
-                .execute_command("echo 'y' | ufw enable")
-                .await
-                .map_err(|e| DeployError::Firewall(format!("Failed to enable UFW: {}", e)))?;
+            // Allow SSH first to prevent lockout, on the port of this session too
+            let mut ssh_ports = vec![22];
+            ssh_ports.extend(self.session_port().await.filter(|port| *port != 22));
+            for port in ssh_ports {
+                self.add_rule(format!("allow {}/tcp", port), "DCD: SSH")
+                    .await?;
+            }
+            self.enable().await?;
         }
 
         Ok(())
     }
 
-    /// Configure ports in UFW
-    pub async fn configure_ports(&mut self, ports: &[PortConfig]) -> DeployResult<()> {
-        // Ensure UFW is ready
-        self.ensure_ufw().await?;
+    /// Server port of this SSH session, if it runs over SSH.
+    async fn session_port(&mut self) -> Option<u16> {
+        let result = self
+            .executor
+            .execute_command("echo \"${SSH_CONNECTION:-}\"")
+            .await
+            .ok()?;
+        // "<client ip> <client port> <server ip> <server port>"
+        result
+            .output
+            .to_stdout_string()
+            .ok()?
+            .split_whitespace()
+            .nth(3)?
+            .parse()
+            .ok()
+    }
+
+    /// Enable ufw behind a watchdog: should enabling cut off this session,
+    /// the watchdog disables ufw again after [`WATCHDOG_SECS`].
+    async fn enable(&mut self) -> DeployResult<()> {
+        let watchdog = run_checked(
+            self.executor,
+            &format!(
+                "nohup sh -c 'sleep {}; ufw --force disable' >/dev/null 2>&1 & echo $!",
+                WATCHDOG_SECS
+            ),
+            "Failed to start the UFW watchdog",
+        )
+        .await?;
+        self.watchdog = watchdog.trim().parse().ok();
 
-        // Get currently opened ports
-        let current_ports = self.get_opened_ports().await?;
+        self.enabled = true;
+        run_checked(
+            self.executor,
+            &format!("timeout {} ufw --force enable", ENABLE_TIMEOUT_SECS),
+            "Failed to enable UFW",
+        )
+        .await?;
 
-        // Configure each port
-        for port_config in ports {
-            if !self.is_port_configured(&current_ports, port_config) {
-                self.add_port_rule(port_config).await?;
-            }
+        // Getting through to the host again proves SSH still works
+        if let Some(pid) = self.watchdog {
+            run_checked(
+                self.executor,
+                &format!("kill {}", pid),
+                &format!(
+                    "Lost the connection after enabling UFW; it is disabled again within {}s",
+                    WATCHDOG_SECS
+                ),
+            )
+            .await?;
+            self.watchdog = None;
         }
+        Ok(())
+    }
 
+    /// Run `ufw <rule> comment '<comment>'`, remembering the rule for
+    /// rollback unless ufw already had it.
+    async fn add_rule(&mut self, rule: String, comment: &str) -> DeployResult<()> {
+        let cmd = format!("ufw {} comment '{}'", rule, comment.replace('\'', ""));
+        let output = run_checked(
+            self.executor,
+            &cmd,
+            &format!("Failed to add UFW rule '{}'", rule),
+        )
+        .await?;
+        if output.contains("Rule added") {
+            self.added_rules.push(rule);
+        }
         Ok(())
     }
 
+    /// Extract port and protocol from UFW rule
+    fn extract_port_from_rule(&self, rule: &str) -> Option<String> {
+        // Example rule: "[ 2] 80/tcp                     ALLOW IN    Anywhere"
+        let parts: Vec<&str> = rule.split_whitespace().collect();
+        parts
+            .iter()
+            .find(|&&p| p.contains('/'))
+            .map(|&s| s.to_string())
+    }
+}
+
+#[async_trait]
+impl FirewallManager for UfwManager<'_> {
+    fn backend(&self) -> FirewallBackend {
+        FirewallBackend::Ufw
+    }
+
+    async fn ensure_enabled(&mut self) -> DeployResult<()> {
+        self.ensure_ufw().await
+    }
+
     /// Get currently opened ports
-    async fn get_opened_ports(&mut self) -> DeployResult<HashSet<String>> {
+    async fn opened_ports(&mut self) -> DeployResult<HashSet<String>> {
         let result = self
             .executor
             .execute_command("ufw status numbered")
@@ -93,99 +184,124 @@ impl<'a> UfwManager<'a> {
         Ok(ports)
     }
 
-    /// Extract port and protocol from UFW rule
-    fn extract_port_from_rule(&self, rule: &str) -> Option<String> {
-        // Example rule: "[ 2] 80/tcp                     ALLOW IN    Anywhere"
-        let parts: Vec<&str> = rule.split_whitespace().collect();
-        parts
-            .iter()
-            .find(|&&p| p.contains('/'))
-            .map(|&s| s.to_string())
-    }
-
-    /// Check if port is already configured
-    fn is_port_configured(&self, current_ports: &HashSet<String>, config: &PortConfig) -> bool {
-        match config.protocol {
-            Protocol::Both => {
-                current_ports.contains(&format!("{}/tcp", config.port))
-                    && current_ports.contains(&format!("{}/udp", config.port))
-            }
-            _ => current_ports.contains(&format!("{}/{}", config.port, config.protocol)),
-        }
-    }
-
-    /// Add new port rule
-    async fn add_port_rule(&mut self, config: &PortConfig) -> DeployResult<()> {
-        let comment = if config.description.is_empty() {
-            "Managed by DCD".to_string()
-        } else {
-            format!("DCD: {}", config.description)
-        };
-
-        match config.protocol {
-            Protocol::Both => {
-                self.add_single_port_rule(config.port, "tcp", &comment)
-                    .await?;
-                self.add_single_port_rule(config.port, "udp", &comment)
-                    .await?;
-            }
-            _ => {
-                self.add_single_port_rule(config.port, &config.protocol.to_string(), &comment)
-                    .await?;
-            }
-        }
-
-        Ok(())
+    /// Add single port rule with specific protocol
+    async fn allow_port(&mut self, port: u16, protocol: &str, comment: &str) -> DeployResult<()> {
+        self.add_rule(format!("allow {}/{}", port, protocol), comment)
+            .await
     }
 
-    /// Add single port rule with specific protocol
-    async fn add_single_port_rule(
+    async fn allow_port_from(
         &mut self,
         port: u16,
         protocol: &str,
+        source: &str,
         comment: &str,
     ) -> DeployResult<()> {
-        let cmd = format!(
-            "ufw allow {}/{} comment '{}'",
-            port,
-            protocol,
-            comment.replace('\'', "\\'")
-        );
-
-        self.executor.execute_command(&cmd).await.map_err(|e| {
-            DeployError::Firewall(format!(
-                "Failed to add port rule {}/{}: {}",
-                port, protocol, e
-            ))
-        })?;
-
-        Ok(())
+        self.add_rule(
+            format!(
+                "allow from {} to any port {} proto {}",
+                source, port, protocol
+            ),
+            comment,
+        )
+        .await
     }
 
-    /// Verify port is accessible
-    pub async fn verify_port(&mut self, port: u16, protocol: &Protocol) -> DeployResult<bool> {
-        // For TCP, we can use nc to test
-        if matches!(protocol, Protocol::Tcp | Protocol::Both) {
-            let cmd = format!("nc -z -v localhost {}", port);
-            let result = self
-                .executor
-                .execute_command(&cmd)
-                .await
-                .map_err(|e| DeployError::Firewall(e.to_string()))?;
-
-            if !result.is_success() {
-                return Ok(false);
+    async fn rollback(&mut self) -> DeployResult<()> {
+        let mut failures = Vec::new();
+        while let Some(rule) = self.added_rules.pop() {
+            let cmd = format!("ufw delete {}", rule);
+            if let Err(e) = run_checked(self.executor, &cmd, &format!("'{}'", cmd)).await {
+                failures.push(e.to_string());
             }
         }
-
-        // For UDP, we can only verify the rule exists
-        if matches!(protocol, Protocol::Udp | Protocol::Both) {
-            let ports = self.get_opened_ports().await?;
-            if !ports.contains(&format!("{}/udp", port)) {
-                return Ok(false);
+        if std::mem::take(&mut self.enabled) {
+            if let Err(e) = run_checked(self.executor, "ufw --force disable", "'ufw disable'").await
+            {
+                failures.push(e.to_string());
             }
         }
+        if let Some(pid) = self.watchdog.take() {
+            // Not needed any more; it may have exited already
+            let _ = self
+                .executor
+                .execute_command(&format!("kill {}", pid))
+                .await;
+        }
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(DeployError::Firewall(format!(
+                "Failed to revert UFW changes: {}",
+                failures.join("; ")
+            )))
+        }
+    }
 
-        Ok(true)
+    async fn tcp_port_open(&mut self, port: u16) -> DeployResult<bool> {
+        tcp_port_open(self.executor, port).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deployer::firewall::{PortConfig, Protocol};
+    use crate::testing::{CommandMatch, MockExecutor};
+
+    #[tokio::test]
+    async fn test_failed_rule_rolls_back() {
+        let mut executor = MockExecutor::new();
+        executor.respond_ok("which ufw", "/usr/sbin/ufw\n");
+        executor.respond_ok("ufw status", "Status: inactive\n");
+        executor.respond_ok(
+            "echo \"${SSH_CONNECTION:-}\"",
+            "203.0.113.7 51000 198.51.100.2 2222\n",
+        );
+        executor.respond_ok(CommandMatch::Prefix("nohup ".into()), "4242\n");
+        executor.respond_ok("timeout 30 ufw --force enable", "Firewall is active\n");
+        executor.respond_ok("kill 4242", "");
+        executor.respond_ok("ufw status numbered", "Status: active\n");
+        // 22/tcp was allowed before, so it stays when rolling back
+        executor.respond_ok(
+            CommandMatch::Prefix("ufw allow 22/tcp ".into()),
+            "Skipping adding existing rule\n",
+        );
+        executor.respond_ok(
+            CommandMatch::Prefix("ufw allow 2222/tcp ".into()),
+            "Rule added\nRule added (v6)\n",
+        );
+        executor.respond_ok(
+            CommandMatch::Prefix("ufw allow 8080/tcp ".into()),
+            "Rule added\n",
+        );
+        executor.respond_err(
+            CommandMatch::Prefix("ufw allow 8080/udp ".into()),
+            1,
+            "ERROR",
+        );
+        executor.respond_ok(CommandMatch::Prefix("ufw delete ".into()), "Rule deleted\n");
+        executor.respond_ok("ufw --force disable", "Firewall stopped\n");
+
+        let ports = [PortConfig {
+            port: 8080,
+            protocol: Protocol::Both,
+            description: "web".into(),
+            sources: Vec::new(),
+        }];
+        let mut ufw = UfwManager::new(&mut executor);
+        let err = ufw.configure_ports(&ports).await.unwrap_err();
+        assert!(err.to_string().contains("8080/udp"));
+
+        let commands = executor.commands();
+        let rollback = &commands[commands.len() - 3..];
+        assert_eq!(
+            rollback,
+            [
+                "ufw delete allow 8080/tcp",
+                "ufw delete allow 2222/tcp",
+                "ufw --force disable"
+            ]
+        );
     }
 }
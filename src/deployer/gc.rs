@@ -0,0 +1,229 @@
+use crate::deployer::state::RemoteState;
+use crate::deployer::types::{ComposeExec, DeployError, DeployResult};
+use crate::deployer::{BACKUP_SUFFIX, PREVIOUS_SUFFIX};
+use crate::executor::shell_quote;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Files removed per remote command, keeping command lines short.
+const REMOVE_BATCH_SIZE: usize = 200;
+
+/// What `dcd gc` removed, or would remove on a dry run.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct GcReport {
+    pub removed: Vec<PathBuf>,
+    /// Whether the rollback point was past its retention and got dropped
+    pub rollback_expired: bool,
+    pub dry_run: bool,
+}
+
+/// `*.backup` and `*.previous` copies under `remote_dir` that sit next to the
+/// file they copy, the way dcd leaves them. Directories bind-mounted into
+/// the project's containers are not searched, so data kept there is never a
+/// candidate even if its names happen to match.
+pub async fn list_copies(
+    executor: &mut (dyn ComposeExec + Send),
+    remote_dir: &Path,
+    project: &str,
+) -> DeployResult<Vec<PathBuf>> {
+    let mounts = run(executor, &bind_mounts_command(project)).await?;
+    let mounted: Vec<PathBuf> = mounts
+        .lines()
+        .map(|line| PathBuf::from(line.trim()))
+        .filter(|source| source.starts_with(remote_dir) || remote_dir.starts_with(source))
+        .collect();
+    if mounted.iter().any(|source| remote_dir.starts_with(source)) {
+        tracing::info!(
+            "{} is mounted into a container as a whole, not searching it",
+            remote_dir.display()
+        );
+        return Ok(Vec::new());
+    }
+    let listing = run(executor, &find_copies_command(remote_dir, &mounted)).await?;
+    let mut copies: Vec<PathBuf> = listing
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect();
+    copies.sort();
+    Ok(copies)
+}
+
+/// Sources of the bind mounts of every container in compose project `project`.
+fn bind_mounts_command(project: &str) -> String {
+    format!(
+        "docker ps -aq --filter label=com.docker.compose.project={} | xargs -r docker inspect \
+         --format '{{{{range .Mounts}}}}{{{{if eq .Type \"bind\"}}}}{{{{println .Source}}}}{{{{end}}}}{{{{end}}}}'",
+        shell_quote(project)
+    )
+}
+
+fn find_copies_command(remote_dir: &Path, mounted: &[PathBuf]) -> String {
+    let prune = if mounted.is_empty() {
+        String::new()
+    } else {
+        let paths: Vec<String> = mounted
+            .iter()
+            .map(|path| format!("-path {}", shell_quote(&path.to_string_lossy())))
+            .collect();
+        format!("\\( {} \\) -prune -o ", paths.join(" -o "))
+    };
+    format!(
+        "find {} {}-type f \\( -name '*{backup}' -o -name '*{previous}' \\) -print 2>/dev/null | \
+         while IFS= read -r f; do o=\"${{f%{backup}}}\"; o=\"${{o%{previous}}}\"; \
+         if [ -e \"$o\" ]; then printf '%s\\n' \"$f\"; fi; done",
+        shell_quote(&remote_dir.to_string_lossy()),
+        prune,
+        backup = BACKUP_SUFFIX,
+        previous = PREVIOUS_SUFFIX
+    )
+}
+
+/// Whether the rollback point in `state` is older than `retention` at `now`
+/// (unix seconds). Its age counts from the deployment that replaced it.
+pub fn rollback_expired(state: &RemoteState, now: u64, retention: Duration) -> bool {
+    let Some(point) = &state.rollback else {
+        return false;
+    };
+    let replaced_at = state
+        .last_deploy
+        .as_ref()
+        .or(point.last_deploy.as_ref())
+        .map_or(0, |record| record.at);
+    now.saturating_sub(replaced_at) > retention.as_secs()
+}
+
+/// The copies among `copies` that nothing refers to any more, assuming no
+/// deployment is pending: all backups, and `*.previous` copies outside the
+/// rollback point (all of them when it expired).
+pub fn select_garbage(
+    copies: &[PathBuf],
+    state: &RemoteState,
+    rollback_expired: bool,
+) -> Vec<PathBuf> {
+    let kept: &[PathBuf] = match &state.rollback {
+        Some(point) if !rollback_expired => &point.paths,
+        _ => &[],
+    };
+    copies
+        .iter()
+        .filter(|copy| {
+            let name = copy.to_string_lossy();
+            if name.ends_with(BACKUP_SUFFIX) {
+                return true;
+            }
+            match name.strip_suffix(PREVIOUS_SUFFIX) {
+                // Rollback paths are synced files or whole directories
+                Some(original) => !kept
+                    .iter()
+                    .any(|path| Path::new(original).starts_with(path)),
+                None => false,
+            }
+        })
+        .cloned()
+        .collect()
+}
+
+/// Delete `files` on the remote host.
+pub async fn remove_files(
+    executor: &mut (dyn ComposeExec + Send),
+    files: &[PathBuf],
+) -> DeployResult<()> {
+    for chunk in files.chunks(REMOVE_BATCH_SIZE) {
+        let quoted: Vec<String> = chunk
+            .iter()
+            .map(|file| shell_quote(&file.to_string_lossy()))
+            .collect();
+        run(executor, &format!("rm -f -- {}", quoted.join(" "))).await?;
+    }
+    Ok(())
+}
+
+async fn run(executor: &mut (dyn ComposeExec + Send), cmd: &str) -> DeployResult<String> {
+    let result = executor
+        .execute_command(cmd)
+        .await
+        .map_err(|e| DeployError::Deployment(format!("Failed to run '{}': {}", cmd, e)))?;
+    if !result.is_success() {
+        return Err(DeployError::Deployment(format!(
+            "Command '{}' failed: {}",
+            cmd,
+            result.output.to_stderr_string()?.trim()
+        )));
+    }
+    Ok(result.output.to_stdout_string()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deployer::state::{DeployRecord, RollbackPoint};
+
+    fn state_with_rollback(paths: &[&str]) -> RemoteState {
+        RemoteState {
+            last_deploy: Some(DeployRecord {
+                at: 1_000,
+                ..Default::default()
+            }),
+            rollback: Some(RollbackPoint {
+                paths: paths.iter().map(PathBuf::from).collect(),
+                profiles: Vec::new(),
+                compose_files: vec![PathBuf::from("docker-compose.yml")],
+                env_files: Vec::new(),
+                images: Default::default(),
+                last_deploy: None,
+                encrypted_env: false,
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_select_garbage() {
+        let copies: Vec<PathBuf> = [
+            "/opt/app/config/app.toml.previous",
+            "/opt/app/docker-compose.yml.backup",
+            "/opt/app/docker-compose.yml.previous",
+            "/opt/app/old/site.conf.previous",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+        let state = state_with_rollback(&["/opt/app/docker-compose.yml", "/opt/app/config"]);
+
+        assert_eq!(
+            select_garbage(&copies, &state, false),
+            vec![
+                PathBuf::from("/opt/app/docker-compose.yml.backup"),
+                PathBuf::from("/opt/app/old/site.conf.previous"),
+            ]
+        );
+        assert_eq!(select_garbage(&copies, &state, true), copies);
+        assert_eq!(
+            select_garbage(&copies, &RemoteState::default(), false),
+            copies
+        );
+    }
+
+    #[test]
+    fn test_rollback_expired() {
+        let state = state_with_rollback(&[]);
+        let day = Duration::from_secs(86_400);
+        assert!(!rollback_expired(&state, 1_000 + 3_600, day));
+        assert!(rollback_expired(&state, 1_000 + 86_401, day));
+        assert!(rollback_expired(&state, 1_001, Duration::ZERO));
+        assert!(!rollback_expired(&RemoteState::default(), 1_000_000, day));
+    }
+
+    #[test]
+    fn test_find_copies_command() {
+        assert_eq!(
+            find_copies_command(Path::new("/opt/app"), &[PathBuf::from("/opt/app/data")]),
+            "find /opt/app \\( -path /opt/app/data \\) -prune -o -type f \
+             \\( -name '*.backup' -o -name '*.previous' \\) -print 2>/dev/null | \
+             while IFS= read -r f; do o=\"${f%.backup}\"; o=\"${o%.previous}\"; \
+             if [ -e \"$o\" ]; then printf '%s\\n' \"$f\"; fi; done"
+        );
+    }
+}
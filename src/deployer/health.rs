@@ -0,0 +1,459 @@
+use super::docker_manager::{HealthCheckResult, UnhealthyService};
+use crate::composer::types::{DependencyCondition, ServiceDependency};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// `depends_on` relations between services, used to explain and short-circuit
+/// the health check loop.
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+    /// Dependencies keyed by dependent service name
+    dependencies: HashMap<String, Vec<ServiceDependency>>,
+    /// Services left out of the health check (`x-dcd: skip-health-check`)
+    skipped: HashSet<String>,
+}
+
+impl DependencyGraph {
+    pub fn new(dependencies: HashMap<String, Vec<ServiceDependency>>) -> Self {
+        Self {
+            dependencies,
+            skipped: HashSet::new(),
+        }
+    }
+
+    /// Ignore the health of `services`.
+    pub fn skipping(mut self, services: impl IntoIterator<Item = String>) -> Self {
+        self.skipped.extend(services);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dependencies.is_empty() && self.skipped.is_empty()
+    }
+
+    /// Services depending on `service`, with how they depend on it.
+    fn dependents_of<'a>(&'a self, service: &'a str) -> Vec<(&'a str, &'a ServiceDependency)> {
+        let mut dependents: Vec<_> = self
+            .dependencies
+            .iter()
+            .filter_map(|(dependent, deps)| {
+                deps.iter()
+                    .find(|d| d.service == service)
+                    .map(|d| (dependent.as_str(), d))
+            })
+            .collect();
+        dependents.sort_by(|a, b| a.0.cmp(b.0));
+        dependents
+    }
+
+    /// One-shot services awaited with `service_completed_successfully` that
+    /// exited with code 0 did exactly what was asked of them.
+    fn completed_successfully(&self, service: &UnhealthyService) -> bool {
+        service.state == "exited"
+            && service.exit_code == 0
+            && self
+                .dependents_of(&service.name)
+                .iter()
+                .any(|(_, dep)| dep.condition == DependencyCondition::CompletedSuccessfully)
+    }
+
+    /// Whether a not-yet-running service is simply waiting for its dependencies.
+    fn is_waiting_on_dependencies(&self, service: &UnhealthyService, pending: &[&str]) -> bool {
+        service.state == "created"
+            && self
+                .dependencies
+                .get(&service.name)
+                .is_some_and(|deps| deps.iter().any(|d| pending.contains(&d.service.as_str())))
+    }
+
+    /// Re-categorize a raw health check result using dependency information:
+    /// successfully completed one-shot dependencies are dropped and services
+    /// still waiting on a dependency count as starting rather than failed.
+    pub fn reconcile(&self, result: HealthCheckResult) -> HealthCheckResult {
+        if self.is_empty() {
+            return result;
+        }
+        let services = match result {
+            HealthCheckResult::Starting(services) | HealthCheckResult::Failed(services) => services,
+            other => return other,
+        };
+
+        let remaining: Vec<UnhealthyService> = services
+            .into_iter()
+            .filter(|s| !self.skipped.contains(&s.name) && !self.completed_successfully(s))
+            .collect();
+        let pending: Vec<&str> = remaining.iter().map(|s| s.name.as_str()).collect();
+
+        let mut starting = Vec::new();
+        let mut failed = Vec::new();
+        for service in &remaining {
+            if service.is_starting() || self.is_waiting_on_dependencies(service, &pending) {
+                starting.push(service.clone());
+            } else {
+                failed.push(service.clone());
+            }
+        }
+
+        if !failed.is_empty() {
+            failed.extend(starting);
+            HealthCheckResult::Failed(failed)
+        } else if !starting.is_empty() {
+            HealthCheckResult::Starting(starting)
+        } else {
+            HealthCheckResult::Healthy
+        }
+    }
+
+    /// Human readable "api waiting on db" descriptions for pending services
+    /// whose dependencies are pending as well.
+    pub fn waiting_chains(&self, pending: &[UnhealthyService]) -> Vec<String> {
+        let names: Vec<&str> = pending.iter().map(|s| s.name.as_str()).collect();
+        let mut chains = Vec::new();
+        for service in pending {
+            if let Some(deps) = self.dependencies.get(&service.name) {
+                let waiting_on: Vec<&str> = deps
+                    .iter()
+                    .map(|d| d.service.as_str())
+                    .filter(|d| names.contains(d))
+                    .collect();
+                if !waiting_on.is_empty() {
+                    chains.push(format!(
+                        "{} waiting on {}",
+                        service.name,
+                        waiting_on.join(", ")
+                    ));
+                }
+            }
+        }
+        chains.sort();
+        chains
+    }
+
+    /// Terminally failed services that other services require, described as
+    /// "db (state: exited, exit code: 1) blocks api". Waiting for more health
+    /// check attempts cannot fix these, so the caller should fail fast.
+    /// Dependencies marked `required: false` do not hold their dependents back.
+    pub fn blocking_failures(&self, services: &[UnhealthyService]) -> Vec<String> {
+        let mut failures = Vec::new();
+        for service in services.iter().filter(|s| s.is_terminal()) {
+            let blocked: Vec<&str> = self
+                .dependents_of(&service.name)
+                .into_iter()
+                .filter(|(_, dep)| dep.required)
+                .map(|(dependent, _)| dependent)
+                .collect();
+            if !blocked.is_empty() {
+                failures.push(format!(
+                    "{} (state: {}, exit code: {}) blocks {}",
+                    service.name,
+                    service.state,
+                    service.exit_code,
+                    blocked.join(", ")
+                ));
+            }
+        }
+        failures.sort();
+        failures
+    }
+}
+
+/// Where a service stands in the health check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceOutcome {
+    Healthy,
+    /// Still starting (or waiting on a dependency) when the check ended
+    Starting,
+    Failed,
+    /// Starting for longer than the health timeout
+    TimedOut,
+}
+
+/// A state of a service seen by the health check, e.g. `running (starting)`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthEvent {
+    /// Milliseconds since the health check started
+    pub at_millis: u64,
+    pub state: String,
+}
+
+/// How one service got through the health check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceTimeline {
+    pub service: String,
+    pub outcome: ServiceOutcome,
+    /// Milliseconds until the service was first seen healthy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub healthy_after_millis: Option<u64>,
+    /// Each state change, the first one included
+    pub events: Vec<HealthEvent>,
+}
+
+impl ServiceTimeline {
+    /// `state @0.0s → state @12.3s`
+    pub fn summary(&self) -> String {
+        self.events
+            .iter()
+            .map(|event| format!("{} @{:.1}s", event.state, event.at_millis as f64 / 1000.0))
+            .collect::<Vec<_>>()
+            .join(" → ")
+    }
+}
+
+struct TrackedService {
+    timeline: ServiceTimeline,
+    /// When the service was first seen starting; its health timeout runs from here
+    starting_since: Option<Instant>,
+}
+
+/// Follows each service through the polls of a health check, so every one
+/// gets its own timeout and a timeline of what it went through.
+pub struct HealthTracker {
+    started: Instant,
+    services: BTreeMap<String, TrackedService>,
+}
+
+impl HealthTracker {
+    pub fn new(started: Instant) -> Self {
+        Self {
+            started,
+            services: BTreeMap::new(),
+        }
+    }
+
+    /// Record one poll at `now`: the state of each service by name and the
+    /// (reconciled) result telling which of them are starting or failed.
+    pub fn observe(
+        &mut self,
+        now: Instant,
+        states: &BTreeMap<String, String>,
+        result: &HealthCheckResult,
+    ) {
+        let at_millis = now.duration_since(self.started).as_millis() as u64;
+        let unhealthy = match result {
+            HealthCheckResult::Starting(services) | HealthCheckResult::Failed(services) => {
+                services.as_slice()
+            }
+            _ => &[],
+        };
+        for (name, state) in states {
+            let tracked = self
+                .services
+                .entry(name.clone())
+                .or_insert_with(|| TrackedService {
+                    timeline: ServiceTimeline {
+                        service: name.clone(),
+                        outcome: ServiceOutcome::Starting,
+                        healthy_after_millis: None,
+                        events: Vec::new(),
+                    },
+                    starting_since: None,
+                });
+            let timeline = &mut tracked.timeline;
+            if timeline
+                .events
+                .last()
+                .is_none_or(|last| &last.state != state)
+            {
+                timeline.events.push(HealthEvent {
+                    at_millis,
+                    state: state.clone(),
+                });
+            }
+            timeline.outcome = match unhealthy.iter().find(|s| &s.name == name) {
+                None => {
+                    timeline.healthy_after_millis.get_or_insert(at_millis);
+                    ServiceOutcome::Healthy
+                }
+                Some(service) if service.is_terminal() => ServiceOutcome::Failed,
+                Some(service) if service.is_starting() => {
+                    tracked.starting_since.get_or_insert(now);
+                    ServiceOutcome::Starting
+                }
+                // Waiting on its dependencies
+                Some(_) if matches!(result, HealthCheckResult::Starting(_)) => {
+                    ServiceOutcome::Starting
+                }
+                Some(_) => ServiceOutcome::Failed,
+            };
+        }
+    }
+
+    /// Those of `starting` that have been starting for `timeout` or longer
+    /// at `now`, each counted from when its own health check started
+    /// reporting (services still waiting on dependencies count from the
+    /// start of the check).
+    pub fn timed_out(
+        &self,
+        now: Instant,
+        timeout: Duration,
+        starting: &[UnhealthyService],
+    ) -> Vec<String> {
+        starting
+            .iter()
+            .filter(|service| {
+                let since = self
+                    .services
+                    .get(&service.name)
+                    .and_then(|tracked| tracked.starting_since)
+                    .unwrap_or(self.started);
+                now.duration_since(since) >= timeout
+            })
+            .map(|service| service.name.clone())
+            .collect()
+    }
+
+    /// The timelines, sorted by service, with `timed_out` services marked so.
+    pub fn finish(self, timed_out: &[String]) -> Vec<ServiceTimeline> {
+        self.services
+            .into_values()
+            .map(|tracked| {
+                let mut timeline = tracked.timeline;
+                if timed_out.contains(&timeline.service) {
+                    timeline.outcome = ServiceOutcome::TimedOut;
+                }
+                timeline
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(name: &str, state: &str, health: &str, exit_code: i32) -> UnhealthyService {
+        UnhealthyService {
+            name: name.to_string(),
+            state: state.to_string(),
+            health: health.to_string(),
+            exit_code,
+            status: String::new(),
+        }
+    }
+
+    fn graph() -> DependencyGraph {
+        let mut deps = HashMap::new();
+        deps.insert(
+            "api".to_string(),
+            vec![
+                ServiceDependency {
+                    service: "cache".to_string(),
+                    condition: DependencyCondition::Started,
+                    required: false,
+                },
+                ServiceDependency {
+                    service: "db".to_string(),
+                    condition: DependencyCondition::Healthy,
+                    required: true,
+                },
+                ServiceDependency {
+                    service: "migrate".to_string(),
+                    condition: DependencyCondition::CompletedSuccessfully,
+                    required: true,
+                },
+            ],
+        );
+        DependencyGraph::new(deps)
+    }
+
+    #[test]
+    fn test_waiting_chains() {
+        let pending = vec![
+            service("db", "running", "starting", 0),
+            service("api", "created", "", 0),
+        ];
+        assert_eq!(graph().waiting_chains(&pending), vec!["api waiting on db"]);
+    }
+
+    #[test]
+    fn test_reconcile_completed_one_shot_and_waiting_dependent() {
+        let result = HealthCheckResult::Failed(vec![
+            service("migrate", "exited", "", 0),
+            service("db", "running", "starting", 0),
+            service("api", "created", "", 0),
+        ]);
+        match graph().reconcile(result) {
+            HealthCheckResult::Starting(services) => {
+                let names: Vec<_> = services.iter().map(|s| s.name.as_str()).collect();
+                assert_eq!(names, vec!["db", "api"]);
+            }
+            other => panic!("Expected Starting, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_reconcile_skipped_service() {
+        let result = HealthCheckResult::Failed(vec![service("worker", "exited", "", 1)]);
+        let graph = DependencyGraph::default().skipping(["worker".to_string()]);
+        assert_eq!(graph.reconcile(result), HealthCheckResult::Healthy);
+    }
+
+    #[test]
+    fn test_health_tracker() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let states = |db: &str, api: &str| {
+            BTreeMap::from([
+                ("api".to_string(), api.to_string()),
+                ("db".to_string(), db.to_string()),
+            ])
+        };
+        let mut tracker = HealthTracker::new(start);
+
+        // api only starts once db is healthy, so its timeout starts later
+        tracker.observe(
+            at(0),
+            &states("running (starting)", "created"),
+            &HealthCheckResult::Starting(vec![
+                service("db", "running", "starting", 0),
+                service("api", "created", "", 0),
+            ]),
+        );
+        tracker.observe(
+            at(20),
+            &states("running (healthy)", "running (starting)"),
+            &HealthCheckResult::Starting(vec![service("api", "running", "starting", 0)]),
+        );
+        let starting = [service("api", "running", "starting", 0)];
+        assert!(tracker
+            .timed_out(at(45), Duration::from_secs(30), &starting)
+            .is_empty());
+        assert_eq!(
+            tracker.timed_out(at(50), Duration::from_secs(30), &starting),
+            ["api"]
+        );
+
+        let timelines = tracker.finish(&["api".to_string()]);
+        assert_eq!(timelines[0].service, "api");
+        assert_eq!(timelines[0].outcome, ServiceOutcome::TimedOut);
+        assert_eq!(
+            timelines[0].summary(),
+            "created @0.0s → running (starting) @20.0s"
+        );
+        assert_eq!(timelines[1].outcome, ServiceOutcome::Healthy);
+        assert_eq!(timelines[1].healthy_after_millis, Some(20_000));
+        assert_eq!(timelines[1].events.len(), 2);
+    }
+
+    #[test]
+    fn test_blocking_failures() {
+        let services = vec![
+            service("db", "exited", "", 1),
+            service("api", "created", "", 0),
+        ];
+        assert_eq!(
+            graph().blocking_failures(&services),
+            vec!["db (state: exited, exit code: 1) blocks api"]
+        );
+        // A failing service nobody depends on does not short-circuit retries
+        assert!(graph()
+            .blocking_failures(&[service("worker", "exited", "", 1)])
+            .is_empty());
+        // Nor does an optional dependency
+        assert!(graph()
+            .blocking_failures(&[service("cache", "exited", "", 1)])
+            .is_empty());
+    }
+}
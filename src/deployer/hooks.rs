@@ -0,0 +1,226 @@
+//! User commands run at stages of a deployment (`[hooks]` in dcd.toml).
+
+use super::docker_manager::LineBuffer;
+use super::types::{ComposeExec, DeployError, DeployResult, DeployerEvent};
+use crate::config::{Hook, HookStage, Hooks};
+use crate::executor::{shell_quote, CommandExecutor, LocalCommandExecutor, OutputChunk};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+
+/// Where hooks run and what they get to see.
+#[derive(Debug, Clone, Default)]
+pub struct HookContext {
+    /// Working directory of local hooks
+    pub project_dir: PathBuf,
+    /// Working directory of remote hooks
+    pub remote_dir: PathBuf,
+    /// `docker-compose` with the deployment's compose and env files, exported
+    /// to remote hooks as `DCD_COMPOSE`
+    pub compose: String,
+    /// Variables compose commands of the deployment run with (profiles, an
+    /// encrypted env), passed on to remote hooks
+    pub compose_env: HashMap<String, String>,
+}
+
+impl HookContext {
+    fn env(&self, stage: HookStage, local: bool) -> HashMap<String, String> {
+        let mut env = if local {
+            HashMap::new()
+        } else {
+            self.compose_env.clone()
+        };
+        env.insert("DCD_HOOK".to_string(), stage.to_string());
+        env.insert(
+            "DCD_REMOTE_DIR".to_string(),
+            self.remote_dir.display().to_string(),
+        );
+        if !local {
+            env.insert("DCD_COMPOSE".to_string(), self.compose.clone());
+        }
+        env
+    }
+}
+
+/// One line describing `hook`, as shown in progress and dry runs.
+pub fn describe_hook(stage: HookStage, hook: &Hook) -> String {
+    format!(
+        "{} hook{}: {}",
+        stage,
+        if hook.local { " (local)" } else { "" },
+        hook.run
+    )
+}
+
+/// Run the hooks of `stage` in order. A required hook that fails stops the
+/// deployment; any other failure is only logged.
+pub async fn run_hooks(
+    executor: &mut (dyn ComposeExec + Send),
+    hooks: &Hooks,
+    stage: HookStage,
+    context: &HookContext,
+    progress_sender: &Option<mpsc::Sender<DeployerEvent>>,
+) -> DeployResult<()> {
+    for hook in hooks.get(stage) {
+        let description = describe_hook(stage, hook);
+        tracing::info!("Running {}", description);
+        send(
+            progress_sender,
+            DeployerEvent::StepStarted(description.clone()),
+        )
+        .await;
+
+        let result = if hook.local {
+            let mut local = LocalCommandExecutor::shell();
+            let command = in_dir(&context.project_dir, &hook.run);
+            let env = context.env(stage, true);
+            run_hook(&mut local, &command, &env, progress_sender).await
+        } else {
+            let command = in_dir(&context.remote_dir, &hook.run);
+            let env = context.env(stage, false);
+            run_hook(executor, &command, &env, progress_sender).await
+        };
+
+        match result {
+            Ok(()) => {
+                send(progress_sender, DeployerEvent::StepCompleted(description)).await;
+            }
+            Err(message) if hook.required => {
+                send(
+                    progress_sender,
+                    DeployerEvent::StepFailed(description, message.clone()),
+                )
+                .await;
+                return Err(DeployError::Hook {
+                    stage,
+                    command: hook.run.clone(),
+                    message,
+                });
+            }
+            Err(message) => {
+                tracing::warn!("Optional {} failed: {}", description, message);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn in_dir(dir: &Path, command: &str) -> String {
+    format!("cd {} && {}", shell_quote(&dir.to_string_lossy()), command)
+}
+
+/// Run one hook command, logging its output and passing it on as progress.
+async fn run_hook<E: CommandExecutor + Send + ?Sized>(
+    executor: &mut E,
+    command: &str,
+    env: &HashMap<String, String>,
+    progress_sender: &Option<mpsc::Sender<DeployerEvent>>,
+) -> Result<(), String> {
+    let (output_tx, mut output) = mpsc::channel::<OutputChunk>(64);
+    let run = executor.execute_command_streaming(command, env, output_tx);
+    let follow = async {
+        let mut lines = LineBuffer::default();
+        let mut last = None;
+        while let Some(chunk) = output.recv().await {
+            for line in lines.push(chunk) {
+                tracing::info!("hook: {}", line);
+                send(progress_sender, DeployerEvent::ComposeOutput(line.clone())).await;
+                last = Some(line);
+            }
+        }
+        for line in lines.finish() {
+            tracing::info!("hook: {}", line);
+            last = Some(line);
+        }
+        last
+    };
+    let (result, last_line) = tokio::join!(run, follow);
+    let result = result.map_err(|e| e.to_string())?;
+    if result.is_success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&result.output.stderr)
+        .trim()
+        .to_string();
+    let detail = if stderr.is_empty() {
+        last_line.unwrap_or_default()
+    } else {
+        stderr
+    };
+    Err(if detail.is_empty() {
+        format!("exit code {}", result.output.exit_code)
+    } else {
+        format!("exit code {}: {}", result.output.exit_code, detail)
+    })
+}
+
+async fn send(sender: &Option<mpsc::Sender<DeployerEvent>>, event: DeployerEvent) {
+    if let Some(sender) = sender {
+        let _ = sender.send(event).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{CommandMatch, MockExecutor};
+
+    fn context() -> HookContext {
+        HookContext {
+            project_dir: PathBuf::from("."),
+            remote_dir: PathBuf::from("/opt/app"),
+            compose: "docker-compose -f docker-compose.yml".into(),
+            compose_env: HashMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_hooks() {
+        let mut hooks = Hooks::default();
+        hooks.push(HookStage::PostUp, Hook::remote("./migrate"));
+        hooks.push(
+            HookStage::PostUp,
+            Hook {
+                required: false,
+                ..Hook::remote("./notify")
+            },
+        );
+        let mut executor = MockExecutor::new();
+        executor.respond_ok(CommandMatch::Contains("./migrate".into()), "migrated\n");
+        executor.respond_err(CommandMatch::Contains("./notify".into()), 1, "no network");
+
+        // The optional hook failing does not fail the stage
+        run_hooks(&mut executor, &hooks, HookStage::PostUp, &context(), &None)
+            .await
+            .unwrap();
+        let ran = executor.commands();
+        assert_eq!(ran.len(), 2);
+        assert!(ran[0].contains("cd /opt/app && ./migrate"));
+        assert!(ran[0].contains("DCD_HOOK='post-up'"));
+        assert!(ran[0].contains("DCD_COMPOSE='docker-compose -f docker-compose.yml'"));
+
+        // Nothing runs for stages without hooks
+        run_hooks(&mut executor, &hooks, HookStage::PreUp, &context(), &None)
+            .await
+            .unwrap();
+        assert_eq!(executor.commands().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_required_hook_failure() {
+        let mut hooks = Hooks::default();
+        hooks.push(HookStage::PreUp, Hook::remote("./check"));
+        hooks.push(HookStage::PreUp, Hook::remote("./never"));
+        let mut executor = MockExecutor::new();
+        executor.respond_err(CommandMatch::Contains("./check".into()), 3, "disk full");
+
+        let error = run_hooks(&mut executor, &hooks, HookStage::PreUp, &context(), &None)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "pre-up hook './check' failed: exit code 3: disk full"
+        );
+        assert_eq!(executor.commands().len(), 1);
+    }
+}
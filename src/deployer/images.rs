@@ -0,0 +1,385 @@
+use crate::deployer::docker_manager::ComposeImage;
+use crate::deployer::types::{ComposeExec, DeployError, DeployResult};
+use crate::executor::shell_quote;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+/// Image a service container was created from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceImage {
+    /// Image reference from the compose file, e.g. `nginx:1.27`
+    pub image: String,
+    /// Content-addressed image id, e.g. `sha256:3f5e...`
+    pub id: String,
+}
+
+impl fmt::Display for ServiceImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digest = self.id.trim_start_matches("sha256:");
+        let tail = &digest[digest.len().saturating_sub(4)..];
+        write!(f, "{} (sha…{})", self.image, tail)
+    }
+}
+
+/// A service whose image differs between two deployments.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageChange {
+    pub service: String,
+    /// `None` when the service is new
+    pub before: Option<ServiceImage>,
+    /// `None` when the service was removed
+    pub after: Option<ServiceImage>,
+}
+
+impl fmt::Display for ImageChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.service)?;
+        match &self.before {
+            Some(image) => write!(f, "{}", image)?,
+            None => f.write_str("(new)")?,
+        }
+        f.write_str(" → ")?;
+        match &self.after {
+            Some(image) => write!(f, "{}", image),
+            None => f.write_str("(removed)"),
+        }
+    }
+}
+
+/// Images of the containers of compose project `project`, keyed by service.
+pub async fn service_images(
+    executor: &mut (dyn ComposeExec + Send),
+    project: &str,
+) -> DeployResult<BTreeMap<String, ServiceImage>> {
+    let cmd = format!(
+        "docker ps -aq --filter label=com.docker.compose.project={} | xargs -r docker inspect --format \
+         '{{{{index .Config.Labels \"com.docker.compose.service\"}}}}\t{{{{.Config.Image}}}}\t{{{{.Image}}}}'",
+        project
+    );
+    let result = executor
+        .execute_command(&cmd)
+        .await
+        .map_err(|e| DeployError::Deployment(format!("Failed to inspect containers: {}", e)))?;
+    if !result.is_success() {
+        return Err(DeployError::Deployment(format!(
+            "Failed to inspect containers: {}",
+            result.output.to_stderr_string()?.trim()
+        )));
+    }
+    Ok(parse_service_images(&result.output.to_stdout_string()?))
+}
+
+fn parse_service_images(output: &str) -> BTreeMap<String, ServiceImage> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let service = fields.next()?.trim();
+            let image = fields.next()?.trim();
+            let id = fields.next()?.trim();
+            if service.is_empty() || id.is_empty() {
+                return None;
+            }
+            Some((
+                service.to_string(),
+                ServiceImage {
+                    image: image.to_string(),
+                    id: id.to_string(),
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Point image references back at the ids recorded for an earlier
+/// deployment, so `up --no-build` recreates the containers from them.
+/// Returns the services whose image is no longer on the host.
+pub async fn retag_images(
+    executor: &mut (dyn ComposeExec + Send),
+    images: &BTreeMap<String, ServiceImage>,
+) -> DeployResult<Vec<String>> {
+    let mut missing = Vec::new();
+    for (service, image) in images {
+        // References by digest or id already name exactly one image
+        if image.image.is_empty() || image.image.contains('@') || image.image == image.id {
+            continue;
+        }
+        let cmd = format!(
+            "docker tag {} {}",
+            shell_quote(&image.id),
+            shell_quote(&image.image)
+        );
+        let result = executor
+            .execute_command(&cmd)
+            .await
+            .map_err(|e| DeployError::Deployment(format!("Failed to tag image: {}", e)))?;
+        if !result.is_success() {
+            tracing::debug!(
+                "Could not tag {} as {}: {}",
+                image.id,
+                image.image,
+                result.output.to_stderr_string()?.trim()
+            );
+            missing.push(service.clone());
+        }
+    }
+    Ok(missing)
+}
+
+/// Services whose image id changed, was added or was removed.
+pub fn diff_images(
+    before: &BTreeMap<String, ServiceImage>,
+    after: &BTreeMap<String, ServiceImage>,
+) -> Vec<ImageChange> {
+    let mut services: Vec<&String> = before.keys().chain(after.keys()).collect();
+    services.sort();
+    services.dedup();
+    services
+        .into_iter()
+        .filter_map(|service| {
+            let old = before.get(service);
+            let new = after.get(service);
+            if old.map(|image| &image.id) == new.map(|image| &image.id) {
+                return None;
+            }
+            Some(ImageChange {
+                service: service.clone(),
+                before: old.cloned(),
+                after: new.cloned(),
+            })
+        })
+        .collect()
+}
+
+/// An image of the project on the host, as listed by `dcd images`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProjectImage {
+    /// `<none>` for untagged images
+    pub repository: String,
+    pub tag: String,
+    pub id: String,
+    /// Registry digest, `<none>` for images built or loaded on the host
+    pub digest: String,
+    pub size: String,
+    /// Age as docker reports it, e.g. `3 days ago`
+    pub created: String,
+    /// Whether a container of the project runs this image
+    pub in_use: bool,
+    /// Containers running this image
+    pub containers: Vec<String>,
+    /// Untagged and unused, so the image prune before the next pull removes it
+    pub dangling: bool,
+}
+
+/// Entry of `docker image ls --format '{{json .}}'`.
+#[derive(Debug, Deserialize)]
+struct ListedImage {
+    #[serde(rename = "ID")]
+    id: String,
+    #[serde(rename = "Repository")]
+    repository: String,
+    #[serde(rename = "Tag")]
+    tag: String,
+    #[serde(rename = "Digest", default)]
+    digest: String,
+    #[serde(rename = "Size", default)]
+    size: String,
+    #[serde(rename = "CreatedSince", default)]
+    created_since: String,
+}
+
+/// Images of compose project `project`: those its containers run (`used`,
+/// from `docker compose images`) and those labelled with the project, which
+/// includes the leftovers of earlier builds.
+pub async fn project_images(
+    executor: &mut (dyn ComposeExec + Send),
+    project: &str,
+    used: &[ComposeImage],
+) -> DeployResult<Vec<ProjectImage>> {
+    let labelled = list_images(
+        executor,
+        &format!(
+            "docker image ls -q --no-trunc --filter label=com.docker.compose.project={}",
+            project
+        ),
+    )
+    .await?;
+    let labelled: HashSet<&str> = labelled
+        .lines()
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .collect();
+    let listed = list_images(
+        executor,
+        "docker image ls --no-trunc --digests --format '{{json .}}'",
+    )
+    .await?;
+    let listed = parse_image_list(&listed)?;
+    Ok(select_project_images(listed, &labelled, used))
+}
+
+async fn list_images(executor: &mut (dyn ComposeExec + Send), cmd: &str) -> DeployResult<String> {
+    let result = executor
+        .execute_command(cmd)
+        .await
+        .map_err(|e| DeployError::Deployment(format!("Failed to list images: {}", e)))?;
+    if !result.is_success() {
+        return Err(DeployError::Deployment(format!(
+            "Failed to list images: {}",
+            result.output.to_stderr_string()?.trim()
+        )));
+    }
+    Ok(result.output.to_stdout_string()?)
+}
+
+fn parse_image_list(output: &str) -> DeployResult<Vec<ListedImage>> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            serde_json::from_str(line)
+                .map_err(|e| DeployError::Deployment(format!("Failed to parse image list: {}", e)))
+        })
+        .collect()
+}
+
+/// Keep the listed images that are labelled with the project or run by one
+/// of its containers; images in use come first.
+fn select_project_images(
+    listed: Vec<ListedImage>,
+    labelled: &HashSet<&str>,
+    used: &[ComposeImage],
+) -> Vec<ProjectImage> {
+    let mut images: Vec<ProjectImage> = listed
+        .into_iter()
+        .filter_map(|image| {
+            let containers: Vec<String> = used
+                .iter()
+                .filter(|container| same_image(&container.id, &image.id))
+                .map(|container| container.container_name.clone())
+                .collect();
+            if containers.is_empty() && !labelled.contains(image.id.as_str()) {
+                return None;
+            }
+            let untagged = image.repository == "<none>" && image.tag == "<none>";
+            Some(ProjectImage {
+                in_use: !containers.is_empty(),
+                dangling: untagged && containers.is_empty(),
+                repository: image.repository,
+                tag: image.tag,
+                id: image.id,
+                digest: image.digest,
+                size: image.size,
+                created: image.created_since,
+                containers,
+            })
+        })
+        .collect();
+    images.sort_by(|a, b| {
+        b.in_use
+            .cmp(&a.in_use)
+            .then_with(|| a.repository.cmp(&b.repository))
+            .then_with(|| a.tag.cmp(&b.tag))
+    });
+    images
+}
+
+/// Whether two image ids name the same image; either may be truncated.
+fn same_image(a: &str, b: &str) -> bool {
+    let a = a.trim_start_matches("sha256:");
+    let b = b.trim_start_matches("sha256:");
+    !a.is_empty() && !b.is_empty() && (a.starts_with(b) || b.starts_with(a))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(image: &str, id: &str) -> ServiceImage {
+        ServiceImage {
+            image: image.to_string(),
+            id: id.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_service_images() {
+        let images = parse_service_images(
+            "web\tnginx:1.27\tsha256:0f1ec3d4\n\
+             db\tpostgres:16\tsha256:99aa77e1\n\
+             \tbusybox\tsha256:1234\n",
+        );
+        assert_eq!(images.len(), 2);
+        assert_eq!(images["web"], image("nginx:1.27", "sha256:0f1ec3d4"));
+    }
+
+    #[test]
+    fn test_diff_images() {
+        let before = BTreeMap::from([
+            ("web".to_string(), image("nginx:1.25", "sha256:77a1b2")),
+            ("db".to_string(), image("postgres:16", "sha256:99aa")),
+            ("old".to_string(), image("redis:7", "sha256:5555")),
+        ]);
+        let after = BTreeMap::from([
+            ("web".to_string(), image("nginx:1.27", "sha256:88c3d4")),
+            ("db".to_string(), image("postgres:16", "sha256:99aa")),
+            ("worker".to_string(), image("app:2", "sha256:abcd")),
+        ]);
+        let changes: Vec<String> = diff_images(&before, &after)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                "old: redis:7 (sha…5555) → (removed)",
+                "web: nginx:1.25 (sha…a1b2) → nginx:1.27 (sha…c3d4)",
+                "worker: (new) → app:2 (sha…abcd)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_select_project_images() {
+        let listed = parse_image_list(
+            r#"{"ID":"sha256:aaaa1111","Repository":"nginx","Tag":"1.27","Digest":"sha256:d1","Size":"187MB","CreatedSince":"2 weeks ago"}
+{"ID":"sha256:bbbb2222","Repository":"myapp-web","Tag":"latest","Digest":"<none>","Size":"95MB","CreatedSince":"1 hour ago"}
+{"ID":"sha256:cccc3333","Repository":"<none>","Tag":"<none>","Digest":"<none>","Size":"94MB","CreatedSince":"2 days ago"}
+{"ID":"sha256:dddd4444","Repository":"redis","Tag":"7","Digest":"sha256:d2","Size":"40MB","CreatedSince":"1 month ago"}"#,
+        )
+        .unwrap();
+        let labelled = HashSet::from(["sha256:bbbb2222", "sha256:cccc3333"]);
+        let used = vec![
+            ComposeImage {
+                container_name: "myapp-proxy-1".into(),
+                repository: "nginx".into(),
+                tag: "1.27".into(),
+                id: "aaaa1111".into(),
+            },
+            ComposeImage {
+                container_name: "myapp-web-1".into(),
+                repository: "myapp-web".into(),
+                tag: "latest".into(),
+                id: "sha256:bbbb2222".into(),
+            },
+        ];
+        let images = select_project_images(listed, &labelled, &used);
+        let summary: Vec<(&str, bool, bool)> = images
+            .iter()
+            .map(|image| (image.repository.as_str(), image.in_use, image.dangling))
+            .collect();
+        // redis is neither used nor labelled by the project
+        assert_eq!(
+            summary,
+            vec![
+                ("myapp-web", true, false),
+                ("nginx", true, false),
+                ("<none>", false, true),
+            ]
+        );
+        assert_eq!(images[1].containers, vec!["myapp-proxy-1"]);
+        assert_eq!(images[2].created, "2 days ago");
+    }
+}
@@ -1,13 +1,225 @@
+pub mod cron;
 pub mod docker_manager;
+pub mod doctor;
+pub mod dry_run;
 pub mod firewall;
+pub mod gc;
+pub mod health;
+pub mod hooks;
+pub mod images;
+pub mod networks;
+pub mod packages;
+pub mod plan;
+pub mod preflight;
+pub mod pull;
+pub mod reboot;
+pub mod registry;
+pub mod report;
+pub mod restart;
+pub mod secrets;
 pub mod service;
+pub mod stacks;
+pub mod state;
+pub mod support;
 pub mod sync;
+pub mod systemd;
+pub mod tuning;
 pub mod types;
+pub mod wait;
+use crate::executor::shell_quote;
 pub use service::Deployer;
+use std::path::{Component, Path, PathBuf};
 use types::{DeployError, DeployResult, DeploymentConfig};
 
 pub const DCD_ENV_FILE: &str = ".env.dcd";
 pub const BACKUP_SUFFIX: &str = ".backup";
+pub const PREVIOUS_SUFFIX: &str = ".previous";
+
+/// Remote directory of a project deployed without --workdir: `/opt/<name of
+/// the local project directory>`.
+pub fn default_remote_dir(project_dir: &Path) -> PathBuf {
+    let project_name = project_dir
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| {
+            tracing::warn!(
+                "Could not determine project directory name from '{}', using 'default_project'",
+                project_dir.display()
+            );
+            "default_project".to_string() // Fallback name
+        });
+    PathBuf::from(format!("/opt/{}", project_name))
+}
+
+/// Name derived from the remote project directory that is safe to use in
+/// unit and cron file names (only `[A-Za-z0-9_-]`).
+pub fn project_slug(working_directory: &Path) -> String {
+    working_directory
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "default_project".to_string())
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// Compose project name docker compose derives from the working directory:
+/// lowercased, keeping only `[a-z0-9_-]`.
+pub fn compose_project_name(working_directory: &Path) -> String {
+    let name: String = working_directory
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-')
+        .collect();
+    name.trim_start_matches(['_', '-']).to_string()
+}
+
+/// Build a `docker-compose` command line for the project in
+/// `working_directory` with the given files. The directory and project name
+/// are passed explicitly and relative files are resolved against the
+/// directory, so the command works from any current directory.
+pub fn compose_command(
+    working_directory: &Path,
+    compose_files: &[PathBuf],
+    env_files: &[PathBuf],
+    subcommand: &str,
+) -> String {
+    let quoted = |path: &Path| shell_quote(&working_directory.join(path).to_string_lossy());
+    let mut cmd = format!(
+        "docker-compose --project-directory {}",
+        shell_quote(&working_directory.to_string_lossy())
+    );
+    // Empty for directories like `/`; compose then falls back to its own default
+    let project = compose_project_name(working_directory);
+    if !project.is_empty() {
+        cmd.push_str(" -p ");
+        cmd.push_str(&project);
+    }
+    for cf in compose_files {
+        cmd.push_str(" -f ");
+        cmd.push_str(&quoted(cf));
+    }
+    for ef in env_files {
+        cmd.push_str(" --env-file ");
+        cmd.push_str(&quoted(ef));
+    }
+    cmd.push(' ');
+    cmd.push_str(subcommand);
+    cmd
+}
+
+/// Human readable size with binary units, e.g. `1.5 GiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Entries and their sizes in bytes from `du -sk` output.
+pub fn parse_du_sizes(output: &str) -> Vec<(PathBuf, u64)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (kib, path) = line.split_once('\t')?;
+            Some((PathBuf::from(path), kib.trim().parse::<u64>().ok()? * 1024))
+        })
+        .collect()
+}
+
+/// System directories `destroy` never removes, whatever `--workdir` says.
+const PROTECTED_DIRS: [&str; 27] = [
+    "/",
+    "/bin",
+    "/boot",
+    "/dev",
+    "/etc",
+    "/home",
+    "/lib",
+    "/lib32",
+    "/lib64",
+    "/media",
+    "/mnt",
+    "/opt",
+    "/proc",
+    "/root",
+    "/run",
+    "/sbin",
+    "/snap",
+    "/srv",
+    "/sys",
+    "/tmp",
+    "/usr",
+    "/usr/local",
+    "/var",
+    "/var/lib",
+    "/var/lib/docker",
+    "/var/log",
+    "/var/tmp",
+];
+
+/// Trees holding the system itself, removed from under no deployment.
+const PROTECTED_TREES: [&str; 6] = ["/bin", "/boot", "/dev", "/etc", "/proc", "/sys"];
+
+/// Refuse to remove `path` recursively unless it is a plain absolute path
+/// below the system directories: no `..`, no characters a shell would
+/// interpret, not a protected directory or a home directory itself.
+pub fn check_removable_dir(path: &Path) -> DeployResult<()> {
+    let refuse = |reason: &str| {
+        Err(DeployError::Configuration(format!(
+            "Refusing to remove '{}': {}",
+            path.display(),
+            reason
+        )))
+    };
+    let text = path.to_string_lossy();
+    if !path.is_absolute() {
+        return refuse("not an absolute path");
+    }
+    let plain = text.chars().all(|c| {
+        c.is_ascii_alphanumeric()
+            || matches!(c, '/' | '.' | '_' | '-' | '+' | '@' | ',' | ':' | '=')
+    });
+    if !plain {
+        return refuse("it contains shell metacharacters or whitespace");
+    }
+    if path
+        .components()
+        .any(|component| !matches!(component, Component::RootDir | Component::Normal(_)))
+    {
+        return refuse("it is not a normalized path");
+    }
+    let normalized: PathBuf = path.components().collect();
+    if PROTECTED_DIRS
+        .iter()
+        .any(|dir| normalized == Path::new(dir))
+        || PROTECTED_TREES
+            .iter()
+            .any(|tree| normalized.starts_with(tree))
+    {
+        return refuse("it is a system directory");
+    }
+    if normalized.parent() == Some(Path::new("/home")) {
+        return refuse("it is a home directory");
+    }
+    Ok(())
+}
 
 /// Deployment configuration validation
 pub fn validate_config(config: &DeploymentConfig) -> DeployResult<()> {
@@ -38,6 +250,15 @@ pub fn validate_config(config: &DeploymentConfig) -> DeployResult<()> {
         }
     }
 
+    if let Some(template) = &config.env_template {
+        if !template.exists() {
+            return Err(DeployError::Configuration(format!(
+                "Env template not found: {}",
+                template.display()
+            )));
+        }
+    }
+
     // Validate local references
     for path in &config.local_references {
         if !path.exists() {
@@ -74,6 +295,8 @@ mod tests {
             exposed_ports: Vec::new(),
             local_references: vec![temp_dir.path().join("config")],
             volumes: Vec::new(),
+            service_dependencies: HashMap::new(),
+            ..Default::default()
         }
     }
 
@@ -96,8 +319,90 @@ mod tests {
             exposed_ports: Vec::new(),
             local_references: Vec::new(),
             volumes: Vec::new(),
+            service_dependencies: HashMap::new(),
+            ..Default::default()
         };
 
         assert!(validate_config(&config).is_err());
     }
+
+    #[test]
+    fn test_compose_project_name() {
+        assert_eq!(compose_project_name(Path::new("/opt/My.App")), "myapp");
+        assert_eq!(
+            compose_project_name(Path::new("/srv/_shop-api")),
+            "shop-api"
+        );
+    }
+
+    #[test]
+    fn test_compose_command() {
+        assert_eq!(
+            compose_command(
+                Path::new("/srv/My App"),
+                &[
+                    PathBuf::from("docker-compose.yml"),
+                    PathBuf::from("/etc/shared/base.yml")
+                ],
+                &[PathBuf::from(".env.dcd")],
+                "ps --format json"
+            ),
+            "docker-compose --project-directory '/srv/My App' -p myapp \
+             -f '/srv/My App/docker-compose.yml' -f /etc/shared/base.yml \
+             --env-file '/srv/My App/.env.dcd' ps --format json"
+        );
+        // No project name to pass for `/`
+        assert_eq!(
+            compose_command(Path::new("/"), &[], &[], "config"),
+            "docker-compose --project-directory / config"
+        );
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+
+    #[test]
+    fn test_check_removable_dir() {
+        for path in [
+            "/opt/app",
+            "/srv/www/site-1",
+            "/home/deploy/apps/shop",
+            "/opt/app/",
+        ] {
+            assert!(check_removable_dir(Path::new(path)).is_ok(), "{}", path);
+        }
+        for path in [
+            "/",
+            "/etc",
+            "/var",
+            "/var/lib/docker",
+            "/home",
+            "/home/deploy",
+            "/etc/nginx",
+            "/opt/app/..",
+            "/opt//../etc",
+            "app",
+            "/opt/$(reboot)",
+            "/opt/app; rm -rf /",
+            "/opt/my app",
+            "/opt/*",
+        ] {
+            assert!(check_removable_dir(Path::new(path)).is_err(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_parse_du_sizes() {
+        assert_eq!(
+            parse_du_sizes("4\t/opt/app/.env.dcd\n1048576\t/opt/app/data dir\nbogus\n"),
+            vec![
+                (PathBuf::from("/opt/app/.env.dcd"), 4096),
+                (PathBuf::from("/opt/app/data dir"), 1024 * 1024 * 1024),
+            ]
+        );
+    }
 }
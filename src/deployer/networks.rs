@@ -0,0 +1,323 @@
+use crate::deployer::types::{ComposeExec, DeployError, DeployResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+/// A network the project created or joins, with what overlaps its subnets.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProjectNetwork {
+    pub name: String,
+    pub id: String,
+    pub driver: String,
+    /// Created by compose for the project; `false` for external networks
+    pub managed: bool,
+    pub subnets: Vec<NetworkSubnet>,
+    pub containers: Vec<AttachedContainer>,
+    pub conflicts: Vec<SubnetConflict>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NetworkSubnet {
+    pub subnet: String,
+    pub gateway: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AttachedContainer {
+    pub name: String,
+    /// Address with prefix, e.g. `172.18.0.2/16`
+    pub ipv4_address: String,
+}
+
+/// Overlap between a subnet of a project network and another network or a
+/// host route. Traffic to the overlapping range goes to only one of them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SubnetConflict {
+    pub subnet: String,
+    /// What it overlaps, e.g. `network proxy` or `route on eth0`
+    pub with: String,
+    pub other_subnet: String,
+}
+
+/// Entry of `docker network inspect`.
+#[derive(Debug, Deserialize)]
+struct InspectedNetwork {
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "Id")]
+    id: String,
+    #[serde(rename = "Driver", default)]
+    driver: String,
+    #[serde(rename = "IPAM", default)]
+    ipam: Ipam,
+    #[serde(rename = "Containers", default)]
+    containers: Option<BTreeMap<String, Endpoint>>,
+    #[serde(rename = "Labels", default)]
+    labels: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Ipam {
+    #[serde(rename = "Config", default)]
+    config: Option<Vec<IpamConfig>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IpamConfig {
+    #[serde(rename = "Subnet", default)]
+    subnet: String,
+    #[serde(rename = "Gateway", default)]
+    gateway: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Endpoint {
+    #[serde(rename = "Name", default)]
+    name: String,
+    #[serde(rename = "IPv4Address", default)]
+    ipv4_address: String,
+}
+
+/// A route of the host outside docker, from `ip -4 route`.
+#[derive(Debug, Clone, PartialEq)]
+struct HostRoute {
+    subnet: String,
+    device: String,
+}
+
+/// Networks of compose project `project`: those compose created for it and
+/// the external ones its containers are attached to. Subnets are checked
+/// against all other docker networks and the host's own routes.
+pub async fn project_networks(
+    executor: &mut (dyn ComposeExec + Send),
+    project: &str,
+) -> DeployResult<Vec<ProjectNetwork>> {
+    let inspected = run(
+        executor,
+        "docker network ls -q | xargs -r docker network inspect",
+    )
+    .await?;
+    let inspected: Vec<InspectedNetwork> = serde_json::from_str(&inspected)
+        .map_err(|e| DeployError::Deployment(format!("Failed to parse networks: {}", e)))?;
+    let containers = run(
+        executor,
+        &format!(
+            "docker ps -aq --no-trunc --filter label=com.docker.compose.project={}",
+            project
+        ),
+    )
+    .await?;
+    let containers: HashSet<&str> = containers.lines().map(str::trim).collect();
+    // Only used to find conflicts, so a host without iproute2 just gets fewer
+    let routes = match run(executor, "ip -4 route show").await {
+        Ok(output) => parse_routes(&output),
+        Err(e) => {
+            tracing::debug!("Could not read host routes: {}", e);
+            Vec::new()
+        }
+    };
+    Ok(select_networks(inspected, project, &containers, &routes))
+}
+
+async fn run(executor: &mut (dyn ComposeExec + Send), cmd: &str) -> DeployResult<String> {
+    let result = executor
+        .execute_command(cmd)
+        .await
+        .map_err(|e| DeployError::Deployment(format!("Failed to run '{}': {}", cmd, e)))?;
+    if !result.is_success() {
+        return Err(DeployError::Deployment(format!(
+            "Command '{}' failed: {}",
+            cmd,
+            result.output.to_stderr_string()?.trim()
+        )));
+    }
+    Ok(result.output.to_stdout_string()?)
+}
+
+fn select_networks(
+    inspected: Vec<InspectedNetwork>,
+    project: &str,
+    project_containers: &HashSet<&str>,
+    routes: &[HostRoute],
+) -> Vec<ProjectNetwork> {
+    let all_subnets: Vec<(String, String)> = inspected
+        .iter()
+        .flat_map(|network| {
+            network
+                .ipam
+                .config
+                .iter()
+                .flatten()
+                .map(|config| (network.name.clone(), config.subnet.clone()))
+        })
+        .collect();
+
+    let mut networks: Vec<ProjectNetwork> = inspected
+        .into_iter()
+        .filter_map(|network| {
+            let managed = network
+                .labels
+                .as_ref()
+                .and_then(|labels| labels.get("com.docker.compose.project"))
+                .is_some_and(|label| label == project);
+            let endpoints = network.containers.unwrap_or_default();
+            let joined = endpoints
+                .keys()
+                .any(|id| project_containers.contains(id.as_str()));
+            if !managed && !joined {
+                return None;
+            }
+            let subnets: Vec<NetworkSubnet> = network
+                .ipam
+                .config
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|config| !config.subnet.is_empty())
+                .map(|config| NetworkSubnet {
+                    subnet: config.subnet,
+                    gateway: Some(config.gateway).filter(|gateway| !gateway.is_empty()),
+                })
+                .collect();
+            let conflicts = subnets
+                .iter()
+                .flat_map(|own| {
+                    let other_networks = all_subnets
+                        .iter()
+                        .filter(|(name, _)| *name != network.name)
+                        .map(|(name, subnet)| (format!("network {}", name), subnet.clone()));
+                    let host_routes = routes
+                        .iter()
+                        .map(|route| (format!("route on {}", route.device), route.subnet.clone()));
+                    other_networks
+                        .chain(host_routes)
+                        .filter(|(_, other)| subnets_overlap(&own.subnet, other))
+                        .map(|(with, other_subnet)| SubnetConflict {
+                            subnet: own.subnet.clone(),
+                            with,
+                            other_subnet,
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+            let mut containers: Vec<AttachedContainer> = endpoints
+                .into_values()
+                .map(|endpoint| AttachedContainer {
+                    name: endpoint.name,
+                    ipv4_address: endpoint.ipv4_address,
+                })
+                .collect();
+            containers.sort_by(|a, b| a.name.cmp(&b.name));
+            Some(ProjectNetwork {
+                name: network.name,
+                id: network.id,
+                driver: network.driver,
+                managed,
+                subnets,
+                containers,
+                conflicts,
+            })
+        })
+        .collect();
+    networks.sort_by(|a, b| b.managed.cmp(&a.managed).then_with(|| a.name.cmp(&b.name)));
+    networks
+}
+
+/// Routes of `ip -4 route show` to a subnet, leaving out the default route
+/// and docker's own bridges (those are compared as networks).
+fn parse_routes(output: &str) -> Vec<HostRoute> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            let subnet = *tokens.first()?;
+            if !subnet.contains('/') {
+                return None;
+            }
+            let device = tokens
+                .windows(2)
+                .find(|pair| pair[0] == "dev")
+                .map(|pair| pair[1])?;
+            if device == "docker0" || device == "docker_gwbridge" || device.starts_with("br-") {
+                return None;
+            }
+            Some(HostRoute {
+                subnet: subnet.to_string(),
+                device: device.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Whether two IPv4 CIDR ranges share addresses; `false` for anything else.
+fn subnets_overlap(a: &str, b: &str) -> bool {
+    let (Some((a, a_len)), Some((b, b_len))) = (parse_cidr(a), parse_cidr(b)) else {
+        return false;
+    };
+    let len = a_len.min(b_len);
+    let mask = if len == 0 { 0 } else { u32::MAX << (32 - len) };
+    a & mask == b & mask
+}
+
+fn parse_cidr(cidr: &str) -> Option<(u32, u32)> {
+    let (address, len) = cidr.split_once('/')?;
+    let address: std::net::Ipv4Addr = address.parse().ok()?;
+    let len: u32 = len.parse().ok().filter(|len| *len <= 32)?;
+    Some((u32::from(address), len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INSPECT: &str = r#"[
+        {"Name": "bridge", "Id": "b0", "Driver": "bridge",
+         "IPAM": {"Config": [{"Subnet": "172.17.0.0/16", "Gateway": "172.17.0.1"}]},
+         "Containers": {}, "Labels": {}},
+        {"Name": "myapp_default", "Id": "n1", "Driver": "bridge",
+         "IPAM": {"Config": [{"Subnet": "10.10.0.0/16", "Gateway": "10.10.0.1"}]},
+         "Containers": {"c1": {"Name": "myapp-web-1", "IPv4Address": "10.10.0.2/16"}},
+         "Labels": {"com.docker.compose.project": "myapp"}},
+        {"Name": "proxy", "Id": "n2", "Driver": "bridge",
+         "IPAM": {"Config": [{"Subnet": "172.30.0.0/16", "Gateway": ""}]},
+         "Containers": {"c1": {"Name": "myapp-web-1", "IPv4Address": "172.30.0.5/16"},
+                        "c9": {"Name": "traefik", "IPv4Address": "172.30.0.2/16"}},
+         "Labels": {}},
+        {"Name": "other_default", "Id": "n3", "Driver": "bridge",
+         "IPAM": {"Config": null}, "Containers": null, "Labels": null},
+        {"Name": "host", "Id": "h", "Driver": "host", "IPAM": {"Config": []}}
+    ]"#;
+
+    #[test]
+    fn test_select_networks() {
+        let inspected: Vec<InspectedNetwork> = serde_json::from_str(INSPECT).unwrap();
+        let routes = parse_routes(
+            "default via 192.168.1.1 dev eth0 proto dhcp metric 100\n\
+             10.10.0.0/24 dev wg0 proto kernel scope link src 10.10.0.1\n\
+             172.17.0.0/16 dev docker0 proto kernel scope link src 172.17.0.1\n\
+             192.168.1.0/24 dev eth0 proto kernel scope link src 192.168.1.20\n",
+        );
+        let networks = select_networks(inspected, "myapp", &HashSet::from(["c1"]), &routes);
+
+        let names: Vec<&str> = networks.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, vec!["myapp_default", "proxy"]);
+        assert!(networks[0].managed && !networks[1].managed);
+        assert_eq!(networks[1].subnets[0].gateway, None);
+        assert_eq!(networks[1].containers.len(), 2);
+        assert_eq!(
+            networks[0].conflicts,
+            vec![SubnetConflict {
+                subnet: "10.10.0.0/16".into(),
+                with: "route on wg0".into(),
+                other_subnet: "10.10.0.0/24".into(),
+            }]
+        );
+        assert!(networks[1].conflicts.is_empty());
+    }
+
+    #[test]
+    fn test_subnets_overlap() {
+        assert!(subnets_overlap("172.18.0.0/16", "172.18.5.0/24"));
+        assert!(subnets_overlap("10.0.0.0/8", "10.200.0.0/16"));
+        assert!(!subnets_overlap("172.18.0.0/16", "172.19.0.0/16"));
+        assert!(!subnets_overlap("fd00::/64", "fd00::/48"));
+    }
+}
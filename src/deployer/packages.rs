@@ -0,0 +1,166 @@
+use crate::deployer::types::{ComposeExec, DeployError, DeployResult};
+
+/// Package manager found on the remote host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageManager {
+    Apt,
+    Dnf,
+    Yum,
+    Zypper,
+    Apk,
+}
+
+impl PackageManager {
+    /// Detection order; the first binary present wins.
+    const ALL: [PackageManager; 5] = [Self::Apt, Self::Dnf, Self::Yum, Self::Zypper, Self::Apk];
+
+    fn binary(self) -> &'static str {
+        match self {
+            Self::Apt => "apt-get",
+            Self::Dnf => "dnf",
+            Self::Yum => "yum",
+            Self::Zypper => "zypper",
+            Self::Apk => "apk",
+        }
+    }
+
+    /// Command that succeeds only if `package` is installed.
+    fn query_command(self, package: &str) -> String {
+        match self {
+            Self::Apt => format!(
+                "dpkg-query -W -f='${{Status}}' {} 2>/dev/null | grep -q 'ok installed'",
+                package
+            ),
+            Self::Dnf | Self::Yum | Self::Zypper => {
+                format!("rpm -q {} >/dev/null 2>&1", package)
+            }
+            Self::Apk => format!("apk info -e {} >/dev/null 2>&1", package),
+        }
+    }
+
+    /// Non-interactive install command for `packages`.
+    fn install_command(self, packages: &[String]) -> String {
+        let packages = packages.join(" ");
+        match self {
+            Self::Apt => format!(
+                "apt-get update && DEBIAN_FRONTEND=noninteractive apt-get install -y {}",
+                packages
+            ),
+            Self::Dnf => format!("dnf install -y {}", packages),
+            Self::Yum => format!("yum install -y {}", packages),
+            Self::Zypper => format!("zypper --non-interactive install {}", packages),
+            Self::Apk => format!("apk add --no-cache {}", packages),
+        }
+    }
+
+    pub async fn detect(executor: &mut (dyn ComposeExec + Send)) -> DeployResult<Option<Self>> {
+        for manager in Self::ALL {
+            let result = executor
+                .execute_command(&format!("command -v {}", manager.binary()))
+                .await
+                .map_err(|e| {
+                    DeployError::Environment(format!("Failed to detect package manager: {}", e))
+                })?;
+            if result.is_success() {
+                return Ok(Some(manager));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// The package manager of the remote host and the packages from `packages`
+/// it does not have installed yet.
+async fn find_missing(
+    executor: &mut (dyn ComposeExec + Send),
+    packages: &[String],
+) -> DeployResult<(PackageManager, Vec<String>)> {
+    let manager = PackageManager::detect(executor).await?.ok_or_else(|| {
+        DeployError::Environment(
+            "No supported package manager (apt-get, dnf, yum, zypper, apk) found on the remote host".into(),
+        )
+    })?;
+
+    let mut missing = Vec::new();
+    for package in packages {
+        let result = executor
+            .execute_command(&manager.query_command(package))
+            .await
+            .map_err(|e| DeployError::Environment(format!("Failed to query {}: {}", package, e)))?;
+        if !result.is_success() {
+            missing.push(package.clone());
+        }
+    }
+    Ok((manager, missing))
+}
+
+/// The packages from `packages` that [`ensure_host_packages`] would install.
+pub async fn missing_host_packages(
+    executor: &mut (dyn ComposeExec + Send),
+    packages: &[String],
+) -> DeployResult<Vec<String>> {
+    if packages.is_empty() {
+        return Ok(Vec::new());
+    }
+    Ok(find_missing(executor, packages).await?.1)
+}
+
+/// Install the packages from `packages` that are not present yet.
+/// Returns the ones that were installed.
+pub async fn ensure_host_packages(
+    executor: &mut (dyn ComposeExec + Send),
+    packages: &[String],
+) -> DeployResult<Vec<String>> {
+    if packages.is_empty() {
+        return Ok(Vec::new());
+    }
+    let (manager, missing) = find_missing(executor, packages).await?;
+    if missing.is_empty() {
+        return Ok(missing);
+    }
+
+    let cmd = manager.install_command(&missing);
+    tracing::info!("Installing host packages: {}", missing.join(", "));
+    let result = executor
+        .execute_command(&cmd)
+        .await
+        .map_err(|e| DeployError::Environment(format!("Failed to install packages: {}", e)))?;
+    if !result.is_success() {
+        return Err(DeployError::Environment(format!(
+            "Failed to install host packages {}: {}",
+            missing.join(", "),
+            result.output.to_stderr_string()?.trim()
+        )));
+    }
+    Ok(missing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_package_manager_commands() {
+        let packages = vec!["nfs-common".to_string(), "cifs-utils".to_string()];
+        assert_eq!(
+            PackageManager::Apt.install_command(&packages),
+            "apt-get update && DEBIAN_FRONTEND=noninteractive apt-get install -y nfs-common cifs-utils"
+        );
+        assert_eq!(
+            PackageManager::Apk.install_command(&packages),
+            "apk add --no-cache nfs-common cifs-utils"
+        );
+        assert_eq!(
+            PackageManager::Zypper.install_command(&packages),
+            "zypper --non-interactive install nfs-common cifs-utils"
+        );
+        assert_eq!(
+            PackageManager::Apt.query_command("nfs-common"),
+            "dpkg-query -W -f='${Status}' nfs-common 2>/dev/null | grep -q 'ok installed'"
+        );
+        assert_eq!(
+            PackageManager::Dnf.query_command("nfs-utils"),
+            "rpm -q nfs-utils >/dev/null 2>&1"
+        );
+    }
+}
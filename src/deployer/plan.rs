@@ -0,0 +1,299 @@
+use crate::composer::types::ComposerOutput;
+use crate::deployer::state::DeployRecord;
+use crate::deployer::sync::{sha256_file, walk_local_directory, IgnoreRules};
+use crate::deployer::types::{DeployError, DeployResult};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Format version of plan files; `apply` refuses others.
+pub const PLAN_VERSION: u32 = 1;
+
+/// What a deployment of the local project consists of, written by `dcd plan`
+/// for review and checked by `dcd apply` before deploying. Values of
+/// variables are only recorded as digests.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeployPlan {
+    pub version: u32,
+    /// Who created the plan, when and from which commit
+    pub created: DeployRecord,
+    /// Compose files as given with `-f` (empty for the defaults)
+    pub compose_files: Vec<PathBuf>,
+    /// Env files as given with `-e` (empty for the defaults)
+    pub env_files: Vec<PathBuf>,
+    pub profiles: Vec<String>,
+    /// Hidden files of referenced directories are synced (`--include-hidden`)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub include_hidden: bool,
+    /// Image of each service, `(build)` for services built on the host
+    pub services: BTreeMap<String, String>,
+    /// Published ports, e.g. `8080:80/tcp`
+    pub ports: Vec<String>,
+    /// SHA-256 of each local file that gets synced, by path relative to
+    /// the project directory
+    pub files: BTreeMap<PathBuf, String>,
+    /// SHA-256 of the resolved compose configuration
+    pub config_digest: String,
+    /// SHA-256 of the variables passed to the deployment
+    pub env_digest: String,
+}
+
+impl DeployPlan {
+    /// Record the deployment described by `analysis`, whose resolved
+    /// compose configuration is `resolved_config`. Referenced directories
+    /// are recorded as `up` syncs them, with their hidden files only if
+    /// `include_hidden`.
+    pub async fn build(
+        analysis: &ComposerOutput,
+        resolved_config: &str,
+        compose_files: Vec<PathBuf>,
+        env_files: Vec<PathBuf>,
+        include_hidden: bool,
+    ) -> DeployResult<Self> {
+        let project_dir = &analysis.resolved_project_dir;
+        let mut files = BTreeMap::new();
+        for path in synced_paths(analysis) {
+            if path.is_dir() {
+                let ignore = IgnoreRules::load(&path, include_hidden)?;
+                let (_, found) = walk_local_directory(&path, &ignore).await?;
+                for file in found {
+                    let local = path.join(&file);
+                    files.insert(relative(project_dir, &local), sha256_file(&local).await?);
+                }
+            } else if path.exists() {
+                files.insert(relative(project_dir, &path), sha256_file(&path).await?);
+            }
+        }
+
+        let mut env: Vec<_> = analysis.consumed_env.iter().collect();
+        env.sort();
+        let env_lines: String = env
+            .into_iter()
+            .map(|(name, value)| format!("{}={}\n", name, value))
+            .collect();
+
+        Ok(Self {
+            version: PLAN_VERSION,
+            created: DeployRecord::current(project_dir),
+            compose_files,
+            env_files,
+            profiles: analysis.active_profiles.clone(),
+            include_hidden,
+            services: service_images(resolved_config)?,
+            ports: analysis
+                .exposed_ports
+                .iter()
+                .map(|port| {
+                    format!(
+                        "{}:{}/{}",
+                        port.published,
+                        port.target,
+                        port.protocol.as_deref().unwrap_or("tcp")
+                    )
+                })
+                .collect(),
+            files,
+            config_digest: sha256_hex(resolved_config.as_bytes()),
+            env_digest: sha256_hex(env_lines.as_bytes()),
+        })
+    }
+
+    /// Read a plan file written by `dcd plan`.
+    pub fn load(path: &Path) -> DeployResult<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            DeployError::Configuration(format!("Failed to read plan {}: {}", path.display(), e))
+        })?;
+        let version = serde_json::from_str::<serde_json::Value>(&content)
+            .ok()
+            .and_then(|plan| plan.get("version")?.as_u64());
+        if version != Some(u64::from(PLAN_VERSION)) {
+            return Err(DeployError::Configuration(format!(
+                "{} is not a plan this dcd version can apply (expected plan format {})",
+                path.display(),
+                PLAN_VERSION
+            )));
+        }
+        serde_json::from_str(&content).map_err(|e| {
+            DeployError::Configuration(format!("Invalid plan {}: {}", path.display(), e))
+        })
+    }
+
+    /// How `current` (a plan built now) differs from this one, one line per
+    /// difference. Empty when the plan can be applied as recorded.
+    pub fn changes(&self, current: &DeployPlan) -> Vec<String> {
+        let mut changes = Vec::new();
+        for (path, digest) in &self.files {
+            match current.files.get(path) {
+                None => changes.push(format!("file removed: {}", path.display())),
+                Some(now) if now != digest => {
+                    changes.push(format!("file changed: {}", path.display()))
+                }
+                Some(_) => {}
+            }
+        }
+        for path in current.files.keys() {
+            if !self.files.contains_key(path) {
+                changes.push(format!("file added: {}", path.display()));
+            }
+        }
+        for (service, image) in &self.services {
+            match current.services.get(service) {
+                None => changes.push(format!("service removed: {}", service)),
+                Some(now) if now != image => {
+                    changes.push(format!("image of {}: {} → {}", service, image, now))
+                }
+                Some(_) => {}
+            }
+        }
+        for service in current.services.keys() {
+            if !self.services.contains_key(service) {
+                changes.push(format!("service added: {}", service));
+            }
+        }
+        if self.ports != current.ports {
+            changes.push(format!(
+                "published ports: {} → {}",
+                self.ports.join(", "),
+                current.ports.join(", ")
+            ));
+        }
+        if self.profiles != current.profiles {
+            changes.push(format!(
+                "active profiles: {} → {}",
+                self.profiles.join(","),
+                current.profiles.join(",")
+            ));
+        }
+        if self.env_digest != current.env_digest {
+            changes.push("variable values changed".to_string());
+        }
+        // Anything else in the resolved configuration (commands, volumes, ...)
+        if changes.is_empty() && self.config_digest != current.config_digest {
+            changes.push("resolved compose configuration changed".to_string());
+        }
+        changes
+    }
+}
+
+/// Local paths `up` syncs: compose and env files plus the top-level entry of
+/// each reference inside the project directory.
+fn synced_paths(analysis: &ComposerOutput) -> Vec<PathBuf> {
+    let project_dir = &analysis.resolved_project_dir;
+    let mut paths: Vec<PathBuf> = analysis
+        .resolved_compose_files
+        .iter()
+        .chain(&analysis.resolved_env_files)
+        .cloned()
+        .collect();
+    for reference in &analysis.local_references {
+        let Ok(relative) = reference.strip_prefix(project_dir) else {
+            continue;
+        };
+        if let Some(first) = relative.components().next() {
+            let top_level = project_dir.join(first);
+            if !paths.contains(&top_level) {
+                paths.push(top_level);
+            }
+        }
+    }
+    paths
+}
+
+fn relative(project_dir: &Path, path: &Path) -> PathBuf {
+    path.strip_prefix(project_dir)
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Image of each service in a resolved compose configuration.
+fn service_images(resolved_config: &str) -> DeployResult<BTreeMap<String, String>> {
+    let config: serde_yaml::Value = serde_yaml::from_str(resolved_config).map_err(|e| {
+        DeployError::Configuration(format!("Failed to parse resolved compose config: {}", e))
+    })?;
+    let Some(services) = config.get("services").and_then(|s| s.as_mapping()) else {
+        return Ok(BTreeMap::new());
+    };
+    Ok(services
+        .iter()
+        .filter_map(|(name, service)| {
+            let image = service
+                .get("image")
+                .and_then(|image| image.as_str())
+                .unwrap_or("(build)");
+            Some((name.as_str()?.to_string(), image.to_string()))
+        })
+        .collect())
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan() -> DeployPlan {
+        DeployPlan {
+            version: PLAN_VERSION,
+            created: DeployRecord::default(),
+            compose_files: Vec::new(),
+            env_files: Vec::new(),
+            profiles: Vec::new(),
+            include_hidden: false,
+            services: service_images(
+                "services:\n  web:\n    image: nginx:1.27\n  worker:\n    build:\n      context: .\n",
+            )
+            .unwrap(),
+            ports: vec!["8080:80/tcp".into()],
+            files: BTreeMap::from([
+                (PathBuf::from("docker-compose.yml"), "aa".into()),
+                (PathBuf::from("config/app.toml"), "bb".into()),
+            ]),
+            config_digest: "c1".into(),
+            env_digest: "e1".into(),
+        }
+    }
+
+    #[test]
+    fn test_service_images() {
+        let plan = plan();
+        assert_eq!(plan.services["web"], "nginx:1.27");
+        assert_eq!(plan.services["worker"], "(build)");
+    }
+
+    #[test]
+    fn test_changes() {
+        let recorded = plan();
+        assert!(recorded.changes(&plan()).is_empty());
+
+        let mut current = plan();
+        current.created.at = 1234;
+        assert!(recorded.changes(&current).is_empty(), "metadata is ignored");
+
+        current
+            .files
+            .insert(PathBuf::from("config/app.toml"), "b2".into());
+        current.files.insert(PathBuf::from(".env"), "dd".into());
+        current.services.insert("web".into(), "nginx:1.28".into());
+        current.env_digest = "e2".into();
+        current.config_digest = "c2".into();
+        assert_eq!(
+            recorded.changes(&current),
+            vec![
+                "file changed: config/app.toml",
+                "file added: .env",
+                "image of web: nginx:1.27 → nginx:1.28",
+                "variable values changed",
+            ]
+        );
+
+        let mut current = plan();
+        current.config_digest = "c2".into();
+        assert_eq!(
+            recorded.changes(&current),
+            vec!["resolved compose configuration changed"]
+        );
+    }
+}
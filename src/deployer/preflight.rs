@@ -0,0 +1,446 @@
+//! Read-only look at a target host before deploying to it: what
+//! `dcd analyze <target>` reports, and the part `dcd up` checks before it
+//! changes anything.
+
+use super::docker_manager::check_remote_os;
+use super::doctor::Finding;
+use super::firewall::{
+    detect_backend, firewall_manager, missing_rules, FirewallBackend, PortConfig,
+};
+use super::format_bytes;
+use super::state::{DeployRecord, RemoteState, STATE_FILE};
+use super::types::{ComposeExec, DeployError, DeployResult};
+use crate::executor::shell_quote;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Free space in the workdir below which `dcd up` refuses to start
+pub const MIN_FREE_DISK: u64 = 1 << 30;
+/// Free space below which a warning is shown
+const LOW_FREE_DISK: u64 = 5 << 30;
+
+/// What a target host looks like to a deployment into `workdir`.
+#[derive(Debug, Clone, Serialize)]
+pub struct HostFacts {
+    /// `PRETTY_NAME` of /etc/os-release, or the kernel name
+    pub os: Option<String>,
+    /// Whether dcd can deploy to the OS (Linux)
+    pub os_supported: bool,
+    /// Version of the docker daemon, if it is installed and reachable
+    pub docker_version: Option<String>,
+    pub compose_version: Option<String>,
+    pub workdir: PathBuf,
+    /// Bytes available on the filesystem the workdir is (or will be) on
+    pub free_disk: Option<u64>,
+    /// `None` if there is no supported firewall
+    pub firewall: Option<FirewallFacts>,
+    pub workdir_state: WorkdirState,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FirewallFacts {
+    pub backend: FirewallBackend,
+    pub active: bool,
+    /// Allowed ports as `port/protocol`; `None` if they could not be read
+    /// (e.g. without root)
+    pub open_ports: Option<Vec<String>>,
+}
+
+/// What the workdir holds already.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum WorkdirState {
+    #[default]
+    Missing,
+    Empty,
+    /// A deployment made by dcd
+    Deployment {
+        project: String,
+        last_deploy: Option<DeployRecord>,
+    },
+    /// Files dcd did not put there
+    Other {
+        entries: usize,
+    },
+}
+
+/// Collect the facts about the host `executor` is connected to.
+pub async fn gather(
+    executor: &mut (dyn ComposeExec + Send),
+    workdir: &Path,
+) -> DeployResult<HostFacts> {
+    let os = run(executor, "cat /etc/os-release 2>/dev/null || uname -sr")
+        .await?
+        .map(|output| os_name(&output));
+    let os_supported = check_remote_os(executor).await.is_ok();
+    let docker_version = run(executor, "docker version --format '{{.Server.Version}}'")
+        .await?
+        .map(|version| version.trim().to_string());
+    let compose_version = run(executor, "docker-compose version --short")
+        .await?
+        .map(|version| version.trim().to_string());
+    let free_disk = free_disk(executor, workdir).await?;
+    let firewall = firewall(executor).await;
+    let workdir_state = workdir_state(executor, workdir).await?;
+    Ok(HostFacts {
+        os,
+        os_supported,
+        docker_version,
+        compose_version,
+        workdir: workdir.to_path_buf(),
+        free_disk,
+        firewall,
+        workdir_state,
+    })
+}
+
+/// The checks `dcd up` runs before changing anything: disk space and what
+/// the workdir holds.
+pub async fn deploy_checks(
+    executor: &mut (dyn ComposeExec + Send),
+    workdir: &Path,
+) -> DeployResult<Vec<Finding>> {
+    let mut findings = Vec::new();
+    if let Some(free) = free_disk(executor, workdir).await? {
+        findings.push(disk_finding(free, workdir));
+    }
+    findings.push(workdir_finding(
+        &workdir_state(executor, workdir).await?,
+        workdir,
+    ));
+    Ok(findings)
+}
+
+impl HostFacts {
+    /// Findings for the report; `ports` are the ones the project publishes.
+    pub fn findings(&self, ports: &[PortConfig]) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        let os = self.os.as_deref().unwrap_or("unknown OS");
+        findings.push(if self.os_supported {
+            Finding::ok("os", os)
+        } else {
+            Finding::error("os", format!("{} is not supported", os))
+                .with_suggestion("dcd deploys to Linux hosts")
+        });
+        findings.push(match &self.docker_version {
+            Some(version) => Finding::ok("docker", format!("Docker {}", version)),
+            None => Finding::warning("docker", "Docker is not installed or not running")
+                .with_suggestion("'dcd up' installs it on supported distributions"),
+        });
+        findings.push(match &self.compose_version {
+            Some(version) => Finding::ok("compose", format!("Docker Compose {}", version)),
+            None => Finding::warning("compose", "docker-compose is not installed")
+                .with_suggestion("'dcd up' installs it on supported distributions"),
+        });
+        findings.push(match self.free_disk {
+            Some(free) => disk_finding(free, &self.workdir),
+            None => Finding::warning(
+                "disk",
+                format!(
+                    "Could not read the free space for {}",
+                    self.workdir.display()
+                ),
+            ),
+        });
+        findings.push(firewall_finding(self.firewall.as_ref(), ports));
+        findings.push(workdir_finding(&self.workdir_state, &self.workdir));
+        findings
+    }
+}
+
+fn disk_finding(free: u64, workdir: &Path) -> Finding {
+    let message = format!("{} free for {}", format_bytes(free), workdir.display());
+    if free < MIN_FREE_DISK {
+        Finding::error(
+            "disk",
+            format!(
+                "Only {} (at least {} needed)",
+                message,
+                format_bytes(MIN_FREE_DISK)
+            ),
+        )
+        .with_suggestion("Free up space, e.g. with 'dcd gc <target>' or 'docker system prune'")
+    } else if free < LOW_FREE_DISK {
+        Finding::warning("disk", message)
+            .with_suggestion("Pulling images may run out of space; consider 'dcd gc <target>'")
+    } else {
+        Finding::ok("disk", message)
+    }
+}
+
+fn firewall_finding(firewall: Option<&FirewallFacts>, ports: &[PortConfig]) -> Finding {
+    let Some(firewall) = firewall else {
+        return Finding::warning(
+            "firewall",
+            "No supported firewall (ufw, firewalld or nftables)",
+        )
+        .with_suggestion("Skip the stage with 'dcd up --skip firewall'");
+    };
+    if !firewall.active {
+        return Finding::ok(
+            "firewall",
+            format!(
+                "{} is not active; 'dcd up' enables it, keeping SSH open",
+                firewall.backend
+            ),
+        );
+    }
+    let Some(open_ports) = &firewall.open_ports else {
+        return Finding::warning(
+            "firewall",
+            format!("Could not read the {} rules", firewall.backend),
+        )
+        .with_suggestion("Connect as root or a user with sudo rights");
+    };
+    let mut message = format!(
+        "{} active, open: {}",
+        firewall.backend,
+        if open_ports.is_empty() {
+            "none".to_string()
+        } else {
+            open_ports.join(", ")
+        }
+    );
+    let missing = missing_rules(ports, &open_ports.iter().cloned().collect());
+    if !missing.is_empty() {
+        let mut to_open: Vec<String> = missing
+            .iter()
+            .map(|rule| format!("{}/{}", rule.port, rule.protocol))
+            .collect();
+        to_open.dedup();
+        message.push_str(&format!("; 'dcd up' opens {}", to_open.join(", ")));
+    }
+    Finding::ok("firewall", message)
+}
+
+fn workdir_finding(state: &WorkdirState, workdir: &Path) -> Finding {
+    let dir = workdir.display();
+    match state {
+        WorkdirState::Missing => Finding::ok(
+            "workdir",
+            format!("{} does not exist yet; 'dcd up' creates it", dir),
+        ),
+        WorkdirState::Empty => Finding::ok("workdir", format!("{} is empty", dir)),
+        WorkdirState::Deployment {
+            project,
+            last_deploy,
+        } => {
+            let mut message = format!("{} holds the dcd deployment of '{}'", dir, project);
+            if let Some(record) = last_deploy {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|elapsed| elapsed.as_secs())
+                    .unwrap_or_default();
+                message.push_str(&format!(" (deployed {})", record.summary(now)));
+            }
+            Finding::ok("workdir", message)
+        }
+        WorkdirState::Other { entries } => Finding::warning(
+            "workdir",
+            format!("{} holds {} entries not deployed by dcd", dir, entries),
+        )
+        .with_suggestion(
+            "Synced files replace ones of the same name; pick another directory with -w",
+        ),
+    }
+}
+
+/// Output of `command`, `None` if it exits non-zero.
+async fn run(
+    executor: &mut (dyn ComposeExec + Send),
+    command: &str,
+) -> DeployResult<Option<String>> {
+    let result = executor
+        .execute_command(command)
+        .await
+        .map_err(|e| DeployError::Deployment(e.to_string()))?;
+    if !result.is_success() {
+        return Ok(None);
+    }
+    Ok(Some(result.output.to_stdout_string()?))
+}
+
+/// `PRETTY_NAME` of an os-release file, or the text itself (`uname` output).
+fn os_name(output: &str) -> String {
+    output
+        .lines()
+        .find_map(|line| line.strip_prefix("PRETTY_NAME="))
+        .map(|name| name.trim().trim_matches('"').to_string())
+        .unwrap_or_else(|| output.trim().to_string())
+}
+
+/// Bytes available where `dir` is, or will be created: its closest existing
+/// ancestor.
+pub async fn free_disk(
+    executor: &mut (dyn ComposeExec + Send),
+    dir: &Path,
+) -> DeployResult<Option<u64>> {
+    let command = format!(
+        "d={}; while [ ! -e \"$d\" ]; do d=$(dirname \"$d\"); done; df -Pk \"$d\"",
+        shell_quote(&dir.to_string_lossy())
+    );
+    Ok(run(executor, &command)
+        .await?
+        .and_then(|output| parse_df_available(&output)))
+}
+
+/// Available bytes from the data line of `df -Pk`.
+fn parse_df_available(output: &str) -> Option<u64> {
+    let line = output.lines().nth(1)?;
+    let available: u64 = line.split_whitespace().nth(3)?.parse().ok()?;
+    Some(available * 1024)
+}
+
+async fn firewall(executor: &mut (dyn ComposeExec + Send)) -> Option<FirewallFacts> {
+    let backend = detect_backend(executor).await.ok()?;
+    let active_command = match backend {
+        FirewallBackend::Ufw => "ufw status | grep -q 'Status: active'",
+        FirewallBackend::Firewalld => "firewall-cmd --state",
+        FirewallBackend::Nftables => "systemctl is-active --quiet nftables",
+    };
+    let active = run(executor, active_command).await.ok().flatten().is_some();
+    let open_ports = if active {
+        match firewall_manager(executor).await {
+            Ok(mut manager) => manager.opened_ports().await.ok().map(|ports| {
+                let mut ports: Vec<String> = ports.into_iter().collect();
+                ports.sort();
+                ports
+            }),
+            Err(_) => None,
+        }
+    } else {
+        Some(Vec::new())
+    };
+    Some(FirewallFacts {
+        backend,
+        active,
+        open_ports,
+    })
+}
+
+/// What `dir` holds: nothing, a dcd deployment or other files.
+pub async fn workdir_state(
+    executor: &mut (dyn ComposeExec + Send),
+    dir: &Path,
+) -> DeployResult<WorkdirState> {
+    let command = format!("ls -1A {}", shell_quote(&dir.to_string_lossy()));
+    let Some(listing) = run(executor, &command).await? else {
+        return Ok(WorkdirState::Missing);
+    };
+    let entries: Vec<&str> = listing.lines().filter(|line| !line.is_empty()).collect();
+    if entries.is_empty() {
+        return Ok(WorkdirState::Empty);
+    }
+    if entries.contains(&STATE_FILE) {
+        if let Some(state) = RemoteState::load(executor, dir).await? {
+            return Ok(WorkdirState::Deployment {
+                project: state.project,
+                last_deploy: state.last_deploy,
+            });
+        }
+    }
+    Ok(WorkdirState::Other {
+        entries: entries.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deployer::doctor::Severity;
+    use crate::deployer::firewall::Protocol;
+    use crate::testing::{CommandMatch, MockExecutor};
+
+    #[test]
+    fn test_parse_df_available() {
+        let output = "\
+Filesystem     1024-blocks     Used Available Capacity Mounted on
+/dev/sda1         41152736 30000000   9034648      77% /
+";
+        assert_eq!(parse_df_available(output), Some(9034648 * 1024));
+        assert_eq!(parse_df_available("Filesystem\n"), None);
+        assert_eq!(
+            os_name("NAME=\"Ubuntu\"\nPRETTY_NAME=\"Ubuntu 24.04.1 LTS\"\n"),
+            "Ubuntu 24.04.1 LTS"
+        );
+        assert_eq!(os_name("Linux 6.8.0\n"), "Linux 6.8.0");
+    }
+
+    #[tokio::test]
+    async fn test_workdir_state() {
+        let mut executor = MockExecutor::new();
+        executor.respond_err(CommandMatch::Exact("ls -1A /opt/missing".into()), 2, "");
+        executor.respond_ok(CommandMatch::Exact("ls -1A /opt/empty".into()), "");
+        executor.respond_ok(
+            CommandMatch::Exact("ls -1A /opt/app".into()),
+            "docker-compose.yml\n.dcd-state.json\n",
+        );
+        executor.respond_ok(
+            CommandMatch::Exact("cat /opt/app/.dcd-state.json 2>/dev/null".into()),
+            r#"{"project": "app"}"#,
+        );
+        executor.respond_ok(CommandMatch::Exact("ls -1A /srv".into()), "www\nbackup\n");
+
+        for (dir, expected) in [
+            ("/opt/missing", WorkdirState::Missing),
+            ("/opt/empty", WorkdirState::Empty),
+            (
+                "/opt/app",
+                WorkdirState::Deployment {
+                    project: "app".into(),
+                    last_deploy: None,
+                },
+            ),
+            ("/srv", WorkdirState::Other { entries: 2 }),
+        ] {
+            let state = workdir_state(&mut executor, Path::new(dir)).await.unwrap();
+            assert_eq!(state, expected, "{}", dir);
+        }
+    }
+
+    #[test]
+    fn test_findings() {
+        let facts = HostFacts {
+            os: Some("Debian GNU/Linux 12 (bookworm)".into()),
+            os_supported: true,
+            docker_version: None,
+            compose_version: Some("2.29.1".into()),
+            workdir: PathBuf::from("/opt/app"),
+            free_disk: Some(512 << 20),
+            firewall: Some(FirewallFacts {
+                backend: FirewallBackend::Ufw,
+                active: true,
+                open_ports: Some(vec!["22/tcp".into(), "80/tcp".into()]),
+            }),
+            workdir_state: WorkdirState::Missing,
+        };
+        let ports = [80, 443].map(|port| PortConfig {
+            port,
+            protocol: Protocol::Tcp,
+            description: String::new(),
+            sources: Vec::new(),
+        });
+        let findings = facts.findings(&ports);
+        let severities: Vec<Severity> = findings.iter().map(|f| f.severity).collect();
+        assert_eq!(
+            severities,
+            [
+                Severity::Ok,
+                Severity::Warning,
+                Severity::Ok,
+                Severity::Error,
+                Severity::Ok,
+                Severity::Ok
+            ]
+        );
+        assert_eq!(
+            findings[3].message,
+            "Only 512.0 MiB free for /opt/app (at least 1.0 GiB needed)"
+        );
+        assert_eq!(
+            findings[4].message,
+            "ufw active, open: 22/tcp, 80/tcp; 'dcd up' opens 443/tcp"
+        );
+    }
+}
@@ -0,0 +1,160 @@
+//! Copying files from the server back into the local project (`dcd pull`).
+
+use super::types::{ComposeExec, DeployError, DeployResult};
+use crate::executor::shell_quote;
+use std::path::{Component, Path, PathBuf};
+
+/// Download `paths` (files or directories, relative to `remote_dir` or
+/// absolute) into `local_dir`. Relative paths keep their place below
+/// `local_dir`; absolute ones land there under their file name. Existing
+/// local files are only replaced with `overwrite`. Returns the local files
+/// written.
+pub async fn pull_files(
+    executor: &mut (dyn ComposeExec + Send),
+    remote_dir: &Path,
+    paths: &[PathBuf],
+    local_dir: &Path,
+    overwrite: bool,
+) -> DeployResult<Vec<PathBuf>> {
+    let mut transfers = Vec::new();
+    for path in paths {
+        let local = local_dir.join(local_name(path)?);
+        let remote = remote_dir.join(path);
+        if is_remote_dir(executor, &remote).await? {
+            let files = executor.list_files(&remote).await.map_err(|e| {
+                DeployError::FileSync(format!("Failed to list {}: {}", remote.display(), e))
+            })?;
+            transfers.extend(
+                files
+                    .into_iter()
+                    .map(|file| (remote.join(&file), local.join(&file))),
+            );
+        } else {
+            transfers.push((remote, local));
+        }
+    }
+
+    if !overwrite {
+        let existing: Vec<String> = transfers
+            .iter()
+            .filter(|(_, local)| local.exists())
+            .map(|(_, local)| local.display().to_string())
+            .collect();
+        if !existing.is_empty() {
+            return Err(DeployError::Configuration(format!(
+                "Not replacing existing local files (use --force): {}",
+                existing.join(", ")
+            )));
+        }
+    }
+
+    let mut pulled = Vec::with_capacity(transfers.len());
+    for (remote, local) in transfers {
+        tracing::debug!("Downloading {} to {}", remote.display(), local.display());
+        executor.download_file(&remote, &local).await.map_err(|e| {
+            DeployError::FileSync(format!("Failed to download {}: {}", remote.display(), e))
+        })?;
+        pulled.push(local);
+    }
+    Ok(pulled)
+}
+
+/// Where a pulled path goes below the local directory. Paths leaving the
+/// remote directory (`..`) would leave the local one too, so they are refused.
+fn local_name(path: &Path) -> DeployResult<PathBuf> {
+    if path.is_absolute() {
+        return path
+            .file_name()
+            .map(PathBuf::from)
+            .ok_or_else(|| DeployError::Configuration(format!("Cannot pull {}", path.display())));
+    }
+    if path
+        .components()
+        .any(|component| matches!(component, Component::ParentDir))
+    {
+        return Err(DeployError::Configuration(format!(
+            "{} leaves the remote directory; give it as an absolute path",
+            path.display()
+        )));
+    }
+    Ok(path.to_path_buf())
+}
+
+async fn is_remote_dir(executor: &mut (dyn ComposeExec + Send), path: &Path) -> DeployResult<bool> {
+    let result = executor
+        .execute_command(&format!("test -d {}", shell_quote(&path.to_string_lossy())))
+        .await
+        .map_err(|e| DeployError::FileSync(e.to_string()))?;
+    Ok(result.is_success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{CommandMatch, MemoryFs, MockExecutor};
+
+    #[tokio::test]
+    async fn test_pull_files() {
+        let fs = MemoryFs::new();
+        fs.insert("/opt/app/certs/live/cert.pem", "cert");
+        fs.insert("/opt/app/certs/key.pem", "key");
+        fs.insert("/opt/app/data/app.db", "db");
+        fs.insert("/var/backups/dump.sql", "dump");
+        let mut executor = MockExecutor::with_fs(fs);
+        executor.respond_ok(CommandMatch::Exact("test -d /opt/app/certs".into()), "");
+        executor.respond_err(CommandMatch::Prefix("test -d".into()), 1, "");
+
+        let local = tempfile::tempdir().unwrap();
+        let paths = ["certs", "data/app.db", "/var/backups/dump.sql"].map(PathBuf::from);
+        let pulled = pull_files(
+            &mut executor,
+            Path::new("/opt/app"),
+            &paths,
+            local.path(),
+            false,
+        )
+        .await
+        .unwrap();
+        let relative: Vec<_> = pulled
+            .iter()
+            .map(|path| path.strip_prefix(local.path()).unwrap().to_path_buf())
+            .collect();
+        assert_eq!(
+            relative,
+            [
+                "certs/key.pem",
+                "certs/live/cert.pem",
+                "data/app.db",
+                "dump.sql"
+            ]
+            .map(PathBuf::from)
+        );
+        assert_eq!(
+            std::fs::read_to_string(local.path().join("certs/live/cert.pem")).unwrap(),
+            "cert"
+        );
+
+        // Existing files are kept unless asked to replace them
+        let error = pull_files(
+            &mut executor,
+            Path::new("/opt/app"),
+            &paths,
+            local.path(),
+            false,
+        )
+        .await
+        .unwrap_err();
+        assert!(error.to_string().contains("use --force"));
+        pull_files(
+            &mut executor,
+            Path::new("/opt/app"),
+            &paths,
+            local.path(),
+            true,
+        )
+        .await
+        .unwrap();
+
+        assert!(local_name(Path::new("../etc/passwd")).is_err());
+    }
+}
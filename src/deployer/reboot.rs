@@ -0,0 +1,33 @@
+use crate::deployer::types::{ComposeExec, DeployError, DeployResult};
+
+/// Identifier of the current boot; changes every time the host restarts.
+pub async fn boot_id(executor: &mut (dyn ComposeExec + Send)) -> DeployResult<String> {
+    let result = executor
+        .execute_command("cat /proc/sys/kernel/random/boot_id")
+        .await
+        .map_err(|e| DeployError::Deployment(format!("Failed to read boot id: {}", e)))?;
+    if !result.is_success() {
+        return Err(DeployError::Deployment(format!(
+            "Failed to read boot id: {}",
+            result.output.to_stderr_string()?.trim()
+        )));
+    }
+    Ok(result.output.to_stdout_string()?.trim().to_string())
+}
+
+/// Schedule a reboot a couple of seconds from now so the command returns
+/// before the SSH session is torn down.
+pub async fn schedule_reboot(executor: &mut (dyn ComposeExec + Send)) -> DeployResult<()> {
+    let cmd = "nohup sh -c 'sleep 2; systemctl reboot || reboot' >/dev/null 2>&1 &";
+    let result = executor
+        .execute_command(cmd)
+        .await
+        .map_err(|e| DeployError::Deployment(format!("Failed to request reboot: {}", e)))?;
+    if !result.is_success() {
+        return Err(DeployError::Deployment(format!(
+            "Failed to request reboot: {}",
+            result.output.to_stderr_string()?.trim()
+        )));
+    }
+    Ok(())
+}
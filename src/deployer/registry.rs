@@ -0,0 +1,362 @@
+use crate::composer::types::PortMapping;
+use crate::deployer::stacks::list_stack_containers;
+use crate::deployer::state::{DeployRecord, RemoteState};
+use crate::deployer::types::{ComposeExec, DeployResult};
+use crate::executor::shell_quote;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// Host-wide list of the remote directories dcd deployed to, one per line.
+pub const REGISTRY_FILE: &str = "/var/lib/dcd/deployments";
+
+/// Host-wide list of the ports dcd projects publish, one
+/// `port/protocol<TAB>remote dir<TAB>project` line per port.
+pub const PORTS_FILE: &str = "/var/lib/dcd/ports";
+
+/// A published port recorded in [`PORTS_FILE`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PortClaim {
+    pub port: u16,
+    pub protocol: String,
+    pub remote_dir: PathBuf,
+    pub project: String,
+}
+
+/// A dcd deployment found on a host.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeployedProject {
+    pub remote_dir: PathBuf,
+    /// Compose project name
+    pub project: String,
+    pub compose_files: Vec<PathBuf>,
+    pub profiles: Vec<String>,
+    /// The last successful deployment, if it was recorded
+    pub last_deploy: Option<DeployRecord>,
+    /// Containers of the project on the host, running or not
+    pub containers: usize,
+    /// Set while a deployment is in progress or was aborted
+    pub pending: bool,
+}
+
+/// Add `remote_dir` to the host's registry. Best effort: the state file in
+/// the directory is the record of the deployment, and a project missing
+/// from the registry is still found through its containers.
+pub async fn register(executor: &mut (dyn ComposeExec + Send), remote_dir: &Path) {
+    let dir = shell_quote(&remote_dir.to_string_lossy());
+    let file = shell_quote(REGISTRY_FILE);
+    let cmd = format!(
+        "mkdir -p \"$(dirname {file})\" && touch {file} && \
+         {{ grep -qxF -- {dir} {file} || printf '%s\\n' {dir} >> {file}; }}"
+    );
+    match executor.execute_command(&cmd).await {
+        Ok(result) if result.is_success() => {}
+        Ok(result) => tracing::warn!(
+            "Failed to add {} to {}: {}",
+            remote_dir.display(),
+            REGISTRY_FILE,
+            result.output.to_stderr_string().unwrap_or_default().trim()
+        ),
+        Err(e) => tracing::warn!(
+            "Failed to add {} to {}: {}",
+            remote_dir.display(),
+            REGISTRY_FILE,
+            e
+        ),
+    }
+}
+
+/// Drop `remote_dir` from the host's registry. Best effort, like [`register`].
+pub async fn unregister(executor: &mut (dyn ComposeExec + Send), remote_dir: &Path) {
+    let dir = shell_quote(&remote_dir.to_string_lossy());
+    let file = shell_quote(REGISTRY_FILE);
+    let cmd = format!(
+        "if [ -f {file} ]; then grep -vxF -- {dir} {file} > {file}.tmp; mv {file}.tmp {file}; fi"
+    );
+    if let Err(e) = executor.execute_command(&cmd).await {
+        tracing::warn!(
+            "Failed to remove {} from {}: {}",
+            remote_dir.display(),
+            REGISTRY_FILE,
+            e
+        );
+    }
+}
+
+/// The ports claimed on the host. Best effort: an unreadable list claims nothing.
+pub async fn port_claims(executor: &mut (dyn ComposeExec + Send)) -> Vec<PortClaim> {
+    let content = executor
+        .execute_command(&format!("cat {} 2>/dev/null", shell_quote(PORTS_FILE)))
+        .await
+        .ok()
+        .filter(|result| result.is_success())
+        .and_then(|result| result.output.to_stdout_string().ok())
+        .unwrap_or_default();
+    parse_port_claims(&content)
+}
+
+/// Ports of `ports` that deployments in other directories than `remote_dir`
+/// claimed. Claims of directories without a dcd state file are stale (the
+/// project was removed by hand) and ignored.
+pub async fn claimed_by_others(
+    executor: &mut (dyn ComposeExec + Send),
+    remote_dir: &Path,
+    ports: &[PortMapping],
+) -> DeployResult<Vec<PortClaim>> {
+    let wanted = published_ports(ports);
+    let mut conflicts = Vec::new();
+    for claim in port_claims(executor).await {
+        if claim.remote_dir == remote_dir || !wanted.contains(&(claim.port, claim.protocol.clone()))
+        {
+            continue;
+        }
+        if RemoteState::load(executor, &claim.remote_dir)
+            .await?
+            .is_some()
+        {
+            conflicts.push(claim);
+        } else {
+            tracing::debug!(
+                "Ignoring stale claim of port {} by {}",
+                claim.port,
+                claim.remote_dir.display()
+            );
+        }
+    }
+    Ok(conflicts)
+}
+
+/// Record `ports` as the ports of the deployment in `remote_dir`, replacing
+/// what it claimed before. Best effort, like [`register`].
+pub async fn claim_ports(
+    executor: &mut (dyn ComposeExec + Send),
+    remote_dir: &Path,
+    project: &str,
+    ports: &[PortMapping],
+) {
+    let claims = published_ports(ports)
+        .into_iter()
+        .map(|(port, protocol)| PortClaim {
+            port,
+            protocol,
+            remote_dir: remote_dir.to_path_buf(),
+            project: project.to_string(),
+        })
+        .collect();
+    update_port_claims(executor, remote_dir, claims).await;
+}
+
+/// Drop the ports claimed by `remote_dir`. Best effort, like [`register`].
+pub async fn release_ports(executor: &mut (dyn ComposeExec + Send), remote_dir: &Path) {
+    update_port_claims(executor, remote_dir, Vec::new()).await;
+}
+
+async fn update_port_claims(
+    executor: &mut (dyn ComposeExec + Send),
+    remote_dir: &Path,
+    claims: Vec<PortClaim>,
+) {
+    let existing = port_claims(executor).await;
+    if claims.is_empty() && existing.iter().all(|claim| claim.remote_dir != remote_dir) {
+        return;
+    }
+    let mut updated: Vec<PortClaim> = existing
+        .into_iter()
+        .filter(|claim| claim.remote_dir != remote_dir)
+        .chain(claims)
+        .collect();
+    updated.sort();
+    let file = shell_quote(PORTS_FILE);
+    let cmd = format!(
+        "mkdir -p \"$(dirname {file})\" && printf '%s' {content} > {file}.tmp && mv {file}.tmp {file}",
+        content = shell_quote(&render_port_claims(&updated))
+    );
+    match executor.execute_command(&cmd).await {
+        Ok(result) if result.is_success() => {}
+        Ok(result) => tracing::warn!(
+            "Failed to update {}: {}",
+            PORTS_FILE,
+            result.output.to_stderr_string().unwrap_or_default().trim()
+        ),
+        Err(e) => tracing::warn!("Failed to update {}: {}", PORTS_FILE, e),
+    }
+}
+
+/// Numeric published ports with their protocol; ranges are left out.
+fn published_ports(ports: &[PortMapping]) -> BTreeSet<(u16, String)> {
+    ports
+        .iter()
+        .filter_map(|mapping| {
+            let port = mapping.published.parse::<u16>().ok()?;
+            let protocol = mapping.protocol.as_deref().unwrap_or("tcp");
+            Some((port, protocol.to_string()))
+        })
+        .collect()
+}
+
+fn parse_port_claims(content: &str) -> Vec<PortClaim> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let (port, protocol) = fields.next()?.split_once('/')?;
+            Some(PortClaim {
+                port: port.parse().ok()?,
+                protocol: protocol.to_string(),
+                remote_dir: PathBuf::from(fields.next()?),
+                project: fields.next()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+fn render_port_claims(claims: &[PortClaim]) -> String {
+    claims
+        .iter()
+        .map(|claim| {
+            format!(
+                "{}/{}\t{}\t{}\n",
+                claim.port,
+                claim.protocol,
+                claim.remote_dir.display(),
+                claim.project
+            )
+        })
+        .collect()
+}
+
+/// All dcd deployments on the host: the registered directories and the
+/// working directories of compose projects, where a dcd state file exists.
+pub async fn list_deployments(
+    executor: &mut (dyn ComposeExec + Send),
+) -> DeployResult<Vec<DeployedProject>> {
+    let registered = executor
+        .execute_command(&format!("cat {} 2>/dev/null", shell_quote(REGISTRY_FILE)))
+        .await
+        .ok()
+        .filter(|result| result.is_success())
+        .and_then(|result| result.output.to_stdout_string().ok())
+        .unwrap_or_default();
+    let containers = list_stack_containers(executor).await.unwrap_or_else(|e| {
+        tracing::warn!("Could not list compose containers: {}", e);
+        Vec::new()
+    });
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for container in &containers {
+        *counts.entry(container.working_dir.as_str()).or_default() += 1;
+    }
+    let candidates = candidate_dirs(&registered, counts.keys().copied());
+
+    let mut deployments = Vec::new();
+    for dir in candidates {
+        let Some(state) = RemoteState::load(executor, &dir).await? else {
+            continue;
+        };
+        let containers = counts
+            .get(dir.to_string_lossy().as_ref())
+            .copied()
+            .unwrap_or_default();
+        deployments.push(DeployedProject {
+            project: if state.project.is_empty() {
+                // Written by a dcd that did not record the name yet
+                super::compose_project_name(&dir)
+            } else {
+                state.project
+            },
+            remote_dir: dir,
+            compose_files: state.compose_files,
+            profiles: state.profiles,
+            last_deploy: state.last_deploy,
+            containers,
+            pending: state.pending.is_some(),
+        });
+    }
+    Ok(deployments)
+}
+
+/// Absolute directories from the registry listing and the compose working
+/// directories, sorted and without duplicates.
+fn candidate_dirs<'a>(
+    registered: &'a str,
+    working_dirs: impl Iterator<Item = &'a str>,
+) -> BTreeSet<PathBuf> {
+    registered
+        .lines()
+        .map(str::trim)
+        .chain(working_dirs)
+        .filter(|dir| dir.starts_with('/'))
+        .map(PathBuf::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{CommandMatch, MemoryFs, MockExecutor};
+
+    fn mapping(published: &str, protocol: Option<&str>) -> PortMapping {
+        PortMapping {
+            mode: None,
+            target: 80,
+            published: published.to_string(),
+            protocol: protocol.map(str::to_string),
+            allow_from: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_claimed_by_others() {
+        let fs = MemoryFs::new();
+        fs.insert("/opt/shop/.dcd-state.json", "{}");
+        fs.insert("/opt/blog/.dcd-state.json", "{}");
+        let mut executor = MockExecutor::with_fs(fs);
+        executor.respond_ok(
+            CommandMatch::Exact(format!("cat {} 2>/dev/null", PORTS_FILE)),
+            "8080/tcp\t/opt/shop\tshop\n443/tcp\t/opt/blog\tblog\n53/udp\t/opt/gone\tgone\n",
+        );
+
+        let ports = [
+            mapping("8080", None),
+            mapping("443", None),
+            mapping("53", Some("udp")),
+            mapping("9000-9001", None),
+        ];
+        let conflicts = claimed_by_others(&mut executor, Path::new("/opt/blog"), &ports)
+            .await
+            .unwrap();
+        // Its own claim and the one of a removed project do not count
+        assert_eq!(
+            conflicts,
+            vec![PortClaim {
+                port: 8080,
+                protocol: "tcp".into(),
+                remote_dir: PathBuf::from("/opt/shop"),
+                project: "shop".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_port_claims_round_trip() {
+        let content = "443/tcp\t/opt/blog\tblog\n8080/tcp\t/opt/shop\tshop\n";
+        let claims = parse_port_claims(&format!("{}garbage\n", content));
+        assert_eq!(claims.len(), 2);
+        assert_eq!(render_port_claims(&claims), content);
+    }
+
+    #[test]
+    fn test_candidate_dirs() {
+        let dirs = candidate_dirs(
+            "/opt/shop\n\n/srv/blog\n",
+            ["/srv/blog", "/home/me/app", ""].into_iter(),
+        );
+        assert_eq!(
+            dirs.into_iter().collect::<Vec<_>>(),
+            vec![
+                PathBuf::from("/home/me/app"),
+                PathBuf::from("/opt/shop"),
+                PathBuf::from("/srv/blog"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,258 @@
+//! Machine-readable summary of one deployment (`dcd up --report-path`), for
+//! CI pipelines and audit trails.
+//!
+//! Only names of environment variables end up in a report, never values.
+
+use super::docker_manager::UnhealthyService;
+use super::health::ServiceTimeline;
+use super::images::{ImageChange, ServiceImage};
+use super::state::STATE_FILE;
+use super::types::{
+    ComposeExec, DeployError, DeployResult, DeployStage, DeploymentConfig, DeploymentStatus,
+    StageDuration,
+};
+use crate::executor::shell_quote;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Name of the report kept next to the state file on the server.
+pub const REMOTE_REPORT_FILE: &str = ".dcd-report.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Json,
+    Yaml,
+}
+
+impl ReportFormat {
+    /// YAML for `.yml` / `.yaml` files, JSON otherwise.
+    pub fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("yml") || ext.eq_ignore_ascii_case("yaml") => {
+                Self::Yaml
+            }
+            _ => Self::Json,
+        }
+    }
+}
+
+/// Outcome of the health stage.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthReport {
+    /// `false` when the health stage was skipped
+    pub checked: bool,
+    pub healthy: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unhealthy: Vec<UnhealthyService>,
+    /// What each service went through during the health check
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub services: Vec<ServiceTimeline>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeployReport {
+    pub dcd_version: String,
+    pub target: String,
+    pub project: String,
+    pub remote_dir: PathBuf,
+    /// Unix timestamp (seconds) the deployment started
+    pub started_at: u64,
+    pub duration_millis: u64,
+    /// Whether the deployment ran to the end; see `health` for the services
+    pub completed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub files_synced: Vec<PathBuf>,
+    pub files_skipped: Vec<PathBuf>,
+    /// Names of the variables handed to compose
+    pub env_vars: Vec<String>,
+    pub ports_opened: Vec<String>,
+    /// Image of each running service after the deployment
+    pub images: BTreeMap<String, ServiceImage>,
+    pub image_changes: Vec<ImageChange>,
+    pub health: HealthReport,
+    pub stages: Vec<StageDuration>,
+}
+
+impl DeployReport {
+    /// Report of deploying `config` to `target` from `started` until now.
+    /// Details of a failed deployment are lost with its status, so only the
+    /// error is recorded then.
+    pub fn new(
+        target: &str,
+        config: &DeploymentConfig,
+        remote_dir: &Path,
+        started: SystemTime,
+        result: Result<&DeploymentStatus, String>,
+    ) -> Self {
+        let env_vars: BTreeSet<String> = config
+            .consumed_env
+            .keys()
+            .chain(config.secrets.keys())
+            .cloned()
+            .collect();
+        let checked = config.runs_stage(DeployStage::Health);
+        let mut report = Self {
+            dcd_version: env!("CARGO_PKG_VERSION").to_string(),
+            target: target.to_string(),
+            project: super::compose_project_name(remote_dir),
+            remote_dir: remote_dir.to_path_buf(),
+            started_at: started
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+            duration_millis: started
+                .elapsed()
+                .map(|elapsed| elapsed.as_millis() as u64)
+                .unwrap_or_default(),
+            completed: result.is_ok(),
+            error: None,
+            files_synced: Vec::new(),
+            files_skipped: Vec::new(),
+            env_vars: env_vars.into_iter().collect(),
+            ports_opened: Vec::new(),
+            images: BTreeMap::new(),
+            image_changes: Vec::new(),
+            health: HealthReport {
+                checked,
+                healthy: false,
+                unhealthy: Vec::new(),
+                services: Vec::new(),
+            },
+            stages: Vec::new(),
+        };
+        match result {
+            Ok(status) => {
+                report.files_synced = status.synced_files.clone();
+                report.files_skipped = status.skipped_files.clone();
+                report.ports_opened = status.opened_ports.clone();
+                report.images = status.images.clone();
+                report.image_changes = status.image_changes.clone();
+                report.health.healthy = checked && status.services_healthy;
+                report.health.unhealthy = status.unhealthy_services.clone();
+                report.health.services = status.health_timelines.clone();
+                report.stages = status.stage_durations.clone();
+            }
+            Err(error) => report.error = Some(error),
+        }
+        report
+    }
+
+    pub fn render(&self, format: ReportFormat) -> DeployResult<String> {
+        let rendered = match format {
+            ReportFormat::Json => serde_json::to_string_pretty(self)
+                .map(|json| json + "\n")
+                .map_err(|e| e.to_string()),
+            ReportFormat::Yaml => serde_yaml::to_string(self).map_err(|e| e.to_string()),
+        };
+        rendered.map_err(|e| DeployError::Deployment(format!("Failed to serialize report: {}", e)))
+    }
+
+    /// Write the report as JSON to [`REMOTE_REPORT_FILE`] in `remote_dir`,
+    /// replacing the one of the previous deployment. Nothing is written if
+    /// the project was never deployed there.
+    pub async fn upload(
+        &self,
+        executor: &mut (dyn ComposeExec + Send),
+        remote_dir: &Path,
+    ) -> DeployResult<()> {
+        let state = remote_dir.join(STATE_FILE);
+        let exists = executor
+            .execute_command(&format!(
+                "test -f {}",
+                shell_quote(&state.to_string_lossy())
+            ))
+            .await
+            .map_err(|e| DeployError::Deployment(e.to_string()))?;
+        if !exists.is_success() {
+            tracing::debug!(
+                "No deployment in {}; not writing a report",
+                remote_dir.display()
+            );
+            return Ok(());
+        }
+        let content = self.render(ReportFormat::Json)?;
+        executor
+            .upload_content(content.as_bytes(), &remote_dir.join(REMOTE_REPORT_FILE))
+            .await
+            .map_err(|e| DeployError::Deployment(format!("Failed to write report: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SecretSource;
+    use crate::testing::{CommandMatch, MemoryFs, MockExecutor};
+    use std::collections::HashMap;
+
+    fn config() -> DeploymentConfig {
+        DeploymentConfig {
+            consumed_env: HashMap::from([
+                ("DB_PASSWORD".to_string(), "hunter22".to_string()),
+                ("PORT".to_string(), "8080".to_string()),
+            ]),
+            secrets: BTreeMap::from([(
+                "API_TOKEN".to_string(),
+                SecretSource::Exec {
+                    command: "pass api".to_string(),
+                },
+            )]),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_report() {
+        let mut status = DeploymentStatus::new();
+        status.services_healthy = true;
+        status.synced_files = vec![PathBuf::from("docker-compose.yml")];
+        status.opened_ports = vec!["8080/tcp".to_string()];
+        status.record_stage(DeployStage::Sync, std::time::Instant::now());
+        let remote_dir = Path::new("/opt/app");
+        let report = DeployReport::new(
+            "deploy@example.com",
+            &config(),
+            remote_dir,
+            SystemTime::now(),
+            Ok(&status),
+        );
+        assert_eq!(report.project, "app");
+        assert_eq!(report.env_vars, ["API_TOKEN", "DB_PASSWORD", "PORT"]);
+        assert!(report.health.checked && report.health.healthy);
+
+        let json = report.render(ReportFormat::Json).unwrap();
+        assert!(!json.contains("hunter22"));
+        assert!(json.contains("\"stage\": \"sync\""));
+        let yaml = report
+            .render(ReportFormat::for_path(Path::new("report.YAML")))
+            .unwrap();
+        assert!(yaml.contains("ports_opened:\n- 8080/tcp\n"));
+
+        let failed = DeployReport::new(
+            "deploy@example.com",
+            &config(),
+            remote_dir,
+            SystemTime::now(),
+            Err("boom".to_string()),
+        );
+        assert!(!failed.completed);
+        assert_eq!(failed.error.as_deref(), Some("boom"));
+
+        // Written only where the project is deployed
+        let fs = MemoryFs::new();
+        let mut executor = MockExecutor::with_fs(fs.clone());
+        executor.respond_err(CommandMatch::Prefix("test -f".into()), 1, "");
+        report.upload(&mut executor, remote_dir).await.unwrap();
+        assert!(fs.read(&remote_dir.join(REMOTE_REPORT_FILE)).is_none());
+        let mut executor = MockExecutor::with_fs(fs.clone());
+        executor.respond_ok(CommandMatch::Prefix("test -f".into()), "");
+        report.upload(&mut executor, remote_dir).await.unwrap();
+        let uploaded: DeployReport =
+            serde_json::from_slice(&fs.read(&remote_dir.join(REMOTE_REPORT_FILE)).unwrap())
+                .unwrap();
+        assert_eq!(uploaded, report);
+    }
+}
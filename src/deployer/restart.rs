@@ -0,0 +1,62 @@
+/// Compose override written by `--enforce-restart-policy`, passed to compose
+/// after the project's own files.
+pub const RESTART_OVERRIDE_FILE: &str = "docker-compose.dcd-restart.yml";
+
+/// Policy used when `--enforce-restart-policy` is given without a value.
+pub const DEFAULT_RESTART_POLICY: &str = "unless-stopped";
+
+/// Accepts the values compose understands for `restart:`.
+pub fn validate_restart_policy(policy: &str) -> Result<(), String> {
+    let valid = match policy.split_once(':') {
+        Some(("on-failure", retries)) => retries.parse::<u32>().is_ok(),
+        Some(_) => false,
+        None => matches!(policy, "no" | "always" | "on-failure" | "unless-stopped"),
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "invalid restart policy '{}' (expected no, always, on-failure[:N] or unless-stopped)",
+            policy
+        ))
+    }
+}
+
+/// Override setting `restart: <policy>` on each of `services`.
+pub fn restart_override(policy: &str, services: &[String]) -> String {
+    let mut content = String::from("# Generated by dcd (--enforce-restart-policy)\nservices:\n");
+    for service in services {
+        content.push_str(&format!("  {}:\n    restart: \"{}\"\n", service, policy));
+    }
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_restart_policy() {
+        assert!(validate_restart_policy("unless-stopped").is_ok());
+        assert!(validate_restart_policy("on-failure").is_ok());
+        assert!(validate_restart_policy("on-failure:5").is_ok());
+        assert!(validate_restart_policy("on-failure:x").is_err());
+        assert!(validate_restart_policy("sometimes").is_err());
+    }
+
+    #[test]
+    fn test_restart_override() {
+        let content = restart_override("unless-stopped", &["web".into(), "worker".into()]);
+        assert_eq!(
+            content,
+            "# Generated by dcd (--enforce-restart-policy)\nservices:\n  \
+             web:\n    restart: \"unless-stopped\"\n  \
+             worker:\n    restart: \"unless-stopped\"\n"
+        );
+        let parsed: serde_yaml::Value = serde_yaml::from_str(&content).unwrap();
+        assert_eq!(
+            parsed["services"]["worker"]["restart"].as_str(),
+            Some("unless-stopped")
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use crate::deployer::types::{DeployError, DeployResult};
+use age::x25519;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Encrypted replacement for .env.dcd on the remote host.
+pub const ENCRYPTED_ENV_FILE: &str = ".env.dcd.age";
+
+/// Encrypts the deployment env with a locally held age identity, so the server
+/// only ever stores ciphertext.
+pub struct EnvCipher {
+    identity: x25519::Identity,
+}
+
+impl EnvCipher {
+    /// Load the first `AGE-SECRET-KEY-...` line from an age identity file
+    /// (as written by `age-keygen`).
+    pub fn load(path: &Path) -> DeployResult<Self> {
+        let path = expand_home(path);
+        let content = std::fs::read_to_string(&path).map_err(|e| {
+            DeployError::Environment(format!(
+                "Failed to read age identity {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let key = content
+            .lines()
+            .map(str::trim)
+            .find(|line| line.starts_with("AGE-SECRET-KEY-"))
+            .ok_or_else(|| {
+                DeployError::Environment(format!("No age secret key found in {}", path.display()))
+            })?;
+        let identity = key.parse().map_err(|e| {
+            DeployError::Environment(format!("Invalid age identity in {}: {}", path.display(), e))
+        })?;
+        Ok(Self { identity })
+    }
+
+    /// Encrypt `env` to this identity's recipient, ASCII-armored.
+    pub fn encrypt(&self, env: &HashMap<String, String>) -> DeployResult<String> {
+        // Sorted so an unchanged env serializes identically
+        let sorted: BTreeMap<&String, &String> = env.iter().collect();
+        let plaintext = serde_json::to_vec(&sorted)
+            .map_err(|e| DeployError::Environment(format!("Failed to serialize env: {}", e)))?;
+        age::encrypt_and_armor(&self.identity.to_public(), &plaintext)
+            .map_err(|e| DeployError::Environment(format!("Failed to encrypt env: {}", e)))
+    }
+
+    /// Decrypt an env previously produced by [`EnvCipher::encrypt`].
+    pub fn decrypt(&self, ciphertext: &[u8]) -> DeployResult<HashMap<String, String>> {
+        let plaintext = age::decrypt(&self.identity, ciphertext).map_err(|e| {
+            DeployError::Environment(format!(
+                "Failed to decrypt {} (wrong age identity?): {}",
+                ENCRYPTED_ENV_FILE, e
+            ))
+        })?;
+        serde_json::from_slice(&plaintext).map_err(|e| {
+            DeployError::Environment(format!("Invalid {} content: {}", ENCRYPTED_ENV_FILE, e))
+        })
+    }
+}
+
+/// `path` with a leading `~` replaced by the home directory.
+pub(crate) fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use age::secrecy::ExposeSecret;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_env_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let key_path = temp_dir.path().join("key.txt");
+        let identity = x25519::Identity::generate();
+        std::fs::write(
+            &key_path,
+            format!(
+                "# created: 2024-01-01\n# public key: {}\n{}\n",
+                identity.to_public(),
+                identity.to_string().expose_secret()
+            ),
+        )
+        .unwrap();
+
+        let cipher = EnvCipher::load(&key_path).unwrap();
+        let env = HashMap::from([
+            ("DB_PASSWORD".to_string(), "s3cr3t value".to_string()),
+            ("API_KEY".to_string(), "abc".to_string()),
+        ]);
+        let armored = cipher.encrypt(&env).unwrap();
+        assert!(armored.starts_with("-----BEGIN AGE ENCRYPTED FILE-----"));
+        assert!(!armored.contains("s3cr3t"));
+        assert_eq!(cipher.decrypt(armored.as_bytes()).unwrap(), env);
+
+        let other = TempDir::new().unwrap().path().join("missing.txt");
+        assert!(EnvCipher::load(&other).is_err());
+    }
+}
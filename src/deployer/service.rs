@@ -1,17 +1,63 @@
 use super::{
-    docker_manager::{DockerManager, HealthCheckResult, SshDockerManager},
-    firewall::{PortConfig, Protocol, UfwManager},
-    sync::{EnvFileManager, FileSync, SyncPlan},
+    check_removable_dir, compose_command, compose_project_name,
+    cron::{cron_file_name, CronManager},
+    default_remote_dir,
+    docker_manager::{
+        build_subcommand, check_remote_os, is_rootless, with_services, DockerManager,
+        HealthCheckResult, ServiceStatus, SshDockerManager,
+    },
+    doctor::{HostDoctor, Severity},
+    dry_run::{localize_config, DryRunReport, ProjectDiff},
+    firewall::{firewall_manager, PortConfig},
+    format_bytes,
+    gc::{self, GcReport},
+    health::{DependencyGraph, HealthTracker},
+    hooks::{describe_hook, run_hooks, HookContext},
+    images::{
+        diff_images, project_images, retag_images, service_images, ProjectImage, ServiceImage,
+    },
+    networks::{project_networks, ProjectNetwork},
+    packages::{ensure_host_packages, missing_host_packages},
+    parse_du_sizes,
+    preflight::deploy_checks,
+    registry::{self, DeployedProject},
+    report::DeployReport,
+    restart::{restart_override, RESTART_OVERRIDE_FILE},
+    secrets::{EnvCipher, ENCRYPTED_ENV_FILE},
+    stacks::{
+        list_stack_containers, port_collisions, project_name_conflict, remove_aborted_resources,
+        shared_images,
+    },
+    state::{
+        discover_remote_project, profiles_env, DeployRecord, PendingDeploy, RemoteState,
+        RollbackPoint, STATE_FILE,
+    },
+    support::{self, secret_values, BundleEntry, BundleTarget},
+    sync::{
+        backup_file, discard_previous, edit_report, env_base_path, find_remote_edits,
+        keep_backups_as_previous, restore_backups, restore_previous, sha256_file, unified_diff,
+        DiffLine, EnvFileManager, EnvTemplate, FileChange, FileDiff, FileSync, PlannedFile,
+        Redaction, SyncPlan, REMOTE_ENV_FILE,
+    },
+    systemd::{SystemdManager, SystemdUnit},
+    tuning::HostTuning,
     types::{
-        ComposeExec, DeployError, DeployResult, DeployerEvent, DeploymentConfig, DeploymentStatus,
+        ComposeExec, DeployError, DeployResult, DeployStage, DeployerEvent, DeploymentConfig,
+        DeploymentStatus, EnvEditPolicy, HealthCheckSettings, ServiceDetail,
     },
-    DCD_ENV_FILE,
+    wait::wait_until_ready,
+    BACKUP_SUFFIX, DCD_ENV_FILE, PREVIOUS_SUFFIX,
 };
-use std::collections::HashSet;
-use std::path::PathBuf;
-use std::time::Duration;
+use crate::config::{HookStage, WaitKind, WaitStrategy};
+use crate::executor::{export_prefix, shell_quote, Redactor};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
 use tokio::sync::mpsc;
 
+/// How long the smoke test URL from a service's hints may take to answer.
+const SMOKE_TEST_TIMEOUT: &str = "30s";
+
 pub struct Deployer<'a> {
     config: DeploymentConfig,
     executor: &'a mut (dyn ComposeExec + Send),
@@ -20,10 +66,6 @@ pub struct Deployer<'a> {
 }
 
 impl<'a> Deployer<'a> {
-    const HEALTH_CHECK_RETRIES: u32 = 5;
-    const MAX_STARTING_ATTEMPTS: u32 = 15;
-    const HEALTH_CHECK_DELAY: Duration = Duration::from_secs(10);
-
     pub fn new(
         config: DeploymentConfig,
         executor: &'a mut (dyn ComposeExec + Send),
@@ -39,14 +81,7 @@ impl<'a> Deployer<'a> {
                 user_path.clone()
             }
             None => {
-                // Extract project name from config.project_dir
-                let project_name = config.project_dir.file_name()
-                    .map(|name| name.to_string_lossy().into_owned())
-                    .unwrap_or_else(|| {
-                        tracing::warn!("Could not determine project directory name from '{}', using 'default_project'", config.project_dir.display());
-                        "default_project".to_string() // Fallback name
-                    });
-                let default_path = PathBuf::from(format!("/opt/{}", project_name));
+                let default_path = default_remote_dir(&config.project_dir);
                 tracing::info!(
                     "No --workdir provided. Using default remote directory: {}",
                     default_path.display()
@@ -70,7 +105,12 @@ impl<'a> Deployer<'a> {
 
     /// Helper to send progress events if a sender exists.
     async fn send_event(&self, event: DeployerEvent) {
-        if let Some(sender) = &self.progress_sender {
+        Self::send_event_to(&self.progress_sender, event).await;
+    }
+
+    /// Like [`Self::send_event`], for when `self` is borrowed elsewhere.
+    async fn send_event_to(sender: &Option<mpsc::Sender<DeployerEvent>>, event: DeployerEvent) {
+        if let Some(sender) = sender {
             if let Err(e) = sender.send(event.clone()).await {
                 // Log error if sending fails (receiver likely dropped in CLI)
                 tracing::warn!("Failed to send progress event: {}", e);
@@ -78,223 +118,1292 @@ impl<'a> Deployer<'a> {
         }
     }
 
-    /// Main deployment method
-    pub async fn deploy(&mut self) -> DeployResult<DeploymentStatus> {
-        let mut status = DeploymentStatus::new();
+    /// What hooks of this deployment run with.
+    fn hook_context(&self) -> HookContext {
+        let (compose_files, env_files) = self.remote_compose_files();
+        HookContext {
+            project_dir: self.config.project_dir.clone(),
+            remote_dir: self.resolved_remote_dir.clone(),
+            compose: compose_command(&self.resolved_remote_dir, &compose_files, &env_files, "")
+                .trim_end()
+                .to_string(),
+            compose_env: self.deploy_compose_env(),
+        }
+    }
+
+    /// Run the hooks configured for `stage`.
+    async fn run_hooks(&mut self, stage: HookStage) -> DeployResult<()> {
+        if self.config.hooks.get(stage).is_empty() {
+            return Ok(());
+        }
+        let context = self.hook_context();
+        run_hooks(
+            self.executor,
+            &self.config.hooks,
+            stage,
+            &context,
+            &self.progress_sender,
+        )
+        .await
+    }
+
+    /// Remote compose and env files (basenames), including the generated
+    /// .env.dcd if present.
+    fn remote_compose_files(&self) -> (Vec<PathBuf>, Vec<PathBuf>) {
+        let mut compose_files = self
+            .config
+            .compose_files
+            .iter()
+            .map(|p| PathBuf::from(p.file_name().expect("Invalid compose file path")))
+            .collect::<Vec<PathBuf>>();
+        let mut env_files = self
+            .config
+            .env_files
+            .iter()
+            .map(|p| PathBuf::from(p.file_name().expect("Invalid env file path")))
+            .filter(|p| !self.uses_env_template() || p.as_os_str() != REMOTE_ENV_FILE)
+            .collect::<Vec<PathBuf>>();
+        // The local copy is removed after deploy, so go by the variables instead.
+        // An encrypted env is passed to compose directly instead.
+        if !self.config.consumed_env.is_empty() && self.config.age_identity.is_none() {
+            env_files.push(PathBuf::from(DCD_ENV_FILE));
+        }
+        // Last, so values edited on the server win
+        if self.uses_env_template() {
+            env_files.push(PathBuf::from(REMOTE_ENV_FILE));
+        }
+        if self.restart_override().is_some() {
+            compose_files.push(PathBuf::from(RESTART_OVERRIDE_FILE));
+        }
+        (compose_files, env_files)
+    }
+
+    /// Whether the remote .env is generated from a template instead of synced.
+    fn uses_env_template(&self) -> bool {
+        self.config.env_template.is_some()
+    }
+
+    /// Contents of the restart policy override, when one is needed.
+    fn restart_override(&self) -> Option<String> {
+        let policy = self.config.restart_policy.as_deref()?;
+        if self.config.services_without_restart.is_empty() {
+            return None;
+        }
+        Some(restart_override(
+            policy,
+            &self.config.services_without_restart,
+        ))
+    }
+
+    /// Environment for compose commands against an existing deployment: the
+    /// profiles recorded at deploy time, or the local ones if nothing was recorded.
+    async fn deployed_compose_env(&mut self) -> DeployResult<HashMap<String, String>> {
+        let state = RemoteState::load(self.executor, &self.resolved_remote_dir).await?;
+        let profiles = match &state {
+            Some(state) => state.profiles.clone(),
+            None => self.config.profiles.clone(),
+        };
+        let mut env = match state {
+            Some(state) if state.encrypted_env => self.decrypt_remote_env().await?,
+            _ => HashMap::new(),
+        };
+        env.extend(profiles_env(&profiles));
+        Ok(env)
+    }
+
+    /// Download and decrypt the env of an encrypted deployment. Values only
+    /// ever exist in the environment of the compose commands on the server.
+    async fn decrypt_remote_env(&mut self) -> DeployResult<HashMap<String, String>> {
+        let identity = self.config.age_identity.clone().ok_or_else(|| {
+            DeployError::Configuration(
+                "The deployed env is encrypted; set age_identity in dcd.toml to use it".into(),
+            )
+        })?;
+        let cipher = EnvCipher::load(&identity)?;
+        let path = self.resolved_remote_dir.join(ENCRYPTED_ENV_FILE);
+        let result = self
+            .executor
+            .execute_command(&format!("cat {}", shell_quote(&path.to_string_lossy())))
+            .await
+            .map_err(|e| {
+                DeployError::Environment(format!("Failed to read {}: {}", path.display(), e))
+            })?;
+        if !result.is_success() {
+            return Err(DeployError::Environment(format!(
+                "Failed to read {}: {}",
+                path.display(),
+                result.output.to_stderr_string()?.trim()
+            )));
+        }
+        cipher.decrypt(result.output.to_stdout_string()?.as_bytes())
+    }
+
+    /// Environment for compose commands run by `up`: the active profiles plus,
+    /// for an encrypted deployment, the variables themselves.
+    fn deploy_compose_env(&self) -> HashMap<String, String> {
+        let mut env = HashMap::new();
+        if self.config.age_identity.is_some() {
+            env.extend(self.config.consumed_env.clone());
+        }
+        env.extend(profiles_env(&self.config.profiles));
+        env
+    }
+
+    /// Replace the local compose context with the project deployed in the
+    /// remote directory, for commands run without (or despite) a local checkout.
+    pub async fn use_remote_project(&mut self) -> DeployResult<()> {
+        let project = discover_remote_project(self.executor, &self.resolved_remote_dir).await?;
+        self.config.compose_files = project.compose_files;
+        self.config.env_files = project.env_files;
+        self.config.profiles = project.profiles;
+        // The recorded env files already include .env.dcd when it was deployed
+        self.config.consumed_env.clear();
+        Ok(())
+    }
+
+    /// Record in the remote state that `paths` are about to be overwritten.
+    async fn mark_pending(&mut self, paths: Vec<PathBuf>) -> DeployResult<()> {
+        let mut state = RemoteState::load(self.executor, &self.resolved_remote_dir)
+            .await?
+            .unwrap_or_default();
+        let previous = RollbackPoint::from_state(&state);
+        let pending = state.pending.get_or_insert_with(|| PendingDeploy {
+            paths: Vec::new(),
+            previous,
+        });
+        if !pending.paths.is_empty() {
+            tracing::warn!(
+                "A previous deployment to {} did not finish; keeping its backups",
+                self.resolved_remote_dir.display()
+            );
+        }
+        for path in paths {
+            if !pending.paths.contains(&path) {
+                pending.paths.push(path);
+            }
+        }
+        state.save(self.executor, &self.resolved_remote_dir).await
+    }
+
+    /// Drop the in-progress marker of a finished deployment and keep the
+    /// files it replaced as the rollback point, replacing the older one.
+    async fn finish_pending(&mut self) -> DeployResult<()> {
+        let Some(mut state) = RemoteState::load(self.executor, &self.resolved_remote_dir).await?
+        else {
+            return Ok(());
+        };
+        let Some(pending) = state.pending.take() else {
+            return Ok(());
+        };
+        let mut stale = pending.paths.clone();
+        if let Some(older) = state.rollback.take() {
+            stale.extend(older.paths);
+        }
+        discard_previous(self.executor, &stale).await?;
+        let kept = keep_backups_as_previous(self.executor, &pending.paths).await?;
+        tracing::debug!("Kept {} file(s) for rollback", kept.len());
+        state.rollback = pending.previous.map(|previous| RollbackPoint {
+            paths: pending.paths,
+            ..previous
+        });
+        state.save(self.executor, &self.resolved_remote_dir).await
+    }
+
+    /// Drop the in-progress marker without touching any files.
+    async fn clear_pending(&mut self) -> DeployResult<()> {
+        let Some(mut state) = RemoteState::load(self.executor, &self.resolved_remote_dir).await?
+        else {
+            return Ok(());
+        };
+        if state.pending.take().is_some() {
+            state.save(self.executor, &self.resolved_remote_dir).await?;
+        }
+        Ok(())
+    }
+
+    /// Remember who deployed what and when, for `dcd status`.
+    async fn record_deploy(&mut self) -> DeployResult<()> {
+        let mut state = RemoteState::load(self.executor, &self.resolved_remote_dir)
+            .await?
+            .unwrap_or_default();
+        let mut record = DeployRecord::current(&self.config.project_dir);
+        for file in &self.config.compose_files {
+            if let Some(name) = file.file_name() {
+                record
+                    .compose_hashes
+                    .insert(PathBuf::from(name), sha256_file(file).await?);
+            }
+        }
+        for pair in self.sync_plan()?.env_files {
+            if let Some(name) = pair.remote_path.file_name() {
+                record
+                    .env_hashes
+                    .insert(PathBuf::from(name), sha256_file(&pair.local_path).await?);
+            }
+        }
+        state.project = compose_project_name(&self.resolved_remote_dir);
+        state.last_deploy = Some(record);
+        state.save(self.executor, &self.resolved_remote_dir).await?;
+        registry::register(self.executor, &self.resolved_remote_dir).await;
+        registry::claim_ports(
+            self.executor,
+            &self.resolved_remote_dir,
+            &state.project,
+            &self.config.exposed_ports,
+        )
+        .await;
+        Ok(())
+    }
+
+    /// Roll back an interrupted deployment: restore the files it replaced and
+    /// remove containers and networks it created but never started.
+    pub async fn cleanup(&mut self) -> DeployResult<DeploymentStatus> {
+        let pending = match RemoteState::load(self.executor, &self.resolved_remote_dir).await? {
+            Some(RemoteState {
+                pending: Some(pending),
+                ..
+            }) => pending,
+            _ => {
+                return Ok(DeploymentStatus::with_message(
+                    "No interrupted deployment found, nothing to clean up.",
+                ))
+            }
+        };
 
-        tracing::info!("🚀 Starting deployment process...");
         self.send_event(DeployerEvent::StepStarted(
-            "Starting Deployment".to_string(),
+            "Restoring backed up files".to_string(),
         ))
         .await;
+        let restored = restore_backups(self.executor, &pending.paths).await?;
+        for path in &restored {
+            tracing::info!("Restored {}", path.display());
+        }
 
-        // Step 1: Prepare environment
-        tracing::info!("Step 1: Preparing remote environment...");
         self.send_event(DeployerEvent::StepStarted(
-            "Preparing environment".to_string(),
+            "Removing leftover containers and networks".to_string(),
         ))
         .await;
-        if let Err(e) = self.prepare_environment(&mut status).await {
-            self.send_event(DeployerEvent::StepFailed(
-                "Preparing environment".to_string(),
-                e.to_string(),
-            ))
+        let project = compose_project_name(&self.resolved_remote_dir);
+        remove_aborted_resources(self.executor, &project).await?;
+
+        // Backups are gone now; the rollback point stays as it was
+        self.clear_pending().await?;
+        self.send_event(DeployerEvent::StepCompleted("Cleanup complete".to_string()))
             .await;
-            return Err(e);
-        }
-        self.send_event(DeployerEvent::StepCompleted(
-            "Preparing environment".to_string(),
+
+        let mut status = DeploymentStatus::with_message(format!(
+            "Restored {} file(s) and removed leftovers of the interrupted deployment.",
+            restored.len()
+        ));
+        status.files_changed = !restored.is_empty();
+        Ok(status)
+    }
+
+    /// Return to the deployment before the last one, or to the one a failed
+    /// deployment replaced: put its files back, re-tag its images and
+    /// recreate the services.
+    pub async fn rollback(&mut self) -> DeployResult<DeploymentStatus> {
+        let mut state = RemoteState::load(self.executor, &self.resolved_remote_dir)
+            .await?
+            .unwrap_or_default();
+        let target = match (&state.pending, &state.rollback) {
+            (Some(pending), _) => pending.previous.clone().ok_or_else(|| {
+                DeployError::Deployment(
+                    "The interrupted deployment was the first one; use `dcd cleanup` instead"
+                        .into(),
+                )
+            })?,
+            (None, Some(point)) => point.clone(),
+            (None, None) => {
+                return Err(DeployError::Deployment(format!(
+                    "No earlier deployment recorded in {}, nothing to roll back to",
+                    self.resolved_remote_dir.display()
+                )))
+            }
+        };
+
+        self.send_event(DeployerEvent::StepStarted(
+            "Restoring previous files".to_string(),
         ))
         .await;
+        let restored = match state.pending.take() {
+            // The backups of a failed deployment hold the version it replaced
+            Some(pending) => restore_backups(self.executor, &pending.paths).await?,
+            None => {
+                state.rollback = None;
+                restore_previous(self.executor, &target.paths).await?
+            }
+        };
+        for path in &restored {
+            tracing::info!("Restored {}", path.display());
+        }
+        let record = target.last_deploy.clone();
+        target.apply_to(&mut state);
+        state.save(self.executor, &self.resolved_remote_dir).await?;
+
+        let missing = retag_images(self.executor, &state.images).await?;
+        if !missing.is_empty() {
+            tracing::warn!(
+                "Previous images of {} are no longer on the host; they keep their current image",
+                missing.join(", ")
+            );
+        }
 
-        // Step 2: Sync files
-        tracing::info!("Step 2: Synchronizing project files...");
         self.send_event(DeployerEvent::StepStarted(
-            "Synchronizing files".to_string(),
+            "Recreating services".to_string(),
         ))
         .await;
-        if let Err(e) = self.sync_files(&mut status).await {
-            self.send_event(DeployerEvent::StepFailed(
-                "Synchronizing files".to_string(),
-                e.to_string(),
-            ))
+        let compose_env = self.deployed_compose_env().await?;
+        let mut docker_manager = SshDockerManager::new(
+            self.executor,
+            self.resolved_remote_dir.clone(),
+            state.compose_files.clone(),
+            state.env_files.clone(),
+        )
+        .await?
+        .with_env(compose_env)
+        .with_progress(self.progress_sender.clone());
+        docker_manager.compose_up(&[]).await?;
+
+        let mut status = DeploymentStatus::new();
+        if self.config.runs_stage(DeployStage::Health) {
+            let dependency_graph = Self::dependency_graph(&self.config);
+            Self::wait_for_healthy(
+                &mut docker_manager,
+                &dependency_graph,
+                &[],
+                self.config.health_check,
+                self.progress_sender.clone(),
+                &mut status,
+            )
             .await;
-            return Err(e);
         }
         self.send_event(DeployerEvent::StepCompleted(
-            "Synchronizing files".to_string(),
+            "Rollback complete".to_string(),
         ))
         .await;
 
-        // Step 3: Configure firewall
-        tracing::info!("Step 3: Configuring firewall (UFW)...");
+        status.files_changed = !restored.is_empty();
+        status.last_deploy = record;
+        status.message = format!(
+            "Restored {} file(s) and recreated the services.",
+            restored.len()
+        );
+        Ok(status)
+    }
+
+    /// Remove dcd's copies on the server that no deployment needs any more,
+    /// dropping the rollback point once it is older than `rollback_retention`.
+    /// With `dry_run` only reports what would be removed.
+    pub async fn gc(
+        &mut self,
+        rollback_retention: Duration,
+        dry_run: bool,
+    ) -> DeployResult<GcReport> {
+        let mut state = RemoteState::load(self.executor, &self.resolved_remote_dir)
+            .await?
+            .unwrap_or_default();
+        if state.pending.is_some() {
+            return Err(DeployError::Deployment(
+                "A deployment did not finish; its backups are still needed. Run `dcd cleanup` \
+                 or `dcd up` first"
+                    .into(),
+            ));
+        }
+
         self.send_event(DeployerEvent::StepStarted(
-            "Configuring firewall".to_string(),
+            "Looking for stale copies".to_string(),
         ))
         .await;
-        if let Err(e) = self.configure_firewall(&mut status).await {
-            self.send_event(DeployerEvent::StepFailed(
-                "Configuring firewall".to_string(),
-                e.to_string(),
-            ))
+        let project = compose_project_name(&self.resolved_remote_dir);
+        let copies = gc::list_copies(self.executor, &self.resolved_remote_dir, &project).await?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        let rollback_expired = gc::rollback_expired(&state, now, rollback_retention);
+        let removed = gc::select_garbage(&copies, &state, rollback_expired);
+
+        if !dry_run {
+            self.send_event(DeployerEvent::StepStarted(format!(
+                "Removing {} file(s)",
+                removed.len()
+            )))
             .await;
-            return Err(e);
+            // Forget the rollback point first, so it never refers to removed copies
+            if rollback_expired {
+                state.rollback = None;
+                state.save(self.executor, &self.resolved_remote_dir).await?;
+            }
+            gc::remove_files(self.executor, &removed).await?;
         }
-        self.send_event(DeployerEvent::StepCompleted(
-            "Configuring firewall".to_string(),
+        self.send_event(DeployerEvent::StepCompleted("Cleanup complete".to_string()))
+            .await;
+
+        Ok(GcReport {
+            removed,
+            rollback_expired,
+            dry_run,
+        })
+    }
+
+    /// Push compose, env and referenced files without touching the running
+    /// services, for config that containers reload on their own.
+    pub async fn sync_only(&mut self) -> DeployResult<DeploymentStatus> {
+        let mut status = DeploymentStatus::new();
+        self.inject_secrets().await?;
+        self.send_event(DeployerEvent::StepStarted(
+            "Synchronizing files".to_string(),
         ))
         .await;
-
-        // Step 4: Deploy services
-        tracing::info!("Step 4: Deploying services using Docker Compose...");
-        self.send_event(DeployerEvent::StepStarted("Deploying services".to_string()))
-            .await;
-        if let Err(e) = self.deploy_services(&mut status).await {
+        if let Err(e) = self.sync_files(&mut status).await {
             self.send_event(DeployerEvent::StepFailed(
-                "Deploying services".to_string(),
+                "Synchronizing files".to_string(),
                 e.to_string(),
             ))
             .await;
             return Err(e);
         }
+        // Nothing gets restarted, so the synced files are final right away
+        self.finish_pending().await?;
         self.send_event(DeployerEvent::StepCompleted(
-            "Deploying services".to_string(),
+            "Synchronizing files".to_string(),
         ))
         .await;
 
+        EnvFileManager::new(self.config.consumed_env.clone(), &self.config.project_dir)
+            .remove_dcd_env()
+            .await?;
         Ok(status)
     }
 
-    pub async fn destroy(
-        &mut self,
-        remove_volumes: bool,
-        remove_images: bool,
-        force: bool,
-    ) -> DeployResult<DeploymentStatus> {
+    /// Main deployment method
+    pub async fn deploy(&mut self) -> DeployResult<DeploymentStatus> {
         let mut status = DeploymentStatus::new();
+
+        // The boot-time unit and cron jobs read .env.dcd on the server, which an
+        // encrypted deployment does not keep
+        if self.config.age_identity.is_some()
+            && (self.config.systemd_unit || !self.config.cron_jobs.is_empty())
+        {
+            return Err(DeployError::Configuration(
+                "age_identity cannot be combined with --systemd or cron jobs; they read the env from the server".into(),
+            ));
+        }
+
+        tracing::info!("🚀 Starting deployment process...");
         self.send_event(DeployerEvent::StepStarted(
-            "Initializing destruction...".to_string(),
+            "Starting Deployment".to_string(),
         ))
         .await;
-        tracing::info!("🔥 Starting destruction process...");
-
-        // Clone the sender before creating the manager which borrows self.executor mutably
-        let cloned_sender = self.progress_sender.clone();
-
-        // Create Docker manager
-        tracing::debug!("Initializing Docker manager for destruction.");
-        // Build list of remote compose and env files (basenames)
-        let compose_files = self
-            .config
-            .compose_files
-            .iter()
-            .map(|p| PathBuf::from(p.file_name().expect("Invalid compose file path")))
-            .collect::<Vec<PathBuf>>();
-        let mut env_files = self
-            .config
-            .env_files
-            .iter()
-            .map(|p| PathBuf::from(p.file_name().expect("Invalid env file path")))
-            .collect::<Vec<PathBuf>>();
-        // Include generated .env.dcd if present
-        let dcd_path = self.config.project_dir.join(DCD_ENV_FILE);
-        if dcd_path.exists() {
-            env_files.push(PathBuf::from(DCD_ENV_FILE));
+        // Before anything is changed: installers and host setup assume Linux
+        if !self.config.on_local_machine {
+            if let Err(e) = check_remote_os(self.executor).await {
+                self.send_event(DeployerEvent::StepFailed(
+                    "Checking the remote OS".to_string(),
+                    e.to_string(),
+                ))
+                .await;
+                return Err(e.into());
+            }
         }
-        let mut docker_manager = SshDockerManager::new(
-            self.executor,
-            self.resolved_remote_dir.clone(),
-            compose_files,
-            env_files,
-        )
-        .await?;
-
-        // Check if any services are running
-        tracing::info!("Checking for running services...");
-        let services_running = docker_manager.has_running_services().await?;
-
-        // If services are running and force is false, return error
-        if services_running && !force {
-            tracing::warn!("Services are running. Destruction aborted. Use --force to override.");
-            status.message = "Services are still running. Use --force to destroy anyway.".into();
-            return Err(DeployError::Deployment(status.message.clone()));
-        } else if services_running && force {
-            tracing::info!(
-                "Force flag enabled. Proceeding with destruction despite running services."
-            );
+        self.preflight().await?;
+        if self.config.runs_stage(DeployStage::Up) {
+            self.check_port_claims().await?;
         }
+        self.inject_secrets().await?;
 
-        // Stop and remove containers
-        tracing::info!(
-            "Stopping services, removing containers{}{}...",
-            if remove_volumes { ", volumes" } else { "" },
-            if remove_images { ", images" } else { "" }
-        );
-        // Use the cloned sender
-        if let Some(sender) = &cloned_sender {
-            let _ = sender
-                .send(DeployerEvent::StepStarted(
-                    "Stopping and removing containers/networks...".to_string(),
+        // Step 1: Prepare environment
+        if self.config.runs_stage(DeployStage::Env) {
+            tracing::info!("Step 1: Preparing remote environment...");
+            let started = Instant::now();
+            self.send_event(DeployerEvent::StepStarted(
+                "Preparing environment".to_string(),
+            ))
+            .await;
+            if let Err(e) = self.prepare_environment(&mut status).await {
+                self.send_event(DeployerEvent::StepFailed(
+                    "Preparing environment".to_string(),
+                    e.to_string(),
                 ))
                 .await;
+                return Err(e);
+            }
+            self.send_event(DeployerEvent::StepCompleted(
+                "Preparing environment".to_string(),
+            ))
+            .await;
+            status.record_stage(DeployStage::Env, started);
         }
-        docker_manager
-            .compose_down(remove_volumes, remove_images)
-            .await?;
-        if let Some(sender) = &cloned_sender {
-            let _ = sender
-                .send(DeployerEvent::StepCompleted(
-                    "Containers and networks removed.".to_string(),
+
+        // Step 2: Sync files
+        if self.config.runs_stage(DeployStage::Sync) {
+            let started = Instant::now();
+            self.run_hooks(HookStage::PreSync).await?;
+            tracing::info!("Step 2: Synchronizing project files...");
+            self.send_event(DeployerEvent::StepStarted(
+                "Synchronizing files".to_string(),
+            ))
+            .await;
+            if let Err(e) = self.sync_files(&mut status).await {
+                self.send_event(DeployerEvent::StepFailed(
+                    "Synchronizing files".to_string(),
+                    e.to_string(),
                 ))
                 .await;
+                return Err(e);
+            }
+            self.send_event(DeployerEvent::StepCompleted(
+                "Synchronizing files".to_string(),
+            ))
+            .await;
+            self.run_hooks(HookStage::PostSync).await?;
+            status.record_stage(DeployStage::Sync, started);
         }
 
-        let mut removal_details = Vec::new();
-        if remove_volumes {
-            removal_details.push("volumes");
-        }
-        if remove_images {
-            removal_details.push("images");
-        }
-
-        // If removing volumes, also remove the remote project directory
-        if remove_volumes {
-            let remote_dir_str = self.resolved_remote_dir.display().to_string();
-            // Use the cloned sender
-            if let Some(sender) = &cloned_sender {
-                let _ = sender
-                    .send(DeployerEvent::StepStarted(format!(
-                        "Removing remote directory: {}",
-                        remote_dir_str
-                    )))
+        // Step 3: Configure firewall
+        let rootless = is_rootless(self.executor);
+        if self.config.runs_stage(DeployStage::Firewall) && rootless {
+            tracing::info!(
+                "Skipping firewall configuration: it needs root, and docker runs rootless."
+            );
+        } else if self.config.runs_stage(DeployStage::Firewall) {
+            tracing::info!("Step 3: Configuring firewall (UFW)...");
+            let started = Instant::now();
+            self.send_event(DeployerEvent::StepStarted(
+                "Configuring firewall".to_string(),
+            ))
+            .await;
+            if let Err(e) = self.configure_firewall(&mut status).await {
+                self.send_event(DeployerEvent::StepFailed(
+                    "Configuring firewall".to_string(),
+                    e.to_string(),
+                ))
+                .await;
+                return Err(e);
+            }
+            self.send_event(DeployerEvent::StepCompleted(
+                "Configuring firewall".to_string(),
+            ))
+            .await;
+            status.record_stage(DeployStage::Firewall, started);
+        }
+
+        // Step 4: Deploy services
+        let service_stages = [DeployStage::Pull, DeployStage::Up, DeployStage::Health];
+        if service_stages
+            .iter()
+            .any(|stage| self.config.runs_stage(*stage))
+        {
+            tracing::info!("Step 4: Deploying services using Docker Compose...");
+            self.send_event(DeployerEvent::StepStarted("Deploying services".to_string()))
+                .await;
+            if let Err(e) = self.deploy_services(&mut status).await {
+                self.send_event(DeployerEvent::StepFailed(
+                    "Deploying services".to_string(),
+                    e.to_string(),
+                ))
+                .await;
+                return Err(e);
+            }
+            self.send_event(DeployerEvent::StepCompleted(
+                "Deploying services".to_string(),
+            ))
+            .await;
+        }
+
+        // Step 5: Sync cron jobs declared via labels
+        if self.config.runs_stage(DeployStage::Up) {
+            if let Err(e) = self.sync_cron_jobs().await {
+                self.send_event(DeployerEvent::StepFailed(
+                    "Installing cron jobs".to_string(),
+                    e.to_string(),
+                ))
+                .await;
+                return Err(e);
+            }
+
+            // Step 6: Install systemd unit (optional)
+            if self.config.systemd_unit && rootless {
+                tracing::warn!(
+                    "Skipping the systemd unit: it needs root; enable lingering for the rootless docker daemon instead (loginctl enable-linger)."
+                );
+            } else if self.config.systemd_unit {
+                tracing::info!("Step 6: Installing systemd unit for auto-start...");
+                self.send_event(DeployerEvent::StepStarted(
+                    "Installing systemd unit".to_string(),
+                ))
+                .await;
+                if let Err(e) = self.install_systemd_unit().await {
+                    self.send_event(DeployerEvent::StepFailed(
+                        "Installing systemd unit".to_string(),
+                        e.to_string(),
+                    ))
                     .await;
+                    return Err(e);
+                }
+                self.send_event(DeployerEvent::StepCompleted(
+                    "Installing systemd unit".to_string(),
+                ))
+                .await;
             }
-            tracing::info!("Removing remote project directory: {}", remote_dir_str);
-            let rm_cmd = format!("rm -rf {}", remote_dir_str);
-            let rm_result = self.executor.execute_command(&rm_cmd).await.map_err(|e| {
-                DeployError::Deployment(format!("Failed to remove directory: {}", e))
-            })?;
-            if !rm_result.is_success() {
-                let error_msg = rm_result.output.to_stderr_string()?;
-                tracing::error!(
-                    "Failed to remove remote directory '{}': {}",
-                    remote_dir_str,
-                    error_msg
+        }
+
+        // Only a deployment that came up keeps its files; otherwise the
+        // backups stay around for `dcd cleanup`
+        if status.services_healthy || !self.config.runs_stage(DeployStage::Health) {
+            self.finish_pending().await?;
+            if self.config.runs_stage(DeployStage::Up) {
+                self.record_deploy().await?;
+            }
+        }
+
+        // Clean up a local .env.dcd left behind by older versions; it holds secrets
+        EnvFileManager::new(self.config.consumed_env.clone(), &self.config.project_dir)
+            .remove_dcd_env()
+            .await?;
+
+        Ok(status)
+    }
+
+    /// How each file a sync would write compares to its remote copy, the
+    /// generated ones (restart override, env) included.
+    async fn planned_files(&mut self) -> DeployResult<Vec<PlannedFile>> {
+        let sync_plan = self.sync_plan()?;
+        let override_content = self.restart_override();
+        let env_content =
+            EnvFileManager::new(self.config.consumed_env.clone(), &self.config.project_dir)
+                .render_dcd_env();
+        let encrypted = self.config.age_identity.is_some();
+        let remote_dir = self.resolved_remote_dir.clone();
+        let template_content = match &self.config.env_template {
+            Some(template) => Some(
+                EnvTemplate::load(template)
+                    .await?
+                    .render(&self.config.consumed_env),
+            ),
+            None => None,
+        };
+
+        let mut file_sync = FileSync::new(self.executor, remote_dir.clone())
+            .with_include_hidden(self.config.include_hidden)
+            .with_progress(self.progress_sender.clone());
+        let mut files = file_sync.plan_files(&sync_plan).await?;
+        if let Some(content) = override_content {
+            let remote_path = remote_dir.join(RESTART_OVERRIDE_FILE);
+            files.push(
+                file_sync
+                    .plan_generated(remote_path, Some(content.as_bytes()))
+                    .await?,
+            );
+        }
+        if encrypted {
+            // Encrypted anew on every deploy
+            let remote_path = remote_dir.join(ENCRYPTED_ENV_FILE);
+            files.push(file_sync.plan_generated(remote_path, None).await?);
+        } else if let Some(content) = env_content {
+            let remote_path = remote_dir.join(DCD_ENV_FILE);
+            files.push(
+                file_sync
+                    .plan_generated(remote_path, Some(content.as_bytes()))
+                    .await?,
+            );
+        }
+        if let Some(content) = template_content {
+            let remote_path = remote_dir.join(REMOTE_ENV_FILE);
+            let planned = file_sync
+                .plan_generated(remote_path, Some(content.as_bytes()))
+                .await?;
+            // An existing .env is only replaced on request
+            if self.config.force_env || planned.change == FileChange::New {
+                files.push(planned);
+            }
+        }
+        Ok(files)
+    }
+
+    /// How the local project differs from the deployment in the remote
+    /// directory: the files a deploy would upload, line diffs of the compose
+    /// and env files it would overwrite, and the compose config `rendered`
+    /// locally against the one of the deployment. Only reads on the remote.
+    pub async fn diff_project(&mut self, rendered: &str) -> DeployResult<ProjectDiff> {
+        self.inject_secrets().await?;
+        let files = self.planned_files().await?;
+        let sync_plan = self.sync_plan()?;
+        let mut file_diffs = FileSync::new(self.executor, self.resolved_remote_dir.clone())
+            .diff_files(&sync_plan)
+            .await?;
+        file_diffs.extend(self.dcd_env_diff().await?);
+        let config = self.config_diff(rendered).await;
+        Ok(ProjectDiff {
+            remote_dir: self.resolved_remote_dir.clone(),
+            files,
+            file_diffs,
+            config,
+        })
+    }
+
+    /// Diff of the deployed .env.dcd against the one a deploy would write,
+    /// values redacted; `None` when unchanged, new or encrypted.
+    async fn dcd_env_diff(&mut self) -> DeployResult<Option<FileDiff>> {
+        if self.config.age_identity.is_some() {
+            return Ok(None);
+        }
+        let Some(content) =
+            EnvFileManager::new(self.config.consumed_env.clone(), &self.config.project_dir)
+                .render_dcd_env()
+        else {
+            return Ok(None);
+        };
+        let remote_path = self.resolved_remote_dir.join(DCD_ENV_FILE);
+        let result = self
+            .executor
+            .execute_command(&format!(
+                "cat {}",
+                shell_quote(&remote_path.to_string_lossy())
+            ))
+            .await
+            .map_err(|e| DeployError::FileSync(e.to_string()))?;
+        let deployed = result.output.to_stdout_string()?;
+        if !result.is_success() || deployed == content {
+            return Ok(None);
+        }
+        Ok(Some(FileDiff {
+            lines: unified_diff(&deployed, &content, Redaction::AllValues),
+            local_path: PathBuf::from(DCD_ENV_FILE),
+            remote_path,
+        }))
+    }
+
+    /// Diff of the deployment's `docker compose config` against `rendered`,
+    /// with secret values masked. Best effort: `None` when nothing is
+    /// deployed or the deployed config cannot be rendered.
+    async fn config_diff(&mut self, rendered: &str) -> Option<Vec<DiffLine>> {
+        let state = match RemoteState::load(self.executor, &self.resolved_remote_dir).await {
+            Ok(state) => state?,
+            Err(e) => {
+                tracing::warn!("Not comparing the compose config: {}", e);
+                return None;
+            }
+        };
+        let env = match self.deployed_compose_env().await {
+            Ok(env) => env,
+            Err(e) => {
+                tracing::warn!(
+                    "Rendering the deployed compose config without its env: {}",
+                    e
                 );
-                // Log error but don't fail the whole destroy operation, as compose down succeeded
-                status.message = format!("Deployment destroyed (containers{}, images{}), but failed to remove project directory: {}", 
-                    if remove_volumes {"+volumes"} else {""}, 
-                    if remove_images {"+images"} else {""}, 
-                    error_msg);
-            } else {
-                removal_details.push("project directory");
-                // Use the cloned sender
-                if let Some(sender) = &cloned_sender {
-                    let _ = sender
-                        .send(DeployerEvent::StepCompleted(
-                            "Remote directory removed.".to_string(),
-                        ))
-                        .await;
+                profiles_env(&state.profiles)
+            }
+        };
+        let cmd = compose_command(
+            &self.resolved_remote_dir,
+            &state.compose_files,
+            &state.env_files,
+            "config",
+        );
+        let deployed = match self.executor.execute_command_with_env(&cmd, &env).await {
+            Ok(result) if result.is_success() => result.output.to_stdout_string().ok()?,
+            Ok(result) => {
+                tracing::warn!(
+                    "Could not render the deployed compose config: {}",
+                    result.output.to_stderr_string().unwrap_or_default().trim()
+                );
+                return None;
+            }
+            Err(e) => {
+                tracing::warn!("Could not render the deployed compose config: {}", e);
+                return None;
+            }
+        };
+        let local = localize_config(
+            rendered,
+            &self.config.project_dir,
+            &self.resolved_remote_dir,
+            &compose_project_name(&self.resolved_remote_dir),
+        );
+        if deployed == local {
+            return Some(Vec::new());
+        }
+
+        let secret_names: Vec<String> = self.config.secrets.keys().cloned().collect();
+        let mut redactor = Redactor::default();
+        redactor.add(secret_values(&self.config.consumed_env, &secret_names));
+        redactor.add(secret_values(&env, &secret_names));
+        let mut lines: Vec<DiffLine> = unified_diff(&deployed, &local, Redaction::SecretKeys)
+            .into_iter()
+            .map(|line| line.map_text(|text| redactor.redact(text)))
+            .collect();
+        if lines.is_empty() {
+            lines.push(DiffLine::Hunk(
+                "(too large to compare line by line)".to_string(),
+            ));
+        }
+        Some(lines)
+    }
+
+    /// Summary of a deployment of this project to `target` that started at
+    /// `started` and ended with `result`.
+    pub fn report(
+        &self,
+        target: &str,
+        started: SystemTime,
+        result: Result<&DeploymentStatus, String>,
+    ) -> DeployReport {
+        DeployReport::new(
+            target,
+            &self.config,
+            &self.resolved_remote_dir,
+            started,
+            result,
+        )
+    }
+
+    /// Keep `report` in the remote project directory.
+    pub async fn upload_report(&mut self, report: &DeployReport) -> DeployResult<()> {
+        report
+            .upload(self.executor, &self.resolved_remote_dir)
+            .await
+    }
+
+    /// What [`Self::deploy`] would change on the server: the files it would
+    /// upload, the firewall rules it would add and every other step that
+    /// changes the host, in order. Only read-only commands are run on the remote.
+    pub async fn plan_deploy(&mut self) -> DeployResult<DryRunReport> {
+        let mut report = DryRunReport {
+            remote_dir: self.resolved_remote_dir.clone(),
+            ..Default::default()
+        };
+        if !self.config.on_local_machine {
+            check_remote_os(self.executor).await?;
+        }
+        self.inject_secrets().await?;
+        let rootless = is_rootless(self.executor);
+
+        let mut host_steps = Vec::new();
+        if self.config.runs_stage(DeployStage::Env) && !rootless {
+            let missing = missing_host_packages(self.executor, &self.config.host_packages).await?;
+            if !missing.is_empty() {
+                host_steps.push(format!("install host packages: {}", missing.join(", ")));
+            }
+            host_steps.extend(
+                HostTuning::new(self.executor, &self.resolved_remote_dir)
+                    .planned_changes(&self.config.sysctls, &self.config.ulimits)
+                    .await?,
+            );
+        }
+
+        let mut sync_steps = Vec::new();
+        if self.config.runs_stage(DeployStage::Sync) {
+            report.files = self.planned_files().await?;
+            sync_steps = self.planned_sync_steps(&report.files).await?;
+        }
+
+        if self.config.runs_stage(DeployStage::Firewall)
+            && !self.config.exposed_ports.is_empty()
+            && !rootless
+        {
+            let port_configs = self.port_configs();
+            let mut firewall = firewall_manager(self.executor).await?;
+            let backend = firewall.backend();
+            report.firewall_rules = firewall
+                .planned_rules(&port_configs)
+                .await
+                .iter()
+                .map(|rule| format!("{}: allow {}", backend, rule))
+                .collect();
+        }
+
+        let service_stages = [DeployStage::Pull, DeployStage::Up, DeployStage::Health];
+        let mut setup_steps = Vec::new();
+        if service_stages
+            .iter()
+            .any(|stage| self.config.runs_stage(*stage))
+        {
+            let docker = self
+                .executor
+                .execute_command("command -v docker")
+                .await
+                .map_err(|e| DeployError::Deployment(e.to_string()))?;
+            if !docker.is_success() {
+                setup_steps.push("install Docker and Docker Compose".to_string());
+            }
+        }
+        let starts_services = self.config.runs_stage(DeployStage::Up);
+        if starts_services {
+            if self.config.install_gpu_toolkit && !self.config.gpu_services.is_empty() {
+                setup_steps.push("install the NVIDIA container toolkit if missing".to_string());
+            }
+            for network in &self.config.external_networks {
+                setup_steps.push(format!("create external network '{}' if missing", network));
+            }
+        }
+
+        let mut finish_steps = Vec::new();
+        if starts_services {
+            finish_steps.push(format!("record the deployed images in {}", STATE_FILE));
+            if !self.config.cron_jobs.is_empty() {
+                finish_steps.push(format!(
+                    "install {} cron job(s) as /etc/cron.d/{}",
+                    self.config.cron_jobs.len(),
+                    cron_file_name(&self.resolved_remote_dir)
+                ));
+            }
+            if self.config.systemd_unit && !rootless {
+                let (compose_files, env_files) = self.remote_compose_files();
+                let unit =
+                    SystemdUnit::new(self.resolved_remote_dir.clone(), compose_files, env_files);
+                finish_steps.push(format!(
+                    "install and enable systemd unit {}",
+                    unit.path().display()
+                ));
+            }
+        }
+        if self.config.runs_stage(DeployStage::Sync) {
+            finish_steps.push(format!(
+                "keep the replaced files as *{} for rollback",
+                PREVIOUS_SUFFIX
+            ));
+        }
+        if starts_services {
+            finish_steps.push(format!("record the deploy in {}", STATE_FILE));
+        }
+
+        let (compose_files, env_files) = self.remote_compose_files();
+        let services = &self.config.services;
+        let remote_dir = &self.resolved_remote_dir;
+        let compose =
+            |subcommand: &str| compose_command(remote_dir, &compose_files, &env_files, subcommand);
+        let hooks = |stage: HookStage| {
+            self.config
+                .hooks
+                .get(stage)
+                .iter()
+                .map(move |hook| describe_hook(stage, hook))
+        };
+        report.commands.extend(host_steps);
+        if self.config.runs_stage(DeployStage::Sync) {
+            report.commands.extend(hooks(HookStage::PreSync));
+            report.commands.extend(sync_steps);
+            report.commands.extend(hooks(HookStage::PostSync));
+        }
+        report.commands.extend(setup_steps);
+        if self.config.runs_stage(DeployStage::Pull) {
+            report
+                .commands
+                .push(compose(&with_services("pull", services)));
+        }
+        if self.config.runs_stage(DeployStage::Up) {
+            report.commands.push(compose(&with_services(
+                build_subcommand(self.config.fresh_build),
+                services,
+            )));
+            report.commands.extend(hooks(HookStage::PreUp));
+            report.commands.push(compose(&with_services(
+                "up -d --remove-orphans --no-build",
+                services,
+            )));
+            report.commands.extend(hooks(HookStage::PostUp));
+        }
+        if self.config.runs_stage(DeployStage::Health) {
+            report.commands.extend(hooks(HookStage::PostHealthy));
+        }
+        report.commands.extend(finish_steps);
+        Ok(report)
+    }
+
+    /// The steps of a sync that change the server besides uploading `files`:
+    /// bookkeeping, backups, merges and the files dcd generates.
+    async fn planned_sync_steps(&mut self, files: &[PlannedFile]) -> DeployResult<Vec<String>> {
+        let mut sync_plan = self.sync_plan()?;
+        let env_pairs = sync_plan.env_files.clone();
+        let merged_env = self.resolve_env_edits(&mut sync_plan).await?;
+        let remote_dir = &self.resolved_remote_dir;
+        let shown = |path: &Path| {
+            path.strip_prefix(remote_dir)
+                .unwrap_or(path)
+                .display()
+                .to_string()
+        };
+
+        let mut steps = vec![format!("mark the deployment in progress in {}", STATE_FILE)];
+        let overwritten: Vec<String> = files
+            .iter()
+            .filter(|file| file.change == FileChange::Changed)
+            .map(|file| shown(&file.remote_path))
+            .collect();
+        if !overwritten.is_empty() {
+            steps.push(format!(
+                "back up the overwritten files as *{}: {}",
+                BACKUP_SUFFIX,
+                overwritten.join(", ")
+            ));
+        }
+        if let Some(owner) = &self.config.file_owner {
+            steps.push(format!("chown -R {} the synced paths", owner));
+        }
+        for (remote_path, _) in &merged_env {
+            steps.push(format!(
+                "merge the edits made on the server into {} (backup kept)",
+                shown(remote_path)
+            ));
+        }
+        if !env_pairs.is_empty() {
+            let bases: Vec<String> = env_pairs
+                .iter()
+                .map(|pair| shown(&env_base_path(&pair.remote_path)))
+                .collect();
+            steps.push(format!(
+                "keep the deployed env files as {}",
+                bases.join(", ")
+            ));
+        }
+        if self.restart_override().is_some() {
+            steps.push(format!(
+                "set restart policy '{}' on {} in {}",
+                self.config.restart_policy.as_deref().unwrap_or_default(),
+                self.config.services_without_restart.join(", "),
+                RESTART_OVERRIDE_FILE
+            ));
+        }
+        if self.uses_env_template() {
+            let remote_env = remote_dir.join(REMOTE_ENV_FILE);
+            if self.config.force_env {
+                steps.push(format!(
+                    "regenerate {} from the env template (backup kept)",
+                    REMOTE_ENV_FILE
+                ));
+            } else if files
+                .iter()
+                .any(|file| file.remote_path == remote_env && file.change == FileChange::New)
+            {
+                steps.push(format!("create {} from the env template", REMOTE_ENV_FILE));
+            }
+        }
+        if self.config.age_identity.is_some() {
+            steps.push(format!(
+                "encrypt the env into {} and remove any plain {}",
+                ENCRYPTED_ENV_FILE, DCD_ENV_FILE
+            ));
+        }
+        Ok(steps)
+    }
+
+    /// Install, update or remove the project's cron jobs.
+    async fn sync_cron_jobs(&mut self) -> DeployResult<()> {
+        if !self.config.cron_jobs.is_empty() {
+            tracing::info!("Step 5: Installing cron jobs...");
+            self.send_event(DeployerEvent::StepStarted(
+                "Installing cron jobs".to_string(),
+            ))
+            .await;
+        }
+        let (compose_files, env_files) = self.remote_compose_files();
+        let changed = CronManager::new(self.executor)
+            .sync(
+                &self.resolved_remote_dir,
+                &compose_files,
+                &env_files,
+                &self.config.cron_jobs,
+            )
+            .await?;
+        if changed {
+            tracing::info!("Cron jobs updated");
+        }
+        if !self.config.cron_jobs.is_empty() {
+            self.send_event(DeployerEvent::StepCompleted(
+                "Installing cron jobs".to_string(),
+            ))
+            .await;
+        }
+        Ok(())
+    }
+
+    /// Install or update the systemd unit that starts the stack on boot.
+    async fn install_systemd_unit(&mut self) -> DeployResult<()> {
+        let (compose_files, env_files) = self.remote_compose_files();
+        let unit = SystemdUnit::new(self.resolved_remote_dir.clone(), compose_files, env_files);
+        let changed = SystemdManager::new(self.executor).install(&unit).await?;
+        if changed {
+            tracing::info!("systemd unit {} installed and enabled", unit.name());
+        }
+        Ok(())
+    }
+
+    pub async fn destroy(
+        &mut self,
+        remove_volumes: bool,
+        remove_images: bool,
+        force: bool,
+    ) -> DeployResult<DeploymentStatus> {
+        let mut status = DeploymentStatus::new();
+        // Refused before anything is stopped, rather than halfway through
+        if remove_volumes {
+            check_removable_dir(&self.resolved_remote_dir)?;
+        }
+        self.send_event(DeployerEvent::StepStarted(
+            "Initializing destruction...".to_string(),
+        ))
+        .await;
+        tracing::info!("🔥 Starting destruction process...");
+
+        // Clone the sender before creating the manager which borrows self.executor mutably
+        let cloned_sender = self.progress_sender.clone();
+
+        if remove_volumes {
+            for (service, hints) in &self.config.service_hints {
+                if let Some(backup) = hints.backup.as_ref().filter(|b| !b.paths.is_empty()) {
+                    tracing::warn!(
+                        "Service '{}' keeps data worth backing up in {}; its volumes are being removed",
+                        service,
+                        backup.paths.join(", ")
+                    );
+                }
+            }
+        }
+
+        let remove_images = remove_images && self.images_removable().await;
+
+        // Create Docker manager
+        tracing::debug!("Initializing Docker manager for destruction.");
+        // Build list of remote compose and env files (basenames)
+        let (compose_files, env_files) = self.remote_compose_files();
+        let compose_env = self.deployed_compose_env().await?;
+        let mut docker_manager = SshDockerManager::new(
+            self.executor,
+            self.resolved_remote_dir.clone(),
+            compose_files,
+            env_files,
+        )
+        .await?
+        .with_env(compose_env);
+
+        // Check if any services are running
+        tracing::info!("Checking for running services...");
+        let services = docker_manager.get_services_status().await?.services;
+        let running: Vec<&ServiceStatus> = services.iter().filter(|s| s.is_running()).collect();
+
+        // If services are running and force is false, return error
+        if !running.is_empty() && !force {
+            tracing::warn!("Services are running. Destruction aborted. Use --force to override.");
+            status.message = "Services are still running. Use --force to destroy anyway.".into();
+            return Err(DeployError::Deployment(status.message.clone()));
+        } else if !running.is_empty() && force {
+            tracing::info!(
+                "Force flag enabled. Proceeding with destruction despite running services."
+            );
+        }
+
+        // Volumes have to be listed while their containers still exist
+        let volumes = if remove_volumes {
+            docker_manager.project_volumes().await?
+        } else {
+            Vec::new()
+        };
+
+        // Stop containers one by one so large stacks show progress
+        for (index, service) in running.iter().enumerate() {
+            tracing::info!("Stopping container {}...", service.name);
+            Self::send_event_to(
+                &cloned_sender,
+                DeployerEvent::StepProgress(
+                    format!("Stopping container {}", service.name),
+                    index + 1,
+                    running.len(),
+                ),
+            )
+            .await;
+            docker_manager.stop_container(&service.name).await?;
+        }
+
+        // Remove containers and networks; volumes follow one by one below
+        tracing::info!(
+            "Removing containers{}...",
+            if remove_images { ", images" } else { "" }
+        );
+        Self::send_event_to(
+            &cloned_sender,
+            DeployerEvent::StepStarted("Removing containers/networks...".to_string()),
+        )
+        .await;
+        docker_manager.compose_down(false, remove_images).await?;
+
+        for (index, volume) in volumes.iter().enumerate() {
+            tracing::info!("Removing volume {}...", volume);
+            Self::send_event_to(
+                &cloned_sender,
+                DeployerEvent::StepProgress(
+                    format!("Removing volume {}", volume),
+                    index + 1,
+                    volumes.len(),
+                ),
+            )
+            .await;
+            docker_manager.remove_volume(volume).await?;
+        }
+
+        // Its containers are gone, so its ports are free for other projects
+        registry::release_ports(self.executor, &self.resolved_remote_dir).await;
+
+        // Remove the auto-start unit so the stack does not come back on reboot
+        match SystemdManager::new(self.executor)
+            .remove(&self.resolved_remote_dir)
+            .await
+        {
+            Ok(true) => tracing::info!("Removed systemd unit for the project."),
+            Ok(false) => {}
+            Err(e) => tracing::warn!("Failed to remove systemd unit: {}", e),
+        }
+        match CronManager::new(self.executor)
+            .remove(&self.resolved_remote_dir)
+            .await
+        {
+            Ok(true) => tracing::info!("Removed cron jobs for the project."),
+            Ok(false) => {}
+            Err(e) => tracing::warn!("Failed to remove cron jobs: {}", e),
+        }
+        Self::send_event_to(
+            &cloned_sender,
+            DeployerEvent::StepCompleted(format!(
+                "Removed {} container(s){}.",
+                services.len(),
+                if volumes.is_empty() {
+                    String::new()
+                } else {
+                    format!(" and {} volume(s)", volumes.len())
+                }
+            )),
+        )
+        .await;
+
+        let mut removal_details = Vec::new();
+        if remove_volumes {
+            removal_details.push(format!("{} volume(s)", volumes.len()));
+        }
+        if remove_images {
+            removal_details.push("images".to_string());
+        }
+
+        // If removing volumes, also remove the remote project directory
+        if remove_volumes {
+            match self.remove_remote_dir(&cloned_sender).await {
+                Ok(freed) => {
+                    removal_details.push(format!("project directory, {}", format_bytes(freed)));
+                    registry::unregister(self.executor, &self.resolved_remote_dir).await;
+                }
+                Err(e) => {
+                    tracing::error!("{}", e);
+                    // Don't fail the whole destroy operation, as compose down succeeded
+                    status.message = format!(
+                        "Deployment destroyed ({}), but failed to remove project directory: {}",
+                        removal_details.join(", "),
+                        e
+                    );
+                    self.send_event(DeployerEvent::StepCompleted(
+                        "Destruction complete.".to_string(),
+                    ))
+                    .await;
+                    return Ok(status);
                 }
             }
         }
@@ -308,42 +1417,388 @@ impl<'a> Deployer<'a> {
             }
         );
 
-        self.send_event(DeployerEvent::StepCompleted(
-            "Destruction complete.".to_string(),
-        ))
-        .await;
-        Ok(status)
+        self.send_event(DeployerEvent::StepCompleted(
+            "Destruction complete.".to_string(),
+        ))
+        .await;
+        Ok(status)
+    }
+
+    /// What [`Self::destroy`] would do with the same arguments: the containers
+    /// it would stop and the commands removing them, their volumes and the
+    /// project directory. Only read-only commands are run on the remote.
+    pub async fn plan_destroy(
+        &mut self,
+        remove_volumes: bool,
+        remove_images: bool,
+        force: bool,
+    ) -> DeployResult<DryRunReport> {
+        if remove_volumes {
+            check_removable_dir(&self.resolved_remote_dir)?;
+        }
+        let remove_images = remove_images && self.images_removable().await;
+        let (compose_files, env_files) = self.remote_compose_files();
+        let compose_env = self.deployed_compose_env().await?;
+        let mut docker_manager = SshDockerManager::new(
+            self.executor,
+            self.resolved_remote_dir.clone(),
+            compose_files.clone(),
+            env_files.clone(),
+        )
+        .await?
+        .with_env(compose_env);
+
+        let services = docker_manager.get_services_status().await?.services;
+        let running: Vec<&ServiceStatus> = services.iter().filter(|s| s.is_running()).collect();
+        if !running.is_empty() && !force {
+            return Err(DeployError::Deployment(
+                "Services are still running. Use --force to destroy anyway.".into(),
+            ));
+        }
+        let volumes = if remove_volumes {
+            docker_manager.project_volumes().await?
+        } else {
+            Vec::new()
+        };
+
+        let mut commands: Vec<String> = running
+            .iter()
+            .map(|service| format!("docker stop {}", shell_quote(&service.name)))
+            .collect();
+        let mut down = compose_command(
+            &self.resolved_remote_dir,
+            &compose_files,
+            &env_files,
+            "down",
+        );
+        if remove_images {
+            down.push_str(" --rmi all");
+        }
+        commands.push(down);
+        commands.extend(
+            volumes
+                .iter()
+                .map(|volume| format!("docker volume rm {}", volume)),
+        );
+        if remove_volumes {
+            commands.push(format!(
+                "rm -rf -- {}",
+                shell_quote(&self.resolved_remote_dir.to_string_lossy())
+            ));
+        }
+        Ok(DryRunReport {
+            remote_dir: self.resolved_remote_dir.clone(),
+            commands,
+            ..Default::default()
+        })
+    }
+
+    /// Whether the project's images can be removed: never while other
+    /// stacks on the host still use them.
+    async fn images_removable(&mut self) -> bool {
+        let project = compose_project_name(&self.resolved_remote_dir);
+        let shared = list_stack_containers(self.executor)
+            .await
+            .map(|containers| shared_images(&containers, &project))
+            .unwrap_or_default();
+        if !shared.is_empty() {
+            tracing::warn!(
+                "Keeping images because other stacks use them: {}",
+                shared.join(", ")
+            );
+        }
+        shared.is_empty()
+    }
+
+    /// Remove the remote project directory entry by entry, reporting the
+    /// bytes removed so far. Returns the bytes freed. The directory is only
+    /// removed if, with symlinks resolved, it passes [`check_removable_dir`]
+    /// and holds the dcd state file.
+    async fn remove_remote_dir(
+        &mut self,
+        sender: &Option<mpsc::Sender<DeployerEvent>>,
+    ) -> DeployResult<u64> {
+        let Some(real_dir) = self.real_remote_dir().await? else {
+            tracing::info!(
+                "Remote directory {} does not exist",
+                self.resolved_remote_dir.display()
+            );
+            return Ok(0);
+        };
+        check_removable_dir(&real_dir)?;
+        let state_file = shell_quote(&real_dir.join(STATE_FILE).to_string_lossy());
+        let has_state = self
+            .executor
+            .execute_command(&format!("test -f {}", state_file))
+            .await
+            .map_err(|e| DeployError::Deployment(format!("Failed to check {}: {}", STATE_FILE, e)))?
+            .is_success();
+        if !has_state {
+            return Err(DeployError::Deployment(format!(
+                "Not removing {}: it holds no {}, so it may not be a dcd deployment; \
+                 remove it by hand if it is",
+                real_dir.display(),
+                STATE_FILE
+            )));
+        }
+
+        let dir = shell_quote(&real_dir.to_string_lossy());
+        tracing::info!("Removing remote project directory: {}", real_dir.display());
+        // Sizes are best effort; an unreadable entry only skews the count
+        let du_cmd = format!(
+            "find {} -mindepth 1 -maxdepth 1 -exec du -sk {{}} + 2>/dev/null",
+            dir
+        );
+        let entries = match self.executor.execute_command(&du_cmd).await {
+            Ok(result) => parse_du_sizes(&result.output.to_stdout_string().unwrap_or_default()),
+            Err(e) => {
+                tracing::warn!("Failed to measure remote directory: {}", e);
+                Vec::new()
+            }
+        };
+        let total: u64 = entries.iter().map(|(_, size)| size).sum();
+        Self::send_event_to(
+            sender,
+            DeployerEvent::StepStarted(format!(
+                "Removing remote directory: {} ({})",
+                self.resolved_remote_dir.display(),
+                format_bytes(total)
+            )),
+        )
+        .await;
+
+        let mut removed = 0;
+        for (index, (path, size)) in entries.iter().enumerate() {
+            Self::send_event_to(
+                sender,
+                DeployerEvent::StepProgress(
+                    format!(
+                        "Removing {} ({} of {} freed)",
+                        path.display(),
+                        format_bytes(removed),
+                        format_bytes(total)
+                    ),
+                    index + 1,
+                    entries.len(),
+                ),
+            )
+            .await;
+            self.run_remove(&format!(
+                "rm -rf -- {}",
+                shell_quote(&path.to_string_lossy())
+            ))
+            .await?;
+            removed += size;
+        }
+        self.run_remove(&format!("rm -rf -- {}", dir)).await?;
+
+        Self::send_event_to(
+            sender,
+            DeployerEvent::StepCompleted(format!(
+                "Remote directory removed ({} freed).",
+                format_bytes(total)
+            )),
+        )
+        .await;
+        Ok(total)
+    }
+
+    /// The remote directory with symlinks resolved, `None` if it is missing.
+    async fn real_remote_dir(&mut self) -> DeployResult<Option<PathBuf>> {
+        let cmd = format!(
+            "cd -- {} 2>/dev/null && pwd -P",
+            shell_quote(&self.resolved_remote_dir.to_string_lossy())
+        );
+        let result = self.executor.execute_command(&cmd).await.map_err(|e| {
+            DeployError::Deployment(format!("Failed to resolve remote directory: {}", e))
+        })?;
+        if !result.is_success() {
+            return Ok(None);
+        }
+        let real = result.output.to_stdout_string()?.trim().to_string();
+        Ok((!real.is_empty()).then(|| PathBuf::from(real)))
+    }
+
+    async fn run_remove(&mut self, cmd: &str) -> DeployResult<()> {
+        let result =
+            self.executor.execute_command(cmd).await.map_err(|e| {
+                DeployError::Deployment(format!("Failed to remove directory: {}", e))
+            })?;
+        if !result.is_success() {
+            return Err(DeployError::Deployment(format!(
+                "Failed to remove remote directory '{}': {}",
+                self.resolved_remote_dir.display(),
+                result.output.to_stderr_string()?.trim()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Add the values of the configured secrets to the variables written to
+    /// .env.dcd (or its encrypted replacement).
+    async fn inject_secrets(&mut self) -> DeployResult<()> {
+        if self.config.secrets.is_empty() {
+            return Ok(());
+        }
+        self.send_event(DeployerEvent::StepStarted("Fetching secrets".to_string()))
+            .await;
+        let mut env_manager = EnvFileManager::new(
+            std::mem::take(&mut self.config.consumed_env),
+            &self.config.project_dir,
+        );
+        let result = env_manager.inject_secrets(&self.config.secrets).await;
+        self.config.consumed_env = env_manager.into_env();
+        result?;
+        tracing::info!("Fetched {} secret(s)", self.config.secrets.len());
+        Ok(())
+    }
+
+    /// Prepare environment (env files, directories)
+    async fn prepare_environment(&mut self, status: &mut DeploymentStatus) -> DeployResult<()> {
+        tracing::debug!("Initializing environment file manager.");
+        // Create env file manager
+        let env_manager =
+            EnvFileManager::new(self.config.consumed_env.clone(), &self.config.project_dir);
+
+        // .env.dcd is streamed to the remote during file sync, never written locally
+        if env_manager.has_env_vars() {
+            tracing::info!(
+                "{} environment variable(s) will be written to remote {}",
+                self.config.consumed_env.len(),
+                DCD_ENV_FILE
+            );
+            status.env_changed = true;
+        } else {
+            tracing::debug!(
+                "No consumed environment variables, skipping {} generation.",
+                DCD_ENV_FILE
+            );
+        }
+
+        // A copy left behind by an older dcd version still holds secrets
+        if env_manager.get_dcd_env_path().exists()
+            && env_manager.is_dcd_env_gitignored().await == Some(false)
+        {
+            tracing::warn!(
+                "{} contains resolved secrets but is not covered by .gitignore; add it to avoid committing it",
+                DCD_ENV_FILE
+            );
+        }
+
+        if is_rootless(self.executor) {
+            self.warn_root_only_settings();
+        } else {
+            let installed = ensure_host_packages(self.executor, &self.config.host_packages).await?;
+            if !installed.is_empty() {
+                tracing::info!("Installed host packages: {}", installed.join(", "));
+            }
+
+            let mut tuning = HostTuning::new(self.executor, &self.resolved_remote_dir);
+            if tuning.apply_sysctls(&self.config.sysctls).await? {
+                tracing::info!("Updated host sysctl settings.");
+            }
+            if tuning.apply_ulimits(&self.config.ulimits).await? {
+                tracing::info!("Updated docker daemon limits.");
+            }
+        }
+
+        // Host sanity warnings never fail the deployment
+        match HostDoctor::new(self.executor).check_clock().await {
+            Ok(findings) => {
+                for finding in findings.iter().filter(|f| f.is_problem()) {
+                    tracing::warn!(
+                        "{}{}",
+                        finding.message,
+                        finding
+                            .suggestion
+                            .as_ref()
+                            .map(|s| format!(". Suggestion: {}", s))
+                            .unwrap_or_default()
+                    );
+                }
+            }
+            Err(e) => tracing::debug!("Remote clock check skipped: {}", e),
+        }
+
+        Ok(())
+    }
+
+    /// Refuse to start on a host without room for the images, and warn about a
+    /// workdir holding files dcd did not put there.
+    async fn preflight(&mut self) -> DeployResult<()> {
+        let findings = match deploy_checks(self.executor, &self.resolved_remote_dir).await {
+            Ok(findings) => findings,
+            Err(e) => {
+                tracing::debug!("Preflight checks skipped: {}", e);
+                return Ok(());
+            }
+        };
+        for finding in findings {
+            let suggestion = finding
+                .suggestion
+                .as_ref()
+                .map(|s| format!(". Suggestion: {}", s))
+                .unwrap_or_default();
+            match finding.severity {
+                Severity::Ok => tracing::debug!("{}", finding.message),
+                Severity::Warning => tracing::warn!("{}{}", finding.message, suggestion),
+                Severity::Error => {
+                    return Err(DeployError::Deployment(format!(
+                        "{}{}",
+                        finding.message, suggestion
+                    )))
+                }
+            }
+        }
+        Ok(())
     }
 
-    /// Prepare environment (env files, directories)
-    async fn prepare_environment(&mut self, status: &mut DeploymentStatus) -> DeployResult<()> {
-        tracing::debug!("Initializing environment file manager.");
-        // Create env file manager
-        let env_manager =
-            EnvFileManager::new(self.config.consumed_env.clone(), &self.config.project_dir);
+    /// Refuse to publish ports another dcd project on the host claimed,
+    /// before anything changes, rather than failing to bind them later.
+    async fn check_port_claims(&mut self) -> DeployResult<()> {
+        let conflicts = registry::claimed_by_others(
+            self.executor,
+            &self.resolved_remote_dir,
+            &self.config.exposed_ports,
+        )
+        .await?;
+        if conflicts.is_empty() {
+            return Ok(());
+        }
+        let details: Vec<String> = conflicts
+            .iter()
+            .map(|claim| {
+                format!(
+                    "port {}/{} already claimed by project '{}' ({})",
+                    claim.port,
+                    claim.protocol,
+                    claim.project,
+                    claim.remote_dir.display()
+                )
+            })
+            .collect();
+        Err(DeployError::Configuration(format!(
+            "{}; publish other ports or destroy that project first",
+            details.join(", ")
+        )))
+    }
 
-        // Generate .env.dcd if we have consumed environment variables
-        if env_manager.has_env_vars() {
-            tracing::info!("Generating {} file locally...", DCD_ENV_FILE);
-            env_manager.generate_dcd_env().await?;
-            tracing::debug!(
-                "{} generated at: {}",
-                DCD_ENV_FILE,
-                env_manager.get_dcd_env_path().display()
-            );
-            status.env_changed = true;
-        } else {
-            tracing::debug!(
-                "No consumed environment variables, skipping {} generation.",
-                DCD_ENV_FILE
+    /// Host settings a rootless deployment leaves alone, since changing them needs root.
+    fn warn_root_only_settings(&self) {
+        if !self.config.host_packages.is_empty() {
+            tracing::warn!(
+                "Docker runs rootless; not installing host packages: {}",
+                self.config.host_packages.join(", ")
             );
         }
-
-        Ok(())
+        if !self.config.sysctls.is_empty() || !self.config.ulimits.is_empty() {
+            tracing::warn!("Docker runs rootless; not applying sysctl and ulimit settings.");
+        }
     }
 
-    /// Synchronize all required files
-    async fn sync_files(&mut self, status: &mut DeploymentStatus) -> DeployResult<()> {
+    /// Compose and env files plus the top-level entries of the project the
+    /// compose files reference.
+    fn sync_plan(&self) -> DeployResult<SyncPlan> {
         let mut sync_plan = SyncPlan::new();
         tracing::debug!("Initializing file synchronization plan.");
         // Keep track of top-level project directories/files already added to the plan
@@ -367,10 +1822,18 @@ impl<'a> Deployer<'a> {
 
         // Add env files
         for file in &self.config.env_files {
-            let remote_path = self.resolved_remote_dir.join(
-                file.file_name()
-                    .ok_or_else(|| DeployError::Configuration("Invalid env file name".into()))?,
-            );
+            let name = file
+                .file_name()
+                .ok_or_else(|| DeployError::Configuration("Invalid env file name".into()))?;
+            if self.uses_env_template() && name == REMOTE_ENV_FILE {
+                tracing::debug!(
+                    "Not syncing '{}'; the remote {} is generated from the env template",
+                    file.display(),
+                    REMOTE_ENV_FILE
+                );
+                continue;
+            }
+            let remote_path = self.resolved_remote_dir.join(name);
             tracing::debug!(
                 "Adding env file to sync plan: '{}' -> '{}'",
                 file.display(),
@@ -379,18 +1842,6 @@ impl<'a> Deployer<'a> {
             sync_plan.add_env_file(file, remote_path);
         }
 
-        // Add .env.dcd if it exists
-        let dcd_env = self.config.project_dir.join(DCD_ENV_FILE);
-        if dcd_env.exists() {
-            tracing::debug!(
-                "Adding generated {} to sync plan: '{}' -> '{}'",
-                DCD_ENV_FILE,
-                dcd_env.display(),
-                self.resolved_remote_dir.join(DCD_ENV_FILE).display()
-            );
-            sync_plan.add_env_file(&dcd_env, self.resolved_remote_dir.join(DCD_ENV_FILE));
-        }
-
         // Add referenced files
         for path in &self.config.local_references {
             tracing::debug!("Processing local reference: '{}'", path.display());
@@ -441,141 +1892,946 @@ impl<'a> Deployer<'a> {
                 }
             }
         }
+        Ok(sync_plan)
+    }
+
+    /// Synchronize all required files
+    async fn sync_files(&mut self, status: &mut DeploymentStatus) -> DeployResult<()> {
+        let mut sync_plan = self.sync_plan()?;
+        let env_pairs = sync_plan.env_files.clone();
+
+        if self.config.show_diff {
+            let diffs = FileSync::new(self.executor, self.resolved_remote_dir.clone())
+                .diff_files(&sync_plan)
+                .await?;
+            for diff in diffs {
+                self.send_event(DeployerEvent::FileDiff(diff)).await;
+            }
+        }
+
+        let merged_env = self.resolve_env_edits(&mut sync_plan).await?;
+
+        // Mark the deployment as in progress before touching any file, so
+        // `dcd cleanup` can restore the backups if it gets interrupted
+        let mut pending_paths = sync_plan.remote_paths();
+        pending_paths.extend(merged_env.iter().map(|(path, _)| path.clone()));
+        pending_paths.push(self.resolved_remote_dir.join(DCD_ENV_FILE));
+        pending_paths.push(self.resolved_remote_dir.join(ENCRYPTED_ENV_FILE));
+        if self.uses_env_template() {
+            pending_paths.push(self.resolved_remote_dir.join(REMOTE_ENV_FILE));
+        }
+        let override_content = self.restart_override();
+        if override_content.is_some() {
+            pending_paths.push(self.resolved_remote_dir.join(RESTART_OVERRIDE_FILE));
+        }
+        self.mark_pending(pending_paths).await?;
+
+        // Perform synchronization
+        tracing::info!("Executing file synchronization...");
+        let mut file_sync = FileSync::new(self.executor, self.resolved_remote_dir.clone())
+            .with_owner(self.config.file_owner.clone())
+            .with_concurrency(self.config.sync_concurrency)
+            .with_include_hidden(self.config.include_hidden)
+            .with_progress(self.progress_sender.clone());
+        let sync_status = file_sync.sync_files(&sync_plan).await?;
+
+        // Update deployment status
+        status.files_changed = !sync_status.files_synced.is_empty();
+        status.synced_files = sync_status.files_synced.clone();
+        status.skipped_files = sync_status.files_skipped.clone();
+        tracing::debug!(
+            "Sync results: {} files synced, {} skipped, {} failed.",
+            sync_status.files_synced.len(),
+            sync_status.files_skipped.len(),
+            sync_status.files_failed.len()
+        );
+
+        if !sync_status.files_failed.is_empty() {
+            let failed_files: Vec<_> = sync_status
+                .files_failed
+                .iter()
+                .map(|(path, _)| path.display().to_string())
+                .collect();
+            status.message = format!("Failed to sync files: {}", failed_files.join(", "));
+            tracing::error!(
+                "File synchronization failed for: {}",
+                failed_files.join(", ")
+            );
+            return Err(DeployError::FileSync(status.message.clone()));
+        }
+
+        for (remote_path, content) in &merged_env {
+            backup_file(self.executor, remote_path).await?;
+            self.executor
+                .upload_content(content.as_bytes(), remote_path)
+                .await
+                .map_err(|e| {
+                    DeployError::FileSync(format!(
+                        "Failed to upload merged {}: {}",
+                        remote_path.display(),
+                        e
+                    ))
+                })?;
+            status.env_changed = true;
+        }
+        // The local env files are the base the next deploy merges against
+        for pair in &env_pairs {
+            self.executor
+                .upload_file(&pair.local_path, &env_base_path(&pair.remote_path), None)
+                .await
+                .map_err(|e| {
+                    DeployError::FileSync(format!(
+                        "Failed to keep a copy of {}: {}",
+                        pair.local_path.display(),
+                        e
+                    ))
+                })?;
+        }
+
+        if let Some(content) = override_content {
+            let remote_override = self.resolved_remote_dir.join(RESTART_OVERRIDE_FILE);
+            tracing::info!(
+                "Setting restart policy '{}' on {}",
+                self.config.restart_policy.as_deref().unwrap_or_default(),
+                self.config.services_without_restart.join(", ")
+            );
+            backup_file(self.executor, &remote_override).await?;
+            self.executor
+                .upload_content(content.as_bytes(), &remote_override)
+                .await
+                .map_err(|e| {
+                    DeployError::FileSync(format!(
+                        "Failed to upload {}: {}",
+                        RESTART_OVERRIDE_FILE, e
+                    ))
+                })?;
+        }
+
+        if let Some(template) = &self.config.env_template {
+            let template = EnvTemplate::load(template).await?;
+            let remote_env = self.resolved_remote_dir.join(REMOTE_ENV_FILE);
+            if self.config.force_env {
+                backup_file(self.executor, &remote_env).await?;
+            }
+            if template
+                .bootstrap(
+                    self.executor,
+                    &remote_env,
+                    &self.config.consumed_env,
+                    self.config.force_env,
+                )
+                .await?
+            {
+                status.env_changed = true;
+            }
+        }
+
+        // Stream the generated env file straight to the remote
+        let env_manager =
+            EnvFileManager::new(self.config.consumed_env.clone(), &self.config.project_dir);
+        let remote_dcd_env = self.resolved_remote_dir.join(DCD_ENV_FILE);
+        backup_file(self.executor, &remote_dcd_env).await?;
+        if let Some(identity) = self.config.age_identity.clone() {
+            // Encrypted uploads cannot be compared, so always count as a change
+            status.env_changed = true;
+            return self.upload_encrypted_env(&identity, &remote_dcd_env).await;
+        }
+        if env_manager
+            .upload_dcd_env(self.executor, &remote_dcd_env)
+            .await?
+        {
+            tracing::debug!("Uploaded {}", remote_dcd_env.display());
+            status.env_changed = true;
+        }
+
+        Ok(())
+    }
+
+    /// Look for env files edited on the server since the last deploy and
+    /// handle them as `env_edits` says. Files merged with those edits are
+    /// taken out of `sync_plan` and returned with their merged content.
+    async fn resolve_env_edits(
+        &mut self,
+        sync_plan: &mut SyncPlan,
+    ) -> DeployResult<Vec<(PathBuf, String)>> {
+        let deployed_hashes = RemoteState::load(self.executor, &self.resolved_remote_dir)
+            .await?
+            .and_then(|state| state.last_deploy)
+            .map(|record| record.env_hashes)
+            .unwrap_or_default();
+        if deployed_hashes.is_empty() {
+            return Ok(Vec::new());
+        }
+        let edited =
+            find_remote_edits(self.executor, &sync_plan.env_files, &deployed_hashes).await?;
+        if edited.is_empty() {
+            return Ok(Vec::new());
+        }
+        let report = edit_report(&edited);
+        match self.config.env_edits {
+            EnvEditPolicy::Abort => Err(DeployError::Environment(format!(
+                "Env files were edited on the server since the last deploy:{}
+                 Use --env-edits merge to keep those edits or --env-edits overwrite to replace them",
+                report
+            ))),
+            EnvEditPolicy::Overwrite => {
+                tracing::warn!("Replacing env files edited on the server:{}", report);
+                Ok(Vec::new())
+            }
+            EnvEditPolicy::Merge => {
+                let mut merged = Vec::new();
+                for file in edited {
+                    let Ok(content) = file.merged else {
+                        return Err(DeployError::Environment(format!(
+                            "Env files edited on the server conflict with local changes:{}
+                             Make both sides agree or use --env-edits overwrite",
+                            report
+                        )));
+                    };
+                    tracing::info!(
+                        "Merging edits made on the server into {}",
+                        file.remote_path.display()
+                    );
+                    sync_plan
+                        .env_files
+                        .retain(|pair| pair.remote_path != file.remote_path);
+                    merged.push((file.remote_path, content));
+                }
+                Ok(merged)
+            }
+        }
+    }
+
+    /// Store the env encrypted as .env.dcd.age and drop any plain .env.dcd
+    /// left by an earlier unencrypted deployment (its backup is kept until
+    /// the deployment succeeds).
+    async fn upload_encrypted_env(
+        &mut self,
+        identity: &std::path::Path,
+        remote_dcd_env: &std::path::Path,
+    ) -> DeployResult<()> {
+        let remote_encrypted = self.resolved_remote_dir.join(ENCRYPTED_ENV_FILE);
+        backup_file(self.executor, &remote_encrypted).await?;
+        let armored = EnvCipher::load(identity)?.encrypt(&self.config.consumed_env)?;
+        self.executor
+            .upload_content(armored.as_bytes(), &remote_encrypted)
+            .await
+            .map_err(|e| {
+                DeployError::Environment(format!("Failed to upload {}: {}", ENCRYPTED_ENV_FILE, e))
+            })?;
+        let result = self
+            .executor
+            .execute_command(&format!(
+                "rm -f {}",
+                shell_quote(&remote_dcd_env.to_string_lossy())
+            ))
+            .await
+            .map_err(|e| DeployError::Environment(e.to_string()))?;
+        if !result.is_success() {
+            return Err(DeployError::Environment(format!(
+                "Failed to remove plain {}: {}",
+                DCD_ENV_FILE,
+                result.output.to_stderr_string()?.trim()
+            )));
+        }
+        tracing::debug!("Uploaded {}", remote_encrypted.display());
+        Ok(())
+    }
+
+    /// Firewall configuration for the exposed ports.
+    fn port_configs(&self) -> Vec<PortConfig> {
+        PortConfig::for_published(&self.config.exposed_ports)
+    }
+
+    /// Configure firewall rules
+    async fn configure_firewall(&mut self, status: &mut DeploymentStatus) -> DeployResult<()> {
+        if self.config.exposed_ports.is_empty() {
+            tracing::info!("No exposed ports found in configuration, skipping firewall setup.");
+            return Ok(());
+        }
+
+        let port_configs = self.port_configs();
+        let mut firewall = firewall_manager(self.executor).await?;
+        tracing::debug!("Using the {} firewall backend.", firewall.backend());
+
+        tracing::info!(
+            "Applying firewall rules for {} port(s)...",
+            port_configs.len()
+        );
+        tracing::debug!("Port configurations to apply: {:?}", port_configs);
+        // Configure ports
+        firewall.configure_ports(&port_configs).await?;
+        status.opened_ports = port_configs
+            .iter()
+            .map(|config| format!("{}/{}", config.port, config.protocol))
+            .collect();
+
+        // TODO: check why i don't pass this check
+        // Verify port accessibility
+        tracing::info!("Verifying firewall rules...");
+        for config in &port_configs {
+            tracing::debug!("Verifying port {}/{}", config.port, config.protocol);
+            if !firewall.verify_port(config.port, &config.protocol).await? {
+                status.message = format!("Port {} is not accessible", config.port);
+                status.ports_changed = true;
+                tracing::warn!(
+                    "Verification failed: Port {}/{} is not accessible after configuration.",
+                    config.port,
+                    config.protocol
+                );
+                // Decide if this should be a hard error or just a warning in status
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Deploy services using docker-compose
+    async fn deploy_services(&mut self, status: &mut DeploymentStatus) -> DeployResult<()> {
+        let starts_services = self.config.runs_stage(DeployStage::Up);
+        let (compose_files, env_files) = self.remote_compose_files();
+        let mut state = RemoteState::default();
+        if starts_services {
+            self.check_other_stacks().await?;
+
+            // Record what is being deployed before starting it, so a failed
+            // rollout can still be torn down with the right profiles
+            let previous = RemoteState::load(self.executor, &self.resolved_remote_dir)
+                .await?
+                .unwrap_or_default();
+            state = RemoteState {
+                project: compose_project_name(&self.resolved_remote_dir),
+                profiles: self.config.profiles.clone(),
+                compose_files: compose_files.clone(),
+                env_files: env_files.clone(),
+                pending: previous.pending,
+                images: previous.images,
+                last_deploy: previous.last_deploy,
+                encrypted_env: self.config.age_identity.is_some(),
+                rollback: previous.rollback,
+            };
+            if state.images.is_empty() {
+                // Nothing recorded yet (first deploy, or by an older dcd)
+                state.images = self.current_images().await;
+            }
+            state.save(self.executor, &self.resolved_remote_dir).await?;
+        }
+
+        tracing::debug!("Initializing Docker manager for service deployment.");
+        let hook_context = self.hook_context();
+        let compose_env = self.deploy_compose_env();
+        let mut docker_manager = SshDockerManager::new(
+            self.executor,
+            self.resolved_remote_dir.clone(),
+            compose_files,
+            env_files,
+        )
+        .await?
+        .with_env(compose_env)
+        .with_progress(self.progress_sender.clone());
+
+        tracing::info!("Ensuring Docker is installed on remote host...");
+        docker_manager
+            .ensure_docker_installed()
+            .await
+            .map_err(|e| {
+                tracing::error!("Docker installation check failed: {}", e);
+                e
+            })?;
+
+        tracing::info!("Ensuring Docker Compose is installed and compatible...");
+        docker_manager
+            .ensure_docker_compose_installed()
+            .await
+            .map_err(|e| {
+                tracing::error!("Docker Compose installation check failed: {}", e);
+                e
+            })?;
+
+        if starts_services && !self.config.gpu_services.is_empty() {
+            tracing::info!(
+                "Checking GPU support for: {}",
+                self.config.gpu_services.join(", ")
+            );
+            docker_manager
+                .ensure_gpu_support(self.config.install_gpu_toolkit)
+                .await
+                .map_err(|e| {
+                    DeployError::Deployment(format!(
+                        "Services {} request GPUs: {}",
+                        self.config.gpu_services.join(", "),
+                        e
+                    ))
+                })?;
+        }
+
+        if starts_services {
+            for network in &self.config.external_networks {
+                if docker_manager.ensure_network(network).await? {
+                    tracing::info!("Created external network '{}'", network);
+                }
+            }
+        }
+
+        // Download and build everything first, so the running version keeps
+        // serving until the containers are recreated
+        if self.config.runs_stage(DeployStage::Pull) {
+            let started = Instant::now();
+            tracing::info!("Pulling images...");
+            Self::send_event_to(
+                &self.progress_sender,
+                DeployerEvent::StepStarted("Pulling images (services keep running)".to_string()),
+            )
+            .await;
+            docker_manager.compose_pull(&self.config.services).await?;
+            Self::send_event_to(
+                &self.progress_sender,
+                DeployerEvent::StepCompleted("Images pulled".to_string()),
+            )
+            .await;
+            status.record_stage(DeployStage::Pull, started);
+        }
+
+        if starts_services {
+            let started = Instant::now();
+            tracing::info!("Building images...");
+            Self::send_event_to(
+                &self.progress_sender,
+                DeployerEvent::StepStarted("Building images on the server".to_string()),
+            )
+            .await;
+            docker_manager
+                .compose_build(&self.config.services, self.config.fresh_build)
+                .await?;
+            Self::send_event_to(
+                &self.progress_sender,
+                DeployerEvent::StepCompleted("Images built".to_string()),
+            )
+            .await;
+            run_hooks(
+                docker_manager.executor(),
+                &self.config.hooks,
+                HookStage::PreUp,
+                &hook_context,
+                &self.progress_sender,
+            )
+            .await?;
+            tracing::info!("Running 'docker compose up -d' ...");
+            Self::send_event_to(
+                &self.progress_sender,
+                DeployerEvent::StepStarted("Recreating services".to_string()),
+            )
+            .await;
+            docker_manager.compose_up(&self.config.services).await?;
+            Self::send_event_to(
+                &self.progress_sender,
+                DeployerEvent::StepCompleted("Services recreated".to_string()),
+            )
+            .await;
+            run_hooks(
+                docker_manager.executor(),
+                &self.config.hooks,
+                HookStage::PostUp,
+                &hook_context,
+                &self.progress_sender,
+            )
+            .await?;
+            status.record_stage(DeployStage::Up, started);
+        }
+
+        if self.config.runs_stage(DeployStage::Health) {
+            let started = Instant::now();
+            let dependency_graph = Self::dependency_graph(&self.config);
+            Self::wait_for_healthy(
+                &mut docker_manager,
+                &dependency_graph,
+                &self.config.services,
+                self.config.health_check,
+                self.progress_sender.clone(),
+                status,
+            )
+            .await;
+
+            if status.services_healthy {
+                self.run_wait_strategies(status).await?;
+            }
+            if status.services_healthy {
+                self.run_hooks(HookStage::PostHealthy).await?;
+            } else if !self.config.hooks.post_healthy.is_empty() {
+                tracing::warn!("Skipping post-healthy hooks: services are not healthy.");
+            }
+            status.record_stage(DeployStage::Health, started);
+        } else {
+            tracing::info!("Skipping health checks.");
+        }
+
+        if starts_services {
+            let images = self.current_images().await;
+            status.image_changes = diff_images(&state.images, &images);
+            status.images = images.clone();
+            state.images = images;
+            state.save(self.executor, &self.resolved_remote_dir).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Images the project's containers currently run. Best effort: an empty
+    /// map when they cannot be inspected (e.g. docker not installed yet).
+    async fn current_images(&mut self) -> BTreeMap<String, ServiceImage> {
+        let project = compose_project_name(&self.resolved_remote_dir);
+        service_images(self.executor, &project)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::debug!("Could not inspect service images: {}", e);
+                BTreeMap::new()
+            })
+    }
+
+    /// The dcd deployments on the host, whichever directory they are in.
+    pub async fn list_deployments(&mut self) -> DeployResult<Vec<DeployedProject>> {
+        registry::list_deployments(self.executor).await
+    }
+
+    /// Images of the deployed project on the host, with the dangling ones
+    /// the image prune of the next deployment would remove marked.
+    pub async fn list_images(&mut self) -> DeployResult<Vec<ProjectImage>> {
+        let (compose_files, env_files) = self.remote_compose_files();
+        let compose_env = self.deployed_compose_env().await?;
+        let used = SshDockerManager::new(
+            self.executor,
+            self.resolved_remote_dir.clone(),
+            compose_files,
+            env_files,
+        )
+        .await?
+        .with_env(compose_env)
+        .compose_images()
+        .await?;
+        let project = compose_project_name(&self.resolved_remote_dir);
+        project_images(self.executor, &project, &used).await
+    }
+
+    /// Containers of the deployed project as reported by `docker compose ps`.
+    pub async fn list_services(&mut self) -> DeployResult<Vec<ServiceStatus>> {
+        let (compose_files, env_files) = self.remote_compose_files();
+        let compose_env = self.deployed_compose_env().await?;
+        let status = SshDockerManager::new(
+            self.executor,
+            self.resolved_remote_dir.clone(),
+            compose_files,
+            env_files,
+        )
+        .await?
+        .with_env(compose_env)
+        .get_services_status()
+        .await?;
+        Ok(status.services)
+    }
+
+    /// Networks of the deployed project, each with the networks and host
+    /// routes its subnets overlap.
+    pub async fn list_networks(&mut self) -> DeployResult<Vec<ProjectNetwork>> {
+        let project = compose_project_name(&self.resolved_remote_dir);
+        project_networks(self.executor, &project).await
+    }
+
+    /// Make sure this project does not step on other compose stacks on the
+    /// host: refuse to take over another directory's project name and warn
+    /// about published ports that are already in use.
+    async fn check_other_stacks(&mut self) -> DeployResult<()> {
+        let containers = match list_stack_containers(self.executor).await {
+            Ok(containers) => containers,
+            Err(e) => {
+                // Nothing to collide with if docker is not set up yet
+                tracing::debug!("Skipping multi-stack checks: {}", e);
+                return Ok(());
+            }
+        };
+        let project = compose_project_name(&self.resolved_remote_dir);
+        if let Some(other_dir) =
+            project_name_conflict(&containers, &project, &self.resolved_remote_dir)
+        {
+            return Err(DeployError::Configuration(format!(
+                "Compose project '{}' is already used by the stack in {}; deploy to a directory with a different name (--workdir)",
+                project, other_dir
+            )));
+        }
+        for (port, protocol, other) in
+            port_collisions(&containers, &project, &self.config.exposed_ports)
+        {
+            tracing::warn!(
+                "Port {}/{} is already published by compose project '{}'",
+                port,
+                protocol,
+                other
+            );
+        }
+        Ok(())
+    }
 
-        // Perform synchronization
-        tracing::info!("Executing file synchronization...");
-        let mut file_sync = FileSync::new(self.executor, self.resolved_remote_dir.clone());
-        let sync_status = file_sync.sync_files(&sync_plan).await?;
+    /// `depends_on` relations of the project, without the services whose
+    /// hints leave them out of the health check.
+    fn dependency_graph(config: &DeploymentConfig) -> DependencyGraph {
+        DependencyGraph::new(config.service_dependencies.clone()).skipping(
+            config
+                .service_hints
+                .iter()
+                .filter(|(_, hints)| hints.skip_health_check)
+                .map(|(service, _)| service.clone()),
+        )
+    }
 
-        // Update deployment status
-        status.files_changed = !sync_status.files_synced.is_empty();
-        tracing::debug!(
-            "Sync results: {} files synced, {} skipped, {} failed.",
-            sync_status.files_synced.len(),
-            sync_status.files_skipped.len(),
-            sync_status.files_failed.len()
+    /// Evaluate the configured wait strategies, then the smoke tests from
+    /// the services' hints; the first one that times out marks the
+    /// deployment unhealthy.
+    async fn run_wait_strategies(&mut self, status: &mut DeploymentStatus) -> DeployResult<()> {
+        let mut checks: Vec<(String, WaitStrategy, &str)> = self
+            .config
+            .wait_strategies
+            .iter()
+            .map(|(service, strategy)| (service.clone(), strategy.clone(), "wait check"))
+            .collect();
+        checks.extend(
+            self.config
+                .service_hints
+                .iter()
+                .filter_map(|(service, hints)| {
+                    let url = hints.smoke_test.clone()?;
+                    let strategy = WaitStrategy {
+                        kind: WaitKind::Http { url },
+                        timeout: Some(SMOKE_TEST_TIMEOUT.to_string()),
+                    };
+                    Some((service.clone(), strategy, "smoke test"))
+                }),
         );
+        for (service, strategy, what) in &checks {
+            tracing::info!("Waiting for {} ({})...", service, strategy.describe());
+            self.send_event(DeployerEvent::HealthCheckStatus(format!(
+                "Waiting for {} ({})",
+                service,
+                strategy.describe()
+            )))
+            .await;
+            if !wait_until_ready(self.executor, strategy).await? {
+                status.services_healthy = false;
+                status.message = format!(
+                    "Service '{}' did not pass its {} ({}) within {}.",
+                    service,
+                    what,
+                    strategy.describe(),
+                    strategy.timeout.as_deref().unwrap_or("60s")
+                );
+                tracing::error!("❌ {}", status.message);
+                return Ok(());
+            }
+            tracing::info!("✅ {} is ready.", service);
+        }
+        Ok(())
+    }
 
-        if !sync_status.files_failed.is_empty() {
-            let failed_files: Vec<_> = sync_status
-                .files_failed
-                .iter()
-                .map(|(path, _)| path.display().to_string())
-                .collect();
-            status.message = format!("Failed to sync files: {}", failed_files.join(", "));
-            tracing::error!(
-                "File synchronization failed for: {}",
-                failed_files.join(", ")
-            );
-            return Err(DeployError::FileSync(status.message.clone()));
+    /// Run a one-off command in a new container of `service`
+    /// (`docker compose run --rm`), attached to the local terminal.
+    /// Returns the command's exit code.
+    pub async fn run_service(
+        &mut self,
+        service: &str,
+        args: &[String],
+        tty: bool,
+        profiles: &[String],
+    ) -> DeployResult<u32> {
+        let mut subcommand = String::from("run --rm");
+        if !tty {
+            subcommand.push_str(" -T");
+        }
+        subcommand.push(' ');
+        subcommand.push_str(&shell_quote(service));
+        for arg in args {
+            subcommand.push(' ');
+            subcommand.push_str(&shell_quote(arg));
         }
 
-        Ok(())
+        let mut env = HashMap::new();
+        if !profiles.is_empty() {
+            env.insert("COMPOSE_PROFILES".to_string(), profiles.join(","));
+        }
+        self.attach_compose(&subcommand, env, tty)
+            .await
+            .map_err(|e| DeployError::Deployment(format!("Failed to run command: {}", e)))
     }
 
-    /// Configure firewall rules
-    async fn configure_firewall(&mut self, status: &mut DeploymentStatus) -> DeployResult<()> {
-        if self.config.exposed_ports.is_empty() {
-            tracing::info!("No exposed ports found in configuration, skipping firewall setup.");
-            return Ok(());
+    /// Run a command in the running container of `service`
+    /// (`docker compose exec`), attached to the local terminal.
+    /// Returns the command's exit code.
+    pub async fn exec_service(
+        &mut self,
+        service: &str,
+        args: &[String],
+        tty: bool,
+    ) -> DeployResult<u32> {
+        let mut subcommand = String::from("exec");
+        if !tty {
+            subcommand.push_str(" -T");
+        }
+        subcommand.push(' ');
+        subcommand.push_str(&shell_quote(service));
+        for arg in args {
+            subcommand.push(' ');
+            subcommand.push_str(&shell_quote(arg));
         }
+        self.attach_compose(&subcommand, HashMap::new(), tty)
+            .await
+            .map_err(|e| DeployError::Deployment(format!("Failed to run command: {}", e)))
+    }
 
-        tracing::debug!("Initializing UFW manager.");
-        let mut ufw = UfwManager::new(self.executor);
+    /// Show the logs of the deployed services (all when `services` is empty)
+    /// on the local terminal, following them when `follow` is set.
+    /// Returns the exit code of `docker compose logs`.
+    pub async fn service_logs(
+        &mut self,
+        services: &[String],
+        follow: bool,
+        tail: Option<u32>,
+    ) -> DeployResult<u32> {
+        let mut subcommand = String::from("logs");
+        if follow {
+            subcommand.push_str(" --follow");
+        }
+        if let Some(lines) = tail {
+            subcommand.push_str(&format!(" --tail {}", lines));
+        }
+        for service in services {
+            subcommand.push(' ');
+            subcommand.push_str(&shell_quote(service));
+        }
+        self.attach_compose(&subcommand, HashMap::new(), false)
+            .await
+            .map_err(|e| DeployError::Deployment(format!("Failed to read logs: {}", e)))
+    }
 
-        // Convert exposed ports to firewall config
-        let port_configs: Vec<PortConfig> = self
-            .config
-            .exposed_ports
-            .iter()
-            .map(|port| PortConfig {
-                port: port.target,
-                protocol: Protocol::from(port.protocol.as_deref().unwrap_or("tcp")),
-                description: format!("Docker service port {}", port.published),
-            })
-            .collect();
+    /// Diagnostics of the deployed project for `dcd support-bundle`, with
+    /// `log_tail` lines of logs per service. Values of the variables in
+    /// `secret_names` are masked along with those that look secret.
+    pub async fn support_bundle(
+        &mut self,
+        log_tail: u32,
+        secret_names: &[String],
+    ) -> Vec<BundleEntry> {
+        let (compose_files, env_files) = self.remote_compose_files();
+        // A bundle is most needed when things are broken, so collect what
+        // works without the deployed env too
+        let env = match self.deployed_compose_env().await {
+            Ok(env) => env,
+            Err(e) => {
+                tracing::warn!("Collecting without the deployed env: {}", e);
+                profiles_env(&self.config.profiles)
+            }
+        };
+        let target = BundleTarget {
+            remote_dir: &self.resolved_remote_dir,
+            compose_files: &compose_files,
+            env_files: &env_files,
+            env: &env,
+            secret_names,
+        };
+        support::collect(self.executor, &target, log_tail).await
+    }
 
-        tracing::info!(
-            "Applying firewall rules for {} port(s)...",
-            port_configs.len()
+    /// Run a compose subcommand in the remote directory attached to the
+    /// local terminal, with the deployed env (decrypted if needed) and
+    /// profiles, overridden by `env`.
+    async fn attach_compose(
+        &mut self,
+        subcommand: &str,
+        mut env: HashMap<String, String>,
+        tty: bool,
+    ) -> DeployResult<u32> {
+        let (compose_files, env_files) = self.remote_compose_files();
+        for (name, value) in self.deployed_compose_env().await? {
+            env.entry(name).or_insert(value);
+        }
+        let compose = compose_command(
+            &self.resolved_remote_dir,
+            &compose_files,
+            &env_files,
+            subcommand,
         );
-        tracing::debug!("Port configurations to apply: {:?}", port_configs);
-        // Configure ports
-        ufw.configure_ports(&port_configs).await?;
+        tracing::debug!("Running attached: {}", compose);
+        let command = if env.is_empty() {
+            compose
+        } else {
+            // The terminal owns stdin, so the values, which may be decrypted
+            // secrets, go through a private file removed once it is sourced
+            // instead of onto the command line
+            let env_file = self.write_private_env_file(&env).await?;
+            format!(
+                ". {path} && rm -f {path} && {}",
+                compose,
+                path = shell_quote(&env_file)
+            )
+        };
+        self.executor
+            .execute_interactive(&command, tty)
+            .await
+            .map_err(|e| DeployError::Deployment(e.to_string()))
+    }
 
-        // TODO: check why i don't pass this check
-        // Verify port accessibility
-        tracing::info!("Verifying firewall rules...");
-        for config in &port_configs {
-            tracing::debug!("Verifying port {}/{}", config.port, config.protocol);
-            if !ufw.verify_port(config.port, &config.protocol).await? {
-                status.message = format!("Port {} is not accessible", config.port);
-                status.ports_changed = true;
-                tracing::warn!(
-                    "Verification failed: Port {}/{} is not accessible after configuration.",
-                    config.port,
-                    config.protocol
-                );
-                // Decide if this should be a hard error or just a warning in status
-            }
+    /// Write `export` statements for `env` to a new file on the server readable
+    /// only by the deploy user, returning its path.
+    async fn write_private_env_file(
+        &mut self,
+        env: &HashMap<String, String>,
+    ) -> DeployResult<String> {
+        let exports = export_prefix(env).map_err(|e| DeployError::Configuration(e.to_string()))?;
+        let result = self
+            .executor
+            .execute_command("umask 077 && mktemp")
+            .await
+            .map_err(|e| DeployError::Deployment(e.to_string()))?;
+        let path = result.output.to_stdout_string()?.trim().to_string();
+        if !result.is_success() || path.is_empty() {
+            return Err(DeployError::Deployment(format!(
+                "Failed to create a temporary env file: {}",
+                result.output.to_stderr_string()?.trim()
+            )));
         }
+        if let Err(e) = self
+            .executor
+            .upload_content(exports.as_bytes(), Path::new(&path))
+            .await
+        {
+            let _ = self
+                .executor
+                .execute_command(&format!("rm -f {}", shell_quote(&path)))
+                .await;
+            return Err(DeployError::Deployment(format!(
+                "Failed to write temporary env file {}: {}",
+                path, e
+            )));
+        }
+        Ok(path)
+    }
 
+    /// Stop the running `services` (all if empty) without removing their containers.
+    pub async fn stop_services(&mut self, services: &[String]) -> DeployResult<()> {
+        let (compose_files, env_files) = self.remote_compose_files();
+        let compose_env = self.deployed_compose_env().await?;
+        let mut docker_manager = SshDockerManager::new(
+            self.executor,
+            self.resolved_remote_dir.clone(),
+            compose_files,
+            env_files,
+        )
+        .await?
+        .with_env(compose_env);
+        if let Some(sender) = &self.progress_sender {
+            let _ = sender
+                .send(DeployerEvent::StepStarted("Stopping services".to_string()))
+                .await;
+        }
+        docker_manager.compose_stop(services).await?;
+        if let Some(sender) = &self.progress_sender {
+            let _ = sender
+                .send(DeployerEvent::StepCompleted("Services stopped".to_string()))
+                .await;
+        }
         Ok(())
     }
 
-    /// Deploy services using docker-compose
-    async fn deploy_services(&mut self, status: &mut DeploymentStatus) -> DeployResult<()> {
-        tracing::debug!("Initializing Docker manager for service deployment.");
-        // Build list of remote compose and env files (basenames)
-        let compose_files = self
-            .config
-            .compose_files
-            .iter()
-            .map(|p| PathBuf::from(p.file_name().expect("Invalid compose file path")))
-            .collect::<Vec<PathBuf>>();
-        let mut env_files = self
-            .config
-            .env_files
-            .iter()
-            .map(|p| PathBuf::from(p.file_name().expect("Invalid env file path")))
-            .collect::<Vec<PathBuf>>();
-        // Include generated .env.dcd if present
-        let dcd_path = self.config.project_dir.join(DCD_ENV_FILE);
-        if dcd_path.exists() {
-            env_files.push(PathBuf::from(DCD_ENV_FILE));
-        }
+    /// Restart the containers of `services` (all if empty) without recreating
+    /// them and, unless the health stage is skipped, wait for them to become healthy.
+    pub async fn restart_services(
+        &mut self,
+        services: &[String],
+    ) -> DeployResult<DeploymentStatus> {
+        let mut status = DeploymentStatus::new();
+        let (compose_files, env_files) = self.remote_compose_files();
+        let compose_env = self.deployed_compose_env().await?;
         let mut docker_manager = SshDockerManager::new(
             self.executor,
             self.resolved_remote_dir.clone(),
             compose_files,
             env_files,
         )
-        .await?;
-
-        tracing::info!("Ensuring Docker is installed on remote host...");
-        docker_manager
-            .ensure_docker_installed()
-            .await
-            .map_err(|e| {
-                tracing::error!("Docker installation check failed: {}", e);
-                e
-            })?;
-
-        tracing::info!("Ensuring Docker Compose is installed and compatible...");
-        docker_manager
-            .ensure_docker_compose_installed()
-            .await
-            .map_err(|e| {
-                tracing::error!("Docker Compose installation check failed: {}", e);
-                e
-            })?;
+        .await?
+        .with_env(compose_env);
+        let step = if services.is_empty() {
+            "Restarting services".to_string()
+        } else {
+            format!("Restarting {}", services.join(", "))
+        };
+        Self::send_event_to(&self.progress_sender, DeployerEvent::StepStarted(step)).await;
+        docker_manager.compose_restart(services).await?;
+
+        if self.config.runs_stage(DeployStage::Health) {
+            let dependency_graph = Self::dependency_graph(&self.config);
+            Self::wait_for_healthy(
+                &mut docker_manager,
+                &dependency_graph,
+                &[],
+                self.config.health_check,
+                self.progress_sender.clone(),
+                &mut status,
+            )
+            .await;
+        }
+        drop(docker_manager);
+        self.send_event(DeployerEvent::StepCompleted(
+            "Services restarted".to_string(),
+        ))
+        .await;
+        Ok(status)
+    }
 
-        // Start services
-        tracing::info!("Running 'docker compose up -d' ...");
-        docker_manager.compose_up().await?;
+    /// Start the already deployed services (without pulling images) and wait
+    /// for them to become healthy.
+    pub async fn start_services(&mut self) -> DeployResult<DeploymentStatus> {
+        let mut status = DeploymentStatus::new();
+        let (compose_files, env_files) = self.remote_compose_files();
+        let compose_env = self.deployed_compose_env().await?;
+        let mut docker_manager = SshDockerManager::new(
+            self.executor,
+            self.resolved_remote_dir.clone(),
+            compose_files,
+            env_files,
+        )
+        .await?
+        .with_env(compose_env)
+        .with_progress(self.progress_sender.clone());
+        if let Some(sender) = &self.progress_sender {
+            let _ = sender
+                .send(DeployerEvent::StepStarted("Starting services".to_string()))
+                .await;
+        }
+        docker_manager.compose_start().await?;
+
+        let dependency_graph = Self::dependency_graph(&self.config);
+        Self::wait_for_healthy(
+            &mut docker_manager,
+            &dependency_graph,
+            &[],
+            self.config.health_check,
+            self.progress_sender.clone(),
+            &mut status,
+        )
+        .await;
+        if status.services_healthy {
+            self.run_wait_strategies(&mut status).await?;
+        }
+        Ok(status)
+    }
 
-        // Clone the sender before the loop to avoid borrow conflicts with docker_manager
-        let cloned_sender = self.progress_sender.clone();
+    /// Poll service health until everything is healthy, a terminal failure is
+    /// found or the retry budget is exhausted. The outcome is recorded in `status`.
+    async fn wait_for_healthy(
+        docker_manager: &mut SshDockerManager<'_>,
+        dependency_graph: &DependencyGraph,
+        services: &[String],
+        settings: HealthCheckSettings,
+        progress_sender: Option<mpsc::Sender<DeployerEvent>>,
+        status: &mut DeploymentStatus,
+    ) {
+        let cloned_sender = progress_sender;
         // Helper closure: Captures cloned_sender by reference.
         // The async move block takes ownership of 'event' and the reference to cloned_sender.
         let send_event_local = |event: DeployerEvent| {
@@ -594,22 +2850,33 @@ impl<'a> Deployer<'a> {
         };
 
         tracing::info!("Checking health of deployed services...");
+        let mut tracker = HealthTracker::new(Instant::now());
+        let mut timed_out = Vec::new();
         let mut attempts = 0;
 
         loop {
             attempts += 1;
-            tracing::info!(
-                "Health check attempt {}/{}...",
-                attempts,
-                Self::HEALTH_CHECK_RETRIES
-            );
+            tracing::info!("Health check attempt {}/{}...", attempts, settings.retries);
             send_event_local(DeployerEvent::HealthCheckAttempt(
                 attempts,
-                Self::HEALTH_CHECK_RETRIES,
+                settings.retries,
             ))
             .await;
 
-            match docker_manager.verify_services_healthy().await {
+            let health = docker_manager.get_services_status().await.map(|compose| {
+                let states: BTreeMap<String, String> = compose
+                    .services
+                    .iter()
+                    .filter(|s| services.is_empty() || services.contains(&s.service))
+                    .map(|s| (s.service.clone(), s.describe()))
+                    .collect();
+                let result = dependency_graph
+                    .reconcile(HealthCheckResult::from_status(&compose))
+                    .only(services);
+                tracker.observe(Instant::now(), &states, &result);
+                result
+            });
+            match health {
                 Ok(HealthCheckResult::Healthy) => {
                     tracing::info!("✅ Services are healthy.");
                     send_event_local(DeployerEvent::HealthCheckStatus(
@@ -630,23 +2897,28 @@ impl<'a> Deployer<'a> {
                     break;
                 }
                 Ok(HealthCheckResult::Failed(failed_services))
-                    if attempts < Self::HEALTH_CHECK_RETRIES =>
+                    if attempts < settings.retries
+                        && dependency_graph
+                            .blocking_failures(&failed_services)
+                            .is_empty() =>
                 {
                     tracing::warn!(
                         "Services not healthy yet. Found {} unhealthy service(s). Retrying in {:?}...", 
                         failed_services.len(),
-                        Self::HEALTH_CHECK_DELAY
+                        settings.interval
                     );
                     send_event_local(DeployerEvent::HealthCheckStatus(format!(
                         "{} unhealthy service(s), retrying...",
                         failed_services.len()
                     )))
                     .await;
-                    tokio::time::sleep(Self::HEALTH_CHECK_DELAY).await;
+                    // Retries are counted, so they keep their interval
+                    tokio::time::sleep(settings.interval).await;
                 }
                 Ok(HealthCheckResult::Failed(failed_services)) => {
                     // Final attempt with unhealthy services
                     status.services_healthy = false;
+                    status.unhealthy_services = failed_services.clone();
 
                     // Create a detailed message about unhealthy services
                     let service_details: Vec<String> = failed_services
@@ -665,11 +2937,21 @@ impl<'a> Deployer<'a> {
                         })
                         .collect();
 
-                    status.message = format!(
-                        "Definitively unhealthy services found after {} attempts: {}.",
-                        attempts,
-                        service_details.join("; ")
-                    );
+                    let blocking = dependency_graph.blocking_failures(&failed_services);
+                    status.message = if blocking.is_empty() {
+                        format!(
+                            "Definitively unhealthy services found after {} attempts: {}.",
+                            attempts,
+                            service_details.join("; ")
+                        )
+                    } else {
+                        format!(
+                            "Dependency failed after {} attempt(s), not waiting for remaining retries: {}. Unhealthy services: {}.",
+                            attempts,
+                            blocking.join("; "),
+                            service_details.join("; ")
+                        )
+                    };
 
                     let event_msg = format!(
                         "Health check failed: {} unhealthy service(s)",
@@ -684,34 +2966,52 @@ impl<'a> Deployer<'a> {
                     break;
                 }
                 Ok(HealthCheckResult::Starting(starting_services))
-                    if attempts < Self::MAX_STARTING_ATTEMPTS =>
+                    if tracker
+                        .timed_out(Instant::now(), settings.timeout, &starting_services)
+                        .is_empty() =>
                 {
+                    let chains = dependency_graph.waiting_chains(&starting_services);
                     tracing::info!(
-                        "Health check: {} service(s) still starting. Waiting longer (attempt {}/{})...",
+                        "Health check: {} service(s) still starting. Waiting longer (attempt {})...",
                         starting_services.len(),
-                        attempts,
-                        Self::MAX_STARTING_ATTEMPTS
+                        attempts
                     );
-                    send_event_local(DeployerEvent::HealthCheckStatus(format!(
-                        "{} service(s) still starting...",
-                        starting_services.len()
-                    )))
-                    .await;
-                    tokio::time::sleep(Self::HEALTH_CHECK_DELAY).await;
+                    let event_msg = if chains.is_empty() {
+                        format!("{} service(s) still starting...", starting_services.len())
+                    } else {
+                        tracing::info!("Dependency chains: {}", chains.join("; "));
+                        format!(
+                            "{} service(s) still starting ({})...",
+                            starting_services.len(),
+                            chains.join("; ")
+                        )
+                    };
+                    send_event_local(DeployerEvent::HealthCheckStatus(event_msg)).await;
+                    Self::pause_health_check(docker_manager, settings).await;
                 }
                 Ok(HealthCheckResult::Starting(starting_services)) => {
-                    // Max starting attempts reached
+                    // Some service has been starting for longer than the timeout
+                    timed_out =
+                        tracker.timed_out(Instant::now(), settings.timeout, &starting_services);
                     status.services_healthy = false;
+                    status.unhealthy_services = starting_services.clone();
                     let service_details: Vec<String> = starting_services
                         .iter()
                         .map(|s| format!("{} (state: {}, health: {})", s.name, s.state, s.health))
                         .collect();
 
                     status.message = format!(
-                        "Services still in 'starting' state after extended timeout ({} attempts): {}.",
-                        attempts,
+                        "Services still in 'starting' state after {:?} ({} timed out): {}.",
+                        settings.timeout,
+                        timed_out.join(", "),
                         service_details.join("; ")
                     );
+                    let chains = dependency_graph.waiting_chains(&starting_services);
+                    if !chains.is_empty() {
+                        status
+                            .message
+                            .push_str(&format!(" Waiting chains: {}.", chains.join("; ")));
+                    }
 
                     send_event_local(DeployerEvent::HealthCheckStatus(format!(
                         "Timeout: {} service(s) still starting",
@@ -734,8 +3034,46 @@ impl<'a> Deployer<'a> {
                 }
             }
         }
+        status.health_timelines = tracker.finish(&timed_out);
+    }
 
-        Ok(())
+    /// Wait before the next health check attempt: until a container of the
+    /// project starts, dies or changes health when watching Docker events
+    /// (at most the interval), otherwise for the interval.
+    async fn pause_health_check(
+        docker_manager: &mut SshDockerManager<'_>,
+        settings: HealthCheckSettings,
+    ) {
+        if settings.watch_events {
+            match docker_manager
+                .wait_for_container_event(settings.interval)
+                .await
+            {
+                Ok(seen) => {
+                    if seen {
+                        tracing::debug!("Container event seen, checking health again");
+                    }
+                    return;
+                }
+                Err(e) => tracing::warn!("Could not watch Docker events, polling instead: {}", e),
+            }
+        }
+        tokio::time::sleep(settings.interval).await;
+    }
+
+    /// Single health check of the deployed services, without waiting or retries.
+    pub async fn check_health(&mut self) -> DeployResult<HealthCheckResult> {
+        let (compose_files, env_files) = self.remote_compose_files();
+        let compose_env = self.deployed_compose_env().await?;
+        let mut docker_manager = SshDockerManager::new(
+            self.executor,
+            self.resolved_remote_dir.clone(),
+            compose_files,
+            env_files,
+        )
+        .await?
+        .with_env(compose_env);
+        Ok(docker_manager.verify_services_healthy().await?)
     }
 
     /// Get current deployment status
@@ -759,25 +3097,19 @@ impl<'a> Deployer<'a> {
                 .await;
         }
         // Build list of remote compose and env files (basenames)
-        let compose_files = self
-            .config
-            .compose_files
-            .iter()
-            .map(|p| PathBuf::from(p.file_name().expect("Invalid compose file path")))
-            .collect::<Vec<PathBuf>>();
-        let env_files = self
-            .config
-            .env_files
-            .iter()
-            .map(|p| PathBuf::from(p.file_name().expect("Invalid env file path")))
-            .collect::<Vec<PathBuf>>();
+        let (compose_files, env_files) = self.remote_compose_files();
+        let compose_env = self.deployed_compose_env().await?;
+        let last_deploy = RemoteState::load(self.executor, &self.resolved_remote_dir)
+            .await?
+            .and_then(|state| state.last_deploy);
         let mut docker_manager = SshDockerManager::new(
             self.executor,
             self.resolved_remote_dir.clone(),
             compose_files,
             env_files,
         )
-        .await?;
+        .await?
+        .with_env(compose_env);
         if let Some(sender) = &cloned_sender {
             let _ = sender
                 .send(DeployerEvent::StepCompleted(
@@ -787,6 +3119,7 @@ impl<'a> Deployer<'a> {
         }
 
         let mut status = DeploymentStatus::new();
+        status.last_deploy = last_deploy;
 
         // Check services health
         tracing::info!("Checking service health...");
@@ -798,7 +3131,8 @@ impl<'a> Deployer<'a> {
                 ))
                 .await;
         }
-        match docker_manager.verify_services_healthy().await? {
+        let dependency_graph = Self::dependency_graph(&self.config);
+        match dependency_graph.reconcile(docker_manager.verify_services_healthy().await?) {
             HealthCheckResult::Healthy => {
                 status.services_healthy = true;
                 tracing::info!("Service health status: Healthy");
@@ -810,6 +3144,7 @@ impl<'a> Deployer<'a> {
             }
             HealthCheckResult::Failed(failed_services) => {
                 status.services_healthy = false;
+                status.unhealthy_services = failed_services.clone();
 
                 // Create a detailed message about unhealthy services
                 let service_details: Vec<String> = failed_services
@@ -834,6 +3169,7 @@ impl<'a> Deployer<'a> {
             }
             HealthCheckResult::Starting(starting_services) => {
                 status.services_healthy = false;
+                status.unhealthy_services = starting_services.clone();
 
                 let service_details: Vec<String> = starting_services
                     .iter()
@@ -846,6 +3182,22 @@ impl<'a> Deployer<'a> {
                 tracing::info!("Service health status: Starting - {}", status.message);
             }
         }
+
+        // Per-container details; a failed restart count lookup is not worth failing over
+        let restarts = docker_manager.restart_counts().await.unwrap_or_else(|e| {
+            tracing::debug!("Could not read restart counts: {}", e);
+            HashMap::new()
+        });
+        status.services = docker_manager
+            .get_services_status()
+            .await?
+            .services
+            .iter()
+            .map(|service| {
+                ServiceDetail::new(service, restarts.get(&service.name).copied().unwrap_or(0))
+            })
+            .collect();
+
         // Use the cloned sender for the final event within the scope of docker_manager
         if let Some(sender) = &cloned_sender {
             let _ = sender
@@ -858,3 +3210,118 @@ impl<'a> Deployer<'a> {
         Ok(status)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{CommandMatch, MemoryFs, MockExecutor};
+
+    #[test]
+    fn test_sync_plan_skips_env_generated_from_template() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path();
+        std::fs::write(project.join("docker-compose.yml"), "services: {}\n").unwrap();
+        std::fs::write(project.join(REMOTE_ENV_FILE), "LOG_LEVEL=info\n").unwrap();
+        std::fs::write(project.join(".env.prod"), "WORKERS=2\n").unwrap();
+        let mut config = DeploymentConfig {
+            project_dir: project.to_path_buf(),
+            remote_dir: Some(PathBuf::from("/srv/app")),
+            compose_files: vec![project.join("docker-compose.yml")],
+            env_files: vec![project.join(REMOTE_ENV_FILE), project.join(".env.prod")],
+            ..Default::default()
+        };
+        let mut executor = MockExecutor::new();
+
+        let synced = |plan: SyncPlan| -> Vec<PathBuf> {
+            plan.env_files.into_iter().map(|p| p.remote_path).collect()
+        };
+        let plan = Deployer::new(config.clone(), &mut executor, None)
+            .sync_plan()
+            .unwrap();
+        assert_eq!(
+            synced(plan),
+            [
+                PathBuf::from("/srv/app").join(REMOTE_ENV_FILE),
+                PathBuf::from("/srv/app/.env.prod")
+            ]
+        );
+
+        config.env_template = Some(project.join(".env.template"));
+        let plan = Deployer::new(config, &mut executor, None)
+            .sync_plan()
+            .unwrap();
+        assert_eq!(synced(plan), [PathBuf::from("/srv/app/.env.prod")]);
+    }
+
+    #[tokio::test]
+    async fn test_planned_sync_steps() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path();
+        std::fs::write(project.join(".env.prod"), "WORKERS=2\n").unwrap();
+        let config = DeploymentConfig {
+            project_dir: project.to_path_buf(),
+            remote_dir: Some(PathBuf::from("/srv/app")),
+            env_files: vec![project.join(".env.prod")],
+            restart_policy: Some("unless-stopped".into()),
+            services_without_restart: vec!["web".into()],
+            env_template: Some(project.join(".env.template")),
+            ..Default::default()
+        };
+        let files = [
+            PlannedFile {
+                local_path: Some(project.join(".env.prod")),
+                remote_path: PathBuf::from("/srv/app/.env.prod"),
+                change: FileChange::Changed,
+            },
+            PlannedFile {
+                local_path: None,
+                remote_path: PathBuf::from("/srv/app").join(REMOTE_ENV_FILE),
+                change: FileChange::New,
+            },
+        ];
+        let mut executor = MockExecutor::new();
+        let steps = Deployer::new(config, &mut executor, None)
+            .planned_sync_steps(&files)
+            .await
+            .unwrap();
+        assert_eq!(
+            steps,
+            [
+                format!("mark the deployment in progress in {}", STATE_FILE),
+                "back up the overwritten files as *.backup: .env.prod".to_string(),
+                "keep the deployed env files as .env.prod.dcd-base".to_string(),
+                format!(
+                    "set restart policy 'unless-stopped' on web in {}",
+                    RESTART_OVERRIDE_FILE
+                ),
+                format!("create {} from the env template", REMOTE_ENV_FILE),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_plan_destroy_sees_profiled_services() {
+        let fs = MemoryFs::new();
+        fs.insert(
+            PathBuf::from("/srv/app").join(STATE_FILE),
+            r#"{"project":"app","profiles":["worker"]}"#,
+        );
+        let mut executor = MockExecutor::with_fs(fs);
+        executor
+            .respond_ok(CommandMatch::Prefix("test -d".into()), "exists")
+            .respond_ok(
+                CommandMatch::Contains("COMPOSE_PROFILES='worker'".into()),
+                r#"{"Command":"worker","CreatedAt":"","ExitCode":0,"Health":"","ID":"def","Image":"app-worker","Labels":"","LocalVolumes":"0","Mounts":"","Name":"app-worker-1","Names":"app-worker-1","Networks":"","Ports":"","Project":"app","Publishers":[],"RunningFor":"","Service":"worker","Size":"","State":"running","Status":"Up 1 hour"}"#,
+            )
+            .respond_ok(CommandMatch::Contains("ps --format json".into()), "");
+        let config = DeploymentConfig {
+            remote_dir: Some(PathBuf::from("/srv/app")),
+            ..Default::default()
+        };
+
+        let mut deployer = Deployer::new(config, &mut executor, None);
+        assert!(deployer.plan_destroy(false, false, false).await.is_err());
+        let report = deployer.plan_destroy(false, false, true).await.unwrap();
+        assert_eq!(report.commands[0], "docker stop app-worker-1");
+    }
+}
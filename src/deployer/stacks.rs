@@ -0,0 +1,228 @@
+use crate::composer::types::PortMapping;
+use crate::deployer::types::{ComposeExec, DeployError, DeployResult};
+use std::collections::BTreeSet;
+use std::path::Path;
+
+/// A compose-managed container on the host, as seen by `docker ps`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackContainer {
+    pub project: String,
+    pub working_dir: String,
+    pub image: String,
+    /// Published host ports with their protocol, e.g. `(8080, "tcp")`
+    pub published_ports: Vec<(u16, String)>,
+}
+
+/// All compose-managed containers on the host, across projects.
+pub async fn list_stack_containers(
+    executor: &mut (dyn ComposeExec + Send),
+) -> DeployResult<Vec<StackContainer>> {
+    let cmd = "docker ps -a --filter label=com.docker.compose.project --format \
+               '{{.Label \"com.docker.compose.project\"}}\t{{.Label \"com.docker.compose.project.working_dir\"}}\t{{.Image}}\t{{.Ports}}'";
+    let result = executor
+        .execute_command(cmd)
+        .await
+        .map_err(|e| DeployError::Deployment(format!("Failed to list containers: {}", e)))?;
+    if !result.is_success() {
+        return Err(DeployError::Deployment(format!(
+            "Failed to list containers: {}",
+            result.output.to_stderr_string()?.trim()
+        )));
+    }
+    Ok(parse_stack_containers(&result.output.to_stdout_string()?))
+}
+
+fn parse_stack_containers(output: &str) -> Vec<StackContainer> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(4, '\t');
+            let project = fields.next()?.trim();
+            if project.is_empty() {
+                return None;
+            }
+            Some(StackContainer {
+                project: project.to_string(),
+                working_dir: fields.next().unwrap_or_default().trim().to_string(),
+                image: fields.next().unwrap_or_default().trim().to_string(),
+                published_ports: parse_published_ports(fields.next().unwrap_or_default()),
+            })
+        })
+        .collect()
+}
+
+/// Parse the `Ports` column, e.g. `0.0.0.0:8080->80/tcp, :::8080->80/tcp, 5432/tcp`.
+/// Unpublished ports are skipped.
+fn parse_published_ports(ports: &str) -> Vec<(u16, String)> {
+    let mut published = BTreeSet::new();
+    for entry in ports.split(',') {
+        let Some((host, container)) = entry.trim().split_once("->") else {
+            continue;
+        };
+        let protocol = container.rsplit_once('/').map_or("tcp", |(_, p)| p);
+        // Host side may be a range (`8000-8001`); take each end of it
+        let host_ports = host.rsplit_once(':').map_or(host, |(_, p)| p);
+        let (start, end) = host_ports
+            .split_once('-')
+            .unwrap_or((host_ports, host_ports));
+        if let (Ok(start), Ok(end)) = (start.parse::<u16>(), end.parse::<u16>()) {
+            for port in start..=end {
+                published.insert((port, protocol.to_string()));
+            }
+        }
+    }
+    published.into_iter().collect()
+}
+
+/// Remove containers that were created but never started and networks no
+/// container uses, for `project` only. Leftovers of an aborted `compose up`.
+pub async fn remove_aborted_resources(
+    executor: &mut (dyn ComposeExec + Send),
+    project: &str,
+) -> DeployResult<()> {
+    let label = format!("label=com.docker.compose.project={}", project);
+    let commands = [
+        format!(
+            "docker ps -aq --filter '{}' --filter status=created | xargs -r docker rm -f",
+            label
+        ),
+        format!("docker network prune -f --filter '{}'", label),
+    ];
+    for cmd in &commands {
+        let result = executor
+            .execute_command(cmd)
+            .await
+            .map_err(|e| DeployError::Deployment(format!("Failed to run '{}': {}", cmd, e)))?;
+        if !result.is_success() {
+            return Err(DeployError::Deployment(format!(
+                "Command '{}' failed: {}",
+                cmd,
+                result.output.to_stderr_string()?.trim()
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Another stack using the compose project name of `remote_dir`, if any.
+/// Returns its working directory.
+pub fn project_name_conflict<'a>(
+    containers: &'a [StackContainer],
+    project: &str,
+    remote_dir: &Path,
+) -> Option<&'a str> {
+    containers
+        .iter()
+        .find(|c| {
+            c.project == project
+                && !c.working_dir.is_empty()
+                && Path::new(&c.working_dir) != remote_dir
+        })
+        .map(|c| c.working_dir.as_str())
+}
+
+/// Ports this project wants to publish that other stacks already publish,
+/// as `(port, protocol, other project)`.
+pub fn port_collisions(
+    containers: &[StackContainer],
+    project: &str,
+    exposed_ports: &[PortMapping],
+) -> Vec<(u16, String, String)> {
+    let mut collisions = BTreeSet::new();
+    for mapping in exposed_ports {
+        let Ok(port) = mapping.published.parse::<u16>() else {
+            continue;
+        };
+        let protocol = mapping.protocol.as_deref().unwrap_or("tcp");
+        for container in containers.iter().filter(|c| c.project != project) {
+            if container
+                .published_ports
+                .iter()
+                .any(|(p, proto)| *p == port && proto == protocol)
+            {
+                collisions.insert((port, protocol.to_string(), container.project.clone()));
+            }
+        }
+    }
+    collisions.into_iter().collect()
+}
+
+/// Images of this project that containers of other stacks also use.
+pub fn shared_images(containers: &[StackContainer], project: &str) -> Vec<String> {
+    let own: BTreeSet<&str> = containers
+        .iter()
+        .filter(|c| c.project == project)
+        .map(|c| c.image.as_str())
+        .collect();
+    containers
+        .iter()
+        .filter(|c| c.project != project && own.contains(c.image.as_str()))
+        .map(|c| c.image.clone())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PS_OUTPUT: &str = "shop\t/opt/shop\tnginx:1.27\t0.0.0.0:80->80/tcp, :::80->80/tcp\n\
+                             shop\t/opt/shop\tpostgres:16\t5432/tcp\n\
+                             blog\t/srv/blog\tnginx:1.27\t0.0.0.0:8080-8081->80-81/tcp\n\
+                             app\t/srv/app\tredis:7\t0.0.0.0:6379->6379/udp\n";
+
+    fn port(published: &str, protocol: Option<&str>) -> PortMapping {
+        PortMapping {
+            mode: None,
+            target: 80,
+            published: published.to_string(),
+            protocol: protocol.map(str::to_string),
+            allow_from: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_stack_containers() {
+        let containers = parse_stack_containers(PS_OUTPUT);
+        assert_eq!(containers.len(), 4);
+        assert_eq!(containers[0].published_ports, vec![(80, "tcp".to_string())]);
+        assert!(containers[1].published_ports.is_empty());
+        assert_eq!(
+            containers[2].published_ports,
+            vec![(8080, "tcp".to_string()), (8081, "tcp".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_project_name_conflict() {
+        let containers = parse_stack_containers(PS_OUTPUT);
+        assert_eq!(
+            project_name_conflict(&containers, "app", Path::new("/opt/app")),
+            Some("/srv/app")
+        );
+        assert_eq!(
+            project_name_conflict(&containers, "shop", Path::new("/opt/shop")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_port_collisions_and_shared_images() {
+        let containers = parse_stack_containers(PS_OUTPUT);
+        let collisions = port_collisions(
+            &containers,
+            "shop",
+            &[
+                port("80", None),
+                port("8081", Some("tcp")),
+                port("6379", None),
+            ],
+        );
+        assert_eq!(
+            collisions,
+            vec![(8081, "tcp".to_string(), "blog".to_string())]
+        );
+        assert_eq!(shared_images(&containers, "shop"), vec!["nginx:1.27"]);
+    }
+}
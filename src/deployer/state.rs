@@ -0,0 +1,402 @@
+use crate::deployer::images::ServiceImage;
+use crate::deployer::types::{ComposeExec, DeployError, DeployResult};
+use crate::executor::shell_quote;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Deployment metadata kept next to the compose files on the remote host.
+pub const STATE_FILE: &str = ".dcd-state.json";
+
+/// File names docker compose picks up by default, in its lookup order.
+const DEFAULT_COMPOSE_FILES: [&str; 4] = [
+    "compose.yaml",
+    "compose.yml",
+    "docker-compose.yaml",
+    "docker-compose.yml",
+];
+
+/// What was deployed, so later commands (status, destroy, ...) act on the
+/// same stack even when the local checkout differs.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RemoteState {
+    /// Compose project name of the deployment
+    pub project: String,
+    /// Compose profiles active at deploy time
+    pub profiles: Vec<String>,
+    /// Compose files (relative to the remote directory) used at deploy time
+    pub compose_files: Vec<PathBuf>,
+    /// Env files (relative to the remote directory) used at deploy time
+    pub env_files: Vec<PathBuf>,
+    /// Set while a deployment is in progress; left behind if it was aborted
+    pub pending: Option<PendingDeploy>,
+    /// Images the services were running after the last deployment
+    pub images: BTreeMap<String, ServiceImage>,
+    /// Who deployed what, and when, the last time `up` succeeded
+    pub last_deploy: Option<DeployRecord>,
+    /// Whether the env is stored as .env.dcd.age instead of .env.dcd
+    pub encrypted_env: bool,
+    /// The deployment before the last successful one, for `dcd rollback`
+    pub rollback: Option<RollbackPoint>,
+}
+
+/// Metadata about a completed deployment.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeployRecord {
+    /// Unix timestamp (seconds) of the deployment
+    pub at: u64,
+    /// Local user that ran the deployment
+    pub by: String,
+    /// Short git commit of the deployed project, if it is a git checkout
+    pub commit: Option<String>,
+    /// dcd version used
+    pub dcd_version: String,
+    /// SHA-256 of each deployed compose file, by remote file name
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub compose_hashes: BTreeMap<PathBuf, String>,
+    /// SHA-256 of each deployed env file, by remote file name, to notice
+    /// edits made to them on the server
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub env_hashes: BTreeMap<PathBuf, String>,
+}
+
+impl DeployRecord {
+    /// Describe a deployment happening now from `project_dir`.
+    pub fn current(project_dir: &Path) -> Self {
+        let at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        let by = std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .unwrap_or_else(|_| "unknown".to_string());
+        let commit = std::process::Command::new("git")
+            .args(["rev-parse", "--short", "HEAD"])
+            .current_dir(project_dir)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+            .filter(|commit| !commit.is_empty());
+        Self {
+            at,
+            by,
+            commit,
+            dcd_version: env!("CARGO_PKG_VERSION").to_string(),
+            compose_hashes: BTreeMap::new(),
+            env_hashes: BTreeMap::new(),
+        }
+    }
+
+    /// One-line summary relative to `now`, e.g.
+    /// `2h ago by alice, commit 4f9c2d1, dcd 0.4.2`.
+    pub fn summary(&self, now: u64) -> String {
+        let mut summary = format!("{} by {}", format_ago(now.saturating_sub(self.at)), self.by);
+        if let Some(commit) = &self.commit {
+            summary.push_str(&format!(", commit {}", commit));
+        }
+        summary.push_str(&format!(", dcd {}", self.dcd_version));
+        summary
+    }
+}
+
+/// Coarse human-readable age, e.g. `45s ago`, `2h ago`, `3d ago`.
+fn format_ago(seconds: u64) -> String {
+    match seconds {
+        0..60 => format!("{}s ago", seconds),
+        60..3600 => format!("{}m ago", seconds / 60),
+        3600..86400 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86400),
+    }
+}
+
+/// Deployment that started changing files on the remote but has not finished.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PendingDeploy {
+    /// Remote files and directories being synced; their previous contents
+    /// are kept as `*.backup` copies until the deployment succeeds
+    pub paths: Vec<PathBuf>,
+    /// What was deployed before this deployment started
+    pub previous: Option<RollbackPoint>,
+}
+
+/// An earlier deployment that `dcd rollback` can return to.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RollbackPoint {
+    /// Remote files and directories whose contents from this deployment are
+    /// kept as `*.previous` copies (empty while a deployment is pending)
+    pub paths: Vec<PathBuf>,
+    pub profiles: Vec<String>,
+    pub compose_files: Vec<PathBuf>,
+    pub env_files: Vec<PathBuf>,
+    pub images: BTreeMap<String, ServiceImage>,
+    pub last_deploy: Option<DeployRecord>,
+    pub encrypted_env: bool,
+}
+
+impl RollbackPoint {
+    /// Capture the deployment described by `state`, if there is one.
+    pub fn from_state(state: &RemoteState) -> Option<Self> {
+        if state.compose_files.is_empty() {
+            return None;
+        }
+        Some(Self {
+            paths: Vec::new(),
+            profiles: state.profiles.clone(),
+            compose_files: state.compose_files.clone(),
+            env_files: state.env_files.clone(),
+            images: state.images.clone(),
+            last_deploy: state.last_deploy.clone(),
+            encrypted_env: state.encrypted_env,
+        })
+    }
+
+    /// Make `state` describe this deployment again.
+    pub fn apply_to(self, state: &mut RemoteState) {
+        state.profiles = self.profiles;
+        state.compose_files = self.compose_files;
+        state.env_files = self.env_files;
+        state.images = self.images;
+        state.last_deploy = self.last_deploy;
+        state.encrypted_env = self.encrypted_env;
+    }
+}
+
+impl RemoteState {
+    /// Read the state file from `remote_dir`. Returns `None` when it is
+    /// missing or unreadable (e.g. deployed by an older dcd).
+    pub async fn load(
+        executor: &mut (dyn ComposeExec + Send),
+        remote_dir: &Path,
+    ) -> DeployResult<Option<Self>> {
+        let path = remote_dir.join(STATE_FILE);
+        let result = executor
+            .execute_command(&format!(
+                "cat {} 2>/dev/null",
+                shell_quote(&path.to_string_lossy())
+            ))
+            .await
+            .map_err(|e| DeployError::Deployment(format!("Failed to read state file: {}", e)))?;
+        if !result.is_success() {
+            tracing::debug!("No deployment state found at {}", path.display());
+            return Ok(None);
+        }
+        match serde_json::from_str(&result.output.to_stdout_string()?) {
+            Ok(state) => Ok(Some(state)),
+            Err(e) => {
+                tracing::warn!("Ignoring invalid state file {}: {}", path.display(), e);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Write the state file into `remote_dir`.
+    pub async fn save(
+        &self,
+        executor: &mut (dyn ComposeExec + Send),
+        remote_dir: &Path,
+    ) -> DeployResult<()> {
+        let path = remote_dir.join(STATE_FILE);
+        executor
+            .execute_command(&format!(
+                "mkdir -p {}",
+                shell_quote(&remote_dir.to_string_lossy())
+            ))
+            .await
+            .map_err(|e| {
+                DeployError::Deployment(format!("Failed to create remote directory: {}", e))
+            })?;
+        let content = serde_json::to_vec_pretty(self)
+            .map_err(|e| DeployError::Deployment(format!("Failed to serialize state: {}", e)))?;
+        executor
+            .upload_content(&content, &path)
+            .await
+            .map_err(|e| DeployError::Deployment(format!("Failed to write state file: {}", e)))
+    }
+}
+
+/// Work out the compose project in `remote_dir` without a local checkout:
+/// prefer the recorded state, otherwise fall back to the default compose and
+/// env file names present on the server.
+pub async fn discover_remote_project(
+    executor: &mut (dyn ComposeExec + Send),
+    remote_dir: &Path,
+) -> DeployResult<RemoteState> {
+    if let Some(state) = RemoteState::load(executor, remote_dir).await? {
+        if !state.compose_files.is_empty() {
+            return Ok(state);
+        }
+    }
+
+    let result = executor
+        .execute_command(&format!(
+            "ls -1A {}",
+            shell_quote(&remote_dir.to_string_lossy())
+        ))
+        .await
+        .map_err(|e| DeployError::Deployment(format!("Failed to list remote directory: {}", e)))?;
+    if !result.is_success() {
+        return Err(DeployError::Configuration(format!(
+            "Remote directory {} does not exist or is not readable",
+            remote_dir.display()
+        )));
+    }
+    let listing = result.output.to_stdout_string()?;
+    let state = project_from_listing(&listing);
+    if state.compose_files.is_empty() {
+        return Err(DeployError::Configuration(format!(
+            "No compose file found in {}",
+            remote_dir.display()
+        )));
+    }
+    tracing::info!(
+        "No deployment state found, using remote files: {}",
+        state
+            .compose_files
+            .iter()
+            .chain(&state.env_files)
+            .map(|p| p.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    Ok(state)
+}
+
+/// Pick the compose file and env files from a directory listing.
+fn project_from_listing(listing: &str) -> RemoteState {
+    let names: Vec<&str> = listing.lines().map(str::trim).collect();
+    let compose_files = DEFAULT_COMPOSE_FILES
+        .iter()
+        .find(|name| names.contains(name))
+        .map(|name| vec![PathBuf::from(name)])
+        .unwrap_or_default();
+    let env_files = [".env", super::DCD_ENV_FILE]
+        .iter()
+        .filter(|name| names.contains(name))
+        .map(PathBuf::from)
+        .collect();
+    RemoteState {
+        compose_files,
+        env_files,
+        ..Default::default()
+    }
+}
+
+/// Environment that activates `profiles` for compose commands.
+pub fn profiles_env(profiles: &[String]) -> HashMap<String, String> {
+    let mut env = HashMap::new();
+    if !profiles.is_empty() {
+        env.insert("COMPOSE_PROFILES".to_string(), profiles.join(","));
+    }
+    env
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_roundtrip_and_defaults() {
+        let state = RemoteState {
+            project: "app".to_string(),
+            profiles: vec!["web".to_string(), "worker".to_string()],
+            compose_files: vec![PathBuf::from("docker-compose.yml")],
+            env_files: vec![PathBuf::from(".env.dcd")],
+            pending: Some(PendingDeploy {
+                paths: vec![PathBuf::from("/opt/app/config")],
+                previous: None,
+            }),
+            images: BTreeMap::from([(
+                "web".to_string(),
+                ServiceImage {
+                    image: "nginx:1.27".to_string(),
+                    id: "sha256:c3d4".to_string(),
+                },
+            )]),
+            last_deploy: Some(DeployRecord {
+                at: 1_700_000_000,
+                by: "alice".to_string(),
+                commit: Some("4f9c2d1".to_string()),
+                dcd_version: "0.4.2".to_string(),
+                compose_hashes: BTreeMap::from([(
+                    PathBuf::from("docker-compose.yml"),
+                    "9f86d081".to_string(),
+                )]),
+                env_hashes: BTreeMap::from([(PathBuf::from(".env.prod"), "2c26b46b".to_string())]),
+            }),
+            encrypted_env: true,
+            rollback: None,
+        };
+        let json = serde_json::to_string(&state).unwrap();
+        assert_eq!(serde_json::from_str::<RemoteState>(&json).unwrap(), state);
+        // Unknown or missing fields must not break older/newer state files
+        assert_eq!(
+            serde_json::from_str::<RemoteState>(r#"{"future": 1}"#).unwrap(),
+            RemoteState::default()
+        );
+    }
+
+    #[test]
+    fn test_rollback_point_roundtrip() {
+        let deployed = RemoteState {
+            profiles: vec!["web".to_string()],
+            compose_files: vec![PathBuf::from("docker-compose.yml")],
+            env_files: vec![PathBuf::from(".env.dcd")],
+            encrypted_env: true,
+            ..Default::default()
+        };
+        let point = RollbackPoint::from_state(&deployed).unwrap();
+        let mut state = RemoteState {
+            compose_files: vec![PathBuf::from("compose.yaml")],
+            ..Default::default()
+        };
+        point.apply_to(&mut state);
+        assert_eq!(state, deployed);
+        assert!(RollbackPoint::from_state(&RemoteState::default()).is_none());
+    }
+
+    #[test]
+    fn test_project_from_listing() {
+        let state =
+            project_from_listing("data\n.env\ndocker-compose.yml\ncompose.yaml\n.env.dcd\n");
+        assert_eq!(state.compose_files, vec![PathBuf::from("compose.yaml")]);
+        assert_eq!(
+            state.env_files,
+            vec![PathBuf::from(".env"), PathBuf::from(".env.dcd")]
+        );
+        assert!(project_from_listing("README.md\n").compose_files.is_empty());
+    }
+
+    #[test]
+    fn test_profiles_env() {
+        assert!(profiles_env(&[]).is_empty());
+        let env = profiles_env(&["web".to_string(), "worker".to_string()]);
+        assert_eq!(env.get("COMPOSE_PROFILES").unwrap(), "web,worker");
+    }
+
+    #[test]
+    fn test_deploy_record_summary() {
+        let record = DeployRecord {
+            at: 1_000_000,
+            by: "alice".to_string(),
+            commit: Some("4f9c2d1".to_string()),
+            dcd_version: "0.4.2".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            record.summary(1_000_000 + 2 * 3600 + 59),
+            "2h ago by alice, commit 4f9c2d1, dcd 0.4.2"
+        );
+        let record = DeployRecord {
+            commit: None,
+            ..record
+        };
+        assert_eq!(record.summary(1_000_030), "30s ago by alice, dcd 0.4.2");
+        assert_eq!(format_ago(3 * 86400), "3d ago");
+    }
+}
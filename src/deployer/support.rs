@@ -0,0 +1,224 @@
+//! Diagnostics gathered from a deployment for bug reports and incident
+//! reviews (`dcd support-bundle`).
+//!
+//! Every command is allowed to fail; its exit code and stderr end up in the
+//! bundle instead. Values of secret variables are masked in all output, and
+//! values of secret-looking keys in the compose config.
+
+use super::compose_command;
+use super::state::STATE_FILE;
+use super::sync::{looks_secret, redact_text, Redaction};
+use super::types::ComposeExec;
+use crate::executor::{shell_quote, Redactor};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// One file of the bundle and the command that produced it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BundleEntry {
+    /// File name inside the bundle
+    pub name: &'static str,
+    pub command: String,
+    /// `None` if the command could not be run at all
+    pub exit_code: Option<u32>,
+    /// stdout followed by stderr, redacted
+    pub content: String,
+}
+
+/// The compose project a bundle is collected for.
+pub struct BundleTarget<'a> {
+    pub remote_dir: &'a Path,
+    /// Relative to `remote_dir`
+    pub compose_files: &'a [PathBuf],
+    /// Relative to `remote_dir`
+    pub env_files: &'a [PathBuf],
+    /// Environment of the compose commands (profiles, decrypted env)
+    pub env: &'a HashMap<String, String>,
+    /// Variables to mask besides those whose names look secret
+    pub secret_names: &'a [String],
+}
+
+/// Run the diagnostic commands for `target` and return their redacted
+/// output, with `log_tail` lines of logs per service.
+pub async fn collect(
+    executor: &mut (dyn ComposeExec + Send),
+    target: &BundleTarget<'_>,
+    log_tail: u32,
+) -> Vec<BundleEntry> {
+    let no_env = HashMap::new();
+    let mut redactor = Redactor::default();
+    redactor.add(secret_values(target.env, target.secret_names));
+    for env_file in target.env_files {
+        let path = target.remote_dir.join(env_file);
+        let (_, content) = run(executor, &cat(&path), &no_env).await;
+        let env = parse_env(&content);
+        redactor.add(secret_values(&env, target.secret_names));
+    }
+
+    let compose = |subcommand: &str| {
+        compose_command(
+            target.remote_dir,
+            target.compose_files,
+            target.env_files,
+            subcommand,
+        )
+    };
+    let commands = [
+        (
+            "host.txt",
+            "uname -a; cat /etc/os-release; uptime".to_string(),
+            &no_env,
+        ),
+        ("docker-info.txt", "docker info".to_string(), &no_env),
+        ("df.txt", "df -h".to_string(), &no_env),
+        (
+            "dcd-state.json",
+            cat(&target.remote_dir.join(STATE_FILE)),
+            &no_env,
+        ),
+        ("compose-config.yml", compose("config"), target.env),
+        ("compose-ps.txt", compose("ps -a"), target.env),
+        (
+            "compose-logs.txt",
+            compose(&format!("logs --no-color --timestamps --tail {}", log_tail)),
+            target.env,
+        ),
+    ];
+
+    let mut entries = Vec::with_capacity(commands.len());
+    for (name, command, env) in commands {
+        let (exit_code, content) = run(executor, &command, env).await;
+        let mut content = redactor.redact(&content);
+        if name == "compose-config.yml" {
+            content = redact_text(&content, Redaction::SecretKeys);
+        }
+        entries.push(BundleEntry {
+            name,
+            command,
+            exit_code,
+            content,
+        });
+    }
+    entries
+}
+
+/// Index of the bundle: each file with the command and its exit status.
+pub fn render_index(entries: &[BundleEntry]) -> String {
+    entries
+        .iter()
+        .map(|entry| {
+            let status = match entry.exit_code {
+                Some(code) => format!("exit {}", code),
+                None => "not run".to_string(),
+            };
+            format!("{}\t{}\t{}\n", entry.name, status, entry.command)
+        })
+        .collect()
+}
+
+/// Values of the variables in `env` that look secret or are in `secret_names`.
+pub(crate) fn secret_values<'a>(
+    env: &'a HashMap<String, String>,
+    secret_names: &'a [String],
+) -> impl Iterator<Item = &'a String> {
+    env.iter()
+        .filter(|(name, _)| looks_secret(name) || secret_names.contains(name))
+        .map(|(_, value)| value)
+}
+
+/// `NAME=value` lines of an env file, quotes around values removed.
+fn parse_env(content: &str) -> HashMap<String, String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(name, value)| {
+            let name = name.trim();
+            let name = name.strip_prefix("export ").unwrap_or(name);
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+                .unwrap_or(value);
+            (name.trim().to_string(), value.to_string())
+        })
+        .collect()
+}
+
+fn cat(path: &Path) -> String {
+    format!("cat {}", shell_quote(&path.to_string_lossy()))
+}
+
+/// Exit code and combined output of `command`; failures to run it are
+/// recorded as the output.
+async fn run(
+    executor: &mut (dyn ComposeExec + Send),
+    command: &str,
+    env: &HashMap<String, String>,
+) -> (Option<u32>, String) {
+    match executor.execute_command_with_env(command, env).await {
+        Ok(result) => {
+            let mut content = String::from_utf8_lossy(&result.output.stdout).into_owned();
+            content.push_str(&String::from_utf8_lossy(&result.output.stderr));
+            (Some(result.output.exit_code), content)
+        }
+        Err(e) => {
+            tracing::debug!("Support bundle command '{}' failed: {}", command, e);
+            (None, format!("Failed to run: {}\n", e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{CommandMatch, MemoryFs, MockExecutor};
+
+    #[tokio::test]
+    async fn test_collect_redacts_secrets() {
+        let fs = MemoryFs::new();
+        fs.insert(
+            "/opt/app/.env",
+            "DB_PASSWORD=\"hunter22\"\nSTRIPE=sk_live_abc\nPORT=8080\n",
+        );
+        fs.insert("/opt/app/.dcd-state.json", "{}");
+        let mut executor = MockExecutor::with_fs(fs);
+        executor.respond_ok(
+            CommandMatch::Contains(" config".into()),
+            "services:\n  web:\n    environment:\n      DB_PASSWORD: hunter22\n      API_TOKEN: from-shell\n      PORT: \"8080\"\n",
+        );
+        executor.respond_ok(
+            CommandMatch::Contains(" logs ".into()),
+            "web | connecting with hunter22 and sk_live_abc on 8080\n",
+        );
+        executor.respond_err(CommandMatch::Prefix("docker info".into()), 1, "no docker");
+
+        let env = HashMap::new();
+        let target = BundleTarget {
+            remote_dir: Path::new("/opt/app"),
+            compose_files: &[PathBuf::from("docker-compose.yml")],
+            env_files: &[PathBuf::from(".env")],
+            env: &env,
+            secret_names: &["STRIPE".to_string()],
+        };
+        let entries = collect(&mut executor, &target, 100).await;
+        let entry = |name| entries.iter().find(|entry| entry.name == name).unwrap();
+
+        let config = &entry("compose-config.yml").content;
+        assert!(config.contains("DB_PASSWORD: <redacted>"));
+        assert!(config.contains("API_TOKEN: <redacted>"));
+        assert!(config.contains("PORT: \"8080\""));
+        assert_eq!(
+            entry("compose-logs.txt").content,
+            "web | connecting with <redacted> and <redacted> on 8080\n"
+        );
+        assert!(entry("compose-logs.txt").command.contains("--tail 100"));
+        assert_eq!(entry("docker-info.txt").exit_code, Some(1));
+        assert_eq!(entry("dcd-state.json").content, "{}");
+        // Commands the mock does not know are recorded as not run
+        assert_eq!(entry("df.txt").exit_code, None);
+        assert!(render_index(&entries).contains("docker-info.txt\texit 1\tdocker info\n"));
+    }
+}
@@ -0,0 +1,150 @@
+//! Uploading many files of a directory as one tar.gz archive.
+//!
+//! Per-file SFTP uploads pay a round trip and a file open for every file,
+//! which dominates for trees of many small files (static sites, translations,
+//! node_modules-like assets). Past [`ARCHIVE_MIN_FILES`] changed files they
+//! are packed locally with `tar`, uploaded once and extracted on the server.
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncWriteExt;
+
+/// Changed files of a directory from which an archive is uploaded instead
+pub const ARCHIVE_MIN_FILES: usize = 32;
+
+/// Total size above which files are uploaded one by one anyway: the archive
+/// would need as much local temp space, and a failed upload starts over.
+pub const ARCHIVE_MAX_BYTES: u64 = 512 << 20;
+
+/// Name of the archive while it sits in the target directory
+pub const REMOTE_ARCHIVE_NAME: &str = ".dcd-upload.tar.gz";
+
+/// Whether `count` changed files of `total_bytes` go up as one archive.
+pub fn use_archive(count: usize, total_bytes: u64) -> bool {
+    count >= ARCHIVE_MIN_FILES && total_bytes <= ARCHIVE_MAX_BYTES
+}
+
+/// A local archive, removed again when dropped.
+pub struct LocalArchive {
+    pub path: PathBuf,
+}
+
+impl LocalArchive {
+    /// Pack `files` (relative to `root`) into a new tar.gz in the temp directory.
+    pub async fn create(root: &Path, files: &[PathBuf]) -> Result<Self, String> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.subsec_nanos())
+            .unwrap_or_default();
+        let archive = Self {
+            path: std::env::temp_dir().join(format!(
+                "dcd-upload-{}-{}.tar.gz",
+                std::process::id(),
+                nanos
+            )),
+        };
+
+        // Names go through stdin, NUL separated, so any file name works
+        // `-h` packs what symlinks point to, as the per-file upload does
+        let mut child = tokio::process::Command::new("tar")
+            .arg("-czhf")
+            .arg(&archive.path)
+            .arg("-C")
+            .arg(root)
+            .args(["--null", "-T", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => "`tar` is not installed".to_string(),
+                _ => e.to_string(),
+            })?;
+        let mut names = Vec::new();
+        for file in files {
+            names.extend_from_slice(file.as_os_str().as_encoded_bytes());
+            names.push(0);
+        }
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(&names).await.map_err(|e| e.to_string())?;
+        }
+        let output = child.wait_with_output().await.map_err(|e| e.to_string())?;
+        if !output.status.success() {
+            return Err(format!(
+                "tar failed ({}): {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(archive)
+    }
+}
+
+impl Drop for LocalArchive {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                tracing::debug!("Failed to remove {}: {}", self.path.display(), e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_use_archive() {
+        assert!(!use_archive(ARCHIVE_MIN_FILES - 1, 1024));
+        assert!(use_archive(ARCHIVE_MIN_FILES, 1024));
+        assert!(!use_archive(1000, ARCHIVE_MAX_BYTES + 1));
+    }
+
+    #[tokio::test]
+    async fn test_create_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("sub dir")).unwrap();
+        std::fs::write(dir.path().join("a.txt"), "a").unwrap();
+        std::fs::write(dir.path().join("sub dir/b.txt"), "b").unwrap();
+        std::fs::write(dir.path().join("skipped.txt"), "c").unwrap();
+        std::os::unix::fs::symlink("a.txt", dir.path().join("link.txt")).unwrap();
+
+        let files = [
+            PathBuf::from("a.txt"),
+            PathBuf::from("link.txt"),
+            PathBuf::from("sub dir/b.txt"),
+        ];
+        let archive = LocalArchive::create(dir.path(), &files).await.unwrap();
+        let listing = std::process::Command::new("tar")
+            .arg("-tzf")
+            .arg(&archive.path)
+            .output()
+            .unwrap();
+        let mut names: Vec<_> = String::from_utf8(listing.stdout)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect();
+        names.sort();
+        assert_eq!(names, ["a.txt", "link.txt", "sub dir/b.txt"]);
+
+        let extracted = tempfile::tempdir().unwrap();
+        let status = std::process::Command::new("tar")
+            .arg("-xzf")
+            .arg(&archive.path)
+            .arg("-C")
+            .arg(extracted.path())
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let link = extracted.path().join("link.txt");
+        assert!(!link.symlink_metadata().unwrap().is_symlink());
+        assert_eq!(std::fs::read_to_string(link).unwrap(), "a");
+
+        let path = archive.path.clone();
+        drop(archive);
+        assert!(!path.exists());
+    }
+}
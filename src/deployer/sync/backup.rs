@@ -0,0 +1,194 @@
+use crate::deployer::types::{ComposeExec, DeployError, DeployResult};
+use crate::deployer::{BACKUP_SUFFIX, PREVIOUS_SUFFIX};
+use crate::executor::shell_quote;
+use std::path::{Path, PathBuf};
+
+/// Copy an existing remote file to `<path>.backup` before it gets overwritten.
+/// An existing backup is kept: it holds the last good version if an earlier
+/// deployment was aborted.
+pub async fn backup_file(executor: &mut (dyn ComposeExec + Send), path: &Path) -> DeployResult<()> {
+    backup_files(executor, &[path.to_path_buf()]).await
+}
+
+/// [`backup_file`] for many files, batched into few remote commands.
+pub async fn backup_files(
+    executor: &mut (dyn ComposeExec + Send),
+    paths: &[PathBuf],
+) -> DeployResult<()> {
+    for chunk in paths.chunks(BACKUP_BATCH_SIZE) {
+        let result = executor
+            .execute_command(&backup_command(chunk))
+            .await
+            .map_err(|e| DeployError::FileSync(format!("Failed to back up file: {}", e)))?;
+        if !result.is_success() {
+            let target = match chunk {
+                [path] => path.display().to_string(),
+                _ => format!("{} files", chunk.len()),
+            };
+            return Err(DeployError::FileSync(format!(
+                "Failed to back up {}: {}",
+                target,
+                result.output.to_stderr_string()?.trim()
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Files backed up per remote command, keeping command lines short.
+const BACKUP_BATCH_SIZE: usize = 200;
+
+fn backup_command(paths: &[PathBuf]) -> String {
+    let quoted: Vec<String> = paths
+        .iter()
+        .map(|path| shell_quote(&path.to_string_lossy()))
+        .collect();
+    format!(
+        "for f in {}; do if [ -f \"$f\" ] && [ ! -e \"$f{suffix}\" ]; then cp -p \"$f\" \"$f{suffix}\" || exit 1; fi; done",
+        quoted.join(" "),
+        suffix = BACKUP_SUFFIX
+    )
+}
+
+/// `find` over the copies with `suffix` belonging to `paths` (files or synced
+/// directories), running `action` on each and printing its path.
+fn find_copies_command(paths: &[PathBuf], suffix: &str, action: &str) -> String {
+    let roots: Vec<String> = paths
+        .iter()
+        .flat_map(|path| {
+            [
+                shell_quote(&path.to_string_lossy()),
+                shell_quote(&format!("{}{}", path.display(), suffix)),
+            ]
+        })
+        .collect();
+    format!(
+        "find {} -type f -name '*{}' -print -exec {} 2>/dev/null; true",
+        roots.join(" "),
+        suffix,
+        action
+    )
+}
+
+async fn run_on_copies(
+    executor: &mut (dyn ComposeExec + Send),
+    paths: &[PathBuf],
+    suffix: &str,
+    action: &str,
+) -> DeployResult<Vec<PathBuf>> {
+    if paths.is_empty() {
+        return Ok(Vec::new());
+    }
+    let result = executor
+        .execute_command(&find_copies_command(paths, suffix, action))
+        .await
+        .map_err(|e| DeployError::FileSync(format!("Failed to process backups: {}", e)))?;
+    let mut found: Vec<PathBuf> = result
+        .output
+        .to_stdout_string()?
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| PathBuf::from(line.trim_end_matches(suffix)))
+        .collect();
+    found.sort();
+    found.dedup();
+    Ok(found)
+}
+
+/// `find -exec` action renaming a copy with suffix `from` to suffix `to`
+/// (an empty `to` moves it back over the original).
+fn rename_action(from: &str, to: &str) -> String {
+    format!("sh -c 'mv -f \"$1\" \"${{1%{}}}{}\"' _ {{}} \\;", from, to)
+}
+
+/// Move the backups under `paths` back into place. Returns the restored files.
+pub async fn restore_backups(
+    executor: &mut (dyn ComposeExec + Send),
+    paths: &[PathBuf],
+) -> DeployResult<Vec<PathBuf>> {
+    let action = rename_action(BACKUP_SUFFIX, "");
+    run_on_copies(executor, paths, BACKUP_SUFFIX, &action).await
+}
+
+/// Delete the backups under `paths` once the deployment went through.
+pub async fn discard_backups(
+    executor: &mut (dyn ComposeExec + Send),
+    paths: &[PathBuf],
+) -> DeployResult<Vec<PathBuf>> {
+    run_on_copies(executor, paths, BACKUP_SUFFIX, "rm -f {} \\;").await
+}
+
+/// Turn the backups under `paths` into `*.previous` copies that
+/// `dcd rollback` can return to. Returns the files that have one.
+pub async fn keep_backups_as_previous(
+    executor: &mut (dyn ComposeExec + Send),
+    paths: &[PathBuf],
+) -> DeployResult<Vec<PathBuf>> {
+    let action = rename_action(BACKUP_SUFFIX, PREVIOUS_SUFFIX);
+    run_on_copies(executor, paths, BACKUP_SUFFIX, &action).await
+}
+
+/// Move the `*.previous` copies under `paths` back into place.
+pub async fn restore_previous(
+    executor: &mut (dyn ComposeExec + Send),
+    paths: &[PathBuf],
+) -> DeployResult<Vec<PathBuf>> {
+    let action = rename_action(PREVIOUS_SUFFIX, "");
+    run_on_copies(executor, paths, PREVIOUS_SUFFIX, &action).await
+}
+
+/// Delete the `*.previous` copies under `paths`.
+pub async fn discard_previous(
+    executor: &mut (dyn ComposeExec + Send),
+    paths: &[PathBuf],
+) -> DeployResult<Vec<PathBuf>> {
+    run_on_copies(executor, paths, PREVIOUS_SUFFIX, "rm -f {} \\;").await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_command() {
+        let cmd = backup_command(&[
+            PathBuf::from("/opt/app/config/a.conf"),
+            PathBuf::from("/opt/app/config/it's.conf"),
+        ]);
+        assert_eq!(
+            cmd,
+            "for f in /opt/app/config/a.conf '/opt/app/config/it'\\''s.conf'; do \
+             if [ -f \"$f\" ] && [ ! -e \"$f.backup\" ]; then cp -p \"$f\" \"$f.backup\" || exit 1; fi; done"
+        );
+    }
+
+    #[test]
+    fn test_rename_action() {
+        assert_eq!(
+            rename_action(".backup", ".previous"),
+            "sh -c 'mv -f \"$1\" \"${1%.backup}.previous\"' _ {} \\;"
+        );
+        assert_eq!(
+            rename_action(".previous", ""),
+            "sh -c 'mv -f \"$1\" \"${1%.previous}\"' _ {} \\;"
+        );
+    }
+
+    #[test]
+    fn test_find_backups_command() {
+        let cmd = find_copies_command(
+            &[
+                PathBuf::from("/opt/app/docker-compose.yml"),
+                PathBuf::from("/opt/app/my config"),
+            ],
+            BACKUP_SUFFIX,
+            "rm -f {} \\;",
+        );
+        assert_eq!(
+            cmd,
+            "find /opt/app/docker-compose.yml /opt/app/docker-compose.yml.backup \
+             '/opt/app/my config' '/opt/app/my config.backup' -type f -name '*.backup' \
+             -print -exec rm -f {} \\; 2>/dev/null; true"
+        );
+    }
+}
@@ -0,0 +1,318 @@
+use crate::executor::redact::REDACTED;
+use std::path::PathBuf;
+
+/// Lines of context shown around each change, as in `diff -u`.
+const CONTEXT_LINES: usize = 3;
+
+/// Files whose line count product exceeds this are reported as changed
+/// without a line diff, to bound the memory of the comparison.
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// Name fragments that mark a variable as secret, so its value is not echoed.
+const SECRET_MARKERS: [&str; 8] = [
+    "SECRET",
+    "PASSWORD",
+    "PASSWD",
+    "TOKEN",
+    "KEY",
+    "PRIVATE",
+    "CREDENTIAL",
+    "AUTH",
+];
+
+/// Whether a variable or key name looks like it holds a secret.
+pub fn looks_secret(name: &str) -> bool {
+    let upper = name.to_uppercase();
+    SECRET_MARKERS.iter().any(|marker| upper.contains(marker))
+}
+
+/// Differences between the deployed copy of a file and the local one that is
+/// about to replace it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileDiff {
+    pub remote_path: PathBuf,
+    pub local_path: PathBuf,
+    /// Empty when the files were too large to compare line by line
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffLine {
+    /// `@@ -a,b +c,d @@` header of a hunk
+    Hunk(String),
+    Context(String),
+    Removed(String),
+    Added(String),
+}
+
+impl DiffLine {
+    /// The same line with its text passed through `f`.
+    pub fn map_text(self, f: impl Fn(&str) -> String) -> Self {
+        match self {
+            DiffLine::Hunk(text) => DiffLine::Hunk(text),
+            DiffLine::Context(text) => DiffLine::Context(f(&text)),
+            DiffLine::Removed(text) => DiffLine::Removed(f(&text)),
+            DiffLine::Added(text) => DiffLine::Added(f(&text)),
+        }
+    }
+}
+
+/// Which values to hide before a diff is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Redaction {
+    /// Env files: every value
+    AllValues,
+    /// Compose files: values of keys that look secret
+    SecretKeys,
+}
+
+/// Unified diff of `old` to `new` with [`CONTEXT_LINES`] lines of context,
+/// redacted line by line after comparing, so changed secrets still show up
+/// as changed.
+pub fn unified_diff(old: &str, new: &str, redaction: Redaction) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    if old.len().saturating_mul(new.len()) > MAX_DIFF_CELLS {
+        return Vec::new();
+    }
+    let ops = diff_ops(&old, &new);
+    let redact = |line: &str| redact_line(line, redaction);
+
+    // Lines of the old and new file before each step
+    let mut before = Vec::with_capacity(ops.len());
+    let (mut old_line, mut new_line) = (0, 0);
+    for op in &ops {
+        before.push((old_line, new_line));
+        match op {
+            Op::Equal(_) => (old_line, new_line) = (old_line + 1, new_line + 1),
+            Op::Removed(_) => old_line += 1,
+            Op::Added(_) => new_line += 1,
+        }
+    }
+
+    let mut lines = Vec::new();
+    let changes: Vec<usize> = (0..ops.len())
+        .filter(|&i| !matches!(ops[i], Op::Equal(_)))
+        .collect();
+    let mut next = 0;
+    while next < changes.len() {
+        // Group changes whose context overlaps into one hunk
+        let start = changes[next].saturating_sub(CONTEXT_LINES);
+        let mut end = changes[next];
+        while next < changes.len() && changes[next] <= end + 2 * CONTEXT_LINES + 1 {
+            end = changes[next];
+            next += 1;
+        }
+        let end = (end + CONTEXT_LINES + 1).min(ops.len());
+
+        let (old_start, new_start) = before[start];
+        let old_count = ops[start..end]
+            .iter()
+            .filter(|op| !matches!(op, Op::Added(_)))
+            .count();
+        let new_count = ops[start..end]
+            .iter()
+            .filter(|op| !matches!(op, Op::Removed(_)))
+            .count();
+        lines.push(DiffLine::Hunk(format!(
+            "@@ -{} +{} @@",
+            hunk_range(old_start, old_count),
+            hunk_range(new_start, new_count)
+        )));
+        for op in &ops[start..end] {
+            lines.push(match *op {
+                Op::Equal(i) => DiffLine::Context(redact(old[i])),
+                Op::Removed(i) => DiffLine::Removed(redact(old[i])),
+                Op::Added(j) => DiffLine::Added(redact(new[j])),
+            });
+        }
+    }
+    lines
+}
+
+/// One step of the edit script, with the line index it refers to.
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Equal(usize),
+    Removed(usize),
+    Added(usize),
+}
+
+// Edit script from a longest-common-subsequence table; compose and env files
+// are small enough for the quadratic table
+fn diff_ops(old: &[&str], new: &[&str]) -> Vec<Op> {
+    let (n, m) = (old.len(), new.len());
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::with_capacity(n + m);
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old[i] == new[j] {
+            ops.push(Op::Equal(i));
+            i += 1;
+            j += 1;
+        } else if j < m && (i == n || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            ops.push(Op::Added(j));
+            j += 1;
+        } else {
+            ops.push(Op::Removed(i));
+            i += 1;
+        }
+    }
+    // Show removals before additions within each run of changes, like diff -u
+    let mut start = 0;
+    while start < ops.len() {
+        if matches!(ops[start], Op::Equal(_)) {
+            start += 1;
+            continue;
+        }
+        let end = ops[start..]
+            .iter()
+            .position(|op| matches!(op, Op::Equal(_)))
+            .map_or(ops.len(), |offset| start + offset);
+        ops[start..end].sort_by_key(|op| matches!(op, Op::Added(_)));
+        start = end;
+    }
+    ops
+}
+
+/// `start,count` of a hunk in one file; `start` is the line before the hunk
+/// when it has no lines there.
+fn hunk_range(before: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", before),
+        1 => (before + 1).to_string(),
+        _ => format!("{},{}", before + 1, count),
+    }
+}
+
+/// `text` with [`redact_line`] applied to every line.
+pub fn redact_text(text: &str, redaction: Redaction) -> String {
+    text.lines()
+        .map(|line| redact_line(line, redaction) + "\n")
+        .collect()
+}
+
+/// Replace the value of a `NAME=value`, `- NAME=value` or `name: value`
+/// line with a placeholder if `redaction` covers it.
+fn redact_line(line: &str, redaction: Redaction) -> String {
+    let trimmed = line.trim_start();
+    if trimmed.starts_with('#') {
+        return line.to_string();
+    }
+    let indent = &line[..line.len() - trimmed.len()];
+    let (prefix, entry) = match trimmed.strip_prefix("- ") {
+        Some(entry) => ("- ", entry),
+        None => ("", trimmed),
+    };
+    let separator = match (entry.find('='), entry.find(": ")) {
+        (Some(eq), Some(colon)) => eq.min(colon),
+        (Some(eq), None) => eq,
+        (None, Some(colon)) => colon,
+        // `key:` alone opens a nested block
+        (None, None) => return line.to_string(),
+    };
+    let key = entry[..separator].trim().trim_matches(['"', '\'']);
+    let key = key.strip_prefix("export ").unwrap_or(key);
+    let hide = match redaction {
+        Redaction::AllValues => true,
+        Redaction::SecretKeys => looks_secret(key),
+    };
+    if !hide
+        || entry[separator..]
+            .trim_start_matches(['=', ':'])
+            .trim()
+            .is_empty()
+    {
+        return line.to_string();
+    }
+    let delimiter = if entry[separator..].starts_with('=') {
+        "="
+    } else {
+        ": "
+    };
+    format!(
+        "{}{}{}{}{}",
+        indent,
+        prefix,
+        &entry[..separator],
+        delimiter,
+        REDACTED
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(lines: &[DiffLine]) -> String {
+        lines
+            .iter()
+            .map(|line| match line {
+                DiffLine::Hunk(text) => format!("{}\n", text),
+                DiffLine::Context(text) => format!(" {}\n", text),
+                DiffLine::Removed(text) => format!("-{}\n", text),
+                DiffLine::Added(text) => format!("+{}\n", text),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        let old = "services:\n  web:\n    image: nginx:1.27\n    ports:\n      - 80:80\n    environment:\n      - DB_PASSWORD=hunter2\n      - LOG_LEVEL=info\n";
+        let new = "services:\n  web:\n    image: nginx:1.28\n    ports:\n      - 80:80\n    environment:\n      - DB_PASSWORD=correct-horse\n      - LOG_LEVEL=info\n";
+        assert_eq!(
+            render(&unified_diff(old, new, Redaction::SecretKeys)),
+            "@@ -1,8 +1,8 @@\n \
+             services:\n   \
+             web:\n\
+             -    image: nginx:1.27\n\
+             +    image: nginx:1.28\n     \
+             ports:\n       \
+             - 80:80\n     \
+             environment:\n\
+             -      - DB_PASSWORD=<redacted>\n\
+             +      - DB_PASSWORD=<redacted>\n       \
+             - LOG_LEVEL=info\n"
+        );
+        assert!(unified_diff(old, old, Redaction::SecretKeys).is_empty());
+    }
+
+    #[test]
+    fn test_unified_diff_hunks() {
+        let old: String = (1..=20).map(|i| format!("line{}\n", i)).collect();
+        let new = old.replace("line2\n", "").replace("line18\n", "changed\n");
+        assert_eq!(
+            render(&unified_diff(&old, &new, Redaction::AllValues)),
+            "@@ -1,5 +1,4 @@\n line1\n-line2\n line3\n line4\n line5\n\
+             @@ -15,6 +14,6 @@\n line15\n line16\n line17\n-line18\n+changed\n line19\n line20\n"
+        );
+    }
+
+    #[test]
+    fn test_redact_line() {
+        let env = |line| redact_line(line, Redaction::AllValues);
+        assert_eq!(env("API_URL=https://x"), "API_URL=<redacted>");
+        assert_eq!(env("export TOKEN=\"abc\""), "export TOKEN=<redacted>");
+        assert_eq!(env("# DB_PASSWORD=old"), "# DB_PASSWORD=old");
+        assert_eq!(env("EMPTY="), "EMPTY=");
+
+        let compose = |line| redact_line(line, Redaction::SecretKeys);
+        assert_eq!(
+            compose("      POSTGRES_PASSWORD: s3cret"),
+            "      POSTGRES_PASSWORD: <redacted>"
+        );
+        assert_eq!(compose("      - API_KEY=abc"), "      - API_KEY=<redacted>");
+        assert_eq!(compose("    image: nginx"), "    image: nginx");
+        assert_eq!(compose("    environment:"), "    environment:");
+    }
+}
@@ -1,9 +1,13 @@
+use crate::config::SecretSource;
 use crate::deployer::{
-    types::{DeployError, DeployResult},
+    secrets::expand_home,
+    types::{ComposeExec, DeployError, DeployResult},
     DCD_ENV_FILE,
 };
-use std::collections::HashMap;
+use crate::executor::shell_quote;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
+use std::process::Stdio;
 use tokio::fs;
 
 pub struct EnvFileManager {
@@ -21,10 +25,54 @@ impl EnvFileManager {
         }
     }
 
-    /// Generate .env.dcd file with consumed environment variables
-    pub async fn generate_dcd_env(&self) -> DeployResult<()> {
+    /// Fetch the values of `secrets` from their stores and add them to the
+    /// variables, overriding any of the same name. Values never appear in
+    /// logs or errors; failures name the source only.
+    pub async fn inject_secrets(
+        &mut self,
+        secrets: &BTreeMap<String, SecretSource>,
+    ) -> DeployResult<()> {
+        self.inject_secrets_with(secrets, |var| std::env::var(var).ok())
+            .await
+    }
+
+    /// [`Self::inject_secrets`] reading `env` sources through `env_var`.
+    async fn inject_secrets_with(
+        &mut self,
+        secrets: &BTreeMap<String, SecretSource>,
+        env_var: impl Fn(&str) -> Option<String>,
+    ) -> DeployResult<()> {
+        let project_dir = self
+            .dcd_env_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        for (name, source) in secrets {
+            let value = fetch_secret(source, &project_dir, &env_var)
+                .await
+                .map_err(|e| {
+                    DeployError::Environment(format!(
+                        "Failed to fetch secret {} from {}: {}",
+                        name,
+                        source.describe(),
+                        e
+                    ))
+                })?;
+            tracing::debug!("Fetched secret {} from {}", name, source.describe());
+            self.consumed_env.insert(name.clone(), value);
+        }
+        Ok(())
+    }
+
+    /// The variables, including injected secrets.
+    pub fn into_env(self) -> HashMap<String, String> {
+        self.consumed_env
+    }
+
+    /// Render the .env.dcd content, or `None` if there are no consumed variables.
+    pub fn render_dcd_env(&self) -> Option<String> {
         if self.consumed_env.is_empty() {
-            return Ok(());
+            return None;
         }
 
         let mut content = String::new();
@@ -40,6 +88,14 @@ impl EnvFileManager {
                 content.push_str(&format!("{}={}\n", key, escape_env_value(value)));
             }
         }
+        Some(content)
+    }
+
+    /// Generate .env.dcd file with consumed environment variables
+    pub async fn generate_dcd_env(&self) -> DeployResult<()> {
+        let Some(content) = self.render_dcd_env() else {
+            return Ok(());
+        };
 
         fs::write(&self.dcd_env_path, content).await.map_err(|e| {
             DeployError::Environment(format!("Failed to write {}: {}", DCD_ENV_FILE, e))
@@ -48,6 +104,76 @@ impl EnvFileManager {
         Ok(())
     }
 
+    /// Write .env.dcd straight to `remote_path` without touching the local
+    /// filesystem; the file is readable by its owner only (0600).
+    /// Returns whether a file was uploaded.
+    pub async fn upload_dcd_env(
+        &self,
+        executor: &mut (dyn ComposeExec + Send),
+        remote_path: &Path,
+    ) -> DeployResult<bool> {
+        let Some(content) = self.render_dcd_env() else {
+            return Ok(false);
+        };
+
+        // Recreate the file as 0600 before writing, so the values are never readable by others
+        let result = executor
+            .execute_command(&format!(
+                "install -m 600 /dev/null {}",
+                shell_quote(&remote_path.to_string_lossy())
+            ))
+            .await
+            .map_err(|e| DeployError::Environment(e.to_string()))?;
+        if !result.is_success() {
+            return Err(DeployError::Environment(format!(
+                "Failed to restrict permissions of {}: {}",
+                remote_path.display(),
+                result.output.to_stderr_string()?.trim()
+            )));
+        }
+        executor
+            .upload_content(content.as_bytes(), remote_path)
+            .await
+            .map_err(|e| {
+                DeployError::Environment(format!("Failed to upload {}: {}", DCD_ENV_FILE, e))
+            })?;
+        Ok(true)
+    }
+
+    /// Delete the locally generated .env.dcd; it holds resolved secrets and is
+    /// not needed once uploaded.
+    pub async fn remove_dcd_env(&self) -> DeployResult<()> {
+        match fs::remove_file(&self.dcd_env_path).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(DeployError::Environment(format!(
+                "Failed to remove {}: {}",
+                DCD_ENV_FILE, e
+            ))),
+        }
+    }
+
+    /// Whether git ignores .env.dcd in the project directory. `None` when the
+    /// directory is not a git work tree or git is unavailable.
+    pub async fn is_dcd_env_gitignored(&self) -> Option<bool> {
+        let dir = self.dcd_env_path.parent()?;
+        let status = tokio::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["check-ignore", "-q", DCD_ENV_FILE])
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .await
+            .ok()?;
+        // check-ignore: 0 = ignored, 1 = not ignored, 128 = error (e.g. no repo)
+        match status.code() {
+            Some(0) => Some(true),
+            Some(1) => Some(false),
+            _ => None,
+        }
+    }
+
     /// Compare environment variables between files
     pub async fn compare_env_files(
         &self,
@@ -120,6 +246,68 @@ impl EnvFileManager {
     }
 }
 
+/// Read the value of one secret on the local machine, environment variables
+/// through `env_var`.
+async fn fetch_secret(
+    source: &SecretSource,
+    project_dir: &Path,
+    env_var: &impl Fn(&str) -> Option<String>,
+) -> Result<String, String> {
+    let value = match source {
+        SecretSource::Env { var } => env_var(var).ok_or_else(|| "not set".to_string())?,
+        SecretSource::File { path } => {
+            let path = project_dir.join(expand_home(path));
+            fs::read_to_string(&path)
+                .await
+                .map_err(|e| format!("{}: {}", path.display(), e))?
+        }
+        SecretSource::Exec { command } => {
+            let mut cmd = tokio::process::Command::new("sh");
+            cmd.arg("-c").arg(command);
+            secret_command_output(cmd, project_dir, "sh").await?
+        }
+        SecretSource::Vault { path, field } => {
+            let mut cmd = tokio::process::Command::new("vault");
+            cmd.args(["kv", "get", &format!("-field={}", field), path]);
+            secret_command_output(cmd, project_dir, "vault").await?
+        }
+        SecretSource::Sops { file, key } => {
+            let mut cmd = tokio::process::Command::new("sops");
+            cmd.args(["--decrypt", "--extract", &format!("[\"{}\"]", key)])
+                .arg(expand_home(file));
+            secret_command_output(cmd, project_dir, "sops").await?
+        }
+    };
+    // Files and command output usually end with a newline that is not part of the value
+    let value = value.strip_suffix('\n').unwrap_or(&value);
+    Ok(value.strip_suffix('\r').unwrap_or(value).to_string())
+}
+
+// Standard output of a secret store command; stderr is only used for errors
+async fn secret_command_output(
+    mut cmd: tokio::process::Command,
+    project_dir: &Path,
+    program: &str,
+) -> Result<String, String> {
+    let output = cmd
+        .current_dir(project_dir)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => format!("`{}` is not installed", program),
+            _ => e.to_string(),
+        })?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} ({})",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    String::from_utf8(output.stdout).map_err(|_| "value is not valid UTF-8".to_string())
+}
+
 /// Normalize environment file content for comparison
 fn normalize_env_content(content: &str) -> String {
     content
@@ -141,7 +329,7 @@ fn normalize_env_content(content: &str) -> String {
 }
 
 /// Escape special characters in environment variable values
-fn escape_env_value(value: &str) -> String {
+pub(super) fn escape_env_value(value: &str) -> String {
     if value.contains(char::is_whitespace) || value.contains('\"') || value.contains('\'') {
         format!("\"{}\"", value.replace('\"', "\\\""))
     } else {
@@ -178,6 +366,95 @@ mod tests {
         assert!(content.contains("API_KEY=\"secret value with spaces\""));
     }
 
+    #[tokio::test]
+    async fn test_remove_dcd_env() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut env = HashMap::new();
+        env.insert("SECRET".to_string(), "value".to_string());
+        let manager = EnvFileManager::new(env, temp_dir.path());
+
+        manager.generate_dcd_env().await.unwrap();
+        assert!(manager.get_dcd_env_path().exists());
+        manager.remove_dcd_env().await.unwrap();
+        assert!(!manager.get_dcd_env_path().exists());
+        // Removing again is not an error
+        manager.remove_dcd_env().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_upload_dcd_env_skips_local_disk() {
+        let project_dir = TempDir::new().unwrap();
+        let remote_dir = TempDir::new().unwrap();
+        let mut env = HashMap::new();
+        env.insert("SECRET".to_string(), "value".to_string());
+        let manager = EnvFileManager::new(env, project_dir.path());
+
+        let mut executor = crate::executor::LocalCommandExecutor::new();
+        let remote_path = remote_dir.path().join(DCD_ENV_FILE);
+        assert!(manager
+            .upload_dcd_env(&mut executor, &remote_path)
+            .await
+            .unwrap());
+
+        let content = std::fs::read_to_string(&remote_path).unwrap();
+        assert!(content.contains("SECRET=value"));
+        assert!(!manager.get_dcd_env_path().exists());
+        let mode = std::os::unix::fs::PermissionsExt::mode(
+            &std::fs::metadata(&remote_path).unwrap().permissions(),
+        );
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[tokio::test]
+    async fn test_inject_secrets() {
+        let project_dir = TempDir::new().unwrap();
+        std::fs::write(project_dir.path().join("api_key"), "from-file\n").unwrap();
+        let mut env = HashMap::new();
+        env.insert("API_KEY".to_string(), "overridden".to_string());
+        let mut manager = EnvFileManager::new(env, project_dir.path());
+
+        let secrets = BTreeMap::from([
+            (
+                "API_KEY".to_string(),
+                SecretSource::File {
+                    path: PathBuf::from("api_key"),
+                },
+            ),
+            (
+                "DB_PASSWORD".to_string(),
+                SecretSource::Env {
+                    var: "DCD_TEST_INJECTED_SECRET".into(),
+                },
+            ),
+            (
+                "TOKEN".to_string(),
+                SecretSource::Exec {
+                    command: "printf 'from exec\\n'".into(),
+                },
+            ),
+        ]);
+        let env_var = |var: &str| (var == "DCD_TEST_INJECTED_SECRET").then(|| "from-env".into());
+        manager
+            .inject_secrets_with(&secrets, env_var)
+            .await
+            .unwrap();
+        let env = manager.into_env();
+        assert_eq!(env["API_KEY"], "from-file");
+        assert_eq!(env["DB_PASSWORD"], "from-env");
+        assert_eq!(env["TOKEN"], "from exec");
+
+        let mut manager = EnvFileManager::new(HashMap::new(), project_dir.path());
+        let failing = BTreeMap::from([(
+            "TOKEN".to_string(),
+            SecretSource::Exec {
+                command: "cat api_key; exit 3".into(),
+            },
+        )]);
+        let err = manager.inject_secrets(&failing).await.unwrap_err();
+        assert!(err.to_string().contains("Failed to fetch secret TOKEN"));
+        assert!(!err.to_string().contains("from-file"));
+    }
+
     #[tokio::test]
     async fn test_normalize_env_content() {
         let content = r#"
@@ -0,0 +1,305 @@
+use super::diff::looks_secret;
+use super::env::escape_env_value;
+use super::SyncPair;
+use crate::deployer::types::{ComposeExec, DeployError, DeployResult};
+use crate::executor::redact::REDACTED;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Suffix of the copy of each env file as dcd last deployed it, kept next to
+/// it on the server as the base of three-way merges.
+pub const ENV_BASE_SUFFIX: &str = ".dcd-base";
+
+/// Where the deployed copy of the env file at `remote_path` is kept.
+pub fn env_base_path(remote_path: &Path) -> PathBuf {
+    let mut name = remote_path.as_os_str().to_os_string();
+    name.push(ENV_BASE_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// A variable changed both locally and on the server since the last deploy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvConflict {
+    pub key: String,
+    /// Value at the last deploy; `None` when unset or unknown
+    pub base: Option<String>,
+    pub local: Option<String>,
+    pub remote: Option<String>,
+}
+
+impl fmt::Display for EnvConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |value: &Option<String>| match value {
+            None => "unset".to_string(),
+            Some(_) if looks_secret(&self.key) => REDACTED.to_string(),
+            Some(value) => format!("'{}'", value),
+        };
+        write!(
+            f,
+            "{}: deployed {}, local {}, server {}",
+            self.key,
+            show(&self.base),
+            show(&self.local),
+            show(&self.remote)
+        )
+    }
+}
+
+/// An env file edited on the server since dcd last deployed it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditedEnvFile {
+    pub local_path: PathBuf,
+    pub remote_path: PathBuf,
+    /// The local file with the server's edits merged in, or the variables
+    /// changed on both sides
+    pub merged: Result<String, Vec<EnvConflict>>,
+}
+
+/// `NAME=value` assignments of an env file in order, quotes around values
+/// removed.
+fn parse_assignments(content: &str) -> Vec<(String, String)> {
+    content.lines().filter_map(parse_assignment).collect()
+}
+
+fn parse_assignment(line: &str) -> Option<(String, String)> {
+    let line = line.trim();
+    if line.starts_with('#') {
+        return None;
+    }
+    let (name, value) = line.split_once('=')?;
+    let name = name.trim();
+    let name = name.strip_prefix("export ").unwrap_or(name).trim();
+    let value = value.trim();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .map(|v| v.replace("\\\"", "\""))
+        .or_else(|| {
+            value
+                .strip_prefix('\'')
+                .and_then(|v| v.strip_suffix('\''))
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| value.to_string());
+    Some((name.to_string(), value))
+}
+
+/// Three-way merge of the variables of an env file: changes made on only one
+/// side since `base` are taken, changes made on both to different values
+/// conflict. The result keeps the layout and comments of `local`, with
+/// variables added on the server appended.
+pub fn merge_env(base: &str, local: &str, remote: &str) -> Result<String, Vec<EnvConflict>> {
+    let base: BTreeMap<String, String> = parse_assignments(base).into_iter().collect();
+    let local_values: BTreeMap<String, String> = parse_assignments(local).into_iter().collect();
+    let remote_assignments = parse_assignments(remote);
+    let remote_values: BTreeMap<String, String> = remote_assignments.iter().cloned().collect();
+
+    let keys: BTreeSet<&String> = local_values.keys().chain(remote_values.keys()).collect();
+    let mut merged = BTreeMap::new();
+    let mut conflicts = Vec::new();
+    for key in keys {
+        let (b, l, r) = (base.get(key), local_values.get(key), remote_values.get(key));
+        let value = if l == b || l == r {
+            r
+        } else if r == b {
+            l
+        } else {
+            conflicts.push(EnvConflict {
+                key: key.clone(),
+                base: b.cloned(),
+                local: l.cloned(),
+                remote: r.cloned(),
+            });
+            continue;
+        };
+        merged.insert(key.clone(), value.cloned());
+    }
+    if !conflicts.is_empty() {
+        return Err(conflicts);
+    }
+
+    let mut content = String::new();
+    for line in local.lines() {
+        match parse_assignment(line) {
+            Some((key, value)) => match merged.get(&key) {
+                Some(Some(merged)) if *merged == value => content.push_str(line),
+                Some(Some(merged)) => {
+                    content.push_str(&format!("{}={}", key, escape_env_value(merged)))
+                }
+                // Removed on the server
+                _ => continue,
+            },
+            None => content.push_str(line),
+        }
+        content.push('\n');
+    }
+    for (key, value) in &remote_assignments {
+        if !local_values.contains_key(key) && matches!(merged.get(key), Some(Some(_))) {
+            content.push_str(&format!("{}={}\n", key, escape_env_value(value)));
+        }
+    }
+    Ok(content)
+}
+
+fn sha256_hex(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// The env files of `pairs` whose copy on the server no longer matches the
+/// hash recorded (by remote file name) at the last deploy and differs from
+/// the local one. Files deployed before hashes were recorded, or missing on
+/// the server, are not checked. Each comes merged against the deployed copy
+/// kept next to it; without that copy only variables set differently on
+/// both sides count as conflicts.
+pub async fn find_remote_edits(
+    executor: &mut (dyn ComposeExec + Send),
+    pairs: &[SyncPair],
+    deployed_hashes: &BTreeMap<PathBuf, String>,
+) -> DeployResult<Vec<EditedEnvFile>> {
+    let mut edited = Vec::new();
+    for pair in pairs {
+        let Some(deployed) = pair
+            .remote_path
+            .file_name()
+            .and_then(|name| deployed_hashes.get(Path::new(name)))
+        else {
+            continue;
+        };
+        let Ok(remote) = executor.download_content(&pair.remote_path).await else {
+            continue;
+        };
+        let remote_hash = sha256_hex(&remote);
+        if &remote_hash == deployed {
+            continue;
+        }
+        let local = tokio::fs::read(&pair.local_path).await.map_err(|e| {
+            DeployError::Environment(format!(
+                "Failed to read env file {}: {}",
+                pair.local_path.display(),
+                e
+            ))
+        })?;
+        if sha256_hex(&local) == remote_hash {
+            continue;
+        }
+        let base = executor
+            .download_content(&env_base_path(&pair.remote_path))
+            .await
+            .ok()
+            .filter(|base| &sha256_hex(base) == deployed)
+            .unwrap_or_default();
+        tracing::debug!(
+            "{} was edited on the server since the last deploy",
+            pair.remote_path.display()
+        );
+        edited.push(EditedEnvFile {
+            local_path: pair.local_path.clone(),
+            remote_path: pair.remote_path.clone(),
+            merged: merge_env(
+                &String::from_utf8_lossy(&base),
+                &String::from_utf8_lossy(&local),
+                &String::from_utf8_lossy(&remote),
+            ),
+        });
+    }
+    Ok(edited)
+}
+
+/// Explain which env files were edited on the server, with their
+/// conflicting variables.
+pub fn edit_report(edited: &[EditedEnvFile]) -> String {
+    let mut report = String::new();
+    for file in edited {
+        match &file.merged {
+            Ok(_) => report.push_str(&format!(
+                "\n  {} (no conflicting changes)",
+                file.remote_path.display()
+            )),
+            Err(conflicts) => {
+                report.push_str(&format!(
+                    "\n  {} ({} conflicting variable(s)):",
+                    file.remote_path.display(),
+                    conflicts.len()
+                ));
+                for conflict in conflicts {
+                    report.push_str(&format!("\n    {}", conflict));
+                }
+            }
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MemoryFs, MockExecutor};
+
+    const BASE: &str = "# App\nLOG_LEVEL=info\nWORKERS=2\nAPI_TOKEN=abc\n";
+
+    #[test]
+    fn test_merge_env() {
+        // Locally WORKERS changed and API_TOKEN went; on the server LOG_LEVEL
+        // was raised and SENTRY_DSN added
+        let local = "# App\nLOG_LEVEL=info\nWORKERS=4\n";
+        let remote = "LOG_LEVEL=debug\nWORKERS=2\nAPI_TOKEN=abc\nSENTRY_DSN=\"https://x y\"\n";
+        let merged = merge_env(BASE, local, remote).unwrap();
+        assert_eq!(
+            merged,
+            "# App\nLOG_LEVEL=debug\nWORKERS=4\nSENTRY_DSN=\"https://x y\"\n"
+        );
+
+        let remote = "LOG_LEVEL=info\nWORKERS=8\nAPI_TOKEN=xyz\n";
+        let conflicts = merge_env(BASE, "LOG_LEVEL=info\nWORKERS=4\n", remote).unwrap_err();
+        assert_eq!(
+            conflicts
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "API_TOKEN: deployed <redacted>, local unset, server <redacted>",
+                "WORKERS: deployed '2', local '4', server '8'"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_remote_edits() {
+        let dir = tempfile::tempdir().unwrap();
+        let local_path = dir.path().join(".env.prod");
+        std::fs::write(&local_path, "LOG_LEVEL=info\nWORKERS=4\n").unwrap();
+        let remote_path = PathBuf::from("/srv/app/.env.prod");
+        let pairs = [SyncPair::new(&local_path, &remote_path, false)];
+        let deployed = BTreeMap::from([(PathBuf::from(".env.prod"), sha256_hex(BASE.as_bytes()))]);
+
+        let fs = MemoryFs::new();
+        let mut executor = MockExecutor::with_fs(fs.clone());
+        fs.insert(&remote_path, BASE);
+        let edited = find_remote_edits(&mut executor, &pairs, &deployed)
+            .await
+            .unwrap();
+        assert!(edited.is_empty());
+
+        fs.insert(&remote_path, "LOG_LEVEL=debug\nWORKERS=2\nAPI_TOKEN=abc\n");
+        fs.insert(env_base_path(&remote_path), BASE);
+        let edited = find_remote_edits(&mut executor, &pairs, &deployed)
+            .await
+            .unwrap();
+        assert_eq!(edited.len(), 1);
+        assert_eq!(
+            edited[0].merged.as_deref(),
+            Ok("LOG_LEVEL=debug\nWORKERS=4\n")
+        );
+
+        // Without the deployed copy, both sides' values of WORKERS conflict
+        fs.insert(env_base_path(&remote_path), "stale");
+        let edited = find_remote_edits(&mut executor, &pairs, &deployed)
+            .await
+            .unwrap();
+        let report = edit_report(&edited);
+        assert!(report.contains("/srv/app/.env.prod (2 conflicting variable(s)):"));
+        assert!(report.contains("WORKERS: deployed unset, local '4', server '2'"));
+    }
+}
@@ -0,0 +1,185 @@
+use super::env::escape_env_value;
+use crate::deployer::types::{ComposeExec, DeployError, DeployResult};
+use crate::executor::shell_quote;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Env file generated from the template on the server; compose reads it
+/// after the synced env files, so values edited there win.
+pub const REMOTE_ENV_FILE: &str = ".env";
+
+/// Template (e.g. `.env.example`) the remote `.env` is bootstrapped from on
+/// the first deploy. Afterwards the remote file belongs to whoever edits it
+/// on the server and is only regenerated on request.
+pub struct EnvTemplate {
+    path: PathBuf,
+    content: String,
+}
+
+impl EnvTemplate {
+    pub async fn load(path: &Path) -> DeployResult<Self> {
+        let content = tokio::fs::read_to_string(path).await.map_err(|e| {
+            DeployError::Environment(format!(
+                "Failed to read env template {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            content,
+        })
+    }
+
+    /// The template with each variable set to its value in `values`, falling
+    /// back to the local environment and then to the template's own value.
+    /// Comments and blank lines are kept.
+    pub fn render(&self, values: &HashMap<String, String>) -> String {
+        let name = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        let mut rendered = format!(
+            "# Generated by DCD from {} on the first deploy; edits made here are kept\n",
+            name
+        );
+        for line in self.content.lines() {
+            let trimmed = line.trim();
+            let assignment = trimmed
+                .strip_prefix("export ")
+                .unwrap_or(trimmed)
+                .split_once('=')
+                .filter(|_| !trimmed.starts_with('#'));
+            let value = assignment.and_then(|(key, _)| {
+                let key = key.trim();
+                values
+                    .get(key)
+                    .cloned()
+                    .or_else(|| std::env::var(key).ok())
+                    .map(|value| (key, value))
+            });
+            match value {
+                Some((key, value)) => {
+                    rendered.push_str(&format!("{}={}\n", key, escape_env_value(&value)))
+                }
+                None => {
+                    rendered.push_str(line);
+                    rendered.push('\n');
+                }
+            }
+        }
+        rendered
+    }
+
+    /// Write the rendered template to `remote_path` (0600) unless a file is
+    /// already there and `force` is off. Returns whether it was written.
+    pub async fn bootstrap(
+        &self,
+        executor: &mut (dyn ComposeExec + Send),
+        remote_path: &Path,
+        values: &HashMap<String, String>,
+        force: bool,
+    ) -> DeployResult<bool> {
+        let quoted = shell_quote(&remote_path.to_string_lossy());
+        if !force {
+            let exists = executor
+                .execute_command(&format!("test -e {}", quoted))
+                .await
+                .map_err(|e| DeployError::Environment(e.to_string()))?;
+            if exists.is_success() {
+                tracing::info!(
+                    "Keeping the remote {}; use --force-env to regenerate it from {}",
+                    REMOTE_ENV_FILE,
+                    self.path.display()
+                );
+                return Ok(false);
+            }
+        }
+
+        let result = executor
+            .execute_command(&format!("install -m 600 /dev/null {}", quoted))
+            .await
+            .map_err(|e| DeployError::Environment(e.to_string()))?;
+        if !result.is_success() {
+            return Err(DeployError::Environment(format!(
+                "Failed to create {}: {}",
+                remote_path.display(),
+                result.output.to_stderr_string()?.trim()
+            )));
+        }
+        executor
+            .upload_content(self.render(values).as_bytes(), remote_path)
+            .await
+            .map_err(|e| {
+                DeployError::Environment(format!("Failed to upload {}: {}", REMOTE_ENV_FILE, e))
+            })?;
+        tracing::info!(
+            "Generated the remote {} from {}",
+            REMOTE_ENV_FILE,
+            self.path.display()
+        );
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{CommandMatch, MemoryFs, MockExecutor};
+
+    fn template() -> EnvTemplate {
+        EnvTemplate {
+            path: PathBuf::from("/project/.env.example"),
+            content: "# Database\nDB_HOST=localhost\nexport DB_PASSWORD=changeme\n\n\
+                      LOG_LEVEL=info\n"
+                .to_string(),
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let values = HashMap::from([
+            ("DB_HOST".to_string(), "db".to_string()),
+            ("DB_PASSWORD".to_string(), "s3cret pass".to_string()),
+        ]);
+        let rendered = template().render(&values);
+        let lines: Vec<&str> = rendered.lines().skip(1).collect();
+        assert_eq!(
+            lines,
+            [
+                "# Database",
+                "DB_HOST=db",
+                "DB_PASSWORD=\"s3cret pass\"",
+                "",
+                "LOG_LEVEL=info"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_keeps_existing() {
+        let remote_path = Path::new("/opt/app/.env");
+        let values = HashMap::from([("DB_HOST".to_string(), "db".to_string())]);
+
+        let fs = MemoryFs::new();
+        let mut executor = MockExecutor::with_fs(fs.clone());
+        executor.respond_ok(CommandMatch::Prefix("test -e ".into()), "");
+        let written = template()
+            .bootstrap(&mut executor, remote_path, &values, false)
+            .await
+            .unwrap();
+        assert!(!written);
+        assert!(fs.read(remote_path).is_none());
+
+        // Forced, the file is regenerated without looking
+        executor.respond_ok(CommandMatch::Prefix("install -m 600".into()), "");
+        let written = template()
+            .bootstrap(&mut executor, remote_path, &values, true)
+            .await
+            .unwrap();
+        assert!(written);
+        let content = String::from_utf8(fs.read(remote_path).unwrap()).unwrap();
+        assert!(content.contains("DB_HOST=db\n"));
+    }
+}
@@ -1,9 +1,18 @@
-use super::{SyncPair, SyncPlan};
-use crate::deployer::types::{ComposeExec, DeployError, DeployResult};
+use super::archive::{use_archive, LocalArchive, REMOTE_ARCHIVE_NAME};
+use super::diff::{unified_diff, FileDiff, Redaction};
+use super::hash_cache::{hash_file, HashCache};
+use super::ignore::IgnoreRules;
+use super::{backup_file, backup_files, SyncPair, SyncPlan};
+use crate::deployer::types::{ComposeExec, DeployError, DeployResult, DeployerEvent};
+use crate::deployer::{BACKUP_SUFFIX, PREVIOUS_SUFFIX};
+use crate::executor::{shell_quote, ExecutorError};
+use serde::Serialize;
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use tokio::fs;
-use tokio::io::AsyncReadExt;
+use tokio::sync::mpsc;
 
 #[derive(Debug, Default)]
 pub struct FileSyncStatus {
@@ -12,9 +21,52 @@ pub struct FileSyncStatus {
     pub files_failed: Vec<(PathBuf, String)>,
 }
 
+/// How a local file compares to its remote copy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileChange {
+    New,
+    Changed,
+    Unchanged,
+}
+
+impl FileChange {
+    fn between(remote_sum: Option<&str>, local_sum: Option<&str>) -> Self {
+        match (remote_sum, local_sum) {
+            (None, _) => FileChange::New,
+            (Some(remote), Some(local)) if remote == local => FileChange::Unchanged,
+            _ => FileChange::Changed,
+        }
+    }
+}
+
+/// A file a sync would upload, or leave alone when unchanged.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedFile {
+    /// `None` for files dcd generates, like .env.dcd
+    pub local_path: Option<PathBuf>,
+    pub remote_path: PathBuf,
+    pub change: FileChange,
+}
+
+/// Uploads in flight at once when syncing a directory, unless configured.
+pub const DEFAULT_SYNC_CONCURRENCY: usize = 8;
+
+/// Files from this size on report byte progress while uploading.
+const UPLOAD_PROGRESS_MIN_BYTES: u64 = 1024 * 1024;
+
 pub struct FileSync<'a> {
     executor: &'a mut (dyn ComposeExec + Send),
     remote_root: PathBuf,
+    /// Owner applied to every synced path after upload
+    owner: Option<String>,
+    /// Maximum number of parallel uploads within a directory
+    concurrency: usize,
+    /// Receives hashing progress of large directories and upload progress
+    /// of large files
+    progress: Option<mpsc::Sender<DeployerEvent>>,
+    /// Sync hidden entries of directories too
+    include_hidden: bool,
 }
 
 impl<'a> FileSync<'a> {
@@ -22,13 +74,47 @@ impl<'a> FileSync<'a> {
         Self {
             executor,
             remote_root,
+            owner: None,
+            concurrency: DEFAULT_SYNC_CONCURRENCY,
+            progress: None,
+            include_hidden: false,
         }
     }
 
+    /// Set the `user[:group]` that synced files should belong to on the remote.
+    pub fn with_owner(mut self, owner: Option<String>) -> Self {
+        self.owner = owner;
+        self
+    }
+
+    /// Limit how many files of a directory are uploaded in parallel.
+    pub fn with_concurrency(mut self, concurrency: Option<usize>) -> Self {
+        self.concurrency = concurrency.unwrap_or(DEFAULT_SYNC_CONCURRENCY).max(1);
+        self
+    }
+
+    /// Sync hidden files and directories of referenced directories, which
+    /// are skipped by default (see [`IgnoreRules`]).
+    pub fn with_include_hidden(mut self, include_hidden: bool) -> Self {
+        self.include_hidden = include_hidden;
+        self
+    }
+
+    /// Report progress of hashing large directories and of uploading large
+    /// files to `progress`.
+    pub fn with_progress(mut self, progress: Option<mpsc::Sender<DeployerEvent>>) -> Self {
+        self.progress = progress;
+        self
+    }
+
     /// Synchronize files according to the sync plan
     pub async fn sync_files(&mut self, plan: &SyncPlan) -> DeployResult<FileSyncStatus> {
         let mut status = FileSyncStatus::default();
 
+        if let Some(owner) = &self.owner {
+            validate_owner(owner)?;
+        }
+
         // Ensure remote directory exists
         self.ensure_remote_directory().await?;
 
@@ -52,9 +138,187 @@ impl<'a> FileSync<'a> {
             self.sync_file(pair, &mut status).await?;
         }
 
+        if let Some(owner) = self.owner.clone() {
+            self.apply_owner(plan, &owner).await?;
+        }
+
         Ok(status)
     }
 
+    /// Evaluate the plan without syncing: how each file, including those in
+    /// directories, compares to its remote copy. Only reads checksums.
+    pub async fn plan_files(&mut self, plan: &SyncPlan) -> DeployResult<Vec<PlannedFile>> {
+        let mut planned = Vec::new();
+        let pairs = plan
+            .compose_files
+            .iter()
+            .chain(&plan.env_files)
+            .chain(&plan.reference_files)
+            .chain(&plan.files);
+        for pair in pairs {
+            if !pair.is_directory {
+                let remote_sum = self.remote_checksum(&pair.remote_path).await?;
+                let local_sum = sha256_file(&pair.local_path).await?;
+                planned.push(PlannedFile {
+                    local_path: Some(pair.local_path.clone()),
+                    remote_path: pair.remote_path.clone(),
+                    change: FileChange::between(remote_sum.as_deref(), Some(&local_sum)),
+                });
+                continue;
+            }
+            let ignore = IgnoreRules::load(&pair.local_path, self.include_hidden)?;
+            let (_, files) = walk_local_directory(&pair.local_path, &ignore).await?;
+            let manifest = self.remote_manifest(&pair.remote_path).await?;
+            let mut sums = self.local_checksums(&pair.local_path, &files).await?;
+            for relative in files {
+                let local_path = pair.local_path.join(&relative);
+                let local_sum = sums.remove(&relative).unwrap_or_default();
+                planned.push(PlannedFile {
+                    change: FileChange::between(
+                        manifest.get(&relative).map(String::as_str),
+                        Some(&local_sum),
+                    ),
+                    local_path: Some(local_path),
+                    remote_path: pair.remote_path.join(relative),
+                });
+            }
+        }
+        Ok(planned)
+    }
+
+    /// Like [`Self::plan_files`] for a file dcd generates. Without `content`
+    /// (e.g. encrypted anew each time) an existing remote copy counts as changed.
+    pub async fn plan_generated(
+        &mut self,
+        remote_path: PathBuf,
+        content: Option<&[u8]>,
+    ) -> DeployResult<PlannedFile> {
+        let remote_sum = self.remote_checksum(&remote_path).await?;
+        let local_sum = content.map(|content| format!("{:x}", Sha256::digest(content)));
+        Ok(PlannedFile {
+            local_path: None,
+            change: FileChange::between(remote_sum.as_deref(), local_sum.as_deref()),
+            remote_path,
+        })
+    }
+
+    /// Diffs of the compose and env files in the plan that would overwrite a
+    /// different remote copy, with env values and secret-looking compose
+    /// values redacted. Files new on the remote are not listed.
+    pub async fn diff_files(&mut self, plan: &SyncPlan) -> DeployResult<Vec<FileDiff>> {
+        let files = plan
+            .compose_files
+            .iter()
+            .map(|pair| (pair, Redaction::SecretKeys))
+            .chain(
+                plan.env_files
+                    .iter()
+                    .map(|pair| (pair, Redaction::AllValues)),
+            );
+        let mut diffs = Vec::new();
+        for (pair, redaction) in files {
+            if !self.should_sync_file(pair).await? {
+                continue;
+            }
+            let cmd = format!("cat {}", quote_path(&pair.remote_path));
+            let result = self.executor.execute_command(&cmd).await.map_err(|e| {
+                DeployError::FileSync(format!(
+                    "Failed to read {}: {}",
+                    pair.remote_path.display(),
+                    e
+                ))
+            })?;
+            if !result.is_success() {
+                continue;
+            }
+            let remote = result.output.to_stdout_string()?;
+            let local = fs::read_to_string(&pair.local_path).await.map_err(|e| {
+                DeployError::FileSync(format!(
+                    "Failed to read {}: {}",
+                    pair.local_path.display(),
+                    e
+                ))
+            })?;
+            diffs.push(FileDiff {
+                remote_path: pair.remote_path.clone(),
+                local_path: pair.local_path.clone(),
+                lines: unified_diff(&remote, &local, redaction),
+            });
+        }
+        Ok(diffs)
+    }
+
+    /// Recursively chown every path in the plan. Unchanged files are included
+    /// so that changing the owner takes effect without re-uploading.
+    async fn apply_owner(&mut self, plan: &SyncPlan, owner: &str) -> DeployResult<()> {
+        let paths: Vec<String> = plan
+            .compose_files
+            .iter()
+            .chain(&plan.env_files)
+            .chain(&plan.reference_files)
+            .chain(&plan.files)
+            .map(|pair| quote_path(&pair.remote_path))
+            .collect();
+        if paths.is_empty() {
+            return Ok(());
+        }
+
+        tracing::info!(
+            "Setting owner of {} synced path(s) to {}",
+            paths.len(),
+            owner
+        );
+        let cmd = format!("chown -R {} {}", shell_quote(owner), paths.join(" "));
+        let result = self
+            .executor
+            .execute_command(&cmd)
+            .await
+            .map_err(|e| DeployError::FileSync(format!("Failed to change ownership: {}", e)))?;
+        if !result.is_success() {
+            return Err(DeployError::FileSync(format!(
+                "Failed to change ownership to '{}': {}",
+                owner,
+                result.output.to_stderr_string()?.trim()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Upload one file, sending [`DeployerEvent::UploadProgress`] each
+    /// percent if it is large enough for that to matter.
+    async fn upload_reporting(
+        &mut self,
+        local_path: &Path,
+        remote_path: &Path,
+    ) -> Result<(), ExecutorError> {
+        let size = fs::metadata(local_path).await.map(|m| m.len()).unwrap_or(0);
+        let Some(sender) = self
+            .progress
+            .clone()
+            .filter(|_| size >= UPLOAD_PROGRESS_MIN_BYTES)
+        else {
+            return self
+                .executor
+                .upload_file(local_path, remote_path, None)
+                .await;
+        };
+        let name = local_path
+            .file_name()
+            .map(PathBuf::from)
+            .unwrap_or_default();
+        let reported = AtomicU64::new(u64::MAX);
+        let report = move |sent: u64, total: u64| {
+            let percent = sent * 100 / total.max(1);
+            if reported.swap(percent, Ordering::Relaxed) != percent {
+                // Dropped rather than waited for when the UI lags behind
+                let _ = sender.try_send(DeployerEvent::UploadProgress(name.clone(), sent, total));
+            }
+        };
+        self.executor
+            .upload_file(local_path, remote_path, Some(&report))
+            .await
+    }
+
     async fn sync_file(
         &mut self,
         pair: &SyncPair,
@@ -63,9 +327,10 @@ impl<'a> FileSync<'a> {
         if pair.is_directory {
             Box::pin(self.sync_directory(pair, status)).await?;
         } else if self.should_sync_file(pair).await? {
+            // Keep the deployed version so an aborted deploy can be rolled back
+            backup_file(self.executor, &pair.remote_path).await?;
             match self
-                .executor
-                .upload_file(&pair.local_path, &pair.remote_path)
+                .upload_reporting(&pair.local_path, &pair.remote_path)
                 .await
             {
                 Ok(_) => status.files_synced.push(pair.local_path.clone()),
@@ -86,69 +351,234 @@ impl<'a> FileSync<'a> {
         Ok(())
     }
 
+    /// Sync a directory tree with one remote checksum listing, one `mkdir`
+    /// and uploads of the changed files running in parallel.
     async fn sync_directory(
         &mut self,
         pair: &SyncPair,
         status: &mut FileSyncStatus,
     ) -> DeployResult<()> {
-        // Create remote directory
-        let mkdir_cmd = format!("mkdir -p {}", pair.remote_path.display());
-        self.executor
+        let ignore = IgnoreRules::load(&pair.local_path, self.include_hidden)?;
+        let (dirs, files) = walk_local_directory(&pair.local_path, &ignore).await?;
+        let manifest = self.remote_manifest(&pair.remote_path).await?;
+
+        let mut mkdir_cmd = format!("mkdir -p {}", quote_path(&pair.remote_path));
+        for dir in &dirs {
+            mkdir_cmd.push(' ');
+            mkdir_cmd.push_str(&quote_path(&pair.remote_path.join(dir)));
+        }
+        let result = self
+            .executor
             .execute_command(&mkdir_cmd)
             .await
             .map_err(|e| {
                 DeployError::FileSync(format!("Failed to create remote directory: {}", e))
             })?;
+        if !result.is_success() {
+            return Err(DeployError::FileSync(format!(
+                "Failed to create remote directory {}: {}",
+                pair.remote_path.display(),
+                result.output.to_stderr_string()?.trim()
+            )));
+        }
 
-        // Recursively sync directory contents
-        let mut entries = fs::read_dir(&pair.local_path)
-            .await
-            .map_err(|e| DeployError::FileSync(format!("Failed to read directory: {}", e)))?;
+        let sums = self.local_checksums(&pair.local_path, &files).await?;
+        let mut changed = Vec::new();
+        for relative in files {
+            let local_path = pair.local_path.join(&relative);
+            if sums
+                .get(&relative)
+                .is_some_and(|sum| manifest.get(&relative) == Some(sum))
+            {
+                status.files_skipped.push(local_path);
+            } else {
+                changed.push(SyncPair::new(
+                    local_path,
+                    pair.remote_path.join(&relative),
+                    false,
+                ));
+            }
+        }
+        if changed.is_empty() {
+            return Ok(());
+        }
 
-        while let Ok(Some(entry)) = entries.next_entry().await {
-            let local_path = entry.path();
-            let relative_path = local_path
-                .strip_prefix(&pair.local_path)
-                .map_err(|e| DeployError::FileSync(e.to_string()))?;
-            let remote_path = pair.remote_path.join(relative_path);
+        // Keep the deployed versions so an aborted deploy can be rolled back
+        let remote_paths: Vec<PathBuf> = changed.iter().map(|p| p.remote_path.clone()).collect();
+        backup_files(self.executor, &remote_paths).await?;
 
-            let is_dir = entry
-                .file_type()
+        tracing::info!(
+            "Uploading {} changed file(s) to {}",
+            changed.len(),
+            pair.remote_path.display()
+        );
+        if self.upload_archive(pair, &changed).await? {
+            status
+                .files_synced
+                .extend(changed.into_iter().map(|file| file.local_path));
+            return Ok(());
+        }
+        let batch: Vec<(PathBuf, PathBuf)> = changed
+            .into_iter()
+            .map(|file| (file.local_path, file.remote_path))
+            .collect();
+        let results = self.executor.upload_files(&batch, self.concurrency).await;
+
+        let mut first_error = None;
+        for ((local_path, _), result) in batch.into_iter().zip(results) {
+            match result {
+                Ok(()) => status.files_synced.push(local_path),
+                Err(e) => {
+                    first_error.get_or_insert_with(|| {
+                        format!("Failed to sync file {}: {}", local_path.display(), e)
+                    });
+                    status.files_failed.push((local_path, e.to_string()));
+                }
+            }
+        }
+        match first_error {
+            Some(message) => Err(DeployError::FileSync(message)),
+            None => Ok(()),
+        }
+    }
+
+    /// Upload the `changed` files of the directory `pair` as one archive when
+    /// there are enough of them. Returns `false` when they should be uploaded
+    /// one by one instead: too few or too large, or no `tar` on either side.
+    async fn upload_archive(
+        &mut self,
+        pair: &SyncPair,
+        changed: &[SyncPair],
+    ) -> DeployResult<bool> {
+        let mut total_bytes = 0;
+        for file in changed {
+            total_bytes += fs::metadata(&file.local_path)
                 .await
-                .map_err(|e| DeployError::FileSync(e.to_string()))?
-                .is_dir();
+                .map_err(|e| DeployError::FileSync(format!("Failed to read file: {}", e)))?
+                .len();
+        }
+        if !use_archive(changed.len(), total_bytes) {
+            return Ok(false);
+        }
+        let has_tar = self
+            .executor
+            .execute_command("command -v tar")
+            .await
+            .is_ok_and(|result| result.is_success());
+        if !has_tar {
+            tracing::debug!("No tar on the server; uploading files one by one");
+            return Ok(false);
+        }
+        let relative: Vec<PathBuf> = changed
+            .iter()
+            .filter_map(|file| file.local_path.strip_prefix(&pair.local_path).ok())
+            .map(Path::to_path_buf)
+            .collect();
+        let archive = match LocalArchive::create(&pair.local_path, &relative).await {
+            Ok(archive) => archive,
+            Err(e) => {
+                tracing::warn!("Uploading files one by one, archiving failed: {}", e);
+                return Ok(false);
+            }
+        };
 
-            let sub_pair = SyncPair::new(local_path, remote_path, is_dir);
-            self.sync_file(&sub_pair, status).await?;
+        let remote_archive = pair.remote_path.join(REMOTE_ARCHIVE_NAME);
+        if let Err(e) = self.upload_reporting(&archive.path, &remote_archive).await {
+            tracing::warn!("Uploading files one by one, archive upload failed: {}", e);
+            return Ok(false);
+        }
+        // Files belong to the SSH user (or `owner`), not to the local uid
+        let cmd = format!(
+            "tar -xzf {archive} -C {dir} --no-same-owner; status=$?; rm -f {archive}; exit $status",
+            archive = quote_path(&remote_archive),
+            dir = quote_path(&pair.remote_path)
+        );
+        match self.executor.execute_command(&cmd).await {
+            Ok(result) if result.is_success() => Ok(true),
+            Ok(result) => {
+                tracing::warn!(
+                    "Uploading files one by one, extracting the archive failed: {}",
+                    result.output.to_stderr_string()?.trim()
+                );
+                Ok(false)
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Uploading files one by one, extracting the archive failed: {}",
+                    e
+                );
+                Ok(false)
+            }
         }
+    }
 
-        Ok(())
+    /// Checksums of `files` below the local directory `root`, from the hash
+    /// cache where they are unchanged since the last deploy.
+    async fn local_checksums(
+        &self,
+        root: &Path,
+        files: &[PathBuf],
+    ) -> DeployResult<HashMap<PathBuf, String>> {
+        HashCache::for_directory(root)
+            .hash_files(root, files, self.progress.as_ref())
+            .await
+    }
+
+    /// Checksums of every file under `remote_dir`, keyed by relative path.
+    /// A missing directory yields an empty manifest.
+    async fn remote_manifest(
+        &mut self,
+        remote_dir: &Path,
+    ) -> DeployResult<HashMap<PathBuf, String>> {
+        let cmd = format!(
+            "cd {} 2>/dev/null || exit 0; find . -type f ! -name '*{}' ! -name '*{}' -exec sha256sum {{}} +",
+            quote_path(remote_dir),
+            BACKUP_SUFFIX,
+            PREVIOUS_SUFFIX
+        );
+        let result = self.executor.execute_command(&cmd).await.map_err(|e| {
+            DeployError::FileSync(format!("Failed to list remote checksums: {}", e))
+        })?;
+        if !result.is_success() {
+            // Unreadable entries only mean those files get uploaded again
+            tracing::debug!(
+                "Checksum listing of {} was incomplete: {}",
+                remote_dir.display(),
+                result.output.to_stderr_string()?.trim()
+            );
+        }
+        Ok(parse_manifest(&result.output.to_stdout_string()?))
     }
 
     async fn should_sync_file(&mut self, pair: &SyncPair) -> DeployResult<bool> {
         // Check if remote file exists and compare checksums
-        let check_cmd = format!("sha256sum {}", pair.remote_path.display());
+        match self.remote_checksum(&pair.remote_path).await? {
+            Some(remote_sum) => Ok(sha256_file(&pair.local_path).await? != remote_sum),
+            None => Ok(true), // File doesn't exist or error reading it, should sync
+        }
+    }
+
+    /// SHA-256 of a remote file, `None` if it is missing or unreadable.
+    async fn remote_checksum(&mut self, remote_path: &Path) -> DeployResult<Option<String>> {
+        let check_cmd = format!("sha256sum {}", quote_path(remote_path));
         match self.executor.execute_command(&check_cmd).await {
             Ok(result) if result.is_success() => {
                 let stdout = result
                     .output
                     .to_stdout_string()
                     .map_err(|e| DeployError::FileSync(e.to_string()))?;
-
                 let remote_sum = stdout
                     .split_whitespace()
                     .next()
                     .ok_or_else(|| DeployError::FileSync("Invalid checksum output".into()))?;
-
-                let local_sum = sha256_file(&pair.local_path).await?;
-                Ok(local_sum != remote_sum)
+                Ok(Some(remote_sum.to_string()))
             }
-            _ => Ok(true), // File doesn't exist or error reading it, should sync
+            _ => Ok(None),
         }
     }
 
     async fn ensure_remote_directory(&mut self) -> DeployResult<()> {
-        let cmd = format!("mkdir -p {}", self.remote_root.display());
+        let cmd = format!("mkdir -p {}", quote_path(&self.remote_root));
         self.executor.execute_command(&cmd).await.map_err(|e| {
             DeployError::FileSync(format!("Failed to create remote directory: {}", e))
         })?;
@@ -156,17 +586,250 @@ impl<'a> FileSync<'a> {
     }
 }
 
-async fn sha256_file(path: impl AsRef<Path>) -> DeployResult<String> {
-    let mut file = fs::File::open(path)
-        .await
-        .map_err(|e| DeployError::FileSync(format!("Failed to open file: {}", e)))?;
+/// Accepts `user`, `user:group`, `uid` and `uid:gid`; anything else is rejected
+/// before it can reach a remote shell.
+pub fn validate_owner(owner: &str) -> DeployResult<()> {
+    let valid_part = |part: &str| {
+        !part.is_empty()
+            && !part.starts_with('-')
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    };
+    let valid = match owner.split_once(':') {
+        Some((user, group)) => valid_part(user) && valid_part(group),
+        None => valid_part(owner),
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(DeployError::Configuration(format!(
+            "Invalid owner '{}': expected user[:group] or uid[:gid]",
+            owner
+        )))
+    }
+}
 
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)
+/// Relative paths of the directories and files below `root` that `ignore`
+/// keeps, depth first.
+pub(crate) async fn walk_local_directory(
+    root: &Path,
+    ignore: &IgnoreRules,
+) -> DeployResult<(Vec<PathBuf>, Vec<PathBuf>)> {
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative) = pending.pop() {
+        let mut entries = fs::read_dir(root.join(&relative))
+            .await
+            .map_err(|e| DeployError::FileSync(format!("Failed to read directory: {}", e)))?;
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|e| DeployError::FileSync(format!("Failed to read directory: {}", e)))?
+        {
+            let path = relative.join(entry.file_name());
+            let is_dir = entry
+                .file_type()
+                .await
+                .map_err(|e| DeployError::FileSync(e.to_string()))?
+                .is_dir();
+            if ignore.is_ignored(&path, is_dir) {
+                tracing::trace!("Not syncing ignored {}", root.join(&path).display());
+                continue;
+            }
+            if is_dir {
+                dirs.push(path.clone());
+                pending.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    Ok((dirs, files))
+}
+
+/// Parse `sha256sum` output run from inside the directory. Names that
+/// `sha256sum` had to escape are left out and simply get uploaded again.
+fn parse_manifest(output: &str) -> HashMap<PathBuf, String> {
+    output
+        .lines()
+        .filter(|line| !line.starts_with('\\'))
+        .filter_map(|line| line.split_once("  "))
+        .map(|(sum, path)| {
+            let path = path.strip_prefix("./").unwrap_or(path);
+            (PathBuf::from(path), sum.to_string())
+        })
+        .collect()
+}
+
+fn quote_path(path: &Path) -> String {
+    shell_quote(&path.to_string_lossy())
+}
+
+pub(crate) async fn sha256_file(path: impl AsRef<Path>) -> DeployResult<String> {
+    let path = path.as_ref().to_path_buf();
+    tokio::task::spawn_blocking(move || hash_file(&path))
         .await
-        .map_err(|e| DeployError::FileSync(format!("Failed to read file: {}", e)))?;
+        .map_err(|e| DeployError::FileSync(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_owner() {
+        assert!(validate_owner("1000").is_ok());
+        assert!(validate_owner("1000:1000").is_ok());
+        assert!(validate_owner("www-data:www-data").is_ok());
+        assert!(validate_owner("").is_err());
+        assert!(validate_owner("1000:").is_err());
+        assert!(validate_owner("-R").is_err());
+        assert!(validate_owner("root; rm -rf /").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_plan_files() {
+        use crate::testing::{CommandMatch, MockExecutor};
+
+        let local = tempfile::tempdir().unwrap();
+        std::fs::write(local.path().join("compose.yml"), "services: {}\n").unwrap();
+        std::fs::create_dir(local.path().join("conf")).unwrap();
+        std::fs::write(local.path().join("conf/a.conf"), "a").unwrap();
+        std::fs::write(local.path().join("conf/b.conf"), "b").unwrap();
+        let a_sum = sha256_file(local.path().join("conf/a.conf")).await.unwrap();
+
+        let mut plan = SyncPlan::new();
+        plan.add_compose_file(local.path().join("compose.yml"), "/srv/app/compose.yml");
+        plan.add_reference(local.path().join("conf"), "/srv/app/conf", true);
+
+        let mut executor = MockExecutor::new();
+        executor
+            .respond_ok(
+                "sha256sum /srv/app/compose.yml",
+                "0000  /srv/app/compose.yml\n",
+            )
+            .respond_ok(
+                CommandMatch::Prefix("cd /srv/app/conf ".into()),
+                &format!("{}  ./a.conf\n", a_sum),
+            )
+            .respond_err("sha256sum /srv/app/.env.dcd", 1, "No such file");
+        let mut file_sync = FileSync::new(&mut executor, PathBuf::from("/srv/app"));
+        let mut planned = file_sync.plan_files(&plan).await.unwrap();
+        planned.push(
+            file_sync
+                .plan_generated(PathBuf::from("/srv/app/.env.dcd"), Some(b"A=1\n"))
+                .await
+                .unwrap(),
+        );
+
+        let mut changes: Vec<(String, FileChange)> = planned
+            .iter()
+            .map(|file| (file.remote_path.display().to_string(), file.change))
+            .collect();
+        changes.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            changes,
+            vec![
+                ("/srv/app/.env.dcd".to_string(), FileChange::New),
+                ("/srv/app/compose.yml".to_string(), FileChange::Changed),
+                ("/srv/app/conf/a.conf".to_string(), FileChange::Unchanged),
+                ("/srv/app/conf/b.conf".to_string(), FileChange::New),
+            ]
+        );
+        assert!(executor
+            .commands()
+            .iter()
+            .all(|cmd| cmd.starts_with("sha256sum") || cmd.starts_with("cd ")));
+    }
 
-    let mut hasher = Sha256::new();
-    hasher.update(&buffer);
-    Ok(format!("{:x}", hasher.finalize()))
+    #[tokio::test]
+    async fn test_sync_directory_as_archive() {
+        use crate::deployer::sync::archive::ARCHIVE_MIN_FILES;
+        use crate::testing::{CommandMatch, MemoryFs, MockExecutor};
+
+        let local = tempfile::tempdir().unwrap();
+        for i in 0..ARCHIVE_MIN_FILES {
+            std::fs::write(local.path().join(format!("{}.txt", i)), "x").unwrap();
+        }
+        let mut plan = SyncPlan::new();
+        plan.add_reference(local.path(), "/srv/app/static", true);
+
+        for has_tar in [true, false] {
+            let fs = MemoryFs::new();
+            let mut executor = MockExecutor::with_fs(fs.clone());
+            executor
+                .respond_ok(CommandMatch::Prefix("mkdir -p".into()), "")
+                .respond_ok(CommandMatch::Prefix("cd ".into()), "")
+                .respond_ok(CommandMatch::Prefix("for f in".into()), "")
+                .respond_ok(CommandMatch::Prefix("tar -xzf".into()), "");
+            if has_tar {
+                executor.respond_ok("command -v tar", "/usr/bin/tar\n");
+            } else {
+                executor.respond_err("command -v tar", 1, "");
+            }
+            let status = FileSync::new(&mut executor, PathBuf::from("/srv/app"))
+                .sync_files(&plan)
+                .await
+                .unwrap();
+            assert_eq!(status.files_synced.len(), ARCHIVE_MIN_FILES);
+
+            // One archive goes up, or every file when the server has no tar
+            let uploaded = fs.paths();
+            if has_tar {
+                assert_eq!(
+                    uploaded,
+                    [PathBuf::from("/srv/app/static").join(REMOTE_ARCHIVE_NAME)]
+                );
+            } else {
+                assert_eq!(uploaded.len(), ARCHIVE_MIN_FILES);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upload_progress() {
+        use crate::testing::{CommandMatch, MemoryFs, MockExecutor};
+
+        let local = tempfile::tempdir().unwrap();
+        let dump = local.path().join("dump.sql");
+        std::fs::write(&dump, vec![b'x'; UPLOAD_PROGRESS_MIN_BYTES as usize]).unwrap();
+        std::fs::write(local.path().join("compose.yml"), "services: {}\n").unwrap();
+        let mut plan = SyncPlan::new();
+        plan.add_compose_file(local.path().join("compose.yml"), "/srv/app/compose.yml");
+        plan.add_reference(&dump, "/srv/app/dump.sql", false);
+
+        let mut executor = MockExecutor::with_fs(MemoryFs::new());
+        executor
+            .respond_err(CommandMatch::Prefix("sha256sum".into()), 1, "No such file")
+            .respond_ok(CommandMatch::Prefix("mkdir -p".into()), "")
+            .respond_ok(CommandMatch::Prefix("for f in".into()), "");
+        let (sender, mut receiver) = mpsc::channel(32);
+        let status = FileSync::new(&mut executor, PathBuf::from("/srv/app"))
+            .with_progress(Some(sender))
+            .sync_files(&plan)
+            .await
+            .unwrap();
+        assert_eq!(status.files_synced.len(), 2);
+
+        // Only the large file reports its bytes
+        let mut events = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            events.push(event.to_string());
+        }
+        assert_eq!(events, ["Uploading dump.sql (1048576/1048576 bytes)"]);
+    }
+
+    #[test]
+    fn test_parse_manifest() {
+        let manifest = parse_manifest(
+            "3b5d  ./nginx.conf\n\
+             9f86  ./conf.d/site a.conf\n\
+             \\e3b0  ./new\\nline.conf\n",
+        );
+        assert_eq!(manifest.len(), 2);
+        assert_eq!(manifest[Path::new("nginx.conf")], "3b5d");
+        assert_eq!(manifest[Path::new("conf.d/site a.conf")], "9f86");
+    }
 }
@@ -0,0 +1,268 @@
+//! Checksums of the files of a synced directory, cached between deploys.
+//!
+//! Hashing a large reference directory (media, models, datasets) reads every
+//! byte of it, so results are kept in the user's cache directory keyed by
+//! path, size and modification time, and only new or touched files are
+//! hashed again. The cache is written while hashing, so an interrupted scan
+//! resumes where it stopped.
+
+use crate::deployer::types::{DeployError, DeployResult, DeployerEvent};
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+
+/// Files hashed at once; more mostly competes for the same disk.
+const HASH_CONCURRENCY: usize = 4;
+
+/// Hashed files between writes of the cache during a scan.
+const SAVE_EVERY: usize = 256;
+
+/// Files changed this recently may change again within the same mtime
+/// tick unnoticed, so their checksums are not cached.
+const MIN_CACHE_AGE: Duration = Duration::from_secs(2);
+
+/// Directories with fewer files to hash report no progress.
+const PROGRESS_MIN_FILES: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct CachedHash {
+    size: u64,
+    /// Modification time in nanoseconds since the epoch
+    mtime: u128,
+    sha256: String,
+}
+
+/// Cached checksums of the files below one local directory.
+#[derive(Debug, Default)]
+pub struct HashCache {
+    /// `None` when there is no cache directory; nothing is persisted then
+    path: Option<PathBuf>,
+    entries: HashMap<PathBuf, CachedHash>,
+}
+
+impl HashCache {
+    /// The cache for `root` in the user's cache directory.
+    pub fn for_directory(root: &Path) -> Self {
+        let root = std::fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+        let digest = Sha256::digest(root.to_string_lossy().as_bytes());
+        let name: String = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+        let path = dirs::cache_dir().map(|dir| {
+            dir.join("dcd")
+                .join("hashes")
+                .join(format!("{}.json", name))
+        });
+        match path {
+            Some(path) => Self::load(path),
+            None => Self::default(),
+        }
+    }
+
+    /// The cache stored in `path`; a missing or unreadable file starts empty.
+    pub fn load(path: PathBuf) -> Self {
+        let entries = std::fs::read(&path)
+            .ok()
+            .and_then(|content| serde_json::from_slice(&content).ok())
+            .unwrap_or_default();
+        Self {
+            path: Some(path),
+            entries,
+        }
+    }
+
+    /// Write the cache, replacing the previous file at once. Failures only
+    /// cost rehashing next time.
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if self.entries.is_empty() && !path.exists() {
+            return;
+        }
+        let write = || -> std::io::Result<()> {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            let temp = path.with_extension("json.tmp");
+            std::fs::write(&temp, serde_json::to_vec(&self.entries)?)?;
+            std::fs::rename(&temp, path)
+        };
+        if let Err(e) = write() {
+            tracing::debug!("Failed to write hash cache {}: {}", path.display(), e);
+        }
+    }
+
+    /// SHA-256 of each of `files` (relative to `root`), reading only those
+    /// not in the cache with their current size and mtime. Progress goes to
+    /// `progress` as [`DeployerEvent::StepProgress`].
+    pub async fn hash_files(
+        &mut self,
+        root: &Path,
+        files: &[PathBuf],
+        progress: Option<&mpsc::Sender<DeployerEvent>>,
+    ) -> DeployResult<HashMap<PathBuf, String>> {
+        let mut sums = HashMap::with_capacity(files.len());
+        let mut stale = Vec::new();
+        for relative in files {
+            let stamp = file_stamp(&root.join(relative)).await?;
+            match self.entries.get(relative) {
+                Some(cached) if (cached.size, cached.mtime) == stamp => {
+                    sums.insert(relative.clone(), cached.sha256.clone());
+                }
+                _ => stale.push((relative.clone(), stamp)),
+            }
+        }
+        // Entries of deleted files go with the next save
+        self.entries.retain(|path, _| sums.contains_key(path));
+        if stale.is_empty() {
+            return Ok(sums);
+        }
+        tracing::debug!(
+            "Hashing {} of {} file(s) in {}",
+            stale.len(),
+            files.len(),
+            root.display()
+        );
+
+        let total = stale.len();
+        let step = (total / 100).max(1);
+        let message = format!("Hashing files in {}", root.display());
+        let cutoff = SystemTime::now()
+            .checked_sub(MIN_CACHE_AGE)
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|elapsed| elapsed.as_nanos())
+            .unwrap_or_default();
+        let hashes = stale.into_iter().map(|(relative, stamp)| {
+            let path = root.join(&relative);
+            async move {
+                let sha256 = tokio::task::spawn_blocking(move || hash_file(&path))
+                    .await
+                    .map_err(|e| DeployError::FileSync(e.to_string()))??;
+                Ok::<_, DeployError>((relative, stamp, sha256))
+            }
+        });
+        let mut hashes = stream::iter(hashes).buffer_unordered(HASH_CONCURRENCY);
+        let mut done = 0;
+        while let Some(result) = hashes.next().await {
+            let (relative, (size, mtime), sha256) = match result {
+                Ok(hashed) => hashed,
+                Err(e) => {
+                    // Keep what was hashed so far for the next attempt
+                    self.save();
+                    return Err(e);
+                }
+            };
+            if mtime < cutoff {
+                self.entries.insert(
+                    relative.clone(),
+                    CachedHash {
+                        size,
+                        mtime,
+                        sha256: sha256.clone(),
+                    },
+                );
+            }
+            sums.insert(relative, sha256);
+            done += 1;
+            if done % SAVE_EVERY == 0 {
+                self.save();
+            }
+            if let Some(sender) = progress.filter(|_| total >= PROGRESS_MIN_FILES) {
+                if done % step == 0 || done == total {
+                    let event = DeployerEvent::StepProgress(message.clone(), done, total);
+                    let _ = sender.send(event).await;
+                }
+            }
+        }
+        self.save();
+        Ok(sums)
+    }
+}
+
+/// Size and modification time (ns since the epoch) of a file.
+async fn file_stamp(path: &Path) -> DeployResult<(u64, u128)> {
+    let metadata = tokio::fs::metadata(path)
+        .await
+        .map_err(|e| DeployError::FileSync(format!("Failed to read {}: {}", path.display(), e)))?;
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or_default();
+    Ok((metadata.len(), mtime))
+}
+
+/// SHA-256 of a file, read in chunks so large files need little memory.
+pub(crate) fn hash_file(path: &Path) -> DeployResult<String> {
+    let mut file = std::fs::File::open(path)
+        .map_err(|e| DeployError::FileSync(format!("Failed to open file: {}", e)))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| DeployError::FileSync(format!("Failed to read file: {}", e)))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_hash_files_uses_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("data");
+        std::fs::create_dir(&root).unwrap();
+        std::fs::write(root.join("a.bin"), "a").unwrap();
+        std::fs::write(root.join("b.bin"), "b").unwrap();
+        // Old enough to be cached
+        let old = SystemTime::now() - Duration::from_secs(60);
+        for name in ["a.bin", "b.bin"] {
+            std::fs::File::options()
+                .write(true)
+                .open(root.join(name))
+                .unwrap()
+                .set_modified(old)
+                .unwrap();
+        }
+        let files = [PathBuf::from("a.bin"), PathBuf::from("b.bin")];
+        let cache_path = dir.path().join("cache.json");
+
+        let mut cache = HashCache::load(cache_path.clone());
+        let sums = cache.hash_files(&root, &files, None).await.unwrap();
+        assert_eq!(
+            sums[Path::new("a.bin")],
+            hash_file(&root.join("a.bin")).unwrap()
+        );
+
+        // A cached checksum is trusted while size and mtime match, so a
+        // forged entry shows it was not recomputed
+        let mut cache = HashCache::load(cache_path.clone());
+        assert_eq!(cache.entries.len(), 2);
+        cache.entries.get_mut(Path::new("a.bin")).unwrap().sha256 = "cached".into();
+        let sums = cache.hash_files(&root, &files, None).await.unwrap();
+        assert_eq!(sums[Path::new("a.bin")], "cached");
+
+        // Touched files are hashed again, deleted ones leave the cache
+        std::fs::write(root.join("a.bin"), "changed").unwrap();
+        let sums = cache.hash_files(&root, &files[..1], None).await.unwrap();
+        assert_eq!(
+            sums[Path::new("a.bin")],
+            hash_file(&root.join("a.bin")).unwrap()
+        );
+        let cache = HashCache::load(cache_path);
+        // a.bin was modified just now, so it is not cached yet
+        assert!(cache.entries.is_empty());
+    }
+}
@@ -0,0 +1,207 @@
+//! Which entries of a referenced directory are left out when syncing it.
+//!
+//! Rules use gitignore syntax and come, in increasing precedence, from the
+//! defaults below, the directory's `.dockerignore` and its `.dcdignore`; the
+//! last matching rule decides, so `!pattern` re-includes an entry. Entries
+//! inside an ignored directory cannot be re-included, as with git.
+
+use crate::deployer::types::{DeployError, DeployResult};
+use regex::Regex;
+use std::path::Path;
+
+/// Ignore files read from the root of a synced directory, in this order.
+const IGNORE_FILES: [&str; 2] = [".dockerignore", ".dcdignore"];
+
+/// Never wanted on the server: version control data and dependency or
+/// cache directories that are rebuilt there anyway.
+const DEFAULT_IGNORES: [&str; 5] = [".git/", ".hg/", ".svn/", "node_modules/", "__pycache__/"];
+
+/// Hidden entries (dotfiles), skipped unless hidden files are included.
+const HIDDEN: &str = ".*";
+
+#[derive(Debug)]
+struct Rule {
+    regex: Regex,
+    negated: bool,
+    dir_only: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct IgnoreRules {
+    rules: Vec<Rule>,
+}
+
+impl IgnoreRules {
+    /// Rules for the directory `root`: the defaults (hidden entries too,
+    /// unless `include_hidden`) followed by its ignore files.
+    pub fn load(root: &Path, include_hidden: bool) -> DeployResult<Self> {
+        let mut rules = Self::default();
+        for pattern in DEFAULT_IGNORES {
+            rules.add(pattern)?;
+        }
+        if !include_hidden {
+            rules.add(HIDDEN)?;
+        }
+        for name in IGNORE_FILES {
+            let path = root.join(name);
+            let content = match std::fs::read_to_string(&path) {
+                Ok(content) => content,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => {
+                    return Err(DeployError::FileSync(format!(
+                        "Failed to read {}: {}",
+                        path.display(),
+                        e
+                    )))
+                }
+            };
+            for line in content.lines() {
+                rules.add(line).map_err(|e| {
+                    DeployError::Configuration(format!("{}: {}", path.display(), e))
+                })?;
+            }
+        }
+        Ok(rules)
+    }
+
+    /// Add one line of an ignore file; blank lines and comments are skipped.
+    fn add(&mut self, line: &str) -> DeployResult<()> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+        let (negated, pattern) = match line.strip_prefix('!') {
+            Some(pattern) => (true, pattern),
+            None => (false, line),
+        };
+        let (dir_only, pattern) = match pattern.strip_suffix('/') {
+            Some(pattern) => (true, pattern),
+            None => (false, pattern),
+        };
+        // A slash anywhere but the end ties the pattern to the root
+        let anchored = pattern.contains('/');
+        let pattern = pattern.trim_start_matches('/');
+        if pattern.is_empty() {
+            return Ok(());
+        }
+        let prefix = if anchored { "^" } else { "^(?:.*/)?" };
+        let regex = Regex::new(&format!("{}{}$", prefix, glob_to_regex(pattern))).map_err(|e| {
+            DeployError::Configuration(format!("Invalid pattern '{}': {}", line, e))
+        })?;
+        self.rules.push(Rule {
+            regex,
+            negated,
+            dir_only,
+        });
+        Ok(())
+    }
+
+    /// Whether `relative` (a path below the root) is left out.
+    pub fn is_ignored(&self, relative: &Path, is_dir: bool) -> bool {
+        let path = relative.to_string_lossy();
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| (is_dir || !rule.dir_only) && rule.regex.is_match(&path))
+            .is_some_and(|rule| !rule.negated)
+    }
+}
+
+/// Regex for a gitignore glob: `*` and `?` stay within a path segment,
+/// `**` spans segments and `[...]` is a character class.
+fn glob_to_regex(pattern: &str) -> String {
+    let mut regex = String::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    // `**/` matches zero or more directories
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => {
+                let mut class = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        closed = true;
+                        break;
+                    }
+                    class.push(c);
+                }
+                if closed {
+                    let class = match class.strip_prefix('!') {
+                        Some(rest) => format!("^{}", rest),
+                        None => class,
+                    };
+                    regex.push('[');
+                    regex.push_str(&class.replace('\\', "\\\\"));
+                    regex.push(']');
+                } else {
+                    regex.push_str(&regex::escape(&format!("[{}", class)));
+                }
+            }
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    regex.push_str(&regex::escape(&escaped.to_string()));
+                }
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(lines: &str, include_hidden: bool) -> IgnoreRules {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(".dcdignore"), lines).unwrap();
+        IgnoreRules::load(dir.path(), include_hidden).unwrap()
+    }
+
+    #[test]
+    fn test_is_ignored() {
+        let rules = rules(
+            "# build output\n*.log\n/dist/\nassets/**/*.psd\n!keep.log\ncache?/\n[!a]b.tmp\n",
+            false,
+        );
+        let ignored = |path: &str, is_dir| rules.is_ignored(Path::new(path), is_dir);
+
+        assert!(ignored(".git", true));
+        assert!(ignored("sub/node_modules", true));
+        assert!(ignored(".htaccess", false));
+        assert!(ignored("logs/app.log", false));
+        assert!(!ignored("logs/keep.log", false));
+        assert!(ignored("dist", true));
+        assert!(!ignored("dist", false));
+        assert!(!ignored("web/dist", true));
+        assert!(ignored("assets/psd/logo.psd", false));
+        assert!(ignored("assets/logo.psd", false));
+        assert!(!ignored("other/logo.psd", false));
+        assert!(ignored("cache1", true));
+        assert!(ignored("bb.tmp", false));
+        assert!(!ignored("ab.tmp", false));
+        assert!(!ignored("nginx.conf", false));
+
+        let rules = IgnoreRules::load(Path::new("/nonexistent"), true).unwrap();
+        assert!(!rules.is_ignored(Path::new(".htaccess"), false));
+        assert!(rules.is_ignored(Path::new(".git"), true));
+    }
+
+    #[test]
+    fn test_reinclude_hidden() {
+        let rules = rules("!.htaccess\n", false);
+        assert!(!rules.is_ignored(Path::new("public/.htaccess"), false));
+        assert!(rules.is_ignored(Path::new(".env"), false));
+    }
+}
@@ -1,10 +1,28 @@
+mod archive;
+mod backup;
+mod diff;
 mod env;
+mod env_merge;
+mod env_template;
 mod files;
+mod hash_cache;
+mod ignore;
 
 use std::path::{Path, PathBuf};
 
+pub use backup::{
+    backup_file, backup_files, discard_backups, discard_previous, keep_backups_as_previous,
+    restore_backups, restore_previous,
+};
+pub use diff::{looks_secret, redact_text, unified_diff, DiffLine, FileDiff, Redaction};
 pub use env::EnvFileManager;
-pub use files::{FileSync, FileSyncStatus};
+pub use env_merge::{
+    edit_report, env_base_path, find_remote_edits, merge_env, EditedEnvFile, EnvConflict,
+};
+pub use env_template::{EnvTemplate, REMOTE_ENV_FILE};
+pub(crate) use files::{sha256_file, walk_local_directory};
+pub use files::{FileChange, FileSync, FileSyncStatus, PlannedFile};
+pub use ignore::IgnoreRules;
 
 /// Represents a file pair for synchronization
 #[derive(Debug, Clone)]
@@ -65,6 +83,17 @@ impl SyncPlan {
             .push(SyncPair::new(local, remote, is_directory));
     }
 
+    /// Remote paths (files and directories) the plan writes to.
+    pub fn remote_paths(&self) -> Vec<PathBuf> {
+        self.compose_files
+            .iter()
+            .chain(&self.env_files)
+            .chain(&self.reference_files)
+            .chain(&self.files)
+            .map(|pair| pair.remote_path.clone())
+            .collect()
+    }
+
     pub fn is_empty(&self) -> bool {
         self.files.is_empty()
             && self.env_files.is_empty()
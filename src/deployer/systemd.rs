@@ -0,0 +1,225 @@
+use crate::deployer::types::{ComposeExec, DeployError, DeployResult};
+use crate::deployer::{compose_command, project_slug};
+use crate::executor::shell_quote;
+use std::path::{Path, PathBuf};
+
+const UNIT_DIR: &str = "/etc/systemd/system";
+
+/// systemd unit that brings a deployed compose stack up on boot and down on stop.
+#[derive(Debug, Clone)]
+pub struct SystemdUnit {
+    project_name: String,
+    working_directory: PathBuf,
+    compose_files: Vec<PathBuf>,
+    env_files: Vec<PathBuf>,
+}
+
+impl SystemdUnit {
+    pub fn new(
+        working_directory: PathBuf,
+        compose_files: Vec<PathBuf>,
+        env_files: Vec<PathBuf>,
+    ) -> Self {
+        let project_name = working_directory
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "default_project".to_string());
+        Self {
+            project_name,
+            working_directory,
+            compose_files,
+            env_files,
+        }
+    }
+
+    /// Unit name derived from the remote project directory, e.g. `dcd-myapp.service`.
+    pub fn name(&self) -> String {
+        unit_name(&self.working_directory)
+    }
+
+    pub fn path(&self) -> PathBuf {
+        Path::new(UNIT_DIR).join(self.name())
+    }
+
+    fn compose_command(&self, subcommand: &str) -> String {
+        format!(
+            "/usr/bin/env {}",
+            compose_command(
+                &self.working_directory,
+                &self.compose_files,
+                &self.env_files,
+                subcommand
+            )
+        )
+    }
+
+    /// Render the unit file contents.
+    pub fn render(&self) -> String {
+        format!(
+            "# Managed by dcd, changes will be overwritten on the next deploy\n\
+             [Unit]\n\
+             Description=dcd stack {name}\n\
+             Requires=docker.service\n\
+             After=docker.service network-online.target\n\
+             Wants=network-online.target\n\
+             \n\
+             [Service]\n\
+             Type=oneshot\n\
+             RemainAfterExit=yes\n\
+             WorkingDirectory={workdir}\n\
+             ExecStart={up}\n\
+             ExecStop={down}\n\
+             TimeoutStartSec=0\n\
+             \n\
+             [Install]\n\
+             WantedBy=multi-user.target\n",
+            name = self.project_name,
+            workdir = self.working_directory.display(),
+            up = self.compose_command("up -d --remove-orphans"),
+            down = self.compose_command("down"),
+        )
+    }
+}
+
+/// Unit name for a remote project directory.
+pub fn unit_name(working_directory: &Path) -> String {
+    format!("dcd-{}.service", project_slug(working_directory))
+}
+
+pub struct SystemdManager<'a> {
+    executor: &'a mut (dyn ComposeExec + Send),
+}
+
+impl<'a> SystemdManager<'a> {
+    pub fn new(executor: &'a mut (dyn ComposeExec + Send)) -> Self {
+        Self { executor }
+    }
+
+    async fn run(&mut self, cmd: &str) -> DeployResult<String> {
+        let result = self
+            .executor
+            .execute_command(cmd)
+            .await
+            .map_err(|e| DeployError::Deployment(format!("Failed to run '{}': {}", cmd, e)))?;
+        if !result.is_success() {
+            return Err(DeployError::Deployment(format!(
+                "Command '{}' failed: {}",
+                cmd,
+                result.output.to_stderr_string()?.trim()
+            )));
+        }
+        Ok(result.output.to_stdout_string()?)
+    }
+
+    /// Write and enable the unit. Returns `false` when an identical unit was
+    /// already installed and enabled.
+    pub async fn install(&mut self, unit: &SystemdUnit) -> DeployResult<bool> {
+        self.run("command -v systemctl").await.map_err(|_| {
+            DeployError::Deployment("systemd is not available on the remote host".into())
+        })?;
+
+        let path = unit.path();
+        let content = unit.render();
+        let current = self
+            .executor
+            .execute_command(&format!(
+                "cat {} 2>/dev/null",
+                shell_quote(&path.to_string_lossy())
+            ))
+            .await
+            .map_err(|e| DeployError::Deployment(format!("Failed to read unit file: {}", e)))?;
+        let enabled = self
+            .executor
+            .execute_command(&format!(
+                "systemctl is-enabled {}",
+                shell_quote(&unit.name())
+            ))
+            .await
+            .map_err(|e| DeployError::Deployment(format!("Failed to query unit: {}", e)))?;
+        if current.is_success()
+            && current.output.to_stdout_string()? == content
+            && enabled.is_success()
+        {
+            tracing::info!("systemd unit {} is up to date", unit.name());
+            return Ok(false);
+        }
+
+        tracing::info!("Installing systemd unit {}", path.display());
+        self.run(&format!(
+            "cat > {} <<'DCD_UNIT_EOF'\n{}DCD_UNIT_EOF",
+            shell_quote(&path.to_string_lossy()),
+            content
+        ))
+        .await?;
+        // Enable only: the stack was just started by the deploy itself.
+        self.run(&format!(
+            "systemctl daemon-reload && systemctl enable {}",
+            shell_quote(&unit.name())
+        ))
+        .await?;
+        Ok(true)
+    }
+
+    /// Disable and remove the unit for `working_directory` if one is installed.
+    /// Returns whether a unit was removed.
+    pub async fn remove(&mut self, working_directory: &Path) -> DeployResult<bool> {
+        let name = unit_name(working_directory);
+        let path = Path::new(UNIT_DIR).join(&name);
+        let exists = self
+            .executor
+            .execute_command(&format!("test -f {}", shell_quote(&path.to_string_lossy())))
+            .await
+            .map_err(|e| DeployError::Deployment(format!("Failed to check unit file: {}", e)))?;
+        if !exists.is_success() {
+            return Ok(false);
+        }
+
+        tracing::info!("Removing systemd unit {}", path.display());
+        self.run(&format!(
+            "systemctl disable {}; rm -f {} && systemctl daemon-reload",
+            shell_quote(&name),
+            shell_quote(&path.to_string_lossy())
+        ))
+        .await?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_name_is_sanitized() {
+        assert_eq!(
+            unit_name(Path::new("/opt/my app.v2")),
+            "dcd-my-app-v2.service"
+        );
+        assert_eq!(
+            unit_name(Path::new("/opt/shop_api")),
+            "dcd-shop_api.service"
+        );
+    }
+
+    #[test]
+    fn test_render_unit() {
+        let unit = SystemdUnit::new(
+            PathBuf::from("/opt/shop"),
+            vec![PathBuf::from("docker-compose.yml")],
+            vec![PathBuf::from(".env.dcd")],
+        );
+        let rendered = unit.render();
+        assert!(rendered.contains("WorkingDirectory=/opt/shop\n"));
+        assert!(rendered.contains(
+            "ExecStart=/usr/bin/env docker-compose --project-directory /opt/shop -p shop -f /opt/shop/docker-compose.yml --env-file /opt/shop/.env.dcd up -d --remove-orphans\n"
+        ));
+        assert!(rendered.contains(
+            "ExecStop=/usr/bin/env docker-compose --project-directory /opt/shop -p shop -f /opt/shop/docker-compose.yml --env-file /opt/shop/.env.dcd down\n"
+        ));
+        assert!(rendered.contains("WantedBy=multi-user.target"));
+        assert_eq!(
+            unit.path(),
+            PathBuf::from("/etc/systemd/system/dcd-shop.service")
+        );
+    }
+}
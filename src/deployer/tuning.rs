@@ -0,0 +1,220 @@
+use crate::config::{ulimit_directive, ulimit_value, HostValue};
+use crate::deployer::project_slug;
+use crate::deployer::types::{ComposeExec, DeployError, DeployResult};
+use crate::executor::shell_quote;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+const SYSCTL_DIR: &str = "/etc/sysctl.d";
+const DOCKER_DROPIN_DIR: &str = "/etc/systemd/system/docker.service.d";
+const MANAGED_HEADER: &str = "# Managed by dcd, changes will be overwritten on the next deploy\n";
+
+/// Render a sysctl.d file for `sysctls`.
+pub fn render_sysctl_file(sysctls: &BTreeMap<String, HostValue>) -> String {
+    let mut content = String::from(MANAGED_HEADER);
+    for (key, value) in sysctls {
+        content.push_str(&format!("{} = {}\n", key, value));
+    }
+    content
+}
+
+/// Render a docker.service drop-in raising the daemon's (and therefore the
+/// containers' default) resource limits.
+pub fn render_ulimits_dropin(ulimits: &BTreeMap<String, HostValue>) -> String {
+    let mut content = format!("{}[Service]\n", MANAGED_HEADER);
+    for (name, value) in ulimits {
+        if let (Some(directive), Some(value)) = (ulimit_directive(name), ulimit_value(value)) {
+            content.push_str(&format!("{}={}\n", directive, value));
+        }
+    }
+    content
+}
+
+/// Applies host-level kernel parameters and limits a stack depends on.
+pub struct HostTuning<'a> {
+    executor: &'a mut (dyn ComposeExec + Send),
+    slug: String,
+}
+
+impl<'a> HostTuning<'a> {
+    pub fn new(executor: &'a mut (dyn ComposeExec + Send), working_directory: &Path) -> Self {
+        Self {
+            executor,
+            slug: project_slug(working_directory),
+        }
+    }
+
+    async fn run(&mut self, cmd: &str) -> DeployResult<String> {
+        let result = self
+            .executor
+            .execute_command(cmd)
+            .await
+            .map_err(|e| DeployError::Environment(format!("Failed to run '{}': {}", cmd, e)))?;
+        if !result.is_success() {
+            return Err(DeployError::Environment(format!(
+                "Command '{}' failed: {}",
+                cmd,
+                result.output.to_stderr_string()?.trim()
+            )));
+        }
+        Ok(result.output.to_stdout_string()?)
+    }
+
+    fn sysctl_path(&self) -> PathBuf {
+        PathBuf::from(SYSCTL_DIR).join(format!("90-dcd-{}.conf", self.slug))
+    }
+
+    fn ulimits_path(&self) -> PathBuf {
+        PathBuf::from(DOCKER_DROPIN_DIR).join(format!("dcd-{}-limits.conf", self.slug))
+    }
+
+    /// Whether `path` already holds `content`.
+    async fn is_current(&mut self, path: &Path, content: &str) -> DeployResult<bool> {
+        let current = self
+            .executor
+            .execute_command(&format!(
+                "cat {} 2>/dev/null",
+                shell_quote(&path.to_string_lossy())
+            ))
+            .await
+            .map_err(|e| {
+                DeployError::Environment(format!("Failed to read {}: {}", path.display(), e))
+            })?;
+        Ok(current.is_success() && current.output.to_stdout_string()? == content)
+    }
+
+    /// Whether the live value of any of `sysctls` differs from the wanted one.
+    async fn sysctls_differ(
+        &mut self,
+        sysctls: &BTreeMap<String, HostValue>,
+    ) -> DeployResult<bool> {
+        for (key, value) in sysctls {
+            let current = self.run(&format!("sysctl -n {}", shell_quote(key))).await?;
+            // Multi-value parameters are printed tab separated
+            if current
+                .split_whitespace()
+                .ne(value.to_string().split_whitespace())
+            {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// What [`Self::apply_sysctls`] and [`Self::apply_ulimits`] would change,
+    /// found with read-only commands.
+    pub async fn planned_changes(
+        &mut self,
+        sysctls: &BTreeMap<String, HostValue>,
+        ulimits: &BTreeMap<String, HostValue>,
+    ) -> DeployResult<Vec<String>> {
+        let mut changes = Vec::new();
+        if !sysctls.is_empty() {
+            let path = self.sysctl_path();
+            if !self.is_current(&path, &render_sysctl_file(sysctls)).await?
+                || self.sysctls_differ(sysctls).await?
+            {
+                changes.push(format!("write {} and load it (sysctl -p)", path.display()));
+            }
+        }
+        if !ulimits.is_empty() {
+            let path = self.ulimits_path();
+            if !self
+                .is_current(&path, &render_ulimits_dropin(ulimits))
+                .await?
+            {
+                changes.push(format!("write {} and restart docker", path.display()));
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Write `content` to `path` unless it is already there. Returns whether
+    /// the file changed.
+    async fn write_if_changed(&mut self, path: &Path, content: &str) -> DeployResult<bool> {
+        if self.is_current(path, content).await? {
+            return Ok(false);
+        }
+        if let Some(parent) = path.parent() {
+            self.run(&format!(
+                "mkdir -p {}",
+                shell_quote(&parent.to_string_lossy())
+            ))
+            .await?;
+        }
+        self.executor
+            .upload_content(content.as_bytes(), path)
+            .await
+            .map_err(|e| {
+                DeployError::Environment(format!("Failed to write {}: {}", path.display(), e))
+            })?;
+        Ok(true)
+    }
+
+    /// Persist `sysctls` in sysctl.d and load any whose live value differs.
+    /// Returns whether anything changed.
+    pub async fn apply_sysctls(
+        &mut self,
+        sysctls: &BTreeMap<String, HostValue>,
+    ) -> DeployResult<bool> {
+        if sysctls.is_empty() {
+            return Ok(false);
+        }
+        let path = self.sysctl_path();
+        let written = self
+            .write_if_changed(&path, &render_sysctl_file(sysctls))
+            .await?;
+        let changed = written || self.sysctls_differ(sysctls).await?;
+        if changed {
+            tracing::info!("Applying sysctl settings from {}", path.display());
+            self.run(&format!(
+                "sysctl -p {}",
+                shell_quote(&path.to_string_lossy())
+            ))
+            .await?;
+        }
+        Ok(changed)
+    }
+
+    /// Install a docker.service drop-in with `ulimits`, restarting docker
+    /// when it changed. Returns whether anything changed.
+    pub async fn apply_ulimits(
+        &mut self,
+        ulimits: &BTreeMap<String, HostValue>,
+    ) -> DeployResult<bool> {
+        if ulimits.is_empty() {
+            return Ok(false);
+        }
+        let path = self.ulimits_path();
+        if !self
+            .write_if_changed(&path, &render_ulimits_dropin(ulimits))
+            .await?
+        {
+            return Ok(false);
+        }
+        tracing::warn!("Docker limits changed, restarting the docker daemon");
+        self.run("systemctl daemon-reload").await?;
+        // Docker may not be installed yet on a fresh host; it picks the drop-in up on first start
+        self.run("if systemctl is-active --quiet docker; then systemctl restart docker; fi")
+            .await?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_host_tuning_files() {
+        let mut sysctls = BTreeMap::new();
+        sysctls.insert("vm.max_map_count".to_string(), HostValue::Int(262144));
+        assert!(render_sysctl_file(&sysctls).ends_with("vm.max_map_count = 262144\n"));
+
+        let mut ulimits = BTreeMap::new();
+        ulimits.insert("nofile".to_string(), HostValue::Text("65536:65536".into()));
+        ulimits.insert("memlock".to_string(), HostValue::Text("unlimited".into()));
+        assert!(render_ulimits_dropin(&ulimits)
+            .ends_with("[Service]\nLimitMEMLOCK=infinity\nLimitNOFILE=65536:65536\n"));
+    }
+}
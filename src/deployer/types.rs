@@ -1,15 +1,24 @@
-use super::docker_manager::DockerError;
-use crate::composer::types::{PortMapping, VolumeMapping};
+use super::docker_manager::{DockerError, ServiceStatus, UnhealthyService};
+use super::health::ServiceTimeline;
+use super::images::{ImageChange, ServiceImage};
+use super::state::DeployRecord;
+use super::sync::FileDiff;
+use crate::composer::types::{
+    CronJob, PortMapping, ServiceDependency, ServiceHints, VolumeMapping,
+};
+use crate::config::{HookStage, Hooks, HostValue, SecretSource, WaitStrategy};
 use crate::executor::{CommandExecutor, FileTransfer, OutputError};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fmt;
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 
 pub trait ComposeExec: CommandExecutor + FileTransfer {}
 impl<T: CommandExecutor + FileTransfer> ComposeExec for T {}
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct DeploymentConfig {
     /// Local project directory
     pub project_dir: PathBuf,
@@ -21,12 +30,214 @@ pub struct DeploymentConfig {
     pub env_files: Vec<PathBuf>,
     /// Environment variables required by the project
     pub consumed_env: HashMap<String, String>,
+    /// Variables fetched from secret stores at deploy time (from dcd.toml)
+    pub secrets: BTreeMap<String, SecretSource>,
     /// Ports that need to be exposed
     pub exposed_ports: Vec<PortMapping>,
     /// Local files/directories that need to be synchronized
     pub local_references: Vec<PathBuf>,
     /// Volume mappings from compose file
     pub volumes: Vec<VolumeMapping>,
+    /// `depends_on` relations keyed by dependent service name
+    pub service_dependencies: HashMap<String, Vec<ServiceDependency>>,
+    /// Compose profiles active for this deployment
+    pub profiles: Vec<String>,
+    /// Services to pull, build, start and check; all when empty
+    pub services: Vec<String>,
+    /// Owner (`user[:group]` or `uid[:gid]`) applied to synced files on the remote
+    pub file_owner: Option<String>,
+    /// Install a systemd unit that brings the stack up on boot
+    pub systemd_unit: bool,
+    /// Extra readiness checks (from dcd.toml) keyed by service name
+    pub wait_strategies: BTreeMap<String, WaitStrategy>,
+    /// Commands run at stages of the deployment (from dcd.toml and --hook)
+    pub hooks: Hooks,
+    /// Periodic jobs installed into /etc/cron.d
+    pub cron_jobs: Vec<CronJob>,
+    /// External networks to create on the host before `compose up`
+    pub external_networks: Vec<String>,
+    /// Services that reserve GPUs (need the NVIDIA container toolkit)
+    pub gpu_services: Vec<String>,
+    /// Install the NVIDIA container toolkit if GPU services need it
+    pub install_gpu_toolkit: bool,
+    /// OS packages the stack needs on the host
+    pub host_packages: Vec<String>,
+    /// Kernel parameters applied on the host before `compose up`
+    pub sysctls: BTreeMap<String, HostValue>,
+    /// Resource limits applied to the docker daemon before `compose up`
+    pub ulimits: BTreeMap<String, HostValue>,
+    /// Pipeline stages that are not run
+    pub skip_stages: BTreeSet<DeployStage>,
+    /// Local age identity; when set the env is stored encrypted on the server
+    pub age_identity: Option<PathBuf>,
+    /// Local template the remote .env is generated from on the first deploy
+    pub env_template: Option<PathBuf>,
+    /// Regenerate the remote .env from the template even if it exists
+    pub force_env: bool,
+    /// How env files edited on the server since the last deploy are handled
+    pub env_edits: EnvEditPolicy,
+    /// Parallel uploads per synced directory; `None` uses the default
+    pub sync_concurrency: Option<usize>,
+    /// Show a diff of each compose and env file before it is overwritten
+    pub show_diff: bool,
+    /// Sync hidden files of referenced directories, skipped by default
+    pub include_hidden: bool,
+    /// Build images with a `build:` section without cache and with fresh
+    /// base images
+    pub fresh_build: bool,
+    /// Restart policy set through an override file on services that have none
+    pub restart_policy: Option<String>,
+    /// Long-running services without a restart policy of their own
+    pub services_without_restart: Vec<String>,
+    /// `x-dcd` hints from the compose file keyed by service name
+    pub service_hints: BTreeMap<String, ServiceHints>,
+    /// How long and how often services are checked after `compose up`
+    pub health_check: HealthCheckSettings,
+    /// Deploying on the machine dcd runs on (the `local` target), which
+    /// need not be Linux
+    pub on_local_machine: bool,
+}
+
+impl DeploymentConfig {
+    pub fn runs_stage(&self, stage: DeployStage) -> bool {
+        !self.skip_stages.contains(&stage)
+    }
+}
+
+/// Timing of the health check that follows `compose up`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HealthCheckSettings {
+    /// Attempts before services reporting unhealthy fail the deployment
+    pub retries: u32,
+    /// Pause between two attempts
+    pub interval: Duration,
+    /// How long each service may stay in the `starting` state, counted
+    /// from when its own health check starts reporting
+    pub timeout: Duration,
+    /// Check again as soon as a container starts, dies or changes health
+    /// (watching `docker events`) rather than only every interval
+    pub watch_events: bool,
+}
+
+impl HealthCheckSettings {
+    pub const DEFAULT_RETRIES: u32 = 5;
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(10);
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(150);
+}
+
+impl Default for HealthCheckSettings {
+    fn default() -> Self {
+        Self {
+            retries: Self::DEFAULT_RETRIES,
+            interval: Self::DEFAULT_INTERVAL,
+            timeout: Self::DEFAULT_TIMEOUT,
+            watch_events: false,
+        }
+    }
+}
+
+/// What to do with env files edited on the server since the last deploy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EnvEditPolicy {
+    /// Stop before changing anything and report the edits
+    #[default]
+    Abort,
+    /// Keep the server's edits where they do not conflict with local changes
+    Merge,
+    /// Replace the edited files with the local ones
+    Overwrite,
+}
+
+impl EnvEditPolicy {
+    pub const ALL: [EnvEditPolicy; 3] = [Self::Abort, Self::Merge, Self::Overwrite];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Abort => "abort",
+            Self::Merge => "merge",
+            Self::Overwrite => "overwrite",
+        }
+    }
+}
+
+impl FromStr for EnvEditPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|policy| policy.as_str() == s.trim())
+            .ok_or_else(|| {
+                format!(
+                    "unknown policy '{}' (expected one of: {})",
+                    s,
+                    Self::ALL.map(|policy| policy.as_str()).join(", ")
+                )
+            })
+    }
+}
+
+/// Named steps of the deploy pipeline, selectable with `--only` / `--skip`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeployStage {
+    /// Prepare the remote environment (.env.dcd, host packages, sysctls)
+    Env,
+    /// Synchronize project files
+    Sync,
+    /// Configure firewall rules
+    Firewall,
+    /// Pull images
+    Pull,
+    /// Start services (plus cron jobs and systemd unit)
+    Up,
+    /// Wait for services to become healthy
+    Health,
+}
+
+impl DeployStage {
+    pub const ALL: [DeployStage; 6] = [
+        Self::Env,
+        Self::Sync,
+        Self::Firewall,
+        Self::Pull,
+        Self::Up,
+        Self::Health,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Env => "env",
+            Self::Sync => "sync",
+            Self::Firewall => "firewall",
+            Self::Pull => "pull",
+            Self::Up => "up",
+            Self::Health => "health",
+        }
+    }
+}
+
+impl fmt::Display for DeployStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for DeployStage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|stage| stage.as_str() == s.trim())
+            .ok_or_else(|| {
+                format!(
+                    "unknown stage '{}' (expected one of: {})",
+                    s,
+                    Self::ALL.map(|stage| stage.as_str()).join(", ")
+                )
+            })
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,6 +252,74 @@ pub struct DeploymentStatus {
     pub services_healthy: bool,
     /// Detailed status message
     pub message: String,
+    /// Services whose image changed with this deployment
+    #[serde(default)]
+    pub image_changes: Vec<ImageChange>,
+    /// Last successful deployment recorded on the host
+    #[serde(default)]
+    pub last_deploy: Option<DeployRecord>,
+    /// Local files uploaded because they differed from the remote copy
+    #[serde(default)]
+    pub synced_files: Vec<PathBuf>,
+    /// Services that were not healthy at the last health check
+    #[serde(default)]
+    pub unhealthy_services: Vec<UnhealthyService>,
+    /// Every container of the project, as found by a status check
+    #[serde(default)]
+    pub services: Vec<ServiceDetail>,
+    /// Local files left alone because the remote copy was up to date
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped_files: Vec<PathBuf>,
+    /// Published ports allowed through the firewall, e.g. `8080/tcp`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub opened_ports: Vec<String>,
+    /// Images the project's containers run after the deployment, by service
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub images: BTreeMap<String, ServiceImage>,
+    /// How long each stage that ran took, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stage_durations: Vec<StageDuration>,
+    /// What each service went through during the health check
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub health_timelines: Vec<ServiceTimeline>,
+}
+
+/// Wall-clock time one deployment stage took.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StageDuration {
+    pub stage: DeployStage,
+    pub millis: u64,
+}
+
+/// One container of the project in a status report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceDetail {
+    /// Compose service name
+    pub name: String,
+    pub container: String,
+    /// e.g. `running`, `exited`, `restarting`
+    pub state: String,
+    /// `None` when the service has no health check
+    pub health: Option<String>,
+    /// Published ports, e.g. `8080->80/tcp`
+    pub ports: Vec<String>,
+    pub image: String,
+    /// How often docker restarted the container
+    pub restarts: u32,
+}
+
+impl ServiceDetail {
+    pub fn new(status: &ServiceStatus, restarts: u32) -> Self {
+        Self {
+            name: status.service.clone(),
+            container: status.name.clone(),
+            state: status.state.clone(),
+            health: Some(status.health.clone()).filter(|health| !health.is_empty()),
+            ports: status.published_ports(),
+            image: status.image.clone(),
+            restarts,
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -63,6 +342,13 @@ pub enum DeployError {
     #[error("Deployment failed: {0}")]
     Deployment(String),
 
+    #[error("{stage} hook '{command}' failed: {message}")]
+    Hook {
+        stage: HookStage,
+        command: String,
+        message: String,
+    },
+
     #[error("Output processing error: {0}")]
     OutputError(#[from] OutputError),
 
@@ -86,9 +372,27 @@ impl DeploymentStatus {
             ports_changed: false,
             services_healthy: false,
             message: String::new(),
+            image_changes: Vec::new(),
+            last_deploy: None,
+            synced_files: Vec::new(),
+            unhealthy_services: Vec::new(),
+            services: Vec::new(),
+            skipped_files: Vec::new(),
+            opened_ports: Vec::new(),
+            images: BTreeMap::new(),
+            stage_durations: Vec::new(),
+            health_timelines: Vec::new(),
         }
     }
 
+    /// Record that `stage` ran from `started` until now.
+    pub fn record_stage(&mut self, stage: DeployStage, started: Instant) {
+        self.stage_durations.push(StageDuration {
+            stage,
+            millis: started.elapsed().as_millis() as u64,
+        });
+    }
+
     pub fn with_message(message: impl Into<String>) -> Self {
         Self {
             message: message.into(),
@@ -111,7 +415,18 @@ pub enum DeployerEvent {
     StepCompleted(String),
     StepFailed(String, String),
     HealthCheckAttempt(u32, u32),
+    /// Working on item `current` of `total` within a step
+    StepProgress(String, usize, usize),
     HealthCheckStatus(String),
+    /// A compose or env file is about to replace a different remote copy
+    FileDiff(FileDiff),
+    /// Pulling image `current` of `total`, with the share of layers
+    /// downloaded once known
+    PullProgress(usize, usize, Option<u8>),
+    /// A line of output of a running compose command
+    ComposeOutput(String),
+    /// Uploading a file: bytes sent so far of its size
+    UploadProgress(PathBuf, u64, u64),
 }
 
 impl fmt::Display for DeployerEvent {
@@ -121,7 +436,20 @@ impl fmt::Display for DeployerEvent {
             DeployerEvent::StepCompleted(msg) => write!(f, "Completed: {}", msg),
             DeployerEvent::StepFailed(step, err) => write!(f, "Failed: {} - {}", step, err),
             DeployerEvent::HealthCheckAttempt(a, t) => write!(f, "Health Check ({}/{})", a, t),
+            DeployerEvent::StepProgress(msg, c, t) => write!(f, "Progress: {} ({}/{})", msg, c, t),
             DeployerEvent::HealthCheckStatus(s) => write!(f, "Health Status: {}", s),
+            DeployerEvent::FileDiff(diff) => write!(f, "Changes to {}", diff.remote_path.display()),
+            DeployerEvent::PullProgress(c, t, percent) => {
+                write!(f, "Pulling image {}/{}", c, t)?;
+                match percent {
+                    Some(percent) => write!(f, " ({}%)", percent),
+                    None => Ok(()),
+                }
+            }
+            DeployerEvent::ComposeOutput(line) => write!(f, "{}", line),
+            DeployerEvent::UploadProgress(path, sent, total) => {
+                write!(f, "Uploading {} ({}/{} bytes)", path.display(), sent, total)
+            }
         }
     }
 }
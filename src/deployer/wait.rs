@@ -0,0 +1,37 @@
+use crate::config::WaitStrategy;
+use crate::deployer::types::{ComposeExec, DeployError, DeployResult};
+use std::time::{Duration, Instant};
+
+const PROBE_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Probe `strategy` on the remote host until it succeeds or its timeout
+/// elapses. Returns whether the check passed.
+pub async fn wait_until_ready(
+    executor: &mut (dyn ComposeExec + Send),
+    strategy: &WaitStrategy,
+) -> DeployResult<bool> {
+    let timeout = strategy.timeout().ok_or_else(|| {
+        DeployError::Configuration(format!("Invalid wait timeout for {}", strategy.describe()))
+    })?;
+    let command = strategy.probe_command();
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        let result = executor
+            .execute_command(&command)
+            .await
+            .map_err(|e| DeployError::Deployment(format!("Failed to run wait probe: {}", e)))?;
+        if result.is_success() {
+            return Ok(true);
+        }
+        tracing::debug!(
+            "{} not ready yet: {}",
+            strategy.describe(),
+            result.output.to_stderr_string().unwrap_or_default().trim()
+        );
+        if Instant::now() + PROBE_INTERVAL > deadline {
+            return Ok(false);
+        }
+        tokio::time::sleep(PROBE_INTERVAL).await;
+    }
+}
@@ -0,0 +1,300 @@
+use super::error::ExecutorError;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use hmac::{Hmac, Mac};
+use russh::keys;
+use sha1::Sha1;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+/// What to do when the server's host key is not in known_hosts. A key that
+/// does not match the recorded one is always rejected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HostKeyPolicy {
+    /// Connect with a warning
+    #[default]
+    Warn,
+    /// Connect without a warning (`--no-warnings`)
+    Accept,
+    /// Refuse to connect (`--strict-host-key-checking`)
+    Strict,
+    /// Record the key in known_hosts and connect (`--accept-new`)
+    AcceptNew,
+}
+
+/// How a host's name is written in a known_hosts entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum HostPattern {
+    Plain(String),
+    /// `|1|salt|hash`: HMAC-SHA1 of the name keyed with the salt, as
+    /// written by `HashKnownHosts yes` or `ssh-keygen -H`
+    Hashed {
+        salt: Vec<u8>,
+        hash: Vec<u8>,
+    },
+}
+
+impl HostPattern {
+    fn parse(pattern: &str) -> Option<Self> {
+        let Some(hashed) = pattern.strip_prefix("|1|") else {
+            return Some(Self::Plain(pattern.to_string()));
+        };
+        let (salt, hash) = hashed.split_once('|')?;
+        Some(Self::Hashed {
+            salt: STANDARD.decode(salt).ok()?,
+            hash: STANDARD.decode(hash).ok()?,
+        })
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            Self::Plain(pattern) => wildcard_match(pattern.as_bytes(), name.as_bytes()),
+            Self::Hashed { salt, hash } => {
+                let Ok(mut mac) = Hmac::<Sha1>::new_from_slice(salt) else {
+                    return false;
+                };
+                mac.update(name.as_bytes());
+                mac.verify_slice(hash).is_ok()
+            }
+        }
+    }
+}
+
+/// Whether `name` matches `pattern`, where `*` stands for any run of
+/// characters and `?` for one, as in OpenSSH host patterns.
+fn wildcard_match(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|skip| wildcard_match(rest, &name[skip..])),
+        Some((b'?', rest)) => !name.is_empty() && wildcard_match(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && wildcard_match(rest, &name[1..]),
+    }
+}
+
+/// Host keys loaded from a known_hosts file.
+#[derive(Debug, Clone)]
+pub struct KnownHosts {
+    path: PathBuf,
+    entries: Vec<(HostPattern, keys::PublicKey)>,
+    /// `@revoked` entries: keys never accepted for matching hosts
+    revoked: Vec<(HostPattern, keys::PublicKey)>,
+}
+
+impl KnownHosts {
+    /// Load the entries of `path`; a missing file has none.
+    pub async fn load(path: &Path) -> Result<Self, ExecutorError> {
+        if !path.exists() {
+            tracing::warn!(
+                "Known hosts file not found at '{}'. No host keys will be pre-trusted.",
+                path.display()
+            );
+            return Ok(Self::parse(path, ""));
+        }
+        let content = fs::read_to_string(path).await.map_err(|e| {
+            ExecutorError::SshError(format!(
+                "Failed to read known_hosts file '{}': {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Ok(Self::parse(path, &content))
+    }
+
+    fn parse(path: &Path, content: &str) -> Self {
+        let mut entries = Vec::new();
+        let mut revoked = Vec::new();
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(rest) = line.strip_prefix("@revoked") {
+                revoked.extend(parse_known_host_line(rest.trim()).unwrap_or_default());
+            } else if line.starts_with('@') {
+                // @cert-authority: host certificates are not supported, so
+                // these keys are not trusted for anything
+                tracing::debug!("Skipping known_hosts marker line '{}'", line);
+            } else {
+                entries.extend(parse_known_host_line(line).unwrap_or_default());
+            }
+        }
+        Self {
+            path: path.to_path_buf(),
+            entries,
+            revoked,
+        }
+    }
+
+    /// Whether `key` is marked `@revoked` for any of `names`.
+    pub fn is_revoked(&self, names: &[String], key: &keys::PublicKey) -> bool {
+        self.revoked.iter().any(|(pattern, revoked)| {
+            revoked == key && names.iter().any(|name| pattern.matches(name))
+        })
+    }
+
+    /// Keys recorded for the first of `names` that has any, like OpenSSH.
+    pub fn keys_for(&self, names: &[String]) -> Vec<&keys::PublicKey> {
+        names
+            .iter()
+            .map(|name| {
+                self.entries
+                    .iter()
+                    .filter(|(pattern, _)| pattern.matches(name))
+                    .map(|(_, key)| key)
+                    .collect::<Vec<_>>()
+            })
+            .find(|keys| !keys.is_empty())
+            .unwrap_or_default()
+    }
+
+    /// Append an entry trusting `key` for `name`, creating the file if needed.
+    pub async fn append(&self, name: &str, key: &keys::PublicKey) -> Result<(), ExecutorError> {
+        let entry = key
+            .to_openssh()
+            .map_err(|e| ExecutorError::SshError(format!("Failed to encode host key: {}", e)))?;
+        let write_error = |e: std::io::Error| {
+            ExecutorError::SshError(format!(
+                "Failed to update known_hosts file '{}': {}",
+                self.path.display(),
+                e
+            ))
+        };
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).await.map_err(write_error)?;
+        }
+        // Don't glue the entry onto a last line without a line break
+        let existing = fs::read(&self.path).await.unwrap_or_default();
+        let separator = if existing.is_empty() || existing.ends_with(b"\n") {
+            ""
+        } else {
+            "\n"
+        };
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .map_err(write_error)?;
+        file.write_all(format!("{}{} {}\n", separator, name, entry.trim_end()).as_bytes())
+            .await
+            .map_err(write_error)?;
+        file.flush().await.map_err(write_error)
+    }
+}
+
+/// Parses a single line from a known_hosts file into one entry per host pattern.
+fn parse_known_host_line(line: &str) -> Option<Vec<(HostPattern, keys::PublicKey)>> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() < 3 {
+        return None; // Not enough parts
+    }
+
+    let key = match keys::parse_public_key_base64(parts[2]) {
+        Ok(key) => key,
+        Err(_) => {
+            tracing::warn!(
+                "Failed to parse public key from known_hosts line '{}'",
+                line
+            );
+            return None;
+        }
+    };
+    Some(
+        parts[0]
+            .split(',')
+            .filter_map(HostPattern::parse)
+            .map(|pattern| (pattern, key.clone()))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "AAAAC3NzaC1lZDI1NTE5AAAAIFGE3eh+64HU6cdGcp9d56rVKFEKl4WUC5msCccJibLF";
+    const OTHER_KEY: &str = "AAAAC3NzaC1lZDI1NTE5AAAAIDWO9nFE7uLMHNrE9SGxkk/7rpBEc2RO7dkkzZp3mwVo";
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_known_hosts_lookup() {
+        let content = format!(
+            "# comment\n\
+             deploy.example.com,203.0.113.7 ssh-ed25519 {KEY}\n\
+             |1|AQIDBAUGBwgJCgsMDQ4PEBESExQ=|gs0/NtJrIuoF6oliy+gvcgPxUxo= ssh-ed25519 {OTHER_KEY}\n\
+             @revoked * ssh-ed25519 {OTHER_KEY}\n"
+        );
+        let known_hosts = KnownHosts::parse(Path::new("known_hosts"), &content);
+        let key = keys::parse_public_key_base64(KEY).unwrap();
+        let other_key = keys::parse_public_key_base64(OTHER_KEY).unwrap();
+
+        assert_eq!(known_hosts.keys_for(&names(&["203.0.113.7"])), vec![&key]);
+        // The hashed entry is for [deploy.example.com]:2222
+        assert_eq!(
+            known_hosts.keys_for(&names(&["[deploy.example.com]:2222", "deploy.example.com"])),
+            vec![&other_key]
+        );
+        assert!(known_hosts
+            .keys_for(&names(&["[deploy.example.com]:22", "other.example.com"]))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_revoked_keys() {
+        let content = format!(
+            "@revoked * ssh-ed25519 {OTHER_KEY}
+             @cert-authority *.example.com ssh-ed25519 {KEY}
+             @revoked old.example.com ssh-ed25519 {KEY}
+"
+        );
+        let known_hosts = KnownHosts::parse(Path::new("known_hosts"), &content);
+        let key = keys::parse_public_key_base64(KEY).unwrap();
+        let other_key = keys::parse_public_key_base64(OTHER_KEY).unwrap();
+
+        assert!(known_hosts.is_revoked(&names(&["deploy.example.com"]), &other_key));
+        assert!(known_hosts.is_revoked(&names(&["203.0.113.7", "old.example.com"]), &key));
+        assert!(!known_hosts.is_revoked(&names(&["deploy.example.com"]), &key));
+        // Markers are not trusted host keys
+        assert!(known_hosts
+            .keys_for(&names(&["deploy.example.com"]))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_hashed_host_pattern() {
+        let pattern =
+            HostPattern::parse("|1|AQIDBAUGBwgJCgsMDQ4PEBESExQ=|iIdRdUBYpuYG2ZyP6vQwGNDfyZs=")
+                .unwrap();
+        assert!(pattern.matches("deploy.example.com"));
+        assert!(!pattern.matches("deploy.example.org"));
+        assert_eq!(HostPattern::parse("|1|not base64|x"), None);
+        let pattern = HostPattern::parse("*.example.?om").unwrap();
+        assert!(pattern.matches("deploy.example.com"));
+        assert!(!pattern.matches("example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_append_known_host() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".ssh").join("known_hosts");
+        let key = keys::parse_public_key_base64(KEY).unwrap();
+
+        let known_hosts = KnownHosts::load(&path).await.unwrap();
+        known_hosts
+            .append("[deploy.example.com]:2222", &key)
+            .await
+            .unwrap();
+
+        let known_hosts = KnownHosts::load(&path).await.unwrap();
+        assert_eq!(
+            known_hosts.keys_for(&names(&["[deploy.example.com]:2222"])),
+            vec![&key]
+        );
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            format!("[deploy.example.com]:2222 ssh-ed25519 {}\n", KEY)
+        );
+    }
+}
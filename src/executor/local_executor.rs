@@ -1,13 +1,22 @@
 use async_trait::async_trait;
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
 use std::time::Instant;
+use tokio::io::{AsyncRead, AsyncReadExt};
 use tokio::process::Command;
+use tokio::sync::mpsc;
 
 use super::error::ExecutorError;
-use super::traits::{CommandExecutor, FileTransfer};
-use super::types::{CommandOutput, CommandResult};
+use super::traits::{CommandExecutor, FileTransfer, UploadProgress};
+use super::types::{CommandOutput, CommandResult, OutputChunk};
 
-pub struct LocalCommandExecutor;
+pub struct LocalCommandExecutor {
+    /// Run commands through `sh -c` instead of splitting them on whitespace
+    shell: bool,
+    /// Variables set for every command
+    command_env: HashMap<String, String>,
+}
 
 impl Default for LocalCommandExecutor {
     fn default() -> Self {
@@ -17,25 +26,51 @@ impl Default for LocalCommandExecutor {
 
 impl LocalCommandExecutor {
     pub fn new() -> Self {
-        Self
+        Self {
+            shell: false,
+            command_env: HashMap::new(),
+        }
     }
-}
 
-#[async_trait]
-impl CommandExecutor for LocalCommandExecutor {
-    async fn execute_command(&mut self, command: &str) -> Result<CommandResult, ExecutorError> {
-        let args: Vec<&str> = command.split_whitespace().collect();
-        if args.is_empty() {
-            return Err(ExecutorError::LocalError("No command provided".to_string()));
+    /// An executor that runs commands with `sh -c`, like a remote shell would,
+    /// so the deployer's commands (pipes, `&&`, redirects) work on this machine.
+    pub fn shell() -> Self {
+        Self {
+            shell: true,
+            command_env: HashMap::new(),
         }
+    }
 
-        let program = args[0];
-        let program_args = &args[1..];
+    fn command(&self, command: &str) -> Result<Command, ExecutorError> {
+        let mut cmd = if self.shell {
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c").arg(command);
+            cmd
+        } else {
+            let args: Vec<&str> = command.split_whitespace().collect();
+            let Some((program, program_args)) = args.split_first() else {
+                return Err(ExecutorError::LocalError("No command provided".to_string()));
+            };
+            let mut cmd = Command::new(program);
+            cmd.args(program_args);
+            cmd
+        };
+        cmd.envs(&self.command_env);
+        Ok(cmd)
+    }
+}
 
+impl LocalCommandExecutor {
+    async fn run(
+        &mut self,
+        command: &str,
+        env: &HashMap<String, String>,
+    ) -> Result<CommandResult, ExecutorError> {
         let start_time = Instant::now();
 
-        let output = Command::new(program)
-            .args(program_args)
+        let output = self
+            .command(command)?
+            .envs(env)
             .output()
             .await
             .map_err(|e| ExecutorError::LocalError(e.to_string()))?;
@@ -51,6 +86,101 @@ impl CommandExecutor for LocalCommandExecutor {
             output: cmd_output,
         })
     }
+}
+
+/// Read `reader` to the end, sending each piece to `output` as `chunk` and
+/// returning all of it.
+async fn forward_output(
+    mut reader: impl AsyncRead + Unpin,
+    output: &mpsc::Sender<OutputChunk>,
+    chunk: fn(Vec<u8>) -> OutputChunk,
+) -> std::io::Result<Vec<u8>> {
+    let mut all = Vec::new();
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(all);
+        }
+        all.extend_from_slice(&buf[..n]);
+        let _ = output.send(chunk(buf[..n].to_vec())).await;
+    }
+}
+
+#[async_trait]
+impl CommandExecutor for LocalCommandExecutor {
+    async fn execute_command(&mut self, command: &str) -> Result<CommandResult, ExecutorError> {
+        self.run(command, &HashMap::new()).await
+    }
+
+    async fn execute_command_with_env(
+        &mut self,
+        command: &str,
+        env: &HashMap<String, String>,
+    ) -> Result<CommandResult, ExecutorError> {
+        self.run(command, env).await
+    }
+
+    async fn execute_command_streaming(
+        &mut self,
+        command: &str,
+        env: &HashMap<String, String>,
+        output: mpsc::Sender<OutputChunk>,
+    ) -> Result<CommandResult, ExecutorError> {
+        let start_time = Instant::now();
+        let local_error = |e: std::io::Error| ExecutorError::LocalError(e.to_string());
+        let mut child = self
+            .command(command)?
+            .envs(env)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(local_error)?;
+        let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+            return Err(ExecutorError::LocalError(
+                "Failed to capture command output".to_string(),
+            ));
+        };
+        let (stdout, stderr) = tokio::try_join!(
+            forward_output(stdout, &output, OutputChunk::Stdout),
+            forward_output(stderr, &output, OutputChunk::Stderr),
+        )
+        .map_err(local_error)?;
+        let status = child.wait().await.map_err(local_error)?;
+
+        let mut cmd_output = CommandOutput::new();
+        cmd_output.stdout = stdout;
+        cmd_output.stderr = stderr;
+        cmd_output.exit_code = status.code().unwrap_or_default() as u32;
+        cmd_output.duration = start_time.elapsed();
+        Ok(CommandResult {
+            command: command.to_string(),
+            output: cmd_output,
+        })
+    }
+
+    async fn execute_interactive(
+        &mut self,
+        command: &str,
+        _tty: bool,
+    ) -> Result<u32, ExecutorError> {
+        // Inherits this terminal, so a tty is there if dcd has one
+        let status = self
+            .command(command)?
+            .status()
+            .await
+            .map_err(|e| ExecutorError::LocalError(e.to_string()))?;
+        Ok(status.code().unwrap_or(1) as u32)
+    }
+
+    fn set_command_env(&mut self, env: HashMap<String, String>) {
+        self.command_env = env;
+    }
+
+    fn command_env(&self) -> &HashMap<String, String> {
+        &self.command_env
+    }
 
     async fn close(&mut self) -> Result<(), ExecutorError> {
         Ok(())
@@ -63,11 +193,136 @@ impl FileTransfer for LocalCommandExecutor {
         &self,
         local_path: &Path,
         remote_path: &Path,
+        progress: Option<&UploadProgress>,
+    ) -> Result<(), ExecutorError> {
+        // Deploying from the directory itself: copying a file onto itself
+        // would truncate it
+        let copied = if is_same_file(local_path, remote_path).await {
+            tokio::fs::metadata(local_path).await.map(|m| m.len())
+        } else {
+            tokio::fs::copy(local_path, remote_path).await
+        }
+        .map_err(|e| ExecutorError::LocalError(e.to_string()))?;
+        if let Some(progress) = progress {
+            progress(copied, copied);
+        }
+        Ok(())
+    }
+
+    async fn upload_content(
+        &self,
+        content: &[u8],
+        remote_path: &Path,
     ) -> Result<(), ExecutorError> {
-        tokio::fs::copy(local_path, remote_path)
+        tokio::fs::write(remote_path, content)
             .await
-            .map_err(|e| ExecutorError::LocalError(e.to_string()))?;
+            .map_err(|e| ExecutorError::LocalError(e.to_string()))
+    }
 
-        Ok(())
+    async fn upload_files(
+        &self,
+        batch: &[(PathBuf, PathBuf)],
+        _concurrency: usize,
+    ) -> Vec<Result<(), ExecutorError>> {
+        let mut results = Vec::with_capacity(batch.len());
+        for (local_path, remote_path) in batch {
+            results.push(self.upload_file(local_path, remote_path, None).await);
+        }
+        results
+    }
+
+    async fn download_content(&self, remote_path: &Path) -> Result<Vec<u8>, ExecutorError> {
+        tokio::fs::read(remote_path)
+            .await
+            .map_err(|e| ExecutorError::LocalError(format!("{}: {}", remote_path.display(), e)))
+    }
+
+    async fn list_files(&self, remote_dir: &Path) -> Result<Vec<PathBuf>, ExecutorError> {
+        let remote_dir = remote_dir.to_path_buf();
+        tokio::task::spawn_blocking(move || list_local_files(&remote_dir))
+            .await
+            .map_err(|e| ExecutorError::LocalError(e.to_string()))?
+    }
+}
+
+/// Regular files below `dir` (following links to files, not to
+/// directories), relative to it and sorted.
+fn list_local_files(dir: &Path) -> Result<Vec<PathBuf>, ExecutorError> {
+    let local_error = |path: &Path, e: std::io::Error| {
+        ExecutorError::LocalError(format!("{}: {}", path.display(), e))
+    };
+    let mut files = Vec::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative) = pending.pop() {
+        let path = dir.join(&relative);
+        for entry in std::fs::read_dir(&path).map_err(|e| local_error(&path, e))? {
+            let entry = entry.map_err(|e| local_error(&path, e))?;
+            let file_type = entry
+                .file_type()
+                .map_err(|e| local_error(&entry.path(), e))?;
+            let relative = relative.join(entry.file_name());
+            if file_type.is_dir() {
+                pending.push(relative);
+            } else if file_type.is_file() || (file_type.is_symlink() && entry.path().is_file()) {
+                files.push(relative);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+async fn is_same_file(a: &Path, b: &Path) -> bool {
+    match (
+        tokio::fs::canonicalize(a).await,
+        tokio::fs::canonicalize(b).await,
+    ) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_shell_commands_and_uploads() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut executor = LocalCommandExecutor::shell();
+        let env = HashMap::from([("NAME".to_string(), "dcd".to_string())]);
+        let result = executor
+            .execute_command_with_env("echo \"hi $NAME\" | tr a-z A-Z && exit 3", &env)
+            .await
+            .unwrap();
+        assert_eq!(result.output.to_stdout_string().unwrap(), "HI DCD\n");
+        assert_eq!(result.output.exit_code, 3);
+
+        // Deploying from the target directory leaves the files alone
+        let file = dir.path().join("docker-compose.yml");
+        std::fs::write(&file, "services: {}\n").unwrap();
+        executor.upload_file(&file, &file, None).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "services: {}\n");
+
+        let copy = dir.path().join("copy.yml");
+        executor.upload_file(&file, &copy, None).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&copy).unwrap(), "services: {}\n");
+    }
+
+    #[tokio::test]
+    async fn test_streaming_output() {
+        let mut executor = LocalCommandExecutor::shell();
+        let (sender, mut output) = mpsc::channel(16);
+        let result = executor
+            .execute_command_streaming("echo out; echo err >&2", &HashMap::new(), sender)
+            .await
+            .unwrap();
+        assert_eq!(result.output.to_stdout_string().unwrap(), "out\n");
+        let mut chunks = Vec::new();
+        while let Some(chunk) = output.recv().await {
+            chunks.push(chunk);
+        }
+        assert!(chunks.contains(&OutputChunk::Stdout(b"out\n".to_vec())));
+        assert!(chunks.contains(&OutputChunk::Stderr(b"err\n".to_vec())));
     }
 }
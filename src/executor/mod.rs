@@ -1,11 +1,22 @@
 pub mod error;
+pub mod known_hosts;
 pub mod local_executor;
+pub mod reachability;
+pub mod recording;
+pub mod redact;
+pub mod session;
+pub mod ssh_config;
 pub mod ssh_executor;
 pub mod traits;
 pub mod types;
 
 pub use error::ExecutorError;
+pub use known_hosts::HostKeyPolicy;
 pub use local_executor::LocalCommandExecutor;
-pub use ssh_executor::SshCommandExecutor;
-pub use traits::{CommandExecutor, FileTransfer};
-pub use types::{CommandOutput, CommandResult, OutputError, OutputFormat, ProcessedOutput};
+pub use recording::{RecordingExecutor, ReplayExecutor};
+pub use redact::Redactor;
+pub use ssh_executor::{AuthMethod, JumpHost, SshAuth, SshCommandExecutor};
+pub use traits::{export_prefix, shell_quote, CommandExecutor, FileTransfer, UploadProgress};
+pub use types::{
+    CommandOutput, CommandResult, OutputChunk, OutputError, OutputFormat, ProcessedOutput,
+};
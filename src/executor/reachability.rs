@@ -0,0 +1,390 @@
+use super::error::ExecutorError;
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpStream;
+use tokio::task::JoinSet;
+
+/// Upper bound for the TCP connect and for the server's greeting; SSH
+/// servers greet right away, so a slow answer means something else is there.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Lines a server may send before its `SSH-` identification (RFC 4253 4.2)
+/// are read up to this many bytes.
+const MAX_GREETING_BYTES: usize = 8192;
+
+/// How long an attempt gets before the next address is tried alongside it
+/// (the connection attempt delay of RFC 8305, "Happy Eyeballs").
+const ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Probe the resolved `addrs` of `target` with [`check_ssh_reachable`] and
+/// return the first one that answers as an SSH server. Each address gets a
+/// head start of [`ATTEMPT_DELAY`] before the next one is tried too, so an
+/// unreachable IPv6 address does not hold up a working IPv4 one.
+pub async fn first_reachable(
+    addrs: Vec<SocketAddr>,
+    target: &str,
+    timeout: Duration,
+) -> Result<SocketAddr, ExecutorError> {
+    let addrs = interleave_families(addrs);
+    let mut attempts = JoinSet::new();
+    let mut errors = Vec::new();
+    let mut next = 0;
+    loop {
+        if let Some(&addr) = addrs.get(next) {
+            next += 1;
+            let target = target.to_string();
+            attempts
+                .spawn(async move { (addr, check_ssh_reachable(addr, &target, timeout).await) });
+        }
+        let finished = if next < addrs.len() {
+            match tokio::time::timeout(ATTEMPT_DELAY, attempts.join_next()).await {
+                Ok(finished) => finished,
+                // Still pending; start the next address as well
+                Err(_) => continue,
+            }
+        } else {
+            attempts.join_next().await
+        };
+        match finished {
+            Some(Ok((addr, Ok(())))) => return Ok(addr),
+            Some(Ok((addr, Err(e)))) => {
+                tracing::debug!("{} ({}) is not reachable: {}", target, addr, e);
+                errors.push(e);
+            }
+            Some(Err(e)) => errors.push(unreachable(format!(
+                "Connection attempt to {} failed: {}",
+                target, e
+            ))),
+            None => break,
+        }
+    }
+
+    if errors.len() <= 1 {
+        return Err(errors
+            .pop()
+            .unwrap_or_else(|| unreachable(format!("No addresses found for host '{}'", target))));
+    }
+    let reasons: Vec<String> = errors
+        .into_iter()
+        .map(|e| match e {
+            ExecutorError::SshError(message) => message,
+            other => other.to_string(),
+        })
+        .collect();
+    Err(unreachable(format!(
+        "Could not reach {} on any of its {} addresses: {}",
+        target,
+        reasons.len(),
+        reasons.join("; ")
+    )))
+}
+
+/// Alternate between address families, starting with the resolver's first
+/// choice, and keep the order within each family (RFC 8305 section 4).
+fn interleave_families(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let Some(first) = addrs.first() else {
+        return addrs;
+    };
+    let first_v6 = first.is_ipv6();
+    let (preferred, other): (Vec<_>, Vec<_>) = addrs
+        .into_iter()
+        .partition(|addr| addr.is_ipv6() == first_v6);
+    let mut preferred = preferred.into_iter();
+    let mut other = other.into_iter();
+    let mut ordered = Vec::new();
+    loop {
+        match (preferred.next(), other.next()) {
+            (None, None) => return ordered,
+            (a, b) => ordered.extend(a.into_iter().chain(b)),
+        }
+    }
+}
+
+/// Open a plain TCP connection to `addr` and wait for the SSH greeting,
+/// so an unreachable or wrong target fails with a specific reason before
+/// any authentication is attempted. `target` is the `host:port` shown in
+/// errors.
+pub async fn check_ssh_reachable(
+    addr: SocketAddr,
+    target: &str,
+    timeout: Duration,
+) -> Result<(), ExecutorError> {
+    let timeout = timeout.min(PROBE_TIMEOUT);
+    // Show the resolved address too when connecting by name
+    let shown = if target == addr.to_string() {
+        target.to_string()
+    } else {
+        format!("{} ({})", target, addr.ip())
+    };
+    let mut stream = match tokio::time::timeout(timeout, TcpStream::connect(addr)).await {
+        Err(_) => {
+            return Err(unreachable(format!(
+                "Timed out connecting to {} after {}s; is the host up, or is the port blocked by a firewall?",
+                shown,
+                timeout.as_secs()
+            )))
+        }
+        Ok(Err(e)) => return Err(unreachable(connect_error_message(&shown, &e))),
+        Ok(Ok(stream)) => stream,
+    };
+
+    let mut greeting = Vec::new();
+    let mut buf = [0u8; 512];
+    let read = tokio::time::timeout(timeout, async {
+        loop {
+            match check_greeting(&greeting) {
+                Greeting::Incomplete if greeting.len() < MAX_GREETING_BYTES => {}
+                _ => return Ok::<(), std::io::Error>(()),
+            }
+            let n = stream.read(&mut buf).await?;
+            if n == 0 {
+                return Ok(());
+            }
+            greeting.extend_from_slice(&buf[..n]);
+        }
+    })
+    .await;
+    match read {
+        Err(_) => {
+            return Err(unreachable(format!(
+                "{} accepted the connection but sent no SSH greeting within {}s; is it an SSH server?",
+                target,
+                timeout.as_secs()
+            )))
+        }
+        Ok(Err(e)) => {
+            return Err(unreachable(format!(
+                "{} dropped the connection before greeting ({}); check sshd's connection limits, fail2ban or TCP wrappers",
+                target, e
+            )))
+        }
+        Ok(Ok(())) => {}
+    }
+
+    match check_greeting(&greeting) {
+        Greeting::Ssh => Ok(()),
+        Greeting::Incomplete if greeting.is_empty() => Err(unreachable(format!(
+            "{} closed the connection without an SSH greeting; check sshd's connection limits, fail2ban or TCP wrappers",
+            target
+        ))),
+        Greeting::Incomplete => Err(unreachable(format!(
+            "{} is not an SSH server (it answered \"{}\")",
+            target,
+            first_line(&greeting)
+        ))),
+        Greeting::NotSsh(line) => Err(unreachable(format!(
+            "{} is not an SSH server (it answered \"{}\")",
+            target, line
+        ))),
+        Greeting::Ssh1(line) => Err(unreachable(format!(
+            "{} only speaks SSH protocol 1 (\"{}\"), which is not supported",
+            target, line
+        ))),
+    }
+}
+
+fn unreachable(message: String) -> ExecutorError {
+    ExecutorError::SshError(message)
+}
+
+fn connect_error_message(target: &str, e: &std::io::Error) -> String {
+    match e.kind() {
+        ErrorKind::ConnectionRefused => format!(
+            "Connection refused by {}: the port is closed; is sshd running and listening on this port?",
+            target
+        ),
+        ErrorKind::HostUnreachable => format!(
+            "{} is unreachable: no route to host; check the address and that the host is up",
+            target
+        ),
+        ErrorKind::NetworkUnreachable => format!(
+            "{} is unreachable: the network is unreachable from this machine (VPN down?)",
+            target
+        ),
+        ErrorKind::TimedOut => format!(
+            "Timed out connecting to {}; is the host up, or is the port blocked by a firewall?",
+            target
+        ),
+        _ => format!("Failed to connect to {}: {}", target, e),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Greeting {
+    /// Identification of an SSH 2 server
+    Ssh,
+    /// Identification of a server that only speaks SSH 1
+    Ssh1(String),
+    /// A complete first line that is not an SSH identification and is no
+    /// pre-identification line either (those are only sent by SSH servers
+    /// in front of `SSH-`, and look like text)
+    NotSsh(String),
+    /// No complete identification line yet
+    Incomplete,
+}
+
+fn check_greeting(data: &[u8]) -> Greeting {
+    let text = String::from_utf8_lossy(data);
+    let mut lines = text.split('\n');
+    // The last piece has no line break yet
+    let complete = lines.clone().count() - 1;
+    for (index, line) in lines.by_ref().take(complete).enumerate() {
+        let line = line.trim_end_matches('\r');
+        if let Some(version) = line.strip_prefix("SSH-") {
+            return if version.starts_with("2.") || version.starts_with("1.99") {
+                Greeting::Ssh
+            } else {
+                Greeting::Ssh1(line.to_string())
+            };
+        }
+        // Binary data or a protocol that answers first (SMTP, FTP, ...)
+        if index == 0 && !looks_like_banner_text(line) {
+            return Greeting::NotSsh(shorten(line));
+        }
+    }
+    Greeting::Incomplete
+}
+
+// Pre-identification lines are free text; anything else is another protocol
+fn looks_like_banner_text(line: &str) -> bool {
+    let protocol_reply = line.len() >= 4
+        && line.as_bytes()[..3].iter().all(u8::is_ascii_digit)
+        && matches!(line.as_bytes()[3], b' ' | b'-');
+    !protocol_reply
+        && !line.starts_with("HTTP/")
+        && !line.starts_with('+')
+        && !line.contains('\u{fffd}')
+        && line.chars().all(|c| !c.is_control() || c == '\t')
+}
+
+fn first_line(data: &[u8]) -> String {
+    shorten(
+        String::from_utf8_lossy(data)
+            .lines()
+            .next()
+            .unwrap_or_default(),
+    )
+}
+
+fn shorten(line: &str) -> String {
+    let line: String = line
+        .chars()
+        .map(|c| if c.is_control() { '?' } else { c })
+        .take(60)
+        .collect();
+    line.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_check_greeting() {
+        assert_eq!(check_greeting(b"SSH-2.0-OpenSSH_9.6\r\n"), Greeting::Ssh);
+        assert_eq!(
+            check_greeting(b"Authorized use only\r\nSSH-2.0-dropbear\r\n"),
+            Greeting::Ssh
+        );
+        assert_eq!(check_greeting(b"SSH-2.0-Open"), Greeting::Incomplete);
+        assert_eq!(
+            check_greeting(b"SSH-1.5-old\n"),
+            Greeting::Ssh1("SSH-1.5-old".into())
+        );
+        assert_eq!(
+            check_greeting(b"220 mail.example.com ESMTP\r\n"),
+            Greeting::NotSsh("220 mail.example.com ESMTP".into())
+        );
+        assert_eq!(
+            check_greeting(b"HTTP/1.1 400 Bad Request\r\n\r\n"),
+            Greeting::NotSsh("HTTP/1.1 400 Bad Request".into())
+        );
+    }
+
+    #[test]
+    fn test_interleave_families() {
+        let v6a: SocketAddr = "[2001:db8::1]:22".parse().unwrap();
+        let v6b: SocketAddr = "[2001:db8::2]:22".parse().unwrap();
+        let v4a: SocketAddr = "192.0.2.1:22".parse().unwrap();
+        let v4b: SocketAddr = "192.0.2.2:22".parse().unwrap();
+        assert_eq!(
+            interleave_families(vec![v6a, v6b, v4a, v4b]),
+            vec![v6a, v4a, v6b, v4b]
+        );
+        assert_eq!(
+            interleave_families(vec![v4a, v6a, v6b]),
+            vec![v4a, v6a, v6b]
+        );
+        assert!(interleave_families(Vec::new()).is_empty());
+    }
+
+    async fn serve_once(reply: &'static [u8]) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let _ = socket.write_all(reply).await;
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_check_ssh_reachable() {
+        let addr = serve_once(b"SSH-2.0-OpenSSH_9.6\r\n").await;
+        check_ssh_reachable(addr, "host:22", Duration::from_secs(5))
+            .await
+            .unwrap();
+
+        let addr = serve_once(b"220 ProFTPD Server\r\n").await;
+        let err = check_ssh_reachable(addr, "host:21", Duration::from_secs(5))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("not an SSH server"), "{}", err);
+
+        let addr = serve_once(b"").await;
+        let err = check_ssh_reachable(addr, "host:22", Duration::from_secs(5))
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("without an SSH greeting"),
+            "{}",
+            err
+        );
+
+        // A port nothing listens on
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        let err = check_ssh_reachable(addr, "host:22", Duration::from_secs(5))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Connection refused"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_first_reachable_skips_dead_addresses() {
+        // Nothing listens here
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let dead = listener.local_addr().unwrap();
+        drop(listener);
+        let alive = serve_once(b"SSH-2.0-OpenSSH_9.6\r\n").await;
+
+        let addr = first_reachable(vec![dead, alive], "host:22", Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(addr, alive);
+
+        let err = first_reachable(vec![dead, dead], "host:22", Duration::from_secs(5))
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("on any of its 2 addresses"),
+            "{}",
+            err
+        );
+    }
+}
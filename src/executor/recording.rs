@@ -0,0 +1,814 @@
+//! Recording of remote sessions and their replay.
+//!
+//! [`RecordingExecutor`] wraps a real executor and appends every command,
+//! its output and every upload to a JSON-lines trace; [`ReplayExecutor`]
+//! plays such a trace back, so a deploy scenario seen once against a real
+//! host can be re-run offline and deterministically.
+//!
+//! Traces never contain the values of variables passed through
+//! `execute_command_with_env` (they are also masked in recorded output) or
+//! the content of uploaded files, only their size and checksum. Output of
+//! other commands is kept as is.
+//!
+//! The same wrapper can echo each command with its exit code and duration
+//! as it runs (`--show-commands`), see [`RecordingExecutor::with_echo`].
+
+use super::traits::{CommandExecutor, FileTransfer, UploadProgress};
+use super::{CommandResult, ExecutorError, OutputChunk, Redactor};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+
+/// One line of a session trace.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SessionEvent {
+    /// Start of a session; a trace holds one per connection
+    Connected {
+        target: String,
+        dcd_version: String,
+        /// Unix timestamp (seconds)
+        at: u64,
+    },
+    /// A command that ran to completion
+    Command {
+        command: String,
+        /// Names of the variables it was given (values are not recorded)
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        env: Vec<String>,
+        exit_code: u32,
+        stdout: String,
+        stderr: String,
+    },
+    /// A command attached to the terminal; its output is not recorded
+    Interactive {
+        command: String,
+        exit_code: u32,
+    },
+    /// A command that could not be run
+    Failed {
+        command: String,
+        error: String,
+    },
+    Upload {
+        remote_path: PathBuf,
+        size: u64,
+        sha256: String,
+    },
+    UploadFailed {
+        remote_path: PathBuf,
+        error: String,
+    },
+}
+
+impl SessionEvent {
+    fn describe(&self) -> String {
+        match self {
+            SessionEvent::Connected { target, .. } => format!("connection to {}", target),
+            SessionEvent::Command { command, .. }
+            | SessionEvent::Interactive { command, .. }
+            | SessionEvent::Failed { command, .. } => format!("command `{}`", command),
+            SessionEvent::Upload { remote_path, .. }
+            | SessionEvent::UploadFailed { remote_path, .. } => {
+                format!("upload to {}", remote_path.display())
+            }
+        }
+    }
+}
+
+/// A command as it starts and ends, for [`RecordingExecutor::with_echo`].
+/// Known secret values in the command are masked.
+#[derive(Debug)]
+pub enum EchoEvent<'a> {
+    Started {
+        command: &'a str,
+    },
+    Finished {
+        command: &'a str,
+        /// `None` if the command could not be run
+        exit_code: Option<u32>,
+        duration: Duration,
+    },
+}
+
+pub type CommandEcho = Box<dyn Fn(&EchoEvent<'_>) + Send + Sync>;
+
+struct Recorder {
+    /// Session trace, if one is written
+    file: Option<File>,
+    echo: Option<CommandEcho>,
+    /// Masks values seen in `execute_command_with_env` from then on
+    redactor: Redactor,
+}
+
+impl Recorder {
+    fn echo_started(&self, command: &str) {
+        if let Some(echo) = &self.echo {
+            echo(&EchoEvent::Started {
+                command: &self.redact(command.as_bytes()),
+            });
+        }
+    }
+
+    fn echo_finished(&self, command: &str, exit_code: Option<u32>, started: Instant) {
+        if let Some(echo) = &self.echo {
+            echo(&EchoEvent::Finished {
+                command: &self.redact(command.as_bytes()),
+                exit_code,
+                duration: started.elapsed(),
+            });
+        }
+    }
+
+    fn write(&mut self, event: &SessionEvent) {
+        let Some(file) = self.file.as_mut() else {
+            return;
+        };
+        let mut line = match serde_json::to_string(event) {
+            Ok(line) => line,
+            Err(e) => {
+                tracing::warn!("Failed to serialize session event: {}", e);
+                return;
+            }
+        };
+        line.push('\n');
+        // One write per line keeps lines whole in an appended file
+        if let Err(e) = file.write_all(line.as_bytes()) {
+            tracing::warn!("Failed to record session event: {}", e);
+        }
+    }
+
+    fn redact(&self, text: &[u8]) -> String {
+        self.redactor.redact(&String::from_utf8_lossy(text))
+    }
+
+    fn record_result(
+        &mut self,
+        command: &str,
+        env: Vec<String>,
+        result: &Result<CommandResult, ExecutorError>,
+    ) {
+        let event = match result {
+            Ok(result) => SessionEvent::Command {
+                command: command.to_string(),
+                env,
+                exit_code: result.output.exit_code,
+                stdout: self.redact(&result.output.stdout),
+                stderr: self.redact(&result.output.stderr),
+            },
+            Err(e) => SessionEvent::Failed {
+                command: command.to_string(),
+                error: self.redact(e.to_string().as_bytes()),
+            },
+        };
+        self.write(&event);
+    }
+
+    fn record_upload(
+        &mut self,
+        remote_path: &Path,
+        content: Option<&[u8]>,
+        result: &Result<(), ExecutorError>,
+    ) {
+        let event = match (result, content) {
+            (Ok(()), Some(content)) => SessionEvent::Upload {
+                remote_path: remote_path.to_path_buf(),
+                size: content.len() as u64,
+                sha256: format!("{:x}", Sha256::digest(content)),
+            },
+            (Ok(()), None) => SessionEvent::Upload {
+                remote_path: remote_path.to_path_buf(),
+                size: 0,
+                sha256: String::new(),
+            },
+            (Err(e), _) => SessionEvent::UploadFailed {
+                remote_path: remote_path.to_path_buf(),
+                error: e.to_string(),
+            },
+        };
+        self.write(&event);
+    }
+}
+
+/// An executor that passes everything on to `inner` and, if enabled,
+/// appends each command and upload to a session trace and echoes commands.
+pub struct RecordingExecutor<E> {
+    inner: E,
+    recorder: Option<Mutex<Recorder>>,
+}
+
+impl<E> RecordingExecutor<E> {
+    /// Record the session with `target` to `path`. The trace is appended to,
+    /// so reconnects (e.g. after a reboot) land in the same file; it is
+    /// created readable by the owner only.
+    pub fn new(inner: E, path: &Path, target: &str) -> Result<Self, ExecutorError> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .mode(0o600)
+            .open(path)
+            .map_err(|e| {
+                ExecutorError::Other(format!(
+                    "Failed to open session trace {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+        let mut recorder = Recorder {
+            file: Some(file),
+            echo: None,
+            redactor: Redactor::default(),
+        };
+        recorder.write(&SessionEvent::Connected {
+            target: target.to_string(),
+            dcd_version: env!("CARGO_PKG_VERSION").to_string(),
+            at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+        });
+        Ok(Self {
+            inner,
+            recorder: Some(Mutex::new(recorder)),
+        })
+    }
+
+    /// Pass everything on to `inner` without recording.
+    pub fn passthrough(inner: E) -> Self {
+        Self {
+            inner,
+            recorder: None,
+        }
+    }
+
+    /// Also hand each command to `echo` when it starts and when it ends.
+    pub fn with_echo(mut self, echo: CommandEcho) -> Self {
+        let recorder = self.recorder.get_or_insert_with(|| {
+            Mutex::new(Recorder {
+                file: None,
+                echo: None,
+                redactor: Redactor::default(),
+            })
+        });
+        recorder
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .echo = Some(echo);
+        self
+    }
+
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut E {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
+
+    fn recorder(&self) -> Option<MutexGuard<'_, Recorder>> {
+        self.recorder.as_ref().map(|recorder| {
+            recorder
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+        })
+    }
+
+    /// Learn the secrets in `env` and echo `command` before running it.
+    fn command_started(&self, command: &str, env: &HashMap<String, String>) -> Instant {
+        if let Some(mut recorder) = self.recorder() {
+            recorder.redactor.add(env.values());
+            recorder.echo_started(command);
+        }
+        Instant::now()
+    }
+
+    fn command_finished(
+        &self,
+        command: &str,
+        env: &HashMap<String, String>,
+        result: &Result<CommandResult, ExecutorError>,
+        started: Instant,
+    ) {
+        if let Some(mut recorder) = self.recorder() {
+            recorder.echo_finished(command, exit_code(result), started);
+            let mut names: Vec<String> = env.keys().cloned().collect();
+            names.sort();
+            recorder.record_result(command, names, result);
+        }
+    }
+}
+
+#[async_trait]
+impl<E: CommandExecutor + Send> CommandExecutor for RecordingExecutor<E> {
+    async fn execute_command(&mut self, command: &str) -> Result<CommandResult, ExecutorError> {
+        let started = Instant::now();
+        if let Some(recorder) = self.recorder() {
+            recorder.echo_started(command);
+        }
+        let result = self.inner.execute_command(command).await;
+        if let Some(mut recorder) = self.recorder() {
+            recorder.echo_finished(command, exit_code(&result), started);
+            recorder.record_result(command, Vec::new(), &result);
+        }
+        result
+    }
+
+    async fn execute_command_with_env(
+        &mut self,
+        command: &str,
+        env: &HashMap<String, String>,
+    ) -> Result<CommandResult, ExecutorError> {
+        let started = self.command_started(command, env);
+        let result = self.inner.execute_command_with_env(command, env).await;
+        self.command_finished(command, env, &result, started);
+        result
+    }
+
+    async fn execute_command_streaming(
+        &mut self,
+        command: &str,
+        env: &HashMap<String, String>,
+        output: mpsc::Sender<OutputChunk>,
+    ) -> Result<CommandResult, ExecutorError> {
+        let started = self.command_started(command, env);
+        let result = self
+            .inner
+            .execute_command_streaming(command, env, output)
+            .await;
+        self.command_finished(command, env, &result, started);
+        result
+    }
+
+    async fn execute_interactive(
+        &mut self,
+        command: &str,
+        tty: bool,
+    ) -> Result<u32, ExecutorError> {
+        let started = Instant::now();
+        if let Some(recorder) = self.recorder() {
+            recorder.echo_started(command);
+        }
+        let result = self.inner.execute_interactive(command, tty).await;
+        if let Some(mut recorder) = self.recorder() {
+            recorder.echo_finished(command, result.as_ref().ok().copied(), started);
+            let event = match &result {
+                Ok(exit_code) => SessionEvent::Interactive {
+                    command: command.to_string(),
+                    exit_code: *exit_code,
+                },
+                Err(e) => SessionEvent::Failed {
+                    command: command.to_string(),
+                    error: e.to_string(),
+                },
+            };
+            recorder.write(&event);
+        }
+        result
+    }
+
+    fn set_command_env(&mut self, env: HashMap<String, String>) {
+        self.inner.set_command_env(env);
+    }
+
+    fn command_env(&self) -> &HashMap<String, String> {
+        self.inner.command_env()
+    }
+
+    async fn close(&mut self) -> Result<(), ExecutorError> {
+        self.inner.close().await
+    }
+}
+
+#[async_trait]
+impl<E: FileTransfer + Send + Sync> FileTransfer for RecordingExecutor<E> {
+    async fn upload_file(
+        &self,
+        local_path: &Path,
+        remote_path: &Path,
+        progress: Option<&UploadProgress>,
+    ) -> Result<(), ExecutorError> {
+        let result = self
+            .inner
+            .upload_file(local_path, remote_path, progress)
+            .await;
+        if let Some(mut recorder) = self.recorder() {
+            let content = std::fs::read(local_path).ok();
+            recorder.record_upload(remote_path, content.as_deref(), &result);
+        }
+        result
+    }
+
+    async fn upload_content(
+        &self,
+        content: &[u8],
+        remote_path: &Path,
+    ) -> Result<(), ExecutorError> {
+        let result = self.inner.upload_content(content, remote_path).await;
+        if let Some(mut recorder) = self.recorder() {
+            recorder.record_upload(remote_path, Some(content), &result);
+        }
+        result
+    }
+
+    async fn upload_files(
+        &self,
+        batch: &[(PathBuf, PathBuf)],
+        concurrency: usize,
+    ) -> Vec<Result<(), ExecutorError>> {
+        let results = self.inner.upload_files(batch, concurrency).await;
+        if let Some(mut recorder) = self.recorder() {
+            // In batch order, whatever order the uploads finished in
+            for ((local, remote), result) in batch.iter().zip(&results) {
+                let content = std::fs::read(local).ok();
+                recorder.record_upload(remote, content.as_deref(), result);
+            }
+        }
+        results
+    }
+
+    // Downloads change nothing on the remote and are not recorded; a replay
+    // cannot serve them
+    async fn download_content(&self, remote_path: &Path) -> Result<Vec<u8>, ExecutorError> {
+        self.inner.download_content(remote_path).await
+    }
+
+    async fn list_files(&self, remote_dir: &Path) -> Result<Vec<PathBuf>, ExecutorError> {
+        self.inner.list_files(remote_dir).await
+    }
+}
+
+fn exit_code(result: &Result<CommandResult, ExecutorError>) -> Option<u32> {
+    result.as_ref().ok().map(|result| result.output.exit_code)
+}
+
+/// Read the sessions of a trace written by [`RecordingExecutor`], one
+/// replay per recorded connection.
+pub fn load_sessions(path: &Path) -> Result<Vec<ReplayExecutor>, ExecutorError> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        ExecutorError::Other(format!(
+            "Failed to read session trace {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+    let mut sessions: Vec<Vec<SessionEvent>> = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event: SessionEvent = serde_json::from_str(line).map_err(|e| {
+            ExecutorError::Other(format!(
+                "Invalid session trace {} line {}: {}",
+                path.display(),
+                index + 1,
+                e
+            ))
+        })?;
+        match sessions.last_mut() {
+            Some(session) if !matches!(event, SessionEvent::Connected { .. }) => {
+                session.push(event)
+            }
+            _ => sessions.push(vec![event]),
+        }
+    }
+    Ok(sessions.into_iter().map(ReplayExecutor::new).collect())
+}
+
+/// An executor that answers from a recorded session, in order. A command or
+/// upload other than the next recorded one fails with an error naming both.
+pub struct ReplayExecutor {
+    target: Option<String>,
+    events: Mutex<VecDeque<SessionEvent>>,
+    step: Mutex<usize>,
+    /// Kept only to answer `command_env`; recorded commands never include it
+    command_env: HashMap<String, String>,
+}
+
+impl ReplayExecutor {
+    pub fn new(events: Vec<SessionEvent>) -> Self {
+        let mut events = VecDeque::from(events);
+        let target = match events.front() {
+            Some(SessionEvent::Connected { target, .. }) => {
+                let target = target.clone();
+                events.pop_front();
+                Some(target)
+            }
+            _ => None,
+        };
+        Self {
+            target,
+            events: Mutex::new(events),
+            step: Mutex::new(0),
+            command_env: HashMap::new(),
+        }
+    }
+
+    /// The host the session was recorded against.
+    pub fn target(&self) -> Option<&str> {
+        self.target.as_deref()
+    }
+
+    /// Recorded events that were not replayed yet.
+    pub fn remaining(&self) -> usize {
+        self.lock_events().len()
+    }
+
+    fn lock_events(&self) -> MutexGuard<'_, VecDeque<SessionEvent>> {
+        self.events
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Take the next event if `accept` recognizes it as the current action.
+    fn next(
+        &self,
+        action: &str,
+        accept: impl FnOnce(&SessionEvent) -> bool,
+    ) -> Result<SessionEvent, ExecutorError> {
+        let mut step = self
+            .step
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        *step += 1;
+        let mut events = self.lock_events();
+        match events.front() {
+            Some(event) if accept(event) => Ok(events.pop_front().expect("front exists")),
+            Some(event) => Err(ExecutorError::Other(format!(
+                "Replay diverged at step {}: recorded {}, got {}",
+                step,
+                event.describe(),
+                action
+            ))),
+            None => Err(ExecutorError::Other(format!(
+                "Replay diverged at step {}: the session ended, got {}",
+                step, action
+            ))),
+        }
+    }
+
+    fn replay_command(&self, command: &str) -> Result<CommandResult, ExecutorError> {
+        let event = self.next(&format!("command `{}`", command), |event| {
+            matches!(event,
+                SessionEvent::Command { command: recorded, .. }
+                | SessionEvent::Failed { command: recorded, .. } if recorded == command)
+        })?;
+        match event {
+            SessionEvent::Command {
+                exit_code,
+                stdout,
+                stderr,
+                ..
+            } => {
+                let mut result = CommandResult::new(command);
+                result.output.stdout = stdout.into_bytes();
+                result.output.stderr = stderr.into_bytes();
+                result.output.exit_code = exit_code;
+                Ok(result)
+            }
+            SessionEvent::Failed { error, .. } => Err(ExecutorError::Other(error)),
+            _ => unreachable!("only commands are accepted"),
+        }
+    }
+
+    fn replay_upload(&self, remote_path: &Path) -> Result<(), ExecutorError> {
+        let event = self.next(&format!("upload to {}", remote_path.display()), |event| {
+            matches!(event,
+                SessionEvent::Upload { remote_path: recorded, .. }
+                | SessionEvent::UploadFailed { remote_path: recorded, .. } if recorded == remote_path)
+        })?;
+        match event {
+            SessionEvent::UploadFailed { error, .. } => Err(ExecutorError::Other(error)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl CommandExecutor for ReplayExecutor {
+    async fn execute_command(&mut self, command: &str) -> Result<CommandResult, ExecutorError> {
+        self.replay_command(command)
+    }
+
+    async fn execute_command_with_env(
+        &mut self,
+        command: &str,
+        _env: &HashMap<String, String>,
+    ) -> Result<CommandResult, ExecutorError> {
+        // Recorded without the values, so only the command is compared
+        self.replay_command(command)
+    }
+
+    async fn execute_interactive(
+        &mut self,
+        command: &str,
+        _tty: bool,
+    ) -> Result<u32, ExecutorError> {
+        let event = self.next(&format!("command `{}`", command), |event| {
+            matches!(event,
+                SessionEvent::Interactive { command: recorded, .. }
+                | SessionEvent::Failed { command: recorded, .. } if recorded == command)
+        })?;
+        match event {
+            SessionEvent::Interactive { exit_code, .. } => Ok(exit_code),
+            SessionEvent::Failed { error, .. } => Err(ExecutorError::Other(error)),
+            _ => unreachable!("only commands are accepted"),
+        }
+    }
+
+    fn set_command_env(&mut self, env: HashMap<String, String>) {
+        self.command_env = env;
+    }
+
+    fn command_env(&self) -> &HashMap<String, String> {
+        &self.command_env
+    }
+
+    async fn close(&mut self) -> Result<(), ExecutorError> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl FileTransfer for ReplayExecutor {
+    async fn upload_file(
+        &self,
+        _local_path: &Path,
+        remote_path: &Path,
+        _progress: Option<&UploadProgress>,
+    ) -> Result<(), ExecutorError> {
+        self.replay_upload(remote_path)
+    }
+
+    async fn upload_content(
+        &self,
+        _content: &[u8],
+        remote_path: &Path,
+    ) -> Result<(), ExecutorError> {
+        self.replay_upload(remote_path)
+    }
+
+    async fn upload_files(
+        &self,
+        batch: &[(PathBuf, PathBuf)],
+        _concurrency: usize,
+    ) -> Vec<Result<(), ExecutorError>> {
+        batch
+            .iter()
+            .map(|(_, remote)| self.replay_upload(remote))
+            .collect()
+    }
+
+    async fn download_content(&self, remote_path: &Path) -> Result<Vec<u8>, ExecutorError> {
+        Err(ExecutorError::Other(format!(
+            "Replay has no recorded download of {}",
+            remote_path.display()
+        )))
+    }
+
+    async fn list_files(&self, remote_dir: &Path) -> Result<Vec<PathBuf>, ExecutorError> {
+        Err(ExecutorError::Other(format!(
+            "Replay has no recorded listing of {}",
+            remote_dir.display()
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{CommandMatch, MockExecutor};
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let trace = dir.path().join("session.jsonl");
+
+        let mut mock = MockExecutor::new();
+        mock.respond_ok("docker ps", "web\n")
+            .respond_ok(
+                CommandMatch::Contains("printenv DB_PASSWORD".into()),
+                "hunter22\n",
+            )
+            .respond_err("false", 1, "");
+        let mut recording = RecordingExecutor::new(mock, &trace, "root@host:22").unwrap();
+        let env = HashMap::from([("DB_PASSWORD".to_string(), "hunter22".to_string())]);
+        recording.execute_command("docker ps").await.unwrap();
+        recording
+            .execute_command_with_env("printenv DB_PASSWORD", &env)
+            .await
+            .unwrap();
+        recording
+            .upload_content(b"A=1\n", Path::new("/opt/app/.env.dcd"))
+            .await
+            .unwrap();
+        recording.execute_command("false").await.unwrap();
+        assert!(recording.execute_command("uname").await.is_err());
+
+        let written = std::fs::read_to_string(&trace).unwrap();
+        assert!(!written.contains("hunter22"), "{}", written);
+        assert!(!written.contains("A=1"), "{}", written);
+
+        let mut sessions = load_sessions(&trace).unwrap();
+        assert_eq!(sessions.len(), 1);
+        let mut replay = sessions.remove(0);
+        assert_eq!(replay.target(), Some("root@host:22"));
+        let ps = replay.execute_command("docker ps").await.unwrap();
+        assert_eq!(ps.output.stdout, b"web\n");
+        let printed = replay
+            .execute_command_with_env("printenv DB_PASSWORD", &HashMap::new())
+            .await
+            .unwrap();
+        assert_eq!(printed.output.stdout, b"<redacted>\n");
+        replay
+            .upload_content(b"A=2\n", Path::new("/opt/app/.env.dcd"))
+            .await
+            .unwrap();
+        assert_eq!(
+            replay
+                .execute_command("false")
+                .await
+                .unwrap()
+                .output
+                .exit_code,
+            1
+        );
+        assert!(replay.execute_command("uname").await.is_err());
+        assert_eq!(replay.remaining(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_echo_commands() {
+        let lines = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let echoed = lines.clone();
+        let mut mock = MockExecutor::new();
+        mock.respond_ok(CommandMatch::Contains("login".into()), "")
+            .respond_err("false", 1, "");
+        let mut executor = RecordingExecutor::passthrough(mock).with_echo(Box::new(move |event| {
+            let line = match event {
+                EchoEvent::Started { command } => format!("$ {}", command),
+                EchoEvent::Finished {
+                    command, exit_code, ..
+                } => format!("{} -> {:?}", command, exit_code),
+            };
+            echoed.lock().unwrap().push(line);
+        }));
+        let env = HashMap::from([("TOKEN".to_string(), "s3cr3t-token".to_string())]);
+        executor
+            .execute_command_with_env("docker login -p s3cr3t-token", &env)
+            .await
+            .unwrap();
+        executor.execute_command("false").await.unwrap();
+        assert!(executor.execute_command("uname").await.is_err());
+        assert_eq!(
+            *lines.lock().unwrap(),
+            vec![
+                "$ docker login -p <redacted>",
+                "docker login -p <redacted> -> Some(0)",
+                "$ false",
+                "false -> Some(1)",
+                "$ uname",
+                "uname -> None",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replay_divergence() {
+        let mut replay = ReplayExecutor::new(vec![SessionEvent::Command {
+            command: "docker ps".into(),
+            env: Vec::new(),
+            exit_code: 0,
+            stdout: String::new(),
+            stderr: String::new(),
+        }]);
+        let err = replay.execute_command("docker info").await.unwrap_err();
+        assert!(err.to_string().contains("step 1"), "{}", err);
+        assert!(err.to_string().contains("`docker ps`"), "{}", err);
+        // A mismatch leaves the recorded event in place
+        replay.execute_command("docker ps").await.unwrap();
+        let err = replay.execute_command("docker ps").await.unwrap_err();
+        assert!(err.to_string().contains("session ended"), "{}", err);
+    }
+
+    #[test]
+    fn test_load_sessions_per_connection() {
+        let dir = tempfile::tempdir().unwrap();
+        let trace = dir.path().join("session.jsonl");
+        for _ in 0..2 {
+            RecordingExecutor::new(MockExecutor::new(), &trace, "root@host:22").unwrap();
+        }
+        assert_eq!(load_sessions(&trace).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,52 @@
+//! Masking of secret values in text that is shown or written to disk.
+
+/// Placeholder for masked secret values.
+pub const REDACTED: &str = "<redacted>";
+
+/// Values shorter than this are not masked; they would blank out unrelated
+/// output (`1`, `true`, ...).
+const MIN_REDACTED_LEN: usize = 4;
+
+/// Secret values seen so far, masked wherever they show up.
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    secrets: Vec<String>,
+}
+
+impl Redactor {
+    /// Mask each of `values` from now on.
+    pub fn add<'a>(&mut self, values: impl IntoIterator<Item = &'a String>) {
+        for value in values {
+            if value.len() >= MIN_REDACTED_LEN && !self.secrets.contains(value) {
+                self.secrets.push(value.clone());
+            }
+        }
+        // Mask longer values first, so none leaves a recognizable tail
+        self.secrets
+            .sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+    }
+
+    pub fn redact(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for secret in &self.secrets {
+            text = text.replace(secret.as_str(), REDACTED);
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        let mut redactor = Redactor::default();
+        let values = ["abc", "token", "token-2"].map(String::from);
+        redactor.add(&values);
+        assert_eq!(
+            redactor.redact("abc token token-2"),
+            "abc <redacted> <redacted>"
+        );
+    }
+}
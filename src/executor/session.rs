@@ -0,0 +1,485 @@
+//! Opt-in reuse of one SSH connection across dcd invocations: a background
+//! dcd process keeps the session open and serves commands from later runs
+//! over a unix socket, until it has been idle for a while.
+
+use super::error::ExecutorError;
+use super::known_hosts::HostKeyPolicy;
+use super::ssh_executor::{PtyRequest, SshCommandExecutor};
+use super::traits::{CommandExecutor, FileTransfer};
+use super::types::{CommandOutput, CommandResult, OutputChunk};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::mpsc;
+
+/// How often an idle session checks its connection and idle time.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A request sent to the session process, one JSON document per line.
+#[derive(Debug, Serialize, Deserialize)]
+enum Request {
+    Exec {
+        command: String,
+        env: HashMap<String, String>,
+    },
+    /// Followed by `Input` lines until the client has no more input
+    Attach {
+        command: String,
+        pty: Option<PtyRequest>,
+    },
+    Input(Vec<u8>),
+    InputEof,
+    UploadFile {
+        local_path: PathBuf,
+        remote_path: PathBuf,
+    },
+    UploadContent {
+        content: Vec<u8>,
+        remote_path: PathBuf,
+    },
+    DownloadContent {
+        remote_path: PathBuf,
+    },
+    ListFiles {
+        remote_dir: PathBuf,
+    },
+}
+
+/// A reply of the session process, one JSON document per line.
+#[derive(Debug, Serialize, Deserialize)]
+enum Reply {
+    Output {
+        stdout: Vec<u8>,
+        stderr: Vec<u8>,
+        exit_code: u32,
+    },
+    Stdout(Vec<u8>),
+    Stderr(Vec<u8>),
+    Exit(u32),
+    Content(Vec<u8>),
+    Files(Vec<PathBuf>),
+    Done,
+    Error(String),
+}
+
+/// Socket of the shared session for these connection settings. Sessions of
+/// other dcd versions live elsewhere since the protocol may differ.
+pub fn socket_path(
+    user: &str,
+    addr: &str,
+    key_path: Option<&Path>,
+    known_hosts: Option<&Path>,
+    host_key_policy: HostKeyPolicy,
+    jump: Option<&str>,
+) -> Result<PathBuf, ExecutorError> {
+    let dir = dirs::runtime_dir()
+        .or_else(dirs::cache_dir)
+        .ok_or_else(|| ExecutorError::Other("Could not determine a runtime directory".into()))?
+        .join("dcd")
+        .join("sessions");
+    let digest = Sha256::digest(format!(
+        "{}|{}@{}|{:?}|{:?}|{:?}|{:?}",
+        env!("CARGO_PKG_VERSION"),
+        user,
+        addr,
+        key_path,
+        known_hosts,
+        host_key_policy,
+        jump
+    ));
+    let name: String = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+    Ok(dir.join(format!("{}.sock", name)))
+}
+
+/// Serve `executor` on `socket` until no request arrived for `idle` or the
+/// SSH connection is lost. Requests are handled one at a time.
+pub async fn serve(
+    mut executor: SshCommandExecutor,
+    socket: &Path,
+    idle: Duration,
+) -> Result<(), ExecutorError> {
+    if let Some(dir) = socket.parent() {
+        std::fs::create_dir_all(dir).map_err(io_error)?;
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700)).map_err(io_error)?;
+    }
+    // Left behind by a session that did not exit cleanly
+    let _ = std::fs::remove_file(socket);
+    let listener = UnixListener::bind(socket).map_err(io_error)?;
+    tracing::debug!("Serving SSH session on {}", socket.display());
+
+    let mut last_request = Instant::now();
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    tracing::warn!("Failed to accept session client: {}", e);
+                    continue;
+                }
+            },
+            _ = tokio::time::sleep(CHECK_INTERVAL) => {
+                if executor.is_closed() {
+                    tracing::warn!("SSH connection closed, ending session");
+                    break;
+                }
+                if last_request.elapsed() >= idle {
+                    break;
+                }
+                continue;
+            }
+        };
+        if let Err(e) = handle_client(&mut executor, stream).await {
+            tracing::debug!("Session client failed: {}", e);
+        }
+        last_request = Instant::now();
+    }
+
+    let _ = std::fs::remove_file(socket);
+    executor.close().await
+}
+
+async fn handle_client(
+    executor: &mut SshCommandExecutor,
+    stream: UnixStream,
+) -> Result<(), ExecutorError> {
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+    let Some(request) = read_message::<Request>(&mut lines).await? else {
+        return Ok(());
+    };
+    let reply = match request {
+        Request::Exec { command, env } => {
+            match executor.execute_command_with_env(&command, &env).await {
+                Ok(result) => Reply::Output {
+                    stdout: result.output.stdout,
+                    stderr: result.output.stderr,
+                    exit_code: result.output.exit_code,
+                },
+                Err(e) => Reply::Error(e.to_string()),
+            }
+        }
+        Request::UploadFile {
+            local_path,
+            remote_path,
+        } => done_or_error(executor.upload_file(&local_path, &remote_path, None).await),
+        Request::UploadContent {
+            content,
+            remote_path,
+        } => done_or_error(executor.upload_content(&content, &remote_path).await),
+        Request::DownloadContent { remote_path } => {
+            match executor.download_content(&remote_path).await {
+                Ok(content) => Reply::Content(content),
+                Err(e) => Reply::Error(e.to_string()),
+            }
+        }
+        Request::ListFiles { remote_dir } => match executor.list_files(&remote_dir).await {
+            Ok(files) => Reply::Files(files),
+            Err(e) => Reply::Error(e.to_string()),
+        },
+        Request::Attach { command, pty } => {
+            return attach_client(executor, &command, pty.as_ref(), lines, write).await;
+        }
+        Request::Input(_) | Request::InputEof => {
+            Reply::Error("Input sent without an attached command".into())
+        }
+    };
+    write_message(&mut write, &reply).await
+}
+
+// Stream the output of `command` to the client while forwarding its input
+async fn attach_client(
+    executor: &mut SshCommandExecutor,
+    command: &str,
+    pty: Option<&PtyRequest>,
+    mut lines: Lines<BufReader<OwnedReadHalf>>,
+    mut write: OwnedWriteHalf,
+) -> Result<(), ExecutorError> {
+    let (input_tx, input) = mpsc::channel(16);
+    let input_task = tokio::spawn(async move {
+        while let Ok(Some(Request::Input(data))) = read_message(&mut lines).await {
+            if input_tx.send(data).await.is_err() {
+                break;
+            }
+        }
+    });
+    let (output_tx, mut output_rx) = mpsc::unbounded_channel();
+    let writer_task = tokio::spawn(async move {
+        while let Some(reply) = output_rx.recv().await {
+            if write_message(&mut write, &reply).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    // Stop the command once the client is gone (e.g. Ctrl-C on `logs --follow`)
+    let mut forward = |chunk: OutputChunk| {
+        let reply = match chunk {
+            OutputChunk::Stdout(data) => Reply::Stdout(data),
+            OutputChunk::Stderr(data) => Reply::Stderr(data),
+        };
+        output_tx.send(reply).is_ok()
+    };
+    let result = executor
+        .execute_attached(command, pty, input, &mut forward)
+        .await;
+    let _ = output_tx.send(match result {
+        Ok(code) => Reply::Exit(code),
+        Err(e) => Reply::Error(e.to_string()),
+    });
+    drop(output_tx);
+    let _ = writer_task.await;
+    input_task.abort();
+    Ok(())
+}
+
+fn done_or_error(result: Result<(), ExecutorError>) -> Reply {
+    match result {
+        Ok(()) => Reply::Done,
+        Err(e) => Reply::Error(e.to_string()),
+    }
+}
+
+/// Client side of a shared session; every request uses its own connection.
+pub struct SessionClient {
+    socket: PathBuf,
+}
+
+impl SessionClient {
+    /// Check that a session is being served at `socket`.
+    pub async fn connect(socket: &Path) -> Result<Self, ExecutorError> {
+        UnixStream::connect(socket).await.map_err(|e| {
+            ExecutorError::Other(format!("No session at {}: {}", socket.display(), e))
+        })?;
+        Ok(Self {
+            socket: socket.to_path_buf(),
+        })
+    }
+
+    async fn send(
+        &self,
+        request: &Request,
+    ) -> Result<(Lines<BufReader<OwnedReadHalf>>, OwnedWriteHalf), ExecutorError> {
+        let stream = UnixStream::connect(&self.socket)
+            .await
+            .map_err(|e| ExecutorError::Other(format!("Shared session is gone: {}", e)))?;
+        let (read, mut write) = stream.into_split();
+        write_message(&mut write, request).await?;
+        Ok((BufReader::new(read).lines(), write))
+    }
+
+    async fn request(&self, request: &Request) -> Result<Reply, ExecutorError> {
+        let (mut lines, _write) = self.send(request).await?;
+        read_message(&mut lines)
+            .await?
+            .ok_or_else(|| ExecutorError::Other("Shared session closed the connection".into()))
+    }
+
+    pub async fn execute(
+        &self,
+        command: &str,
+        env: &HashMap<String, String>,
+    ) -> Result<CommandResult, ExecutorError> {
+        let request = Request::Exec {
+            command: command.to_string(),
+            env: env.clone(),
+        };
+        match self.request(&request).await? {
+            Reply::Output {
+                stdout,
+                stderr,
+                exit_code,
+            } => {
+                let mut output = CommandOutput::new();
+                output.stdout = stdout;
+                output.stderr = stderr;
+                output.exit_code = exit_code;
+                output.stop_timing();
+                Ok(CommandResult {
+                    command: command.to_string(),
+                    output,
+                })
+            }
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    pub async fn attach(
+        &self,
+        command: &str,
+        pty: Option<&PtyRequest>,
+        mut input: mpsc::Receiver<Vec<u8>>,
+        output: &mut (dyn FnMut(OutputChunk) -> bool + Send),
+    ) -> Result<u32, ExecutorError> {
+        let request = Request::Attach {
+            command: command.to_string(),
+            pty: pty.cloned(),
+        };
+        let (mut lines, mut write) = self.send(&request).await?;
+        let input_task = tokio::spawn(async move {
+            while let Some(data) = input.recv().await {
+                if write_message(&mut write, &Request::Input(data))
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+            }
+            let _ = write_message(&mut write, &Request::InputEof).await;
+        });
+
+        let result = loop {
+            let reply = match read_message(&mut lines).await {
+                Ok(Some(reply)) => reply,
+                Ok(None) => {
+                    break Err(ExecutorError::Other(
+                        "Shared session closed the connection".into(),
+                    ))
+                }
+                Err(e) => break Err(e),
+            };
+            let keep_going = match reply {
+                Reply::Stdout(data) => output(OutputChunk::Stdout(data)),
+                Reply::Stderr(data) => output(OutputChunk::Stderr(data)),
+                Reply::Exit(code) => break Ok(code),
+                reply => break Err(unexpected(reply)),
+            };
+            if !keep_going {
+                // Dropping the connection stops the command in the session
+                break Ok(0);
+            }
+        };
+        input_task.abort();
+        result
+    }
+
+    pub async fn upload_file(
+        &self,
+        local_path: &Path,
+        remote_path: &Path,
+    ) -> Result<(), ExecutorError> {
+        // The session may run in another working directory
+        let local_path = std::fs::canonicalize(local_path).map_err(io_error)?;
+        let request = Request::UploadFile {
+            local_path,
+            remote_path: remote_path.to_path_buf(),
+        };
+        match self.request(&request).await? {
+            Reply::Done => Ok(()),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    pub async fn upload_content(
+        &self,
+        content: &[u8],
+        remote_path: &Path,
+    ) -> Result<(), ExecutorError> {
+        let request = Request::UploadContent {
+            content: content.to_vec(),
+            remote_path: remote_path.to_path_buf(),
+        };
+        match self.request(&request).await? {
+            Reply::Done => Ok(()),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    pub async fn download_content(&self, remote_path: &Path) -> Result<Vec<u8>, ExecutorError> {
+        let request = Request::DownloadContent {
+            remote_path: remote_path.to_path_buf(),
+        };
+        match self.request(&request).await? {
+            Reply::Content(content) => Ok(content),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    pub async fn list_files(&self, remote_dir: &Path) -> Result<Vec<PathBuf>, ExecutorError> {
+        let request = Request::ListFiles {
+            remote_dir: remote_dir.to_path_buf(),
+        };
+        match self.request(&request).await? {
+            Reply::Files(files) => Ok(files),
+            reply => Err(unexpected(reply)),
+        }
+    }
+}
+
+// Errors of the remote side are passed through as SSH errors
+fn unexpected(reply: Reply) -> ExecutorError {
+    match reply {
+        Reply::Error(message) => ExecutorError::SshError(message),
+        reply => ExecutorError::Other(format!("Unexpected reply from shared session: {:?}", reply)),
+    }
+}
+
+async fn write_message<T: Serialize>(
+    write: &mut OwnedWriteHalf,
+    message: &T,
+) -> Result<(), ExecutorError> {
+    let mut line = serde_json::to_vec(message).map_err(|e| ExecutorError::Other(e.to_string()))?;
+    line.push(b'\n');
+    write.write_all(&line).await.map_err(io_error)
+}
+
+async fn read_message<T: for<'de> Deserialize<'de>>(
+    lines: &mut Lines<BufReader<OwnedReadHalf>>,
+) -> Result<Option<T>, ExecutorError> {
+    match lines.next_line().await.map_err(io_error)? {
+        Some(line) => serde_json::from_str(&line)
+            .map(Some)
+            .map_err(|e| ExecutorError::Other(format!("Invalid session message: {}", e))),
+        None => Ok(None),
+    }
+}
+
+fn io_error(e: std::io::Error) -> ExecutorError {
+    ExecutorError::Other(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_socket_path_depends_on_settings() {
+        let key = Path::new("/home/me/.ssh/id_ed25519");
+        let policy = HostKeyPolicy::Warn;
+        let a = socket_path("deploy", "example.com:22", Some(key), None, policy, None).unwrap();
+        let b = socket_path("deploy", "example.com:22", Some(key), None, policy, None).unwrap();
+        let c = socket_path("root", "example.com:22", Some(key), None, policy, None).unwrap();
+        let strict = socket_path(
+            "deploy",
+            "example.com:22",
+            Some(key),
+            None,
+            HostKeyPolicy::Strict,
+            None,
+        )
+        .unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_ne!(a, strict);
+        assert!(a.to_string_lossy().ends_with(".sock"));
+    }
+
+    #[test]
+    fn test_messages_are_single_lines() {
+        let request = Request::Exec {
+            command: "printf 'a\\nb'".to_string(),
+            env: HashMap::from([("KEY".to_string(), "multi\nline".to_string())]),
+        };
+        let line = serde_json::to_string(&request).unwrap();
+        assert!(!line.contains('\n'));
+        assert!(matches!(
+            serde_json::from_str::<Request>(&line).unwrap(),
+            Request::Exec { .. }
+        ));
+    }
+}
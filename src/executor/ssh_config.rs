@@ -0,0 +1,243 @@
+use std::path::{Path, PathBuf};
+
+/// Settings `~/.ssh/config` gives one host; `None` where it sets nothing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SshHostConfig {
+    /// Real host name for an alias (`HostName`, with `%h` expanded)
+    pub hostname: Option<String>,
+    pub user: Option<String>,
+    pub port: Option<u16>,
+    /// First `IdentityFile`
+    pub identity_file: Option<PathBuf>,
+    /// `ProxyJump`, unless it is `none`
+    pub proxy_jump: Option<String>,
+}
+
+/// A `Host` section; `None` patterns mark a `Match` section, which is not
+/// evaluated and never applies.
+#[derive(Debug)]
+struct Section {
+    patterns: Option<Vec<String>>,
+    options: Vec<(String, String)>,
+}
+
+impl Section {
+    /// A host matches if one of the patterns does and no negated (`!`) one does.
+    fn applies_to(&self, host: &str) -> bool {
+        let Some(patterns) = &self.patterns else {
+            return false;
+        };
+        let mut matched = false;
+        for pattern in patterns {
+            if let Some(negated) = pattern.strip_prefix('!') {
+                if wildcard_match(negated, host) {
+                    return false;
+                }
+            } else if wildcard_match(pattern, host) {
+                matched = true;
+            }
+        }
+        matched
+    }
+}
+
+/// OpenSSH client configuration, as far as dcd uses it: host aliases with
+/// their host name, user, port, identity and jump host.
+#[derive(Debug, Default)]
+pub struct SshConfig {
+    sections: Vec<Section>,
+}
+
+impl SshConfig {
+    /// `~/.ssh/config`, or an empty configuration if there is none.
+    pub fn load_default() -> Self {
+        let Some(path) = dirs::home_dir().map(|home| home.join(".ssh").join("config")) else {
+            return Self::default();
+        };
+        Self::load(&path)
+    }
+
+    /// The configuration in `path`; unreadable files count as empty.
+    pub fn load(path: &Path) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(content) => Self::parse(&content),
+            Err(e) => {
+                if path.exists() {
+                    tracing::warn!("Ignoring SSH config {}: {}", path.display(), e);
+                }
+                Self::default()
+            }
+        }
+    }
+
+    pub fn parse(content: &str) -> Self {
+        // Options before the first Host line apply to every host
+        let mut sections = vec![Section {
+            patterns: Some(vec!["*".to_string()]),
+            options: Vec::new(),
+        }];
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            // `Keyword value`, `Keyword=value` or `Keyword = value`
+            let (keyword, value) = match line.split_once(|c: char| c.is_whitespace() || c == '=') {
+                Some((keyword, value)) => (
+                    keyword.to_ascii_lowercase(),
+                    value
+                        .trim_start_matches(|c: char| c.is_whitespace() || c == '=')
+                        .trim(),
+                ),
+                None => continue,
+            };
+            match keyword.as_str() {
+                "host" => sections.push(Section {
+                    patterns: Some(value.split_whitespace().map(unquote).collect()),
+                    options: Vec::new(),
+                }),
+                "match" => sections.push(Section {
+                    patterns: None,
+                    options: Vec::new(),
+                }),
+                "include" => tracing::debug!("SSH config: Include is not supported ({})", value),
+                _ => {
+                    if let Some(section) = sections.last_mut() {
+                        section.options.push((keyword, unquote(value)));
+                    }
+                }
+            }
+        }
+        Self { sections }
+    }
+
+    /// Settings for `host` as given on the command line; like ssh, the first
+    /// value found for an option wins.
+    pub fn host(&self, host: &str) -> SshHostConfig {
+        let mut config = SshHostConfig::default();
+        let mut proxy_jump = None;
+        let options = self
+            .sections
+            .iter()
+            .filter(|section| section.applies_to(host))
+            .flat_map(|section| section.options.iter());
+        for (keyword, value) in options {
+            match keyword.as_str() {
+                "hostname" if config.hostname.is_none() => {
+                    config.hostname = Some(value.replace("%h", host));
+                }
+                "user" if config.user.is_none() => config.user = Some(value.clone()),
+                "port" if config.port.is_none() => match value.parse() {
+                    Ok(port) => config.port = Some(port),
+                    Err(_) => tracing::warn!("SSH config: invalid Port '{}' for {}", value, host),
+                },
+                "identityfile" if config.identity_file.is_none() => {
+                    config.identity_file = Some(PathBuf::from(value));
+                }
+                "proxyjump" if proxy_jump.is_none() => proxy_jump = Some(value.clone()),
+                _ => {}
+            }
+        }
+        config.proxy_jump = proxy_jump.filter(|jump| !jump.eq_ignore_ascii_case("none"));
+        config
+    }
+}
+
+fn unquote(value: &str) -> String {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+        .to_string()
+}
+
+/// `*` and `?` wildcards of ssh host patterns, ignoring case.
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    fn matches(pattern: &[u8], text: &[u8]) -> bool {
+        match pattern.split_first() {
+            None => text.is_empty(),
+            Some((b'*', rest)) => (0..=text.len()).any(|skip| matches(rest, &text[skip..])),
+            Some((b'?', rest)) => !text.is_empty() && matches(rest, &text[1..]),
+            Some((c, rest)) => {
+                text.first().is_some_and(|t| t.eq_ignore_ascii_case(c)) && matches(rest, &text[1..])
+            }
+        }
+    }
+    matches(pattern.as_bytes(), text.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+# Defaults for everything
+User fallback
+
+Host mybox web-?
+    HostName %h.internal.example.com
+    Port 2222
+    IdentityFile ~/.ssh/mybox_ed25519
+    IdentityFile ~/.ssh/other
+
+Host mybox
+    User deploy
+    Port 22
+
+Host *.prod !bastion.prod
+    ProxyJump=admin@bastion.prod:2200
+
+Match user root
+    User ignored
+
+Host direct.prod
+    ProxyJump none
+"#;
+
+    #[test]
+    fn test_host_alias() {
+        let config = SshConfig::parse(CONFIG);
+        assert_eq!(
+            config.host("mybox"),
+            SshHostConfig {
+                hostname: Some("mybox.internal.example.com".into()),
+                // The global User comes first
+                user: Some("fallback".into()),
+                port: Some(2222),
+                identity_file: Some(PathBuf::from("~/.ssh/mybox_ed25519")),
+                proxy_jump: None,
+            }
+        );
+        assert_eq!(config.host("web-1").port, Some(2222));
+        assert_eq!(config.host("web-10").port, None);
+    }
+
+    #[test]
+    fn test_proxy_jump() {
+        let config = SshConfig::parse(CONFIG);
+        assert_eq!(
+            config.host("api.prod").proxy_jump.as_deref(),
+            Some("admin@bastion.prod:2200")
+        );
+        assert_eq!(config.host("bastion.prod").proxy_jump, None);
+        // An earlier ProxyJump wins over the later `none`
+        assert_eq!(
+            config.host("direct.prod").proxy_jump.as_deref(),
+            Some("admin@bastion.prod:2200")
+        );
+        assert_eq!(
+            SshConfig::parse("Host a\n  ProxyJump none\nHost *\n  ProxyJump b\n")
+                .host("a")
+                .proxy_jump,
+            None
+        );
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*.example.com", "API.example.com"));
+        assert!(wildcard_match("web-?", "web-1"));
+        assert!(!wildcard_match("web-?", "web-12"));
+        assert!(wildcard_match("*", ""));
+    }
+}
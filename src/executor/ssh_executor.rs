@@ -1,17 +1,26 @@
 use super::error::ExecutorError;
-use super::traits::{CommandExecutor, FileTransfer};
-use super::types::{CommandOutput, CommandResult};
+use super::known_hosts::{HostKeyPolicy, KnownHosts};
+use super::local_executor::LocalCommandExecutor;
+use super::reachability::first_reachable;
+use super::session::SessionClient;
+use super::traits::{export_prefix, send_output, CommandExecutor, FileTransfer, UploadProgress};
+use super::types::{CommandOutput, CommandResult, OutputChunk};
 use anyhow::Result;
 use async_trait::async_trait;
 use colored::*;
 use dirs;
+use futures::stream::{self, StreamExt};
 use russh::keys::PublicKeyBase64;
 use russh::{client, keys, ChannelMsg, Disconnect};
 use russh_sftp::{client::SftpSession, protocol::OpenFlags};
-use std::{collections::HashMap, path::Path, sync::Arc, time::Duration};
-use tokio::fs;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::{collections::HashMap, net::SocketAddr, path::Path, sync::Arc, time::Duration};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc, Mutex};
 
 /// Prints a formatted error message for a host key mismatch to stderr.
 fn print_host_key_mismatch_error(host: &str, fingerprint: &str) {
@@ -27,6 +36,23 @@ fn print_host_key_mismatch_error(host: &str, fingerprint: &str) {
     );
 }
 
+/// Prints an error for a host key marked `@revoked` in known_hosts to stderr.
+fn print_revoked_host_key_error(host: &str, fingerprint: &str) {
+    eprintln!(
+        "{}\n{}\nHost: {}\nPresented Key Fingerprint (SHA256): {}\n{}\n{}",
+        "!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!"
+            .red()
+            .bold(),
+        "ERROR: REVOKED HOST KEY!".red().bold(),
+        host.cyan(),
+        fingerprint.yellow(),
+        "The presented key is marked @revoked in your known_hosts file. Connection rejected.".red(),
+        "!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!"
+            .red()
+            .bold()
+    );
+}
+
 /// Prints a formatted warning message for an unknown host key to stderr.
 fn print_unknown_host_key_warning(host: &str, fingerprint: &str, key_base64: &str) {
     eprintln!(
@@ -50,29 +76,52 @@ fn print_unknown_host_key_warning(host: &str, fingerprint: &str, key_base64: &st
     );
 }
 
-/// A simple client handler that *always* accepts the server key.
-/// In production, you should do proper host-key checking.
+/// Prints a formatted error message for an unknown host key refused by
+/// `--strict-host-key-checking` to stderr.
+fn print_unknown_host_key_rejected(host: &str, fingerprint: &str, key_base64: &str) {
+    eprintln!(
+        "{}\nHost: {}\nKey Fingerprint (SHA256): {}\n{}\n{}",
+        "ERROR: HOST KEY NOT IN KNOWN_HOSTS; CONNECTION REFUSED (--strict-host-key-checking)"
+            .red()
+            .bold(),
+        host.cyan(),
+        fingerprint.yellow(),
+        "Verify the fingerprint with the server's administrator before trusting it.".red(),
+        format!(
+            "Add the key to your known_hosts file ('{} {}') or connect once with --accept-new.",
+            host.cyan(),
+            key_base64.green()
+        )
+        .red(),
+    );
+}
+
+/// How [`ClientHandler`] verifies the server's host key.
+#[derive(Debug)]
+pub struct HostKeyCheck {
+    /// Names the host may be recorded under in known_hosts, in lookup order;
+    /// a key accepted with [`HostKeyPolicy::AcceptNew`] goes under the first
+    pub names: Vec<String>,
+    pub known_hosts: KnownHosts,
+    pub policy: HostKeyPolicy,
+}
+
+/// Verifies the server key against known_hosts; unknown hosts are handled
+/// according to the [`HostKeyPolicy`].
 #[derive(Debug)]
 struct ClientHandler {
     /// The hostname or IP address the client intended to connect to.
     target_host: String,
-    /// Keys loaded from the known_hosts file.
-    trusted_keys: Arc<HashMap<String, Vec<keys::PublicKey>>>,
-    /// If true, suppress printing the unknown-host-key warning.
-    suppress_unknown_host_warning: bool,
+    check: HostKeyCheck,
 }
 
 impl ClientHandler {
-    /// Create a new client handler with the given target host, trusted keys, and warning flag.
-    fn new(
-        target_host: String,
-        trusted_keys: Arc<HashMap<String, Vec<keys::PublicKey>>>,
-        suppress_unknown_host_warning: bool,
-    ) -> Self {
+    /// Create a new client handler; the host is shown by the first of its
+    /// names (the one it was addressed by).
+    fn new(check: HostKeyCheck) -> Self {
         Self {
-            target_host,
-            trusted_keys,
-            suppress_unknown_host_warning,
+            target_host: check.names.first().cloned().unwrap_or_default(),
+            check,
         }
     }
 }
@@ -87,33 +136,65 @@ impl client::Handler for ClientHandler {
         let fingerprint = server_public_key.fingerprint(Default::default());
         let fingerprint_str = fingerprint.to_string();
 
-        match self.trusted_keys.get(&self.target_host) {
-            Some(known_keys_for_host) => {
-                // Host IS in known_hosts, check if the presented key matches any known key
-                if known_keys_for_host
-                    .iter()
-                    .any(|known_key| known_key == server_public_key)
+        // A revoked key is refused whatever the policy
+        if self
+            .check
+            .known_hosts
+            .is_revoked(&self.check.names, server_public_key)
+        {
+            print_revoked_host_key_error(&self.target_host, &fingerprint_str);
+            return Ok(false);
+        }
+
+        // The first name with recorded keys decides, like OpenSSH does
+        let known_keys_for_host = self.check.known_hosts.keys_for(&self.check.names);
+        if !known_keys_for_host.is_empty() {
+            // Host IS in known_hosts, check if the presented key matches any known key
+            if known_keys_for_host.contains(&server_public_key) {
+                // Key matches a known key for this host.
+                tracing::debug!(
+                    "Host key for {} verified (fingerprint: {}).",
+                    self.target_host,
+                    fingerprint
+                );
+                return Ok(true);
+            }
+            // Key MISMATCH! This is a potential security risk (MitM attack).
+            print_host_key_mismatch_error(&self.target_host, &fingerprint_str);
+            return Ok(false); // Reject the connection due to key mismatch
+        }
+
+        match self.check.policy {
+            HostKeyPolicy::Warn => {
+                print_unknown_host_key_warning(
+                    &self.target_host,
+                    &fingerprint_str,
+                    &server_public_key.public_key_base64(),
+                );
+                Ok(true)
+            }
+            HostKeyPolicy::Accept => Ok(true),
+            HostKeyPolicy::Strict => {
+                print_unknown_host_key_rejected(
+                    &self.target_host,
+                    &fingerprint_str,
+                    &server_public_key.public_key_base64(),
+                );
+                Ok(false)
+            }
+            HostKeyPolicy::AcceptNew => {
+                match self
+                    .check
+                    .known_hosts
+                    .append(&self.target_host, server_public_key)
+                    .await
                 {
-                    // Key matches a known key for this host.
-                    tracing::debug!(
-                        "Host key for {} verified (fingerprint: {}).",
+                    Ok(()) => tracing::info!(
+                        "Permanently added {} ({}) to the list of known hosts.",
                         self.target_host,
                         fingerprint
-                    );
-                    Ok(true)
-                } else {
-                    // Key MISMATCH! This is a potential security risk (MitM attack).
-                    print_host_key_mismatch_error(&self.target_host, &fingerprint_str);
-                    Ok(false) // Reject the connection due to key mismatch
-                }
-            }
-            None => {
-                if !self.suppress_unknown_host_warning {
-                    print_unknown_host_key_warning(
-                        &self.target_host,
-                        &fingerprint_str,
-                        &server_public_key.public_key_base64(),
-                    );
+                    ),
+                    Err(e) => tracing::warn!("{}", e),
                 }
                 Ok(true)
             }
@@ -134,82 +215,223 @@ fn expand_tilde_path(key_path: &Path) -> Result<std::path::PathBuf, ExecutorErro
             Ok(home.join(stripped))
         } else {
             // Handle unsupported tilde patterns like ~user/path
-            return Err(ExecutorError::SshError(format!(
+            Err(ExecutorError::SshError(format!(
                 "Unsupported tilde pattern '{}'. Only '~' and '~/' are supported for path expansion.",
                 path_str
-            )));
+            )))
         }
     } else {
         Ok(key_path.to_path_buf())
     }
 }
 
-/// Attempts to connect with a specific SSH key
-async fn try_ssh_connection<A: tokio::net::ToSocketAddrs + Clone>(
-    key_path: &Path,
-    username: &str,
-    addr: A,
-    target_host_str: &str,
-    known_hosts_map: &Arc<HashMap<String, Vec<keys::PublicKey>>>,
-    timeout: Duration,
-    suppress_unknown_host_warning: bool,
-) -> Result<SshClient, ExecutorError> {
-    SshClient::connect(
-        key_path,
-        username,
-        addr,
-        target_host_str.to_string(),
-        Arc::clone(known_hosts_map),
-        timeout,
-        suppress_unknown_host_warning,
-    )
-    .await
+/// How OpenSSH writes `host` in known_hosts: as is on port 22, otherwise
+/// as `[host]:port`, so endpoints on other ports of the same host (e.g.
+/// containers or jails behind one IP) keep keys of their own.
+fn known_host_entry(host: &str, port: u16) -> String {
+    if port == 22 {
+        host.to_string()
+    } else {
+        format!("[{}]:{}", host, port)
+    }
+}
+
+/// Names a host may be listed under in known_hosts, in lookup order: the
+/// hostname, then the IP, both in the form for the port. The first one is
+/// the name new keys are recorded under.
+fn known_host_names(host: &str, addr: SocketAddr) -> Vec<String> {
+    let mut names = vec![known_host_entry(host, addr.port())];
+    let ip = known_host_entry(&addr.ip().to_string(), addr.port());
+    if !names.contains(&ip) {
+        names.push(ip);
+    }
+    names
+}
+
+/// A way of authenticating to the SSH server; see [`SshAuth`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthMethod {
+    /// Private key file (`--identity`, otherwise ~/.ssh/id_rsa and ~/.ssh/id_ed25519)
+    Key,
+    /// Keys held by the ssh-agent at `SSH_AUTH_SOCK`
+    Agent,
+    /// Password (also answers keyboard-interactive prompts)
+    Password,
+}
+
+impl AuthMethod {
+    pub const ALL: [AuthMethod; 3] = [AuthMethod::Key, AuthMethod::Agent, AuthMethod::Password];
+    /// Order used unless configured otherwise; passwords are opt-in.
+    pub const DEFAULT_ORDER: [AuthMethod; 2] = [AuthMethod::Key, AuthMethod::Agent];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthMethod::Key => "key",
+            AuthMethod::Agent => "agent",
+            AuthMethod::Password => "password",
+        }
+    }
+}
+
+impl fmt::Display for AuthMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for AuthMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|method| method.as_str() == s.trim())
+            .ok_or_else(|| {
+                format!(
+                    "unknown auth method '{}' (expected one of: {})",
+                    s,
+                    Self::ALL.map(|method| method.as_str()).join(", ")
+                )
+            })
+    }
+}
+
+/// How [`SshCommandExecutor::connect`] authenticates.
+#[derive(Debug, Clone)]
+pub struct SshAuth {
+    /// Methods to try, in order, until one succeeds
+    pub methods: Vec<AuthMethod>,
+    /// Private key for [`AuthMethod::Key`]; the default keys are tried when unset
+    pub key_path: Option<PathBuf>,
+    /// Asked for the password of `user@host` when [`AuthMethod::Password`]
+    /// is reached; `None` skips password authentication
+    pub password: Option<fn(&str) -> Option<String>>,
+}
+
+impl Default for SshAuth {
+    fn default() -> Self {
+        Self {
+            methods: AuthMethod::DEFAULT_ORDER.to_vec(),
+            key_path: None,
+            password: None,
+        }
+    }
+}
+
+/// Channels opened at once on one connection unless configured otherwise:
+/// up to four SFTP channels for batch uploads plus one for commands. sshd
+/// allows 10 sessions per connection by default (`MaxSessions`).
+pub const DEFAULT_SSH_CHANNELS: usize = 5;
+
+/// Shell prefix that runs the `export` statements sent on stdin, so that
+/// variable values stay off the remote command line.
+const ENV_FROM_STDIN: &str = "eval \"$(cat)\"; ";
+
+/// Bytes read from a local file and written over SFTP at a time.
+const UPLOAD_CHUNK_SIZE: usize = 256 * 1024;
+
+/// Bastion host that connections to the target are tunneled through (`--jump`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JumpHost {
+    pub user: String,
+    /// `host:port` of the bastion
+    pub addr: String,
 }
 
 /// The underlying SSH client that manages the russh connection and optional SFTP session.
 pub struct SshClient {
     session: client::Handle<ClientHandler>,
-    sftp: Arc<Mutex<Option<SftpSession>>>,
+    sftp: Mutex<Option<Arc<SftpSession>>>,
+    /// SFTP channels besides `sftp`, opened on demand by batch uploads
+    sftp_pool: Mutex<Vec<Arc<SftpSession>>>,
+    /// Channels open at once at most; one is kept free for commands
+    max_channels: usize,
+    /// Bastion the connection is tunneled through; closing it would end the tunnel
+    jump: Option<Box<SshClient>>,
+}
+
+fn client_config(timeout: Duration) -> Arc<client::Config> {
+    Arc::new(client::Config {
+        inactivity_timeout: Some(timeout),
+        ..Default::default()
+    })
 }
 
 impl SshClient {
-    /// Establish an SSH connection using the given private key, username, and address.
+    /// Establish an SSH connection to `addr` and authenticate as `username`
+    /// with the first method of `auth` that succeeds, once `host_key_check`
+    /// accepted the server's key.
     pub async fn connect<A: tokio::net::ToSocketAddrs>(
-        key_path: impl AsRef<Path>,
-        username: &str,
         addr: A,
-        target_host_str: String,
-        known_hosts_map: Arc<HashMap<String, Vec<keys::PublicKey>>>,
+        username: &str,
+        auth: &SshAuth,
+        host_key_check: HostKeyCheck,
         timeout: Duration,
-        suppress_unknown_host_warning: bool,
     ) -> Result<Self, ExecutorError> {
-        let key_pair = keys::load_secret_key(key_path.as_ref(), None)
-            .map_err(|e| ExecutorError::SshError(format!(
-                "Failed to load SSH private key from '{}': {}. Please check file permissions and key format.",
-                key_path.as_ref().display(),
-                e
-            )))?;
+        let target_host_str = host_key_check.names.first().cloned().unwrap_or_default();
+        let handler = ClientHandler::new(host_key_check);
 
-        let config = client::Config {
-            inactivity_timeout: Some(timeout),
-            ..Default::default()
-        };
-        let config = Arc::new(config);
-        // Create the handler with the resolved host and loaded keys provided by caller
-        let handler = ClientHandler::new(
-            target_host_str.clone(),
-            Arc::clone(&known_hosts_map),
-            suppress_unknown_host_warning,
-        );
-
-        let mut session = client::connect(config, addr, handler)
+        let session = client::connect(client_config(timeout), addr, handler)
             .await
             .map_err(|e| ExecutorError::SshError(format!(
                 "Failed to establish SSH connection to '{}': {}. Please check network connectivity and host availability.",
                 target_host_str,
                 e
             )))?;
+        Self::authenticate(session, username, auth, &target_host_str, None).await
+    }
 
+    /// Like [`Self::connect`], reaching `host:port` through a direct-tcpip
+    /// channel of `jump`; the bastion resolves `host`.
+    pub async fn connect_via(
+        jump: SshClient,
+        host: &str,
+        port: u16,
+        username: &str,
+        auth: &SshAuth,
+        host_key_check: HostKeyCheck,
+        timeout: Duration,
+    ) -> Result<Self, ExecutorError> {
+        let target_host_str = host_key_check.names.first().cloned().unwrap_or_default();
+        let channel = jump
+            .session
+            .channel_open_direct_tcpip(host, port as u32, "127.0.0.1", 0)
+            .await
+            .map_err(|e| {
+                ExecutorError::SshError(format!(
+                    "The jump host could not open a connection to {}:{}: {}",
+                    host, port, e
+                ))
+            })?;
+        let handler = ClientHandler::new(host_key_check);
+        let session =
+            client::connect_stream(client_config(timeout), channel.into_stream(), handler)
+                .await
+                .map_err(|e| {
+                    ExecutorError::SshError(format!(
+                        "Failed to establish SSH connection to '{}' through the jump host: {}",
+                        target_host_str, e
+                    ))
+                })?;
+        Self::authenticate(
+            session,
+            username,
+            auth,
+            &target_host_str,
+            Some(Box::new(jump)),
+        )
+        .await
+    }
+
+    /// Authenticate `session` as `username` with the first method of `auth`
+    /// that succeeds.
+    async fn authenticate(
+        mut session: client::Handle<ClientHandler>,
+        username: &str,
+        auth: &SshAuth,
+        target_host_str: &str,
+        jump: Option<Box<SshClient>>,
+    ) -> Result<Self, ExecutorError> {
         // Get the best supported RSA hash algorithm, falling back to SHA1 if server doesn't support negotiation
         let best_hash = session
             .best_supported_rsa_hash()
@@ -219,40 +441,96 @@ impl SshClient {
 
         tracing::debug!("Using RSA hash algorithm: {:?}", best_hash);
 
-        // Authenticate using the private key
-        let auth_result = session
-            .authenticate_publickey(
-                username,
-                keys::key::PrivateKeyWithHashAlg::new(
-                    Arc::new(key_pair),
-                    best_hash, // Use the negotiated hash (or None for non-RSA or SHA1 fallback)
-                ),
-            )
-            .await
-            .map_err(|e| ExecutorError::SshError(e.to_string()))?;
-
-        if !auth_result.success() {
-            return Err(ExecutorError::SshError(format!(
-                "SSH authentication failed using key '{}'. Please check the key file and ensure it's authorized on the remote host.",
-                key_path.as_ref().display()
-            )));
+        let mut failures = Vec::new();
+        for method in &auth.methods {
+            let result = match method {
+                AuthMethod::Key => {
+                    authenticate_with_keys(
+                        &mut session,
+                        username,
+                        auth.key_path.as_deref(),
+                        best_hash,
+                    )
+                    .await
+                }
+                AuthMethod::Agent => {
+                    authenticate_with_agent(&mut session, username, best_hash).await
+                }
+                AuthMethod::Password => {
+                    let login = format!("{}@{}", username, target_host_str);
+                    authenticate_with_password(&mut session, username, &login, auth.password).await
+                }
+            };
+            match result {
+                Ok(()) => {
+                    tracing::debug!("Authenticated to {} via {}", target_host_str, method);
+                    return Ok(Self {
+                        session,
+                        sftp: Mutex::new(None),
+                        sftp_pool: Mutex::new(Vec::new()),
+                        max_channels: DEFAULT_SSH_CHANNELS,
+                        jump,
+                    });
+                }
+                Err(e) => {
+                    tracing::debug!("Authentication via {} failed: {}", method, e);
+                    failures.push(format!("{}: {}", method, e));
+                }
+            }
         }
 
-        Ok(Self {
-            session,
-            sftp: Arc::new(Mutex::new(None)),
-        })
+        Err(ExecutorError::SshError(format!(
+            "SSH authentication as '{}' failed. Tried {}. Use --identity, load a key into ssh-agent or add 'password' to --auth.",
+            username,
+            if failures.is_empty() {
+                "no methods".to_string()
+            } else {
+                failures.join("; ")
+            }
+        )))
     }
 
     /// If not already present, create an SFTP session and store it for reuse.
-    async fn get_sftp_session(&self) -> Result<Arc<Mutex<Option<SftpSession>>>, ExecutorError> {
-        {
-            let sftp_guard = self.sftp.lock().await;
-            if sftp_guard.is_some() {
-                return Ok(self.sftp.clone());
+    /// The session is shared, so several uploads can be in flight at once.
+    async fn get_sftp_session(&self) -> Result<Arc<SftpSession>, ExecutorError> {
+        // Held while opening, so concurrent callers don't open a session each
+        let mut guard = self.sftp.lock().await;
+        if let Some(sftp) = guard.as_ref() {
+            return Ok(Arc::clone(sftp));
+        }
+
+        let sftp = Arc::new(self.open_sftp_session().await?);
+        *guard = Some(Arc::clone(&sftp));
+
+        Ok(sftp)
+    }
+
+    /// Up to `count` SFTP sessions on separate channels, opening more as
+    /// needed. Stops short if the server refuses further channels.
+    async fn get_sftp_sessions(
+        &self,
+        count: usize,
+    ) -> Result<Vec<Arc<SftpSession>>, ExecutorError> {
+        let mut sessions = vec![self.get_sftp_session().await?];
+        let mut pool = self.sftp_pool.lock().await;
+        while pool.len() + 1 < count {
+            match self.open_sftp_session().await {
+                Ok(sftp) => pool.push(Arc::new(sftp)),
+                Err(e) => {
+                    tracing::debug!(
+                        "Uploading over {} SFTP channel(s); opening another failed: {}",
+                        pool.len() + 1,
+                        e
+                    );
+                    break;
+                }
             }
         }
+        sessions.extend(pool.iter().take(count.saturating_sub(1)).cloned());
+        Ok(sessions)
+    }
 
+    async fn open_sftp_session(&self) -> Result<SftpSession, ExecutorError> {
         let channel = self
             .session
             .channel_open_session()
@@ -264,78 +542,220 @@ impl SshClient {
             .await
             .map_err(|e| ExecutorError::SshError(e.to_string()))?;
 
-        let sftp = SftpSession::new(channel.into_stream())
+        SftpSession::new(channel.into_stream())
             .await
-            .map_err(|e| ExecutorError::SshError(e.to_string()))?;
-
-        let mut guard = self.sftp.lock().await;
-        *guard = Some(sftp);
+            .map_err(|e| ExecutorError::SshError(e.to_string()))
+    }
 
-        Ok(self.sftp.clone())
+    /// Upload `batch` of `(local, remote)` paths spread over several SFTP
+    /// channels, with at most `concurrency` files in flight.
+    async fn upload_files_internal(
+        &self,
+        batch: &[(PathBuf, PathBuf)],
+        concurrency: usize,
+    ) -> Vec<Result<(), ExecutorError>> {
+        let concurrency = concurrency.max(1);
+        let sftp_channels = self.max_channels.saturating_sub(1).max(1);
+        let channels = concurrency.min(sftp_channels).min(batch.len());
+        let sessions = match self.get_sftp_sessions(channels).await {
+            Ok(sessions) => sessions,
+            Err(e) => return batch.iter().map(|_| Err(e.clone())).collect(),
+        };
+        // Collected first: a stream over a borrowing closure trips up the Send check
+        let uploads: Vec<_> = batch
+            .iter()
+            .zip(sessions.iter().cycle())
+            .map(|((local_path, remote_path), sftp)| async move {
+                let content = tokio::fs::read(local_path)
+                    .await
+                    .map_err(|e| ExecutorError::SshError(e.to_string()))?;
+                write_remote_file(sftp, &content, remote_path).await
+            })
+            .collect();
+        stream::iter(uploads).buffered(concurrency).collect().await
     }
 
-    /// Internal helper for uploading a file via SFTP.
+    /// Internal helper for uploading a file via SFTP, streamed in chunks of
+    /// [`UPLOAD_CHUNK_SIZE`] so large files are never held in memory whole.
     async fn upload_file_internal(
         &self,
         local_path: &Path,
         remote_path: &Path,
+        progress: Option<&UploadProgress>,
     ) -> Result<(), ExecutorError> {
-        let sftp_session = self.get_sftp_session().await?;
-        let mut sftp_guard = sftp_session.lock().await;
-        let sftp = sftp_guard
-            .as_mut()
-            .ok_or_else(|| ExecutorError::SshError("SFTP session not available".to_string()))?;
-
-        let mut local_file = tokio::fs::File::open(local_path)
-            .await
-            .map_err(|e| ExecutorError::SshError(e.to_string()))?;
-
-        let remote_str = remote_path
-            .to_str()
-            .ok_or_else(|| ExecutorError::SshError("Invalid UTF-8 in remote path".to_string()))?;
+        let io_error = |e: std::io::Error| ExecutorError::SshError(e.to_string());
+        let mut local_file = tokio::fs::File::open(local_path).await.map_err(io_error)?;
+        let total = local_file.metadata().await.map_err(io_error)?.len();
 
+        let sftp = self.get_sftp_session().await?;
         let mut remote_file = sftp
             .open_with_flags(
-                remote_str,
+                remote_str(remote_path)?,
                 OpenFlags::CREATE | OpenFlags::WRITE | OpenFlags::TRUNCATE,
             )
             .await
             .map_err(|e| ExecutorError::SshError(e.to_string()))?;
 
-        let mut buffer = Vec::new();
-        local_file
-            .read_to_end(&mut buffer)
-            .await
-            .map_err(|e| ExecutorError::SshError(e.to_string()))?;
+        let mut buffer = vec![0; UPLOAD_CHUNK_SIZE];
+        let mut sent = 0;
+        if let Some(progress) = progress {
+            progress(sent, total);
+        }
+        loop {
+            let read = local_file.read(&mut buffer).await.map_err(io_error)?;
+            if read == 0 {
+                break;
+            }
+            remote_file
+                .write_all(&buffer[..read])
+                .await
+                .map_err(io_error)?;
+            sent += read as u64;
+            if let Some(progress) = progress {
+                progress(sent, total.max(sent));
+            }
+        }
+        remote_file.flush().await.map_err(io_error)
+    }
 
-        remote_file
-            .write_all(&buffer)
-            .await
-            .map_err(|e| ExecutorError::SshError(e.to_string()))?;
+    /// Internal helper for writing in-memory content to a remote file via SFTP.
+    async fn upload_content_internal(
+        &self,
+        content: &[u8],
+        remote_path: &Path,
+    ) -> Result<(), ExecutorError> {
+        let sftp = self.get_sftp_session().await?;
+        write_remote_file(&sftp, content, remote_path).await
+    }
 
-        remote_file
-            .flush()
+    /// Read a remote file via SFTP.
+    async fn download_content_internal(
+        &self,
+        remote_path: &Path,
+    ) -> Result<Vec<u8>, ExecutorError> {
+        let sftp = self.get_sftp_session().await?;
+        sftp.read(remote_str(remote_path)?)
             .await
-            .map_err(|e| ExecutorError::SshError(e.to_string()))?;
+            .map_err(|e| ExecutorError::SshError(format!("{}: {}", remote_path.display(), e)))
+    }
 
-        Ok(())
+    /// Walk `remote_dir` via SFTP, collecting regular files relative to it.
+    async fn list_files_internal(&self, remote_dir: &Path) -> Result<Vec<PathBuf>, ExecutorError> {
+        let sftp = self.get_sftp_session().await?;
+        let sftp_error = |path: &Path, e: russh_sftp::client::error::Error| {
+            ExecutorError::SshError(format!("{}: {}", path.display(), e))
+        };
+        let mut files = Vec::new();
+        let mut pending = vec![PathBuf::new()];
+        while let Some(relative) = pending.pop() {
+            let dir = remote_dir.join(&relative);
+            let entries = sftp
+                .read_dir(remote_str(&dir)?)
+                .await
+                .map_err(|e| sftp_error(&dir, e))?;
+            for entry in entries {
+                let path = relative.join(entry.file_name());
+                let file_type = entry.file_type();
+                if file_type.is_dir() {
+                    pending.push(path);
+                } else if file_type.is_file() {
+                    files.push(path);
+                } else if file_type.is_symlink() {
+                    // Linked directories are left out, which also avoids loops
+                    let target = remote_dir.join(&path);
+                    match sftp.metadata(remote_str(&target)?).await {
+                        Ok(metadata) if metadata.file_type().is_file() => files.push(path),
+                        _ => tracing::debug!("Skipping link {}", target.display()),
+                    }
+                }
+            }
+        }
+        files.sort();
+        Ok(files)
     }
 
-    /// Internal helper for executing a command over SSH.
+    /// Internal helper for executing a command over SSH with `env` set. `shown` is what
+    /// gets logged and recorded in the result. Output is also sent to `stream` as it
+    /// arrives, if given.
+    ///
+    /// Variables are sent as `env` requests first. Servers refuse names missing
+    /// from their `AcceptEnv`; those are written to the command's stdin and
+    /// exported from there instead, never put on the command line where anyone
+    /// on the server could read their values (`ps`, `/proc/*/cmdline`).
     async fn execute_command_internal(
         &mut self,
         command: &str,
+        shown: &str,
+        env: &HashMap<String, String>,
+        stream: Option<&mpsc::Sender<OutputChunk>>,
     ) -> Result<CommandResult, ExecutorError> {
+        // Reject bad names before any of the values leave this machine
+        export_prefix(env)?;
         let mut channel = self
             .session
             .channel_open_session()
             .await
             .map_err(|e| ExecutorError::SshError(e.to_string()))?;
 
+        let mut names: Vec<&String> = env.keys().collect();
+        names.sort();
+        for name in &names {
+            channel
+                .set_env(true, name.as_str(), env[*name].as_str())
+                .await
+                .map_err(|e| ExecutorError::SshError(e.to_string()))?;
+        }
+        // Replies come back in the order the requests were sent
+        let mut refused = HashMap::new();
+        let mut answered = 0;
+        while answered < names.len() {
+            match channel.wait().await {
+                Some(ChannelMsg::Success) => answered += 1,
+                Some(ChannelMsg::Failure) => {
+                    let name = names[answered];
+                    refused.insert(name.clone(), env[name].clone());
+                    answered += 1;
+                }
+                Some(_) => {}
+                None => {
+                    return Err(ExecutorError::SshError(
+                        "Channel closed while setting the environment".to_string(),
+                    ))
+                }
+            }
+        }
+        if !refused.is_empty() {
+            let mut refused_names: Vec<&String> = refused.keys().collect();
+            refused_names.sort();
+            tracing::debug!(
+                "Server refused env requests for {:?}; sending them on stdin",
+                refused_names
+            );
+        }
+
+        let exports = if refused.is_empty() {
+            None
+        } else {
+            Some(export_prefix(&refused)?)
+        };
+        let command = match exports {
+            Some(_) => format!("{}{}", ENV_FROM_STDIN, command),
+            None => command.to_string(),
+        };
         channel
-            .exec(true, command)
+            .exec(true, command.as_str())
             .await
             .map_err(|e| ExecutorError::SshError(e.to_string()))?;
+        if let Some(exports) = exports {
+            channel
+                .data(exports.as_bytes())
+                .await
+                .map_err(|e| ExecutorError::SshError(e.to_string()))?;
+            channel
+                .eof()
+                .await
+                .map_err(|e| ExecutorError::SshError(e.to_string()))?;
+        }
 
         let mut output = CommandOutput::new();
 
@@ -343,9 +763,15 @@ impl SshClient {
             match msg {
                 ChannelMsg::Data { data } => {
                     output.stdout.extend_from_slice(&data);
+                    if let Some(stream) = stream {
+                        let _ = stream.send(OutputChunk::Stdout(data.to_vec())).await;
+                    }
                 }
                 ChannelMsg::ExtendedData { data, .. } => {
                     output.stderr.extend_from_slice(&data);
+                    if let Some(stream) = stream {
+                        let _ = stream.send(OutputChunk::Stderr(data.to_vec())).await;
+                    }
                 }
                 ChannelMsg::ExitStatus { exit_status } => {
                     output.exit_code = exit_status;
@@ -357,227 +783,644 @@ impl SshClient {
 
         tracing::debug!(
             "SSH Command '{}' completed with exit code {}",
-            command,
+            shown,
             output.exit_code
         );
 
         Ok(CommandResult {
-            command: command.to_string(),
+            command: shown.to_string(),
             output,
         })
     }
 
+    /// Internal helper for running a command with streamed output. Bytes
+    /// received on `input` are forwarded to the remote process (closing it
+    /// sends EOF); returning `false` from `output` stops the command.
+    async fn execute_attached_internal(
+        &mut self,
+        command: &str,
+        pty: Option<&PtyRequest>,
+        mut input: mpsc::Receiver<Vec<u8>>,
+        output: &mut (dyn FnMut(OutputChunk) -> bool + Send),
+    ) -> Result<u32, ExecutorError> {
+        let channel = self
+            .session
+            .channel_open_session()
+            .await
+            .map_err(|e| ExecutorError::SshError(e.to_string()))?;
+
+        if let Some(pty) = pty {
+            channel
+                .request_pty(false, &pty.term, pty.cols, pty.rows, 0, 0, &[])
+                .await
+                .map_err(|e| ExecutorError::SshError(e.to_string()))?;
+        }
+        channel
+            .exec(true, command)
+            .await
+            .map_err(|e| ExecutorError::SshError(e.to_string()))?;
+
+        let (mut reader, writer) = channel.split();
+        let mut input_open = true;
+        let mut exit_code = 0;
+        loop {
+            tokio::select! {
+                msg = reader.wait() => {
+                    let keep_going = match msg {
+                        None => break,
+                        Some(ChannelMsg::Data { data }) => output(OutputChunk::Stdout(data.to_vec())),
+                        Some(ChannelMsg::ExtendedData { data, .. }) => {
+                            output(OutputChunk::Stderr(data.to_vec()))
+                        }
+                        Some(ChannelMsg::ExitStatus { exit_status }) => {
+                            exit_code = exit_status;
+                            true
+                        }
+                        Some(_) => true,
+                    };
+                    if !keep_going {
+                        let _ = writer.close().await;
+                        break;
+                    }
+                }
+                data = input.recv(), if input_open => match data {
+                    Some(data) => {
+                        if writer.data(&data[..]).await.is_err() {
+                            input_open = false;
+                        }
+                    }
+                    None => {
+                        input_open = false;
+                        let _ = writer.eof().await;
+                    }
+                },
+            }
+        }
+
+        tracing::debug!(
+            "Attached SSH command '{}' completed with exit code {}",
+            command,
+            exit_code
+        );
+        Ok(exit_code)
+    }
+
     /// Internal helper for disconnecting cleanly from the SSH session.
     async fn close_internal(&mut self) -> Result<(), ExecutorError> {
-        self.session
+        let closed = self
+            .session
             .disconnect(Disconnect::ByApplication, "", "English")
             .await
-            .map_err(|e| ExecutorError::SshError(e.to_string()))
+            .map_err(|e| ExecutorError::SshError(e.to_string()));
+        // The tunnel is no longer needed
+        if let Some(jump) = &mut self.jump {
+            Box::pin(jump.close_internal()).await?;
+        }
+        closed
     }
 }
 
-/// Parses a single line from a known_hosts file.
-fn parse_known_host_line(line: &str) -> Option<(Vec<String>, keys::PublicKey)> {
-    let parts: Vec<&str> = line.split_whitespace().collect();
-    if parts.len() < 3 {
-        return None; // Not enough parts
-    }
+/// `remote_path` as the UTF-8 string SFTP expects.
+fn remote_str(remote_path: &Path) -> Result<&str, ExecutorError> {
+    remote_path
+        .to_str()
+        .ok_or_else(|| ExecutorError::SshError("Invalid UTF-8 in remote path".to_string()))
+}
 
-    let hosts_part = parts[0];
-    let key_data = parts[2];
+/// Create or truncate `remote_path` through `sftp` and write `content` to it.
+async fn write_remote_file(
+    sftp: &SftpSession,
+    content: &[u8],
+    remote_path: &Path,
+) -> Result<(), ExecutorError> {
+    let remote_str = remote_str(remote_path)?;
+
+    let mut remote_file = sftp
+        .open_with_flags(
+            remote_str,
+            OpenFlags::CREATE | OpenFlags::WRITE | OpenFlags::TRUNCATE,
+        )
+        .await
+        .map_err(|e| ExecutorError::SshError(e.to_string()))?;
 
-    let hosts = hosts_part.split(',').map(String::from).collect();
+    remote_file
+        .write_all(content)
+        .await
+        .map_err(|e| ExecutorError::SshError(e.to_string()))?;
 
-    match keys::parse_public_key_base64(key_data) {
-        Ok(key) => Some((hosts, key)),
-        Err(_) => {
-            tracing::warn!(
-                "Failed to parse public key from known_hosts line '{}'",
-                line
-            );
-            None
-        }
-    }
-}
+    remote_file
+        .flush()
+        .await
+        .map_err(|e| ExecutorError::SshError(e.to_string()))?;
 
-/// Loads known hosts from a specified file path.
-/// Returns a map where keys are hostnames/IPs and values are lists of valid public keys.
-async fn load_known_hosts(
-    path: &Path,
-) -> Result<HashMap<String, Vec<keys::PublicKey>>, ExecutorError> {
-    let mut trusted_keys: HashMap<String, Vec<keys::PublicKey>> = HashMap::new();
+    Ok(())
+}
 
-    if !path.exists() {
-        tracing::warn!(
-            "Known hosts file not found at '{}'. No host keys will be pre-trusted.",
-            path.display()
-        );
-        return Ok(trusted_keys); // Return empty map if file doesn't exist
+/// Try the given key, or the default keys that exist, one after another.
+async fn authenticate_with_keys(
+    session: &mut client::Handle<ClientHandler>,
+    username: &str,
+    key_path: Option<&Path>,
+    hash: Option<keys::HashAlg>,
+) -> Result<(), String> {
+    let candidates = match key_path {
+        Some(path) => vec![path.to_path_buf()],
+        None => default_key_paths()
+            .into_iter()
+            .filter(|path| path.exists())
+            .collect(),
+    };
+    if candidates.is_empty() {
+        return Err("no SSH keys found (tried ~/.ssh/id_rsa and ~/.ssh/id_ed25519)".to_string());
     }
 
-    let content = fs::read_to_string(path).await.map_err(|e| {
-        ExecutorError::SshError(format!(
-            "Failed to read known_hosts file '{}': {}",
-            path.display(),
-            e
-        ))
-    })?;
+    let mut errors = Vec::new();
+    for path in candidates {
+        tracing::debug!("Trying SSH key: {}", path.display());
+        let key_pair = match keys::load_secret_key(&path, None) {
+            Ok(key_pair) => key_pair,
+            Err(e) => {
+                errors.push(format!("{}: {}", path.display(), e));
+                continue;
+            }
+        };
+        let key = keys::key::PrivateKeyWithHashAlg::new(Arc::new(key_pair), hash);
+        match session.authenticate_publickey(username, key).await {
+            Ok(result) if result.success() => {
+                tracing::info!("Successfully connected using SSH key: {}", path.display());
+                return Ok(());
+            }
+            Ok(_) => errors.push(format!("{}: not accepted by the server", path.display())),
+            Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+        }
+    }
+    Err(errors.join(", "))
+}
 
-    for line in content.lines() {
-        let trimmed = line.trim();
-        if trimmed.is_empty() || trimmed.starts_with('#') {
-            continue; // Skip empty lines and comments
+/// Try every key the ssh-agent at `SSH_AUTH_SOCK` offers.
+async fn authenticate_with_agent(
+    session: &mut client::Handle<ClientHandler>,
+    username: &str,
+    hash: Option<keys::HashAlg>,
+) -> Result<(), String> {
+    let mut agent = keys::agent::client::AgentClient::connect_env()
+        .await
+        .map_err(|e| format!("no ssh-agent available ({})", e))?;
+    let identities = agent
+        .request_identities()
+        .await
+        .map_err(|e| format!("failed to list agent keys: {}", e))?;
+    if identities.is_empty() {
+        return Err("the agent holds no keys".to_string());
+    }
+    for key in identities {
+        let comment = key.comment().to_string();
+        match session
+            .authenticate_publickey_with(username, key, hash, &mut agent)
+            .await
+        {
+            Ok(result) if result.success() => {
+                tracing::info!("Successfully connected using agent key {}", comment);
+                return Ok(());
+            }
+            Ok(_) => tracing::debug!("Agent key {} not accepted", comment),
+            Err(e) => return Err(e.to_string()),
         }
+    }
+    Err("no agent key was accepted by the server".to_string())
+}
 
-        if let Some((hosts, key)) = parse_known_host_line(trimmed) {
-            for host in hosts {
-                trusted_keys.entry(host).or_default().push(key.clone());
+/// Ask for a password and send it as password authentication, then as the
+/// answer to keyboard-interactive password prompts (servers often only enable
+/// one). Any other prompt, such as a one-time code, fails the method.
+async fn authenticate_with_password(
+    session: &mut client::Handle<ClientHandler>,
+    username: &str,
+    login: &str,
+    ask: Option<fn(&str) -> Option<String>>,
+) -> Result<(), String> {
+    let password = ask
+        .and_then(|ask| ask(login))
+        .ok_or_else(|| "no password available".to_string())?;
+    let result = session
+        .authenticate_password(username, password.clone())
+        .await
+        .map_err(|e| e.to_string())?;
+    if result.success() {
+        return Ok(());
+    }
+
+    let mut response = session
+        .authenticate_keyboard_interactive_start(username, None)
+        .await
+        .map_err(|e| e.to_string())?;
+    // Servers may send a few (possibly empty) rounds of prompts
+    for _ in 0..3 {
+        match response {
+            client::KeyboardInteractiveAuthResponse::Success => return Ok(()),
+            client::KeyboardInteractiveAuthResponse::Failure { .. } => break,
+            client::KeyboardInteractiveAuthResponse::InfoRequest { prompts, .. } => {
+                if let Some(prompt) = prompts.iter().find(|p| !is_password_prompt(p)) {
+                    return Err(format!(
+                        "the server asked '{}', which dcd cannot answer; use a key or ssh-agent",
+                        prompt.prompt.trim()
+                    ));
+                }
+                let answers = prompts.iter().map(|_| password.clone()).collect();
+                response = session
+                    .authenticate_keyboard_interactive_respond(answers)
+                    .await
+                    .map_err(|e| e.to_string())?;
             }
         }
     }
+    Err("password not accepted by the server".to_string())
+}
 
-    Ok(trusted_keys)
+/// Whether a keyboard-interactive prompt asks for the account password: hidden
+/// input mentioning it, unlike verification codes or questions.
+fn is_password_prompt(prompt: &client::Prompt) -> bool {
+    !prompt.echo && prompt.prompt.to_lowercase().contains("password")
+}
+
+fn default_key_paths() -> Vec<PathBuf> {
+    dirs::home_dir()
+        .map(|home| {
+            let ssh_dir = home.join(".ssh");
+            vec![ssh_dir.join("id_rsa"), ssh_dir.join("id_ed25519")]
+        })
+        .unwrap_or_default()
+}
+
+/// Pseudo-terminal requested for an attached command.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PtyRequest {
+    pub term: String,
+    pub cols: u32,
+    pub rows: u32,
+}
+
+impl PtyRequest {
+    /// Type and size of the local terminal.
+    pub fn local() -> Self {
+        let (cols, rows) = crossterm::terminal::size().unwrap_or((80, 24));
+        Self {
+            term: std::env::var("TERM").unwrap_or_else(|_| "xterm".to_string()),
+            cols: cols as u32,
+            rows: rows as u32,
+        }
+    }
 }
 
 /// A high-level wrapper that implements the `CommandExecutor` and `FileTransfer` traits.
 pub struct SshCommandExecutor {
-    client: SshClient,
+    transport: Transport,
+    /// Variables exported for every command
+    command_env: HashMap<String, String>,
+}
+
+/// How commands reach the host: over our own connection, through a
+/// session kept open by another dcd process (`--reuse-connection`), or
+/// straight on this machine (the `local` target).
+enum Transport {
+    Direct(SshClient),
+    Session(SessionClient),
+    Local(LocalCommandExecutor),
 }
 
 impl SshCommandExecutor {
-    /// Create a new SSH-based executor by connecting to the remote host.
+    /// Use the shared session served at `socket` instead of connecting.
+    pub async fn attach(socket: &Path) -> Result<Self, ExecutorError> {
+        Ok(Self {
+            transport: Transport::Session(SessionClient::connect(socket).await?),
+            command_env: HashMap::new(),
+        })
+    }
+
+    /// Run commands and copy files on this machine instead of a remote host.
+    pub fn local() -> Self {
+        Self {
+            transport: Transport::Local(LocalCommandExecutor::shell()),
+            command_env: HashMap::new(),
+        }
+    }
+
+    /// Whether the underlying SSH connection has been closed.
+    pub fn is_closed(&self) -> bool {
+        match &self.transport {
+            Transport::Direct(client) => client.session.is_closed(),
+            Transport::Session(_) | Transport::Local(_) => false,
+        }
+    }
+
+    /// Run `command` with streamed output, see [`OutputChunk`]. Bytes received
+    /// on `input` are forwarded to its stdin; returning `false` from `output`
+    /// stops the command.
+    pub async fn execute_attached(
+        &mut self,
+        command: &str,
+        pty: Option<&PtyRequest>,
+        input: mpsc::Receiver<Vec<u8>>,
+        output: &mut (dyn FnMut(OutputChunk) -> bool + Send),
+    ) -> Result<u32, ExecutorError> {
+        let command = &self.with_exports(command)?;
+        match &mut self.transport {
+            Transport::Direct(client) => {
+                client
+                    .execute_attached_internal(command, pty, input, output)
+                    .await
+            }
+            Transport::Session(session) => session.attach(command, pty, input, output).await,
+            Transport::Local(_) => Err(ExecutorError::LocalError(
+                "Attached commands need an SSH connection".to_string(),
+            )),
+        }
+    }
+
+    /// Open at most `channels` SSH channels at once on the connection (at
+    /// least two: one for SFTP uploads and one for commands). Only direct
+    /// connections open channels themselves.
+    pub fn with_max_channels(mut self, channels: usize) -> Self {
+        if let Transport::Direct(client) = &mut self.transport {
+            client.max_channels = channels.max(2);
+        }
+        self
+    }
+
+    /// `command` preceded by exports of the variables set for every command.
+    fn with_exports(&self, command: &str) -> Result<String, ExecutorError> {
+        if self.command_env.is_empty() {
+            return Ok(command.to_string());
+        }
+        Ok(format!("{}{}", export_prefix(&self.command_env)?, command))
+    }
+
+    /// The variables set for every command, overridden by `env`.
+    fn merged_env(&self, env: &HashMap<String, String>) -> HashMap<String, String> {
+        let mut merged = self.command_env.clone();
+        merged.extend(env.iter().map(|(k, v)| (k.clone(), v.clone())));
+        merged
+    }
+
+    /// Create a new SSH-based executor by connecting to the remote host,
+    /// through `jump` if given.
     pub async fn connect(
-        key_path: Option<impl AsRef<Path>>,
+        auth: &SshAuth,
         username: &str,
         addr: &str,
         timeout: Duration,
-        suppress_unknown_host_warning: bool,
+        host_key_policy: HostKeyPolicy,
+        known_hosts: Option<&Path>,
+        jump: Option<&JumpHost>,
     ) -> Result<Self, ExecutorError> {
-        // --- Resolve hostname/IP ---
-        let resolved_addr = tokio::net::lookup_host(addr)
-            .await
-            .map_err(|e| {
-                ExecutorError::SshError(format!("Failed to resolve host '{}': {}", addr, e))
-            })?
-            .next() // Take the first resolved address
-            .ok_or_else(|| {
-                ExecutorError::SshError(format!("No addresses found for host '{}'", addr))
-            })?;
-
-        // Use resolved IP address string for key lookup. Could also try hostname.
-        // Using IP is generally more reliable if DNS entries change but IP stays same.
-        let target_host_str = resolved_addr.ip().to_string();
-        tracing::debug!("Resolved target host '{}' to IP: {}", addr, target_host_str);
-
         // --- Load Known Hosts ---
-        let known_hosts_path = dirs::home_dir()
-            .map(|home| home.join(".ssh").join("known_hosts"))
-            .ok_or_else(|| {
-                ExecutorError::SshError(
-                    "Could not determine home directory for known_hosts file.".to_string(),
-                )
-            })?;
+        let known_hosts_path = match known_hosts {
+            Some(path) => expand_tilde_path(path)?,
+            None => dirs::home_dir()
+                .map(|home| home.join(".ssh").join("known_hosts"))
+                .ok_or_else(|| {
+                    ExecutorError::SshError(
+                        "Could not determine home directory for known_hosts file.".to_string(),
+                    )
+                })?,
+        };
 
         tracing::debug!("Loading known hosts from: {}", known_hosts_path.display());
-        let known_hosts_map = Arc::new(load_known_hosts(&known_hosts_path).await?);
+        let known_hosts = KnownHosts::load(&known_hosts_path).await?;
 
-        // --- Try SSH Connection with Key(s) ---
-        if let Some(user_key) = key_path {
-            // User specified a key - use only that key
-            let expanded_path = expand_tilde_path(user_key.as_ref())?;
+        // --- Connect and authenticate ---
+        let mut auth = auth.clone();
+        if let Some(key_path) = &auth.key_path {
+            // A key the user asked for must exist
+            let expanded_path = expand_tilde_path(key_path)?;
             if !expanded_path.exists() {
                 return Err(ExecutorError::SshError(format!(
                     "Specified SSH key file not found: {}",
                     expanded_path.display()
                 )));
             }
-
             tracing::debug!("Using user-specified SSH key: {}", expanded_path.display());
-            let client = try_ssh_connection(
-                &expanded_path,
-                username,
-                resolved_addr,
-                &target_host_str,
-                &known_hosts_map,
-                timeout,
-                suppress_unknown_host_warning,
-            )
-            .await?;
-
-            Ok(SshCommandExecutor { client })
-        } else {
-            // Auto-detect and try multiple keys
-            let home_dir = dirs::home_dir().ok_or_else(|| {
-                ExecutorError::SshError("Could not determine home directory".to_string())
-            })?;
-
-            let ssh_dir = home_dir.join(".ssh");
-            let potential_keys = vec![ssh_dir.join("id_rsa"), ssh_dir.join("id_ed25519")];
-
-            let mut connection_errors = Vec::new();
-
-            for key_path in &potential_keys {
-                if !key_path.exists() {
-                    tracing::debug!("SSH key not found: {}", key_path.display());
-                    continue;
-                }
+            auth.key_path = Some(expanded_path);
+        }
 
-                tracing::debug!("Trying SSH key: {}", key_path.display());
-                match try_ssh_connection(
-                    key_path,
+        let client = match jump {
+            None => {
+                let host_key_check = HostKeyCheck {
+                    names: Vec::new(),
+                    known_hosts,
+                    policy: host_key_policy,
+                };
+                Self::direct_client(&auth, username, addr, timeout, host_key_check).await?
+            }
+            Some(jump) => {
+                // The bastion checks its key against the same known_hosts
+                let bastion_check = HostKeyCheck {
+                    names: Vec::new(),
+                    known_hosts: known_hosts.clone(),
+                    policy: host_key_policy,
+                };
+                let bastion =
+                    Self::direct_client(&auth, &jump.user, &jump.addr, timeout, bastion_check)
+                        .await
+                        .map_err(|e| {
+                            ExecutorError::SshError(format!("Jump host {}: {}", jump.addr, e))
+                        })?;
+                let (host, port) = split_host_port(addr);
+                tracing::debug!("Connecting to {} through jump host {}", addr, jump.addr);
+                // Only the bastion knows the target's IP, so the name is all there is to look up
+                let host_key_check = HostKeyCheck {
+                    names: vec![known_host_entry(&host, port)],
+                    known_hosts,
+                    policy: host_key_policy,
+                };
+                SshClient::connect_via(
+                    bastion,
+                    &host,
+                    port,
                     username,
-                    resolved_addr,
-                    &target_host_str,
-                    &known_hosts_map,
+                    &auth,
+                    host_key_check,
                     timeout,
-                    suppress_unknown_host_warning,
                 )
-                .await
-                {
-                    Ok(client) => {
-                        tracing::info!(
-                            "Successfully connected using SSH key: {}",
-                            key_path.display()
-                        );
-                        return Ok(SshCommandExecutor { client });
-                    }
-                    Err(e) => {
-                        tracing::debug!("Failed to connect with key {}: {}", key_path.display(), e);
-                        connection_errors.push(format!("{}: {}", key_path.display(), e));
-                    }
-                }
+                .await?
             }
+        };
+        Ok(SshCommandExecutor {
+            transport: Transport::Direct(client),
+            command_env: HashMap::new(),
+        })
+    }
+
+    /// Resolve `addr`, find an address that answers and connect to it
+    /// directly. The host key is looked up under the names of `addr`.
+    async fn direct_client(
+        auth: &SshAuth,
+        username: &str,
+        addr: &str,
+        timeout: Duration,
+        mut host_key_check: HostKeyCheck,
+    ) -> Result<SshClient, ExecutorError> {
+        // --- Resolve hostname/IP ---
+        let resolved_addrs: Vec<SocketAddr> = tokio::net::lookup_host(addr)
+            .await
+            .map_err(|e| {
+                ExecutorError::SshError(format!("Failed to resolve host '{}': {}", addr, e))
+            })?
+            .collect();
+        tracing::debug!("Host '{}' resolved to {:?}", addr, resolved_addrs);
 
-            // If we get here, all keys failed
-            let existing_keys: Vec<_> = potential_keys
-                .iter()
-                .filter(|k| k.exists())
-                .map(|k| k.display().to_string())
-                .collect();
+        // Fail with a specific reason if nothing SSH-like answers, instead of
+        // russh's generic connection error; every address gets a try
+        let resolved_addr = first_reachable(resolved_addrs, addr, timeout).await?;
 
-            if existing_keys.is_empty() {
-                Err(ExecutorError::SshError(
-                    "No SSH keys found. Tried ~/.ssh/id_rsa and ~/.ssh/id_ed25519. Please specify a key with --identity or generate SSH keys.".to_string()
-                ))
-            } else {
-                Err(ExecutorError::SshError(format!(
-                    "Failed to connect with any available SSH keys. Tried: {}. Errors: {}",
-                    existing_keys.join(", "),
-                    connection_errors.join("; ")
-                )))
-            }
-        }
+        // Look the host key up under the name the user gave as well as the IP,
+        // in the form OpenSSH records for the port
+        let (target_host_str, _) = split_host_port(addr);
+        host_key_check.names = known_host_names(&target_host_str, resolved_addr);
+        tracing::debug!(
+            "Resolved target host '{}' to IP {}, known_hosts names: {:?}",
+            addr,
+            resolved_addr.ip(),
+            host_key_check.names
+        );
+
+        SshClient::connect(resolved_addr, username, auth, host_key_check, timeout).await
     }
 }
 
+/// Host and port of a `host:port` or `[host]:port` address; port 22 if
+/// the port is missing or invalid.
+fn split_host_port(addr: &str) -> (String, u16) {
+    let (host, port) = match addr.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() => (host, port.parse().unwrap_or(22)),
+        _ => (addr, 22),
+    };
+    (
+        host.trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string(),
+        port,
+    )
+}
+
 #[async_trait]
 impl CommandExecutor for SshCommandExecutor {
     async fn execute_command(&mut self, command: &str) -> Result<CommandResult, ExecutorError> {
-        self.client.execute_command_internal(command).await
+        if !self.command_env.is_empty() {
+            return self
+                .execute_command_with_env(command, &HashMap::new())
+                .await;
+        }
+        match &mut self.transport {
+            Transport::Direct(client) => {
+                client
+                    .execute_command_internal(command, command, &HashMap::new(), None)
+                    .await
+            }
+            Transport::Session(session) => session.execute(command, &HashMap::new()).await,
+            Transport::Local(local) => local.execute_command(command).await,
+        }
+    }
+
+    async fn execute_command_with_env(
+        &mut self,
+        command: &str,
+        env: &HashMap<String, String>,
+    ) -> Result<CommandResult, ExecutorError> {
+        if env.is_empty() && self.command_env.is_empty() {
+            return self.execute_command(command).await;
+        }
+        let env = &self.merged_env(env);
+        match &mut self.transport {
+            Transport::Direct(client) => {
+                client
+                    .execute_command_internal(command, command, env, None)
+                    .await
+            }
+            Transport::Session(session) => session.execute(command, env).await,
+            Transport::Local(local) => local.execute_command_with_env(command, env).await,
+        }
+    }
+
+    async fn execute_command_streaming(
+        &mut self,
+        command: &str,
+        env: &HashMap<String, String>,
+        output: mpsc::Sender<OutputChunk>,
+    ) -> Result<CommandResult, ExecutorError> {
+        let env = &self.merged_env(env);
+        match &mut self.transport {
+            Transport::Direct(client) => {
+                client
+                    .execute_command_internal(command, command, env, Some(&output))
+                    .await
+            }
+            // The session answers with the whole output at once
+            Transport::Session(session) => {
+                let result = session.execute(command, env).await?;
+                send_output(&output, &result).await;
+                Ok(result)
+            }
+            Transport::Local(local) => local.execute_command_streaming(command, env, output).await,
+        }
+    }
+
+    async fn execute_interactive(
+        &mut self,
+        command: &str,
+        tty: bool,
+    ) -> Result<u32, ExecutorError> {
+        if let Transport::Local(local) = &mut self.transport {
+            let command = format!(
+                "{}{}",
+                if self.command_env.is_empty() {
+                    String::new()
+                } else {
+                    export_prefix(&self.command_env)?
+                },
+                command
+            );
+            return local.execute_interactive(&command, tty).await;
+        }
+        let pty = tty.then(PtyRequest::local);
+        let (input_tx, input) = mpsc::channel(16);
+        let stdin_task = tokio::spawn(async move {
+            let mut stdin = tokio::io::stdin();
+            let mut buf = [0u8; 1024];
+            while let Ok(n) = stdin.read(&mut buf).await {
+                if n == 0 || input_tx.send(buf[..n].to_vec()).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        // Raw mode lets keystrokes (including Ctrl-C) reach the remote pty
+        let raw_mode = tty && crossterm::terminal::enable_raw_mode().is_ok();
+        let result = self
+            .execute_attached(command, pty.as_ref(), input, &mut write_local_output)
+            .await;
+        if raw_mode {
+            let _ = crossterm::terminal::disable_raw_mode();
+        }
+        stdin_task.abort();
+        result
+    }
+
+    fn set_command_env(&mut self, env: HashMap<String, String>) {
+        self.command_env = env;
+    }
+
+    fn command_env(&self) -> &HashMap<String, String> {
+        &self.command_env
     }
 
     async fn close(&mut self) -> Result<(), ExecutorError> {
-        self.client.close_internal().await
+        match &mut self.transport {
+            Transport::Direct(client) => client.close_internal().await,
+            // The session stays open for later invocations
+            Transport::Session(_) => Ok(()),
+            Transport::Local(local) => local.close().await,
+        }
     }
 }
 
@@ -587,9 +1430,152 @@ impl FileTransfer for SshCommandExecutor {
         &self,
         local_path: &Path,
         remote_path: &Path,
+        progress: Option<&UploadProgress>,
     ) -> Result<(), ExecutorError> {
-        self.client
-            .upload_file_internal(local_path, remote_path)
-            .await
+        match &self.transport {
+            Transport::Direct(client) => {
+                client
+                    .upload_file_internal(local_path, remote_path, progress)
+                    .await
+            }
+            // The session only answers once the upload is done
+            Transport::Session(session) => {
+                session.upload_file(local_path, remote_path).await?;
+                if let Some(progress) = progress {
+                    let total = tokio::fs::metadata(local_path)
+                        .await
+                        .map(|m| m.len())
+                        .unwrap_or_default();
+                    progress(total, total);
+                }
+                Ok(())
+            }
+            Transport::Local(local) => local.upload_file(local_path, remote_path, progress).await,
+        }
+    }
+
+    async fn upload_content(
+        &self,
+        content: &[u8],
+        remote_path: &Path,
+    ) -> Result<(), ExecutorError> {
+        match &self.transport {
+            Transport::Direct(client) => client.upload_content_internal(content, remote_path).await,
+            Transport::Session(session) => session.upload_content(content, remote_path).await,
+            Transport::Local(local) => local.upload_content(content, remote_path).await,
+        }
+    }
+
+    async fn upload_files(
+        &self,
+        batch: &[(PathBuf, PathBuf)],
+        concurrency: usize,
+    ) -> Vec<Result<(), ExecutorError>> {
+        match &self.transport {
+            Transport::Direct(client) => client.upload_files_internal(batch, concurrency).await,
+            Transport::Local(local) => local.upload_files(batch, concurrency).await,
+            // Each request to the session gets a connection of its own
+            Transport::Session(session) => {
+                let uploads: Vec<_> = batch
+                    .iter()
+                    .map(|(local_path, remote_path)| session.upload_file(local_path, remote_path))
+                    .collect();
+                stream::iter(uploads)
+                    .buffered(concurrency.max(1))
+                    .collect()
+                    .await
+            }
+        }
+    }
+
+    async fn download_content(&self, remote_path: &Path) -> Result<Vec<u8>, ExecutorError> {
+        match &self.transport {
+            Transport::Direct(client) => client.download_content_internal(remote_path).await,
+            Transport::Session(session) => session.download_content(remote_path).await,
+            Transport::Local(local) => local.download_content(remote_path).await,
+        }
+    }
+
+    async fn list_files(&self, remote_dir: &Path) -> Result<Vec<PathBuf>, ExecutorError> {
+        match &self.transport {
+            Transport::Direct(client) => client.list_files_internal(remote_dir).await,
+            Transport::Session(session) => session.list_files(remote_dir).await,
+            Transport::Local(local) => local.list_files(remote_dir).await,
+        }
+    }
+}
+
+// Print output of an interactive command as it arrives
+fn write_local_output(chunk: OutputChunk) -> bool {
+    match chunk {
+        OutputChunk::Stdout(data) => {
+            let mut stdout = std::io::stdout();
+            let _ = stdout.write_all(&data).and_then(|_| stdout.flush());
+        }
+        OutputChunk::Stderr(data) => {
+            let mut stderr = std::io::stderr();
+            let _ = stderr.write_all(&data).and_then(|_| stderr.flush());
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_password_prompt() {
+        let prompt = |text: &str, echo| client::Prompt {
+            prompt: text.to_string(),
+            echo,
+        };
+        assert!(is_password_prompt(&prompt("Password: ", false)));
+        assert!(is_password_prompt(&prompt(
+            "(deploy@host) password:",
+            false
+        )));
+        assert!(!is_password_prompt(&prompt("Verification code: ", false)));
+        assert!(!is_password_prompt(&prompt("Password: ", true)));
+    }
+
+    #[test]
+    fn test_auth_method_from_str() {
+        assert_eq!("agent".parse::<AuthMethod>(), Ok(AuthMethod::Agent));
+        assert_eq!(" password".parse::<AuthMethod>(), Ok(AuthMethod::Password));
+        assert!("kerberos"
+            .parse::<AuthMethod>()
+            .unwrap_err()
+            .contains("key, agent, password"));
+    }
+
+    #[test]
+    fn test_split_host_port() {
+        assert_eq!(
+            split_host_port("deploy.example.com:2222"),
+            ("deploy.example.com".to_string(), 2222)
+        );
+        assert_eq!(split_host_port("[::1]:22"), ("::1".to_string(), 22));
+        assert_eq!(split_host_port("bastion"), ("bastion".to_string(), 22));
+    }
+
+    #[test]
+    fn test_known_host_names_order() {
+        let addr: SocketAddr = "203.0.113.7:2222".parse().unwrap();
+        assert_eq!(
+            known_host_names("deploy.example.com", addr),
+            vec!["[deploy.example.com]:2222", "[203.0.113.7]:2222"]
+        );
+        // Connecting by IP does not repeat the same names
+        assert_eq!(
+            known_host_names("203.0.113.7", addr),
+            vec!["[203.0.113.7]:2222"]
+        );
+        // Port 22 uses the plain names
+        let addr: SocketAddr = "203.0.113.7:22".parse().unwrap();
+        assert_eq!(
+            known_host_names("deploy.example.com", addr),
+            vec!["deploy.example.com", "203.0.113.7"]
+        );
     }
 }
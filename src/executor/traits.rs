@@ -1,7 +1,10 @@
 use async_trait::async_trait;
-use std::path::Path;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
 
-use super::{CommandResult, ExecutorError};
+use super::{CommandResult, ExecutorError, OutputChunk};
 
 /// A trait for executing commands in a uniform way (local, SSH, etc.).
 #[async_trait]
@@ -9,14 +12,223 @@ pub trait CommandExecutor {
     /// Execute a command and return a `CommandResult` containing stdout/stderr/exit code.
     async fn execute_command(&mut self, command: &str) -> Result<CommandResult, ExecutorError>;
 
+    /// Execute a command with additional environment variables.
+    ///
+    /// The default implementation exports the variables in the shell running
+    /// the command. The SSH executor sends `env` requests instead and passes
+    /// the names the server refuses (those missing from `AcceptEnv`) on the
+    /// command's stdin, so values never show up in the remote process list.
+    async fn execute_command_with_env(
+        &mut self,
+        command: &str,
+        env: &HashMap<String, String>,
+    ) -> Result<CommandResult, ExecutorError> {
+        if env.is_empty() {
+            return self.execute_command(command).await;
+        }
+        let command = format!("{}{}", export_prefix(env)?, command);
+        self.execute_command(&command).await
+    }
+
+    /// Execute a command with additional environment variables, sending its
+    /// output to `output` as it arrives. The result still holds all of it.
+    ///
+    /// The default implementation sends the output in one piece once the
+    /// command finishes; executors that can stream (SSH, local) override it.
+    async fn execute_command_streaming(
+        &mut self,
+        command: &str,
+        env: &HashMap<String, String>,
+        output: mpsc::Sender<OutputChunk>,
+    ) -> Result<CommandResult, ExecutorError> {
+        let result = self.execute_command_with_env(command, env).await?;
+        send_output(&output, &result).await;
+        Ok(result)
+    }
+
+    /// Run a command attached to the local terminal and return its exit code.
+    ///
+    /// The default implementation buffers the output and prints it once the
+    /// command finishes; executors that can stream (SSH) override this and
+    /// allocate a pseudo-terminal when `tty` is set.
+    async fn execute_interactive(
+        &mut self,
+        command: &str,
+        tty: bool,
+    ) -> Result<u32, ExecutorError> {
+        let _ = tty;
+        let result = self.execute_command(command).await?;
+        let mut stdout = std::io::stdout();
+        let mut stderr = std::io::stderr();
+        stdout
+            .write_all(&result.output.stdout)
+            .and_then(|_| stdout.flush())
+            .and_then(|_| stderr.write_all(&result.output.stderr))
+            .map_err(|e| ExecutorError::Other(e.to_string()))?;
+        Ok(result.output.exit_code)
+    }
+
+    /// Export `env` for every command run from now on, e.g. the `DOCKER_HOST`
+    /// of a rootless docker daemon. Variables passed with a command win.
+    fn set_command_env(&mut self, env: HashMap<String, String>);
+
+    /// Variables set with [`Self::set_command_env`].
+    fn command_env(&self) -> &HashMap<String, String>;
+
     /// Close or clean up the executor (e.g., disconnect SSH).
     async fn close(&mut self) -> Result<(), ExecutorError>;
 }
 
-/// A trait for uploading files. SSH uses SFTP; local might do a filesystem copy.
+/// Told the bytes of an upload sent so far and the total size, as it goes.
+pub type UploadProgress = dyn Fn(u64, u64) + Send + Sync;
+
+/// A trait for uploading and downloading files. SSH uses SFTP; local might do a filesystem copy.
 /// Keep it separate so that executors that don't need file transfers aren't forced to implement it.
 #[async_trait]
-pub trait FileTransfer {
-    async fn upload_file(&self, local_path: &Path, remote_path: &Path)
+pub trait FileTransfer: Sync {
+    /// Copy `local_path` to `remote_path`, reporting to `progress` as bytes
+    /// go out; transports that cannot tell report once, when done.
+    async fn upload_file(
+        &self,
+        local_path: &Path,
+        remote_path: &Path,
+        progress: Option<&UploadProgress>,
+    ) -> Result<(), ExecutorError>;
+
+    /// Write in-memory content to `remote_path` without a local temporary file.
+    async fn upload_content(&self, content: &[u8], remote_path: &Path)
         -> Result<(), ExecutorError>;
+
+    /// Upload a batch of `(local, remote)` files with up to `concurrency` in
+    /// flight. Returns one result per file, in the order of `batch`.
+    async fn upload_files(
+        &self,
+        batch: &[(PathBuf, PathBuf)],
+        concurrency: usize,
+    ) -> Vec<Result<(), ExecutorError>>;
+
+    /// Read the content of the file at `remote_path`.
+    async fn download_content(&self, remote_path: &Path) -> Result<Vec<u8>, ExecutorError>;
+
+    /// Regular files below `remote_dir`, relative to it, sorted. Symlinks to
+    /// files are included; directories are walked, not listed.
+    async fn list_files(&self, remote_dir: &Path) -> Result<Vec<PathBuf>, ExecutorError>;
+
+    /// Copy `remote_path` to `local_path`, creating missing local directories.
+    async fn download_file(
+        &self,
+        remote_path: &Path,
+        local_path: &Path,
+    ) -> Result<(), ExecutorError> {
+        let content = self.download_content(remote_path).await?;
+        write_local_file(local_path, &content).await
+    }
+
+    /// Copy the files below `remote_dir` into `local_dir`, keeping their
+    /// layout. Returns the files copied, relative to both directories.
+    async fn download_dir(
+        &self,
+        remote_dir: &Path,
+        local_dir: &Path,
+    ) -> Result<Vec<PathBuf>, ExecutorError> {
+        let files = self.list_files(remote_dir).await?;
+        for file in &files {
+            self.download_file(&remote_dir.join(file), &local_dir.join(file))
+                .await?;
+        }
+        Ok(files)
+    }
+}
+
+/// Write a downloaded file, creating its parent directories.
+async fn write_local_file(local_path: &Path, content: &[u8]) -> Result<(), ExecutorError> {
+    let local_error = |e: std::io::Error| {
+        ExecutorError::LocalError(format!("Failed to write {}: {}", local_path.display(), e))
+    };
+    if let Some(parent) = local_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(local_error)?;
+    }
+    tokio::fs::write(local_path, content)
+        .await
+        .map_err(local_error)
+}
+
+/// Send the output of a finished command to `output`, for executors that
+/// cannot stream.
+pub(crate) async fn send_output(output: &mpsc::Sender<OutputChunk>, result: &CommandResult) {
+    if !result.output.stdout.is_empty() {
+        let _ = output
+            .send(OutputChunk::Stdout(result.output.stdout.clone()))
+            .await;
+    }
+    if !result.output.stderr.is_empty() {
+        let _ = output
+            .send(OutputChunk::Stderr(result.output.stderr.clone()))
+            .await;
+    }
+}
+
+/// Build an `export K='v' ...; ` prefix with sorted, validated names and
+/// single-quoted values.
+pub fn export_prefix(env: &HashMap<String, String>) -> Result<String, ExecutorError> {
+    let mut keys: Vec<&String> = env.keys().collect();
+    keys.sort();
+    let mut assignments = Vec::with_capacity(keys.len());
+    for key in keys {
+        if !is_valid_env_name(key) {
+            return Err(ExecutorError::Other(format!(
+                "Invalid environment variable name '{}'",
+                key
+            )));
+        }
+        assignments.push(format!("{}='{}'", key, env[key].replace('\'', "'\\''")));
+    }
+    Ok(format!("export {}; ", assignments.join(" ")))
+}
+
+/// Quote a single argument for a POSIX shell.
+pub fn shell_quote(arg: &str) -> String {
+    if !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c))
+    {
+        return arg.to_string();
+    }
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+pub(crate) fn is_valid_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_prefix() {
+        let mut env = HashMap::new();
+        env.insert("COMPOSE_PROJECT_NAME".to_string(), "shop".to_string());
+        env.insert("DOCKER_CONFIG".to_string(), "/opt/it's".to_string());
+        assert_eq!(
+            export_prefix(&env).unwrap(),
+            "export COMPOSE_PROJECT_NAME='shop' DOCKER_CONFIG='/opt/it'\\''s'; "
+        );
+
+        env.insert("BAD NAME".to_string(), "x".to_string());
+        assert!(export_prefix(&env).is_err());
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("db:migrate"), "db:migrate");
+        assert_eq!(shell_quote("hello world"), "'hello world'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote(""), "''");
+    }
 }
@@ -30,6 +30,13 @@ pub enum OutputError {
     OutputTooLarge { size: usize },
 }
 
+/// A piece of output of a running command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputChunk {
+    Stdout(Vec<u8>),
+    Stderr(Vec<u8>),
+}
+
 /// Contains the raw output (stdout/stderr), exit code, timing information, etc.
 #[derive(Debug, Clone)]
 pub struct CommandOutput {
@@ -1,4 +1,6 @@
 pub mod cli;
 pub mod composer;
+pub mod config;
 pub mod deployer;
 pub mod executor;
+pub mod testing;
@@ -10,16 +10,33 @@ async fn main() {
 
     let progress_likely_active = match &cli_args.command {
         Commands::Up(up_args) => !up_args.no_progress,
+        Commands::Apply(apply_args) => !apply_args.up.no_progress,
         Commands::Status(up_args) => !up_args.no_progress,
         Commands::Destroy(up_args) => !up_args.no_progress,
+        Commands::Reboot(up_args) => !up_args.no_progress,
+        Commands::SyncOnly(up_args) => !up_args.no_progress,
+        Commands::Cleanup(up_args) => !up_args.no_progress,
+        Commands::Rollback(up_args) => !up_args.no_progress,
+        Commands::Gc(up_args) => !up_args.no_progress,
         _ => false,
     };
 
     // Setup tracing subscriber
     // If progress is likely active, hide INFO logs by default to keep output clean.
     // Otherwise, show INFO logs by default. Verbosity flags override this.
-    let default_level = if progress_likely_active && cli_args.verbose == 0 {
+    // `run` and `exec` hand the terminal to the remote command and `health` is meant for
+    // scripts, so keep logs out of the way
+    let quiet_by_default = matches!(
+        cli_args.command,
+        Commands::Run(_) | Commands::Exec(_) | Commands::Health(_)
+    );
+
+    let default_level = if cli_args.quiet {
+        LevelFilter::OFF
+    } else if progress_likely_active && cli_args.verbose == 0 {
         LevelFilter::INFO // Hide INFO when progress bar is active and no -v
+    } else if quiet_by_default && cli_args.verbose == 0 {
+        LevelFilter::WARN
     } else {
         // Show INFO by default, or DEBUG/TRACE if -v/-vv is set
         match cli_args.verbose {
@@ -40,9 +57,22 @@ async fn main() {
         .init();
 
     // Execute the command
-    if let Err(e) = dcd::cli::run(cli_args).await {
-        // Print user-facing error message clearly
-        eprintln!("{}: {}", "Error".red().bold(), e);
+    // With --output json stdout belongs to the JSON document
+    let quiet = cli_args.quiet && !cli_args.json();
+    let command = cli_args.command.name();
+    let result = dcd::cli::run(cli_args).await;
+    if quiet {
+        // Single line for wrapping tools: `<command>: ok|failed (exit <code>)[: <error>]`
+        match &result {
+            Ok(()) => println!("{}: ok (exit 0)", command),
+            Err(e) => println!("{}: failed (exit 1): {}", command, e),
+        }
+    }
+    if let Err(e) = result {
+        if !quiet {
+            // Print user-facing error message clearly
+            eprintln!("{}: {}", "Error".red().bold(), e);
+        }
         process::exit(1);
     }
 }
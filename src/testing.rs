@@ -0,0 +1,439 @@
+//! Test doubles for the executor traits, so deployment logic can be tested
+//! without SSH or Docker.
+//!
+//! [`MockExecutor`] answers commands from a script and keeps a log of what
+//! ran; uploads land in a [`MemoryFs`], which also answers `cat` of files
+//! uploaded earlier:
+//!
+//! ```
+//! use dcd::executor::{CommandExecutor, FileTransfer};
+//! use dcd::testing::{CommandMatch, MockExecutor};
+//! use std::path::Path;
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! let mut executor = MockExecutor::new();
+//! executor
+//!     .respond_ok("docker compose version --format json", r#"{"version":"v2.27.0"}"#)
+//!     .respond_err(CommandMatch::Prefix("docker pull".into()), 1, "denied");
+//!
+//! executor.upload_content(b"A=1\n", Path::new("/opt/app/.env.dcd")).await.unwrap();
+//! let env = executor.execute_command("cat '/opt/app/.env.dcd'").await.unwrap();
+//! assert_eq!(env.output.stdout, b"A=1\n");
+//!
+//! let pull = executor.execute_command("docker pull nginx").await.unwrap();
+//! assert!(!pull.is_success());
+//! assert_eq!(executor.commands().len(), 2);
+//! # }
+//! ```
+
+use crate::executor::{
+    CommandExecutor, CommandResult, ExecutorError, FileTransfer, UploadProgress,
+};
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Which commands a scripted response applies to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandMatch {
+    /// The whole command
+    Exact(String),
+    /// Commands starting with this text
+    Prefix(String),
+    /// Commands containing this text anywhere (e.g. after an `export` or `cd`)
+    Contains(String),
+}
+
+impl CommandMatch {
+    pub fn matches(&self, command: &str) -> bool {
+        match self {
+            CommandMatch::Exact(expected) => command == expected,
+            CommandMatch::Prefix(prefix) => command.starts_with(prefix.as_str()),
+            CommandMatch::Contains(text) => command.contains(text.as_str()),
+        }
+    }
+}
+
+impl From<&str> for CommandMatch {
+    fn from(command: &str) -> Self {
+        CommandMatch::Exact(command.to_string())
+    }
+}
+
+impl From<String> for CommandMatch {
+    fn from(command: String) -> Self {
+        CommandMatch::Exact(command)
+    }
+}
+
+/// A successful result with `stdout`.
+pub fn success(stdout: &str) -> Result<CommandResult, ExecutorError> {
+    Ok(exited(0, stdout, ""))
+}
+
+/// A result with a non-zero `exit_code` and `stderr`.
+pub fn failure(exit_code: u32, stderr: &str) -> Result<CommandResult, ExecutorError> {
+    Ok(exited(exit_code, "", stderr))
+}
+
+fn exited(exit_code: u32, stdout: &str, stderr: &str) -> CommandResult {
+    let mut result = CommandResult::new("mock_command");
+    result.output.stdout = stdout.as_bytes().to_vec();
+    result.output.stderr = stderr.as_bytes().to_vec();
+    result.output.exit_code = exit_code;
+    result
+}
+
+struct Rule {
+    matcher: CommandMatch,
+    /// Played in order; the last one repeats
+    responses: VecDeque<Result<CommandResult, ExecutorError>>,
+}
+
+/// A [`CommandExecutor`] and [`FileTransfer`] that plays back scripted
+/// responses.
+///
+/// Rules are tried in the order they were added and the first match answers.
+/// Adding a response for a matcher that already has one queues it: the
+/// responses are played in order and the last one repeats, which scripts
+/// e.g. a service that is starting twice and then healthy. Commands without
+/// a rule fail with [`ExecutorError::Other`], except `cat` of a file in the
+/// [`MemoryFs`].
+#[derive(Default)]
+pub struct MockExecutor {
+    rules: Vec<Rule>,
+    commands: Vec<String>,
+    fs: MemoryFs,
+    /// Set with `set_command_env`; commands are matched without it
+    command_env: HashMap<String, String>,
+}
+
+impl MockExecutor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A mock whose uploads go to `fs`, e.g. one shared with other mocks.
+    pub fn with_fs(fs: MemoryFs) -> Self {
+        Self {
+            fs,
+            ..Self::default()
+        }
+    }
+
+    /// Answer commands matching `matcher` with `result`.
+    pub fn respond(
+        &mut self,
+        matcher: impl Into<CommandMatch>,
+        result: Result<CommandResult, ExecutorError>,
+    ) -> &mut Self {
+        let matcher = matcher.into();
+        match self.rules.iter_mut().find(|rule| rule.matcher == matcher) {
+            Some(rule) => rule.responses.push_back(result),
+            None => self.rules.push(Rule {
+                matcher,
+                responses: VecDeque::from([result]),
+            }),
+        }
+        self
+    }
+
+    /// Answer commands matching `matcher` successfully with `stdout`.
+    pub fn respond_ok(&mut self, matcher: impl Into<CommandMatch>, stdout: &str) -> &mut Self {
+        self.respond(matcher, success(stdout))
+    }
+
+    /// Answer commands matching `matcher` with a non-zero exit code.
+    pub fn respond_err(
+        &mut self,
+        matcher: impl Into<CommandMatch>,
+        exit_code: u32,
+        stderr: &str,
+    ) -> &mut Self {
+        self.respond(matcher, failure(exit_code, stderr))
+    }
+
+    /// Every command run so far, in order.
+    pub fn commands(&self) -> &[String] {
+        &self.commands
+    }
+
+    /// Whether a command matching `matcher` was run.
+    pub fn ran(&self, matcher: impl Into<CommandMatch>) -> bool {
+        let matcher = matcher.into();
+        self.commands.iter().any(|command| matcher.matches(command))
+    }
+
+    /// The files uploaded through this executor.
+    pub fn fs(&self) -> &MemoryFs {
+        &self.fs
+    }
+
+    fn scripted(&mut self, command: &str) -> Option<Result<CommandResult, ExecutorError>> {
+        let rule = self
+            .rules
+            .iter_mut()
+            .find(|rule| rule.matcher.matches(command))?;
+        if rule.responses.len() > 1 {
+            rule.responses.pop_front()
+        } else {
+            rule.responses.front().cloned()
+        }
+    }
+}
+
+#[async_trait]
+impl CommandExecutor for MockExecutor {
+    async fn execute_command(&mut self, command: &str) -> Result<CommandResult, ExecutorError> {
+        self.commands.push(command.to_string());
+        let result = match self.scripted(command) {
+            Some(result) => result,
+            None => match cat_path(command) {
+                Some(path) => match self.fs.read(&path) {
+                    Some(content) => {
+                        let mut result = exited(0, "", "");
+                        result.output.stdout = content;
+                        Ok(result)
+                    }
+                    None => failure(1, &format!("cat: {}: No such file", path.display())),
+                },
+                None => Err(ExecutorError::Other(format!(
+                    "Mock response not found for command: {}",
+                    command
+                ))),
+            },
+        };
+        result.map(|mut result| {
+            result.command = command.to_string();
+            result
+        })
+    }
+
+    fn set_command_env(&mut self, env: HashMap<String, String>) {
+        self.command_env = env;
+    }
+
+    fn command_env(&self) -> &HashMap<String, String> {
+        &self.command_env
+    }
+
+    async fn close(&mut self) -> Result<(), ExecutorError> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl FileTransfer for MockExecutor {
+    async fn upload_file(
+        &self,
+        local_path: &Path,
+        remote_path: &Path,
+        progress: Option<&UploadProgress>,
+    ) -> Result<(), ExecutorError> {
+        self.fs.upload_file(local_path, remote_path, progress).await
+    }
+
+    async fn upload_content(
+        &self,
+        content: &[u8],
+        remote_path: &Path,
+    ) -> Result<(), ExecutorError> {
+        self.fs.upload_content(content, remote_path).await
+    }
+
+    async fn upload_files(
+        &self,
+        batch: &[(PathBuf, PathBuf)],
+        concurrency: usize,
+    ) -> Vec<Result<(), ExecutorError>> {
+        self.fs.upload_files(batch, concurrency).await
+    }
+
+    async fn download_content(&self, remote_path: &Path) -> Result<Vec<u8>, ExecutorError> {
+        self.fs.download_content(remote_path).await
+    }
+
+    async fn list_files(&self, remote_dir: &Path) -> Result<Vec<PathBuf>, ExecutorError> {
+        self.fs.list_files(remote_dir).await
+    }
+}
+
+/// The file read by a plain `cat <path>` (quoted or not, stderr discarded).
+fn cat_path(command: &str) -> Option<PathBuf> {
+    let rest = command
+        .strip_prefix("cat ")?
+        .trim()
+        .trim_end_matches("2>/dev/null")
+        .trim();
+    let path = match rest.strip_prefix('\'') {
+        Some(quoted) => quoted.strip_suffix('\'')?,
+        None if !rest.contains(char::is_whitespace) => rest,
+        None => return None,
+    };
+    (!path.is_empty() && !path.contains('\'')).then(|| PathBuf::from(path))
+}
+
+/// An in-memory remote filesystem: a [`FileTransfer`] that keeps uploaded
+/// files by remote path. Clones share the same files.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryFs {
+    files: Arc<Mutex<BTreeMap<PathBuf, Vec<u8>>>>,
+}
+
+impl MemoryFs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Put a file on the "remote" directly.
+    pub fn insert(&self, path: impl Into<PathBuf>, content: impl Into<Vec<u8>>) {
+        self.lock().insert(path.into(), content.into());
+    }
+
+    pub fn read(&self, path: &Path) -> Option<Vec<u8>> {
+        self.lock().get(path).cloned()
+    }
+
+    pub fn read_to_string(&self, path: &Path) -> Option<String> {
+        self.read(path)
+            .map(|content| String::from_utf8_lossy(&content).into_owned())
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.lock().contains_key(path)
+    }
+
+    /// Paths of all files, sorted.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.lock().keys().cloned().collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<PathBuf, Vec<u8>>> {
+        // A panicking test must not hide the files from the others
+        self.files
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[async_trait]
+impl FileTransfer for MemoryFs {
+    async fn upload_file(
+        &self,
+        local_path: &Path,
+        remote_path: &Path,
+        progress: Option<&UploadProgress>,
+    ) -> Result<(), ExecutorError> {
+        let content = std::fs::read(local_path).map_err(|e| {
+            ExecutorError::Other(format!("Failed to read {}: {}", local_path.display(), e))
+        })?;
+        if let Some(progress) = progress {
+            let total = content.len() as u64;
+            progress(total, total);
+        }
+        self.insert(remote_path, content);
+        Ok(())
+    }
+
+    async fn upload_content(
+        &self,
+        content: &[u8],
+        remote_path: &Path,
+    ) -> Result<(), ExecutorError> {
+        self.insert(remote_path, content);
+        Ok(())
+    }
+
+    async fn upload_files(
+        &self,
+        batch: &[(PathBuf, PathBuf)],
+        _concurrency: usize,
+    ) -> Vec<Result<(), ExecutorError>> {
+        let mut results = Vec::with_capacity(batch.len());
+        for (local, remote) in batch {
+            results.push(self.upload_file(local, remote, None).await);
+        }
+        results
+    }
+
+    async fn download_content(&self, remote_path: &Path) -> Result<Vec<u8>, ExecutorError> {
+        self.read(remote_path)
+            .ok_or_else(|| ExecutorError::Other(format!("No such file: {}", remote_path.display())))
+    }
+
+    async fn list_files(&self, remote_dir: &Path) -> Result<Vec<PathBuf>, ExecutorError> {
+        let files: Vec<PathBuf> = self
+            .paths()
+            .iter()
+            .filter_map(|path| path.strip_prefix(remote_dir).ok())
+            .filter(|path| !path.as_os_str().is_empty())
+            .map(Path::to_path_buf)
+            .collect();
+        if files.is_empty() {
+            return Err(ExecutorError::Other(format!(
+                "No such directory: {}",
+                remote_dir.display()
+            )));
+        }
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scripted_responses_in_order() {
+        let mut executor = MockExecutor::new();
+        executor
+            .respond_ok(CommandMatch::Contains("compose ps".into()), "starting")
+            .respond_ok(CommandMatch::Contains("compose ps".into()), "healthy")
+            .respond_err("docker info", 1, "permission denied");
+
+        for expected in ["starting", "healthy", "healthy"] {
+            let result = executor
+                .execute_command("cd /opt/app && docker compose ps")
+                .await
+                .unwrap();
+            assert_eq!(result.output.to_stdout_string().unwrap(), expected);
+        }
+        let info = executor.execute_command("docker info").await.unwrap();
+        assert_eq!(info.output.exit_code, 1);
+        assert!(executor.execute_command("uname").await.is_err());
+        assert!(executor.ran(CommandMatch::Prefix("docker".into())));
+        assert_eq!(executor.commands().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_memory_fs() {
+        let fs = MemoryFs::new();
+        let mut executor = MockExecutor::with_fs(fs.clone());
+        executor
+            .upload_content(b"{}", Path::new("/opt/app/.dcd-state.json"))
+            .await
+            .unwrap();
+        assert!(fs.contains(Path::new("/opt/app/.dcd-state.json")));
+
+        let cat = executor
+            .execute_command("cat '/opt/app/.dcd-state.json' 2>/dev/null")
+            .await
+            .unwrap();
+        assert_eq!(cat.output.stdout, b"{}");
+        let missing = executor
+            .execute_command("cat /opt/app/missing")
+            .await
+            .unwrap();
+        assert!(!missing.is_success());
+    }
+
+    #[test]
+    fn test_cat_path() {
+        assert_eq!(cat_path("cat '/a b/c'"), Some(PathBuf::from("/a b/c")));
+        assert_eq!(cat_path("cat /a 2>/dev/null"), Some(PathBuf::from("/a")));
+        assert_eq!(cat_path("cat /a /b"), None);
+        assert_eq!(cat_path("cat /a | grep x"), None);
+    }
+}
@@ -1,3 +1,5 @@
+#![cfg(feature = "integration-tests")]
+
 use std::time::Duration;
 use tokio::fs;
 use tokio::process::Command;
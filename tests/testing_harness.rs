@@ -0,0 +1,73 @@
+use dcd::deployer::registry::{self, REGISTRY_FILE};
+use dcd::deployer::state::{DeployRecord, RemoteState, STATE_FILE};
+use dcd::testing::{CommandMatch, MockExecutor};
+use std::path::Path;
+
+#[tokio::test]
+async fn test_state_round_trip() {
+    let mut executor = MockExecutor::new();
+    executor.respond_ok(CommandMatch::Prefix("mkdir -p".into()), "");
+
+    let state = RemoteState {
+        project: "shop".into(),
+        profiles: vec!["web".into()],
+        ..Default::default()
+    };
+    state
+        .save(&mut executor, Path::new("/opt/shop"))
+        .await
+        .unwrap();
+    assert!(executor
+        .fs()
+        .contains(&Path::new("/opt/shop").join(STATE_FILE)));
+
+    let loaded = RemoteState::load(&mut executor, Path::new("/opt/shop"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(loaded.project, "shop");
+    assert_eq!(loaded.profiles, vec!["web".to_string()]);
+    assert!(RemoteState::load(&mut executor, Path::new("/opt/none"))
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_list_deployments() {
+    let mut executor = MockExecutor::new();
+    executor
+        .respond_ok(
+            CommandMatch::Prefix("docker ps -a".into()),
+            "blog\t/srv/blog\tghost:5\t\nblog\t/srv/blog\tmysql:8\t\nother\t/home/me/x\tredis\t\n",
+        )
+        .fs()
+        .insert(REGISTRY_FILE, "/opt/shop\n");
+    let state = |project: &str| {
+        serde_json::to_vec(&RemoteState {
+            project: project.into(),
+            last_deploy: Some(DeployRecord {
+                at: 1_700_000_000,
+                by: "ci".into(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .unwrap()
+    };
+    executor
+        .fs()
+        .insert(Path::new("/opt/shop").join(STATE_FILE), state("shop"));
+    executor
+        .fs()
+        .insert(Path::new("/srv/blog").join(STATE_FILE), state("blog"));
+
+    let deployments = registry::list_deployments(&mut executor).await.unwrap();
+    let found: Vec<(&str, usize)> = deployments
+        .iter()
+        .map(|d| (d.project.as_str(), d.containers))
+        .collect();
+    // /home/me/x has containers but no dcd state
+    assert_eq!(found, vec![("shop", 0), ("blog", 2)]);
+    assert!(deployments.iter().all(|d| !d.pending));
+}